@@ -1,53 +1,157 @@
 //! Benchmark command implementation
-
-use super::{channel_type_name, print_info};
-use crate::{ChannelType, OutputFormat};
+//!
+//! With `--profile`, messages are echoed over a framed pipe channel while a
+//! span profiler attributes the time to the phases ipckit instruments.
+//!
+//! With `--role`, two `ipckit bench` processes measure a real cross-process
+//! pipe or socket: the server echoes length-prefixed frames and the client
+//! times each round trip.
+
+use super::{channel_type_name, print_info, print_success};
+use crate::{BenchFormat, BenchRole, ChannelType};
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Dispatch, Event, Metadata, Subscriber};
+
+/// How long a `--role client` keeps retrying to reach its server
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Options of the `bench` command.
+pub struct BenchOptions {
+    pub channel_type: ChannelType,
+    pub iterations: u64,
+    /// Message sizes to run, one result each
+    pub sizes: Vec<usize>,
+    pub warmup: u64,
+    pub profile: bool,
+    /// Side of a cross-process benchmark, if any
+    pub role: Option<BenchRole>,
+    /// Channel name for cross-process benchmarks
+    pub name: String,
+    pub format: BenchFormat,
+    /// Write the report here instead of stdout
+    pub output: Option<PathBuf>,
+}
 
-pub fn bench(
-    channel_type: ChannelType,
-    iterations: u64,
-    message_size: usize,
-    warmup: u64,
-    format: OutputFormat,
-    verbose: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+pub fn bench(options: BenchOptions, verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let BenchOptions {
+        channel_type,
+        iterations,
+        sizes,
+        warmup,
+        profile,
+        role,
+        name,
+        format,
+        output,
+    } = options;
+
+    if role.is_some() && !matches!(channel_type, ChannelType::Pipe | ChannelType::Socket) {
+        return Err("--role benchmarks pipes and sockets only; use --type pipe or socket".into());
+    }
+    if let Some(BenchRole::Server) = role {
+        return serve_echo(channel_type, &name, verbose);
+    }
+
+    let sizes_label = sizes
+        .iter()
+        .map(|size| size.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
     print_info(&format!(
         "Benchmarking {} with {} iterations, {} byte messages",
         channel_type_name(channel_type),
         iterations,
-        message_size
+        sizes_label
     ));
 
-    // Create test message
-    let message: Vec<u8> = (0..message_size).map(|i| (i % 256) as u8).collect();
+    if profile {
+        if !matches!(channel_type, ChannelType::Pipe) {
+            return Err("--profile instruments framed pipe channels only; use --type pipe".into());
+        }
+        let mut report = String::new();
+        for &size in &sizes {
+            let breakdown = profile_pipe_channel(&test_message(size), iterations, warmup, verbose)?;
+            report.push_str(&format_breakdown(&breakdown, format));
+        }
+        return write_report(&report, output);
+    }
+
+    // One connection to the server carries the whole sweep
+    let mut server = match role {
+        Some(_) => Some(connect_bench_server(channel_type, &name)?),
+        None => None,
+    };
+    let mut results = Vec::with_capacity(sizes.len());
+    for &size in &sizes {
+        let message = test_message(size);
+        results.push(match server.as_mut() {
+            Some(stream) => {
+                bench_cross_process(channel_type, stream, &message, iterations, warmup, verbose)?
+            }
+            None => bench_in_process(channel_type, &message, iterations, warmup, verbose)?,
+        });
+    }
+
+    write_report(&format_results(&results, format), output)
+}
+
+/// Test message of `size` bytes
+fn test_message(size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i % 256) as u8).collect()
+}
+
+/// Print `report`, or write it to `output`
+fn write_report(report: &str, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    match output {
+        Some(path) => {
+            std::fs::write(&path, console::strip_ansi_codes(report).as_ref())?;
+            print_success(&format!("Report written to {}", path.display()));
+        }
+        None => print!("{}", report),
+    }
+    Ok(())
+}
 
-    // Run benchmark based on channel type
-    let results = match channel_type {
-        ChannelType::Thread => bench_thread_channel(&message, iterations, warmup, verbose)?,
+fn bench_in_process(
+    channel_type: ChannelType,
+    message: &[u8],
+    iterations: u64,
+    warmup: u64,
+    verbose: bool,
+) -> Result<BenchResults, Box<dyn std::error::Error>> {
+    Ok(match channel_type {
+        ChannelType::Thread => bench_thread_channel(message, iterations, warmup, verbose)?,
         ChannelType::Pipe => {
             print_info("Note: Pipe benchmark requires separate server/client processes");
-            print_info("Using in-memory simulation for demonstration");
-            bench_simulated(&message, iterations, warmup, verbose)?
+            print_info(
+                "Using in-memory simulation; run with --role server|client to measure the pipe",
+            );
+            bench_simulated(message, iterations, warmup, verbose)?
         }
         ChannelType::Socket => {
             print_info("Note: Socket benchmark requires separate server/client processes");
-            print_info("Using in-memory simulation for demonstration");
-            bench_simulated(&message, iterations, warmup, verbose)?
+            print_info(
+                "Using in-memory simulation; run with --role server|client to measure the socket",
+            );
+            bench_simulated(message, iterations, warmup, verbose)?
         }
-        ChannelType::Shm => bench_shared_memory(&message, iterations, warmup, verbose)?,
+        ChannelType::Shm => bench_shared_memory(message, iterations, warmup, verbose)?,
         ChannelType::File => {
             print_info("Note: File channel benchmark uses disk I/O");
-            bench_file_channel(&message, iterations, warmup, verbose)?
+            bench_file_channel(message, iterations, warmup, verbose)?
         }
-    };
-
-    // Print results
-    print_results(&results, format);
-
-    Ok(())
+    })
 }
 
 #[derive(Debug)]
@@ -297,6 +401,163 @@ fn bench_simulated(
     ))
 }
 
+/// Bidirectional byte stream between the two bench processes
+trait BenchStream: Read + Write {}
+
+impl<T: Read + Write> BenchStream for T {}
+
+/// Run the echo side of a cross-process benchmark until its client leaves.
+fn serve_echo(
+    channel_type: ChannelType,
+    name: &str,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    print_info(&format!(
+        "Waiting for a bench client on {} '{}'",
+        channel_type_name(channel_type),
+        name
+    ));
+
+    let mut stream: Box<dyn BenchStream> = match channel_type {
+        ChannelType::Pipe => {
+            let mut pipe = ipckit::NamedPipe::create(name)?;
+            pipe.wait_for_client()?;
+            Box::new(pipe)
+        }
+        _ => Box::new(ipckit::LocalSocketListener::bind(name)?.accept()?),
+    };
+
+    let mut frames = 0u64;
+    let mut buf = Vec::new();
+    while read_frame(&mut stream, &mut buf)? {
+        write_frame(&mut stream, &buf)?;
+        frames += 1;
+        if verbose && frames.is_multiple_of(10_000) {
+            print_info(&format!("Echoed {} frames", frames));
+        }
+    }
+
+    print_success(&format!("Client finished after {} frames", frames));
+    Ok(())
+}
+
+/// Time round trips to a `--role server` process.
+fn bench_cross_process(
+    channel_type: ChannelType,
+    stream: &mut Box<dyn BenchStream>,
+    message: &[u8],
+    iterations: u64,
+    warmup: u64,
+    verbose: bool,
+) -> Result<BenchResults, Box<dyn std::error::Error>> {
+    let mut reply = Vec::with_capacity(message.len());
+
+    let mut round_trip = |stream: &mut Box<dyn BenchStream>| -> std::io::Result<()> {
+        write_frame(stream, message)?;
+        if !read_frame(stream, &mut reply)? {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        if reply.len() != message.len() {
+            return Err(std::io::Error::other(
+                "bench server echoed a different size",
+            ));
+        }
+        Ok(())
+    };
+
+    if verbose && warmup > 0 {
+        print_info(&format!("Warming up with {} round trips...", warmup));
+    }
+    for _ in 0..warmup {
+        round_trip(stream)?;
+    }
+
+    let pb = ProgressBar::new(iterations);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
+            )
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+
+    let mut latencies = Vec::with_capacity(iterations as usize);
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        let iter_start = Instant::now();
+        round_trip(stream)?;
+        latencies.push(iter_start.elapsed());
+        pb.inc(1);
+    }
+
+    let total_time = start.elapsed();
+    pb.finish_with_message("Done");
+
+    let label = match channel_type {
+        ChannelType::Pipe => "Named Pipe (cross-process)",
+        _ => "Local Socket (cross-process)",
+    };
+    Ok(calculate_results(
+        label,
+        message.len(),
+        iterations,
+        total_time,
+        latencies,
+    ))
+}
+
+/// Connect to a bench server, retrying while it starts up.
+fn connect_bench_server(
+    channel_type: ChannelType,
+    name: &str,
+) -> Result<Box<dyn BenchStream>, Box<dyn std::error::Error>> {
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    loop {
+        let result: ipckit::Result<Box<dyn BenchStream>> = match channel_type {
+            ChannelType::Pipe => {
+                ipckit::NamedPipe::connect(name).map(|p| Box::new(p) as Box<dyn BenchStream>)
+            }
+            _ => ipckit::LocalSocketStream::connect(name)
+                .map(|s| Box::new(s) as Box<dyn BenchStream>),
+        };
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => {
+                return Err(format!(
+                    "no bench server on '{}' (start one with --role server): {}",
+                    name, e
+                )
+                .into())
+            }
+        }
+    }
+}
+
+/// Write a length-prefixed frame.
+fn write_frame<W: Write + ?Sized>(writer: &mut W, payload: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+/// Read a length-prefixed frame into `buf`; `false` when the peer has gone.
+fn read_frame<R: Read + ?Sized>(reader: &mut R, buf: &mut Vec<u8>) -> std::io::Result<bool> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if matches!(e.kind(), ErrorKind::UnexpectedEof | ErrorKind::BrokenPipe) => {
+            return Ok(false)
+        }
+        Err(e) => return Err(e),
+    }
+    buf.resize(u32::from_le_bytes(len) as usize, 0);
+    reader.read_exact(buf)?;
+    Ok(true)
+}
+
 fn calculate_results(
     channel_type: &str,
     message_size: usize,
@@ -336,56 +597,98 @@ fn calculate_results(
     }
 }
 
-fn print_results(results: &BenchResults, format: OutputFormat) {
+fn format_results(results: &[BenchResults], format: BenchFormat) -> String {
+    let mut out = String::new();
     match format {
-        OutputFormat::Json => {
-            let json = serde_json::json!({
-                "channel_type": results.channel_type,
-                "iterations": results.iterations,
-                "message_size": results.message_size,
-                "total_time_ms": results.total_time.as_millis(),
-                "throughput_msgs_per_sec": results.throughput_msgs,
-                "throughput_bytes_per_sec": results.throughput_bytes,
-                "latency": {
-                    "avg_us": results.avg_latency.as_micros(),
-                    "min_us": results.min_latency.as_micros(),
-                    "max_us": results.max_latency.as_micros(),
-                    "p50_us": results.p50_latency.as_micros(),
-                    "p95_us": results.p95_latency.as_micros(),
-                    "p99_us": results.p99_latency.as_micros(),
-                }
-            });
-            println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        BenchFormat::Json => {
+            // A single run keeps the plain object shape
+            let json = match results {
+                [results] => results_json(results),
+                _ => results.iter().map(results_json).collect(),
+            };
+            let _ = writeln!(out, "{}", serde_json::to_string_pretty(&json).unwrap());
         }
-        _ => {
-            println!();
-            println!("{}", style("Benchmark Results").bold().underlined());
-            println!();
-            println!("  Channel:        {}", style(&results.channel_type).cyan());
-            println!("  Iterations:     {}", results.iterations);
-            println!("  Message Size:   {} bytes", results.message_size);
-            println!("  Total Time:     {:.3?}", results.total_time);
-            println!();
-            println!("{}", style("Throughput").bold());
-            println!(
-                "  Messages/sec:   {}",
-                style(format!("{:.2}", results.throughput_msgs)).green()
-            );
-            println!(
-                "  Bytes/sec:      {}",
-                style(format_bytes(results.throughput_bytes)).green()
+        BenchFormat::Csv => {
+            let _ = writeln!(
+                out,
+                "channel_type,iterations,message_size,total_time_ms,throughput_msgs_per_sec,throughput_bytes_per_sec,avg_us,min_us,max_us,p50_us,p95_us,p99_us"
             );
-            println!();
-            println!("{}", style("Latency").bold());
-            println!("  Average:        {:?}", results.avg_latency);
-            println!("  Min:            {:?}", results.min_latency);
-            println!("  Max:            {:?}", results.max_latency);
-            println!("  p50:            {:?}", results.p50_latency);
-            println!("  p95:            {:?}", results.p95_latency);
-            println!("  p99:            {:?}", results.p99_latency);
-            println!();
+            for results in results {
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{},{:.2},{:.2},{},{},{},{},{},{}",
+                    results.channel_type,
+                    results.iterations,
+                    results.message_size,
+                    results.total_time.as_millis(),
+                    results.throughput_msgs,
+                    results.throughput_bytes,
+                    results.avg_latency.as_micros(),
+                    results.min_latency.as_micros(),
+                    results.max_latency.as_micros(),
+                    results.p50_latency.as_micros(),
+                    results.p95_latency.as_micros(),
+                    results.p99_latency.as_micros(),
+                );
+            }
+        }
+        BenchFormat::Text => {
+            for results in results {
+                let _ = writeln!(out);
+                let _ = writeln!(out, "{}", style("Benchmark Results").bold().underlined());
+                let _ = writeln!(out);
+                let _ = writeln!(
+                    out,
+                    "  Channel:        {}",
+                    style(&results.channel_type).cyan()
+                );
+                let _ = writeln!(out, "  Iterations:     {}", results.iterations);
+                let _ = writeln!(out, "  Message Size:   {} bytes", results.message_size);
+                let _ = writeln!(out, "  Total Time:     {:.3?}", results.total_time);
+                let _ = writeln!(out);
+                let _ = writeln!(out, "{}", style("Throughput").bold());
+                let _ = writeln!(
+                    out,
+                    "  Messages/sec:   {}",
+                    style(format!("{:.2}", results.throughput_msgs)).green()
+                );
+                let _ = writeln!(
+                    out,
+                    "  Bytes/sec:      {}",
+                    style(format_bytes(results.throughput_bytes)).green()
+                );
+                let _ = writeln!(out);
+                let _ = writeln!(out, "{}", style("Latency").bold());
+                let _ = writeln!(out, "  Average:        {:?}", results.avg_latency);
+                let _ = writeln!(out, "  Min:            {:?}", results.min_latency);
+                let _ = writeln!(out, "  Max:            {:?}", results.max_latency);
+                let _ = writeln!(out, "  p50:            {:?}", results.p50_latency);
+                let _ = writeln!(out, "  p95:            {:?}", results.p95_latency);
+                let _ = writeln!(out, "  p99:            {:?}", results.p99_latency);
+                let _ = writeln!(out);
+            }
         }
     }
+    out
+}
+
+fn results_json(results: &BenchResults) -> serde_json::Value {
+    serde_json::json!({
+        "channel_type": results.channel_type,
+        "iterations": results.iterations,
+        "message_size": results.message_size,
+        "total_time_ms": results.total_time.as_millis(),
+        "throughput_msgs_per_sec": results.throughput_msgs,
+        "throughput_bytes_per_sec": results.throughput_bytes,
+        "latency": {
+            "avg_us": results.avg_latency.as_micros(),
+            "min_us": results.min_latency.as_micros(),
+            "max_us": results.max_latency.as_micros(),
+            "p50_us": results.p50_latency.as_micros(),
+            "p95_us": results.p95_latency.as_micros(),
+            "p99_us": results.p99_latency.as_micros(),
+        }
+    })
 }
 
 fn format_bytes(bytes: f64) -> String {
@@ -403,3 +706,291 @@ fn format_bytes(bytes: f64) -> String {
         format!("{:.2} B/s", bytes)
     }
 }
+
+/// Phases reported by `--profile`, with the ipckit span each is measured by.
+///
+/// Wakeup has no span of its own: it is whatever wall time the other phases
+/// do not account for.
+const PROFILE_PHASES: [(&str, &str); 4] = [
+    ("serialize", "serialize"),
+    ("deserialize", "deserialize"),
+    ("framing", "frame"),
+    ("syscall", "syscall"),
+];
+
+/// Message echoed by the profiling benchmark.
+#[derive(Serialize, Deserialize)]
+struct ProfileMessage {
+    seq: u64,
+    payload: Vec<u8>,
+}
+
+thread_local! {
+    /// Entered spans on this thread as `(entered at, time spent in children)`.
+    static SPAN_STACK: RefCell<Vec<(Instant, Duration)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Minimal subscriber that sums the self time of ipckit spans by name.
+#[derive(Default)]
+struct SpanProfiler {
+    next_id: AtomicU64,
+    names: Mutex<HashMap<u64, &'static str>>,
+    self_time: Mutex<HashMap<&'static str, Duration>>,
+}
+
+impl Subscriber for SpanProfiler {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.is_span() && metadata.target().starts_with("ipckit")
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+
+        self.names
+            .lock()
+            .unwrap()
+            .insert(id, span.metadata().name());
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {
+        SPAN_STACK.with(|stack| stack.borrow_mut().push((Instant::now(), Duration::ZERO)));
+    }
+
+    fn exit(&self, span: &Id) {
+        let Some((entered, children)) = SPAN_STACK.with(|stack| stack.borrow_mut().pop()) else {
+            return;
+        };
+        let elapsed = entered.elapsed();
+        SPAN_STACK.with(|stack| {
+            if let Some(parent) = stack.borrow_mut().last_mut() {
+                parent.1 += elapsed;
+            }
+        });
+
+        if let Some(name) = self.names.lock().unwrap().get(&span.into_u64()) {
+            *self.self_time.lock().unwrap().entry(name).or_default() +=
+                elapsed.saturating_sub(children);
+        }
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        self.names.lock().unwrap().remove(&span.into_u64());
+        true
+    }
+}
+
+#[derive(Debug)]
+struct Breakdown {
+    messages: u64,
+    message_size: usize,
+    encoded_size: usize,
+    wall_time: Duration,
+    /// `(phase, total time)` in report order, ending with wakeup
+    phases: Vec<(&'static str, Duration)>,
+}
+
+fn profile_pipe_channel(
+    message: &[u8],
+    iterations: u64,
+    warmup: u64,
+    verbose: bool,
+) -> Result<Breakdown, Box<dyn std::error::Error>> {
+    use ipckit::IpcChannel;
+
+    let name = format!("ipckit_bench_profile_{}", std::process::id());
+    let dispatch = Dispatch::new(SpanProfiler::default());
+
+    // Echo server; warmup runs before the profiler is installed
+    let server = std::thread::spawn({
+        let name = name.clone();
+        let dispatch = dispatch.clone();
+        move || -> ipckit::Result<()> {
+            let mut channel = IpcChannel::<ProfileMessage>::create(&name)?;
+            channel.wait_for_client()?;
+            let mut echo = || -> ipckit::Result<()> {
+                let msg = channel.recv()?;
+                channel.send(&msg)
+            };
+            for _ in 0..warmup {
+                echo()?;
+            }
+            tracing::dispatcher::with_default(&dispatch, || {
+                tracing::callsite::rebuild_interest_cache();
+                (0..iterations).try_for_each(|_| echo())
+            })
+        }
+    });
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut client = loop {
+        match IpcChannel::<ProfileMessage>::connect(&name) {
+            Ok(client) => break client,
+            Err(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(10)),
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    let mut msg = ProfileMessage {
+        seq: 0,
+        payload: message.to_vec(),
+    };
+    let encoded_size = serde_json::to_vec(&msg)?.len();
+
+    if verbose && warmup > 0 {
+        print_info(&format!("Warming up with {} round trips...", warmup));
+    }
+    for _ in 0..warmup {
+        client.send(&msg)?;
+        msg = client.recv()?;
+    }
+
+    // No progress bar here: its redraws would show up as wakeup time
+    let start = Instant::now();
+    tracing::dispatcher::with_default(&dispatch, || -> ipckit::Result<()> {
+        // Spans first hit during warmup had no subscriber and were cached as
+        // disabled
+        tracing::callsite::rebuild_interest_cache();
+        for seq in 0..iterations {
+            msg.seq = seq;
+            client.send(&msg)?;
+            msg = client.recv()?;
+        }
+        Ok(())
+    })?;
+    let wall_time = start.elapsed();
+
+    server.join().map_err(|_| "echo server panicked")??;
+
+    let profiler = dispatch
+        .downcast_ref::<SpanProfiler>()
+        .expect("dispatch wraps a SpanProfiler");
+    let self_time = profiler.self_time.lock().unwrap();
+    let mut phases: Vec<(&'static str, Duration)> = PROFILE_PHASES
+        .iter()
+        .map(|(phase, span)| (*phase, self_time.get(span).copied().unwrap_or_default()))
+        .collect();
+    let accounted: Duration = phases.iter().map(|(_, t)| *t).sum();
+    phases.push(("wakeup", wall_time.saturating_sub(accounted)));
+
+    Ok(Breakdown {
+        messages: iterations * 2,
+        message_size: message.len(),
+        encoded_size,
+        wall_time,
+        phases,
+    })
+}
+
+fn format_breakdown(breakdown: &Breakdown, format: BenchFormat) -> String {
+    let mut out = String::new();
+    const BAR_WIDTH: f64 = 30.0;
+
+    let messages = breakdown.messages.max(1) as u32;
+    let share = |time: Duration| {
+        if breakdown.wall_time.is_zero() {
+            0.0
+        } else {
+            time.as_secs_f64() / breakdown.wall_time.as_secs_f64() * 100.0
+        }
+    };
+
+    match format {
+        BenchFormat::Json => {
+            let phases: Vec<_> = breakdown
+                .phases
+                .iter()
+                .map(|(phase, time)| {
+                    serde_json::json!({
+                        "phase": phase,
+                        "total_us": time.as_micros(),
+                        "per_message_ns": (*time / messages).as_nanos(),
+                        "percent": share(*time),
+                    })
+                })
+                .collect();
+            let json = serde_json::json!({
+                "transport": "pipe",
+                "messages": breakdown.messages,
+                "message_size": breakdown.message_size,
+                "encoded_size": breakdown.encoded_size,
+                "wall_time_us": breakdown.wall_time.as_micros(),
+                "phases": phases,
+            });
+            let _ = writeln!(out, "{}", serde_json::to_string_pretty(&json).unwrap());
+        }
+        BenchFormat::Csv => {
+            let _ = writeln!(out, "phase,message_size,total_us,per_message_ns,percent");
+            for (phase, time) in &breakdown.phases {
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{},{:.2}",
+                    phase,
+                    breakdown.message_size,
+                    time.as_micros(),
+                    (*time / messages).as_nanos(),
+                    share(*time)
+                );
+            }
+        }
+        BenchFormat::Text => {
+            let _ = writeln!(out);
+            let _ = writeln!(
+                out,
+                "{}",
+                style("IPC Overhead Breakdown").bold().underlined()
+            );
+            let _ = writeln!(out);
+            let _ = writeln!(
+                out,
+                "  Transport:      {}",
+                style("Named Pipe (JSON)").cyan()
+            );
+            let _ = writeln!(
+                out,
+                "  Messages:       {} ({} round trips)",
+                breakdown.messages,
+                breakdown.messages / 2
+            );
+            let _ = writeln!(
+                out,
+                "  Message Size:   {} bytes ({} bytes encoded)",
+                breakdown.message_size, breakdown.encoded_size
+            );
+            let _ = writeln!(out, "  Wall Time:      {:.3?}", breakdown.wall_time);
+            let _ = writeln!(out);
+            let _ = writeln!(
+                out,
+                "  {}",
+                style(format!("{:<14}{:>12}{:>9}", "Phase", "Per msg", "Share")).bold()
+            );
+            for (phase, time) in &breakdown.phases {
+                let percent = share(*time);
+                let bar = "#".repeat((percent / 100.0 * BAR_WIDTH).round() as usize);
+                let _ = writeln!(
+                    out,
+                    "  {:<14}{:>12}{:>8.1}%  {}",
+                    phase,
+                    format!("{:.2?}", *time / messages),
+                    percent,
+                    style(bar).green()
+                );
+            }
+            let _ = writeln!(out);
+            let _ = writeln!(
+                out,
+                "  {}",
+                style("wakeup = wall time not spent in the other phases (scheduling, transit)")
+                    .dim()
+            );
+            let _ = writeln!(out);
+        }
+    }
+    out
+}
@@ -1,11 +1,18 @@
 //! Code generation command
 
 use crate::{ChannelType, GenerateTarget};
+use ipckit::api_server::ROUTES_PATH;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use super::{print_info, print_success};
+use super::{api_client, print_info, print_success};
+
+/// Version of ipckit the generated code is written against
+const IPCKIT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Generate code templates
 pub fn generate(
@@ -27,10 +34,49 @@ pub fn generate(
     let code = match target {
         GenerateTarget::Client => generate_client(channel_type, name),
         GenerateTarget::Server => generate_server(channel_type, name),
-        GenerateTarget::Python => generate_python(channel_type, name),
+        GenerateTarget::Python => generate_python(channel_type, name)?,
         GenerateTarget::Handler => generate_handler(name),
     };
 
+    write_output(&code, output)
+}
+
+/// Generate a typed TypeScript client for an API server.
+///
+/// Routes come from the OpenAPI document if one is given, otherwise from the
+/// route index of the server listening on `socket`.
+pub fn generate_typescript(
+    socket: &str,
+    openapi: Option<PathBuf>,
+    output: Option<PathBuf>,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (source, api) = match openapi {
+        Some(path) => {
+            if verbose {
+                print_info(&format!("Reading OpenAPI document {}", path.display()));
+            }
+            (path.display().to_string(), load_openapi(&path)?)
+        }
+        None => {
+            if verbose {
+                print_info(&format!("Fetching routes from {}", socket));
+            }
+            (socket.to_string(), fetch_routes(socket)?)
+        }
+    };
+
+    if verbose {
+        print_info(&format!(
+            "Generating client for {} routes",
+            api.routes.len()
+        ));
+    }
+
+    write_output(&render_typescript(&source, &api), output)
+}
+
+fn write_output(code: &str, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
     match output {
         Some(path) => {
             let mut file = fs::File::create(&path)?;
@@ -55,375 +101,716 @@ fn channel_type_name(ct: ChannelType) -> &'static str {
     }
 }
 
-fn generate_client(channel_type: ChannelType, name: &str) -> String {
-    match channel_type {
-        ChannelType::Pipe => format!(
-            r#"//! IPC Client for channel: {name}
+/// Dependency block shared by the Rust templates
+fn rust_header(title: &str) -> String {
+    format!(
+        r#"//! {title}
 //!
-//! Generated by ipckit CLI
-
-use ipckit::{{IpcChannel, IpcError}};
-use serde::{{Deserialize, Serialize}};
+//! Generated by ipckit CLI. Dependencies:
+//!
+//! ```toml
+//! ipckit = "{IPCKIT_VERSION}"
+//! serde = {{ version = "1", features = ["derive"] }}
+//! serde_json = "1"
+//! ```
+"#
+    )
+}
 
+/// Request and response types used by the pipe templates
+const PIPE_PROTOCOL: &str = r#"
+/// A command sent to the server
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Request {{
+pub struct Request {
     pub command: String,
-    pub params: serde_json::Value,
-}}
+    #[serde(default)]
+    pub params: Value,
+}
 
+/// The server's reply to a [`Request`]
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Response {{
+pub struct Response {
     pub success: bool,
-    pub data: Option<serde_json::Value>,
+    #[serde(default)]
+    pub data: Option<Value>,
+    #[serde(default)]
     pub error: Option<String>,
-}}
+}
+"#;
+
+fn generate_client(channel_type: ChannelType, name: &str) -> String {
+    match channel_type {
+        ChannelType::Pipe => format!(
+            r#"{header}
+use ipckit::{{IpcChannel, IpcError}};
+use serde::{{Deserialize, Serialize}};
+use serde_json::{{json, Value}};
 
+/// Name of the channel the server listens on
+pub const CHANNEL: &str = "{name}";
+{PIPE_PROTOCOL}
 pub struct Client {{
-    channel: IpcChannel<serde_json::Value>,
+    channel: IpcChannel<Value>,
 }}
 
 impl Client {{
-    /// Connect to the IPC server
+    /// Connect to the server
     pub fn connect() -> Result<Self, IpcError> {{
-        let channel = IpcChannel::connect("{name}")?;
-        Ok(Self {{ channel }})
+        Ok(Self {{
+            channel: IpcChannel::connect(CHANNEL)?,
+        }})
     }}
 
-    /// Send a request and receive a response
-    pub fn request(&mut self, command: &str, params: serde_json::Value) -> Result<Response, IpcError> {{
-        let request = serde_json::json!({{
-            "command": command,
-            "params": params,
-        }});
+    /// Send a command and wait for its result
+    pub fn call(&mut self, command: &str, params: Value) -> Result<Value, IpcError> {{
+        let request = Request {{
+            command: command.to_string(),
+            params,
+        }};
+        let request =
+            serde_json::to_value(&request).map_err(|e| IpcError::serialization(e.to_string()))?;
         self.channel.send(&request)?;
-        self.channel.recv()
+
+        let response: Response = serde_json::from_value(self.channel.recv()?)
+            .map_err(|e| IpcError::deserialization(e.to_string()))?;
+        if response.success {{
+            Ok(response.data.unwrap_or(Value::Null))
+        }} else {{
+            Err(IpcError::Other(
+                response.error.unwrap_or_else(|| "request failed".to_string()),
+            ))
+        }}
     }}
 
     /// Ping the server
-    pub fn ping(&mut self) -> Result<String, IpcError> {{
-        let response = self.request("ping", serde_json::json!({{}}))?;
-        Ok(response.data.unwrap_or_default().to_string())
+    pub fn ping(&mut self) -> Result<Value, IpcError> {{
+        self.call("ping", json!({{}}))
+    }}
+
+    /// Have the server send `params` back
+    pub fn echo(&mut self, params: Value) -> Result<Value, IpcError> {{
+        self.call("echo", params)
     }}
 }}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {{
     let mut client = Client::connect()?;
 
-    let response = client.ping()?;
-    println!("Server response: {{}}", response);
+    println!("ping -> {{}}", client.ping()?);
+    println!("echo -> {{}}", client.echo(json!({{"hello": "world"}}))?);
 
     Ok(())
 }}
-"#
+"#,
+            header = rust_header(&format!("IPC client for channel: {name}")),
         ),
         ChannelType::Socket => format!(
-            r#"//! Socket Client for channel: {name}
-//!
-//! Generated by ipckit CLI
+            r#"{header}
+use ipckit::SocketClient;
+use serde_json::json;
 
-use ipckit::{{SocketClient, IpcError}};
-use serde::{{Deserialize, Serialize}};
+/// Path of the socket the server listens on
+pub const SOCKET: &str = "{name}";
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Request {{
-    pub command: String,
-    pub params: serde_json::Value,
-}}
+fn main() -> Result<(), Box<dyn std::error::Error>> {{
+    let mut client = SocketClient::connect(SOCKET)?;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Response {{
-    pub success: bool,
-    pub data: Option<serde_json::Value>,
-    pub error: Option<String>,
+    println!("ping -> {{}}", client.request("ping", json!({{}}))?);
+    println!(
+        "echo -> {{}}",
+        client.request("echo", json!({{"hello": "world"}}))?
+    );
+
+    Ok(())
+}}
+"#,
+            header = rust_header(&format!("Socket client for: {name}")),
+        ),
+        ChannelType::Shm => format!(
+            r#"{header}
+use ipckit::SharedMemory;
+use serde_json::Value;
+
+/// Name of the shared memory segment the server creates
+pub const SEGMENT: &str = "{name}";
+
+/// Read the JSON state the server last published.
+///
+/// The segment holds a little-endian `u32` length followed by that many
+/// bytes of JSON.
+pub fn read_state(shm: &SharedMemory) -> Result<Value, Box<dyn std::error::Error>> {{
+    let len = u32::from_le_bytes(shm.read(0, 4)?.try_into().unwrap_or_default()) as usize;
+    Ok(serde_json::from_slice(&shm.read(4, len)?)?)
 }}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {{
-    let mut client = SocketClient::connect("{name}")?;
+    let shm = SharedMemory::open(SEGMENT)?;
 
-    let request = serde_json::json!({{
-        "command": "ping",
-        "params": {{}},
-    }});
-
-    client.send(&serde_json::to_vec(&request)?)?;
-    let response = client.recv()?;
-
-    println!("Response: {{}}", String::from_utf8_lossy(&response));
+    for _ in 0..5 {{
+        println!("state -> {{}}", read_state(&shm)?);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    }}
 
     Ok(())
 }}
-"#
+"#,
+            header = rust_header(&format!("Shared memory reader for segment: {name}")),
         ),
-        _ => format!(
-            r#"//! Client for {name} ({ct})
-//!
-//! Generated by ipckit CLI
+        ChannelType::File => format!(
+            r#"{header}
+use ipckit::FileChannel;
+use serde_json::json;
+use std::time::Duration;
 
-use ipckit::IpcError;
+/// Directory the backend exchanges messages in
+pub const DIR: &str = "{name}";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {{
-    // TODO: Implement client for {ct} channel type
-    println!("Client for channel: {name}");
+    let mut channel = FileChannel::frontend(DIR)?;
+
+    let id = channel.send_request("ping", json!({{}}))?;
+    let response = channel.wait_response(&id, Duration::from_secs(5))?;
+    match response.error {{
+        Some(error) => eprintln!("ping failed: {{}}", error),
+        None => println!("ping -> {{}}", response.payload),
+    }}
+
     Ok(())
 }}
 "#,
-            ct = channel_type_name(channel_type)
+            header = rust_header(&format!("File channel frontend for directory: {name}")),
         ),
+        ChannelType::Thread => generate_thread(name),
     }
 }
 
 fn generate_server(channel_type: ChannelType, name: &str) -> String {
     match channel_type {
         ChannelType::Pipe => format!(
-            r#"//! IPC Server for channel: {name}
-//!
-//! Generated by ipckit CLI
-
+            r#"{header}
 use ipckit::{{IpcChannel, IpcError}};
 use serde::{{Deserialize, Serialize}};
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Request {{
-    pub command: String,
-    pub params: serde_json::Value,
-}}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Response {{
-    pub success: bool,
-    pub data: Option<serde_json::Value>,
-    pub error: Option<String>,
-}}
-
-pub struct Server {{
-    channel: IpcChannel<serde_json::Value>,
-}}
-
-impl Server {{
-    /// Create a new IPC server
-    pub fn create() -> Result<Self, IpcError> {{
-        let channel = IpcChannel::create("{name}")?;
-        Ok(Self {{ channel }})
+use serde_json::{{json, Value}};
+
+/// Name of the channel to listen on
+pub const CHANNEL: &str = "{name}";
+{PIPE_PROTOCOL}
+impl Response {{
+    fn ok(data: Value) -> Self {{
+        Self {{
+            success: true,
+            data: Some(data),
+            error: None,
+        }}
     }}
 
-    /// Wait for a client to connect
-    pub fn wait_for_client(&mut self) -> Result<(), IpcError> {{
-        self.channel.wait_for_client()
+    fn err(error: String) -> Self {{
+        Self {{
+            success: false,
+            data: None,
+            error: Some(error),
+        }}
     }}
+}}
 
-    /// Handle incoming requests
-    pub fn handle_request(&mut self) -> Result<(), IpcError> {{
-        let request: Request = self.channel.recv()?;
+/// Handle one command
+fn handle(request: Request) -> Response {{
+    match request.command.as_str() {{
+        "ping" => Response::ok(json!("pong")),
+        "echo" => Response::ok(request.params),
+        other => Response::err(format!("unknown command: {{}}", other)),
+    }}
+}}
 
-        let response = match request.command.as_str() {{
-            "ping" => Response {{
-                success: true,
-                data: Some(serde_json::json!("pong")),
-                error: None,
-            }},
-            "echo" => Response {{
-                success: true,
-                data: Some(request.params),
-                error: None,
-            }},
-            _ => Response {{
-                success: false,
-                data: None,
-                error: Some(format!("Unknown command: {{}}", request.command)),
-            }},
+/// Serve a connected client until it disconnects
+fn serve(channel: &mut IpcChannel<Value>) -> Result<(), IpcError> {{
+    loop {{
+        let response = match serde_json::from_value::<Request>(channel.recv()?) {{
+            Ok(request) => handle(request),
+            Err(e) => Response::err(format!("malformed request: {{}}", e)),
         }};
-
-        self.channel.send(&serde_json::to_value(&response)?)?;
-        Ok(())
+        let response =
+            serde_json::to_value(&response).map_err(|e| IpcError::serialization(e.to_string()))?;
+        channel.send(&response)?;
     }}
 }}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {{
-    println!("Starting IPC server on channel: {name}");
-
-    let mut server = Server::create()?;
+    println!("Serving channel: {{}}", CHANNEL);
 
     loop {{
-        println!("Waiting for client...");
-        server.wait_for_client()?;
+        // A named pipe serves one client; create a fresh one for the next
+        let mut channel = IpcChannel::create(CHANNEL)?;
+        channel.wait_for_client()?;
+        println!("Client connected");
 
-        println!("Client connected, handling requests...");
-        if let Err(e) = server.handle_request() {{
-            eprintln!("Error handling request: {{}}", e);
+        if let Err(e) = serve(&mut channel) {{
+            println!("Client disconnected: {{}}", e);
         }}
     }}
 }}
-"#
+"#,
+            header = rust_header(&format!("IPC server for channel: {name}")),
         ),
         ChannelType::Socket => format!(
-            r#"//! Socket Server for channel: {name}
-//!
-//! Generated by ipckit CLI
+            r#"{header}
+use ipckit::socket_server::MessageType;
+use ipckit::{{Connection, FnHandler, IpcError, Message, SocketServer, SocketServerConfig}};
+use serde_json::json;
+
+/// Path of the socket to listen on
+pub const SOCKET: &str = "{name}";
+
+/// Handle one message; the returned message is sent back to the client
+fn handle(conn: &mut Connection, msg: Message) -> Result<Option<Message>, IpcError> {{
+    if msg.msg_type == MessageType::Ping {{
+        return Ok(Some(Message::pong()));
+    }}
+    let reply = match msg.method() {{
+        Some("ping") => Message::response(json!("pong")),
+        Some("echo") => Message::response(msg.params().cloned().unwrap_or_default()),
+        Some(other) => Message::from_error(&IpcError::NotFound(format!(
+            "unknown method: {{}}",
+            other
+        ))),
+        None => {{
+            println!("[{{}}] {{:?}}", conn.id(), msg.payload);
+            return Ok(None);
+        }}
+    }};
+    Ok(Some(reply))
+}}
 
-use ipckit::{{SocketServer, SocketServerConfig, ConnectionHandler, Connection, Message, IpcError}};
-use std::sync::Arc;
+fn main() -> Result<(), Box<dyn std::error::Error>> {{
+    let server = SocketServer::new(SocketServerConfig::with_path(SOCKET))?;
+    println!("Serving socket: {{}}", SOCKET);
 
-struct MyHandler;
+    server.run(FnHandler::new(handle))?;
 
-impl ConnectionHandler for MyHandler {{
-    fn on_connect(&self, conn: &Connection) {{
-        println!("Client connected: {{}}", conn.id());
+    Ok(())
+}}
+"#,
+            header = rust_header(&format!("Socket server for: {name}")),
+        ),
+        ChannelType::Shm => format!(
+            r#"{header}
+use ipckit::SharedMemory;
+use serde_json::{{json, Value}};
+use std::time::Duration;
+
+/// Name of the shared memory segment to create
+pub const SEGMENT: &str = "{name}";
+
+/// Size of the segment in bytes
+pub const SIZE: usize = 64 * 1024;
+
+/// Publish `state` as a little-endian `u32` length followed by its JSON.
+///
+/// Readers may see a half-written update; use `ipckit::ShmDoubleBuffer`
+/// when every snapshot must be consistent.
+pub fn write_state(shm: &mut SharedMemory, state: &Value) -> Result<(), Box<dyn std::error::Error>> {{
+    let data = serde_json::to_vec(state)?;
+    if data.len() + 4 > shm.size() {{
+        return Err("state does not fit in the segment".into());
     }}
+    shm.write(4, &data)?;
+    shm.write(0, &(data.len() as u32).to_le_bytes())?;
+    Ok(())
+}}
 
-    fn on_message(&self, conn: &Connection, msg: Message) -> Option<Message> {{
-        println!("Received from {{}}: {{}} bytes", conn.id(), msg.data.len());
+fn main() -> Result<(), Box<dyn std::error::Error>> {{
+    let mut shm = SharedMemory::create(SEGMENT, SIZE)?;
+    println!("Publishing to segment: {{}}", SEGMENT);
 
-        // Echo the message back
-        Some(msg)
+    for tick in 0u64.. {{
+        write_state(&mut shm, &json!({{"tick": tick, "status": "running"}}))?;
+        std::thread::sleep(Duration::from_secs(1));
     }}
 
-    fn on_disconnect(&self, conn: &Connection) {{
-        println!("Client disconnected: {{}}", conn.id());
-    }}
+    Ok(())
 }}
+"#,
+            header = rust_header(&format!("Shared memory writer for segment: {name}")),
+        ),
+        ChannelType::File => format!(
+            r#"{header}
+use ipckit::{{FileChannel, FileMessageType}};
+use serde_json::json;
+use std::time::Duration;
+
+/// Directory to exchange messages in
+pub const DIR: &str = "{name}";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {{
-    println!("Starting socket server on: {name}");
+    let mut channel = FileChannel::backend(DIR)?;
+    println!("Serving file channel in: {{}}", DIR);
 
-    let config = SocketServerConfig::default();
-    let handler = Arc::new(MyHandler);
+    loop {{
+        for msg in channel.recv_wait(Duration::from_secs(1))? {{
+            if msg.msg_type != FileMessageType::Request {{
+                continue;
+            }}
+            match msg.method.as_deref() {{
+                Some("ping") => channel.send_response(&msg.id, json!("pong"))?,
+                Some("echo") => channel.send_response(&msg.id, msg.payload.clone())?,
+                other => channel.send_error(&msg.id, &format!("unknown method: {{:?}}", other))?,
+            }}
+        }}
+    }}
+}}
+"#,
+            header = rust_header(&format!("File channel backend for directory: {name}")),
+        ),
+        ChannelType::Thread => generate_thread(name),
+    }
+}
 
-    let server = SocketServer::new("{name}", config, handler)?;
-    server.run()?;
+/// Thread channels live inside one process, so both halves are generated
+/// together.
+fn generate_thread(name: &str) -> String {
+    format!(
+        r#"{header}
+use ipckit::{{IpcError, ThreadChannel, ThreadSender}};
+use serde_json::{{json, Value}};
 
-    Ok(())
+/// A command for the worker, with where to send its result
+pub struct Job {{
+    pub command: String,
+    pub params: Value,
+    pub reply: ThreadSender<Result<Value, String>>,
 }}
-"#
-        ),
-        _ => format!(
-            r#"//! Server for {name} ({ct})
-//!
-//! Generated by ipckit CLI
 
-use ipckit::IpcError;
+/// Run the worker until every job sender is dropped
+fn spawn_worker() -> (ThreadSender<Job>, std::thread::JoinHandle<()>) {{
+    let (tx, rx) = ThreadChannel::<Job>::unbounded();
+    let handle = std::thread::Builder::new()
+        .name("{name}".to_string())
+        .spawn(move || {{
+            while let Ok(job) = rx.recv() {{
+                let result = match job.command.as_str() {{
+                    "ping" => Ok(json!("pong")),
+                    "echo" => Ok(job.params),
+                    other => Err(format!("unknown command: {{}}", other)),
+                }};
+                let _ = job.reply.send(result);
+            }}
+        }})
+        .expect("failed to spawn worker");
+    (tx, handle)
+}}
+
+/// Send a command to the worker and wait for its result
+fn call(jobs: &ThreadSender<Job>, command: &str, params: Value) -> Result<Value, IpcError> {{
+    let (reply, result) = ThreadChannel::bounded(1);
+    jobs.send(Job {{
+        command: command.to_string(),
+        params,
+        reply,
+    }})?;
+    result.recv()?.map_err(IpcError::Other)
+}}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {{
-    // TODO: Implement server for {ct} channel type
-    println!("Server for channel: {name}");
+    let (jobs, worker) = spawn_worker();
+
+    println!("ping -> {{}}", call(&jobs, "ping", json!({{}}))?);
+    println!("echo -> {{}}", call(&jobs, "echo", json!({{"hello": "world"}}))?);
+
+    drop(jobs);
+    worker.join().expect("worker panicked");
     Ok(())
 }}
 "#,
-            ct = channel_type_name(channel_type)
-        ),
-    }
+        header = rust_header(&format!("In-process worker for thread channel: {name}")),
+    )
 }
 
-fn generate_python(channel_type: ChannelType, name: &str) -> String {
-    match channel_type {
+fn generate_python(
+    channel_type: ChannelType,
+    name: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let code = match channel_type {
         ChannelType::Pipe => format!(
-            r#"""
-IPC Client/Server for channel: {name}
+            r#""""
+IPC client and server for channel: {name}
 
-Generated by ipckit CLI
+Generated by ipckit CLI. Run with `server` as the only argument to serve,
+without arguments to run the asyncio client against a running server.
 """
 
+import asyncio
+import sys
+from typing import Any
+
 import ipckit
-import json
+
+CHANNEL = "{name}"
 
 
 class Client:
-    """IPC Client for {name}"""
+    """Blocking client for {name}"""
 
-    def __init__(self):
-        self.channel = ipckit.IpcChannel.connect("{name}")
+    def __init__(self) -> None:
+        self.channel = ipckit.IpcChannel.connect(CHANNEL)
 
-    def request(self, command: str, params: dict = None) -> dict:
-        """Send a request and receive a response"""
-        request = {{
-            "command": command,
-            "params": params or {{}},
-        }}
-        self.channel.send(json.dumps(request).encode())
-        response = self.channel.recv()
-        return json.loads(response.decode())
+    def call(self, command: str, params: Any = None) -> Any:
+        """Send a command and wait for its result"""
+        self.channel.send_json({{"command": command, "params": params or {{}}}})
+        response = self.channel.recv_json()
+        if not response.get("success"):
+            raise RuntimeError(response.get("error") or "request failed")
+        return response.get("data")
+
+    def ping(self) -> Any:
+        return self.call("ping")
+
+
+class AsyncClient:
+    """asyncio client for {name}
+
+    ipckit calls block, so they run in a worker thread; the lock keeps each
+    request paired with its response.
+    """
+
+    def __init__(self, client: Client) -> None:
+        self._client = client
+        self._lock = asyncio.Lock()
+
+    @classmethod
+    async def connect(cls) -> "AsyncClient":
+        return cls(await asyncio.to_thread(Client))
+
+    async def call(self, command: str, params: Any = None) -> Any:
+        async with self._lock:
+            return await asyncio.to_thread(self._client.call, command, params)
 
-    def ping(self) -> str:
-        """Ping the server"""
-        response = self.request("ping")
-        return response.get("data", "")
+    async def ping(self) -> Any:
+        return await self.call("ping")
 
 
 class Server:
-    """IPC Server for {name}"""
+    """Server for {name}"""
 
-    def __init__(self):
-        self.channel = ipckit.IpcChannel.create("{name}")
+    def __init__(self) -> None:
         self.handlers = {{
-            "ping": self._handle_ping,
-            "echo": self._handle_echo,
+            "ping": lambda params: "pong",
+            "echo": lambda params: params,
         }}
 
-    def _handle_ping(self, params: dict) -> dict:
-        return {{"success": True, "data": "pong"}}
+    def handle(self, request: dict) -> dict:
+        command = request.get("command", "")
+        handler = self.handlers.get(command)
+        if handler is None:
+            return {{"success": False, "error": f"unknown command: {{command}}"}}
+        try:
+            return {{"success": True, "data": handler(request.get("params", {{}}))}}
+        except Exception as e:
+            return {{"success": False, "error": str(e)}}
+
+    def run(self) -> None:
+        print(f"Serving channel: {{CHANNEL}}")
+        while True:
+            # A named pipe serves one client; create a fresh one for the next
+            channel = ipckit.IpcChannel.create(CHANNEL)
+            channel.wait_for_client()
+            try:
+                while True:
+                    channel.send_json(self.handle(channel.recv_json()))
+            except Exception as e:
+                print(f"Client disconnected: {{e}}")
+            del channel
 
-    def _handle_echo(self, params: dict) -> dict:
-        return {{"success": True, "data": params}}
 
-    def run(self):
-        """Run the server loop"""
-        print(f"Server listening on: {name}")
+async def main() -> None:
+    client = await AsyncClient.connect()
+    print("ping ->", await client.ping())
+    print("echo ->", await client.call("echo", {{"hello": "world"}}))
 
-        while True:
-            self.channel.wait_for_client()
 
-            try:
-                data = self.channel.recv()
-                request = json.loads(data.decode())
-
-                command = request.get("command", "")
-                params = request.get("params", {{}})
-
-                handler = self.handlers.get(command)
-                if handler:
-                    response = handler(params)
-                else:
-                    response = {{
-                        "success": False,
-                        "error": f"Unknown command: {{command}}"
-                    }}
-
-                self.channel.send(json.dumps(response).encode())
-            except Exception as e:
-                print(f"Error: {{e}}")
+if __name__ == "__main__":
+    if sys.argv[1:] == ["server"]:
+        Server().run()
+    else:
+        asyncio.run(main())
+"#
+        ),
+        ChannelType::Socket => format!(
+            r#""""
+asyncio client for the ipckit API server on: {name}
+
+Generated by ipckit CLI.
+"""
+
+import asyncio
+from typing import Any
+
+import ipckit
+
+SOCKET = "{name}"
+
+
+class AsyncApiClient:
+    """asyncio wrapper around ipckit.ApiClient
+
+    Each request runs in a worker thread, so many can be in flight at once.
+    """
+
+    def __init__(self, socket_path: str = SOCKET, timeout_ms: int | None = 30_000) -> None:
+        self._client = ipckit.ApiClient(socket_path, timeout_ms)
+
+    async def get(self, path: str) -> Any:
+        return await asyncio.to_thread(self._client.get, path)
+
+    async def post(self, path: str, body: Any = None) -> Any:
+        return await asyncio.to_thread(self._client.post, path, body)
+
+    async def put(self, path: str, body: Any = None) -> Any:
+        return await asyncio.to_thread(self._client.put, path, body)
+
+    async def delete(self, path: str) -> Any:
+        return await asyncio.to_thread(self._client.delete, path)
+
+
+async def main() -> None:
+    client = AsyncApiClient()
+    routes = await client.get("{ROUTES_PATH}")
+    for route in routes:
+        print(route["method"], route["path"])
 
 
 if __name__ == "__main__":
-    import sys
+    asyncio.run(main())
+"#
+        ),
+        ChannelType::File => format!(
+            r#""""
+File channel client and backend for directory: {name}
+
+Generated by ipckit CLI. Run with `server` as the only argument to serve,
+without arguments to run the asyncio client.
+"""
+
+import asyncio
+import sys
+from typing import Any
+
+import ipckit
+
+DIR = "{name}"
+
 
-    if len(sys.argv) > 1 and sys.argv[1] == "server":
-        server = Server()
-        server.run()
+class AsyncClient:
+    """asyncio frontend for {name}"""
+
+    def __init__(self, timeout_ms: int = 5_000) -> None:
+        self.channel = ipckit.FileChannel.frontend(DIR)
+        self.timeout_ms = timeout_ms
+
+    def _call(self, method: str, params: Any) -> Any:
+        request_id = self.channel.send_request(method, params or {{}})
+        response = self.channel.wait_response(request_id, self.timeout_ms)
+        if response.get("error"):
+            raise RuntimeError(response["error"])
+        return response.get("payload")
+
+    async def call(self, method: str, params: Any = None) -> Any:
+        return await asyncio.to_thread(self._call, method, params)
+
+
+def serve() -> None:
+    channel = ipckit.FileChannel.backend(DIR)
+    print(f"Serving file channel in: {{DIR}}")
+    while True:
+        for msg in channel.recv_wait(1000):
+            if msg.get("type") != "request":
+                continue
+            method = msg.get("method")
+            if method == "ping":
+                channel.send_response(msg["id"], "pong")
+            elif method == "echo":
+                channel.send_response(msg["id"], msg.get("payload"))
+            else:
+                channel.send_error(msg["id"], f"unknown method: {{method}}")
+
+
+async def main() -> None:
+    client = AsyncClient()
+    print("ping ->", await client.call("ping"))
+
+
+if __name__ == "__main__":
+    if sys.argv[1:] == ["server"]:
+        serve()
     else:
-        client = Client()
-        print(f"Ping response: {{client.ping()}}")
+        asyncio.run(main())
 "#
         ),
-        _ => format!(
-            r#"""
-Client/Server for {name} ({ct})
+        ChannelType::Shm => format!(
+            r#""""
+Shared memory reader and writer for segment: {name}
 
-Generated by ipckit CLI
+Generated by ipckit CLI. The segment holds a little-endian u32 length
+followed by that many bytes of JSON. Run with `server` as the only argument
+to publish, without arguments to watch with asyncio.
 """
 
+import asyncio
+import json
+import struct
+import sys
+import time
+from typing import Any
+
 import ipckit
 
+SEGMENT = "{name}"
+SIZE = 64 * 1024
+
+
+def write_state(shm: ipckit.SharedMemory, state: Any) -> None:
+    data = json.dumps(state).encode()
+    shm.write(4, data)
+    shm.write(0, struct.pack("<I", len(data)))
+
+
+def read_state(shm: ipckit.SharedMemory) -> Any:
+    (length,) = struct.unpack("<I", shm.read(0, 4))
+    return json.loads(shm.read(4, length))
+
 
-def main():
-    # TODO: Implement for {ct} channel type
-    print("Channel: {name}")
+def serve() -> None:
+    shm = ipckit.SharedMemory.create(SEGMENT, SIZE)
+    print(f"Publishing to segment: {{SEGMENT}}")
+    tick = 0
+    while True:
+        write_state(shm, {{"tick": tick, "status": "running"}})
+        tick += 1
+        time.sleep(1)
+
+
+async def watch(interval: float = 1.0) -> None:
+    shm = ipckit.SharedMemory.open(SEGMENT)
+    while True:
+        print("state ->", read_state(shm))
+        await asyncio.sleep(interval)
 
 
 if __name__ == "__main__":
-    main()
-"#,
-            ct = channel_type_name(channel_type)
+    if sys.argv[1:] == ["server"]:
+        serve()
+    else:
+        asyncio.run(watch())
+"#
         ),
-    }
+        ChannelType::Thread => {
+            return Err(
+                "Thread channels only exist inside one Rust process; generate Python code \
+                 for a pipe, socket, file or shm channel instead"
+                    .into(),
+            )
+        }
+    };
+    Ok(code)
 }
 
 fn generate_handler(name: &str) -> String {
@@ -520,3 +907,395 @@ mod tests {{
         name_lower = name.to_lowercase()
     )
 }
+
+/// Routes and types to generate a TypeScript client for
+struct ApiDescription {
+    routes: Vec<Route>,
+    /// Named schemas, already rendered as TypeScript types
+    types: BTreeMap<String, String>,
+}
+
+struct Route {
+    method: String,
+    path: String,
+    operation_id: Option<String>,
+    summary: Option<String>,
+    /// TypeScript type of the JSON body, for methods that send one
+    body: Option<String>,
+    response: String,
+}
+
+/// Read the route index of a running API server. It carries no types.
+fn fetch_routes(socket: &str) -> Result<ApiDescription, Box<dyn std::error::Error>> {
+    let client = api_client(socket, Some(Duration::from_secs(5)));
+    let index = client.get(ROUTES_PATH).map_err(|e| {
+        format!(
+            "Could not read {} from {}: {} (is route_index registered?)",
+            ROUTES_PATH, socket, e
+        )
+    })?;
+    let entries = index
+        .as_array()
+        .ok_or_else(|| format!("{} did not return a list of routes", ROUTES_PATH))?;
+
+    let routes = entries
+        .iter()
+        .filter_map(|r| Some((r["method"].as_str()?, r["path"].as_str()?)))
+        .filter(|(method, _)| !matches!(*method, "OPTIONS" | "HEAD"))
+        .map(|(method, path)| Route {
+            method: method.to_string(),
+            path: path.to_string(),
+            operation_id: None,
+            summary: None,
+            body: has_body(method).then(|| "unknown".to_string()),
+            response: "unknown".to_string(),
+        })
+        .collect();
+
+    Ok(ApiDescription {
+        routes,
+        types: BTreeMap::new(),
+    })
+}
+
+/// Read routes and JSON schemas from an OpenAPI 3 document in JSON.
+fn load_openapi(path: &Path) -> Result<ApiDescription, Box<dyn std::error::Error>> {
+    let doc: Value = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| format!("{} is not an OpenAPI JSON document: {}", path.display(), e))?;
+    let paths = doc["paths"]
+        .as_object()
+        .ok_or_else(|| format!("{} has no 'paths' object", path.display()))?;
+
+    let mut routes = Vec::new();
+    for (route_path, item) in paths {
+        for method in ["get", "post", "put", "patch", "delete"] {
+            let Some(op) = item.get(method) else {
+                continue;
+            };
+            let json_schema =
+                |content: &Value| content["content"]["application/json"]["schema"].clone();
+            let body = op.get("requestBody").map(|b| schema_to_ts(&json_schema(b)));
+            let response = op["responses"]
+                .as_object()
+                .and_then(|responses| {
+                    responses
+                        .iter()
+                        .find(|(status, _)| status.starts_with('2'))
+                        .map(|(_, r)| r)
+                })
+                .map_or("unknown".to_string(), |r| schema_to_ts(&json_schema(r)));
+            routes.push(Route {
+                method: method.to_uppercase(),
+                path: route_path.clone(),
+                operation_id: op["operationId"].as_str().map(str::to_string),
+                summary: op["summary"].as_str().map(str::to_string),
+                body: body.or_else(|| has_body(method).then(|| "unknown".to_string())),
+                response,
+            });
+        }
+    }
+
+    let types = doc["components"]["schemas"]
+        .as_object()
+        .map(|schemas| {
+            schemas
+                .iter()
+                .map(|(name, schema)| (ts_type_name(name), schema_to_ts(schema)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(ApiDescription { routes, types })
+}
+
+fn has_body(method: &str) -> bool {
+    matches!(
+        method.to_ascii_uppercase().as_str(),
+        "POST" | "PUT" | "PATCH"
+    )
+}
+
+/// Render a JSON schema as a TypeScript type.
+fn schema_to_ts(schema: &Value) -> String {
+    let ty = if let Some(reference) = schema["$ref"].as_str() {
+        ts_type_name(reference.rsplit('/').next().unwrap_or(reference))
+    } else if let Some(values) = schema["enum"].as_array() {
+        values
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(" | ")
+    } else if let Some(variants) = schema["oneOf"]
+        .as_array()
+        .or_else(|| schema["anyOf"].as_array())
+    {
+        variants
+            .iter()
+            .map(schema_to_ts)
+            .collect::<Vec<_>>()
+            .join(" | ")
+    } else if let Some(parts) = schema["allOf"].as_array() {
+        parts
+            .iter()
+            .map(schema_to_ts)
+            .collect::<Vec<_>>()
+            .join(" & ")
+    } else {
+        match schema["type"].as_str() {
+            Some("string") => "string".to_string(),
+            Some("integer" | "number") => "number".to_string(),
+            Some("boolean") => "boolean".to_string(),
+            Some("null") => "null".to_string(),
+            Some("array") => format!("Array<{}>", schema_to_ts(&schema["items"])),
+            Some("object") | None if schema.get("properties").is_some() => object_to_ts(schema),
+            Some("object") => match &schema["additionalProperties"] {
+                Value::Object(_) => format!(
+                    "Record<string, {}>",
+                    schema_to_ts(&schema["additionalProperties"])
+                ),
+                _ => "Record<string, unknown>".to_string(),
+            },
+            _ => "unknown".to_string(),
+        }
+    };
+    if schema["nullable"].as_bool() == Some(true) {
+        format!("{} | null", ty)
+    } else {
+        ty
+    }
+}
+
+fn object_to_ts(schema: &Value) -> String {
+    let required: HashSet<&str> = schema["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let fields: Vec<String> = schema["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, prop)| {
+            let key = if is_ts_identifier(name) {
+                name.clone()
+            } else {
+                Value::from(name.as_str()).to_string()
+            };
+            let optional = if required.contains(name.as_str()) {
+                ""
+            } else {
+                "?"
+            };
+            format!("{}{}: {}", key, optional, schema_to_ts(prop))
+        })
+        .collect();
+    format!("{{ {} }}", fields.join("; "))
+}
+
+fn is_ts_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Split `s` into its alphanumeric words.
+fn words(s: &str) -> impl Iterator<Item = &str> {
+    s.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+}
+
+fn pascal_case(s: &str) -> String {
+    words(s)
+        .map(|w| {
+            let mut chars = w.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn camel_case(s: &str) -> String {
+    let pascal = pascal_case(s);
+    let mut chars = pascal.chars();
+    let ident = chars
+        .next()
+        .map(|c| c.to_ascii_lowercase().to_string() + chars.as_str())
+        .unwrap_or_default();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", ident)
+    } else {
+        ident
+    }
+}
+
+fn ts_type_name(s: &str) -> String {
+    let name = pascal_case(s);
+    if name.starts_with(|c: char| c.is_ascii_digit()) || name.is_empty() {
+        format!("T{}", name)
+    } else {
+        name
+    }
+}
+
+/// A path parameter: its name in the route and whether it spans segments
+fn path_params(path: &str) -> Vec<(String, bool)> {
+    path.split('/')
+        .filter_map(|s| s.strip_prefix('{')?.strip_suffix('}'))
+        .map(|p| match p.strip_prefix('*') {
+            Some(rest) => (rest.to_string(), true),
+            None => (p.to_string(), false),
+        })
+        .collect()
+}
+
+/// Method name for a route: its operationId, or method and path
+/// (`GET /v1/tasks/{id}` becomes `getV1TasksById`).
+fn method_name(route: &Route) -> String {
+    if let Some(id) = route
+        .operation_id
+        .as_deref()
+        .filter(|id| words(id).next().is_some())
+    {
+        return camel_case(id);
+    }
+    let mut name = route.method.to_ascii_lowercase();
+    for segment in route.path.split('/').filter(|s| !s.is_empty()) {
+        match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(param) => {
+                name.push_str("By");
+                name.push_str(&pascal_case(param));
+            }
+            None => name.push_str(&pascal_case(segment)),
+        }
+    }
+    name
+}
+
+fn render_typescript(source: &str, api: &ApiDescription) -> String {
+    let mut out = format!(
+        r#"// Typed client for an ipckit API server
+//
+// Generated by ipckit CLI {IPCKIT_VERSION} from {source}. Do not edit by hand.
+//
+// The API server listens on a local socket; point `baseUrl` at whatever
+// exposes it over HTTP to this code (a webview bridge or a proxy).
+"#
+    );
+
+    for (name, ty) in &api.types {
+        out.push_str(&format!("\nexport type {} = {};\n", name, ty));
+    }
+
+    out.push_str(
+        r#"
+export class ApiError extends Error {
+  constructor(
+    readonly status: number,
+    readonly body: unknown,
+  ) {
+    super(`API request failed with status ${status}`);
+  }
+}
+
+export interface ClientOptions {
+  /** Headers sent with every request */
+  headers?: Record<string, string>;
+  /** fetch implementation, defaults to the global one */
+  fetch?: typeof fetch;
+}
+
+export class IpckitClient {
+  private readonly baseUrl: string;
+
+  constructor(
+    baseUrl: string,
+    private readonly options: ClientOptions = {},
+  ) {
+    this.baseUrl = baseUrl.replace(/\/+$/, "");
+  }
+
+  /** Send a request, returning the decoded JSON (or text) response body. */
+  async request<T>(method: string, path: string, body?: unknown): Promise<T> {
+    const doFetch = this.options.fetch ?? fetch;
+    const headers: Record<string, string> = { ...this.options.headers };
+    if (body !== undefined) {
+      headers["Content-Type"] = "application/json";
+    }
+    const response = await doFetch(this.baseUrl + path, {
+      method,
+      headers,
+      body: body === undefined ? undefined : JSON.stringify(body),
+    });
+    const isJson = response.headers.get("Content-Type")?.includes("json") ?? false;
+    const text = await response.text();
+    const data: unknown = isJson && text ? JSON.parse(text) : text;
+    if (!response.ok) {
+      throw new ApiError(response.status, data);
+    }
+    return data as T;
+  }
+
+  /** Open a WebSocket on the same host, passing each JSON message to `onMessage`. */
+  subscribe<T = unknown>(path: string, onMessage: (message: T) => void): WebSocket {
+    const socket = new WebSocket(this.baseUrl.replace(/^http/, "ws") + path);
+    socket.onmessage = (event) => onMessage(JSON.parse(String(event.data)) as T);
+    return socket;
+  }
+"#,
+    );
+
+    let mut used = HashSet::new();
+    for route in &api.routes {
+        let base = method_name(route);
+        let mut name = base.clone();
+        let mut n = 2;
+        while !used.insert(name.clone()) {
+            name = format!("{}{}", base, n);
+            n += 1;
+        }
+
+        let params = path_params(&route.path);
+        let mut args: Vec<String> = params
+            .iter()
+            .map(|(p, _)| format!("{}: string", camel_case(p)))
+            .collect();
+        if let Some(ref body) = route.body {
+            args.push(format!("body: {}", body));
+        }
+
+        let mut url = route.path.clone();
+        for (param, wildcard) in &params {
+            let (placeholder, encode) = if *wildcard {
+                (format!("{{*{}}}", param), "encodeURI")
+            } else {
+                (format!("{{{}}}", param), "encodeURIComponent")
+            };
+            url = url.replace(
+                &placeholder,
+                &format!("${{{}({})}}", encode, camel_case(param)),
+            );
+        }
+        let body_arg = if route.body.is_some() { ", body" } else { "" };
+
+        out.push_str("\n  /**\n");
+        if let Some(ref summary) = route.summary {
+            out.push_str(&format!("   * {}\n   *\n", summary.replace("*/", "* /")));
+        }
+        out.push_str(&format!("   * `{} {}`\n   */\n", route.method, route.path));
+        out.push_str(&format!(
+            "  {}({}): Promise<{}> {{\n    return this.request(\"{}\", `{}`{});\n  }}\n",
+            name,
+            args.join(", "),
+            route.response,
+            route.method,
+            url,
+            body_arg
+        ));
+    }
+
+    out.push_str("}\n");
+    out
+}
@@ -1,8 +1,12 @@
 //! Listen command implementation
+//!
+//! Besides listening on a channel directly, `--attach` connects to a running
+//! socket server as a traffic observer and prints every message exchanged by
+//! its other clients.
 
 use super::{channel_type_name, format_output, print_error, print_info, print_success};
 use crate::{ChannelType, OutputFormat};
-use ipckit::{LocalSocketListener, NamedPipe, SharedMemory};
+use ipckit::{LocalSocketListener, NamedPipe, SharedMemory, SocketClient, TapRecord};
 use std::io::Read;
 use std::time::Duration;
 
@@ -144,3 +148,54 @@ pub fn listen(
 
     Ok(())
 }
+
+pub fn listen_attach(
+    socket: &str,
+    format: OutputFormat,
+    timeout_ms: u64,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    print_info(&format!("Attaching to socket server '{}'...", socket));
+
+    let mut client = if timeout_ms > 0 {
+        SocketClient::connect_timeout(socket, Duration::from_millis(timeout_ms))?
+    } else {
+        SocketClient::connect(socket)?
+    };
+    client
+        .attach()
+        .map_err(|e| format!("Attach rejected (is allow_attach enabled?): {}", e))?;
+    print_success("Attached, printing live traffic (Ctrl+C to stop)");
+
+    loop {
+        let msg = match client.recv() {
+            Ok(msg) => msg,
+            Err(e) => {
+                print_info(&format!("Detached: {}", e));
+                break;
+            }
+        };
+
+        let Some(record) = TapRecord::from_message(&msg) else {
+            if verbose {
+                println!("Ignoring non-tap message: {:?}", msg.msg_type);
+            }
+            continue;
+        };
+
+        match format {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&record)?),
+            _ => {
+                let body = serde_json::to_vec(&record.message)?;
+                println!(
+                    "[conn {}] {} {}",
+                    record.connection,
+                    record.direction.arrow(),
+                    format_output(&body, format)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
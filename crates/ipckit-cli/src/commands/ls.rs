@@ -0,0 +1,74 @@
+//! List the channels advertised in the discovery registry
+
+use crate::{ChannelType, OutputFormat};
+use clap::ValueEnum;
+use console::style;
+use ipckit::{ChannelEntry, Discovery};
+use std::time::SystemTime;
+
+use super::print_info;
+
+/// List advertised channels, optionally only those of one type.
+pub fn ls(
+    channel_type: Option<ChannelType>,
+    format: OutputFormat,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let discovery = Discovery::new();
+    if verbose {
+        print_info(&format!("Reading registry {}", discovery.dir().display()));
+    }
+
+    let kind = channel_type
+        .and_then(|ct| ct.to_possible_value())
+        .map(|v| v.get_name().to_string());
+    let entries: Vec<ChannelEntry> = discovery
+        .list()?
+        .into_iter()
+        .filter(|e| kind.as_ref().is_none_or(|k| *k == e.kind))
+        .collect();
+
+    if let OutputFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        style(format!(
+            "{:<32} {:<8} {:>8}  {:<10} {}",
+            "NAME", "TYPE", "PID", "AGE", "PURPOSE"
+        ))
+        .bold()
+    );
+    for entry in &entries {
+        println!(
+            "{:<32} {:<8} {:>8}  {:<10} {}",
+            entry.name,
+            entry.kind,
+            entry.pid,
+            format_age(entry.registered_at),
+            style(entry.purpose.as_deref().unwrap_or("")).dim()
+        );
+    }
+    if entries.is_empty() {
+        println!("{}", style("(no channels advertised)").dim());
+    }
+    Ok(())
+}
+
+fn format_age(registered: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(registered)
+        .unwrap_or_default()
+        .as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h", secs / 3600)
+    } else {
+        format!("{}d", secs / 86400)
+    }
+}
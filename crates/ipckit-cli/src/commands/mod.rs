@@ -6,23 +6,84 @@ mod create;
 mod generate;
 mod info;
 mod listen;
+mod ls;
 mod monitor;
+mod record;
+mod replay;
 mod send;
 mod serve;
+mod shell;
+mod task;
+mod top;
 
-pub use bench::bench;
+pub use bench::{bench, BenchOptions};
 pub use completions::completions;
 pub use create::create;
-pub use generate::generate;
+pub use generate::{generate, generate_typescript};
 pub use info::info;
-pub use listen::listen;
+pub use listen::{listen, listen_attach};
+pub use ls::ls;
 pub use monitor::monitor;
+pub use record::record;
+pub use replay::replay;
 pub use send::send;
 pub use serve::serve;
+pub use shell::shell;
+pub use task::{task_cancel, task_inspect, task_logs, task_ls};
+pub use top::top;
 
 use crate::{ChannelType, OutputFormat};
 use console::{style, Term};
+use ipckit::{ApiClient, ContentFormat};
 use std::io::Write;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Settings applied to every API client, from the config file
+#[derive(Default)]
+pub struct ClientOptions {
+    /// Bearer token sent with each request
+    pub token: Option<String>,
+    /// Encoding of request bodies
+    pub format: ContentFormat,
+}
+
+static CLIENT_OPTIONS: OnceLock<ClientOptions> = OnceLock::new();
+
+/// Set the options used by [`api_client`]; only the first call has an effect
+pub fn set_client_options(options: ClientOptions) {
+    let _ = CLIENT_OPTIONS.set(options);
+}
+
+/// Create an API client for `socket` with the configured token and codec
+pub fn api_client(socket: &str, timeout: Option<Duration>) -> ApiClient {
+    let mut client = ApiClient::new(socket).format(
+        CLIENT_OPTIONS
+            .get()
+            .map(|options| options.format)
+            .unwrap_or_default(),
+    );
+    client.set_timeout(timeout);
+    match CLIENT_OPTIONS
+        .get()
+        .and_then(|options| options.token.as_ref())
+    {
+        Some(token) => client.bearer_token(token),
+        None => client,
+    }
+}
+
+/// Socket path used by `serve` when none is given
+pub fn default_socket() -> String {
+    #[cfg(windows)]
+    {
+        "\\\\.\\pipe\\ipckit".to_string()
+    }
+    #[cfg(unix)]
+    {
+        "/tmp/ipckit.sock".to_string()
+    }
+}
 
 /// Print a success message
 pub fn print_success(msg: &str) {
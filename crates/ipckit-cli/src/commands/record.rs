@@ -0,0 +1,287 @@
+//! Record command implementation
+//!
+//! Captures every frame received on a channel into a JSON Lines session file
+//! that can later be fed back with `ipckit replay`.
+//!
+//! Pipes and sockets are read with ipckit's length-prefixed framing, as
+//! written by [`IpcChannel`] and the socket clients, so each entry holds one
+//! whole message however the bytes arrived.
+//!
+//! The first line of a session is a [`SessionEntry::Header`] describing the
+//! channel; every following line is a [`SessionEntry::Frame`] carrying the
+//! payload (base64-encoded) and its offset from the start of the recording.
+
+use super::{channel_type_name, print_error, print_info, print_success};
+use crate::ChannelType;
+use base64::Engine;
+use clap::ValueEnum;
+use ipckit::{IpcChannel, IpcError, LocalSocketTransport, SharedMemory};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A decoded frame: its offset from the start of the recording and payload.
+pub type RecordedFrame = (Duration, Vec<u8>);
+
+/// A single line of a recorded session file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SessionEntry {
+    /// Describes the channel the session was captured from.
+    Header {
+        /// Channel type name (as accepted by `--type`)
+        channel_type: String,
+        /// Channel name
+        name: String,
+        /// Unix timestamp (seconds) at which the recording started
+        started_at: f64,
+    },
+    /// A captured frame.
+    Frame {
+        /// Milliseconds since the start of the recording
+        offset_ms: f64,
+        /// Frame payload, base64-encoded
+        data: String,
+    },
+}
+
+impl SessionEntry {
+    /// Get the channel type recorded in a header entry.
+    pub fn channel_type(&self) -> Option<ChannelType> {
+        match self {
+            SessionEntry::Header { channel_type, .. } => {
+                ChannelType::from_str(channel_type, true).ok()
+            }
+            SessionEntry::Frame { .. } => None,
+        }
+    }
+
+    /// Decode the payload of a frame entry.
+    pub fn decode_frame(&self) -> Result<Option<RecordedFrame>, Box<dyn std::error::Error>> {
+        match self {
+            SessionEntry::Frame { offset_ms, data } => {
+                let bytes = base64::engine::general_purpose::STANDARD.decode(data)?;
+                Ok(Some((Duration::from_secs_f64(offset_ms / 1000.0), bytes)))
+            }
+            SessionEntry::Header { .. } => Ok(None),
+        }
+    }
+}
+
+/// Writes session entries to a JSON Lines file.
+struct SessionWriter {
+    writer: BufWriter<File>,
+    start: Instant,
+    frames: u64,
+    bytes: u64,
+}
+
+impl SessionWriter {
+    fn create(path: &Path, channel_type: ChannelType, name: &str) -> std::io::Result<Self> {
+        let mut session = Self {
+            writer: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+            frames: 0,
+            bytes: 0,
+        };
+
+        let channel_type = channel_type
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default();
+        session.write_entry(&SessionEntry::Header {
+            channel_type,
+            name: name.to_string(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or(Duration::ZERO)
+                .as_secs_f64(),
+        })?;
+
+        Ok(session)
+    }
+
+    fn record(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let entry = SessionEntry::Frame {
+            offset_ms: self.start.elapsed().as_secs_f64() * 1000.0,
+            data: base64::engine::general_purpose::STANDARD.encode(data),
+        };
+        self.write_entry(&entry)?;
+        self.frames += 1;
+        self.bytes += data.len() as u64;
+        Ok(())
+    }
+
+    fn write_entry(&mut self, entry: &SessionEntry) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.writer, entry)?;
+        self.writer.write_all(b"\n")?;
+        // Flush every entry so an interrupted recording is still usable
+        self.writer.flush()
+    }
+}
+
+pub fn record(
+    channel_type: ChannelType,
+    name: &str,
+    out: &Path,
+    max_frames: Option<u64>,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut session = SessionWriter::create(out, channel_type, name)?;
+    let limit_reached =
+        |session: &SessionWriter| max_frames.is_some_and(|max| session.frames >= max);
+
+    print_info(&format!(
+        "Recording {} '{}' to {:?} (Ctrl+C to stop)...",
+        channel_type_name(channel_type),
+        name,
+        out
+    ));
+
+    match channel_type {
+        ChannelType::Pipe => {
+            let mut channel = IpcChannel::<Vec<u8>>::create(name)?;
+            if verbose {
+                println!("Named pipe created, waiting for client...");
+            }
+            channel.wait_for_client()?;
+            print_success("Client connected");
+            record_frames(&mut channel, &mut session, max_frames)?;
+        }
+
+        ChannelType::Socket => {
+            if verbose {
+                println!("Socket bound, waiting for connections...");
+            }
+
+            // One client at a time; a new listener is bound once it leaves
+            while !limit_reached(&session) {
+                let mut channel =
+                    IpcChannel::<Vec<u8>>::create_with(&LocalSocketTransport, name)?;
+                if let Err(e) = channel.wait_for_client() {
+                    print_error(&format!("Accept error: {}", e));
+                    break;
+                }
+                print_success("Client connected");
+                record_frames(&mut channel, &mut session, max_frames)?;
+            }
+        }
+
+        ChannelType::Shm => {
+            let shm = SharedMemory::open(name)?;
+            if verbose {
+                println!("Shared memory opened");
+            }
+
+            // Every observed change of the segment is recorded as one frame
+            let mut last_data: Vec<u8> = Vec::new();
+            while !limit_reached(&session) {
+                let data = shm.read(0, shm.size())?;
+                if data != last_data {
+                    session.record(&data)?;
+                    last_data = data;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        ChannelType::File => {
+            let mut channel = ipckit::FileChannel::frontend(name)?;
+            if verbose {
+                println!("File channel opened");
+            }
+
+            'poll: loop {
+                for msg in channel.recv()? {
+                    session.record(&serde_json::to_vec(&msg)?)?;
+                    if limit_reached(&session) {
+                        break 'poll;
+                    }
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+
+        ChannelType::Thread => {
+            print_error("Thread channels cannot be recorded via CLI (they are in-process only)");
+            return Err("Thread channels are in-process only".into());
+        }
+    }
+
+    print_success(&format!(
+        "Recorded {} frames ({} bytes) to {:?}",
+        session.frames, session.bytes, out
+    ));
+
+    Ok(())
+}
+
+/// Record the frames a client sends until it disconnects or the limit is hit.
+fn record_frames(
+    channel: &mut IpcChannel<Vec<u8>>,
+    session: &mut SessionWriter,
+    max_frames: Option<u64>,
+) -> std::io::Result<()> {
+    while max_frames.is_none_or(|max| session.frames < max) {
+        match channel.recv_bytes() {
+            Ok(frame) => session.record(&frame)?,
+            Err(IpcError::Closed) => {
+                print_info("Connection closed");
+                break;
+            }
+            Err(IpcError::Io(ref e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                print_info("Connection closed");
+                break;
+            }
+            Err(e) => {
+                print_error(&format!("Read error: {}", e));
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn test_session_entry_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+
+        let mut session = SessionWriter::create(&path, ChannelType::Socket, "my_socket").unwrap();
+        session.record(b"first").unwrap();
+        session.record(&[0, 159, 255]).unwrap();
+        drop(session);
+
+        let entries: Vec<SessionEntry> = BufReader::new(File::open(&path).unwrap())
+            .lines()
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 3);
+
+        match &entries[0] {
+            SessionEntry::Header { name, .. } => assert_eq!(name, "my_socket"),
+            SessionEntry::Frame { .. } => panic!("expected a header first"),
+        }
+        assert!(matches!(
+            entries[0].channel_type(),
+            Some(ChannelType::Socket)
+        ));
+        assert!(entries[0].decode_frame().unwrap().is_none());
+
+        let frames: Vec<RecordedFrame> = entries[1..]
+            .iter()
+            .map(|entry| entry.decode_frame().unwrap().unwrap())
+            .collect();
+        assert_eq!(frames[0].1, b"first");
+        assert_eq!(frames[1].1, [0, 159, 255]);
+        assert!(frames[0].0 <= frames[1].0);
+        assert!(entries[1].channel_type().is_none());
+    }
+}
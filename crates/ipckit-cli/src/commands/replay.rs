@@ -0,0 +1,142 @@
+//! Replay command implementation
+//!
+//! Re-sends the frames of a session captured with `ipckit record`, either with
+//! the original inter-frame timing or as fast as possible.
+
+use super::record::{RecordedFrame, SessionEntry};
+use super::{channel_type_name, print_error, print_info, print_success};
+use crate::ChannelType;
+use ipckit::{FileMessage, IpcChannel, LocalSocketTransport, SharedMemory};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::time::Instant;
+
+pub fn replay(
+    session: &Path,
+    channel_type: Option<ChannelType>,
+    name: Option<String>,
+    fast: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (header, frames) = load_session(session)?;
+
+    let (recorded_type, recorded_name) = match &header {
+        SessionEntry::Header { name, .. } => (header.channel_type(), name.clone()),
+        SessionEntry::Frame { .. } => unreachable!("load_session always returns a header"),
+    };
+    let channel_type = channel_type
+        .or(recorded_type)
+        .ok_or("Session header has an unknown channel type; pass --type")?;
+    let name = name.unwrap_or(recorded_name);
+
+    print_info(&format!(
+        "Replaying {} frames to {} '{}'{}",
+        frames.len(),
+        channel_type_name(channel_type),
+        name,
+        if fast { " (fast)" } else { "" }
+    ));
+
+    let mut sink = Sink::open(channel_type, &name)?;
+    let start = Instant::now();
+    let mut bytes = 0usize;
+
+    for (i, (offset, data)) in frames.iter().enumerate() {
+        if !fast {
+            let elapsed = start.elapsed();
+            if *offset > elapsed {
+                std::thread::sleep(*offset - elapsed);
+            }
+        }
+
+        sink.send(data)?;
+        bytes += data.len();
+
+        if verbose {
+            println!(
+                "[{:>10.3} ms] frame {} ({} bytes)",
+                offset.as_secs_f64() * 1000.0,
+                i + 1,
+                data.len()
+            );
+        }
+    }
+
+    print_success(&format!(
+        "Replayed {} frames ({} bytes) in {:.2?}",
+        frames.len(),
+        bytes,
+        start.elapsed()
+    ));
+
+    Ok(())
+}
+
+fn load_session(
+    path: &Path,
+) -> Result<(SessionEntry, Vec<RecordedFrame>), Box<dyn std::error::Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut header = None;
+    let mut frames = Vec::new();
+
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry: SessionEntry = serde_json::from_str(&line)
+            .map_err(|e| format!("{:?}:{}: invalid session entry: {}", path, line_no + 1, e))?;
+
+        match entry.decode_frame()? {
+            Some(frame) => frames.push(frame),
+            None if header.is_none() => header = Some(entry),
+            None => {
+                return Err(format!("{:?}:{}: duplicate session header", path, line_no + 1).into())
+            }
+        }
+    }
+
+    let header = header.ok_or("Session file has no header")?;
+    Ok((header, frames))
+}
+
+/// Destination that replayed frames are written to.
+enum Sink {
+    /// Pipes and sockets get one ipckit frame per recorded frame
+    Channel(IpcChannel<Vec<u8>>),
+    Shm(SharedMemory),
+    File(ipckit::FileChannel),
+}
+
+impl Sink {
+    fn open(channel_type: ChannelType, name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(match channel_type {
+            ChannelType::Pipe => Sink::Channel(IpcChannel::connect(name)?),
+            ChannelType::Socket => {
+                Sink::Channel(IpcChannel::connect_with(&LocalSocketTransport, name)?)
+            }
+            ChannelType::Shm => Sink::Shm(SharedMemory::open(name)?),
+            ChannelType::File => Sink::File(ipckit::FileChannel::backend(name)?),
+            ChannelType::Thread => {
+                print_error(
+                    "Thread channels cannot be replayed via CLI (they are in-process only)",
+                );
+                return Err("Thread channels are in-process only".into());
+            }
+        })
+    }
+
+    fn send(&mut self, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Sink::Channel(channel) => channel.send_bytes(data)?,
+            Sink::Shm(shm) => shm.write(0, data)?,
+            Sink::File(channel) => {
+                let message: FileMessage = serde_json::from_slice(data)?;
+                channel.send(&message)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,71 +1,147 @@
 //! Serve command implementation
 
-use super::{print_info, print_success};
-use ipckit::socket_server::{Connection, FnHandler, Message, SocketServer, SocketServerConfig};
+use super::{default_socket, print_info, print_success};
+use ipckit::api_server::ROUTES_PATH;
+use ipckit::socket_server::SocketServerConfig;
 use ipckit::task_manager::{TaskManager, TaskManagerConfig};
+use ipckit::{
+    ApiServer, ApiServerConfig, ChannelEntry, Discovery, IpckitConfig, Response, RouteTarget,
+    ServiceManifest,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 pub fn serve(
     socket: Option<String>,
     _port: Option<u16>,
+    manifest: Option<PathBuf>,
+    config: &IpckitConfig,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let socket_path = socket.unwrap_or_else(|| {
-        #[cfg(windows)]
-        {
-            "\\\\.\\pipe\\ipckit".to_string()
-        }
-        #[cfg(unix)]
-        {
-            "/tmp/ipckit.sock".to_string()
-        }
-    });
+    if let Some(manifest) = manifest {
+        return serve_manifest(&manifest, socket, verbose);
+    }
 
-    print_info(&format!("Starting API server on {}", socket_path));
+    let socket_path = socket
+        .or_else(|| config.server.socket.clone())
+        .unwrap_or_else(default_socket);
 
-    // Create task manager
-    let _task_manager = TaskManager::new(TaskManagerConfig::default());
+    print_info(&format!("Starting API server on {}", socket_path));
 
-    // Create socket server config
-    let config = SocketServerConfig::with_path(&socket_path);
+    let task_manager = Arc::new(TaskManager::new(TaskManagerConfig::default()));
 
-    // Create and run server
-    let server = SocketServer::new(config)?;
+    let server = ApiServer::new(ApiServerConfig {
+        socket_config: SocketServerConfig {
+            allow_attach: true,
+            ..SocketServerConfig::with_path(&socket_path)
+        },
+        ..config.api_server_config()
+    });
+    server
+        .router()
+        .get("/v1/health", |_req| {
+            Response::ok(serde_json::json!({"status": "ok"}))
+        })
+        .task_routes(task_manager)
+        .metrics_route("/metrics")
+        .route_index(ROUTES_PATH);
 
     print_success(&format!("API server listening on {}", socket_path));
+    if config.server.auth_token.is_some() {
+        print_info("Requests must carry the configured bearer token");
+    }
 
     if verbose {
         println!("Available endpoints:");
-        println!("  GET  /v1/tasks          - List all tasks");
-        println!("  GET  /v1/tasks/{{id}}     - Get task by ID");
-        println!("  POST /v1/tasks          - Create a new task");
-        println!("  DELETE /v1/tasks/{{id}}  - Cancel a task");
-        println!("  GET  /v1/health         - Health check");
+        println!("  GET    /v1/tasks                 - List all tasks");
+        println!("  GET    /v1/tasks/{{id}}            - Get task by ID");
+        println!("  POST   /v1/tasks                 - Register a task");
+        println!("  DELETE /v1/tasks/{{id}}            - Cancel a task");
+        println!("  POST   /v1/tasks/{{id}}/{{action}}   - Report progress/logs/stdout/stderr/complete/fail");
+        println!("  GET    /v1/health                - Health check");
+        println!("  GET    /v1/_routes               - List routes");
+        println!("  GET    /metrics                  - Prometheus metrics");
+        println!();
+        println!(
+            "Inspect live traffic with: ipckit listen --attach {}",
+            socket_path
+        );
+        println!("Watch it live with: ipckit top --socket {}", socket_path);
     }
 
+    // Advertise the socket for `ipckit ls`; serving works without it
+    let _registration = Discovery::new()
+        .register(ChannelEntry::new(&socket_path, "socket").purpose("ipckit API server"))
+        .ok();
+
     println!("Press Ctrl+C to stop...");
 
-    // Simple handler that responds to messages
-    server.run(FnHandler::new(|_conn: &mut Connection, msg: Message| {
-        // Get the request content
-        let request = if let Some(binary) = msg.as_binary() {
-            String::from_utf8_lossy(&binary).to_string()
-        } else if let Some(text) = msg.as_text() {
-            text.to_string()
-        } else {
-            serde_json::to_string(&msg.payload).unwrap_or_default()
-        };
-
-        // Simple routing
-        let response = if request.contains("GET /v1/health") {
-            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"status\":\"ok\"}"
-        } else if request.contains("GET /v1/tasks") {
-            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n[]"
-        } else {
-            "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\n\r\n{\"error\":\"Not Found\"}"
-        };
-
-        Ok(Some(Message::binary(response.as_bytes().to_vec())))
-    }))?;
+    server.run()?;
+
+    Ok(())
+}
+
+fn serve_manifest(
+    path: &Path,
+    socket: Option<String>,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut manifest = ServiceManifest::load(path)?;
+
+    // --socket overrides the manifest's sockets, keeping all namespaces mounted
+    if let Some(socket) = socket {
+        manifest.sockets = vec![ipckit::SocketSpec {
+            path: socket,
+            namespaces: Vec::new(),
+        }];
+    }
+
+    print_info(&format!(
+        "Starting service '{}' from {:?}",
+        manifest.name, path
+    ));
+
+    if verbose {
+        let routes = manifest.routes.iter().map(|r| ("", r)).chain(
+            manifest
+                .namespaces
+                .iter()
+                .flat_map(|ns| ns.routes.iter().map(move |r| (ns.prefix.as_str(), r))),
+        );
+        println!("Routes:");
+        for (prefix, route) in routes {
+            let kind = match &route.target {
+                RouteTarget::Static { .. } => "static".to_string(),
+                RouteTarget::Dir { dir } => format!("dir {:?}", dir),
+                RouteTarget::Proxy { socket, .. } => format!("proxy -> {}", socket),
+            };
+            println!("  {:<6} {}{}  ({})", route.method, prefix, route.path, kind);
+        }
+        for task in &manifest.tasks {
+            match task.interval_secs {
+                Some(secs) => println!("Task '{}' every {}s", task.name, secs),
+                None => println!("Task '{}' at startup", task.name),
+            }
+        }
+        for webhook in &manifest.webhooks {
+            println!(
+                "Webhook {}{} <- {:?}",
+                webhook.socket, webhook.path, webhook.events
+            );
+        }
+    }
+
+    let service = manifest.start(TaskManagerConfig::default())?;
+    let discovery = Discovery::new();
+    let mut registrations = Vec::new();
+    for socket in &service.manifest().sockets {
+        print_success(&format!("API server listening on {}", socket.path));
+        let entry = ChannelEntry::new(&socket.path, "socket").purpose(&service.manifest().name);
+        registrations.extend(discovery.register(entry).ok());
+    }
+
+    println!("Press Ctrl+C to stop...");
+    service.join()?;
 
     Ok(())
 }
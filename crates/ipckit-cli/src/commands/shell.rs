@@ -0,0 +1,530 @@
+//! Shell command implementation
+//!
+//! An interactive prompt for debugging a running daemon from one terminal:
+//! raw message send/recv, JSON-RPC calls and HTTP-style API requests, with
+//! `{{var}}` templating, persistent history and tab completion of the
+//! server's routes.
+
+use super::{api_client, channel_type_name, print_error, print_info, print_success};
+use crate::ChannelType;
+use console::{style, Key, Term};
+use ipckit::api_server::ROUTES_PATH;
+use ipckit::socket_server::{Message, SocketClient};
+use ipckit::{Method, NamedPipe};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Number of history entries kept across sessions
+const HISTORY_LIMIT: usize = 1000;
+
+/// Default wait for `recv` without an explicit timeout
+const DEFAULT_RECV_TIMEOUT_MS: u64 = 5000;
+
+const COMMANDS: &[&str] = &[
+    "help", "send", "recv", "call", "routes", "let", "vars", "history", "exit", "quit", "GET",
+    "POST", "PUT", "DELETE", "PATCH",
+];
+
+const HELP: &str = "\
+Commands:
+  send <json|text>          Send a message
+  recv [timeout_ms]         Wait for a message (default 5000 ms)
+  call <method> [json]      Send a request and wait for the response (socket)
+  GET|POST|PUT|DELETE|PATCH <path> [json]
+                            Make an API request (socket)
+  routes                    List the server's routes
+  let <name> = <json|text>  Set a template variable
+  vars                      List template variables
+  history                   Show command history
+  help                      Show this help
+  exit, quit                Leave the shell
+
+Templates: {{name}} expands a variable, {{last}} the last result and
+{{last.items.0.id}} a field inside it.";
+
+pub fn shell(
+    channel_type: ChannelType,
+    name: &str,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let target = match channel_type {
+        ChannelType::Socket => Target::Socket(None),
+        ChannelType::Pipe => Target::Pipe(NamedPipe::connect(name)?),
+        other => {
+            return Err(format!(
+                "The shell supports sockets and pipes, not {}",
+                channel_type_name(other)
+            )
+            .into())
+        }
+    };
+
+    let mut session = Session {
+        target,
+        name: name.to_string(),
+        vars: BTreeMap::new(),
+        last: JsonValue::Null,
+        routes: Vec::new(),
+        verbose,
+    };
+    session.fetch_routes();
+
+    print_info(&format!(
+        "Connected to {} '{}'. Type 'help' for commands.",
+        channel_type_name(channel_type),
+        name
+    ));
+
+    let term = Term::stdout();
+    let interactive = term.is_term() && io::stdin().is_terminal();
+    let history_path = dirs::home_dir().map(|home| home.join(".ipckit_history"));
+    let mut history = history_path
+        .as_deref()
+        .map(load_history)
+        .unwrap_or_default();
+    let mut stdin = io::stdin().lock();
+
+    loop {
+        let line = if interactive {
+            let prompt = format!("{} ", style("ipckit>").cyan().bold());
+            match read_line(&term, &prompt, &history, |line| session.complete(line))? {
+                Some(line) => line,
+                None => break,
+            }
+        } else {
+            let mut line = String::new();
+            if stdin.read_line(&mut line)? == 0 {
+                break;
+            }
+            line
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if history.last().map(String::as_str) != Some(line) {
+            history.push(line.to_string());
+        }
+
+        match session.execute(line, &history) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => print_error(&e.to_string()),
+        }
+    }
+
+    if let Some(path) = history_path {
+        save_history(&path, &history);
+    }
+    Ok(())
+}
+
+enum Target {
+    /// Connected lazily so API-only sessions don't hold a connection open
+    Socket(Option<Box<SocketClient>>),
+    Pipe(NamedPipe),
+}
+
+struct Session {
+    target: Target,
+    name: String,
+    vars: BTreeMap<String, JsonValue>,
+    last: JsonValue,
+    /// `(method, path)` pairs from the server's route index
+    routes: Vec<(String, String)>,
+    verbose: bool,
+}
+
+impl Session {
+    /// Run one command line, returning `false` to leave the shell.
+    fn execute(
+        &mut self,
+        line: &str,
+        history: &[String],
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let line = self.expand(line)?;
+        let (command, rest) = split_word(&line);
+
+        match command {
+            "exit" | "quit" => return Ok(false),
+            "help" => println!("{}", HELP),
+            "history" => {
+                for (i, entry) in history.iter().enumerate() {
+                    println!("{:>5}  {}", i + 1, entry);
+                }
+            }
+            "vars" => {
+                for (name, value) in &self.vars {
+                    println!("{} = {}", style(name).bold(), value);
+                }
+            }
+            "let" => {
+                let (name, value) = rest.split_once('=').ok_or("Usage: let <name> = <value>")?;
+                let name = name.trim();
+                if name.is_empty() || name == "last" {
+                    return Err(format!("Invalid variable name '{}'", name).into());
+                }
+                self.vars
+                    .insert(name.to_string(), parse_value(value.trim()));
+            }
+            "routes" => {
+                self.fetch_routes();
+                if self.routes.is_empty() {
+                    print_info("The server does not publish a route index");
+                }
+                for (method, path) in &self.routes {
+                    println!("  {:<6} {}", method, path);
+                }
+            }
+            "send" => self.send(rest)?,
+            "recv" => {
+                let timeout = match rest {
+                    "" => DEFAULT_RECV_TIMEOUT_MS,
+                    ms => ms.parse().map_err(|_| "Usage: recv [timeout_ms]")?,
+                };
+                self.recv(Duration::from_millis(timeout))?;
+            }
+            "call" => {
+                let (method, params) = split_word(rest);
+                if method.is_empty() {
+                    return Err("Usage: call <method> [json]".into());
+                }
+                let params = if params.is_empty() {
+                    JsonValue::Null
+                } else {
+                    serde_json::from_str(params)?
+                };
+                let result = self.client()?.request(method, params)?;
+                self.show(result);
+            }
+            _ => match Method::parse(&command.to_uppercase()) {
+                Some(method) => self.api_request(method, rest)?,
+                None => {
+                    return Err(format!("Unknown command '{}', try 'help'", command).into());
+                }
+            },
+        }
+
+        Ok(true)
+    }
+
+    fn send(&mut self, payload: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if payload.is_empty() {
+            return Err("Usage: send <json|text>".into());
+        }
+        match &mut self.target {
+            Target::Pipe(pipe) => {
+                pipe.write_all(payload.as_bytes())?;
+                pipe.flush()?;
+            }
+            Target::Socket(_) => {
+                let msg = match serde_json::from_str(payload) {
+                    Ok(value) => Message::json(value),
+                    Err(_) => Message::text(payload),
+                };
+                self.client()?.send(&msg)?;
+            }
+        }
+        print_success(&format!("Sent {} bytes", payload.len()));
+        Ok(())
+    }
+
+    fn recv(&mut self, timeout: Duration) -> Result<(), Box<dyn std::error::Error>> {
+        if let Target::Pipe(pipe) = &mut self.target {
+            let mut buf = vec![0u8; 64 * 1024];
+            let n = pipe.read(&mut buf)?;
+            let value = serde_json::from_slice(&buf[..n])
+                .unwrap_or_else(|_| JsonValue::String(String::from_utf8_lossy(&buf[..n]).into()));
+            self.show(value);
+            return Ok(());
+        }
+
+        let client = self.client()?;
+        let deadline = Instant::now() + timeout;
+        let msg = loop {
+            if let Some(msg) = client.try_recv()? {
+                break msg;
+            }
+            if Instant::now() >= deadline {
+                return Err("No message received before the timeout".into());
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        self.show(serde_json::to_value(&msg)?);
+        Ok(())
+    }
+
+    fn api_request(
+        &mut self,
+        method: Method,
+        rest: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.target, Target::Socket(_)) {
+            return Err("API requests need a socket".into());
+        }
+        let (path, body) = split_word(rest);
+        if !path.starts_with('/') {
+            return Err(format!("Usage: {} <path> [json]", method.as_str()).into());
+        }
+        let body = if body.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(body)?)
+        };
+
+        let started = Instant::now();
+        let result = api_client(&self.name, None).request(method, path, body)?;
+        if self.verbose {
+            print_info(&format!(
+                "{} {} took {:?}",
+                method.as_str(),
+                path,
+                started.elapsed()
+            ));
+        }
+        self.show(result);
+        Ok(())
+    }
+
+    /// Get the socket connection, connecting on first use.
+    fn client(&mut self) -> Result<&mut SocketClient, Box<dyn std::error::Error>> {
+        match &mut self.target {
+            Target::Socket(client) => {
+                if client.is_none() {
+                    *client = Some(Box::new(SocketClient::connect(&self.name)?));
+                }
+                Ok(client.as_mut().expect("just connected"))
+            }
+            Target::Pipe(_) => Err("This command needs a socket".into()),
+        }
+    }
+
+    /// Pretty-print a result and remember it as `{{last}}`.
+    fn show(&mut self, value: JsonValue) {
+        match &value {
+            JsonValue::String(s) => println!("{}", s),
+            other => println!(
+                "{}",
+                serde_json::to_string_pretty(other).unwrap_or_else(|_| other.to_string())
+            ),
+        }
+        self.last = value;
+    }
+
+    fn fetch_routes(&mut self) {
+        if !matches!(self.target, Target::Socket(_)) {
+            return;
+        }
+        let client = api_client(&self.name, Some(Duration::from_secs(2)));
+        let routes = match client.get(ROUTES_PATH) {
+            Ok(JsonValue::Array(routes)) => routes,
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                if self.verbose {
+                    print_info(&format!("Route index unavailable: {}", e));
+                }
+                Vec::new()
+            }
+        };
+        self.routes = routes
+            .iter()
+            .filter_map(|r| Some((r["method"].as_str()?.into(), r["path"].as_str()?.into())))
+            .collect();
+    }
+
+    /// Expand `{{name}}` and `{{last.path}}` placeholders.
+    fn expand(&self, line: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let mut out = String::new();
+        let mut rest = line;
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..]
+                .find("}}")
+                .ok_or("Unterminated '{{' in template")?;
+            out.push_str(&rest[..start]);
+
+            let expr = rest[start + 2..start + end].trim();
+            let mut parts = expr.split('.');
+            let root = parts.next().unwrap_or_default();
+            let mut value = match root {
+                "last" => &self.last,
+                name => self
+                    .vars
+                    .get(name)
+                    .ok_or_else(|| format!("Unknown variable '{}'", name))?,
+            };
+            for part in parts {
+                value = match value {
+                    JsonValue::Array(items) => {
+                        part.parse::<usize>().ok().and_then(|i| items.get(i))
+                    }
+                    JsonValue::Object(map) => map.get(part),
+                    _ => None,
+                }
+                .ok_or_else(|| format!("'{}' has no field '{}'", expr, part))?;
+            }
+            match value {
+                JsonValue::String(s) => out.push_str(s),
+                other => out.push_str(&other.to_string()),
+            }
+
+            rest = &rest[start + end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Get the full-line completions for `line`.
+    fn complete(&self, line: &str) -> Vec<String> {
+        let (command, rest) = split_word(line);
+        if !line.contains(' ') {
+            return COMMANDS
+                .iter()
+                .filter(|c| c.starts_with(command))
+                .map(|c| format!("{} ", c))
+                .collect();
+        }
+
+        let method = command.to_uppercase();
+        if Method::parse(&method).is_none() || rest.contains(' ') {
+            return Vec::new();
+        }
+        self.routes
+            .iter()
+            .filter(|(m, path)| *m == method && path.starts_with(rest))
+            .map(|(_, path)| format!("{} {}", command, path))
+            .collect()
+    }
+}
+
+/// Read one line with history and completion, or `None` at end of input.
+fn read_line(
+    term: &Term,
+    prompt: &str,
+    history: &[String],
+    complete: impl Fn(&str) -> Vec<String>,
+) -> io::Result<Option<String>> {
+    let mut buf: Vec<char> = Vec::new();
+    let mut cursor = 0usize;
+    let mut recall = history.len();
+
+    let redraw = |buf: &[char], cursor: usize| -> io::Result<()> {
+        term.clear_line()?;
+        term.write_str(prompt)?;
+        term.write_str(&buf.iter().collect::<String>())?;
+        term.move_cursor_left(buf.len() - cursor)?;
+        term.flush()
+    };
+    redraw(&buf, cursor)?;
+
+    loop {
+        match term.read_key()? {
+            Key::Enter => {
+                term.write_line("")?;
+                return Ok(Some(buf.into_iter().collect()));
+            }
+            Key::CtrlC => {
+                term.write_line("^C")?;
+                buf.clear();
+                cursor = 0;
+            }
+            // Ctrl-D on an empty line ends the session
+            Key::Char('\u{4}') if buf.is_empty() => {
+                term.write_line("")?;
+                return Ok(None);
+            }
+            Key::Char(c) if !c.is_control() => {
+                buf.insert(cursor, c);
+                cursor += 1;
+            }
+            Key::Backspace if cursor > 0 => {
+                cursor -= 1;
+                buf.remove(cursor);
+            }
+            Key::Del if cursor < buf.len() => {
+                buf.remove(cursor);
+            }
+            Key::ArrowLeft => cursor = cursor.saturating_sub(1),
+            Key::ArrowRight => cursor = (cursor + 1).min(buf.len()),
+            Key::Home => cursor = 0,
+            Key::End => cursor = buf.len(),
+            Key::ArrowUp if recall > 0 => {
+                recall -= 1;
+                buf = history[recall].chars().collect();
+                cursor = buf.len();
+            }
+            Key::ArrowDown if recall < history.len() => {
+                recall += 1;
+                buf = history
+                    .get(recall)
+                    .map(|h| h.chars().collect())
+                    .unwrap_or_default();
+                cursor = buf.len();
+            }
+            Key::Tab if cursor == buf.len() => {
+                let line: String = buf.iter().collect();
+                let candidates = complete(&line);
+                let prefix = common_prefix(&candidates);
+                if prefix.len() > line.len() {
+                    buf = prefix.chars().collect();
+                    cursor = buf.len();
+                } else if candidates.len() > 1 {
+                    term.write_line("")?;
+                    for candidate in &candidates {
+                        term.write_line(&format!("  {}", candidate.trim_end()))?;
+                    }
+                }
+            }
+            _ => continue,
+        }
+        redraw(&buf, cursor)?;
+    }
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+/// Split off the first whitespace-separated word.
+fn split_word(line: &str) -> (&str, &str) {
+    let line = line.trim_start();
+    match line.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (line, ""),
+    }
+}
+
+/// Parse a variable value as JSON, falling back to a plain string.
+fn parse_value(value: &str) -> JsonValue {
+    serde_json::from_str(value).unwrap_or_else(|_| JsonValue::String(value.to_string()))
+}
+
+fn load_history(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .map(|s| s.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn save_history(path: &Path, history: &[String]) {
+    let start = history.len().saturating_sub(HISTORY_LIMIT);
+    let mut contents = history[start..].join("\n");
+    contents.push('\n');
+    let _ = std::fs::write(path, contents);
+}
@@ -0,0 +1,167 @@
+//! Task commands: talk to a daemon's `/v1/tasks` routes
+
+use crate::OutputFormat;
+use console::style;
+use ipckit::TaskInfo;
+use std::time::{Duration, SystemTime};
+
+use super::{api_client, print_success};
+
+/// How long each request to the daemon may take, not counting long-polls.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long one `logs -f` poll waits on the server for new lines.
+const FOLLOW_POLL_MS: u64 = 10_000;
+
+/// List tasks, most recent first.
+pub fn task_ls(
+    socket: &str,
+    all: bool,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client(socket, Some(REQUEST_TIMEOUT));
+    let mut tasks: Vec<TaskInfo> = serde_json::from_value(client.get("/v1/tasks")?)?;
+    tasks.retain(|t| all || !t.status.is_terminal());
+    tasks.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+
+    if let OutputFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(&tasks)?);
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        style(format!(
+            "{:<20} {:<24} {:<12} {:<10} {:>8}  {}",
+            "ID", "NAME", "TYPE", "STATUS", "PROGRESS", "CREATED"
+        ))
+        .bold()
+    );
+    for task in &tasks {
+        let status = format!("{:?}", task.status).to_lowercase();
+        let status = match task.status {
+            ipckit::TaskStatus::Failed => style(format!("{:<10}", status)).red(),
+            ipckit::TaskStatus::Completed => style(format!("{:<10}", status)).green(),
+            ipckit::TaskStatus::Running => style(format!("{:<10}", status)).cyan(),
+            _ => style(format!("{:<10}", status)).dim(),
+        };
+        println!(
+            "{:<20} {:<24} {:<12} {} {:>7}%  {}",
+            truncate(&task.id, 20),
+            truncate(&task.name, 24),
+            truncate(&task.task_type, 12),
+            status,
+            task.progress,
+            format_age(task.created_at)
+        );
+    }
+    if tasks.is_empty() {
+        println!(
+            "{}",
+            style(if all {
+                "(no tasks)"
+            } else {
+                "(no active tasks, use --all to include finished ones)"
+            })
+            .dim()
+        );
+    }
+    Ok(())
+}
+
+/// Print everything the daemon knows about a task.
+pub fn task_inspect(socket: &str, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client(socket, Some(REQUEST_TIMEOUT));
+    let info = client.get(&format!("/v1/tasks/{}", id))?;
+    check_found(&info, id)?;
+    println!("{}", serde_json::to_string_pretty(&info)?);
+    Ok(())
+}
+
+/// Cancel a task.
+pub fn task_cancel(socket: &str, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client(socket, Some(REQUEST_TIMEOUT));
+    let info = client.delete(&format!("/v1/tasks/{}", id))?;
+    check_found(&info, id)?;
+    print_success(&format!("Cancelled task '{}'", id));
+    Ok(())
+}
+
+/// Print a task's log lines, optionally following until it finishes.
+pub fn task_logs(socket: &str, id: &str, follow: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let poll = Duration::from_millis(FOLLOW_POLL_MS);
+    let client = api_client(socket, Some(REQUEST_TIMEOUT + poll));
+    let mut after: Option<u64> = None;
+
+    loop {
+        let mut path = format!("/v1/tasks/{}/logs", id);
+        let mut query = Vec::new();
+        if let Some(after) = after {
+            query.push(format!("after={}", after));
+        }
+        if follow {
+            query.push(format!("timeout_ms={}", FOLLOW_POLL_MS));
+        }
+        if !query.is_empty() {
+            path = format!("{}?{}", path, query.join("&"));
+        }
+
+        let body = client.get(&path)?;
+        check_found(&body, id)?;
+        let logs = body["logs"].as_array().cloned().unwrap_or_default();
+        for line in &logs {
+            print_line(line);
+            after = line["id"].as_u64().or(after);
+        }
+
+        let finished = ["completed", "failed", "cancelled"]
+            .contains(&body["status"].as_str().unwrap_or_default());
+        if !follow || (finished && logs.is_empty()) {
+            return Ok(());
+        }
+    }
+}
+
+fn print_line(line: &serde_json::Value) {
+    let message = line["message"].as_str().unwrap_or_default();
+    match line["level"].as_str().unwrap_or("info") {
+        "stdout" => println!("{}", message),
+        "stderr" => eprintln!("{}", message),
+        "error" => println!("{} {}", style("ERROR").red().bold(), message),
+        "warn" | "warning" => println!("{} {}", style("WARN").yellow().bold(), message),
+        level => println!("{} {}", style(level.to_uppercase()).dim(), message),
+    }
+}
+
+/// Turn the API's `{"error": "Not Found"}` reply into an error.
+fn check_found(body: &serde_json::Value, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match body["error"].as_str() {
+        Some(error) => Err(format!("task '{}': {}", id, error).into()),
+        None => Ok(()),
+    }
+}
+
+fn format_age(created: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(created)
+        .unwrap_or_default()
+        .as_secs();
+    if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h ago", secs / 3600)
+    } else {
+        format!("{}d ago", secs / 86400)
+    }
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    if s.chars().count() <= max_len {
+        s.to_string()
+    } else {
+        let head: String = s.chars().take(max_len - 1).collect();
+        format!("{}…", head)
+    }
+}
@@ -0,0 +1,256 @@
+//! Live dashboard for a running daemon
+
+use console::{style, Term};
+use ipckit::metrics::MetricsSnapshot;
+use ipckit::socket_server::CONNECTIONS_GAUGE;
+use ipckit::{ApiClient, TaskInfo};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use super::{api_client, print_info};
+
+/// How long each request to the daemon may take.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Metrics as served by `Router::metrics_route` with `?format=json`.
+#[derive(Default, serde::Deserialize)]
+struct Metrics {
+    #[serde(default)]
+    channels: BTreeMap<String, MetricsSnapshot>,
+    #[serde(default)]
+    gauges: BTreeMap<String, i64>,
+}
+
+/// One refresh worth of data.
+struct Sample {
+    taken: Instant,
+    metrics: Metrics,
+    tasks: Option<Vec<TaskInfo>>,
+}
+
+/// Show a live view of a daemon's channels, connections and tasks.
+pub fn top(
+    socket: &str,
+    metrics_path: &str,
+    interval_ms: u64,
+    iterations: Option<u64>,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if verbose {
+        print_info(&format!("Watching {} ({})", socket, metrics_path));
+    }
+
+    let client = api_client(socket, Some(REQUEST_TIMEOUT));
+    let term = Term::stdout();
+    let interval = Duration::from_millis(interval_ms);
+    let start = Instant::now();
+    let mut previous: Option<Sample> = None;
+    let mut refreshes = 0;
+
+    loop {
+        let sample = fetch(&client, metrics_path)?;
+        let _ = term.clear_screen();
+        render(&term, socket, start.elapsed(), &sample, previous.as_ref());
+        previous = Some(sample);
+
+        refreshes += 1;
+        if iterations.is_some_and(|n| refreshes >= n) {
+            return Ok(());
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+fn fetch(client: &ApiClient, metrics_path: &str) -> Result<Sample, Box<dyn std::error::Error>> {
+    let separator = if metrics_path.contains('?') { '&' } else { '?' };
+    let metrics = client.get(&format!("{metrics_path}{separator}format=json"))?;
+    let metrics: Metrics = serde_json::from_value(metrics)
+        .map_err(|e| format!("{} did not return JSON metrics: {}", metrics_path, e))?;
+
+    // Daemons without task routes still get a channel view
+    let tasks = client
+        .get("/v1/tasks")
+        .ok()
+        .and_then(|tasks| serde_json::from_value(tasks).ok());
+
+    Ok(Sample {
+        taken: Instant::now(),
+        metrics,
+        tasks,
+    })
+}
+
+fn render(
+    mut term: &Term,
+    socket: &str,
+    uptime: Duration,
+    sample: &Sample,
+    previous: Option<&Sample>,
+) {
+    let connections = sample
+        .metrics
+        .gauges
+        .get(CONNECTIONS_GAUGE)
+        .map_or_else(|| "-".to_string(), |n| n.to_string());
+    let active: Vec<&TaskInfo> = sample
+        .tasks
+        .iter()
+        .flatten()
+        .filter(|t| !t.status.is_terminal())
+        .collect();
+
+    let _ = writeln!(
+        term,
+        "{} {}  {} {}  {} {}  {} {}",
+        style("ipckit top").cyan().bold(),
+        style(socket).dim(),
+        style("up").dim(),
+        style(format_duration(uptime)).green(),
+        style("connections").dim(),
+        style(connections).yellow(),
+        style("tasks").dim(),
+        style(active.len()).yellow()
+    );
+    let _ = writeln!(term);
+
+    let _ = writeln!(
+        term,
+        "  {}",
+        style(format!(
+            "{:<20} {:>9} {:>9} {:>10} {:>8} {:>8} {:>8} {:>7}",
+            "CHANNEL", "OUT/s", "IN/s", "BYTES/s", "P50", "P95", "P99", "ERRORS"
+        ))
+        .bold()
+        .reverse()
+    );
+    if sample.metrics.channels.is_empty() {
+        let _ = writeln!(term, "  {}", style("(no channels registered)").dim());
+    }
+    for (name, snapshot) in &sample.metrics.channels {
+        let before = previous.and_then(|p| p.metrics.channels.get(name).map(|s| (p.taken, s)));
+        let (out_rate, in_rate, byte_rate) = match before {
+            Some((taken, before)) => {
+                let secs = sample.taken.duration_since(taken).as_secs_f64().max(1e-3);
+                let rate = |now: u64, then: u64| now.saturating_sub(then) as f64 / secs;
+                (
+                    rate(snapshot.messages_sent, before.messages_sent),
+                    rate(snapshot.messages_received, before.messages_received),
+                    rate(snapshot.bytes_sent, before.bytes_sent)
+                        + rate(snapshot.bytes_received, before.bytes_received),
+                )
+            }
+            // Until there is a previous sample, show the lifetime averages
+            None => (
+                snapshot.send_throughput,
+                snapshot.recv_throughput,
+                snapshot.send_bandwidth + snapshot.recv_bandwidth,
+            ),
+        };
+        let errors = snapshot.send_errors + snapshot.receive_errors;
+        let errors = if errors > 0 {
+            style(format!("{:>7}", errors)).red().to_string()
+        } else {
+            style(format!("{:>7}", errors)).dim().to_string()
+        };
+
+        let _ = writeln!(
+            term,
+            "  {:<20} {:>9} {:>9} {:>10} {:>8} {:>8} {:>8} {}",
+            truncate(name, 20),
+            format_rate(out_rate),
+            format_rate(in_rate),
+            format_bytes(byte_rate),
+            format_latency(snapshot.p50_latency_us),
+            format_latency(snapshot.p95_latency_us),
+            format_latency(snapshot.p99_latency_us),
+            errors
+        );
+    }
+
+    let _ = writeln!(term);
+    let _ = writeln!(
+        term,
+        "  {}",
+        style(format!(
+            "{:<20} {:<24} {:<10} {:>8}  {}",
+            "TASK", "NAME", "STATUS", "PROGRESS", "MESSAGE"
+        ))
+        .bold()
+        .reverse()
+    );
+    match sample.tasks {
+        None => {
+            let _ = writeln!(term, "  {}", style("(task routes not available)").dim());
+        }
+        Some(_) if active.is_empty() => {
+            let _ = writeln!(term, "  {}", style("(no active tasks)").dim());
+        }
+        Some(_) => {
+            for task in active {
+                let _ = writeln!(
+                    term,
+                    "  {:<20} {:<24} {:<10} {:>7}%  {}",
+                    truncate(&task.id, 20),
+                    truncate(&task.name, 24),
+                    format!("{:?}", task.status).to_lowercase(),
+                    task.progress,
+                    style(task.progress_message.as_deref().unwrap_or("")).dim()
+                );
+            }
+        }
+    }
+
+    let _ = writeln!(term);
+    let _ = writeln!(term, "  {}", style("Press Ctrl+C to exit").dim());
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+fn format_rate(per_sec: f64) -> String {
+    if per_sec < 1000.0 {
+        format!("{:.1}", per_sec)
+    } else if per_sec < 1_000_000.0 {
+        format!("{:.1}K", per_sec / 1000.0)
+    } else {
+        format!("{:.1}M", per_sec / 1_000_000.0)
+    }
+}
+
+fn format_bytes(per_sec: f64) -> String {
+    if per_sec < 1024.0 {
+        format!("{:.0}B", per_sec)
+    } else if per_sec < 1024.0 * 1024.0 {
+        format!("{:.1}KiB", per_sec / 1024.0)
+    } else {
+        format!("{:.1}MiB", per_sec / (1024.0 * 1024.0))
+    }
+}
+
+fn format_latency(us: u64) -> String {
+    if us < 1000 {
+        format!("{}µs", us)
+    } else if us < 1_000_000 {
+        format!("{:.1}ms", us as f64 / 1000.0)
+    } else {
+        format!("{:.1}s", us as f64 / 1_000_000.0)
+    }
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    if s.chars().count() <= max_len {
+        s.to_string()
+    } else {
+        let head: String = s.chars().take(max_len - 1).collect();
+        format!("{}…", head)
+    }
+}
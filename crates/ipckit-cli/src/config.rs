@@ -0,0 +1,118 @@
+//! Config file handling
+//!
+//! Loads `ipckit.toml` (or the file given with `--config`) and resolves the
+//! channel, socket and client settings commands fall back to when they are
+//! not given on the command line.
+
+use crate::commands::{default_socket, ClientOptions};
+use crate::ChannelType;
+use clap::ValueEnum;
+use ipckit::config_file::CONFIG_FILE_NAME;
+use ipckit::{EndpointConfig, IpckitConfig};
+use std::path::{Path, PathBuf};
+
+/// Loaded configuration plus the endpoint selected with `--endpoint`.
+pub struct Context {
+    pub config: IpckitConfig,
+    endpoint: Option<String>,
+}
+
+impl Context {
+    /// Load `path`, or the first config file found in the working directory
+    /// or the user config directory, and apply `IPCKIT_*` overrides.
+    pub fn load(
+        path: Option<&Path>,
+        endpoint: Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => default_locations().into_iter().find(|p| p.is_file()),
+        };
+        let mut config = match path {
+            Some(path) => IpckitConfig::load(&path)
+                .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?,
+            None => IpckitConfig::default(),
+        };
+        config.apply_env()?;
+
+        let context = Self { config, endpoint };
+        // Report an unknown --endpoint up front
+        context.endpoint()?;
+        Ok(context)
+    }
+
+    /// The endpoint named with `--endpoint`, or the default endpoint.
+    pub fn endpoint(&self) -> ipckit::Result<Option<&EndpointConfig>> {
+        self.config.endpoint(self.endpoint.as_deref())
+    }
+
+    /// Fill in a missing channel type or name from the selected endpoint.
+    pub fn channel(
+        &self,
+        channel_type: Option<ChannelType>,
+        name: Option<String>,
+    ) -> Result<(ChannelType, String), Box<dyn std::error::Error>> {
+        if let (Some(channel_type), Some(name)) = (channel_type, &name) {
+            return Ok((channel_type, name.clone()));
+        }
+        let endpoint = self.endpoint()?.ok_or(
+            "--type and --name are required unless --endpoint is given or a default endpoint is configured",
+        )?;
+        let channel_type = match channel_type {
+            Some(channel_type) => channel_type,
+            None => ChannelType::from_str(&endpoint.kind, true)?,
+        };
+        Ok((channel_type, name.unwrap_or_else(|| endpoint.name.clone())))
+    }
+
+    /// Socket of the daemon to talk to: `socket`, else the selected socket
+    /// endpoint, else the configured server socket.
+    pub fn socket(&self, socket: Option<String>) -> ipckit::Result<String> {
+        if let Some(socket) = socket {
+            return Ok(socket);
+        }
+        if let Some(endpoint) = self.endpoint()?.filter(|e| e.kind == "socket") {
+            return Ok(endpoint.name.clone());
+        }
+        Ok(self.server_socket())
+    }
+
+    /// Socket `serve` listens on when `--socket` is not given.
+    fn server_socket(&self) -> String {
+        self.config
+            .server
+            .socket
+            .clone()
+            .unwrap_or_else(default_socket)
+    }
+
+    /// Token and codec for API requests to `socket`.
+    ///
+    /// The token of a socket endpoint with that path is used, or the server
+    /// token when `socket` is the configured server socket.
+    pub fn client_options(&self, socket: &str) -> ipckit::Result<ClientOptions> {
+        let is_socket = |e: &&EndpointConfig| e.kind == "socket" && e.name == socket;
+        let endpoint = self
+            .endpoint()?
+            .filter(is_socket)
+            .or_else(|| self.config.endpoints.values().find(is_socket));
+        let token = endpoint.and_then(|e| e.token.clone()).or_else(|| {
+            (self.server_socket() == socket)
+                .then(|| self.config.server.auth_token.clone())
+                .flatten()
+        });
+        Ok(ClientOptions {
+            token,
+            format: self.config.codec(endpoint)?,
+        })
+    }
+}
+
+/// Config files looked up when `--config` is not given, in order.
+fn default_locations() -> Vec<PathBuf> {
+    let mut locations = vec![PathBuf::from(CONFIG_FILE_NAME)];
+    if let Some(dir) = dirs::config_dir() {
+        locations.push(dir.join("ipckit").join("config.toml"));
+    }
+    locations
+}
@@ -11,20 +11,71 @@
 //! # Listen for messages
 //! ipckit listen --type pipe --name my_pipe
 //!
+//! # Inspect live traffic of a running `ipckit serve`
+//! ipckit listen --attach /tmp/ipckit.sock
+//!
 //! # Send a message
 //! ipckit send --type pipe --name my_pipe "Hello, World!"
 //!
 //! # Benchmark
 //! ipckit bench --type pipe --iterations 1000
+//! ipckit bench --type pipe --profile
+//!
+//! # Cross-process round trips, sweeping message sizes, as CSV
+//! ipckit bench --type socket --role server &
+//! ipckit bench --type socket --role client --sizes 64,1024,65536 --format csv
 //!
 //! # Generate code
 //! ipckit generate client --type pipe --name my_pipe
+//! ipckit generate typescript --socket /tmp/ipckit.sock --output client.ts
+//!
+//! # List the channels running processes have advertised
+//! ipckit ls
 //!
 //! # Monitor channels
 //! ipckit monitor
+//!
+//! # Live dashboard of a running daemon
+//! ipckit top --socket /tmp/ipckit.sock
+//!
+//! # Manage a daemon's tasks
+//! ipckit task ls --all
+//! ipckit task logs -f build-1
+//!
+//! # Record a session and replay it later
+//! ipckit record --type socket --name my_socket --out session.jsonl
+//! ipckit replay session.jsonl
+//!
+//! # Explore a running daemon interactively
+//! ipckit shell --type socket --name /tmp/ipckit.sock
+//!
+//! # Use an endpoint defined in ipckit.toml instead of --type/--name
+//! ipckit --endpoint daemon shell
+//! ```
+//!
+//! ## Configuration
+//!
+//! `--config` (or `IPCKIT_CONFIG`) names a TOML, YAML or JSON file; without
+//! it `./ipckit.toml` and then `<config dir>/ipckit/config.toml` are used if
+//! present. The file defines named endpoints, the default endpoint and codec,
+//! auth tokens and `serve` settings:
+//!
+//! ```toml
+//! [defaults]
+//! endpoint = "daemon"
+//!
+//! [endpoints.daemon]
+//! type = "socket"
+//! name = "/tmp/ipckit.sock"
+//! token = "s3cret"
+//!
+//! [server]
+//! socket = "/tmp/ipckit.sock"
+//! auth_token = "s3cret"
 //! ```
 
 mod commands;
+mod config;
 
 use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
@@ -37,9 +88,13 @@ use std::path::PathBuf;
 #[command(propagate_version = true)]
 struct Cli {
     /// Configuration file path
-    #[arg(short, long, env = "IPCKIT_CONFIG")]
+    #[arg(short, long, env = "IPCKIT_CONFIG", global = true)]
     config: Option<PathBuf>,
 
+    /// Configured endpoint to use when --type/--name are not given
+    #[arg(short, long, global = true)]
+    endpoint: Option<String>,
+
     /// Verbose output
     #[arg(short, long, default_value = "false")]
     verbose: bool,
@@ -52,13 +107,13 @@ struct Cli {
 enum Commands {
     /// Create a new IPC channel
     Create {
-        /// Channel type
+        /// Channel type (defaults to the endpoint's)
         #[arg(short = 't', long, value_enum)]
-        channel_type: ChannelType,
+        channel_type: Option<ChannelType>,
 
-        /// Channel name
+        /// Channel name (defaults to the endpoint's)
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
 
         /// Size (for shared memory)
         #[arg(short, long, default_value = "4096")]
@@ -67,13 +122,17 @@ enum Commands {
 
     /// Listen on a channel and print messages
     Listen {
-        /// Channel type
+        /// Channel type (defaults to the endpoint's)
         #[arg(short = 't', long, value_enum)]
-        channel_type: ChannelType,
+        channel_type: Option<ChannelType>,
 
-        /// Channel name
+        /// Channel name (defaults to the endpoint's)
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
+
+        /// Attach to a running socket server and print its live traffic
+        #[arg(long, value_name = "SOCKET", conflicts_with_all = ["channel_type", "name"])]
+        attach: Option<String>,
 
         /// Output format
         #[arg(long, value_enum, default_value = "text")]
@@ -86,13 +145,13 @@ enum Commands {
 
     /// Send a message to a channel
     Send {
-        /// Channel type
+        /// Channel type (defaults to the endpoint's)
         #[arg(short = 't', long, value_enum)]
-        channel_type: ChannelType,
+        channel_type: Option<ChannelType>,
 
-        /// Channel name
+        /// Channel name (defaults to the endpoint's)
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
 
         /// Message to send (use '-' for stdin)
         message: String,
@@ -116,13 +175,35 @@ enum Commands {
         #[arg(long, default_value = "1024")]
         message_size: usize,
 
+        /// Comma-separated message sizes to sweep, instead of --message-size
+        #[arg(long, value_delimiter = ',', conflicts_with = "message_size")]
+        sizes: Vec<usize>,
+
         /// Number of warmup iterations
         #[arg(long, default_value = "100")]
         warmup: u64,
 
+        /// Break per-message time down into serialization, framing, syscall
+        /// and wakeup phases (pipe only)
+        #[arg(long)]
+        profile: bool,
+
+        /// Run one side of a cross-process benchmark (pipe and socket only);
+        /// start the server first, then the client
+        #[arg(long, value_enum, conflicts_with = "profile")]
+        role: Option<BenchRole>,
+
+        /// Channel name shared by the --role server and client
+        #[arg(short, long, default_value = "ipckit_bench")]
+        name: String,
+
         /// Output format
         #[arg(long, value_enum, default_value = "text")]
-        format: OutputFormat,
+        format: BenchFormat,
+
+        /// Write the report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 
     /// Generate shell completions
@@ -134,13 +215,13 @@ enum Commands {
 
     /// Show channel information
     Info {
-        /// Channel type
+        /// Channel type (defaults to the endpoint's)
         #[arg(short = 't', long, value_enum)]
-        channel_type: ChannelType,
+        channel_type: Option<ChannelType>,
 
-        /// Channel name
+        /// Channel name (defaults to the endpoint's)
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
     },
 
     /// Start an API server
@@ -152,6 +233,21 @@ enum Commands {
         /// Port for HTTP server (if using TCP)
         #[arg(short, long)]
         port: Option<u16>,
+
+        /// Service manifest (TOML or JSON) describing sockets, routes, tasks and webhooks
+        #[arg(short, long)]
+        manifest: Option<PathBuf>,
+    },
+
+    /// Interactive prompt for sending requests to a channel
+    Shell {
+        /// Channel type (defaults to the endpoint's)
+        #[arg(short = 't', long, value_enum)]
+        channel_type: Option<ChannelType>,
+
+        /// Channel name (defaults to the endpoint's)
+        #[arg(short, long)]
+        name: Option<String>,
     },
 
     /// Generate code templates
@@ -161,6 +257,18 @@ enum Commands {
         target: GenerateCommand,
     },
 
+    /// List the channels running processes have advertised
+    #[command(alias = "list")]
+    Ls {
+        /// Only list channels of this type
+        #[arg(short = 't', long, value_enum)]
+        channel_type: Option<ChannelType>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+
     /// Monitor channel activity
     Monitor {
         /// Channel type to monitor (optional, monitors all if not specified)
@@ -179,19 +287,85 @@ enum Commands {
         #[arg(long, default_value = "1000")]
         interval: u64,
     },
+
+    /// Live dashboard of a running daemon's channels, connections and tasks
+    Top {
+        /// Socket path of the daemon (defaults to the endpoint's, then the `serve` socket)
+        #[arg(short, long)]
+        socket: Option<String>,
+
+        /// Path of the daemon's metrics route
+        #[arg(long, default_value = "/metrics")]
+        metrics_path: String,
+
+        /// Refresh interval in milliseconds
+        #[arg(long, default_value = "1000")]
+        interval: u64,
+
+        /// Exit after this many refreshes
+        #[arg(short = 'n', long)]
+        iterations: Option<u64>,
+    },
+
+    /// Manage tasks on a running daemon
+    Task {
+        /// Socket path of the daemon (defaults to the endpoint's, then the `serve` socket)
+        #[arg(short, long, global = true)]
+        socket: Option<String>,
+
+        #[command(subcommand)]
+        action: TaskCommand,
+    },
+
+    /// Record all frames received on a channel to a session file
+    Record {
+        /// Channel type (defaults to the endpoint's)
+        #[arg(short = 't', long, value_enum)]
+        channel_type: Option<ChannelType>,
+
+        /// Channel name (defaults to the endpoint's)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Session file to write (JSON Lines)
+        #[arg(short, long)]
+        out: PathBuf,
+
+        /// Stop after this many frames
+        #[arg(long)]
+        max_frames: Option<u64>,
+    },
+
+    /// Replay a recorded session to a channel
+    Replay {
+        /// Session file produced by `record`
+        session: PathBuf,
+
+        /// Channel type (defaults to the recorded one)
+        #[arg(short = 't', long, value_enum)]
+        channel_type: Option<ChannelType>,
+
+        /// Channel name (defaults to the recorded one)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Send frames as fast as possible instead of with original timing
+        #[arg(long)]
+        fast: bool,
+    },
 }
 
 #[derive(Subcommand, Clone)]
 enum GenerateCommand {
     /// Generate client code
     Client {
-        /// Channel type
+        /// Channel type (defaults to the endpoint's)
         #[arg(short = 't', long, value_enum)]
-        channel_type: ChannelType,
+        channel_type: Option<ChannelType>,
 
-        /// Channel name
+        /// Channel name (defaults to the endpoint's)
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
 
         /// Output file (prints to stdout if not specified)
         #[arg(short, long)]
@@ -200,13 +374,13 @@ enum GenerateCommand {
 
     /// Generate server code
     Server {
-        /// Channel type
+        /// Channel type (defaults to the endpoint's)
         #[arg(short = 't', long, value_enum)]
-        channel_type: ChannelType,
+        channel_type: Option<ChannelType>,
 
-        /// Channel name
+        /// Channel name (defaults to the endpoint's)
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
 
         /// Output file (prints to stdout if not specified)
         #[arg(short, long)]
@@ -215,13 +389,28 @@ enum GenerateCommand {
 
     /// Generate Python bindings example
     Python {
-        /// Channel type
+        /// Channel type (defaults to the endpoint's)
         #[arg(short = 't', long, value_enum)]
-        channel_type: ChannelType,
+        channel_type: Option<ChannelType>,
 
-        /// Channel name
+        /// Channel name (defaults to the endpoint's)
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
+
+        /// Output file (prints to stdout if not specified)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Generate a typed TypeScript client for an API server
+    Typescript {
+        /// Read routes from the API server on this socket (default: the endpoint's, then the `serve` socket)
+        #[arg(short, long, conflicts_with = "openapi")]
+        socket: Option<String>,
+
+        /// Read routes and types from an OpenAPI document (JSON) instead
+        #[arg(long)]
+        openapi: Option<PathBuf>,
 
         /// Output file (prints to stdout if not specified)
         #[arg(short, long)]
@@ -240,6 +429,43 @@ enum GenerateCommand {
     },
 }
 
+#[derive(Subcommand, Clone)]
+enum TaskCommand {
+    /// List tasks
+    #[command(alias = "list")]
+    Ls {
+        /// Include completed, failed and cancelled tasks
+        #[arg(short, long)]
+        all: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Show everything known about a task as JSON
+    Inspect {
+        /// Task ID
+        id: String,
+    },
+
+    /// Cancel a task
+    Cancel {
+        /// Task ID
+        id: String,
+    },
+
+    /// Print a task's log and output lines
+    Logs {
+        /// Task ID
+        id: String,
+
+        /// Keep printing new lines until the task finishes
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
 #[derive(Clone, Copy, ValueEnum)]
 pub enum ChannelType {
     /// Named pipe
@@ -264,6 +490,24 @@ pub enum OutputFormat {
     Hex,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+pub enum BenchFormat {
+    /// Plain text
+    Text,
+    /// JSON
+    Json,
+    /// CSV, one row per message size
+    Csv,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum BenchRole {
+    /// Echo frames back to the client
+    Server,
+    /// Send frames and time the round trips
+    Client,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum GenerateTarget {
     Client,
@@ -282,39 +526,69 @@ fn main() {
 }
 
 fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let ctx = config::Context::load(cli.config.as_deref(), cli.endpoint)?;
+
     match cli.command {
         Commands::Create {
             channel_type,
             name,
             size,
-        } => commands::create(channel_type, &name, size, cli.verbose),
+        } => {
+            let (channel_type, name) = ctx.channel(channel_type, name)?;
+            commands::create(channel_type, &name, size, cli.verbose)
+        }
 
         Commands::Listen {
             channel_type,
             name,
+            attach,
             format,
             timeout,
-        } => commands::listen(channel_type, &name, format, timeout, cli.verbose),
+        } => match attach {
+            Some(socket) => commands::listen_attach(&socket, format, timeout, cli.verbose),
+            None => {
+                let (channel_type, name) = ctx.channel(channel_type, name)?;
+                commands::listen(channel_type, &name, format, timeout, cli.verbose)
+            }
+        },
 
         Commands::Send {
             channel_type,
             name,
             message,
             file,
-        } => commands::send(channel_type, &name, &message, file, cli.verbose),
+        } => {
+            let (channel_type, name) = ctx.channel(channel_type, name)?;
+            commands::send(channel_type, &name, &message, file, cli.verbose)
+        }
 
         Commands::Bench {
             channel_type,
             iterations,
             message_size,
+            sizes,
             warmup,
+            profile,
+            role,
+            name,
             format,
+            output,
         } => commands::bench(
-            channel_type,
-            iterations,
-            message_size,
-            warmup,
-            format,
+            commands::BenchOptions {
+                channel_type,
+                iterations,
+                sizes: if sizes.is_empty() {
+                    vec![message_size]
+                } else {
+                    sizes
+                },
+                warmup,
+                profile,
+                role,
+                name,
+                format,
+                output,
+            },
             cli.verbose,
         ),
 
@@ -323,44 +597,75 @@ fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             Ok(())
         }
 
-        Commands::Info { channel_type, name } => commands::info(channel_type, &name, cli.verbose),
+        Commands::Info { channel_type, name } => {
+            let (channel_type, name) = ctx.channel(channel_type, name)?;
+            commands::info(channel_type, &name, cli.verbose)
+        }
+
+        Commands::Serve {
+            socket,
+            port,
+            manifest,
+        } => commands::serve(socket, port, manifest, &ctx.config, cli.verbose),
 
-        Commands::Serve { socket, port } => commands::serve(socket, port, cli.verbose),
+        Commands::Shell { channel_type, name } => {
+            let (channel_type, name) = ctx.channel(channel_type, name)?;
+            commands::set_client_options(ctx.client_options(&name)?);
+            commands::shell(channel_type, &name, cli.verbose)
+        }
 
         Commands::Generate { target } => match target {
             GenerateCommand::Client {
                 channel_type,
                 name,
                 output,
-            } => commands::generate(
-                GenerateTarget::Client,
-                channel_type,
-                &name,
-                output,
-                cli.verbose,
-            ),
+            } => {
+                let (channel_type, name) = ctx.channel(channel_type, name)?;
+                commands::generate(
+                    GenerateTarget::Client,
+                    channel_type,
+                    &name,
+                    output,
+                    cli.verbose,
+                )
+            }
             GenerateCommand::Server {
                 channel_type,
                 name,
                 output,
-            } => commands::generate(
-                GenerateTarget::Server,
-                channel_type,
-                &name,
-                output,
-                cli.verbose,
-            ),
+            } => {
+                let (channel_type, name) = ctx.channel(channel_type, name)?;
+                commands::generate(
+                    GenerateTarget::Server,
+                    channel_type,
+                    &name,
+                    output,
+                    cli.verbose,
+                )
+            }
             GenerateCommand::Python {
                 channel_type,
                 name,
                 output,
-            } => commands::generate(
-                GenerateTarget::Python,
-                channel_type,
-                &name,
+            } => {
+                let (channel_type, name) = ctx.channel(channel_type, name)?;
+                commands::generate(
+                    GenerateTarget::Python,
+                    channel_type,
+                    &name,
+                    output,
+                    cli.verbose,
+                )
+            }
+            GenerateCommand::Typescript {
+                socket,
+                openapi,
                 output,
-                cli.verbose,
-            ),
+            } => {
+                let socket = ctx.socket(socket)?;
+                commands::set_client_options(ctx.client_options(&socket)?);
+                commands::generate_typescript(&socket, openapi, output, cli.verbose)
+            }
             GenerateCommand::Handler { name, output } => commands::generate(
                 GenerateTarget::Handler,
                 ChannelType::Pipe, // Default, not used for handler
@@ -370,11 +675,55 @@ fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             ),
         },
 
+        Commands::Ls {
+            channel_type,
+            format,
+        } => commands::ls(channel_type, format, cli.verbose),
+
         Commands::Monitor {
             channel_type,
             name,
             format,
             interval,
         } => commands::monitor(channel_type, name, format, interval, cli.verbose),
+
+        Commands::Top {
+            socket,
+            metrics_path,
+            interval,
+            iterations,
+        } => {
+            let socket = ctx.socket(socket)?;
+            commands::set_client_options(ctx.client_options(&socket)?);
+            commands::top(&socket, &metrics_path, interval, iterations, cli.verbose)
+        }
+
+        Commands::Task { socket, action } => {
+            let socket = ctx.socket(socket)?;
+            commands::set_client_options(ctx.client_options(&socket)?);
+            match action {
+                TaskCommand::Ls { all, format } => commands::task_ls(&socket, all, format),
+                TaskCommand::Inspect { id } => commands::task_inspect(&socket, &id),
+                TaskCommand::Cancel { id } => commands::task_cancel(&socket, &id),
+                TaskCommand::Logs { id, follow } => commands::task_logs(&socket, &id, follow),
+            }
+        }
+
+        Commands::Record {
+            channel_type,
+            name,
+            out,
+            max_frames,
+        } => {
+            let (channel_type, name) = ctx.channel(channel_type, name)?;
+            commands::record(channel_type, &name, &out, max_frames, cli.verbose)
+        }
+
+        Commands::Replay {
+            session,
+            channel_type,
+            name,
+            fast,
+        } => commands::replay(&session, channel_type, name, fast, cli.verbose),
     }
 }
@@ -6,7 +6,10 @@
 //!
 //! - `#[ipc_handler]` - Mark an impl block as an IPC handler
 //! - `#[command]` - Define a command handler method
-//! - `#[derive(IpcMessage)]` - Derive serialization for IPC messages
+//! - `service!` - Typed request/response services with generated client stubs
+//! - `#[derive(IpcMessage)]` - Derive serialization and `#[ipc(...)]` validation for IPC messages
+//! - `ipc_message!` - Declarative message types with the same validation rules
+//! - `#[derive(ShmStruct)]` - Map a `#[repr(C)]` struct into shared memory
 //! - `ipc_channel!` - Declarative channel creation
 //! - `ipc_commands!` - Declarative command routing
 //!
@@ -35,6 +38,9 @@
 //!     }
 //! }
 //!
+//! // Serve the commands as POST /v1/my_app/{command}
+//! api_server.router().mount("/v1/my_app", MyHandler);
+//!
 //! // Declarative channel creation
 //! ipc_channel!(my_channel, pipe, "my_pipe");
 //!
@@ -48,8 +54,8 @@
 
 use darling::FromMeta;
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput, ImplItem, ItemImpl, Meta};
+use quote::{quote, ToTokens};
+use syn::{parse_macro_input, DeriveInput, ImplItem, ItemImpl};
 
 /// Attributes for the `#[ipc_handler]` macro.
 #[derive(Debug, Default, FromMeta)]
@@ -62,6 +68,32 @@ struct IpcHandlerArgs {
     timeout_ms: Option<u64>,
 }
 
+/// Validation rules of an `#[ipc(...)]` field attribute.
+#[derive(Debug, Default, FromMeta)]
+struct FieldRules {
+    /// Reject empty strings and collections
+    #[darling(default)]
+    not_empty: bool,
+    /// Inclusive bounds
+    #[darling(default)]
+    range: Option<RangeRule>,
+    /// Pattern a string must match
+    #[darling(default)]
+    regex: Option<String>,
+    /// Maximum length in characters or elements
+    #[darling(default)]
+    max_len: Option<usize>,
+}
+
+/// Bounds of a `range(min = .., max = ..)` rule.
+#[derive(Debug, FromMeta)]
+struct RangeRule {
+    #[darling(default)]
+    min: Option<syn::Expr>,
+    #[darling(default)]
+    max: Option<syn::Expr>,
+}
+
 /// Attributes for the `#[command]` macro.
 #[derive(Debug, Default, FromMeta)]
 #[allow(dead_code)]
@@ -76,7 +108,20 @@ struct CommandArgs {
 
 /// Mark an impl block as an IPC handler.
 ///
-/// This macro generates the necessary boilerplate for handling IPC commands.
+/// This macro generates the necessary boilerplate for handling IPC commands,
+/// including an `ipckit::CommandHandler` impl so the handler can be mounted
+/// with `Router::mount` or served with `ipckit::CommandService`.
+///
+/// Commands may be `async fn`s: `handle_command_async` awaits them, while
+/// `handle_command` runs them to completion on the calling thread.
+///
+/// Commands may also take `&mut self`, and a shared reference parameter
+/// (`ctx: &Ctx`) that is injected rather than deserialized. Such handlers
+/// implement `ipckit::StatefulHandler` instead of `ipckit::CommandHandler`
+/// and are mounted wrapped in `ipckit::Stateful`, which holds the context
+/// and locks the handler: `&self` commands share a read lock, `&mut self`
+/// commands take the write lock. All commands of a handler must use the same
+/// context type.
 ///
 /// ## Attributes
 ///
@@ -92,7 +137,25 @@ struct CommandArgs {
 ///     fn ping(&self) -> String {
 ///         "pong".to_string()
 ///     }
+///
+///     #[command]
+///     async fn fetch(&self, url: String) -> String {
+///         download(&url).await
+///     }
+/// }
+///
+/// server.router().mount("/v1/my_service", MyService);
+///
+/// #[ipc_handler(channel = "jobs")]
+/// impl Jobs {
+///     #[command]
+///     fn create(&mut self, ctx: &Ctx, name: String) -> String {
+///         self.created += 1;
+///         ctx.tasks.create(TaskBuilder::new(&name, "job")).id().to_string()
+///     }
 /// }
+///
+/// server.router().mount("/v1/jobs", Stateful::new(Jobs::default(), ctx));
 /// ```
 #[proc_macro_attribute]
 pub fn ipc_handler(attr: TokenStream, item: TokenStream) -> TokenStream {
@@ -112,18 +175,23 @@ fn parse_handler_args(attr: TokenStream) -> Result<IpcHandlerArgs, syn::Error> {
         return Ok(IpcHandlerArgs::default());
     }
 
-    let meta: Meta = syn::parse(attr)?;
-    IpcHandlerArgs::from_meta(&meta).map_err(|e| syn::Error::new_spanned(&meta, e.to_string()))
+    let items = darling::ast::NestedMeta::parse_meta_list(attr.into())?;
+    IpcHandlerArgs::from_list(&items).map_err(|e| e.into())
 }
 
 fn expand_ipc_handler(args: IpcHandlerArgs, input: ItemImpl) -> proc_macro2::TokenStream {
     let self_ty = &input.self_ty;
     let generics = &input.generics;
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
 
     // Collect command methods
     let mut command_handlers = Vec::new();
+    let mut async_command_handlers = Vec::new();
+    let mut ref_command_handlers = Vec::new();
     let mut command_names = Vec::new();
+    let mut mut_command_names = Vec::new();
+    // Type of the `&Context` parameter commands receive
+    let mut context: Option<syn::Type> = None;
 
     for item in &input.items {
         if let ImplItem::Fn(method) = item {
@@ -138,22 +206,45 @@ fn expand_ipc_handler(args: IpcHandlerArgs, input: ItemImpl) -> proc_macro2::Tok
                 let command_name = method_name.to_string();
                 command_names.push(command_name.clone());
 
-                // Generate parameter extraction
-                let params: Vec<_> = method
+                let is_mut = method
                     .sig
-                    .inputs
-                    .iter()
-                    .filter_map(|arg| {
-                        if let syn::FnArg::Typed(pat_type) = arg {
-                            if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
-                                let name = &pat_ident.ident;
-                                let ty = &pat_type.ty;
-                                return Some((name.clone(), ty.clone()));
+                    .receiver()
+                    .is_some_and(|r| r.reference.is_some() && r.mutability.is_some());
+                if is_mut {
+                    mut_command_names.push(command_name.clone());
+                }
+
+                // Shared references are the injected context; everything
+                // else is deserialized from the parameters
+                let mut params = Vec::new();
+                let mut args = Vec::new();
+                for arg in &method.sig.inputs {
+                    let syn::FnArg::Typed(pat_type) = arg else {
+                        continue;
+                    };
+                    if let syn::Type::Reference(reference) = &*pat_type.ty {
+                        if reference.mutability.is_none() {
+                            let ty = &*reference.elem;
+                            match context {
+                                Some(ref existing) if existing != ty => {
+                                    return syn::Error::new_spanned(
+                                        ty,
+                                        "all commands of a handler must take the same context type",
+                                    )
+                                    .to_compile_error();
+                                }
+                                _ => context = Some(ty.clone()),
                             }
+                            args.push(quote! { ctx });
+                            continue;
                         }
-                        None
-                    })
-                    .collect();
+                    }
+                    if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
+                        let name = &pat_ident.ident;
+                        params.push((name.clone(), pat_type.ty.clone()));
+                        args.push(quote! { #name });
+                    }
+                }
 
                 let param_extractions: Vec<_> = params
                     .iter()
@@ -163,7 +254,7 @@ fn expand_ipc_handler(args: IpcHandlerArgs, input: ItemImpl) -> proc_macro2::Tok
                             let #name: #ty = params
                                 .get(#name_str)
                                 .cloned()
-                                .ok_or_else(|| ipckit::IpcError::Other(
+                                .ok_or_else(|| ipckit::IpcError::Deserialization(
                                     format!("Missing parameter: {}", #name_str)
                                 ))
                                 .and_then(|v| serde_json::from_value(v)
@@ -172,18 +263,41 @@ fn expand_ipc_handler(args: IpcHandlerArgs, input: ItemImpl) -> proc_macro2::Tok
                     })
                     .collect();
 
-                let param_names: Vec<_> = params.iter().map(|(name, _)| name).collect();
+                // Async commands are awaited by handle_command_async and driven
+                // to completion on the calling thread by handle_command
+                let (call, async_call) = if method.sig.asyncness.is_some() {
+                    (
+                        quote! {
+                            ipckit::command_handler::block_on(self.#method_name(#(#args),*))
+                        },
+                        quote! { self.#method_name(#(#args),*).await },
+                    )
+                } else {
+                    let call = quote! { self.#method_name(#(#args),*) };
+                    (call.clone(), call)
+                };
 
                 let handler = quote! {
                     #command_name => {
                         #(#param_extractions)*
-                        let result = self.#method_name(#(#param_names),*);
+                        let result = #call;
                         serde_json::to_value(&result)
                             .map_err(|e| ipckit::IpcError::Serialization(e.to_string()))
                     }
                 };
-
+                if !is_mut {
+                    ref_command_handlers.push(handler.clone());
+                }
                 command_handlers.push(handler);
+
+                async_command_handlers.push(quote! {
+                    #command_name => {
+                        #(#param_extractions)*
+                        let result = #async_call;
+                        serde_json::to_value(&result)
+                            .map_err(|e| ipckit::IpcError::Serialization(e.to_string()))
+                    }
+                });
             }
         }
     }
@@ -191,11 +305,26 @@ fn expand_ipc_handler(args: IpcHandlerArgs, input: ItemImpl) -> proc_macro2::Tok
     let channel_name = args.channel.unwrap_or_else(|| "default".to_string());
     let timeout = args.timeout_ms.unwrap_or(30000);
 
-    // Generate the handler trait implementation
+    if context.is_some() || !mut_command_names.is_empty() {
+        return expand_stateful_handler(StatefulParts {
+            input: &input,
+            channel_name: &channel_name,
+            timeout,
+            command_names: &command_names,
+            mut_command_names: &mut_command_names,
+            context: context.as_ref(),
+            command_handlers: &command_handlers,
+            async_command_handlers: &async_command_handlers,
+            ref_command_handlers: &ref_command_handlers,
+        });
+    }
+
+    // Generate the handler methods and the CommandHandler impl used to mount
+    // the handler on an ApiServer router or a SocketServer
     let expanded = quote! {
         #input
 
-        impl #impl_generics #self_ty #ty_generics #where_clause {
+        impl #impl_generics #self_ty #where_clause {
             /// Get the channel name for this handler.
             pub fn channel_name(&self) -> &'static str {
                 #channel_name
@@ -212,6 +341,8 @@ fn expand_ipc_handler(args: IpcHandlerArgs, input: ItemImpl) -> proc_macro2::Tok
             }
 
             /// Handle a command by name.
+            ///
+            /// `async` commands are run to completion on the calling thread.
             pub fn handle_command(
                 &self,
                 command: &str,
@@ -224,12 +355,185 @@ fn expand_ipc_handler(args: IpcHandlerArgs, input: ItemImpl) -> proc_macro2::Tok
                     )),
                 }
             }
+
+            /// Handle a command by name, awaiting `async` commands.
+            pub async fn handle_command_async(
+                &self,
+                command: &str,
+                params: serde_json::Map<String, serde_json::Value>,
+            ) -> ipckit::Result<serde_json::Value> {
+                match command {
+                    #(#async_command_handlers)*
+                    _ => Err(ipckit::IpcError::NotFound(
+                        format!("Unknown command: {}", command)
+                    )),
+                }
+            }
+        }
+
+        impl #impl_generics ipckit::CommandHandler for #self_ty #where_clause {
+            fn channel_name(&self) -> &'static str {
+                <#self_ty>::channel_name(self)
+            }
+
+            fn commands(&self) -> &'static [&'static str] {
+                <#self_ty>::commands(self)
+            }
+
+            fn handle_command(
+                &self,
+                command: &str,
+                params: serde_json::Map<String, serde_json::Value>,
+            ) -> ipckit::Result<serde_json::Value> {
+                <#self_ty>::handle_command(self, command, params)
+            }
         }
     };
 
     expanded
 }
 
+/// What [`expand_ipc_handler`] collected for a handler with `&mut self` or
+/// context commands.
+struct StatefulParts<'a> {
+    input: &'a ItemImpl,
+    channel_name: &'a str,
+    timeout: u64,
+    command_names: &'a [String],
+    mut_command_names: &'a [String],
+    context: Option<&'a syn::Type>,
+    command_handlers: &'a [proc_macro2::TokenStream],
+    async_command_handlers: &'a [proc_macro2::TokenStream],
+    ref_command_handlers: &'a [proc_macro2::TokenStream],
+}
+
+/// Generate the handler methods and the `StatefulHandler` impl served
+/// through `ipckit::Stateful`.
+fn expand_stateful_handler(parts: StatefulParts<'_>) -> proc_macro2::TokenStream {
+    let StatefulParts {
+        input,
+        channel_name,
+        timeout,
+        command_names,
+        mut_command_names,
+        context,
+        command_handlers,
+        async_command_handlers,
+        ref_command_handlers,
+    } = parts;
+    let self_ty = &input.self_ty;
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+
+    let receiver = if mut_command_names.is_empty() {
+        quote! { &self }
+    } else {
+        quote! { &mut self }
+    };
+    let (ctx_param, ctx_arg, context_ty) = match context {
+        Some(ty) => (quote! { ctx: &#ty, }, quote! { ctx, }, quote! { #ty }),
+        None => (quote! {}, quote! {}, quote! { () }),
+    };
+
+    quote! {
+        #input
+
+        impl #impl_generics #self_ty #where_clause {
+            /// Get the channel name for this handler.
+            pub fn channel_name(&self) -> &'static str {
+                #channel_name
+            }
+
+            /// Get the default timeout in milliseconds.
+            pub fn default_timeout_ms(&self) -> u64 {
+                #timeout
+            }
+
+            /// Get the list of available commands.
+            pub fn commands(&self) -> &'static [&'static str] {
+                &[#(#command_names),*]
+            }
+
+            /// Handle a command by name.
+            ///
+            /// `async` commands are run to completion on the calling thread.
+            pub fn handle_command(
+                #receiver,
+                #ctx_param
+                command: &str,
+                params: serde_json::Map<String, serde_json::Value>,
+            ) -> ipckit::Result<serde_json::Value> {
+                match command {
+                    #(#command_handlers)*
+                    _ => Err(ipckit::IpcError::NotFound(
+                        format!("Unknown command: {}", command)
+                    )),
+                }
+            }
+
+            /// Handle a command by name, awaiting `async` commands.
+            pub async fn handle_command_async(
+                #receiver,
+                #ctx_param
+                command: &str,
+                params: serde_json::Map<String, serde_json::Value>,
+            ) -> ipckit::Result<serde_json::Value> {
+                match command {
+                    #(#async_command_handlers)*
+                    _ => Err(ipckit::IpcError::NotFound(
+                        format!("Unknown command: {}", command)
+                    )),
+                }
+            }
+        }
+
+        impl #impl_generics ipckit::StatefulHandler for #self_ty #where_clause {
+            type Context = #context_ty;
+
+            fn channel_name(&self) -> &'static str {
+                <#self_ty>::channel_name(self)
+            }
+
+            fn commands(&self) -> &'static [&'static str] {
+                <#self_ty>::commands(self)
+            }
+
+            fn is_mut_command(command: &str) -> bool {
+                [#(#mut_command_names),*].contains(&command)
+            }
+
+            #[allow(unused_variables)]
+            fn handle_command_ref(
+                &self,
+                ctx: &Self::Context,
+                command: &str,
+                params: serde_json::Map<String, serde_json::Value>,
+            ) -> ipckit::Result<serde_json::Value> {
+                match command {
+                    #(#ref_command_handlers)*
+                    _ if <Self as ipckit::StatefulHandler>::is_mut_command(command) => {
+                        Err(ipckit::IpcError::InvalidState(
+                            format!("Command {} needs mutable access", command)
+                        ))
+                    }
+                    _ => Err(ipckit::IpcError::NotFound(
+                        format!("Unknown command: {}", command)
+                    )),
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn handle_command_mut(
+                &mut self,
+                ctx: &Self::Context,
+                command: &str,
+                params: serde_json::Map<String, serde_json::Value>,
+            ) -> ipckit::Result<serde_json::Value> {
+                <#self_ty>::handle_command(self, #ctx_arg command, params)
+            }
+        }
+    }
+}
+
 /// Mark a method as a command handler.
 ///
 /// This attribute is used within an `#[ipc_handler]` impl block to mark
@@ -257,66 +561,75 @@ pub fn command(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
 /// Derive macro for IPC messages.
 ///
-/// Automatically implements serialization and validation for IPC message types.
+/// Adds JSON conversion and a `validate()` method checking the `#[ipc(...)]`
+/// rules of each field. `validate()` runs every rule and returns the
+/// failures as `ipckit::ValidationErrors`; `Option` fields are only checked
+/// when they hold a value.
+///
+/// ## Field rules
+///
+/// - `not_empty` - String or collection must not be empty
+/// - `range(min = A, max = B)` - Inclusive bounds; either may be omitted.
+///   The bounds are expressions of the field's type
+/// - `regex = "..."` - String must match the pattern, which is checked
+///   at compile time
+/// - `max_len = N` - At most `N` characters or elements
 ///
 /// ## Example
 ///
 /// ```rust,ignore
-/// #[derive(IpcMessage)]
+/// #[derive(Serialize, Deserialize, IpcMessage)]
 /// struct CreateUserRequest {
+///     #[ipc(not_empty, max_len = 64)]
 ///     name: String,
+///     #[ipc(regex = "^[^@]+@[^@]+$")]
 ///     email: String,
+///     #[ipc(range(min = 18, max = 130))]
 ///     age: Option<u8>,
 /// }
+///
+/// if let Err(errors) = request.validate() {
+///     for error in errors.errors() {
+///         eprintln!("{}: {}", error.field, error.message);
+///     }
+/// }
 /// ```
 #[proc_macro_derive(IpcMessage, attributes(ipc))]
 pub fn derive_ipc_message(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let expanded = expand_ipc_message(input);
+    let expanded = expand_ipc_message(input).unwrap_or_else(|e| e.to_compile_error());
     TokenStream::from(expanded)
 }
 
-fn expand_ipc_message(input: DeriveInput) -> proc_macro2::TokenStream {
+fn expand_ipc_message(input: DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
     let name = &input.ident;
     let generics = &input.generics;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    // Generate validation code based on fields
-    let validation = match &input.data {
-        syn::Data::Struct(data) => {
-            let field_validations: Vec<_> = data
-                .fields
-                .iter()
-                .filter_map(|field| {
-                    let field_name = field.ident.as_ref()?;
-                    let _field_name_str = field_name.to_string();
-
-                    // Check for validation attributes
-                    for attr in &field.attrs {
-                        if attr.path().is_ident("ipc") {
-                            // Could parse validation rules here
-                            return Some(quote! {
-                                // Validate field
-                            });
-                        }
-                    }
-                    None
-                })
-                .collect();
-
-            quote! {
-                #(#field_validations)*
-                Ok(())
+    let mut field_validations = Vec::new();
+    if let syn::Data::Struct(data) = &input.data {
+        for field in &data.fields {
+            let Some(ident) = &field.ident else { continue };
+            for attr in field.attrs.iter().filter(|a| a.path().is_ident("ipc")) {
+                let items = darling::ast::NestedMeta::parse_meta_list(
+                    attr.meta.require_list()?.tokens.clone(),
+                )?;
+                let rules = FieldRules::from_list(&items)?;
+                field_validations.push(expand_field_rules(attr, ident, &field.ty, &rules)?);
             }
         }
-        _ => quote! { Ok(()) },
-    };
+    }
 
-    quote! {
+    Ok(quote! {
         impl #impl_generics #name #ty_generics #where_clause {
-            /// Validate this message.
-            pub fn validate(&self) -> ipckit::Result<()> {
-                #validation
+            /// Validate this message against its `#[ipc(...)]` field rules.
+            pub fn validate(&self) -> std::result::Result<(), ipckit::ValidationErrors> {
+                #[allow(unused_imports)]
+                use ipckit::validation::HasLength as _;
+                #[allow(unused_mut)]
+                let mut errors = ipckit::ValidationErrors::new();
+                #(#field_validations)*
+                errors.into_result()
             }
 
             /// Convert to JSON value.
@@ -331,34 +644,836 @@ fn expand_ipc_message(input: DeriveInput) -> proc_macro2::TokenStream {
                     .map_err(|e| ipckit::IpcError::Deserialization(e.to_string()))
             }
         }
+    })
+}
+
+/// Generate the checks of one `#[ipc(...)]` attribute.
+///
+/// An invalid `regex` pattern is reported on `attr` at compile time.
+fn expand_field_rules(
+    attr: &syn::Attribute,
+    ident: &syn::Ident,
+    ty: &syn::Type,
+    rules: &FieldRules,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let field = ident.to_string();
+    let field = field.trim_start_matches("r#");
+    let mut checks = Vec::new();
+
+    if rules.not_empty {
+        checks.push(quote! {
+            if value.length() == 0 {
+                errors.add(#field, "not_empty", "must not be empty");
+            }
+        });
+    }
+    if let Some(max_len) = rules.max_len {
+        let message = format!("length must be at most {}", max_len);
+        checks.push(quote! {
+            if value.length() > #max_len {
+                errors.add(#field, "max_len", #message);
+            }
+        });
+    }
+    if let Some(ref range) = rules.range {
+        if let Some(ref min) = range.min {
+            let message = format!("must be at least {}", expr_text(min));
+            checks.push(quote! {
+                if *value < #min {
+                    errors.add(#field, "range", #message);
+                }
+            });
+        }
+        if let Some(ref max) = range.max {
+            let message = format!("must be at most {}", expr_text(max));
+            checks.push(quote! {
+                if *value > #max {
+                    errors.add(#field, "range", #message);
+                }
+            });
+        }
+    }
+    if let Some(ref pattern) = rules.regex {
+        if let Err(e) = regex::Regex::new(pattern) {
+            return Err(syn::Error::new_spanned(
+                attr,
+                format!("invalid regex {:?}: {}", pattern, e),
+            ));
+        }
+        let message = format!("must match {}", pattern);
+        // Each rule compiles its pattern once, on first use
+        checks.push(quote! {
+            {
+                static REGEX: ::std::sync::OnceLock<ipckit::validation::Regex> =
+                    ::std::sync::OnceLock::new();
+                let regex = REGEX.get_or_init(|| {
+                    ipckit::validation::Regex::new(#pattern)
+                        .expect("pattern checked at compile time")
+                });
+                if !ipckit::validation::matches_regex(value, regex) {
+                    errors.add(#field, "regex", #message);
+                }
+            }
+        });
+    }
+
+    Ok(if is_option(ty) {
+        quote! {
+            if let Some(value) = &self.#ident {
+                #(#checks)*
+            }
+        }
+    } else {
+        quote! {
+            {
+                let value = &self.#ident;
+                #(#checks)*
+            }
+        }
+    })
+}
+
+/// Render an expression the way it is usually written, e.g. `-5` or
+/// `u8::MAX` rather than the token stream's `- 5` and `u8 :: MAX`.
+fn expr_text(expr: &syn::Expr) -> String {
+    fn push_tokens(tokens: proc_macro2::TokenStream, out: &mut String, spaced: &mut bool) {
+        use proc_macro2::{Delimiter, TokenTree};
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::None => ("", ""),
+                    };
+                    out.push_str(open);
+                    *spaced = false;
+                    push_tokens(group.stream(), out, spaced);
+                    out.push_str(close);
+                    *spaced = false;
+                }
+                TokenTree::Punct(punct) => {
+                    out.push(punct.as_char());
+                    *spaced = false;
+                }
+                // Words need a space between them, as in `x as i64`
+                word => {
+                    if *spaced {
+                        out.push(' ');
+                    }
+                    out.push_str(&word.to_string());
+                    *spaced = true;
+                }
+            }
+        }
+    }
+
+    let mut out = String::new();
+    push_tokens(expr.to_token_stream(), &mut out, &mut false);
+    out
+}
+
+/// Check whether a field type is spelled `Option<..>`.
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "Option"),
+        _ => false,
     }
 }
 
+/// Derive `ipckit::ShmStruct` for a `#[repr(C)]` struct.
+///
+/// The schema hashed into the shared memory layout header is built from the
+/// struct name and each field's name and type, so renaming, reordering or
+/// retyping a field makes older processes refuse the segment. Every field
+/// must itself be `ShmStruct` (fixed-size integers, floats, arrays of them,
+/// or other derived structs).
+///
+/// ## Attributes
+///
+/// - `#[shm(version = N)]` - Layout version (default: 1); bump it when the
+///   meaning of a field changes but its name and type do not
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// #[derive(Clone, Copy, ShmStruct)]
+/// #[repr(C)]
+/// #[shm(version = 2)]
+/// struct Viewport {
+///     width: u32,
+///     height: u32,
+///     camera: [f32; 16],
+/// }
+///
+/// let viewport = ipckit::ShmMapped::<Viewport>::open("viewport")?;
+/// ```
+#[proc_macro_derive(ShmStruct, attributes(shm))]
+pub fn derive_shm_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_shm_struct(input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(e) => TokenStream::from(e.to_compile_error()),
+    }
+}
+
+fn expand_shm_struct(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "ShmStruct cannot be derived for generic types",
+        ));
+    }
+
+    let fields = match &input.data {
+        syn::Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "ShmStruct can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut has_repr_c = false;
+    let mut version = 1u32;
+    for attr in &input.attrs {
+        if attr.path().is_ident("repr") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("C") || meta.path.is_ident("transparent") {
+                    has_repr_c = true;
+                }
+                Ok(())
+            })?;
+        } else if attr.path().is_ident("shm") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("version") {
+                    version = meta.value()?.parse::<syn::LitInt>()?.base10_parse()?;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `version = N`"))
+                }
+            })?;
+        }
+    }
+    if !has_repr_c {
+        return Err(syn::Error::new_spanned(
+            name,
+            "ShmStruct requires #[repr(C)] or #[repr(transparent)]",
+        ));
+    }
+
+    let field_schemas: Vec<String> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let field_name = field
+                .ident
+                .as_ref()
+                .map_or_else(|| i.to_string(), |ident| ident.to_string());
+            let ty = &field.ty;
+            let ty = quote!(#ty).to_string().replace(' ', "");
+            format!("{}:{}", field_name, ty)
+        })
+        .collect();
+    let schema = format!("{}{{{}}}", name, field_schemas.join(","));
+    let field_types = fields.iter().map(|f| &f.ty);
+
+    Ok(quote! {
+        unsafe impl ipckit::ShmStruct for #name {
+            const VERSION: u32 = #version;
+            const SCHEMA: &'static str = #schema;
+        }
+
+        // Every field must be valid for any bit pattern
+        const _: () = {
+            fn assert_shm_struct<T: ipckit::ShmStruct>() {}
+            fn assert_fields() {
+                #(assert_shm_struct::<#field_types>();)*
+            }
+        };
+    })
+}
+
 /// Router macro for defining routes declaratively.
 ///
+/// Each entry is `METHOD "path" => handler`, where `handler` is any
+/// expression usable with `Router::route` (a `Fn(Request) -> Response`).
+///
+/// Handlers that take path parameters directly can be written as
+/// `fn name(param, ...)`: the listed names must match the path's `{param}`
+/// segments exactly, each value is parsed with `FromStr` into the type the
+/// function expects, and a value that fails to parse yields `400 Bad Request`.
+///
+/// Routes are checked at compile time: unknown methods, malformed path
+/// parameters and duplicate routes (same method and path shape) are errors.
+///
 /// ## Example
 ///
 /// ```rust,ignore
+/// fn get_task(id: String) -> Response { ... }
+/// fn get_log_line(id: String, line: usize) -> Response { ... }
+///
 /// let router = router! {
 ///     GET "/tasks" => list_tasks,
-///     GET "/tasks/{id}" => get_task,
+///     GET "/tasks/{id}" => fn get_task(id),
+///     GET "/tasks/{id}/log/{line}" => fn get_log_line(id, line),
 ///     POST "/tasks" => create_task,
-///     DELETE "/tasks/{id}" => delete_task,
+///     DELETE "/tasks/{id}" => |req| delete_task(req),
 /// };
 /// ```
 #[proc_macro]
-pub fn router(_input: TokenStream) -> TokenStream {
-    // Parse route definitions
-    // Format: METHOD "path" => handler,
-    let expanded = quote! {
+pub fn router(input: TokenStream) -> TokenStream {
+    let routes = parse_macro_input!(input as RouteList);
+    match expand_router(routes) {
+        Ok(expanded) => TokenStream::from(expanded),
+        // A duplicate route reports two errors, which must be wrapped in a
+        // block to be valid in expression position
+        Err(e) => {
+            let errors = e.to_compile_error();
+            TokenStream::from(quote! { { #errors } })
+        }
+    }
+}
+
+/// HTTP methods accepted by `router!`, matching `ipckit::Method`.
+const ROUTE_METHODS: &[&str] = &["GET", "POST", "PUT", "DELETE", "PATCH", "OPTIONS", "HEAD"];
+
+/// Parsed `router!` input.
+struct RouteList {
+    routes: Vec<RouteDef>,
+}
+
+/// A single `METHOD "path" => handler` entry.
+struct RouteDef {
+    method: syn::Ident,
+    path: syn::LitStr,
+    handler: RouteHandler,
+}
+
+/// The right-hand side of a route entry.
+enum RouteHandler {
+    /// A `Fn(Request) -> Response` expression
+    Request(syn::Expr),
+    /// `fn name(params...)`, called with the parsed path parameters
+    Params {
+        func: syn::Path,
+        params: Vec<syn::Ident>,
+    },
+}
+
+impl syn::parse::Parse for RouteList {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut routes = Vec::new();
+        while !input.is_empty() {
+            let method: syn::Ident = input.parse()?;
+            let path: syn::LitStr = input.parse()?;
+            input.parse::<syn::Token![=>]>()?;
+
+            let handler = if input.peek(syn::Token![fn]) {
+                input.parse::<syn::Token![fn]>()?;
+                let func = input.call(syn::Path::parse_mod_style)?;
+                let content;
+                syn::parenthesized!(content in input);
+                let params = content
+                    .parse_terminated(syn::Ident::parse, syn::Token![,])?
+                    .into_iter()
+                    .collect();
+                RouteHandler::Params { func, params }
+            } else {
+                RouteHandler::Request(input.parse()?)
+            };
+
+            routes.push(RouteDef {
+                method,
+                path,
+                handler,
+            });
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<syn::Token![,]>()?;
+        }
+        Ok(Self { routes })
+    }
+}
+
+/// Validate a route path and return its parameter names and its shape, the
+/// path with parameter names erased, used to detect duplicate routes.
+fn parse_route_path(path: &syn::LitStr) -> syn::Result<(Vec<String>, String)> {
+    let value = path.value();
+    if !value.starts_with('/') {
+        return Err(syn::Error::new_spanned(
+            path,
+            "route path must start with '/'",
+        ));
+    }
+
+    let segments: Vec<&str> = value
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let mut params: Vec<String> = Vec::new();
+    let mut shape = Vec::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        if !segment.contains('{') && !segment.contains('}') {
+            shape.push(segment.to_string());
+            continue;
+        }
+
+        let inner = segment
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .filter(|s| !s.contains('{') && !s.contains('}'))
+            .ok_or_else(|| {
+                syn::Error::new_spanned(
+                    path,
+                    format!("path parameter `{}` must span a whole segment", segment),
+                )
+            })?;
+
+        let (name, wildcard) = match inner.strip_prefix('*') {
+            Some(name) => (name, true),
+            None => (inner, false),
+        };
+        if syn::parse_str::<syn::Ident>(name).is_err() {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!("invalid path parameter name `{}`", name),
+            ));
+        }
+        if params.iter().any(|p| p == name) {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!("duplicate path parameter `{}`", name),
+            ));
+        }
+        if wildcard && i + 1 != segments.len() {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!("wildcard `{{*{}}}` must be the last segment", name),
+            ));
+        }
+
+        params.push(name.to_string());
+        shape.push(if wildcard { "{*}" } else { "{}" }.to_string());
+    }
+
+    Ok((params, format!("/{}", shape.join("/"))))
+}
+
+fn expand_router(input: RouteList) -> syn::Result<proc_macro2::TokenStream> {
+    let mut seen: Vec<(String, String, &syn::LitStr)> = Vec::new();
+    let mut registrations = Vec::new();
+
+    for route in &input.routes {
+        let method_str = route.method.to_string();
+        if !ROUTE_METHODS.contains(&method_str.as_str()) {
+            return Err(syn::Error::new_spanned(
+                &route.method,
+                format!(
+                    "unknown HTTP method `{}`, expected one of {}",
+                    method_str,
+                    ROUTE_METHODS.join(", ")
+                ),
+            ));
+        }
+
+        let (path_params, shape) = parse_route_path(&route.path)?;
+        if let Some((_, _, first)) = seen
+            .iter()
+            .find(|(m, s, _)| *m == method_str && *s == shape)
+        {
+            let mut err = syn::Error::new_spanned(
+                &route.path,
+                format!("duplicate route `{} {}`", method_str, route.path.value()),
+            );
+            err.combine(syn::Error::new_spanned(first, "first defined here"));
+            return Err(err);
+        }
+        seen.push((method_str, shape, &route.path));
+
+        let method = &route.method;
+        let path = &route.path;
+        let handler = match &route.handler {
+            RouteHandler::Request(expr) => quote! { #expr },
+            RouteHandler::Params { func, params } => {
+                for param in params {
+                    if !path_params.iter().any(|p| param == p) {
+                        return Err(syn::Error::new_spanned(
+                            param,
+                            format!("`{}` is not a parameter of `{}`", param, path.value()),
+                        ));
+                    }
+                }
+                for name in &path_params {
+                    if !params.iter().any(|p| p == name) {
+                        return Err(syn::Error::new_spanned(
+                            func,
+                            format!("handler does not take path parameter `{}`", name),
+                        ));
+                    }
+                }
+                if let Some(dup) = params
+                    .iter()
+                    .enumerate()
+                    .find(|(i, p)| params[..*i].contains(p))
+                {
+                    return Err(syn::Error::new_spanned(
+                        dup.1,
+                        format!("path parameter `{}` is passed twice", dup.1),
+                    ));
+                }
+
+                let extractions = params.iter().map(|param| {
+                    let name = param.to_string();
+                    quote! {
+                        let #param = match __request.path_param(#name).unwrap_or_default().parse() {
+                            Ok(value) => value,
+                            Err(e) => {
+                                return ipckit::Response::bad_request(&format!(
+                                    "Invalid path parameter '{}': {}",
+                                    #name, e
+                                ))
+                            }
+                        };
+                    }
+                });
+                quote! {
+                    move |__request: ipckit::Request| {
+                        #(#extractions)*
+                        #func(#(#params),*)
+                    }
+                }
+            }
+        };
+
+        registrations.push(quote! {
+            router.route(ipckit::Method::#method, #path, #handler);
+        });
+    }
+
+    Ok(quote! {
         {
             let mut router = ipckit::Router::new();
-            // Routes would be parsed and added here
+            #(#registrations)*
             router
         }
-    };
+    })
+}
 
-    TokenStream::from(expanded)
+/// Define a typed request/response service over the socket server.
+///
+/// Each method names its request and response types, which must implement
+/// `Serialize` and `Deserialize`, and may limit how long a call takes with
+/// `timeout_ms`. For a service `Name` this generates:
+///
+/// - the trait `Name`, with one `fn method(&self, ctx: &ipckit::service::CallContext,
+///   request: Req) -> ipckit::Result<Resp>` per method
+/// - `NameServer<T: Name>`, an `ipckit::service::ServiceDefinition` to serve
+///   with `ipckit::service::ServiceHandler`
+/// - `NameClient`, with `fn method(&mut self, request: Req) -> ipckit::Result<Resp>`
+///   and `fn method_with(&mut self, request: Req, options: &ipckit::service::CallOptions)`
+///   per method
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// service! {
+///     /// Render jobs.
+///     pub service Renderer {
+///         /// Render a frame.
+///         fn render(RenderRequest) -> RenderResponse, timeout_ms = 30000;
+///         /// Get the queue length.
+///         fn queue(QueueRequest) -> QueueResponse;
+///     }
+/// }
+///
+/// SocketServer::at(path)?.spawn(ServiceHandler::new(RendererServer::new(MyRenderer)));
+///
+/// let mut client = RendererClient::connect(path)?;
+/// let frame = client.render(RenderRequest { frame: 1 })?;
+/// ```
+#[proc_macro]
+pub fn service(input: TokenStream) -> TokenStream {
+    let service = parse_macro_input!(input as ServiceDef);
+    match expand_service(service) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(e) => TokenStream::from(e.to_compile_error()),
+    }
+}
+
+/// Parsed `service!` input.
+struct ServiceDef {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    name: syn::Ident,
+    methods: Vec<ServiceMethod>,
+}
+
+/// A single `fn name(Request) -> Response, timeout_ms = N;` entry.
+struct ServiceMethod {
+    attrs: Vec<syn::Attribute>,
+    name: syn::Ident,
+    request: syn::Type,
+    response: syn::Type,
+    timeout_ms: Option<syn::LitInt>,
+}
+
+impl syn::parse::Parse for ServiceDef {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let vis: syn::Visibility = input.parse()?;
+        let keyword: syn::Ident = input.parse()?;
+        if keyword != "service" {
+            return Err(syn::Error::new_spanned(keyword, "expected `service`"));
+        }
+        let name: syn::Ident = input.parse()?;
+
+        let content;
+        syn::braced!(content in input);
+        let mut methods = Vec::new();
+        while !content.is_empty() {
+            let attrs = content.call(syn::Attribute::parse_outer)?;
+            content.parse::<syn::Token![fn]>()?;
+            let name: syn::Ident = content.parse()?;
+            let args;
+            syn::parenthesized!(args in content);
+            let request: syn::Type = args.parse()?;
+            content.parse::<syn::Token![->]>()?;
+            let response: syn::Type = content.parse()?;
+
+            let mut timeout_ms = None;
+            if content.peek(syn::Token![,]) {
+                content.parse::<syn::Token![,]>()?;
+                let key: syn::Ident = content.parse()?;
+                if key != "timeout_ms" {
+                    return Err(syn::Error::new_spanned(key, "expected `timeout_ms`"));
+                }
+                content.parse::<syn::Token![=]>()?;
+                timeout_ms = Some(content.parse()?);
+            }
+            content.parse::<syn::Token![;]>()?;
+
+            methods.push(ServiceMethod {
+                attrs,
+                name,
+                request,
+                response,
+                timeout_ms,
+            });
+        }
+
+        Ok(Self {
+            attrs,
+            vis,
+            name,
+            methods,
+        })
+    }
+}
+
+fn expand_service(input: ServiceDef) -> syn::Result<proc_macro2::TokenStream> {
+    let ServiceDef {
+        attrs,
+        vis,
+        name,
+        methods,
+    } = input;
+    let server = syn::Ident::new(&format!("{}Server", name), name.span());
+    let client = syn::Ident::new(&format!("{}Client", name), name.span());
+    let service_name = name.to_string();
+
+    for (i, method) in methods.iter().enumerate() {
+        if let Some(first) = methods[..i].iter().find(|m| m.name == method.name) {
+            let mut err = syn::Error::new_spanned(
+                &method.name,
+                format!("duplicate service method `{}`", method.name),
+            );
+            err.combine(syn::Error::new_spanned(&first.name, "first defined here"));
+            return Err(err);
+        }
+    }
+
+    let trait_methods = methods.iter().map(|m| {
+        let ServiceMethod {
+            attrs,
+            name,
+            request,
+            response,
+            ..
+        } = m;
+        quote! {
+            #(#attrs)*
+            fn #name(
+                &self,
+                ctx: &ipckit::service::CallContext,
+                request: #request,
+            ) -> ipckit::Result<#response>;
+        }
+    });
+
+    let infos = methods.iter().map(|m| {
+        let method_name = m.name.to_string();
+        let timeout = match &m.timeout_ms {
+            Some(ms) => quote! { Some(std::time::Duration::from_millis(#ms)) },
+            None => quote! { None },
+        };
+        quote! {
+            ipckit::service::MethodInfo {
+                name: #method_name,
+                timeout: #timeout,
+            }
+        }
+    });
+
+    let dispatch_arms = methods.iter().map(|m| {
+        let ServiceMethod {
+            name: method,
+            request,
+            response,
+            ..
+        } = m;
+        let method_name = method.to_string();
+        quote! {
+            #method_name => {
+                let request: #request = ipckit::service::decode(params)?;
+                let response: #response = <T as #name>::#method(&self.inner, ctx, request)?;
+                ipckit::service::encode(&response)
+            }
+        }
+    });
+
+    let client_methods = methods.iter().enumerate().map(|(i, m)| {
+        let ServiceMethod {
+            attrs,
+            name: method,
+            request,
+            response,
+            ..
+        } = m;
+        let with = syn::Ident::new(&format!("{}_with", method), method.span());
+        quote! {
+            #(#attrs)*
+            pub fn #method(&mut self, request: #request) -> ipckit::Result<#response> {
+                self.#with(request, &ipckit::service::CallOptions::default())
+            }
+
+            #(#attrs)*
+            ///
+            /// Overrides the timeout or adds a cancellation token with `options`.
+            pub fn #with(
+                &mut self,
+                request: #request,
+                options: &ipckit::service::CallOptions,
+            ) -> ipckit::Result<#response> {
+                self.inner.call(&Self::METHODS[#i], &request, options)
+            }
+        }
+    });
+
+    let server_doc = format!(
+        "Serves a [`{}`] implementation; see `ipckit::service::ServiceHandler`.",
+        name
+    );
+    let client_doc = format!("Typed client for the [`{}`] service.", name);
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis trait #name: Send + Sync + 'static {
+            #(#trait_methods)*
+        }
+
+        #[doc = #server_doc]
+        #vis struct #server<T> {
+            inner: T,
+        }
+
+        impl<T> #server<T> {
+            /// Wrap an implementation of the service.
+            pub fn new(inner: T) -> Self {
+                Self { inner }
+            }
+
+            /// Get the wrapped implementation.
+            pub fn inner(&self) -> &T {
+                &self.inner
+            }
+
+            /// Unwrap the implementation.
+            pub fn into_inner(self) -> T {
+                self.inner
+            }
+        }
+
+        impl<T: #name> ipckit::service::ServiceDefinition for #server<T> {
+            const NAME: &'static str = #service_name;
+
+            fn methods() -> &'static [ipckit::service::MethodInfo] {
+                #client::METHODS
+            }
+
+            fn dispatch(
+                &self,
+                ctx: &ipckit::service::CallContext,
+                method: &str,
+                params: serde_json::Value,
+            ) -> ipckit::Result<serde_json::Value> {
+                match method {
+                    #(#dispatch_arms)*
+                    _ => Err(ipckit::IpcError::NotFound(format!(
+                        "Unknown service method: {}.{}",
+                        #service_name, method
+                    ))),
+                }
+            }
+        }
+
+        #[doc = #client_doc]
+        #vis struct #client {
+            inner: ipckit::service::ServiceClient,
+        }
+
+        impl #client {
+            /// Methods of the service.
+            pub const METHODS: &'static [ipckit::service::MethodInfo] = &[#(#infos),*];
+
+            /// Connect to the server at `path`.
+            pub fn connect(path: &str) -> ipckit::Result<Self> {
+                Ok(Self {
+                    inner: ipckit::service::ServiceClient::connect(path, #service_name)?,
+                })
+            }
+
+            /// Call the service over an existing client.
+            pub fn from_client(client: ipckit::SocketClient) -> Self {
+                Self {
+                    inner: ipckit::service::ServiceClient::new(client, #service_name),
+                }
+            }
+
+            /// Set the timeout of methods declared without one.
+            pub fn default_timeout(&mut self, timeout: Option<std::time::Duration>) {
+                self.inner.default_timeout(timeout);
+            }
+
+            /// Get the untyped service client.
+            pub fn inner_mut(&mut self) -> &mut ipckit::service::ServiceClient {
+                &mut self.inner
+            }
+
+            #(#client_methods)*
+        }
+    })
 }
 
 /// Declarative channel creation macro.
@@ -593,15 +1708,17 @@ pub fn ipc_commands(input: TokenStream) -> TokenStream {
 /// Declarative message type definition macro.
 ///
 /// Defines an IPC message type with automatic serialization and validation.
+/// Fields take the same `#[ipc(...)]` rules as [`IpcMessage`](derive@IpcMessage),
+/// and `#[default]` fields fall back to their default when missing.
 ///
 /// ## Syntax
 ///
 /// ```rust,ignore
 /// ipc_message! {
 ///     pub struct MyMessage {
-///         #[validate(not_empty)]
+///         #[ipc(not_empty)]
 ///         name: String,
-///         #[validate(range(0..100))]
+///         #[ipc(range(min = 0, max = 100))]
 ///         age: u8,
 ///         #[default]
 ///         optional_field: Option<String>,
@@ -632,27 +1749,37 @@ pub fn ipc_commands(input: TokenStream) -> TokenStream {
 /// ```
 #[proc_macro]
 pub fn ipc_message(input: TokenStream) -> TokenStream {
-    let input_str = input.to_string();
+    let item = match syn::parse::<syn::ItemStruct>(input) {
+        Ok(item) => item,
+        Err(e) => {
+            return syn::Error::new(e.span(), "ipc_message! expects a struct definition")
+                .to_compile_error()
+                .into();
+        }
+    };
 
-    // Simple parsing - extract struct definition
-    // In a real implementation, we'd use syn to properly parse this
-    let expanded = if input_str.contains("struct") {
-        // Parse the struct definition
-        let struct_def: proc_macro2::TokenStream = input_str.parse().unwrap_or_else(|_| quote! {});
+    let methods = match expand_ipc_message(item.clone().into()) {
+        Ok(methods) => methods,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
-        quote! {
-            #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-            #struct_def
+    // The rules live in the generated `validate`; `#[default]` becomes serde's
+    let mut item = item;
+    for field in item.fields.iter_mut() {
+        field.attrs.retain(|a| !a.path().is_ident("ipc"));
+        for attr in field.attrs.iter_mut() {
+            if attr.path().is_ident("default") {
+                *attr = syn::parse_quote!(#[serde(default)]);
+            }
         }
-    } else {
-        syn::Error::new(
-            proc_macro2::Span::call_site(),
-            "ipc_message! expects a struct definition",
-        )
-        .to_compile_error()
-    };
+    }
 
-    TokenStream::from(expanded)
+    TokenStream::from(quote! {
+        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+        #item
+
+        #methods
+    })
 }
 
 /// Middleware chain macro for IPC handlers.
@@ -0,0 +1,101 @@
+//! Node bindings for ApiClient
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use ipckit::{ApiClient as RustApiClient, IpcError, Method};
+use napi::Result;
+use napi_derive::napi;
+
+use crate::blocking;
+
+/// Client for an ipckit API server.
+///
+/// Requests resolve to the response body, decoded from JSON or MessagePack.
+#[napi]
+pub struct ApiClient {
+    inner: Arc<RustApiClient>,
+}
+
+impl ApiClient {
+    async fn send(
+        &self,
+        method: Method,
+        path: String,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        blocking(move || inner.request(method, &path, body)).await
+    }
+}
+
+#[napi]
+impl ApiClient {
+    /// Create a client for the server at `socketPath`, or at the default
+    /// socket if omitted.
+    #[napi(constructor)]
+    pub fn new(socket_path: Option<String>, timeout_ms: Option<u32>) -> Self {
+        let timeout = timeout_ms.map(|ms| Duration::from_millis(u64::from(ms)));
+        let inner = match (socket_path, timeout) {
+            (Some(path), Some(timeout)) => RustApiClient::with_timeout(&path, timeout),
+            (Some(path), None) => RustApiClient::new(&path),
+            (None, Some(timeout)) => RustApiClient::connect_timeout(timeout),
+            (None, None) => RustApiClient::connect(),
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// The connection timeout in milliseconds, if any.
+    #[napi(getter)]
+    pub fn timeout_ms(&self) -> Option<u32> {
+        self.inner.get_timeout().map(|d| d.as_millis() as u32)
+    }
+
+    /// Make a GET request.
+    #[napi]
+    pub async fn get(&self, path: String) -> Result<serde_json::Value> {
+        self.send(Method::GET, path, None).await
+    }
+
+    /// Make a POST request.
+    #[napi]
+    pub async fn post(
+        &self,
+        path: String,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        self.send(Method::POST, path, body).await
+    }
+
+    /// Make a PUT request.
+    #[napi]
+    pub async fn put(
+        &self,
+        path: String,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        self.send(Method::PUT, path, body).await
+    }
+
+    /// Make a DELETE request.
+    #[napi]
+    pub async fn delete(&self, path: String) -> Result<serde_json::Value> {
+        self.send(Method::DELETE, path, None).await
+    }
+
+    /// Make a request with any method, e.g. `"PATCH"`.
+    #[napi]
+    pub async fn request(
+        &self,
+        method: String,
+        path: String,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        let method = Method::parse(&method).ok_or_else(|| {
+            crate::to_napi_error(IpcError::InvalidName(format!("unknown method: {}", method)))
+        })?;
+        self.send(method, path, body).await
+    }
+}
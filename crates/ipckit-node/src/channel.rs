@@ -0,0 +1,135 @@
+//! Node bindings for IpcChannel
+
+use std::sync::Arc;
+
+use ipckit::{IpcChannel as RustIpcChannel, IpcError};
+use napi::bindgen_prelude::Buffer;
+use napi::Result;
+use napi_derive::napi;
+use parking_lot::Mutex;
+
+use crate::{blocking, is_end_of_stream};
+
+/// High-level IPC channel for message passing.
+///
+/// Operations run one at a time in the order they were called, so a pending
+/// `recv` holds up a later `send` on the same channel.
+#[napi]
+pub struct IpcChannel {
+    name: String,
+    is_server: bool,
+    inner: Arc<Mutex<Option<RustIpcChannel<Vec<u8>>>>>,
+}
+
+impl IpcChannel {
+    fn new(inner: RustIpcChannel<Vec<u8>>) -> Self {
+        Self {
+            name: inner.name().to_string(),
+            is_server: inner.is_server(),
+            inner: Arc::new(Mutex::new(Some(inner))),
+        }
+    }
+
+    /// Run `f` on the open channel, off the JavaScript thread.
+    async fn run<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut RustIpcChannel<Vec<u8>>) -> ipckit::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let inner = self.inner.clone();
+        blocking(move || f(inner.lock().as_mut().ok_or(IpcError::Closed)?)).await
+    }
+}
+
+#[napi]
+impl IpcChannel {
+    /// Create a new IPC channel server.
+    #[napi]
+    pub async fn create(name: String) -> Result<IpcChannel> {
+        blocking(move || RustIpcChannel::create(&name).map(Self::new)).await
+    }
+
+    /// Connect to an existing IPC channel.
+    #[napi]
+    pub async fn connect(name: String) -> Result<IpcChannel> {
+        blocking(move || RustIpcChannel::connect(&name).map(Self::new)).await
+    }
+
+    /// The channel name.
+    #[napi(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Whether this is the server end.
+    #[napi(getter)]
+    pub fn is_server(&self) -> bool {
+        self.is_server
+    }
+
+    /// Whether the channel has been closed.
+    #[napi(getter)]
+    pub fn closed(&self) -> bool {
+        self.inner.try_lock().is_some_and(|inner| inner.is_none())
+    }
+
+    /// Wait for a client to connect (server only).
+    #[napi]
+    pub async fn wait_for_client(&self) -> Result<()> {
+        self.run(|channel| channel.wait_for_client()).await
+    }
+
+    /// Send a message.
+    #[napi]
+    pub async fn send(&self, data: Buffer) -> Result<()> {
+        let data = data.to_vec();
+        self.run(move |channel| channel.send_bytes(data)).await
+    }
+
+    /// Receive a message.
+    ///
+    /// Resolves to `null` once the peer has disconnected.
+    #[napi]
+    pub async fn recv(&self) -> Result<Option<Buffer>> {
+        self.run(|channel| match channel.recv_bytes() {
+            Ok(data) => Ok(Some(data.into())),
+            Err(e) if is_end_of_stream(&e) => Ok(None),
+            Err(e) => Err(e),
+        })
+        .await
+    }
+
+    /// Send a JSON value.
+    #[napi]
+    pub async fn send_json(&self, value: serde_json::Value) -> Result<()> {
+        self.run(move |channel| {
+            let data =
+                serde_json::to_vec(&value).map_err(|e| IpcError::serialization(e.to_string()))?;
+            channel.send_bytes(data)
+        })
+        .await
+    }
+
+    /// Receive a JSON value.
+    ///
+    /// Resolves to `undefined` once the peer has disconnected.
+    #[napi]
+    pub async fn recv_json(&self) -> Result<Option<serde_json::Value>> {
+        self.run(|channel| match channel.recv_bytes() {
+            Ok(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .map_err(|e| IpcError::deserialization(e.to_string())),
+            Err(e) if is_end_of_stream(&e) => Ok(None),
+            Err(e) => Err(e),
+        })
+        .await
+    }
+
+    /// Close the channel once pending operations have finished.
+    #[napi]
+    pub async fn close(&self) -> Result<()> {
+        self.run(|_| Ok(())).await?;
+        self.inner.lock().take();
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! Node bindings for EventBus and EventSubscriber
+
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use ipckit::{
+    Event as RustEvent, EventBus as RustEventBus, EventBusConfig, EventFilter as RustEventFilter,
+    EventSubscriber as RustEventSubscriber, IpcError,
+};
+use napi::Result;
+use napi_derive::napi;
+
+use crate::blocking;
+
+/// An event received from the bus.
+#[napi(object)]
+pub struct Event {
+    /// Event ID, increasing in publish order
+    pub id: i64,
+    /// Publish time in milliseconds since the Unix epoch, as for `Date`
+    pub timestamp: f64,
+    /// Event type (e.g., "task.progress", "log.stdout", "task.completed")
+    pub event_type: String,
+    /// Associated resource ID (e.g., task_id)
+    pub resource_id: Option<String>,
+    /// Event data
+    pub data: serde_json::Value,
+    /// W3C `traceparent` of the trace the event was published in
+    pub traceparent: Option<String>,
+}
+
+impl From<RustEvent> for Event {
+    fn from(event: RustEvent) -> Self {
+        Self {
+            id: event.id as i64,
+            timestamp: event
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or(Duration::ZERO)
+                .as_secs_f64()
+                * 1000.0,
+            event_type: event.event_type,
+            resource_id: event.resource_id,
+            data: event.data,
+            traceparent: event.trace.map(|trace| trace.to_string()),
+        }
+    }
+}
+
+/// Which events a subscription receives.
+#[napi(object)]
+pub struct EventFilter {
+    /// Event type pattern, e.g. `"task.*"`
+    pub event_type: Option<String>,
+    /// Only events for this resource
+    pub resource_id: Option<String>,
+}
+
+/// Convert an optional filter, where `None` matches every event.
+fn to_filter(filter: Option<EventFilter>) -> RustEventFilter {
+    let mut f = RustEventFilter::new();
+    if let Some(filter) = filter {
+        if let Some(pattern) = filter.event_type {
+            f = f.event_type(&pattern);
+        }
+        if let Some(id) = filter.resource_id {
+            f = f.resource(&id);
+        }
+    }
+    f
+}
+
+/// In-process publish-subscribe event bus.
+#[napi]
+pub struct EventBus {
+    inner: RustEventBus,
+}
+
+#[napi]
+impl EventBus {
+    /// Create a new event bus.
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self {
+            inner: RustEventBus::new(EventBusConfig::default()),
+        }
+    }
+
+    /// Publish an event and return its ID.
+    #[napi]
+    pub fn publish(
+        &self,
+        event_type: String,
+        data: Option<serde_json::Value>,
+        resource_id: Option<String>,
+    ) -> i64 {
+        let data = data.unwrap_or_else(|| serde_json::json!({}));
+        let event = match resource_id {
+            Some(id) => RustEvent::with_resource(&event_type, &id, data),
+            None => RustEvent::new(&event_type, data),
+        };
+        let id = event.id as i64;
+        self.inner.publish(event);
+        id
+    }
+
+    /// Subscribe to events matching `filter`, or to every event if omitted.
+    #[napi]
+    pub fn subscribe(&self, filter: Option<EventFilter>) -> EventSubscriber {
+        EventSubscriber {
+            inner: Arc::new(self.inner.subscribe(to_filter(filter))),
+        }
+    }
+
+    /// Retained events matching `filter`, oldest first.
+    #[napi]
+    pub fn history(&self, filter: Option<EventFilter>) -> Vec<Event> {
+        self.inner
+            .history(&to_filter(filter))
+            .into_iter()
+            .map(Event::from)
+            .collect()
+    }
+
+    /// The number of active subscribers.
+    #[napi(getter)]
+    pub fn subscriber_count(&self) -> u32 {
+        self.inner.subscriber_count() as u32
+    }
+}
+
+/// Subscription to an [`EventBus`].
+#[napi]
+pub struct EventSubscriber {
+    inner: Arc<RustEventSubscriber>,
+}
+
+#[napi]
+impl EventSubscriber {
+    /// Receive the next event.
+    ///
+    /// Resolves to `null` once unsubscribed and drained, and rejects with a
+    /// `Timeout` error if `timeoutMs` elapses first.
+    #[napi]
+    pub async fn recv(&self, timeout_ms: Option<u32>) -> Result<Option<Event>> {
+        let inner = self.inner.clone();
+        blocking(move || {
+            let event = match timeout_ms {
+                Some(ms) => match inner.recv_timeout(Duration::from_millis(u64::from(ms))) {
+                    Ok(event) => Some(event),
+                    Err(IpcError::Closed) => None,
+                    Err(e) => return Err(e),
+                },
+                None => inner.recv(),
+            };
+            Ok(event.map(Event::from))
+        })
+        .await
+    }
+
+    /// Receive an event if one is queued.
+    #[napi]
+    pub fn try_recv(&self) -> Option<Event> {
+        self.inner.try_recv().map(Event::from)
+    }
+
+    /// Receive every queued event.
+    #[napi]
+    pub fn drain(&self) -> Vec<Event> {
+        self.inner.try_iter().map(Event::from).collect()
+    }
+
+    /// The number of events waiting to be received.
+    #[napi(getter)]
+    pub fn pending(&self) -> u32 {
+        self.inner.pending() as u32
+    }
+
+    /// Remove this subscriber from the bus, keeping the events already
+    /// queued. Returns `false` if it was already removed.
+    #[napi]
+    pub fn unsubscribe(&self) -> bool {
+        self.inner.unsubscribe()
+    }
+}
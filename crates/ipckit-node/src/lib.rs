@@ -0,0 +1,82 @@
+//! Node.js bindings for ipckit
+//!
+//! This crate builds a native addon with napi-rs, so Node and Electron
+//! frontends can talk to ipckit daemons without shelling out to the CLI or
+//! speaking the socket protocol by hand.
+//!
+//! Every blocking operation runs on the addon's thread pool and returns a
+//! promise, so the JavaScript event loop is never blocked.
+//!
+//! The bindings are organized into submodules:
+//! - `socket`: LocalSocketStream bindings
+//! - `channel`: IpcChannel bindings
+//! - `api`: ApiClient bindings for HTTP-over-Socket RESTful APIs
+//! - `event`: EventBus and EventSubscriber bindings for publish-subscribe events
+
+mod api;
+mod channel;
+mod event;
+mod socket;
+
+use ipckit::IpcError;
+use napi::{Error, Result, Status};
+
+/// Convert an ipckit error into a JavaScript error.
+///
+/// The error's `code` names the ipckit error kind, e.g. `Timeout` or
+/// `Closed`, so callers can branch on it without parsing messages.
+pub(crate) fn to_napi_error(err: IpcError) -> Error {
+    let status = match err {
+        IpcError::InvalidName(_) => Status::InvalidArg,
+        IpcError::Timeout => Status::Cancelled,
+        _ => Status::GenericFailure,
+    };
+    Error::new(status, format!("{}: {}", error_kind(&err), err))
+}
+
+/// Short name of an ipckit error kind.
+fn error_kind(err: &IpcError) -> &'static str {
+    match err {
+        IpcError::Io(_) => "Io",
+        IpcError::Closed => "Closed",
+        IpcError::InvalidName(_) => "InvalidName",
+        IpcError::AlreadyExists(_) => "AlreadyExists",
+        IpcError::NotFound(_) => "NotFound",
+        IpcError::PermissionDenied(_) => "PermissionDenied",
+        IpcError::Timeout => "Timeout",
+        IpcError::Serialization(_) => "Serialization",
+        IpcError::Deserialization(_) => "Deserialization",
+        IpcError::Platform(_) => "Platform",
+        IpcError::InvalidState(_) => "InvalidState",
+        IpcError::WouldBlock => "WouldBlock",
+        IpcError::Incompatible(_) => "Incompatible",
+        IpcError::BufferTooSmall { .. } => "BufferTooSmall",
+        IpcError::Other(_) => "Other",
+    }
+}
+
+/// Run a blocking ipckit call on the blocking thread pool.
+pub(crate) async fn blocking<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> ipckit::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Error::from_reason(e.to_string()))?
+        .map_err(to_napi_error)
+}
+
+/// Whether an error means the peer has gone away.
+pub(crate) fn is_end_of_stream(err: &IpcError) -> bool {
+    match err {
+        IpcError::Closed => true,
+        IpcError::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::UnexpectedEof
+                | std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::ConnectionReset
+        ),
+        _ => false,
+    }
+}
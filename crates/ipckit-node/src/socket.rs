@@ -0,0 +1,150 @@
+//! Node bindings for LocalSocketStream
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use ipckit::{IpcError, LocalSocketStream as RustLocalSocketStream};
+use napi::bindgen_prelude::Buffer;
+use napi::Result;
+use napi_derive::napi;
+use parking_lot::Mutex;
+
+use crate::{blocking, is_end_of_stream};
+
+type Half = Arc<Mutex<Option<RustLocalSocketStream>>>;
+
+/// Bidirectional local socket connection.
+///
+/// Uses Unix Domain Sockets on Unix and Named Pipes on Windows. Reads and
+/// writes use separate handles, so a pending `read` or `recvJson` does not
+/// hold up writes. JSON messages use the same length-prefixed framing as the
+/// Python `LocalSocketStream`.
+#[napi]
+pub struct LocalSocketStream {
+    name: String,
+    reader: Half,
+    writer: Half,
+}
+
+/// Run `f` on one half of an open stream.
+fn with<R>(
+    half: &Half,
+    f: impl FnOnce(&mut RustLocalSocketStream) -> std::io::Result<R>,
+) -> ipckit::Result<R> {
+    let mut guard = half.lock();
+    let stream = guard.as_mut().ok_or(IpcError::Closed)?;
+    Ok(f(stream)?)
+}
+
+#[napi]
+impl LocalSocketStream {
+    /// Connect to a local socket server.
+    #[napi]
+    pub async fn connect(name: String) -> Result<LocalSocketStream> {
+        blocking(move || {
+            let writer = RustLocalSocketStream::connect(&name)?;
+            let reader = writer.try_clone()?;
+            Ok(LocalSocketStream {
+                name,
+                reader: Arc::new(Mutex::new(Some(reader))),
+                writer: Arc::new(Mutex::new(Some(writer))),
+            })
+        })
+        .await
+    }
+
+    /// The socket name.
+    #[napi(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Whether the connection has been closed.
+    #[napi(getter)]
+    pub fn closed(&self) -> bool {
+        self.writer.lock().is_none()
+    }
+
+    /// Read up to `size` bytes. Resolves to an empty buffer at end of stream.
+    #[napi]
+    pub async fn read(&self, size: u32) -> Result<Buffer> {
+        let reader = self.reader.clone();
+        blocking(move || {
+            let mut buf = vec![0u8; size as usize];
+            let n = with(&reader, |stream| stream.read(&mut buf))?;
+            buf.truncate(n);
+            Ok(buf.into())
+        })
+        .await
+    }
+
+    /// Write all of `data`.
+    #[napi]
+    pub async fn write(&self, data: Buffer) -> Result<()> {
+        let writer = self.writer.clone();
+        let data = data.to_vec();
+        blocking(move || {
+            with(&writer, |stream| {
+                stream.write_all(&data)?;
+                stream.flush()
+            })
+        })
+        .await
+    }
+
+    /// Send a JSON value as one length-prefixed message.
+    #[napi]
+    pub async fn send_json(&self, value: serde_json::Value) -> Result<()> {
+        let writer = self.writer.clone();
+        blocking(move || {
+            let json_bytes =
+                serde_json::to_vec(&value).map_err(|e| IpcError::serialization(e.to_string()))?;
+            // Length prefix (4 bytes, big-endian)
+            let len_bytes = (json_bytes.len() as u32).to_be_bytes();
+            with(&writer, |stream| {
+                stream.write_all(&len_bytes)?;
+                stream.write_all(&json_bytes)?;
+                stream.flush()
+            })
+        })
+        .await
+    }
+
+    /// Receive one length-prefixed JSON message.
+    ///
+    /// Resolves to `undefined` once the peer has disconnected.
+    #[napi]
+    pub async fn recv_json(&self) -> Result<Option<serde_json::Value>> {
+        let reader = self.reader.clone();
+        blocking(move || {
+            let json_bytes = with(&reader, |stream| {
+                let mut len_bytes = [0u8; 4];
+                stream.read_exact(&mut len_bytes)?;
+                let mut json_bytes = vec![0u8; u32::from_be_bytes(len_bytes) as usize];
+                stream.read_exact(&mut json_bytes)?;
+                Ok(json_bytes)
+            });
+            match json_bytes {
+                Ok(json_bytes) => serde_json::from_slice(&json_bytes)
+                    .map(Some)
+                    .map_err(|e| IpcError::deserialization(e.to_string())),
+                Err(e) if is_end_of_stream(&e) => Ok(None),
+                Err(e) => Err(e),
+            }
+        })
+        .await
+    }
+
+    /// Close the connection, waking any pending read.
+    #[napi]
+    pub fn close(&self) {
+        if let Some(writer) = self.writer.lock().take() {
+            let _ = writer.shutdown();
+        }
+        // A pending read holds the reader; it sees end of stream and the
+        // handle is dropped on the next call
+        if let Some(mut reader) = self.reader.try_lock() {
+            reader.take();
+        }
+    }
+}
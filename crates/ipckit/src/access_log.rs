@@ -0,0 +1,359 @@
+//! Access Log - Structured per-request tracing for the API and socket servers
+//!
+//! [`LoggingMiddleware`] wraps every request an [`ApiServer`] serves, and
+//! every connection and message a [`SocketServer`] handles, in a `tracing`
+//! span carrying the connection id, method, path, status and latency. When
+//! the request finishes an `INFO` event (`WARN` for failures) is emitted
+//! inside the span, so any subscriber (fmt, JSON, OpenTelemetry) gets one
+//! access log line per request, and events logged by handlers are attributed
+//! to the request that caused them.
+//!
+//! | Span                | Fields                                                              |
+//! |---------------------|---------------------------------------------------------------------|
+//! | `api.request`       | `conn_id`, `method`, `path`, `status`, `latency_ms`, `body`, `trace_id` |
+//! | `socket.connection` | `conn_id`, `messages`, `duration_ms`                                |
+//! | `socket.message`    | `conn_id`, `method`, `outcome`, `latency_ms`, `trace_id`            |
+//!
+//! Request bodies are only recorded for one in every
+//! [`sample_bodies`](LoggingMiddleware::sample_bodies) requests, truncated to
+//! [`max_body_bytes`](LoggingMiddleware::max_body_bytes).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::{ApiServer, ApiServerConfig, LoggingMiddleware};
+//!
+//! let config = ApiServerConfig {
+//!     access_log: Some(LoggingMiddleware::new().sample_bodies(100)),
+//!     ..Default::default()
+//! };
+//! let server = ApiServer::new(config);
+//! ```
+//!
+//! [`ApiServer`]: crate::api_server::ApiServer
+//! [`SocketServer`]: crate::socket_server::SocketServer
+
+use crate::socket_server::{ConnectionId, Message, MessageType};
+use crate::trace_context::TraceContext;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::field::Empty;
+use tracing::Span;
+
+/// Default number of body bytes recorded for a sampled request.
+const DEFAULT_MAX_BODY_BYTES: usize = 1024;
+
+/// Access logging for [`ApiServer`](crate::api_server::ApiServer) and
+/// [`SocketServer`](crate::socket_server::SocketServer).
+///
+/// Enable it through `ApiServerConfig::access_log` or
+/// `SocketServerConfig::access_log`. Clones share the body sampling counter.
+#[derive(Debug, Clone)]
+pub struct LoggingMiddleware {
+    sample_every: u64,
+    max_body_bytes: usize,
+    requests: Arc<AtomicU64>,
+}
+
+impl Default for LoggingMiddleware {
+    fn default() -> Self {
+        Self {
+            sample_every: 0,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            requests: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl LoggingMiddleware {
+    /// Create access logging without body sampling.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the body of one in every `every` requests (0 disables).
+    pub fn sample_bodies(mut self, every: u64) -> Self {
+        self.sample_every = every;
+        self
+    }
+
+    /// Truncate sampled bodies to `max` bytes (default: 1024).
+    pub fn max_body_bytes(mut self, max: usize) -> Self {
+        self.max_body_bytes = max;
+        self
+    }
+
+    /// Open the span for an HTTP request (internal).
+    pub(crate) fn request(
+        &self,
+        conn_id: ConnectionId,
+        method: &str,
+        path: &str,
+        body: &[u8],
+    ) -> AccessSpan {
+        let span = tracing::info_span!(
+            "api.request",
+            conn_id,
+            method,
+            path,
+            status = Empty,
+            latency_ms = Empty,
+            body = Empty,
+            trace_id = Empty,
+        );
+        if let Some(body) = self.sample(body) {
+            span.record("body", body.as_str());
+        }
+        AccessSpan::new(span)
+    }
+
+    /// Open the span covering a whole socket connection (internal).
+    pub(crate) fn connection(&self, conn_id: ConnectionId) -> AccessSpan {
+        let span = tracing::info_span!(
+            "socket.connection",
+            conn_id,
+            messages = Empty,
+            duration_ms = Empty,
+        );
+        AccessSpan::new(span)
+    }
+
+    /// Open the span for one socket message (internal).
+    pub(crate) fn message(&self, conn_id: ConnectionId, msg: &Message) -> AccessSpan {
+        let method = match msg.method() {
+            Some(method) => method.to_string(),
+            None => format!("{:?}", msg.msg_type).to_lowercase(),
+        };
+        let span = tracing::info_span!(
+            "socket.message",
+            conn_id,
+            method = method.as_str(),
+            outcome = Empty,
+            latency_ms = Empty,
+            trace_id = Empty,
+        );
+        AccessSpan::new(span)
+    }
+
+    /// The body to record for this request, if it is sampled.
+    fn sample(&self, body: &[u8]) -> Option<String> {
+        if self.sample_every == 0 {
+            return None;
+        }
+        let n = self.requests.fetch_add(1, Ordering::Relaxed);
+        if !n.is_multiple_of(self.sample_every) {
+            return None;
+        }
+        let len = body.len().min(self.max_body_bytes);
+        Some(String::from_utf8_lossy(&body[..len]).into_owned())
+    }
+}
+
+/// An open access log span and when it started (internal).
+pub(crate) struct AccessSpan {
+    span: Span,
+    start: Instant,
+}
+
+impl AccessSpan {
+    fn new(span: Span) -> Self {
+        if let Some(trace) = TraceContext::current() {
+            trace.attach(&span);
+        }
+        Self {
+            span,
+            start: Instant::now(),
+        }
+    }
+
+    /// The span, for entering it while the request is handled.
+    pub(crate) fn span(&self) -> &Span {
+        &self.span
+    }
+
+    fn latency_ms(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+
+    /// Close an HTTP request span with the response status.
+    pub(crate) fn finish_request(self, status: u16) {
+        let latency_ms = self.latency_ms();
+        self.span.record("status", status);
+        self.span.record("latency_ms", latency_ms);
+        if status >= 500 {
+            tracing::warn!(parent: &self.span, status, latency_ms, "request failed");
+        } else {
+            tracing::info!(parent: &self.span, status, latency_ms, "request");
+        }
+    }
+
+    /// Close a socket message span with the handler's reply.
+    pub(crate) fn finish_message(self, reply: Result<Option<&Message>, &crate::IpcError>) {
+        let latency_ms = self.latency_ms();
+        let outcome = match reply {
+            Ok(Some(msg)) if msg.msg_type == MessageType::Error => "error",
+            Ok(Some(_)) => "reply",
+            Ok(None) => "none",
+            Err(_) => "failed",
+        };
+        self.span.record("outcome", outcome);
+        self.span.record("latency_ms", latency_ms);
+        match reply {
+            Err(e) => {
+                tracing::warn!(parent: &self.span, outcome, latency_ms, error = %e, "message failed")
+            }
+            Ok(_) => tracing::info!(parent: &self.span, outcome, latency_ms, "message"),
+        }
+    }
+
+    /// Close a connection span with the number of messages handled.
+    pub(crate) fn finish_connection(self, messages: u64) {
+        let duration_ms = self.latency_ms();
+        self.span.record("messages", messages);
+        self.span.record("duration_ms", duration_ms);
+        tracing::info!(parent: &self.span, messages, duration_ms, "connection closed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    type Fields = HashMap<String, String>;
+
+    /// Records span fields and event messages.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Recorded>);
+
+    #[derive(Default)]
+    struct Recorded {
+        next_id: AtomicU64,
+        spans: Mutex<HashMap<u64, (&'static str, Fields)>>,
+        events: Mutex<Vec<String>>,
+    }
+
+    struct Visitor<'a>(&'a mut Fields);
+
+    impl Visit for Visitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let id = self.0.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+            let mut fields = HashMap::new();
+            span.record(&mut Visitor(&mut fields));
+            self.0
+                .spans
+                .lock()
+                .unwrap()
+                .insert(id, (span.metadata().name(), fields));
+            Id::from_u64(id)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            if let Some((_, fields)) = self.0.spans.lock().unwrap().get_mut(&span.into_u64()) {
+                values.record(&mut Visitor(fields));
+            }
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = HashMap::new();
+            event.record(&mut Visitor(&mut fields));
+            self.0
+                .events
+                .lock()
+                .unwrap()
+                .push(fields.remove("message").unwrap_or_default());
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    impl Recorder {
+        fn span(&self, name: &str) -> Fields {
+            let spans = self.0.spans.lock().unwrap();
+            let mut matching: Vec<_> = spans.iter().filter(|(_, (n, _))| *n == name).collect();
+            matching.sort_by_key(|(id, _)| **id);
+            matching.last().map(|(_, (_, f))| f.clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_request_span_fields() {
+        let recorder = Recorder::default();
+        let logging = LoggingMiddleware::new().sample_bodies(2).max_body_bytes(5);
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let access = logging.request(3, "POST", "/v1/tasks", b"{\"name\":\"x\"}");
+            access.finish_request(201);
+        });
+        let fields = recorder.span("api.request");
+        assert_eq!(fields["conn_id"], "3");
+        assert_eq!(fields["method"], "POST");
+        assert_eq!(fields["path"], "/v1/tasks");
+        assert_eq!(fields["status"], "201");
+        assert!(fields["latency_ms"].parse::<f64>().is_ok());
+        assert_eq!(fields["body"], "{\"nam");
+
+        // Only one in every two bodies is sampled
+        tracing::subscriber::with_default(recorder.clone(), || {
+            logging
+                .request(3, "GET", "/v1/tasks", b"{}")
+                .finish_request(500);
+        });
+        let fields = recorder.span("api.request");
+        assert_eq!(fields["status"], "500");
+        assert!(!fields.contains_key("body"));
+        assert_eq!(
+            *recorder.0.events.lock().unwrap(),
+            ["request", "request failed"]
+        );
+    }
+
+    #[test]
+    fn test_socket_spans() {
+        let recorder = Recorder::default();
+        let logging = LoggingMiddleware::new();
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let connection = logging.connection(9);
+            let request = Message::request("ping", serde_json::json!({}));
+            logging
+                .message(9, &request)
+                .finish_message(Ok(Some(&Message::pong())));
+            logging
+                .message(9, &Message::text("hi"))
+                .finish_message(Ok(None));
+            connection.finish_connection(2);
+        });
+
+        let message = recorder.span("socket.message");
+        assert_eq!(message["method"], "text");
+        assert_eq!(message["outcome"], "none");
+        let connection = recorder.span("socket.connection");
+        assert_eq!(connection["conn_id"], "9");
+        assert_eq!(connection["messages"], "2");
+    }
+}
@@ -6,11 +6,27 @@
 //!
 //! ## Features
 //!
-//! - Lightweight HTTP/1.1 parsing (no heavy framework dependencies)
+//! - Strict HTTP/1.1 request parsing with `httparse` (no heavy framework
+//!   dependencies), including folded headers and inline chunked bodies
 //! - RESTful routing with path parameters
 //! - JSON request/response bodies
 //! - Streaming responses (SSE)
-//! - Middleware support
+//! - Middleware support, with typed request [`Extensions`] for passing data
+//!   such as user identity or request IDs on to handlers
+//! - Versioned route scopes with deprecation headers
+//! - Route groups with their own middleware (e.g. auth on admin routes only)
+//! - JSON or MessagePack bodies, negotiated via `Accept`/`Content-Type`
+//! - Request body (413) and header (431) size limits, and per-connection
+//!   rate limits (429)
+//! - Streamed request bodies for uploads too large to buffer ([`BodyReader`])
+//! - `Idempotency-Key` handling, so retried requests run once
+//!   ([`IdempotencyMiddleware`])
+//! - Handlers returning `Result<impl IntoResponse, ApiError>` or [`Json`]
+//!   values, with [`IpcError`]s mapped to status codes ([`IntoResponse`])
+//! - Bearer token authentication ([`ApiServerConfig::auth_token`]) and
+//!   settings loaded from an ipckit config file ([`ApiServerConfig::from_file`])
+//! - Graceful shutdown: in-flight requests finish while new ones get 503
+//!   ([`ApiServer::shutdown_state`])
 //!
 //! ## Example
 //!
@@ -31,16 +47,116 @@
 //!
 //! server.run()?;
 //! ```
+//!
+//! ## Streaming uploads
+//!
+//! [`ApiClient::post_stream`] sends a body of any size in chunks, each in
+//! its own message, instead of one buffered frame. The handler reads it as
+//! it arrives through [`Request::body_reader`], which also works for
+//! ordinary buffered bodies:
+//!
+//! ```rust,ignore
+//! router.post("/v1/archives", |mut req| {
+//!     let mut file = std::fs::File::create("upload.tar")?;
+//!     let written = std::io::copy(&mut req.body_reader(), &mut file)?;
+//!     Response::created(json!({"bytes": written}))
+//! });
+//!
+//! client.post_stream("/v1/archives", std::fs::File::open("big.tar")?)?;
+//! ```
+//!
+//! ## Versioning
+//!
+//! Group routes under a version prefix with [`Router::scope`]. Old versions
+//! keep working while announcing their replacement:
+//!
+//! ```rust,ignore
+//! use ipckit::{Deprecation, Response};
+//!
+//! let mut router = server.router();
+//! router
+//!     .scope("/v1")
+//!     .deprecated(Deprecation::new().sunset("Sat, 01 May 2027 00:00:00 GMT").successor("/v2"))
+//!     .get("/status", |_req| Response::ok(json!({"ok": true})));
+//! router
+//!     .scope("/v2")
+//!     .get("/status", |_req| Response::ok(json!({"healthy": true})));
+//! ```
+//!
+//! ## Route groups
+//!
+//! Middleware added with [`Router::middleware`] runs for every route.
+//! [`Router::group`] scopes middleware to the routes registered inside it,
+//! after the global middleware:
+//!
+//! ```rust,ignore
+//! router.get("/health", |_req| Response::ok(json!({"ok": true})));
+//! router.group("/v1/admin", |g| {
+//!     g.middleware(|req, next| match req.header("authorization") {
+//!         Some("Bearer secret") => next(req),
+//!         _ => Response::new(401),
+//!     });
+//!     g.delete("/tasks/{id}", cancel_task);
+//! });
+//! ```
 
+use crate::access_log::LoggingMiddleware;
+use crate::command_handler::{command_params, CommandHandler};
+use crate::error::ErrorCode;
+use crate::event_stream::{EventBus, EventFilter, EventId};
+use crate::graceful::ShutdownState;
+use crate::health::Health;
+use crate::idempotency::{IdempotencyMiddleware, IDEMPOTENCY_HEADER};
+use crate::msgpack;
+use crate::runtime_config::{RateLimit, RateLimiter, RuntimeConfig};
 use crate::socket_server::{
-    Connection, ConnectionHandler, Message, SocketClient, SocketServer, SocketServerConfig,
+    binary_frame_prefix, Connection, ConnectionHandler, ConnectionId, ConnectionRegistry,
+    FrameVerdict, Message, SocketClient, SocketServer, SocketServerConfig,
 };
+use crate::task_manager::{CancellationToken, TaskBuilder, TaskFilter, TaskHandle, TaskManager};
+use crate::trace_context::{TraceContext, TRACE_HEADER};
+use crate::transport::{LocalSocketTransport, Transport};
+use crate::validation::ValidationErrors;
 use crate::IpcError;
 use parking_lot::RwLock;
+use serde::Serialize;
 use serde_json::Value as JsonValue;
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Read};
+use std::io::Read;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tracing::Span;
+
+/// How often an in-flight request checks whether its client went away.
+const PEER_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Upper bound on how long a command long-poll may hold a connection.
+const MAX_COMMAND_WAIT: Duration = Duration::from_secs(30);
+
+/// Default for [`ApiServerConfig::max_body_size`].
+const DEFAULT_MAX_BODY_SIZE: usize = 8 * 1024 * 1024;
+
+/// Default for [`ApiServerConfig::max_headers`].
+const DEFAULT_MAX_HEADERS: usize = 64;
+
+/// Default for [`ApiServerConfig::max_header_size`].
+const DEFAULT_MAX_HEADER_SIZE: usize = 16 * 1024;
+
+/// Size of the body chunks sent by [`ApiClient::request_stream`].
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Body chunks buffered between a connection and a handler that reads them.
+const STREAM_BUFFER_CHUNKS: usize = 16;
+
+/// Conventional path of the route index, see [`Router::route_index`].
+pub const ROUTES_PATH: &str = "/v1/_routes";
+
+/// Path of the runtime config admin endpoint.
+pub const ADMIN_CONFIG_PATH: &str = "/v1/_admin/config";
 
 /// HTTP method.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -83,6 +199,87 @@ impl Method {
     }
 }
 
+/// Typed values attached to a request or connection, keyed by their type.
+///
+/// Middleware inserts values such as the authenticated user or a request ID,
+/// and handlers further down the chain read them back with
+/// [`Request::extension`]. Socket connections carry one too, see
+/// [`Connection::state`]. Each type holds at most one value, so wrap plain
+/// types (`String`, `u64`) in a newtype to keep them apart.
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a value, returning the previous value of the same type.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok().map(|old| *old))
+    }
+
+    /// Get the value of type `T`.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Get a mutable reference to the value of type `T`.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Remove and return the value of type `T`.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|value| *value))
+    }
+
+    /// Check whether a value of type `T` is present.
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Get the number of values.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Check whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Remove all values.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Move every value of `other` into this map, replacing values of the
+    /// same type.
+    pub fn extend(&mut self, other: Extensions) {
+        self.map.extend(other.map);
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
 /// HTTP request.
 #[derive(Debug)]
 pub struct Request {
@@ -94,12 +291,19 @@ pub struct Request {
     pub query: HashMap<String, String>,
     /// Request headers
     pub headers: HashMap<String, String>,
-    /// Request body (parsed as JSON if Content-Type is application/json)
+    /// Request body (parsed if Content-Type is JSON or MessagePack)
     pub body: Option<JsonValue>,
     /// Raw body bytes
     pub raw_body: Vec<u8>,
     /// Path parameters (extracted from route matching)
     pub params: HashMap<String, String>,
+    /// Cancelled when the client aborts the request or closes the connection
+    pub cancel_token: CancellationToken,
+    /// Values attached by middleware for downstream handlers
+    pub extensions: Extensions,
+    /// Body still arriving from the client, for `Transfer-Encoding: chunked`
+    /// requests; `raw_body` is then empty
+    pub body_stream: Option<BodyReader>,
 }
 
 impl Request {
@@ -113,9 +317,18 @@ impl Request {
             body: None,
             raw_body: Vec::new(),
             params: HashMap::new(),
+            cancel_token: CancellationToken::new(),
+            extensions: Extensions::new(),
+            body_stream: None,
         }
     }
 
+    /// Deserialize the JSON body; a missing body reads as `null`.
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> crate::Result<T> {
+        serde_json::from_value(self.body.clone().unwrap_or(JsonValue::Null))
+            .map_err(|e| IpcError::Deserialization(e.to_string()))
+    }
+
     /// Get a query parameter.
     pub fn query_param(&self, name: &str) -> Option<&str> {
         self.query.get(name).map(|s| s.as_str())
@@ -131,6 +344,31 @@ impl Request {
         self.headers.get(&name.to_lowercase()).map(|s| s.as_str())
     }
 
+    /// Get a value attached by middleware.
+    ///
+    /// ```rust,ignore
+    /// #[derive(Clone)]
+    /// struct User(String);
+    ///
+    /// router.middleware(|mut req, next| {
+    ///     req.insert_extension(User("alice".into()));
+    ///     next(req)
+    /// });
+    /// router.get("/whoami", |req| {
+    ///     let user = req.extension::<User>().unwrap();
+    ///     Response::ok(json!({"user": user.0}))
+    /// });
+    /// ```
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get()
+    }
+
+    /// Attach a value for handlers further down the chain, returning the
+    /// previous value of the same type.
+    pub fn insert_extension<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.extensions.insert(value)
+    }
+
     /// Get the Content-Type header.
     pub fn content_type(&self) -> Option<&str> {
         self.header("content-type")
@@ -143,19 +381,76 @@ impl Request {
             .unwrap_or(true)
     }
 
+    /// Get the body format the client prefers, per its `Accept` header.
+    pub fn preferred_format(&self) -> ContentFormat {
+        ContentFormat::negotiate(self.header("accept"))
+    }
+
     /// Parse the request from raw HTTP data.
     pub fn parse(data: &[u8]) -> Result<Self, ParseError> {
-        let mut reader = BufReader::new(data);
-        let mut first_line = String::new();
-        reader.read_line(&mut first_line)?;
-
-        let parts: Vec<&str> = first_line.split_whitespace().collect();
-        if parts.len() < 2 {
-            return Err(ParseError::InvalidRequestLine);
+        Self::parse_with_limits(
+            data,
+            &RequestLimits {
+                max_body_size: usize::MAX,
+                ..RequestLimits::default()
+            },
+        )
+    }
+
+    /// Parse the request, rejecting a `Content-Length` above `max_body_size`
+    /// before any of the body is buffered.
+    pub fn parse_with_limit(data: &[u8], max_body_size: usize) -> Result<Self, ParseError> {
+        Self::parse_with_limits(
+            data,
+            &RequestLimits {
+                max_body_size,
+                ..RequestLimits::default()
+            },
+        )
+    }
+
+    /// Parse the request, enforcing `limits` on its head and body.
+    ///
+    /// Folded header lines are joined to the header they continue, repeated
+    /// headers are combined with `", "`, and a `Transfer-Encoding: chunked`
+    /// body sent along with the head is decoded. A chunked request without
+    /// any body bytes is left for the server to stream (see
+    /// [`body_reader`](Self::body_reader)).
+    pub fn parse_with_limits(data: &[u8], limits: &RequestLimits) -> Result<Self, ParseError> {
+        let head_end = find_head_end(data);
+        let head_size = head_end.unwrap_or(data.len());
+        if head_size > limits.max_header_size {
+            return Err(ParseError::HeadersTooLarge {
+                size: head_size,
+                limit: limits.max_header_size,
+            });
+        }
+        let head_end = head_end.ok_or(ParseError::Incomplete)?;
+        let head = unfold_headers(&data[..head_end]);
+
+        let mut header_buf = vec![httparse::EMPTY_HEADER; limits.max_headers];
+        let mut parsed = httparse::Request::new(&mut header_buf);
+        match parsed.parse(&head) {
+            Ok(httparse::Status::Complete(_)) => {}
+            Ok(httparse::Status::Partial) => return Err(ParseError::Incomplete),
+            Err(httparse::Error::TooManyHeaders) => {
+                return Err(ParseError::TooManyHeaders {
+                    limit: limits.max_headers,
+                })
+            }
+            Err(
+                e @ (httparse::Error::HeaderName
+                | httparse::Error::HeaderValue
+                | httparse::Error::NewLine),
+            ) => return Err(ParseError::InvalidHeader(e.to_string())),
+            Err(_) => return Err(ParseError::InvalidRequestLine),
         }
 
-        let method = Method::parse(parts[0]).ok_or(ParseError::InvalidMethod)?;
-        let full_path = parts[1];
+        let method = parsed
+            .method
+            .and_then(Method::parse)
+            .ok_or(ParseError::InvalidMethod)?;
+        let full_path = parsed.path.ok_or(ParseError::InvalidRequestLine)?;
 
         // Parse path and query string
         let (path, query) = if let Some(idx) = full_path.find('?') {
@@ -166,41 +461,64 @@ impl Request {
             (full_path.to_string(), HashMap::new())
         };
 
-        // Parse headers
-        let mut headers = HashMap::new();
-        loop {
-            let mut line = String::new();
-            reader.read_line(&mut line)?;
-            let line = line.trim();
-            if line.is_empty() {
-                break;
-            }
-            if let Some(idx) = line.find(':') {
-                let key = line[..idx].trim().to_lowercase();
-                let value = line[idx + 1..].trim().to_string();
-                headers.insert(key, value);
-            }
+        // Header names are case-insensitive; repeated ones are combined
+        let mut headers: HashMap<String, String> = HashMap::new();
+        for header in parsed.headers.iter() {
+            let key = header.name.to_ascii_lowercase();
+            let value = String::from_utf8_lossy(header.value).trim().to_string();
+            headers
+                .entry(key)
+                .and_modify(|existing| {
+                    existing.push_str(", ");
+                    existing.push_str(&value);
+                })
+                .or_insert(value);
         }
 
         // Parse body
-        let mut raw_body = Vec::new();
-        if let Some(len_str) = headers.get("content-length") {
-            if let Ok(len) = len_str.parse::<usize>() {
-                raw_body.resize(len, 0);
-                reader.read_exact(&mut raw_body)?;
+        let rest = &data[head_end..];
+        let chunked = headers
+            .get("transfer-encoding")
+            .is_some_and(|v| v.eq_ignore_ascii_case("chunked"));
+        let raw_body = match (chunked, headers.get("content-length")) {
+            // Ambiguous framing is how requests get smuggled
+            (true, Some(_)) => {
+                return Err(ParseError::InvalidHeader(
+                    "both Content-Length and Transfer-Encoding are set".to_string(),
+                ))
             }
-        }
+            (true, None) if rest.is_empty() => Vec::new(),
+            (true, None) => {
+                let body = decode_chunked(rest, limits.max_body_size)?;
+                // The body is complete, nothing more to stream
+                headers.remove("transfer-encoding");
+                headers.insert("content-length".to_string(), body.len().to_string());
+                body
+            }
+            (false, Some(len_str)) => {
+                let len = len_str.parse::<usize>().map_err(|_| {
+                    ParseError::InvalidHeader(format!("invalid Content-Length: {}", len_str))
+                })?;
+                if len > limits.max_body_size {
+                    return Err(ParseError::BodyTooLarge {
+                        size: len,
+                        limit: limits.max_body_size,
+                    });
+                }
+                rest.get(..len).ok_or(ParseError::Incomplete)?.to_vec()
+            }
+            (false, None) => Vec::new(),
+        };
 
-        // Try to parse body as JSON
+        // Try to parse body as JSON or MessagePack
         let body = if !raw_body.is_empty() {
-            let content_type = headers.get("content-type").map(|s| s.as_str());
-            if content_type
-                .map(|s| s.contains("application/json"))
-                .unwrap_or(false)
+            match headers
+                .get("content-type")
+                .and_then(|s| ContentFormat::from_content_type(s))
             {
-                serde_json::from_slice(&raw_body).ok()
-            } else {
-                None
+                Some(ContentFormat::Json) => serde_json::from_slice(&raw_body).ok(),
+                Some(ContentFormat::MsgPack) => msgpack::from_slice(&raw_body).ok(),
+                None => None,
             }
         } else {
             None
@@ -214,8 +532,179 @@ impl Request {
             body,
             raw_body,
             params: HashMap::new(),
+            cancel_token: CancellationToken::new(),
+            extensions: Extensions::new(),
+            body_stream: None,
         })
     }
+
+    /// Check if the body is streamed rather than buffered in `raw_body`.
+    pub fn is_streaming(&self) -> bool {
+        self.body_stream.is_some()
+    }
+
+    /// Take the body as a reader.
+    ///
+    /// Streams a chunked body as it arrives; a buffered body is read from
+    /// `raw_body`, which is left empty.
+    pub fn body_reader(&mut self) -> BodyReader {
+        self.body_stream
+            .take()
+            .unwrap_or_else(|| BodyReader::buffered(std::mem::take(&mut self.raw_body)))
+    }
+
+    /// Whether the client announced a chunked body (internal)
+    fn is_chunked(&self) -> bool {
+        self.header("transfer-encoding")
+            .is_some_and(|v| v.eq_ignore_ascii_case("chunked"))
+    }
+}
+
+/// Feeds a streamed body to its [`BodyReader`].
+type BodySender = crossbeam_channel::Sender<std::io::Result<Vec<u8>>>;
+
+/// Reads a request body, see [`Request::body_reader`].
+///
+/// For a streamed body, reads block until the client sends the next chunk
+/// and fail with `UnexpectedEof` if the client disconnects first.
+pub struct BodyReader {
+    chunks: Option<crossbeam_channel::Receiver<std::io::Result<Vec<u8>>>>,
+    current: Vec<u8>,
+    pos: usize,
+}
+
+impl BodyReader {
+    fn buffered(body: Vec<u8>) -> Self {
+        Self {
+            chunks: None,
+            current: body,
+            pos: 0,
+        }
+    }
+
+    fn streamed(chunks: crossbeam_channel::Receiver<std::io::Result<Vec<u8>>>) -> Self {
+        Self {
+            chunks: Some(chunks),
+            current: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.current.len() {
+            // The sender is dropped once the last chunk has arrived
+            match self.chunks.as_ref().map(|rx| rx.recv()) {
+                Some(Ok(chunk)) => {
+                    self.current = chunk?;
+                    self.pos = 0;
+                }
+                Some(Err(_)) | None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.current.len() - self.pos);
+        buf[..n].copy_from_slice(&self.current[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl std::fmt::Debug for BodyReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BodyReader")
+            .field("streamed", &self.chunks.is_some())
+            .finish()
+    }
+}
+
+/// Encoding of structured request and response bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentFormat {
+    /// `application/json`
+    #[default]
+    Json,
+    /// `application/msgpack`
+    MsgPack,
+}
+
+impl ContentFormat {
+    /// Get the MIME type.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ContentFormat::Json => "application/json",
+            ContentFormat::MsgPack => msgpack::CONTENT_TYPE,
+        }
+    }
+
+    /// Recognize a `Content-Type` header value.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        match mime.to_ascii_lowercase().as_str() {
+            "application/json" => Some(ContentFormat::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(ContentFormat::MsgPack)
+            }
+            _ => None,
+        }
+    }
+
+    /// Pick the format an `Accept` header prefers.
+    ///
+    /// MessagePack is only chosen when the client names it with a higher
+    /// quality than JSON; a missing header or wildcard means JSON, so older
+    /// clients are unaffected.
+    pub fn negotiate(accept: Option<&str>) -> Self {
+        let Some(accept) = accept else {
+            return ContentFormat::Json;
+        };
+
+        let mut json_q = 0.0f32;
+        let mut msgpack_q = 0.0f32;
+        for range in accept.split(',') {
+            let mut parts = range.split(';');
+            let mime = parts.next().unwrap_or_default().trim();
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            match mime {
+                "*/*" | "application/*" => json_q = json_q.max(q),
+                other => match Self::from_content_type(other) {
+                    Some(ContentFormat::Json) => json_q = json_q.max(q),
+                    Some(ContentFormat::MsgPack) => msgpack_q = msgpack_q.max(q),
+                    None => {}
+                },
+            }
+        }
+
+        if msgpack_q > json_q {
+            ContentFormat::MsgPack
+        } else {
+            ContentFormat::Json
+        }
+    }
+}
+
+/// Size limits enforced by [`Request::parse_with_limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// Largest body, in bytes (default: 8 MiB)
+    pub max_body_size: usize,
+    /// Most headers in one request (default: 64)
+    pub max_headers: usize,
+    /// Largest request line plus headers, in bytes (default: 16 KiB)
+    pub max_header_size: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            max_headers: DEFAULT_MAX_HEADERS,
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+        }
+    }
 }
 
 /// Parse error.
@@ -223,9 +712,37 @@ impl Request {
 pub enum ParseError {
     InvalidRequestLine,
     InvalidMethod,
+    /// A malformed header, or a malformed or ambiguous body framing header
+    InvalidHeader(String),
+    /// A malformed chunked body
+    InvalidBody(String),
+    /// The head or the announced body is cut short
+    Incomplete,
+    BodyTooLarge {
+        size: usize,
+        limit: usize,
+    },
+    HeadersTooLarge {
+        size: usize,
+        limit: usize,
+    },
+    TooManyHeaders {
+        limit: usize,
+    },
     IoError(std::io::Error),
 }
 
+impl ParseError {
+    /// Get the HTTP status a request failing with this error is answered with.
+    pub fn status(&self) -> u16 {
+        match self {
+            ParseError::BodyTooLarge { .. } => 413,
+            ParseError::HeadersTooLarge { .. } | ParseError::TooManyHeaders { .. } => 431,
+            _ => 400,
+        }
+    }
+}
+
 impl From<std::io::Error> for ParseError {
     fn from(e: std::io::Error) -> Self {
         ParseError::IoError(e)
@@ -237,6 +754,22 @@ impl std::fmt::Display for ParseError {
         match self {
             ParseError::InvalidRequestLine => write!(f, "Invalid request line"),
             ParseError::InvalidMethod => write!(f, "Invalid HTTP method"),
+            ParseError::InvalidHeader(e) => write!(f, "Invalid header: {}", e),
+            ParseError::InvalidBody(e) => write!(f, "Invalid chunked body: {}", e),
+            ParseError::Incomplete => write!(f, "Incomplete request"),
+            ParseError::BodyTooLarge { size, limit } => {
+                write!(f, "Request body of {} bytes exceeds {} bytes", size, limit)
+            }
+            ParseError::HeadersTooLarge { size, limit } => {
+                write!(
+                    f,
+                    "Request headers of {} bytes exceed {} bytes",
+                    size, limit
+                )
+            }
+            ParseError::TooManyHeaders { limit } => {
+                write!(f, "Request has more than {} headers", limit)
+            }
             ParseError::IoError(e) => write!(f, "IO error: {}", e),
         }
     }
@@ -244,6 +777,90 @@ impl std::fmt::Display for ParseError {
 
 impl std::error::Error for ParseError {}
 
+/// Offset just past the blank line ending a request head, accepting bare
+/// `\n` line endings.
+fn find_head_end(data: &[u8]) -> Option<usize> {
+    let mut line_start = 0;
+    for (i, &byte) in data.iter().enumerate() {
+        if byte == b'\n' {
+            let line = &data[line_start..i];
+            if line.is_empty() || line == b"\r" {
+                return Some(i + 1);
+            }
+            line_start = i + 1;
+        }
+    }
+    None
+}
+
+/// Join obsolete folded header lines (RFC 9112 §5.2) to the line they
+/// continue, replacing the fold with a space.
+fn unfold_headers(head: &[u8]) -> Cow<'_, [u8]> {
+    let folded = head
+        .windows(2)
+        .any(|w| w[0] == b'\n' && (w[1] == b' ' || w[1] == b'\t'));
+    if !folded {
+        return Cow::Borrowed(head);
+    }
+
+    let mut out = Vec::with_capacity(head.len());
+    let mut i = 0;
+    while i < head.len() {
+        let fold_len = match &head[i..] {
+            [b'\r', b'\n', b' ' | b'\t', ..] => 3,
+            [b'\n', b' ' | b'\t', ..] => 2,
+            _ => 0,
+        };
+        if fold_len == 0 {
+            out.push(head[i]);
+            i += 1;
+            continue;
+        }
+        i += fold_len;
+        while i < head.len() && (head[i] == b' ' || head[i] == b'\t') {
+            i += 1;
+        }
+        out.push(b' ');
+    }
+    Cow::Owned(out)
+}
+
+/// Decode a complete `Transfer-Encoding: chunked` body of at most `limit`
+/// bytes.
+fn decode_chunked(mut data: &[u8], limit: usize) -> Result<Vec<u8>, ParseError> {
+    let mut body = Vec::new();
+    loop {
+        let (start, size) = match httparse::parse_chunk_size(data) {
+            Ok(httparse::Status::Complete(chunk)) => chunk,
+            Ok(httparse::Status::Partial) => return Err(ParseError::Incomplete),
+            Err(_) => return Err(ParseError::InvalidBody("bad chunk size".to_string())),
+        };
+        let size = usize::try_from(size).unwrap_or(usize::MAX);
+        if size == 0 {
+            // Trailers are ignored
+            return Ok(body);
+        }
+        if body.len().saturating_add(size) > limit {
+            return Err(ParseError::BodyTooLarge {
+                size: body.len().saturating_add(size),
+                limit,
+            });
+        }
+        data = &data[start..];
+        let chunk = data.get(..size).ok_or(ParseError::Incomplete)?;
+        body.extend_from_slice(chunk);
+        data = match &data[size..] {
+            [b'\r', b'\n', rest @ ..] | [b'\n', rest @ ..] => rest,
+            [] => return Err(ParseError::Incomplete),
+            _ => {
+                return Err(ParseError::InvalidBody(
+                    "chunk data longer than its size".to_string(),
+                ))
+            }
+        };
+    }
+}
+
 fn parse_query_string(query: &str) -> HashMap<String, String> {
     let mut params = HashMap::new();
     for pair in query.split('&') {
@@ -258,6 +875,19 @@ fn parse_query_string(query: &str) -> HashMap<String, String> {
     params
 }
 
+fn urlencoding_encode(s: &str) -> String {
+    let mut result = String::new();
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                result.push(byte as char)
+            }
+            _ => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    result
+}
+
 fn urlencoding_decode(s: &str) -> String {
     let mut result = String::new();
     let mut chars = s.chars().peekable();
@@ -280,7 +910,7 @@ fn urlencoding_decode(s: &str) -> String {
 }
 
 /// HTTP response.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Response {
     /// HTTP status code
     pub status: u16,
@@ -293,7 +923,7 @@ pub struct Response {
 }
 
 /// Response body type.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ResponseBody {
     /// JSON response
     Json(JsonValue),
@@ -386,6 +1016,37 @@ impl Response {
         resp
     }
 
+    /// Create a 413 Payload Too Large response.
+    pub fn payload_too_large(message: &str) -> Self {
+        let mut resp = Self::new(413);
+        resp.headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+        resp.body = ResponseBody::Json(serde_json::json!({
+            "error": "Payload Too Large",
+            "message": message
+        }));
+        resp
+    }
+
+    /// Create a 431 Request Header Fields Too Large response.
+    pub fn request_header_fields_too_large(message: &str) -> Self {
+        let mut resp = Self::new(431);
+        resp.headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+        resp.body = ResponseBody::Json(serde_json::json!({
+            "error": "Request Header Fields Too Large",
+            "message": message
+        }));
+        resp
+    }
+
+    /// Create a 429 Too Many Requests response.
+    pub fn too_many_requests() -> Self {
+        Self::new(429)
+            .header("Retry-After", "1")
+            .json(serde_json::json!({"error": "Rate limit exceeded"}))
+    }
+
     /// Create a 500 Internal Server Error response.
     pub fn internal_error(message: &str) -> Self {
         let mut resp = Self::new(500);
@@ -398,6 +1059,30 @@ impl Response {
         resp
     }
 
+    /// Create a 503 Service Unavailable response.
+    pub fn service_unavailable(message: &str) -> Self {
+        let mut resp = Self::new(503);
+        resp.headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+        resp.body = ResponseBody::Json(serde_json::json!({
+            "error": "Service Unavailable",
+            "message": message
+        }));
+        resp
+    }
+
+    /// Create an error response from an [`IpcError`].
+    ///
+    /// The status code is derived from the error code, and the body carries
+    /// the error's wire form (`code`, `kind`, `message`, `retryable`) next to
+    /// the usual `error` reason phrase.
+    pub fn from_error(err: &IpcError) -> Self {
+        let status = error_status(err.code());
+        let mut body = err.to_json();
+        body["error"] = JsonValue::from(status_message(status));
+        Self::new(status).json(body)
+    }
+
     /// Set a header.
     pub fn header(mut self, key: &str, value: &str) -> Self {
         self.headers.insert(key.to_string(), value.to_string());
@@ -428,6 +1113,17 @@ impl Response {
         self
     }
 
+    /// Re-encode a JSON body in `format`; other bodies are left as they are.
+    pub fn encode_as(mut self, format: ContentFormat) -> Self {
+        if format == ContentFormat::MsgPack {
+            if let ResponseBody::Json(ref value) = self.body {
+                let bytes = msgpack::to_vec(value);
+                self = self.bytes(bytes, format.content_type());
+            }
+        }
+        self
+    }
+
     /// Convert response to HTTP bytes.
     pub fn to_bytes(&self) -> Vec<u8> {
         let body_bytes = match &self.body {
@@ -454,64 +1150,754 @@ impl Response {
     }
 }
 
-fn status_message(status: u16) -> &'static str {
-    match status {
-        200 => "OK",
-        201 => "Created",
-        204 => "No Content",
-        400 => "Bad Request",
-        401 => "Unauthorized",
-        403 => "Forbidden",
-        404 => "Not Found",
-        405 => "Method Not Allowed",
-        500 => "Internal Server Error",
-        502 => "Bad Gateway",
-        503 => "Service Unavailable",
-        _ => "Unknown",
+impl From<IpcError> for Response {
+    fn from(err: IpcError) -> Self {
+        Self::from_error(&err)
     }
 }
 
-/// Path segment for pattern matching.
-#[derive(Debug, Clone)]
-enum PathSegment {
-    /// Static path segment
-    Static(String),
-    /// Parameter path segment {:name}
-    Param(String),
-    /// Wildcard {*rest}
-    Wildcard(String),
+/// HTTP status an error code is answered with.
+fn error_status(code: ErrorCode) -> u16 {
+    match code {
+        ErrorCode::InvalidName
+        | ErrorCode::BufferTooSmall
+        | ErrorCode::Serialization
+        | ErrorCode::Deserialization => 400,
+        ErrorCode::PermissionDenied => 403,
+        ErrorCode::NotFound => 404,
+        ErrorCode::AlreadyExists | ErrorCode::InvalidState | ErrorCode::Incompatible => 409,
+        ErrorCode::Closed | ErrorCode::WouldBlock => 503,
+        ErrorCode::Timeout => 504,
+        ErrorCode::Io | ErrorCode::Platform | ErrorCode::Other => 500,
+    }
 }
 
-/// Path pattern for route matching.
-#[derive(Debug, Clone)]
-pub struct PathPattern {
-    segments: Vec<PathSegment>,
-    #[allow(dead_code)]
-    original: String,
+/// A value a route handler can return.
+///
+/// Besides [`Response`], handlers may return `Result<T, E>` where both sides
+/// implement `IntoResponse` (typically `Result<Json<T>, ApiError>`), so `?`
+/// turns failures into error responses. [`IpcError`]s map to a status code
+/// the same way as [`Response::from_error`].
+///
+/// ```rust,ignore
+/// router.get("/v1/tasks/{id}", move |req| -> Result<Json<TaskInfo>, ApiError> {
+///     let id = req.path_param("id").unwrap_or_default();
+///     let task = manager.get(id).ok_or_else(|| ApiError::not_found("no such task"))?;
+///     Ok(Json(task))
+/// });
+/// ```
+pub trait IntoResponse {
+    /// Convert into a response.
+    fn into_response(self) -> Response;
 }
 
-impl PathPattern {
-    /// Parse a path pattern.
-    pub fn parse(pattern: &str) -> Self {
-        let segments: Vec<PathSegment> = pattern
-            .trim_matches('/')
-            .split('/')
-            .filter(|s| !s.is_empty())
-            .map(|s| {
-                if s.starts_with("{*") && s.ends_with('}') {
-                    PathSegment::Wildcard(s[2..s.len() - 1].to_string())
-                } else if s.starts_with('{') && s.ends_with('}') {
-                    PathSegment::Param(s[1..s.len() - 1].to_string())
-                } else {
-                    PathSegment::Static(s.to_string())
-                }
-            })
-            .collect();
-
-        Self {
-            segments,
-            original: pattern.to_string(),
-        }
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for std::result::Result<T, E> {
+    fn into_response(self) -> Response {
+        match self {
+            Ok(value) => value.into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+/// `200 OK` with the value as the JSON body.
+impl IntoResponse for JsonValue {
+    fn into_response(self) -> Response {
+        Response::ok(self)
+    }
+}
+
+/// `204 No Content`.
+impl IntoResponse for () {
+    fn into_response(self) -> Response {
+        Response::no_content()
+    }
+}
+
+/// The inner response with its status replaced.
+impl<T: IntoResponse> IntoResponse for (u16, T) {
+    fn into_response(self) -> Response {
+        let mut response = self.1.into_response();
+        response.status = self.0;
+        response
+    }
+}
+
+impl IntoResponse for IpcError {
+    fn into_response(self) -> Response {
+        Response::from_error(&self)
+    }
+}
+
+/// A serializable value returned as a `200 OK` JSON body.
+///
+/// Serialization failures are answered with 500.
+#[derive(Debug, Clone)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        match serde_json::to_value(&self.0) {
+            Ok(body) => Response::ok(body),
+            Err(e) => Response::internal_error(&e.to_string()),
+        }
+    }
+}
+
+/// An error answered with a status code and a JSON body of the form
+/// `{"error": reason, "message": ...}`.
+///
+/// Converts from [`IpcError`] (keeping its wire form and status mapping),
+/// [`ValidationErrors`] (422 with the field errors under `errors`) and
+/// `serde_json::Error` (400), so `?` works on all of them in handlers.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    status: u16,
+    body: JsonValue,
+}
+
+impl ApiError {
+    /// Create an error with a status code and message.
+    pub fn new(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({
+                "error": status_message(status),
+                "message": message
+            }),
+        }
+    }
+
+    /// Create a 400 Bad Request error.
+    pub fn bad_request(message: &str) -> Self {
+        Self::new(400, message)
+    }
+
+    /// Create a 403 Forbidden error.
+    pub fn forbidden(message: &str) -> Self {
+        Self::new(403, message)
+    }
+
+    /// Create a 404 Not Found error.
+    pub fn not_found(message: &str) -> Self {
+        Self::new(404, message)
+    }
+
+    /// Create a 409 Conflict error.
+    pub fn conflict(message: &str) -> Self {
+        Self::new(409, message)
+    }
+
+    /// Create a 500 Internal Server Error.
+    pub fn internal(message: &str) -> Self {
+        Self::new(500, message)
+    }
+
+    /// Add a field to the JSON body.
+    pub fn with(mut self, key: &str, value: JsonValue) -> Self {
+        self.body[key] = value;
+        self
+    }
+
+    /// Get the status code.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Get the message.
+    pub fn message(&self) -> &str {
+        self.body["message"].as_str().unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.status, self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        Response::new(self.status).json(self.body)
+    }
+}
+
+impl From<IpcError> for ApiError {
+    fn from(err: IpcError) -> Self {
+        let status = error_status(err.code());
+        let mut body = err.to_json();
+        body["error"] = JsonValue::from(status_message(status));
+        Self { status, body }
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        Self::new(422, &errors.to_string()).with(
+            "errors",
+            serde_json::to_value(errors.errors()).unwrap_or_default(),
+        )
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::bad_request(&err.to_string())
+    }
+}
+
+fn status_message(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown",
+    }
+}
+
+/// Deprecation notice attached to the responses of a [`Scope`].
+///
+/// Responses carry `Deprecation: true`, plus `Sunset` and a
+/// `Link: <...>; rel="successor-version"` header when set, so clients can
+/// warn before an API version is removed.
+#[derive(Debug, Clone, Default)]
+pub struct Deprecation {
+    sunset: Option<String>,
+    successor: Option<String>,
+}
+
+impl Deprecation {
+    /// Create a deprecation notice.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the HTTP date after which the routes may be removed.
+    pub fn sunset(mut self, date: &str) -> Self {
+        self.sunset = Some(date.to_string());
+        self
+    }
+
+    /// Set the path of the replacement API.
+    pub fn successor(mut self, path: &str) -> Self {
+        self.successor = Some(path.to_string());
+        self
+    }
+
+    /// Add the deprecation headers to a response.
+    pub fn apply(&self, response: &mut Response) {
+        response
+            .headers
+            .insert("Deprecation".to_string(), "true".to_string());
+        if let Some(ref sunset) = self.sunset {
+            response
+                .headers
+                .insert("Sunset".to_string(), sunset.clone());
+        }
+        if let Some(ref successor) = self.successor {
+            response.headers.insert(
+                "Link".to_string(),
+                format!("<{}>; rel=\"successor-version\"", successor),
+            );
+        }
+    }
+}
+
+/// Routes registered under a common path prefix, such as an API version.
+///
+/// Created by [`Router::scope`] or [`Router::group`]. Scopes nest, and a
+/// deprecation or middleware set on a scope applies to the routes registered
+/// through it (and its nested scopes) afterwards.
+pub struct Scope<'a> {
+    router: &'a mut Router,
+    prefix: String,
+    deprecation: Option<Arc<Deprecation>>,
+    middlewares: Vec<SharedMiddleware>,
+}
+
+impl Scope<'_> {
+    /// Get the path prefix.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Mark the scope's routes as deprecated.
+    pub fn deprecated(mut self, deprecation: Deprecation) -> Self {
+        self.deprecation = Some(Arc::new(deprecation));
+        self
+    }
+
+    /// Open a nested scope.
+    pub fn scope(&mut self, prefix: &str) -> Scope<'_> {
+        Scope {
+            prefix: join_prefix(&self.prefix, prefix),
+            deprecation: self.deprecation.clone(),
+            middlewares: self.middlewares.clone(),
+            router: self.router,
+        }
+    }
+
+    /// Register routes in a nested scope built by `build`.
+    pub fn group<F>(&mut self, prefix: &str, build: F) -> &mut Self
+    where
+        F: FnOnce(&mut Scope<'_>),
+    {
+        build(&mut self.scope(prefix));
+        self
+    }
+
+    /// Add middleware for the routes registered through this scope from now on.
+    ///
+    /// It runs after the router's global middleware, and after middleware
+    /// added to enclosing scopes.
+    pub fn middleware<F>(&mut self, middleware: F) -> &mut Self
+    where
+        F: Fn(Request, &dyn Fn(Request) -> Response) -> Response + Send + Sync + 'static,
+    {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Register a GET route.
+    pub fn get<F, R>(&mut self, path: &str, handler: F) -> &mut Self
+    where
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::GET, path, handler)
+    }
+
+    /// Register a POST route.
+    pub fn post<F, R>(&mut self, path: &str, handler: F) -> &mut Self
+    where
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::POST, path, handler)
+    }
+
+    /// Register a PUT route.
+    pub fn put<F, R>(&mut self, path: &str, handler: F) -> &mut Self
+    where
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::PUT, path, handler)
+    }
+
+    /// Register a DELETE route.
+    pub fn delete<F, R>(&mut self, path: &str, handler: F) -> &mut Self
+    where
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::DELETE, path, handler)
+    }
+
+    /// Register a PATCH route.
+    pub fn patch<F, R>(&mut self, path: &str, handler: F) -> &mut Self
+    where
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::PATCH, path, handler)
+    }
+
+    /// Register a route with a specific method.
+    pub fn route<F, R>(&mut self, method: Method, path: &str, handler: F) -> &mut Self
+    where
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        let handler = move |req| handler(req).into_response();
+        let path = join_prefix(&self.prefix, path);
+        let middlewares = self.middlewares.clone();
+        let deprecation = self.deprecation.clone();
+        self.router.route(method, &path, move |req| {
+            let mut response = apply_middlewares(&middlewares, req, &handler);
+            if let Some(ref deprecation) = deprecation {
+                deprecation.apply(&mut response);
+            }
+            response
+        });
+        self
+    }
+
+    /// Register the task API backed by `manager` under `{prefix}/tasks`.
+    ///
+    /// Under `/v1` these are the endpoints
+    /// [`CliBridge`](crate::cli_bridge::CliBridge) reports to.
+    /// `DELETE {prefix}/tasks/{id}` cancels the task, which wrapped
+    /// commands observe and react to by killing their process tree.
+    /// `POST {prefix}/tasks/{id}/commands` sends a command to the task's owner,
+    /// which long-polls `GET {prefix}/tasks/{id}/commands?after=&timeout_ms=`.
+    /// `GET {prefix}/tasks/{id}/logs?after=&timeout_ms=` returns the task's
+    /// log and output lines, long-polling for new ones when `timeout_ms` is set.
+    /// Tasks created with a `parent_id` (and optional `weight`) are sub-tasks of
+    /// that task; `GET {prefix}/tasks?parent={id}` lists its direct sub-tasks
+    /// and `?children_of={id}` its whole subtree.
+    pub fn task_routes(&mut self, manager: Arc<TaskManager>) -> &mut Self {
+        let tm = Arc::clone(&manager);
+        self.get("/tasks", move |req| {
+            let mut filter = TaskFilter::new();
+            if let Some(parent) = req.query_param("parent") {
+                filter = filter.parent(parent);
+            }
+            if let Some(ancestor) = req.query_param("children_of") {
+                filter = filter.children_of(ancestor);
+            }
+            Response::ok(serde_json::json!(tm.list(&filter)))
+        });
+
+        let tm = Arc::clone(&manager);
+        self.post("/tasks", move |req| {
+            let body = req.body.unwrap_or_default();
+            let Some(name) = body["name"].as_str() else {
+                return Response::bad_request("Missing task name");
+            };
+            let mut builder = TaskBuilder::new(name, body["type"].as_str().unwrap_or("task"));
+            if let Some(id) = body["id"].as_str() {
+                builder = builder.id(id);
+            }
+            if let Some(ms) = body["stall_timeout_ms"].as_u64() {
+                builder = builder.stall_timeout(Duration::from_millis(ms));
+            }
+            if let Ok(action) = serde_json::from_value(body["on_stall"].clone()) {
+                builder = builder.on_stall(action);
+            }
+            if let Some(ms) = body["timeout_ms"].as_u64() {
+                builder = builder.timeout(Duration::from_millis(ms));
+            }
+            if let Some(parent) = body["parent_id"].as_str() {
+                builder = builder.parent(parent);
+            }
+            if let Some(weight) = body["weight"].as_f64() {
+                builder = builder.weight(weight);
+            }
+            if let Some(metadata) = body["metadata"].as_object() {
+                for (key, value) in metadata {
+                    builder = builder.metadata(key, value.clone());
+                }
+            }
+
+            let handle = match tm.try_create(builder) {
+                Ok(handle) => handle,
+                Err(IpcError::AlreadyExists(_)) => {
+                    return Response::new(409)
+                        .json(serde_json::json!({"error": "Task already exists"}))
+                }
+                Err(e) => return e.into(),
+            };
+            if body["status"] == "running" {
+                handle.start();
+            }
+            Response::created(serde_json::json!(handle.info()))
+        });
+
+        let tm = Arc::clone(&manager);
+        self.get("/tasks/{id}", move |req| {
+            match req.path_param("id").and_then(|id| tm.get(id)) {
+                Some(info) => Response::ok(serde_json::json!(info)),
+                None => Response::not_found(),
+            }
+        });
+
+        let tm = Arc::clone(&manager);
+        self.delete("/tasks/{id}", move |req| {
+            let id = req.path_param("id").unwrap_or_default();
+            match tm.cancel(id) {
+                Ok(()) => Response::ok(serde_json::json!(tm.get(id))),
+                Err(e) => e.into(),
+            }
+        });
+
+        type TaskAction = fn(&TaskHandle, &JsonValue);
+        let actions: [(&str, TaskAction); 7] = [
+            ("progress", |task, body| {
+                let progress = body["progress"].as_u64().unwrap_or(0).min(100) as u8;
+                task.set_progress(progress, body["message"].as_str());
+            }),
+            ("logs", |task, body| {
+                task.log(
+                    body["level"].as_str().unwrap_or("info"),
+                    body["message"].as_str().unwrap_or_default(),
+                );
+            }),
+            ("stdout", |task, body| {
+                task.stdout(body["line"].as_str().unwrap_or_default())
+            }),
+            ("stderr", |task, body| {
+                task.stderr(body["line"].as_str().unwrap_or_default())
+            }),
+            ("complete", |task, body| {
+                task.complete(body["result"].clone())
+            }),
+            ("fail", |task, body| {
+                task.fail(body["error"].as_str().unwrap_or("Unknown error"))
+            }),
+            ("heartbeat", |task, _| task.heartbeat()),
+        ];
+        for (action, apply) in actions {
+            let tm = Arc::clone(&manager);
+            self.post(&format!("/tasks/{{id}}/{}", action), move |req| {
+                let Some(task) = req.path_param("id").and_then(|id| tm.get_handle(id)) else {
+                    return Response::not_found();
+                };
+                // Late reports from a cancelled task must not overwrite its status
+                if task.status().is_terminal() && matches!(action, "complete" | "fail") {
+                    return Response::new(409)
+                        .json(serde_json::json!({"error": "Task already finished"}));
+                }
+                apply(&task, req.body.as_ref().unwrap_or(&JsonValue::Null));
+                Response::ok(serde_json::json!({"status": task.status()}))
+            });
+        }
+
+        let tm = Arc::clone(&manager);
+        self.post("/tasks/{id}/commands", move |req| {
+            let id = req.path_param("id").unwrap_or_default();
+            let body = req.body.clone().unwrap_or_default();
+            let Some(command) = body["command"].as_str() else {
+                return Response::bad_request("Missing command");
+            };
+            match tm.send_command(id, command, body["args"].clone()) {
+                Ok(event_id) => Response::ok(serde_json::json!({"id": event_id})),
+                Err(e) => e.into(),
+            }
+        });
+
+        // Long-poll: blocks until a command arrives, the task finishes or
+        // `timeout_ms` elapses
+        let tm = Arc::clone(&manager);
+        self.get("/tasks/{id}/commands", move |req| {
+            let id = req.path_param("id").unwrap_or_default();
+            let after = req.query_param("after").and_then(|v| v.parse().ok());
+            let timeout = req
+                .query_param("timeout_ms")
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or_default()
+                .min(MAX_COMMAND_WAIT);
+
+            match tm.wait_commands(id, after, timeout) {
+                Ok((status, events)) => {
+                    let commands: Vec<JsonValue> = events
+                        .into_iter()
+                        .map(|e| {
+                            serde_json::json!({
+                                "id": e.id,
+                                "command": e.data["command"],
+                                "args": e.data["args"],
+                            })
+                        })
+                        .collect();
+                    Response::ok(serde_json::json!({"status": status, "commands": commands}))
+                }
+                Err(e) => e.into(),
+            }
+        });
+
+        let tm = Arc::clone(&manager);
+        self.get("/tasks/{id}/logs", move |req| {
+            let id = req.path_param("id").unwrap_or_default();
+            let after = req.query_param("after").and_then(|v| v.parse().ok());
+            let timeout = req
+                .query_param("timeout_ms")
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or_default()
+                .min(MAX_COMMAND_WAIT);
+
+            match tm.wait_logs(id, after, timeout) {
+                Ok((status, events)) => {
+                    let logs: Vec<JsonValue> = events
+                        .into_iter()
+                        .map(|e| {
+                            serde_json::json!({
+                                "id": e.id,
+                                "timestamp": e.timestamp
+                                    .duration_since(std::time::UNIX_EPOCH)
+                                    .unwrap_or_default()
+                                    .as_secs_f64(),
+                                "level": e.data["level"],
+                                "message": e.data["message"],
+                            })
+                        })
+                        .collect();
+                    Response::ok(serde_json::json!({"status": status, "logs": logs}))
+                }
+                Err(e) => e.into(),
+            }
+        });
+
+        self
+    }
+
+    /// Register the connection admin API backed by `registry` under
+    /// `{prefix}/connections`.
+    ///
+    /// `GET {prefix}/connections` lists the live connections and
+    /// `GET {prefix}/connections/{id}` shows one.
+    /// `DELETE {prefix}/connections/{id}` drops the connection and returns
+    /// it as it was last seen.
+    pub fn connection_routes(&mut self, registry: ConnectionRegistry) -> &mut Self {
+        let reg = registry.clone();
+        self.get("/connections", move |_req| {
+            Response::ok(serde_json::json!(reg.list()))
+        });
+
+        let reg = registry.clone();
+        self.get("/connections/{id}", move |req| {
+            match connection_id(&req).and_then(|id| reg.get(id)) {
+                Some(info) => Response::ok(serde_json::json!(info)),
+                None => Response::not_found(),
+            }
+        });
+
+        self.delete("/connections/{id}", move |req| {
+            let Some(id) = connection_id(&req) else {
+                return Response::not_found();
+            };
+            let info = registry.get(id);
+            if !registry.disconnect(id) {
+                return Response::not_found();
+            }
+            Response::ok(serde_json::json!(info))
+        });
+
+        self
+    }
+
+    /// Register the event replay API backed by `bus` under `{prefix}/events`.
+    ///
+    /// `GET {prefix}/events?after=&type=&resource=` returns the retained
+    /// events with an ID greater than `after` as
+    /// `{"epoch": 1234, "events": [...]}`, limited to the comma-separated
+    /// event type patterns and resource IDs when given.
+    /// `GET {prefix}/events/epoch` returns the epoch alone; event IDs are only
+    /// comparable within one epoch (see [`EventBus::epoch`]).
+    pub fn event_routes(&mut self, bus: EventBus) -> &mut Self {
+        let events_bus = bus.clone();
+        self.get("/events", move |req| {
+            let after = match req.query_param("after").map(str::parse::<EventId>) {
+                None => None,
+                Some(Ok(after)) => Some(after),
+                Some(Err(_)) => return Response::bad_request("Invalid 'after' event ID"),
+            };
+            let mut filter = EventFilter::new();
+            for pattern in req
+                .query_param("type")
+                .into_iter()
+                .flat_map(|t| t.split(','))
+            {
+                filter = filter.event_type(pattern);
+            }
+            for id in req
+                .query_param("resource")
+                .into_iter()
+                .flat_map(|r| r.split(','))
+            {
+                filter = filter.resource(id);
+            }
+            match events_bus.replay_since(after, &filter) {
+                Ok(events) => Response::ok(serde_json::json!({
+                    "epoch": events_bus.epoch(),
+                    "events": events,
+                })),
+                Err(e) => e.into(),
+            }
+        });
+
+        self.get("/events/epoch", move |_req| {
+            Response::ok(serde_json::json!({ "epoch": bus.epoch() }))
+        });
+
+        self
+    }
+}
+
+/// The `{id}` path parameter of a connection route.
+fn connection_id(req: &Request) -> Option<ConnectionId> {
+    req.path_param("id")?.parse().ok()
+}
+
+fn join_prefix(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    match path.trim_start_matches('/') {
+        "" if prefix.is_empty() => "/".to_string(),
+        "" => prefix.to_string(),
+        rest => format!("{}/{}", prefix, rest),
+    }
+}
+
+/// Path segment for pattern matching.
+#[derive(Debug, Clone)]
+enum PathSegment {
+    /// Static path segment
+    Static(String),
+    /// Parameter path segment {:name}
+    Param(String),
+    /// Wildcard {*rest}
+    Wildcard(String),
+}
+
+/// Path pattern for route matching.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    segments: Vec<PathSegment>,
+    original: String,
+}
+
+impl PathPattern {
+    /// Parse a path pattern.
+    pub fn parse(pattern: &str) -> Self {
+        let segments: Vec<PathSegment> = pattern
+            .trim_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                if s.starts_with("{*") && s.ends_with('}') {
+                    PathSegment::Wildcard(s[2..s.len() - 1].to_string())
+                } else if s.starts_with('{') && s.ends_with('}') {
+                    PathSegment::Param(s[1..s.len() - 1].to_string())
+                } else {
+                    PathSegment::Static(s.to_string())
+                }
+            })
+            .collect();
+
+        Self {
+            segments,
+            original: pattern.to_string(),
+        }
+    }
+
+    /// Get the pattern as written.
+    pub fn as_str(&self) -> &str {
+        &self.original
     }
 
     /// Match a path against this pattern.
@@ -572,11 +1958,37 @@ struct Route {
 pub type MiddlewareFn =
     Box<dyn Fn(Request, &dyn Fn(Request) -> Response) -> Response + Send + Sync>;
 
+/// Middleware shared by the routes of a [`Scope`].
+type SharedMiddleware =
+    Arc<dyn Fn(Request, &dyn Fn(Request) -> Response) -> Response + Send + Sync>;
+
+/// Compare two byte strings without exiting early on the first mismatch.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Run `req` through `middlewares` (first outermost), then `handler`.
+fn apply_middlewares(
+    middlewares: &[SharedMiddleware],
+    req: Request,
+    handler: &dyn Fn(Request) -> Response,
+) -> Response {
+    match middlewares.split_first() {
+        Some((first, rest)) => first(req, &|req| apply_middlewares(rest, req, handler)),
+        None => handler(req),
+    }
+}
+
 /// API router.
 pub struct Router {
     routes: Vec<Route>,
     middlewares: Vec<MiddlewareFn>,
     not_found_handler: Option<HandlerFn>,
+    /// `(method, pattern)` of every route, shared with the route index
+    index: Arc<RwLock<Vec<(Method, String)>>>,
 }
 
 impl Default for Router {
@@ -592,63 +2004,252 @@ impl Router {
             routes: Vec::new(),
             middlewares: Vec::new(),
             not_found_handler: None,
+            index: Arc::default(),
         }
     }
 
     /// Register a GET route.
-    pub fn get<F>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn get<F, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
-        F: Fn(Request) -> Response + Send + Sync + 'static,
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
     {
         self.route(Method::GET, path, handler)
     }
 
     /// Register a POST route.
-    pub fn post<F>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn post<F, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
-        F: Fn(Request) -> Response + Send + Sync + 'static,
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
     {
         self.route(Method::POST, path, handler)
     }
 
     /// Register a PUT route.
-    pub fn put<F>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn put<F, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
-        F: Fn(Request) -> Response + Send + Sync + 'static,
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
     {
         self.route(Method::PUT, path, handler)
     }
 
     /// Register a DELETE route.
-    pub fn delete<F>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn delete<F, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
-        F: Fn(Request) -> Response + Send + Sync + 'static,
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
     {
         self.route(Method::DELETE, path, handler)
     }
 
     /// Register a PATCH route.
-    pub fn patch<F>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn patch<F, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
-        F: Fn(Request) -> Response + Send + Sync + 'static,
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
     {
         self.route(Method::PATCH, path, handler)
     }
 
     /// Register a route with a specific method.
-    pub fn route<F>(&mut self, method: Method, path: &str, handler: F) -> &mut Self
+    pub fn route<F, R>(&mut self, method: Method, path: &str, handler: F) -> &mut Self
     where
-        F: Fn(Request) -> Response + Send + Sync + 'static,
+        F: Fn(Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
     {
         self.routes.push(Route {
             method,
             pattern: PathPattern::parse(path),
-            handler: Box::new(handler),
+            handler: Box::new(move |req| handler(req).into_response()),
+        });
+        self.index.write().push((method, path.to_string()));
+        self
+    }
+
+    /// Get the method and path pattern of every registered route.
+    pub fn routes(&self) -> Vec<(Method, String)> {
+        self.index.read().clone()
+    }
+
+    /// Register a GET route listing every route as `[{"method", "path"}]`.
+    ///
+    /// Routes registered later are included too. Tools such as
+    /// `ipckit shell` read it (conventionally at [`ROUTES_PATH`]) to offer
+    /// completion.
+    pub fn route_index(&mut self, path: &str) -> &mut Self {
+        let index = Arc::clone(&self.index);
+        self.get(path, move |_req| {
+            let routes: Vec<JsonValue> = index
+                .read()
+                .iter()
+                .map(|(method, path)| serde_json::json!({"method": method.as_str(), "path": path}))
+                .collect();
+            Response::ok(JsonValue::Array(routes))
+        })
+    }
+
+    /// Register a GET route serving the global [`MetricsRegistry`] in
+    /// Prometheus text format (conventionally at `/metrics`).
+    ///
+    /// With `?format=json` the route returns the channel snapshots and gauges
+    /// as JSON instead, which is what `ipckit top` reads.
+    ///
+    /// [`MetricsRegistry`]: crate::metrics::MetricsRegistry
+    pub fn metrics_route(&mut self, path: &str) -> &mut Self {
+        self.get(path, |req| {
+            let registry = crate::metrics::MetricsRegistry::global();
+            if req.query_param("format") == Some("json") {
+                return Response::ok(serde_json::json!({
+                    "channels": registry.snapshots(),
+                    "gauges": registry.gauges(),
+                }));
+            }
+            let body = registry.to_prometheus("ipckit");
+            Response::new(200)
+                .text(&body)
+                .header("Content-Type", "text/plain; version=0.0.4")
+        })
+    }
+
+    /// Register `GET /healthz` (liveness) and `GET /readyz` (readiness)
+    /// backed by `health`.
+    ///
+    /// `/healthz` always answers 200 with the uptime and never runs checks.
+    /// `/readyz` runs every registered check and answers with the full
+    /// [`HealthReport`](crate::health::HealthReport), or 503 when any check
+    /// is unhealthy.
+    pub fn health_routes(&mut self, health: Health) -> &mut Self {
+        let h = health.clone();
+        self.get("/healthz", move |_req| {
+            Response::ok(serde_json::json!({
+                "status": "ok",
+                "uptime_secs": h.uptime().as_secs_f64(),
+            }))
         });
+        self.get("/readyz", move |_req| {
+            let report = health.report();
+            let status = if report.is_ready() { 200 } else { 503 };
+            Response::new(status).json(serde_json::to_value(report).unwrap_or_default())
+        })
+    }
+
+    /// Register the task API backed by `manager` under `/v1/tasks`.
+    ///
+    /// See [`Scope::task_routes`] to serve it under another version prefix.
+    pub fn task_routes(&mut self, manager: Arc<TaskManager>) -> &mut Self {
+        self.scope("/v1").task_routes(manager);
         self
     }
 
-    /// Add middleware.
+    /// Register the connection admin API backed by `registry` under
+    /// `/v1/connections`.
+    ///
+    /// See [`Scope::connection_routes`]. [`ApiServer`] registers these for
+    /// its own connections when [`ApiServerConfig::admin_routes`] is set.
+    pub fn connection_routes(&mut self, registry: ConnectionRegistry) -> &mut Self {
+        self.scope("/v1").connection_routes(registry);
+        self
+    }
+
+    /// Register the event replay API backed by `bus` under `/v1/events`.
+    ///
+    /// See [`Scope::event_routes`]; [`ApiClient`] is a
+    /// [`ResumeSource`](crate::session_resume::ResumeSource) for it.
+    pub fn event_routes(&mut self, bus: EventBus) -> &mut Self {
+        self.scope("/v1").event_routes(bus);
+        self
+    }
+
+    /// Mount a [`CommandHandler`] (such as an `#[ipc_handler]` struct) at `prefix`.
+    ///
+    /// Each command is served at `POST {prefix}/{command}` with its parameters
+    /// as a JSON object body, and `GET {prefix}` lists the available commands.
+    pub fn mount<H>(&mut self, prefix: &str, handler: H) -> &mut Self
+    where
+        H: CommandHandler + Send + Sync + 'static,
+    {
+        let prefix = prefix.trim_end_matches('/');
+        let handler = Arc::new(handler);
+
+        let h = Arc::clone(&handler);
+        self.get(prefix, move |_req| {
+            Response::ok(serde_json::json!({
+                "channel": h.channel_name(),
+                "commands": h.commands(),
+            }))
+        });
+
+        self.post(&format!("{}/{{command}}", prefix), move |req| {
+            let command = req.path_param("command").unwrap_or_default().to_string();
+            match command_params(req.body)
+                .and_then(|params| handler.handle_command(&command, params))
+            {
+                Ok(value) => Response::ok(value),
+                Err(e) => e.into(),
+            }
+        })
+    }
+
+    /// Register `GET` and `PUT /v1/_admin/config` backed by `config`.
+    ///
+    /// `PUT` takes a JSON object of setting names to new values and applies
+    /// it atomically; settings not registered in `config` are refused with
+    /// 403 and invalid values with 400, leaving every setting unchanged.
+    pub fn config_routes(&mut self, config: Arc<RuntimeConfig>) -> &mut Self {
+        let cfg = Arc::clone(&config);
+        self.get(ADMIN_CONFIG_PATH, move |_req| Response::ok(cfg.snapshot()));
+
+        self.put(ADMIN_CONFIG_PATH, move |req| {
+            match config.update(req.body.as_ref().unwrap_or(&JsonValue::Null)) {
+                Ok(snapshot) => Response::ok(snapshot),
+                Err(e) => e.into(),
+            }
+        })
+    }
+
+    /// Add middleware that answers 429 when `limiter` has no tokens left.
+    pub fn rate_limit(&mut self, limiter: RateLimiter) -> &mut Self {
+        self.middleware(move |req, next| {
+            if limiter.try_acquire() {
+                next(req)
+            } else {
+                Response::too_many_requests()
+            }
+        })
+    }
+
+    /// Open a scope whose routes are registered under `prefix`.
+    pub fn scope(&mut self, prefix: &str) -> Scope<'_> {
+        Scope {
+            prefix: join_prefix("", prefix),
+            deprecation: None,
+            middlewares: Vec::new(),
+            router: self,
+        }
+    }
+
+    /// Register a group of routes under `prefix`, with middleware that only
+    /// applies to them.
+    ///
+    /// ```rust,ignore
+    /// router.group("/v1/admin", |g| {
+    ///     g.middleware(require_token);
+    ///     g.delete("/tasks/{id}", cancel_task);
+    /// });
+    /// ```
+    pub fn group<F>(&mut self, prefix: &str, build: F) -> &mut Self
+    where
+        F: FnOnce(&mut Scope<'_>),
+    {
+        build(&mut self.scope(prefix));
+        self
+    }
+
+    /// Add middleware that runs for every route.
+    ///
+    /// Use [`group`](Self::group) to limit middleware to some routes.
     pub fn middleware<F>(&mut self, middleware: F) -> &mut Self
     where
         F: Fn(Request, &dyn Fn(Request) -> Response) -> Response + Send + Sync + 'static,
@@ -667,7 +2268,15 @@ impl Router {
     }
 
     /// Handle a request.
-    pub fn handle(&self, mut req: Request) -> Response {
+    ///
+    /// JSON responses are re-encoded as MessagePack when the request's
+    /// `Accept` header prefers it.
+    pub fn handle(&self, req: Request) -> Response {
+        let format = req.preferred_format();
+        self.dispatch(req).encode_as(format)
+    }
+
+    fn dispatch(&self, mut req: Request) -> Response {
         // Find matching route
         for route in &self.routes {
             if route.method == req.method {
@@ -710,6 +2319,44 @@ pub struct ApiServerConfig {
     pub enable_cors: bool,
     /// CORS allowed origins
     pub cors_origins: Vec<String>,
+    /// Trace every request in an `api.request` span (disabled by default)
+    pub access_log: Option<LoggingMiddleware>,
+    /// Largest accepted request body (default: 8 MiB); larger ones are
+    /// answered with 413
+    pub max_body_size: usize,
+    /// Most headers accepted in one request (default: 64); requests with
+    /// more are answered with 431
+    pub max_headers: usize,
+    /// Largest accepted request line plus headers (default: 16 KiB); larger
+    /// ones are answered with 431
+    pub max_header_size: usize,
+    /// Token bucket applied to each connection separately; over-limit
+    /// requests are answered with 429 (unlimited by default)
+    pub rate_limit: Option<RateLimit>,
+    /// Serve `GET /v1/connections` and `DELETE /v1/connections/{id}` for
+    /// this server's own connections (disabled by default)
+    pub admin_routes: bool,
+    /// Serve `GET /healthz` and `GET /readyz` from [`ApiServer::health`],
+    /// reporting this server's connections (disabled by default)
+    pub health_routes: bool,
+    /// Replay cached responses to retried requests carrying the same
+    /// `Idempotency-Key` header (disabled by default)
+    pub idempotency: Option<IdempotencyMiddleware>,
+    /// Bearer token every request must carry in its `Authorization` header;
+    /// others are answered with 401 (no authentication by default)
+    pub auth_token: Option<String>,
+}
+
+impl ApiServerConfig {
+    /// Load the `[server]` settings of an ipckit config file, with
+    /// `IPCKIT_*` environment overrides applied.
+    ///
+    /// See [`IpckitConfig`](crate::config_file::IpckitConfig).
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> crate::Result<Self> {
+        let mut config = crate::config_file::IpckitConfig::load(path)?;
+        config.apply_env()?;
+        Ok(config.api_server_config())
+    }
 }
 
 impl Default for ApiServerConfig {
@@ -718,10 +2365,33 @@ impl Default for ApiServerConfig {
             socket_config: SocketServerConfig::default(),
             enable_cors: true,
             cors_origins: vec!["*".to_string()],
+            access_log: None,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            max_headers: DEFAULT_MAX_HEADERS,
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+            rate_limit: None,
+            admin_routes: false,
+            health_routes: false,
+            idempotency: None,
+            auth_token: None,
         }
     }
 }
 
+/// Per-connection state kept by [`ApiHandler`].
+struct ConnectionState {
+    /// Cancelled when the client disconnects
+    token: CancellationToken,
+    /// The connection's own bucket when `rate_limit` is configured
+    limiter: Option<RateLimiter>,
+}
+
+impl Drop for ConnectionState {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
 /// API Server handler for socket connections.
 #[derive(Clone)]
 struct ApiHandler {
@@ -729,8 +2399,64 @@ struct ApiHandler {
     config: ApiServerConfig,
 }
 
+impl ApiHandler {
+    fn limits(&self) -> RequestLimits {
+        RequestLimits {
+            max_body_size: self.config.max_body_size,
+            max_headers: self.config.max_headers,
+            max_header_size: self.config.max_header_size,
+        }
+    }
+}
+
+/// Get the reply to a request that failed to parse.
+fn parse_error_reply(e: &ParseError) -> Message {
+    let resp = match e.status() {
+        413 => Response::payload_too_large(&e.to_string()),
+        431 => Response::request_header_fields_too_large(&e.to_string()),
+        _ => Response::bad_request(&e.to_string()),
+    };
+    Message::binary(resp.to_bytes())
+}
+
 impl ConnectionHandler for ApiHandler {
-    fn on_message(&self, _conn: &mut Connection, msg: Message) -> crate::Result<Option<Message>> {
+    fn on_connect(&self, conn: &mut Connection) -> crate::Result<Extensions> {
+        // Refuse an oversized request once its head is in, without
+        // buffering the rest of the frame
+        let limits = self.limits();
+        conn.set_frame_check(Arc::new(move |_, head| {
+            let Some(data) = binary_frame_prefix(head) else {
+                return FrameVerdict::Accept;
+            };
+            match Request::parse_with_limits(&data, &limits) {
+                Err(ParseError::Incomplete) if find_head_end(&data).is_none() => {
+                    FrameVerdict::NeedMore
+                }
+                Err(e) if matches!(e.status(), 413 | 431) => {
+                    FrameVerdict::Reject(parse_error_reply(&e))
+                }
+                _ => FrameVerdict::Accept,
+            }
+        }));
+
+        // Request tokens are children of this one, so checking any of them
+        // notices a client that went away
+        let token = match conn.peer_watch() {
+            Some(closed) => CancellationToken::new().cancel_when(PEER_POLL_INTERVAL, closed),
+            None => CancellationToken::new(),
+        };
+        let mut state = Extensions::new();
+        state.insert(ConnectionState {
+            token,
+            limiter: self
+                .config
+                .rate_limit
+                .and_then(|l| RateLimiter::new(l).ok()),
+        });
+        Ok(state)
+    }
+
+    fn on_message(&self, conn: &mut Connection, msg: Message) -> crate::Result<Option<Message>> {
         // Get the raw HTTP data from the message
         let data = if let Some(binary_data) = msg.as_binary() {
             binary_data
@@ -742,33 +2468,134 @@ impl ConnectionHandler for ApiHandler {
         };
 
         // Parse request from message data
-        let request = match Request::parse(&data) {
+        let mut request = match Request::parse_with_limits(&data, &self.limits()) {
             Ok(req) => req,
-            Err(e) => {
-                let resp = Response::bad_request(&e.to_string());
-                return Ok(Some(Message::binary(resp.to_bytes())));
-            }
+            Err(e) => return Ok(Some(parse_error_reply(&e))),
         };
 
-        // Handle CORS preflight
-        if request.method == Method::OPTIONS && self.config.enable_cors {
-            let resp = self.cors_preflight_response();
-            return Ok(Some(Message::binary(resp.to_bytes())));
-        }
+        // A traceparent header takes precedence over the frame's trace
+        let trace = request
+            .header(TRACE_HEADER)
+            .and_then(|value| value.parse::<TraceContext>().ok())
+            .map(|ctx| ctx.child());
+        let _trace = trace.as_ref().map(TraceContext::enter);
+
+        let access = self.config.access_log.as_ref().map(|log| {
+            log.request(
+                conn.id(),
+                request.method.as_str(),
+                &request.path,
+                &request.raw_body,
+            )
+        });
+        let span = access
+            .as_ref()
+            .map_or_else(Span::none, |a| a.span().clone());
 
-        // Route the request
-        let mut response = self.router.read().handle(request);
+        // The body of a chunked request follows in further messages
+        let body = request.is_chunked().then(|| {
+            let (tx, rx) = crossbeam_channel::bounded(STREAM_BUFFER_CHUNKS);
+            request.body_stream = Some(BodyReader::streamed(rx));
+            tx
+        });
+        // Its chunks are not requests, so leave them unchecked
+        let check = body.as_ref().and_then(|_| conn.take_frame_check());
+
+        let (token, admitted) = match conn.state::<ConnectionState>() {
+            Some(state) => (
+                state.token.child(),
+                state.limiter.as_ref().is_none_or(RateLimiter::try_acquire),
+            ),
+            None => (CancellationToken::new(), true),
+        };
 
-        // Add CORS headers
-        if self.config.enable_cors {
-            self.add_cors_headers(&mut response);
-        }
+        let response = if !admitted {
+            drain_body(conn, body);
+            Response::too_many_requests()
+        } else if request.method == Method::OPTIONS && self.config.enable_cors {
+            // Handle CORS preflight
+            drain_body(conn, body);
+            self.cors_preflight_response()
+        } else {
+            // Route the request
+            request.cancel_token = token;
+            let mut response = self.route(conn, request, body, &span);
+
+            // Add CORS headers
+            if self.config.enable_cors {
+                self.add_cors_headers(&mut response);
+            }
+            response
+        };
 
+        if let Some(check) = check {
+            conn.set_frame_check(check);
+        }
+        if let Some(access) = access {
+            access.finish_request(response.status);
+        }
         Ok(Some(Message::binary(response.to_bytes())))
     }
+
+    fn on_reject(&self, conn: &mut Connection) {
+        let response =
+            Response::service_unavailable("server is shutting down").header("Connection", "close");
+        let _ = conn.send(&Message::binary(response.to_bytes()));
+    }
 }
 
 impl ApiHandler {
+    /// Route a request on the connection's thread, inside `span`, answering
+    /// 500 if the handler panics.
+    ///
+    /// For a streamed request, `body` forwards the chunks that follow to the
+    /// handler's [`BodyReader`] from a helper thread, as this one is busy
+    /// running the handler; the whole body is consumed before the response
+    /// is returned, even if the handler answers without reading it.
+    fn route(
+        &self,
+        conn: &mut Connection,
+        request: Request,
+        body: Option<BodySender>,
+        span: &Span,
+    ) -> Response {
+        let token = request.cancel_token.clone();
+        let handle = || {
+            let _entered = span.enter();
+            std::panic::catch_unwind(AssertUnwindSafe(|| self.router.read().handle(request)))
+                .unwrap_or_else(|_| Response::internal_error("Handler panicked"))
+        };
+
+        let Some(body) = body else {
+            return handle();
+        };
+        let answered = &AtomicBool::new(false);
+        let token = &token;
+        std::thread::scope(|scope| {
+            // Owns the sender, so the reader sees the end once the body is in
+            scope.spawn(move || {
+                for chunk in body_chunks(conn) {
+                    if chunk.is_err() {
+                        token.cancel();
+                    }
+                    // A handler that answered or dropped its reader discards the rest
+                    let mut chunk = chunk;
+                    while !answered.load(Ordering::SeqCst) {
+                        match body.send_timeout(chunk, PEER_POLL_INTERVAL) {
+                            Err(crossbeam_channel::SendTimeoutError::Timeout(unsent)) => {
+                                chunk = unsent
+                            }
+                            _ => break,
+                        }
+                    }
+                }
+            });
+            let response = handle();
+            answered.store(true, Ordering::SeqCst);
+            response
+        })
+    }
+
     fn cors_preflight_response(&self) -> Response {
         let origin = if self.config.cors_origins.contains(&"*".to_string()) {
             "*".to_string()
@@ -806,6 +2633,8 @@ impl ApiHandler {
 pub struct ApiServer {
     config: ApiServerConfig,
     router: Arc<RwLock<Router>>,
+    health: Health,
+    shutdown: Arc<ShutdownState>,
 }
 
 impl ApiServer {
@@ -814,9 +2643,21 @@ impl ApiServer {
         Self {
             config,
             router: Arc::new(RwLock::new(Router::new())),
+            health: Health::new(),
+            shutdown: Arc::new(ShutdownState::new()),
         }
     }
 
+    /// Get the server's shutdown state.
+    ///
+    /// Remains usable after the server is moved into [`spawn`](Self::spawn).
+    /// Signaling shutdown answers new requests with 503, and
+    /// [`run`](Self::run) returns once in-flight requests have completed
+    /// (see [`SocketServer::run`]).
+    pub fn shutdown_state(&self) -> Arc<ShutdownState> {
+        Arc::clone(&self.shutdown)
+    }
+
     /// Get mutable reference to the router.
     pub fn router(&self) -> impl std::ops::DerefMut<Target = Router> + '_ {
         self.router.write()
@@ -824,15 +2665,48 @@ impl ApiServer {
 
     /// Run the server (blocking).
     pub fn run(self) -> crate::Result<()> {
+        if let Some(ref limit) = self.config.rate_limit {
+            limit.validate()?;
+        }
         let handler = ApiHandler {
             router: Arc::clone(&self.router),
             config: self.config.clone(),
         };
 
-        let server = SocketServer::new(self.config.socket_config)?;
+        let server =
+            SocketServer::new(self.config.socket_config)?.with_shutdown_state(self.shutdown);
+        // The bearer check runs outermost, ahead of user middleware and
+        // idempotency replay, so nothing is served to an unauthenticated peer
+        if let Some(token) = self.config.auth_token {
+            let expected = format!("Bearer {}", token);
+            let auth: MiddlewareFn = Box::new(move |req, next| match req.header("authorization") {
+                Some(value) if constant_time_eq(value.as_bytes(), expected.as_bytes()) => next(req),
+                _ => Response::unauthorized("missing or invalid bearer token"),
+            });
+            self.router.write().middlewares.insert(0, auth);
+        }
+        if self.config.admin_routes {
+            self.router.write().connection_routes(server.registry());
+        }
+        if self.config.health_routes {
+            self.health.connections(server.registry());
+            self.router.write().health_routes(self.health.clone());
+        }
+        if let Some(idempotency) = self.config.idempotency {
+            self.router
+                .write()
+                .middleware(move |req, next| idempotency.handle(req, next));
+        }
         server.run(handler)
     }
 
+    /// The server's health state, for registering checks and a task manager
+    /// before [`run`](Self::run). Served when
+    /// [`ApiServerConfig::health_routes`] is set.
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
     /// Start the server in a background thread.
     pub fn spawn(self) -> std::thread::JoinHandle<crate::Result<()>> {
         std::thread::spawn(move || self.run())
@@ -844,6 +2718,11 @@ pub struct ApiClient {
     socket_path: String,
     /// Connection timeout (None = no timeout, blocks indefinitely)
     timeout: Option<std::time::Duration>,
+    transport: Arc<dyn Transport>,
+    /// Sent as `Authorization: Bearer <token>`
+    token: Option<String>,
+    /// Encoding of request bodies
+    format: ContentFormat,
 }
 
 impl ApiClient {
@@ -852,6 +2731,9 @@ impl ApiClient {
         Self {
             socket_path: socket_path.to_string(),
             timeout: None,
+            transport: Arc::new(LocalSocketTransport),
+            token: None,
+            format: ContentFormat::Json,
         }
     }
 
@@ -860,6 +2742,9 @@ impl ApiClient {
         Self {
             socket_path: socket_path.to_string(),
             timeout: Some(timeout),
+            transport: Arc::new(LocalSocketTransport),
+            token: None,
+            format: ContentFormat::Json,
         }
     }
 
@@ -873,14 +2758,41 @@ impl ApiClient {
         Self::with_timeout(&SocketServerConfig::default().path, timeout)
     }
 
-    /// Set the connection timeout.
-    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) {
-        self.timeout = timeout;
+    /// Set the connection timeout.
+    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Get the connection timeout.
+    pub fn get_timeout(&self) -> Option<std::time::Duration> {
+        self.timeout
+    }
+
+    /// Reach the server over another transport, matching the server's
+    /// [`SocketServerConfig::transport`].
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Authenticate to a server with [`ApiServerConfig::auth_token`] set.
+    pub fn bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
     }
 
-    /// Get the connection timeout.
-    pub fn get_timeout(&self) -> Option<std::time::Duration> {
-        self.timeout
+    /// Encode request bodies as `format` instead of JSON.
+    pub fn format(mut self, format: ContentFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Extra header lines for authentication (internal)
+    fn auth_header(&self) -> String {
+        self.token
+            .as_ref()
+            .map(|token| format!("Authorization: Bearer {}\r\n", token))
+            .unwrap_or_default()
     }
 
     /// Make a GET request.
@@ -903,29 +2815,116 @@ impl ApiClient {
         self.request(Method::DELETE, path, None)
     }
 
+    /// Make a POST request, streaming the body from `reader`.
+    ///
+    /// The body is sent in chunks as it is read, so it can be larger than
+    /// memory or the server's `max_body_size`; the handler reads it with
+    /// [`Request::body_reader`].
+    pub fn post_stream<R: Read>(&self, path: &str, reader: R) -> crate::Result<JsonValue> {
+        self.request_stream(Method::POST, path, "application/octet-stream", reader)
+    }
+
+    /// Make a request, streaming the body from `reader`.
+    pub fn request_stream<R: Read>(
+        &self,
+        method: Method,
+        path: &str,
+        content_type: &str,
+        mut reader: R,
+    ) -> crate::Result<JsonValue> {
+        let mut client = self.connect_client()?;
+        let trace = TraceContext::current_or_root();
+        let _trace = trace.enter();
+
+        let head = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: {}\r\n{}: {}\r\n{}Transfer-Encoding: chunked\r\n\r\n",
+            method.as_str(),
+            path,
+            content_type,
+            TRACE_HEADER,
+            trace,
+            self.auth_header()
+        );
+        client.send(&Message::binary(head.into_bytes()))?;
+
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            // An empty chunk ends the body
+            client.send(&Message::binary(buf[..n].to_vec()))?;
+            if n == 0 {
+                break;
+            }
+        }
+
+        Self::parse_response(client.recv()?)
+    }
+
     /// Make a request.
-    fn request(
+    pub fn request(
         &self,
         method: Method,
         path: &str,
         body: Option<JsonValue>,
     ) -> crate::Result<JsonValue> {
-        // Connect with or without timeout
-        let mut client = match self.timeout {
-            Some(timeout) => SocketClient::connect_timeout(&self.socket_path, timeout)?,
-            None => SocketClient::connect(&self.socket_path)?,
-        };
+        self.send_request(method, path, body, None)
+    }
+
+    /// Make a request that is safe to retry.
+    ///
+    /// Send the same `key` with every attempt; a server with
+    /// [`ApiServerConfig::idempotency`] set runs the handler once and replays
+    /// its response to the retries.
+    pub fn request_idempotent(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<JsonValue>,
+        key: &str,
+    ) -> crate::Result<JsonValue> {
+        self.send_request(method, path, body, Some(key))
+    }
+
+    /// Send a request with an optional idempotency key (internal)
+    fn send_request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<JsonValue>,
+        idempotency_key: Option<&str>,
+    ) -> crate::Result<JsonValue> {
+        let mut client = self.connect_client()?;
+
+        // The first hop of a trace starts it
+        let trace = TraceContext::current_or_root();
+        let _trace = trace.enter();
 
         // Build HTTP request
         let body_bytes = body
             .as_ref()
-            .map(|b| serde_json::to_vec(b).unwrap_or_default())
+            .map(|b| match self.format {
+                ContentFormat::Json => serde_json::to_vec(b).unwrap_or_default(),
+                ContentFormat::MsgPack => msgpack::to_vec(b),
+            })
             .unwrap_or_default();
 
+        let key_header = idempotency_key
+            .map(|key| format!("{}: {}\r\n", IDEMPOTENCY_HEADER, key))
+            .unwrap_or_default();
         let request_str = format!(
-            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: {}\r\nAccept: {}\r\n{}: {}\r\n{}{}Content-Length: {}\r\n\r\n",
             method.as_str(),
             path,
+            self.format.content_type(),
+            self.format.content_type(),
+            TRACE_HEADER,
+            trace,
+            self.auth_header(),
+            key_header,
             body_bytes.len()
         );
 
@@ -937,12 +2936,111 @@ impl ApiClient {
         client.send(&msg)?;
 
         // Read response
-        let response = client.recv()?;
+        Self::parse_response(client.recv()?)
+    }
+
+    /// Forward `req` to `path` on this client's server and return the
+    /// upstream response as it is.
+    ///
+    /// The method, query string, headers and raw body are passed on, so
+    /// authentication, idempotency keys and trace context reach the upstream
+    /// server. Its status, headers and body are relayed unchanged, whatever
+    /// the content type. Fails only if the upstream cannot be reached or
+    /// answers with something other than an HTTP response.
+    pub fn forward(&self, req: &Request, path: &str) -> crate::Result<Response> {
+        let mut client = self.connect_client()?;
+
+        let mut target = path.to_string();
+        let mut query: Vec<_> = req.query.iter().collect();
+        query.sort();
+        for (i, (key, value)) in query.into_iter().enumerate() {
+            target.push(if i == 0 { '?' } else { '&' });
+            target.push_str(&urlencoding_encode(key));
+            if !value.is_empty() {
+                target.push('=');
+                target.push_str(&urlencoding_encode(value));
+            }
+        }
+
+        let mut head = format!("{} {} HTTP/1.1\r\n", req.method.as_str(), target);
+        for (key, value) in &req.headers {
+            // Framing is recomputed for the buffered body
+            if matches!(
+                key.as_str(),
+                "content-length" | "transfer-encoding" | "connection"
+            ) {
+                continue;
+            }
+            head.push_str(&format!("{}: {}\r\n", key, value));
+        }
+        head.push_str(&format!("Content-Length: {}\r\n\r\n", req.raw_body.len()));
+
+        let mut request_bytes = head.into_bytes();
+        request_bytes.extend_from_slice(&req.raw_body);
+        client.send(&Message::binary(request_bytes))?;
+
+        let reply = client.recv()?;
+        let data = reply
+            .as_binary()
+            .ok_or_else(|| IpcError::deserialization("expected an HTTP response"))?;
+        Self::parse_raw_response(&data)
+    }
+
+    /// Rebuild a [`Response`] from its HTTP bytes (internal)
+    fn parse_raw_response(data: &[u8]) -> crate::Result<Response> {
+        let mut header_buf = [httparse::EMPTY_HEADER; 64];
+        let mut parsed = httparse::Response::new(&mut header_buf);
+        let body_start = match parsed.parse(data) {
+            Ok(httparse::Status::Complete(n)) => n,
+            Ok(httparse::Status::Partial) => {
+                return Err(IpcError::deserialization("incomplete HTTP response"))
+            }
+            Err(e) => return Err(IpcError::deserialization(e.to_string())),
+        };
+
+        let mut response = Response::new(parsed.code.unwrap_or(502));
+        if let Some(reason) = parsed.reason {
+            response.status_message = reason.to_string();
+        }
+        for header in parsed.headers.iter() {
+            // `to_bytes` adds its own
+            if header.name.eq_ignore_ascii_case("content-length") {
+                continue;
+            }
+            response.headers.insert(
+                header.name.to_string(),
+                String::from_utf8_lossy(header.value).into_owned(),
+            );
+        }
+        let body = &data[body_start..];
+        if !body.is_empty() {
+            response.body = ResponseBody::Bytes(body.to_vec());
+        }
+        Ok(response)
+    }
+
+    /// Connect with or without timeout (internal)
+    fn connect_client(&self) -> crate::Result<SocketClient> {
+        match self.timeout {
+            Some(timeout) => SocketClient::connect_timeout_with(
+                Arc::clone(&self.transport),
+                &self.socket_path,
+                timeout,
+            ),
+            None => SocketClient::connect_with(self.transport.as_ref(), &self.socket_path),
+        }
+    }
 
+    /// Decode the body of a response message (internal)
+    fn parse_response(response: Message) -> crate::Result<JsonValue> {
         // Extract response body
         if let Some(binary_data) = response.as_binary() {
             if let Some(body_start) = find_body_start(&binary_data) {
                 let body = &binary_data[body_start..];
+                let head = String::from_utf8_lossy(&binary_data[..body_start]).to_lowercase();
+                if head.contains(&format!("content-type: {}", msgpack::CONTENT_TYPE)) {
+                    return msgpack::from_slice(body);
+                }
                 serde_json::from_slice(body).map_err(|e| IpcError::Serialization(e.to_string()))
             } else {
                 Ok(JsonValue::Null)
@@ -956,6 +3054,43 @@ impl ApiClient {
     }
 }
 
+/// Receive the chunks of a streamed body until the empty end marker.
+fn body_chunks(conn: &mut Connection) -> impl Iterator<Item = std::io::Result<Vec<u8>>> + '_ {
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let chunk = match conn.recv() {
+            Ok(msg) => msg.as_binary().ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "expected a binary body chunk",
+                )
+            }),
+            Err(e) => Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                e.to_string(),
+            )),
+        };
+        match chunk {
+            Ok(chunk) if chunk.is_empty() => None,
+            Ok(chunk) => Some(Ok(chunk)),
+            Err(e) => {
+                done = true;
+                Some(Err(e))
+            }
+        }
+    })
+}
+
+/// Discard the rest of a streamed body nobody will read.
+fn drain_body(conn: &mut Connection, body: Option<BodySender>) {
+    if body.is_some() {
+        body_chunks(conn).for_each(drop);
+    }
+}
+
 fn find_body_start(data: &[u8]) -> Option<usize> {
     for i in 0..data.len().saturating_sub(3) {
         if &data[i..i + 4] == b"\r\n\r\n" {
@@ -1024,6 +3159,232 @@ mod tests {
         assert_eq!(resp.status, 404);
     }
 
+    #[test]
+    fn test_middleware_extensions() {
+        #[derive(Debug, PartialEq)]
+        struct User(String);
+        struct RequestId(u64);
+
+        let mut router = Router::new();
+        router
+            .middleware(|mut req, next| {
+                match req.header("authorization") {
+                    Some(token) => {
+                        let user = User(token.trim_start_matches("Bearer ").to_string());
+                        req.insert_extension(user);
+                    }
+                    None => return Response::new(401),
+                }
+                next(req)
+            })
+            .middleware(|mut req, next| {
+                assert!(req.extensions.contains::<User>());
+                req.insert_extension(RequestId(7));
+                next(req)
+            });
+        router.get("/whoami", |req| {
+            let user = req.extension::<User>().unwrap();
+            let id = req.extension::<RequestId>().unwrap();
+            Response::ok(serde_json::json!({"user": user.0, "request_id": id.0}))
+        });
+
+        let mut req = Request::new(Method::GET, "/whoami");
+        req.headers
+            .insert("authorization".to_string(), "Bearer alice".to_string());
+        let resp = router.handle(req);
+        let ResponseBody::Json(body) = resp.body else {
+            panic!("expected a JSON body");
+        };
+        assert_eq!(body, serde_json::json!({"user": "alice", "request_id": 7}));
+
+        let resp = router.handle(Request::new(Method::GET, "/whoami"));
+        assert_eq!(resp.status, 401);
+
+        let mut extensions = Extensions::new();
+        assert!(extensions.insert(User("a".into())).is_none());
+        assert_eq!(extensions.insert(User("b".into())), Some(User("a".into())));
+        extensions.get_mut::<User>().unwrap().0.push('!');
+        assert_eq!(extensions.get::<User>(), Some(&User("b!".into())));
+        assert!(extensions.get::<RequestId>().is_none());
+        assert_eq!(extensions.len(), 1);
+        assert_eq!(extensions.remove::<User>(), Some(User("b!".into())));
+        assert!(extensions.is_empty());
+    }
+
+    #[test]
+    fn test_route_index() {
+        let mut router = Router::new();
+        router.route_index(ROUTES_PATH);
+        router.get("/v1/tasks/{id}", |_| Response::no_content());
+
+        let resp = router.handle(Request::new(Method::GET, ROUTES_PATH));
+        let ResponseBody::Json(body) = resp.body else {
+            panic!("expected a JSON body");
+        };
+        assert_eq!(
+            body,
+            serde_json::json!([
+                {"method": "GET", "path": ROUTES_PATH},
+                {"method": "GET", "path": "/v1/tasks/{id}"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_metrics_route() {
+        let metrics = crate::metrics::MetricsRegistry::global().get_or_create("api_test_channel");
+        metrics.record_send(64);
+
+        let mut router = Router::new();
+        router.metrics_route("/metrics");
+
+        let resp = router.handle(Request::new(Method::GET, "/metrics"));
+        assert_eq!(resp.status, 200);
+        assert_eq!(
+            resp.headers.get("Content-Type").map(String::as_str),
+            Some("text/plain; version=0.0.4")
+        );
+        match resp.body {
+            ResponseBody::Text(body) => {
+                assert!(body.contains("ipckit_bytes_sent_total{channel=\"api_test_channel\"} 64"))
+            }
+            other => panic!("unexpected body: {:?}", other),
+        }
+
+        let mut req = Request::new(Method::GET, "/metrics");
+        req.query.insert("format".to_string(), "json".to_string());
+        let resp = router.handle(req);
+        match resp.body {
+            ResponseBody::Json(body) => {
+                assert_eq!(body["channels"]["api_test_channel"]["bytes_sent"], 64);
+                assert!(body["gauges"].is_object());
+            }
+            other => panic!("unexpected body: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_route_group_middleware() {
+        let order = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let mut router = Router::new();
+        let log = Arc::clone(&order);
+        router.middleware(move |req, next| {
+            log.lock().push("global");
+            next(req)
+        });
+        router.get("/health", |_| Response::ok(serde_json::json!({"ok": true})));
+
+        let log = Arc::clone(&order);
+        router.group("/v1/admin", |g| {
+            g.get("/open", |_| Response::ok(serde_json::json!("before auth")));
+            g.middleware(move |req, next| {
+                log.lock().push("auth");
+                match req.header("authorization") {
+                    Some("Bearer secret") => next(req),
+                    _ => Response::new(401),
+                }
+            });
+            g.delete("/tasks/{id}", |req| {
+                Response::ok(serde_json::json!({"cancelled": req.path_param("id")}))
+            });
+            g.group("/nested", |n| {
+                n.get("/ping", |_| Response::ok(serde_json::json!("pong")));
+            });
+        });
+
+        // The health check and routes added before the middleware stay open
+        assert_eq!(
+            router.handle(Request::new(Method::GET, "/health")).status,
+            200
+        );
+        assert_eq!(
+            router
+                .handle(Request::new(Method::GET, "/v1/admin/open"))
+                .status,
+            200
+        );
+        assert_eq!(*order.lock(), ["global", "global"]);
+
+        let delete = Request::new(Method::DELETE, "/v1/admin/tasks/7");
+        assert_eq!(router.handle(delete).status, 401);
+        let ping = Request::new(Method::GET, "/v1/admin/nested/ping");
+        assert_eq!(router.handle(ping).status, 401);
+
+        let mut delete = Request::new(Method::DELETE, "/v1/admin/tasks/7");
+        delete
+            .headers
+            .insert("authorization".to_string(), "Bearer secret".to_string());
+        let resp = router.handle(delete);
+        assert_eq!(resp.status, 200);
+        order.lock().clear();
+        let mut ping = Request::new(Method::GET, "/v1/admin/nested/ping");
+        ping.headers
+            .insert("authorization".to_string(), "Bearer secret".to_string());
+        assert_eq!(router.handle(ping).status, 200);
+        // Global middleware runs first
+        assert_eq!(*order.lock(), ["global", "auth"]);
+    }
+
+    #[test]
+    fn test_scope_versioning_and_negotiation() {
+        let mut router = Router::new();
+        router
+            .scope("/v1")
+            .deprecated(
+                Deprecation::new()
+                    .sunset("Sat, 01 May 2027 00:00:00 GMT")
+                    .successor("/v2/status"),
+            )
+            .get("/status", |_| Response::ok(serde_json::json!({"ok": true})));
+        router
+            .scope("/v2/")
+            .scope("status")
+            .get("", |_| Response::ok(serde_json::json!({"healthy": true})));
+
+        let resp = router.handle(Request::new(Method::GET, "/v1/status"));
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.headers["Deprecation"], "true");
+        assert_eq!(resp.headers["Sunset"], "Sat, 01 May 2027 00:00:00 GMT");
+        assert_eq!(
+            resp.headers["Link"],
+            "</v2/status>; rel=\"successor-version\""
+        );
+
+        let mut req = Request::new(Method::GET, "/v2/status");
+        req.headers.insert(
+            "accept".to_string(),
+            "application/json;q=0.5, application/msgpack".to_string(),
+        );
+        let resp = router.handle(req);
+        assert!(!resp.headers.contains_key("Deprecation"));
+        assert_eq!(resp.headers["Content-Type"], msgpack::CONTENT_TYPE);
+        match resp.body {
+            ResponseBody::Bytes(ref bytes) => assert_eq!(
+                msgpack::from_slice(bytes).unwrap(),
+                serde_json::json!({"healthy": true})
+            ),
+            ref other => panic!("expected MessagePack bytes, got {:?}", other),
+        }
+
+        // Old clients send no Accept header, or a wildcard, and keep JSON
+        assert_eq!(ContentFormat::negotiate(None), ContentFormat::Json);
+        assert_eq!(
+            ContentFormat::negotiate(Some("*/*, application/msgpack;q=0.9")),
+            ContentFormat::Json
+        );
+
+        // MessagePack request bodies are decoded like JSON ones
+        let payload = msgpack::to_vec(&serde_json::json!({"name": "build"}));
+        let mut raw = format!(
+            "POST /v2/tasks HTTP/1.1\r\nContent-Type: application/msgpack\r\nContent-Length: {}\r\n\r\n",
+            payload.len()
+        )
+        .into_bytes();
+        raw.extend(payload);
+        let req = Request::parse(&raw).unwrap();
+        assert_eq!(req.body.unwrap()["name"], "build");
+    }
+
     #[test]
     fn test_response_to_bytes() {
         let resp = Response::ok(serde_json::json!({"key": "value"}));
@@ -1035,6 +3396,96 @@ mod tests {
         assert!(text.contains("\"key\":\"value\""));
     }
 
+    #[test]
+    fn test_error_responses() {
+        let resp = Response::from(IpcError::NotFound("task-9".to_string()));
+        assert_eq!(resp.status, 404);
+        let ResponseBody::Json(body) = &resp.body else {
+            panic!("expected JSON body");
+        };
+        assert_eq!(body["error"], "Not Found");
+        assert_eq!(body["kind"], "not_found");
+        assert_eq!(body["code"], ErrorCode::NotFound.as_i32());
+        assert_eq!(body["retryable"], false);
+
+        assert_eq!(Response::from_error(&IpcError::Timeout).status, 504);
+        assert_eq!(
+            Response::from_error(&IpcError::InvalidState("busy".into())).status,
+            409
+        );
+
+        let msg = Message::from_error(&IpcError::Closed);
+        assert_eq!(msg.error_code(), Some(ErrorCode::Closed));
+        assert_eq!(msg.payload["retryable"], true);
+        assert_eq!(Message::text("hi").error_code(), None);
+    }
+
+    #[test]
+    fn test_into_response_handlers() {
+        #[derive(serde::Deserialize)]
+        struct Add {
+            a: i64,
+            b: i64,
+        }
+
+        let mut router = Router::new();
+        router
+            .post("/add", |req| -> Result<Json<i64>, ApiError> {
+                let add: Add = req.json()?;
+                if add.a < 0 {
+                    return Err(ApiError::bad_request("a must not be negative"));
+                }
+                Ok(Json(add.a + add.b))
+            })
+            .get("/missing", |_req| -> crate::Result<JsonValue> {
+                Err(IpcError::NotFound("thing".to_string()))
+            })
+            .delete("/thing", |_req| (202, ()))
+            .get("/invalid", |_req| {
+                let mut errors = ValidationErrors::new();
+                errors.add("name", "not_empty", "must not be empty");
+                Err::<(), _>(ApiError::from(errors))
+            });
+
+        let call = |method, path: &str, body: Option<JsonValue>| {
+            let mut req = Request::new(method, path);
+            req.body = body;
+            let resp = router.handle(req);
+            let body = match resp.body {
+                ResponseBody::Json(v) => v,
+                _ => JsonValue::Null,
+            };
+            (resp.status, body)
+        };
+
+        let ok = call(
+            Method::POST,
+            "/add",
+            Some(serde_json::json!({"a": 1, "b": 2})),
+        );
+        assert_eq!(ok, (200, serde_json::json!(3)));
+        let (status, body) = call(Method::POST, "/add", Some(serde_json::json!({"a": 1})));
+        assert_eq!(
+            (status, body["kind"].as_str()),
+            (400, Some("deserialization"))
+        );
+        assert_eq!(
+            call(
+                Method::POST,
+                "/add",
+                Some(serde_json::json!({"a": -1, "b": 0}))
+            )
+            .0,
+            400
+        );
+        let (status, body) = call(Method::GET, "/missing", None);
+        assert_eq!((status, body["error"].as_str()), (404, Some("Not Found")));
+        assert_eq!(call(Method::DELETE, "/thing", None).0, 202);
+        let (status, body) = call(Method::GET, "/invalid", None);
+        assert_eq!(status, 422);
+        assert_eq!(body["errors"][0]["field"], "name");
+    }
+
     #[test]
     fn test_request_parse() {
         let raw = b"GET /v1/tasks?limit=10 HTTP/1.1\r\nHost: localhost\r\n\r\n";
@@ -1044,4 +3495,492 @@ mod tests {
         assert_eq!(req.path, "/v1/tasks");
         assert_eq!(req.query.get("limit"), Some(&"10".to_string()));
     }
+
+    #[test]
+    fn test_request_parse_body_limit() {
+        let raw = b"POST /v1/tasks HTTP/1.1\r\nContent-Length: 999999999999\r\n\r\n{}";
+        match Request::parse_with_limit(raw, 1024) {
+            Err(ParseError::BodyTooLarge { size, limit }) => {
+                assert_eq!((size, limit), (999_999_999_999, 1024));
+            }
+            other => panic!("expected BodyTooLarge, got {:?}", other.map(|r| r.path)),
+        }
+
+        let raw = b"POST /v1/tasks HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}";
+        assert_eq!(Request::parse_with_limit(raw, 2).unwrap().raw_body, b"{}");
+    }
+
+    #[test]
+    fn test_request_parse_headers_and_chunked_body() {
+        // Folded and repeated headers, lowercase method, bare newlines
+        let raw = b"post /v1/tasks HTTP/1.1\nX-Note: first\n  second\nAccept: a\nACCEPT: b\n\n";
+        let req = Request::parse(raw).unwrap();
+        assert_eq!(req.method, Method::POST);
+        assert_eq!(req.header("x-note"), Some("first second"));
+        assert_eq!(req.header("Accept"), Some("a, b"));
+
+        // A chunked body sent along with the head is decoded
+        let raw = b"POST /v1/tasks HTTP/1.1\r\nContent-Type: application/json\r\n\
+            Transfer-Encoding: chunked\r\n\r\n3\r\n{\"a\r\n4\r\n\":1}\r\n0\r\n\r\n";
+        let req = Request::parse(raw).unwrap();
+        assert_eq!(req.raw_body, b"{\"a\":1}");
+        assert_eq!(req.body.as_ref().unwrap()["a"], 1);
+        assert!(!req.is_chunked());
+        assert!(matches!(
+            Request::parse_with_limit(raw, 4),
+            Err(ParseError::BodyTooLarge { .. })
+        ));
+
+        let status = |raw: &[u8], limits: &RequestLimits| {
+            Request::parse_with_limits(raw, limits)
+                .err()
+                .map(|e| e.status())
+        };
+        let limits = RequestLimits {
+            max_headers: 2,
+            max_header_size: 64,
+            ..RequestLimits::default()
+        };
+        assert_eq!(
+            status(b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n", &limits),
+            Some(431)
+        );
+        let long = format!("GET / HTTP/1.1\r\nA: {}\r\n\r\n", "x".repeat(64));
+        assert_eq!(status(long.as_bytes(), &limits), Some(431));
+        // Even without the end of the head in sight
+        assert_eq!(status(&[b'a'; 100], &limits), Some(431));
+
+        // Malformed input is rejected rather than guessed at
+        for raw in [
+            &b"GET / HTTP/1.1\r\nBad Header: 1\r\n\r\n"[..],
+            b"GET / HTTP/1.1\r\nHost: x\r\n",
+            b"BREW / HTTP/1.1\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}",
+            b"POST / HTTP/1.1\r\nContent-Length: -1\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: 2\r\nTransfer-Encoding: chunked\r\n\r\n{}",
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+        ] {
+            assert_eq!(status(raw, &RequestLimits::default()), Some(400));
+        }
+    }
+
+    #[test]
+    fn test_connection_limits() {
+        let socket_name = format!("test_api_limits_{}", std::process::id());
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&socket_name),
+            max_body_size: 16,
+            rate_limit: Some(RateLimit {
+                per_second: 0.001,
+                burst: 2,
+            }),
+            ..Default::default()
+        });
+        server.router().post("/echo", |_req| Response::no_content());
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let status = |client: &mut SocketClient, body: &str| {
+            let raw = format!(
+                "POST /echo HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            client.send(&Message::binary(raw.into_bytes())).unwrap();
+            let reply = client.recv().unwrap().as_binary().unwrap();
+            String::from_utf8_lossy(&reply[9..12]).into_owned()
+        };
+
+        let mut client = SocketClient::connect(&socket_name).unwrap();
+        assert_eq!(status(&mut client, "{}"), "204");
+        assert_eq!(status(&mut client, &"x".repeat(17)), "413");
+        assert_eq!(status(&mut client, "{}"), "204");
+        assert_eq!(status(&mut client, "{}"), "429");
+
+        // Each connection has its own bucket
+        let mut other = SocketClient::connect(&socket_name).unwrap();
+        assert_eq!(status(&mut other, "{}"), "204");
+    }
+
+    #[test]
+    fn test_oversized_request_rejected_before_body() {
+        use crate::local_socket::LocalSocketStream;
+        use std::io::Write;
+
+        let socket_name = format!("test_api_early_413_{}", std::process::id());
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&socket_name),
+            max_body_size: 16,
+            ..Default::default()
+        });
+        server.router().post("/echo", |_req| Response::no_content());
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let frame = |raw: Vec<u8>| {
+            let data = serde_json::to_vec(&Message::binary(raw)).unwrap();
+            let mut frame = (data.len() as u32).to_le_bytes().to_vec();
+            frame.extend_from_slice(&data);
+            frame
+        };
+        let status = |stream: &mut LocalSocketStream| {
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).unwrap();
+            let mut data = vec![0u8; u32::from_le_bytes(len) as usize];
+            stream.read_exact(&mut data).unwrap();
+            let reply = serde_json::from_slice::<Message>(&data).unwrap();
+            let reply = reply.as_binary().unwrap();
+            String::from_utf8_lossy(&reply[9..12]).into_owned()
+        };
+
+        let body = vec![b'x'; 1 << 20];
+        let mut raw = format!(
+            "POST /echo HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        raw.extend_from_slice(&body);
+        let big = frame(raw);
+
+        // The reply comes while most of the frame is still unsent
+        let mut stream = LocalSocketStream::connect(&socket_name).unwrap();
+        stream.write_all(&big[..256]).unwrap();
+        assert_eq!(status(&mut stream), "413");
+
+        // The rest of the frame is dropped and the connection stays usable
+        stream.write_all(&big[256..]).unwrap();
+        let small = b"POST /echo HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}".to_vec();
+        stream.write_all(&frame(small)).unwrap();
+        assert_eq!(status(&mut stream), "204");
+    }
+
+    #[test]
+    fn test_streamed_request_body() {
+        let socket_name = format!("test_api_stream_{}", std::process::id());
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&socket_name),
+            max_body_size: 1024,
+            ..Default::default()
+        });
+        server
+            .router()
+            .post("/upload", |mut req| {
+                let streaming = req.is_streaming();
+                let mut body = Vec::new();
+                req.body_reader().read_to_end(&mut body).unwrap();
+                let sum: u64 = body.iter().map(|&b| b as u64).sum();
+                Response::ok(serde_json::json!({
+                    "streaming": streaming,
+                    "len": body.len(),
+                    "sum": sum,
+                }))
+            })
+            .post("/ignore", |_req| Response::ok(serde_json::json!("ignored")));
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        // Far above max_body_size and spanning many chunks
+        let data: Vec<u8> = (0..STREAM_CHUNK_SIZE * 5 + 123).map(|i| i as u8).collect();
+        let client = ApiClient::new(&socket_name);
+        let result = client.post_stream("/upload", data.as_slice()).unwrap();
+        assert_eq!(result["streaming"], true);
+        assert_eq!(result["len"], data.len());
+        let sum: u64 = data.iter().map(|&b| b as u64).sum();
+        assert_eq!(result["sum"], sum);
+
+        // A handler that never reads the body still gets the client its response
+        let ignored = client.post_stream("/ignore", data.as_slice()).unwrap();
+        assert_eq!(ignored, "ignored");
+
+        // Buffered bodies read the same way
+        let result = client
+            .post("/upload", Some(serde_json::json!([1, 2])))
+            .unwrap();
+        assert_eq!(result["streaming"], false);
+        assert_eq!(result["len"], 5);
+    }
+
+    #[test]
+    fn test_trace_context_propagates() {
+        let socket_name = format!("test_api_trace_{}", std::process::id());
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&socket_name),
+            ..Default::default()
+        });
+        server.router().get("/trace", |_req| {
+            let trace = TraceContext::current().unwrap();
+            Response::ok(serde_json::json!({
+                "trace_id": trace.trace_id(),
+                "parent": trace.parent_span_id(),
+            }))
+        });
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let client = ApiClient::new(&socket_name);
+        let trace = TraceContext::root();
+        let reply = {
+            let _trace = trace.enter();
+            client.get("/trace").unwrap()
+        };
+        assert_eq!(reply["trace_id"], trace.trace_id());
+        assert_eq!(reply["parent"], trace.span_id());
+
+        // Without a current context the request starts its own trace
+        let first = client.get("/trace").unwrap();
+        let second = client.get("/trace").unwrap();
+        assert_ne!(first["trace_id"], second["trace_id"]);
+    }
+
+    #[test]
+    fn test_idempotent_requests_run_once() {
+        let socket_name = format!("test_api_idempotency_{}", std::process::id());
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&socket_name),
+            idempotency: Some(IdempotencyMiddleware::new()),
+            ..Default::default()
+        });
+        let created = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = Arc::clone(&created);
+        server.router().post("/v1/tasks", move |_req| {
+            let n = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Response::created(serde_json::json!({"id": n}))
+        });
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let client = ApiClient::new(&socket_name);
+        let body = Some(serde_json::json!({"name": "build"}));
+        let first = client
+            .request_idempotent(Method::POST, "/v1/tasks", body.clone(), "retry-1")
+            .unwrap();
+        let retry = client
+            .request_idempotent(Method::POST, "/v1/tasks", body.clone(), "retry-1")
+            .unwrap();
+        assert_eq!(first, retry);
+        assert_eq!(created.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        client.post("/v1/tasks", body).unwrap();
+        assert_eq!(created.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_auth_runs_before_idempotent_replay() {
+        let socket_name = format!("test_api_auth_idempotency_{}", std::process::id());
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&socket_name),
+            idempotency: Some(IdempotencyMiddleware::new()),
+            auth_token: Some("s3cret".to_string()),
+            ..Default::default()
+        });
+        server
+            .router()
+            .middleware(|req, next| next(req))
+            .post("/v1/tasks", |_req| {
+                Response::created(serde_json::json!({"id": "task-1"}))
+            });
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let body = Some(serde_json::json!({"name": "build"}));
+        let authorized = ApiClient::new(&socket_name).bearer_token("s3cret");
+        let created = authorized
+            .request_idempotent(Method::POST, "/v1/tasks", body.clone(), "k1")
+            .unwrap();
+        assert_eq!(created["id"], "task-1");
+
+        // The cached response is not replayed to a client without the token
+        let anonymous = ApiClient::new(&socket_name);
+        let replay = anonymous
+            .request_idempotent(Method::POST, "/v1/tasks", body.clone(), "k1")
+            .unwrap();
+        assert_eq!(replay["error"], "Unauthorized");
+        let wrong = ApiClient::new(&socket_name).bearer_token("s3cres");
+        let replay = wrong
+            .request_idempotent(Method::POST, "/v1/tasks", body, "k1")
+            .unwrap();
+        assert_eq!(replay["error"], "Unauthorized");
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"Bearer abc", b"Bearer abc"));
+        assert!(!constant_time_eq(b"Bearer abc", b"Bearer abd"));
+        assert!(!constant_time_eq(b"Bearer ab", b"Bearer abc"));
+    }
+
+    #[test]
+    fn test_admin_connection_routes() {
+        let socket_name = format!("test_api_admin_{}", std::process::id());
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&socket_name),
+            admin_routes: true,
+            ..Default::default()
+        });
+        server.router().get("/ping", |_req| Response::no_content());
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let mut idle = SocketClient::connect(&socket_name).unwrap();
+        let raw = b"GET /ping HTTP/1.1\r\n\r\n".to_vec();
+        idle.send(&Message::binary(raw)).unwrap();
+        idle.recv().unwrap();
+
+        let client = ApiClient::new(&socket_name);
+        let connections = client.get("/v1/connections").unwrap();
+        let connections = connections.as_array().unwrap();
+        // The idle client and the admin request itself
+        assert_eq!(connections.len(), 2);
+        let id = connections[0]["id"].as_u64().unwrap();
+        assert_eq!(connections[0]["messages_received"], 1);
+
+        let kicked = client.delete(&format!("/v1/connections/{}", id)).unwrap();
+        assert_eq!(kicked["id"], id);
+        assert!(idle.recv().is_err());
+        let missing = client.get("/v1/connections/9999").unwrap();
+        assert_eq!(missing["error"], "Not Found");
+    }
+
+    #[test]
+    fn test_task_routes_delete_cancels_task() {
+        let manager = Arc::new(TaskManager::new(Default::default()));
+        let mut router = Router::new();
+        router.task_routes(Arc::clone(&manager));
+
+        let mut req = Request::new(Method::POST, "/v1/tasks");
+        req.body = Some(serde_json::json!({"id": "cli-1", "name": "Build", "status": "running"}));
+        assert_eq!(router.handle(req).status, 201);
+
+        let handle = manager.get_handle("cli-1").unwrap();
+        let token = handle.cancel_token();
+        assert!(!token.is_cancelled());
+
+        let resp = router.handle(Request::new(Method::DELETE, "/v1/tasks/cli-1"));
+        assert_eq!(resp.status, 200);
+        assert!(token.is_cancelled());
+
+        // A late failure report from the killed process does not overwrite the status
+        let resp = router.handle(Request::new(Method::POST, "/v1/tasks/cli-1/fail"));
+        assert_eq!(resp.status, 409);
+        assert_eq!(
+            manager.get("cli-1").unwrap().status,
+            crate::task_manager::TaskStatus::Cancelled
+        );
+    }
+
+    #[test]
+    fn test_task_logs_route() {
+        let manager = Arc::new(TaskManager::new(Default::default()));
+        let mut router = Router::new();
+        router.task_routes(Arc::clone(&manager));
+
+        let task = manager.create(TaskBuilder::new("Build", "build").id("build-1"));
+        task.log("warn", "low disk");
+        task.stdout("compiling");
+        task.set_progress(50, None);
+
+        let logs = |query: &str| {
+            let mut req = Request::new(Method::GET, "/v1/tasks/build-1/logs");
+            for pair in query.split('&').filter(|p| !p.is_empty()) {
+                let (k, v) = pair.split_once('=').unwrap();
+                req.query.insert(k.to_string(), v.to_string());
+            }
+            match router.handle(req).body {
+                ResponseBody::Json(body) => body,
+                other => panic!("unexpected body: {:?}", other),
+            }
+        };
+
+        let body = logs("");
+        assert_eq!(body["status"], "pending");
+        let lines = body["logs"].as_array().unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "warn");
+        assert_eq!(lines[1]["message"], "compiling");
+
+        let last = lines[1]["id"].as_u64().unwrap();
+        let body = logs(&format!("after={}&timeout_ms=10", last));
+        assert!(body["logs"].as_array().unwrap().is_empty());
+
+        let resp = router.handle(Request::new(Method::GET, "/v1/tasks/missing/logs"));
+        assert_eq!(resp.status, 404);
+    }
+
+    #[cfg(not(feature = "backend-interprocess"))]
+    #[test]
+    fn test_handler_panic_answers_500() {
+        let socket_name = format!("test_api_panic_{}", std::process::id());
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&socket_name),
+            ..Default::default()
+        });
+        server
+            .router()
+            .get("/panic", |_req| -> Response { panic!("handler bug") })
+            .get("/ok", |_req| Response::ok(serde_json::json!("ok")));
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        // The connection's thread survives to serve the next request
+        let mut client = SocketClient::connect(&socket_name).unwrap();
+        for (path, expected) in [("/panic", "500"), ("/ok", "200")] {
+            let head = format!("GET {} HTTP/1.1\r\nContent-Length: 0\r\n\r\n", path);
+            client.send(&Message::binary(head.into_bytes())).unwrap();
+            let reply = client.recv().unwrap().as_binary().unwrap();
+            assert!(String::from_utf8_lossy(&reply).starts_with(&format!("HTTP/1.1 {}", expected)));
+        }
+    }
+
+    #[test]
+    fn test_aborted_request_cancels_linked_task() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let socket_name = format!("test_api_abort_{}", std::process::id());
+        let manager = Arc::new(TaskManager::new(Default::default()));
+        let observed = Arc::new(AtomicBool::new(false));
+
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&socket_name),
+            ..Default::default()
+        });
+        {
+            let manager = Arc::clone(&manager);
+            let observed = Arc::clone(&observed);
+            server.router().post("/slow", move |req| {
+                let task = manager.create(
+                    TaskBuilder::new("slow", "test")
+                        .id("slow-1")
+                        .cancel_on(&req.cancel_token),
+                );
+                task.start();
+                let deadline = std::time::Instant::now() + Duration::from_secs(5);
+                while !task.is_cancelled() && std::time::Instant::now() < deadline {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                observed.store(task.is_cancelled(), Ordering::SeqCst);
+                Response::no_content()
+            });
+        }
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let mut client = SocketClient::connect(&socket_name).unwrap();
+        client
+            .send(&Message::binary(
+                b"POST /slow HTTP/1.1\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            ))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        drop(client);
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(3);
+        while !observed.load(Ordering::SeqCst) && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(observed.load(Ordering::SeqCst));
+        assert_eq!(
+            manager.get("slow-1").unwrap().status,
+            crate::task_manager::TaskStatus::Cancelled
+        );
+    }
 }
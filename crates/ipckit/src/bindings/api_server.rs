@@ -1,5 +1,6 @@
 //! Python bindings for API Server
 
+use crate::access_log::LoggingMiddleware;
 use crate::api_server::{ApiClient, ApiServerConfig, Request, Response};
 use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
@@ -22,11 +23,14 @@ pub struct PyApiServerConfig {
 #[pymethods]
 impl PyApiServerConfig {
     #[new]
-    #[pyo3(signature = (socket_path=None, enable_cors=true, cors_origins=None))]
+    #[pyo3(signature = (socket_path=None, enable_cors=true, cors_origins=None, access_log=false, admin_routes=false, health_routes=false))]
     fn new(
         socket_path: Option<String>,
         enable_cors: bool,
         cors_origins: Option<Vec<String>>,
+        access_log: bool,
+        admin_routes: bool,
+        health_routes: bool,
     ) -> Self {
         let mut config = ApiServerConfig::default();
 
@@ -40,6 +44,13 @@ impl PyApiServerConfig {
             config.cors_origins = origins;
         }
 
+        if access_log {
+            config.access_log = Some(LoggingMiddleware::new());
+        }
+
+        config.admin_routes = admin_routes;
+        config.health_routes = health_routes;
+
         Self { inner: config }
     }
 
@@ -73,6 +84,50 @@ impl PyApiServerConfig {
         self.inner.cors_origins = origins;
     }
 
+    /// Whether every request is traced in an `api.request` span.
+    #[getter]
+    fn access_log(&self) -> bool {
+        self.inner.access_log.is_some()
+    }
+
+    #[setter]
+    fn set_access_log(&mut self, value: bool) {
+        self.inner.access_log = value.then(LoggingMiddleware::new);
+    }
+
+    /// Largest accepted request body in bytes.
+    #[getter]
+    fn max_body_size(&self) -> usize {
+        self.inner.max_body_size
+    }
+
+    #[setter]
+    fn set_max_body_size(&mut self, value: usize) {
+        self.inner.max_body_size = value;
+    }
+
+    /// Whether `/v1/connections` admin routes are served.
+    #[getter]
+    fn admin_routes(&self) -> bool {
+        self.inner.admin_routes
+    }
+
+    #[setter]
+    fn set_admin_routes(&mut self, value: bool) {
+        self.inner.admin_routes = value;
+    }
+
+    /// Whether `/healthz` and `/readyz` are served.
+    #[getter]
+    fn health_routes(&self) -> bool {
+        self.inner.health_routes
+    }
+
+    #[setter]
+    fn set_health_routes(&mut self, value: bool) {
+        self.inner.health_routes = value;
+    }
+
     fn __repr__(&self) -> String {
         format!(
             "ApiServerConfig(socket_path='{}', enable_cors={}, cors_origins={:?})",
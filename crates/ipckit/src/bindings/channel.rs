@@ -6,6 +6,7 @@ use pyo3::prelude::*;
 use pyo3::types::{PyBytes, PyDict, PyList};
 use std::time::Duration;
 
+use super::is_end_of_stream;
 use super::json_utils::{json_value_to_py, py_to_json_value};
 use crate::error::IpcError;
 use crate::file_channel::{
@@ -13,9 +14,22 @@ use crate::file_channel::{
 };
 
 /// Python wrapper for IpcChannel
+///
+/// Usable as a context manager, which closes the channel on exit, and as an
+/// iterator over received messages, which ends when the peer disconnects.
 #[pyclass(name = "IpcChannel")]
 pub struct PyIpcChannel {
-    inner: crate::channel::IpcChannel<Vec<u8>>,
+    inner: Option<crate::channel::IpcChannel<Vec<u8>>>,
+}
+
+impl PyIpcChannel {
+    fn get(&self) -> PyResult<&crate::channel::IpcChannel<Vec<u8>>> {
+        Ok(self.inner.as_ref().ok_or(IpcError::Closed)?)
+    }
+
+    fn get_mut(&mut self) -> PyResult<&mut crate::channel::IpcChannel<Vec<u8>>> {
+        Ok(self.inner.as_mut().ok_or(IpcError::Closed)?)
+    }
 }
 
 #[pymethods]
@@ -24,44 +38,53 @@ impl PyIpcChannel {
     #[staticmethod]
     fn create(name: &str) -> PyResult<Self> {
         let inner = crate::channel::IpcChannel::create(name)?;
-        Ok(Self { inner })
+        Ok(Self { inner: Some(inner) })
     }
 
     /// Connect to an existing IPC channel
     #[staticmethod]
     fn connect(name: &str) -> PyResult<Self> {
         let inner = crate::channel::IpcChannel::connect(name)?;
-        Ok(Self { inner })
+        Ok(Self { inner: Some(inner) })
     }
 
     /// Get the channel name
     #[getter]
-    fn name(&self) -> &str {
-        self.inner.name()
+    fn name(&self) -> PyResult<String> {
+        Ok(self.get()?.name().to_string())
     }
 
     /// Check if this is the server end
     #[getter]
-    fn is_server(&self) -> bool {
-        self.inner.is_server()
+    fn is_server(&self) -> PyResult<bool> {
+        Ok(self.get()?.is_server())
+    }
+
+    /// Check if the channel has been closed
+    #[getter]
+    fn closed(&self) -> bool {
+        self.inner.is_none()
     }
 
     /// Wait for a client to connect (server only)
     fn wait_for_client(&mut self, py: Python<'_>) -> PyResult<()> {
+        let inner = self.get_mut()?;
         // Release GIL to allow other Python threads to run
-        py.detach(|| self.inner.wait_for_client())?;
+        py.detach(|| inner.wait_for_client())?;
         Ok(())
     }
 
     /// Send bytes through the channel
     fn send(&mut self, py: Python<'_>, data: Vec<u8>) -> PyResult<()> {
-        py.detach(|| self.inner.send_bytes(&data))?;
+        let inner = self.get_mut()?;
+        py.detach(|| inner.send_bytes(&data))?;
         Ok(())
     }
 
     /// Receive bytes from the channel
     fn recv(&mut self, py: Python<'_>) -> PyResult<Py<PyBytes>> {
-        let data = py.detach(|| self.inner.recv_bytes())?;
+        let inner = self.get_mut()?;
+        let data = py.detach(|| inner.recv_bytes())?;
         Ok(PyBytes::new(py, &data).into())
     }
 
@@ -70,25 +93,71 @@ impl PyIpcChannel {
         let value = py_to_json_value(obj)?;
         let json_bytes = serde_json::to_vec(&value)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
-        py.detach(|| self.inner.send_bytes(&json_bytes))?;
+        let inner = self.get_mut()?;
+        py.detach(|| inner.send_bytes(&json_bytes))?;
         Ok(())
     }
 
     /// Receive a JSON object (uses Rust serde_json)
     fn recv_json(&mut self, py: Python<'_>) -> PyResult<Py<PyAny>> {
-        let data = py.detach(|| self.inner.recv_bytes())?;
+        let inner = self.get_mut()?;
+        let data = py.detach(|| inner.recv_bytes())?;
         let value: serde_json::Value =
             serde_json::from_slice(&data).map_err(|e| IpcError::deserialization(e.to_string()))?;
         json_value_to_py(py, &value)
     }
+
+    /// Close the channel, releasing the underlying pipe
+    fn close(&mut self) {
+        self.inner = None;
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<Py<PyAny>>,
+        _exc_value: Option<Py<PyAny>>,
+        _traceback: Option<Py<PyAny>>,
+    ) {
+        self.close();
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Receive the next message, stopping once the peer has disconnected
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<Py<PyBytes>>> {
+        let inner = self.get_mut()?;
+        match py.detach(|| inner.recv_bytes()) {
+            Ok(data) => Ok(Some(PyBytes::new(py, &data).into())),
+            Err(e) if is_end_of_stream(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
 }
 
 /// Python wrapper for FileChannel - File-based IPC for frontend-backend communication
 ///
-/// All JSON serialization is done in Rust for better performance.
+/// All JSON serialization is done in Rust for better performance. Usable as
+/// a context manager, which closes the channel on exit, and as an iterator
+/// over the messages waiting in the inbox.
 #[pyclass(name = "FileChannel")]
 pub struct PyFileChannel {
-    inner: RustFileChannel,
+    inner: Option<RustFileChannel>,
+}
+
+impl PyFileChannel {
+    fn get(&self) -> PyResult<&RustFileChannel> {
+        Ok(self.inner.as_ref().ok_or(IpcError::Closed)?)
+    }
+
+    fn get_mut(&mut self) -> PyResult<&mut RustFileChannel> {
+        Ok(self.inner.as_mut().ok_or(IpcError::Closed)?)
+    }
 }
 
 #[pymethods]
@@ -97,52 +166,52 @@ impl PyFileChannel {
     #[staticmethod]
     fn backend(dir: &str) -> PyResult<Self> {
         let inner = RustFileChannel::backend(dir)?;
-        Ok(Self { inner })
+        Ok(Self { inner: Some(inner) })
     }
 
     /// Create a frontend-side file channel
     #[staticmethod]
     fn frontend(dir: &str) -> PyResult<Self> {
         let inner = RustFileChannel::frontend(dir)?;
-        Ok(Self { inner })
+        Ok(Self { inner: Some(inner) })
     }
 
     /// Get the channel directory path
     #[getter]
-    fn dir(&self) -> String {
-        self.inner.dir().to_string_lossy().to_string()
+    fn dir(&self) -> PyResult<String> {
+        Ok(self.get()?.dir().to_string_lossy().to_string())
     }
 
     /// Send a request message (JSON serialization done in Rust)
     fn send_request(&self, method: &str, params: &Bound<'_, PyAny>) -> PyResult<String> {
         let json_value = py_to_json_value(params)?;
-        let id = self.inner.send_request(method, json_value)?;
+        let id = self.get()?.send_request(method, json_value)?;
         Ok(id)
     }
 
     /// Send a response to a request
     fn send_response(&self, request_id: &str, result: &Bound<'_, PyAny>) -> PyResult<()> {
         let json_value = py_to_json_value(result)?;
-        self.inner.send_response(request_id, json_value)?;
+        self.get()?.send_response(request_id, json_value)?;
         Ok(())
     }
 
     /// Send an error response
     fn send_error(&self, request_id: &str, error: &str) -> PyResult<()> {
-        self.inner.send_error(request_id, error)?;
+        self.get()?.send_error(request_id, error)?;
         Ok(())
     }
 
     /// Send an event (fire-and-forget, no response expected)
     fn send_event(&self, name: &str, payload: &Bound<'_, PyAny>) -> PyResult<()> {
         let json_value = py_to_json_value(payload)?;
-        self.inner.send_event(name, json_value)?;
+        self.get()?.send_event(name, json_value)?;
         Ok(())
     }
 
     /// Receive all new messages
     fn recv(&mut self, py: Python<'_>) -> PyResult<Py<PyAny>> {
-        let messages = self.inner.recv()?;
+        let messages = self.get_mut()?.recv()?;
         let list = PyList::empty(py);
         for msg in messages {
             list.append(file_message_to_py(py, msg)?)?;
@@ -152,12 +221,44 @@ impl PyFileChannel {
 
     /// Receive a single new message (non-blocking)
     fn recv_one(&mut self, py: Python<'_>) -> PyResult<Py<PyAny>> {
-        match self.inner.recv_one()? {
+        match self.get_mut()?.recv_one()? {
             Some(msg) => file_message_to_py(py, msg),
             None => Ok(py.None()),
         }
     }
 
+    /// Start watching the inbox so recv_wait/wait_response block until it changes
+    fn watch(&mut self) -> PyResult<()> {
+        self.get_mut()?.watch()?;
+        Ok(())
+    }
+
+    /// Check if the inbox is being watched
+    #[getter]
+    fn is_watching(&self) -> bool {
+        self.inner
+            .as_ref()
+            .is_some_and(RustFileChannel::is_watching)
+    }
+
+    /// Check if the channel has been closed
+    #[getter]
+    fn closed(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    /// Wait up to timeout_ms for new messages (empty list on timeout)
+    fn recv_wait(&mut self, py: Python<'_>, timeout_ms: u64) -> PyResult<Py<PyAny>> {
+        let timeout = Duration::from_millis(timeout_ms);
+        let inner = self.get_mut()?;
+        let messages = py.detach(|| inner.recv_wait(timeout))?;
+        let list = PyList::empty(py);
+        for msg in messages {
+            list.append(file_message_to_py(py, msg)?)?;
+        }
+        Ok(list.into())
+    }
+
     /// Wait for a response to a specific request
     fn wait_response(
         &mut self,
@@ -166,15 +267,50 @@ impl PyFileChannel {
         timeout_ms: u64,
     ) -> PyResult<Py<PyAny>> {
         let timeout = Duration::from_millis(timeout_ms);
-        let msg = self.inner.wait_response(request_id, timeout)?;
+        let msg = self.get_mut()?.wait_response(request_id, timeout)?;
         file_message_to_py(py, msg)
     }
 
     /// Clear all messages in both inbox and outbox
     fn clear(&self) -> PyResult<()> {
-        self.inner.clear()?;
+        self.get()?.clear()?;
         Ok(())
     }
+
+    /// Prune messages both sides have already read; returns the number removed
+    fn compact(&self) -> PyResult<usize> {
+        Ok(self.get()?.compact()?)
+    }
+
+    /// Close the channel, stopping any inbox watcher
+    fn close(&mut self) {
+        self.inner = None;
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<Py<PyAny>>,
+        _exc_value: Option<Py<PyAny>>,
+        _traceback: Option<Py<PyAny>>,
+    ) {
+        self.close();
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Take the next waiting message, stopping once the inbox is empty
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<Py<PyAny>>> {
+        match self.get_mut()?.recv_one()? {
+            Some(msg) => file_message_to_py(py, msg).map(Some),
+            None => Ok(None),
+        }
+    }
 }
 
 /// Convert FileMessage to Python dict (all in Rust, no Python json module)
@@ -317,8 +317,11 @@ pub fn wrap_command(
         }
     }
 
-    // Add default progress parser
-    cmd = cmd.progress_parser(parsers::CompositeParser::default_all());
+    // Use the program's preset progress parser if it has one
+    cmd = match <dyn ProgressParser>::for_tool(program) {
+        Some(parser) => cmd.progress_parser(parser),
+        None => cmd.progress_parser(parsers::CompositeParser::default_all()),
+    };
 
     // Run the command (release GIL during execution)
     let output = py
@@ -337,7 +340,8 @@ pub fn wrap_command(
 ///
 /// Args:
 ///     line: The line to parse
-///     parser_type: Parser type ("percentage", "fraction", "progress_bar", or "all")
+///     parser_type: Parser type ("percentage", "fraction", "progress_bar", "all",
+///         or a tool preset: "cargo", "pip", "ffmpeg", "rsync")
 ///
 /// Returns:
 ///     ProgressInfo if progress was found, None otherwise
@@ -348,7 +352,10 @@ pub fn parse_progress(line: &str, parser_type: &str) -> Option<PyProgressInfo> {
         "percentage" => Box::new(parsers::PercentageParser),
         "fraction" => Box::new(parsers::FractionParser),
         "progress_bar" => Box::new(parsers::ProgressBarParser),
-        _ => Box::new(parsers::CompositeParser::default_all()),
+        tool => match <dyn ProgressParser>::for_tool(tool) {
+            Some(parser) => Box::new(parser),
+            None => Box::new(parsers::CompositeParser::default_all()),
+        },
     };
 
     parser
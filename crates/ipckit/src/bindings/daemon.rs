@@ -0,0 +1,79 @@
+//! Python bindings for single-instance daemons
+
+use crate::daemon::SingleInstance;
+use crate::discovery::runtime_dir;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+/// Python wrapper for SingleInstance.
+///
+/// The lock is held until `release()` is called, the context manager exits,
+/// or the object is garbage collected.
+#[pyclass(name = "SingleInstance")]
+pub struct PySingleInstance {
+    inner: Option<SingleInstance>,
+}
+
+impl PySingleInstance {
+    fn get(&self) -> PyResult<&SingleInstance> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("instance lock already released"))
+    }
+}
+
+#[pymethods]
+impl PySingleInstance {
+    /// Become the only running instance of `name`.
+    #[staticmethod]
+    #[pyo3(signature = (name, dir=None))]
+    fn acquire(name: &str, dir: Option<String>) -> PyResult<Self> {
+        let dir = dir.map_or_else(runtime_dir, Into::into);
+        Ok(Self {
+            inner: Some(SingleInstance::acquire_in(dir, name)?),
+        })
+    }
+
+    /// Get the pid of the running instance of `name`, if there is one.
+    #[staticmethod]
+    #[pyo3(signature = (name, dir=None))]
+    fn running(name: &str, dir: Option<String>) -> Option<u32> {
+        let dir = dir.map_or_else(runtime_dir, Into::into);
+        SingleInstance::running_in(dir, name)
+    }
+
+    /// Get the daemon name.
+    #[getter]
+    fn name(&self) -> PyResult<String> {
+        Ok(self.get()?.name().to_string())
+    }
+
+    /// Get the lock file path.
+    #[getter]
+    fn path(&self) -> PyResult<String> {
+        Ok(self.get()?.path().to_string_lossy().into_owned())
+    }
+
+    /// Remove a socket file left behind by a previous instance.
+    fn cleanup_socket(&self, path: &str) -> PyResult<()> {
+        Ok(self.get()?.cleanup_socket(path)?)
+    }
+
+    /// Release the lock.
+    fn release(&mut self) {
+        self.inner = None;
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<Py<PyAny>>,
+        _exc_value: Option<Py<PyAny>>,
+        _traceback: Option<Py<PyAny>>,
+    ) {
+        self.release();
+    }
+}
@@ -0,0 +1,146 @@
+//! Python bindings for channel Discovery
+
+use crate::discovery::{ChannelEntry, Discovery, Registration};
+use pyo3::prelude::*;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Python wrapper for ChannelEntry.
+#[pyclass(name = "ChannelEntry")]
+#[derive(Clone)]
+pub struct PyChannelEntry {
+    inner: ChannelEntry,
+}
+
+#[pymethods]
+impl PyChannelEntry {
+    /// Get the channel name.
+    #[getter]
+    fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Get the channel type.
+    #[getter]
+    fn kind(&self) -> &str {
+        &self.inner.kind
+    }
+
+    /// Get the pid of the serving process.
+    #[getter]
+    fn pid(&self) -> u32 {
+        self.inner.pid
+    }
+
+    /// Get what the channel is for.
+    #[getter]
+    fn purpose(&self) -> Option<&str> {
+        self.inner.purpose.as_deref()
+    }
+
+    /// Get the registration time as Unix timestamp.
+    #[getter]
+    fn registered_at(&self) -> f64 {
+        self.inner
+            .registered_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs_f64()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ChannelEntry(name='{}', kind='{}', pid={})",
+            self.inner.name, self.inner.kind, self.inner.pid
+        )
+    }
+}
+
+/// Python wrapper for Registration.
+///
+/// The channel stays advertised until `unregister()` is called, the context
+/// manager exits, or the object is garbage collected.
+#[pyclass(name = "Registration")]
+pub struct PyRegistration {
+    inner: Option<Registration>,
+    entry: ChannelEntry,
+}
+
+#[pymethods]
+impl PyRegistration {
+    /// Get the advertised entry.
+    #[getter]
+    fn entry(&self) -> PyChannelEntry {
+        PyChannelEntry {
+            inner: self.entry.clone(),
+        }
+    }
+
+    /// Stop advertising the channel.
+    fn unregister(&mut self) {
+        self.inner = None;
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<Py<PyAny>>,
+        _exc_value: Option<Py<PyAny>>,
+        _traceback: Option<Py<PyAny>>,
+    ) {
+        self.unregister();
+    }
+}
+
+/// Python wrapper for Discovery.
+#[pyclass(name = "Discovery")]
+pub struct PyDiscovery {
+    inner: Discovery,
+}
+
+#[pymethods]
+impl PyDiscovery {
+    /// Open the default registry, or the one in `dir`.
+    #[new]
+    #[pyo3(signature = (dir=None))]
+    fn new(dir: Option<String>) -> Self {
+        Self {
+            inner: dir.map_or_else(Discovery::new, Discovery::at),
+        }
+    }
+
+    /// Get the registry directory.
+    #[getter]
+    fn dir(&self) -> String {
+        self.inner.dir().to_string_lossy().into_owned()
+    }
+
+    /// Advertise a channel served by this process.
+    #[pyo3(signature = (name, kind, purpose=None))]
+    fn register(&self, name: &str, kind: &str, purpose: Option<&str>) -> PyResult<PyRegistration> {
+        let mut entry = ChannelEntry::new(name, kind);
+        entry.purpose = purpose.map(str::to_string);
+        let registration = self.inner.register(entry)?;
+        Ok(PyRegistration {
+            entry: registration.entry().clone(),
+            inner: Some(registration),
+        })
+    }
+
+    /// List all channels whose process is still alive.
+    fn list(&self) -> PyResult<Vec<PyChannelEntry>> {
+        Ok(self
+            .inner
+            .list()?
+            .into_iter()
+            .map(|inner| PyChannelEntry { inner })
+            .collect())
+    }
+
+    /// Find a live channel by name.
+    fn find(&self, name: &str) -> PyResult<Option<PyChannelEntry>> {
+        Ok(self.inner.find(name)?.map(|inner| PyChannelEntry { inner }))
+    }
+}
@@ -254,6 +254,12 @@ impl PyEventPublisher {
         self.inner.publish(event.inner.clone());
     }
 
+    /// Publish a list of events in a single batch.
+    fn publish_batch(&self, events: Vec<PyRef<'_, PyEvent>>) {
+        self.inner
+            .publish_batch(events.iter().map(|e| e.inner.clone()).collect());
+    }
+
     /// Publish a progress event.
     fn progress(&self, resource_id: &str, current: u64, total: u64, message: &str) {
         self.inner.progress(resource_id, current, total, message);
@@ -356,6 +362,12 @@ impl PyEventSubscriber {
             .collect()
     }
 
+    /// Remove this subscriber from the bus, keeping the events already queued.
+    /// Returns False if it was already removed.
+    fn unsubscribe(&self) -> bool {
+        self.inner.unsubscribe()
+    }
+
     fn __repr__(&self) -> String {
         format!("EventSubscriber(filter={:?})", self.inner.filter())
     }
@@ -411,11 +423,28 @@ impl PyEventBus {
         self.inner.clear_history();
     }
 
+    /// Get the number of live subscriptions.
+    fn subscriber_count(&self) -> usize {
+        self.inner.subscriber_count()
+    }
+
+    /// Remove every subscriber with an event type pattern matching `pattern`.
+    /// Returns how many were removed.
+    fn unsubscribe_matching(&self, pattern: &str) -> usize {
+        self.inner.unsubscribe_matching(pattern)
+    }
+
     /// Publish an event directly.
     fn publish(&self, event: &PyEvent) {
         self.inner.publish(event.inner.clone());
     }
 
+    /// Publish a list of events directly in a single batch.
+    fn publish_batch(&self, events: Vec<PyRef<'_, PyEvent>>) {
+        self.inner
+            .publish_batch(events.iter().map(|e| e.inner.clone()).collect());
+    }
+
     fn __repr__(&self) -> String {
         "EventBus()".to_string()
     }
@@ -7,6 +7,7 @@ use pyo3::types::PyBytes;
 use std::io::{Read, Write};
 use std::time::Duration;
 
+use super::is_end_of_stream;
 use super::json_utils::{json_value_to_py, py_to_json_value};
 use crate::error::IpcError;
 use crate::graceful::{
@@ -127,10 +128,22 @@ impl PyGracefulNamedPipe {
 ///
 /// This class wraps an IpcChannel with graceful shutdown capabilities,
 /// preventing errors when background threads continue sending messages
-/// after the main event loop has closed.
+/// after the main event loop has closed. Usable as a context manager, which
+/// shuts the channel down and closes it on exit, and as an iterator over
+/// received messages, which ends on shutdown or when the peer disconnects.
 #[pyclass(name = "GracefulIpcChannel")]
 pub struct PyGracefulIpcChannel {
-    inner: RustGracefulIpcChannel<Vec<u8>>,
+    inner: Option<RustGracefulIpcChannel<Vec<u8>>>,
+}
+
+impl PyGracefulIpcChannel {
+    fn get(&self) -> PyResult<&RustGracefulIpcChannel<Vec<u8>>> {
+        Ok(self.inner.as_ref().ok_or(IpcError::Closed)?)
+    }
+
+    fn get_mut(&mut self) -> PyResult<&mut RustGracefulIpcChannel<Vec<u8>>> {
+        Ok(self.inner.as_mut().ok_or(IpcError::Closed)?)
+    }
 }
 
 #[pymethods]
@@ -139,38 +152,47 @@ impl PyGracefulIpcChannel {
     #[staticmethod]
     fn create(name: &str) -> PyResult<Self> {
         let inner = RustGracefulIpcChannel::create(name)?;
-        Ok(Self { inner })
+        Ok(Self { inner: Some(inner) })
     }
 
     /// Connect to an existing IPC channel with graceful shutdown
     #[staticmethod]
     fn connect(name: &str) -> PyResult<Self> {
         let inner = RustGracefulIpcChannel::connect(name)?;
-        Ok(Self { inner })
+        Ok(Self { inner: Some(inner) })
     }
 
     /// Get the channel name
     #[getter]
-    fn name(&self) -> &str {
-        self.inner.name()
+    fn name(&self) -> PyResult<String> {
+        Ok(self.get()?.name().to_string())
     }
 
     /// Check if this is the server end
     #[getter]
-    fn is_server(&self) -> bool {
-        self.inner.is_server()
+    fn is_server(&self) -> PyResult<bool> {
+        Ok(self.get()?.is_server())
     }
 
     /// Check if the channel has been shutdown
     #[getter]
     fn is_shutdown(&self) -> bool {
-        self.inner.is_shutdown()
+        self.inner
+            .as_ref()
+            .is_none_or(RustGracefulIpcChannel::is_shutdown)
+    }
+
+    /// Check if the channel has been closed
+    #[getter]
+    fn closed(&self) -> bool {
+        self.inner.is_none()
     }
 
     /// Wait for a client to connect (server only)
     fn wait_for_client(&mut self, py: Python<'_>) -> PyResult<()> {
+        let inner = self.get_mut()?;
         // Release GIL to allow other Python threads to run
-        py.detach(|| self.inner.wait_for_client())?;
+        py.detach(|| inner.wait_for_client())?;
         Ok(())
     }
 
@@ -181,12 +203,15 @@ impl PyGracefulIpcChannel {
     /// - Pending operations may still complete
     /// - Use drain() to wait for pending operations
     fn shutdown(&self) {
-        self.inner.shutdown();
+        if let Some(ref inner) = self.inner {
+            inner.shutdown();
+        }
     }
 
     /// Wait for all pending operations to complete
     fn drain(&self, py: Python<'_>) -> PyResult<()> {
-        py.detach(|| self.inner.drain())?;
+        let inner = self.get()?;
+        py.detach(|| inner.drain())?;
         Ok(())
     }
 
@@ -196,19 +221,22 @@ impl PyGracefulIpcChannel {
     /// Raises TimeoutError if the drain doesn't complete within the timeout.
     fn shutdown_timeout(&self, py: Python<'_>, timeout_ms: u64) -> PyResult<()> {
         let timeout = Duration::from_millis(timeout_ms);
-        py.detach(|| self.inner.shutdown_timeout(timeout))?;
+        let inner = self.get()?;
+        py.detach(|| inner.shutdown_timeout(timeout))?;
         Ok(())
     }
 
     /// Send bytes through the channel
     fn send(&mut self, py: Python<'_>, data: Vec<u8>) -> PyResult<()> {
-        py.detach(|| self.inner.send_bytes(&data))?;
+        let inner = self.get_mut()?;
+        py.detach(|| inner.send_bytes(&data))?;
         Ok(())
     }
 
     /// Receive bytes from the channel
     fn recv(&mut self, py: Python<'_>) -> PyResult<Py<PyBytes>> {
-        let data = py.detach(|| self.inner.recv_bytes())?;
+        let inner = self.get_mut()?;
+        let data = py.detach(|| inner.recv_bytes())?;
         Ok(PyBytes::new(py, &data).into())
     }
 
@@ -217,15 +245,52 @@ impl PyGracefulIpcChannel {
         let value = py_to_json_value(obj)?;
         let json_bytes = serde_json::to_vec(&value)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
-        py.detach(|| self.inner.send_bytes(&json_bytes))?;
+        let inner = self.get_mut()?;
+        py.detach(|| inner.send_bytes(&json_bytes))?;
         Ok(())
     }
 
     /// Receive a JSON object (uses Rust serde_json)
     fn recv_json(&mut self, py: Python<'_>) -> PyResult<Py<PyAny>> {
-        let data = py.detach(|| self.inner.recv_bytes())?;
+        let inner = self.get_mut()?;
+        let data = py.detach(|| inner.recv_bytes())?;
         let value: serde_json::Value =
             serde_json::from_slice(&data).map_err(|e| IpcError::deserialization(e.to_string()))?;
         json_value_to_py(py, &value)
     }
+
+    /// Shut the channel down and close it, releasing the underlying pipe
+    fn close(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            inner.shutdown();
+        }
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<Py<PyAny>>,
+        _exc_value: Option<Py<PyAny>>,
+        _traceback: Option<Py<PyAny>>,
+    ) {
+        self.close();
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Receive the next message, stopping on shutdown or once the peer has
+    /// disconnected
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<Py<PyBytes>>> {
+        let inner = self.get_mut()?;
+        match py.detach(|| inner.recv_bytes()) {
+            Ok(data) => Ok(Some(PyBytes::new(py, &data).into())),
+            Err(e) if is_end_of_stream(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
 }
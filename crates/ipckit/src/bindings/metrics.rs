@@ -127,6 +127,11 @@ impl PyChannelMetrics {
         self.inner.latency_percentile(percentile)
     }
 
+    /// Get latency at a quantile in 0.0..=1.0 (e.g., 0.999 for p99.9).
+    fn latency_quantile(&self, q: f64) -> u64 {
+        self.inner.latency_quantile(q)
+    }
+
     /// Get elapsed time in seconds.
     #[getter]
     fn elapsed_secs(&self) -> f64 {
@@ -274,6 +279,11 @@ impl PyMetricsSnapshot {
         self.inner.p99_latency_us
     }
 
+    #[getter]
+    fn p999_latency_us(&self) -> u64 {
+        self.inner.p999_latency_us
+    }
+
     #[getter]
     fn elapsed_secs(&self) -> f64 {
         self.inner.elapsed_secs
@@ -333,6 +343,7 @@ fn snapshot_to_dict(py: Python<'_>, snapshot: &MetricsSnapshot) -> PyResult<Py<P
     dict.set_item("p50_latency_us", snapshot.p50_latency_us)?;
     dict.set_item("p95_latency_us", snapshot.p95_latency_us)?;
     dict.set_item("p99_latency_us", snapshot.p99_latency_us)?;
+    dict.set_item("p999_latency_us", snapshot.p999_latency_us)?;
     dict.set_item("elapsed_secs", snapshot.elapsed_secs)?;
     dict.set_item("send_throughput", snapshot.send_throughput)?;
     dict.set_item("recv_throughput", snapshot.recv_throughput)?;
@@ -11,14 +11,19 @@
 //! - `graceful`: GracefulNamedPipe and GracefulIpcChannel bindings
 //! - `socket`: LocalSocketListener and LocalSocketStream bindings
 //! - `cli_bridge`: CLI Bridge bindings for CLI tool integration
+//! - `daemon`: Single-instance locking for daemons
+//! - `discovery`: Channel registry for finding channels served by other processes
 //! - `metrics`: ChannelMetrics bindings for performance monitoring
 //! - `api_server`: API Server bindings for HTTP-over-Socket RESTful API
 //! - `event_stream`: EventBus bindings for publish-subscribe events
 //! - `task_manager`: TaskManager bindings for task lifecycle management
+//! - `testing`: Loopback transport and chaos wrapper, exposed as `ipckit.testing`
 
 mod api_server;
 mod channel;
 mod cli_bridge;
+mod daemon;
+mod discovery;
 mod event_stream;
 mod graceful;
 mod json_utils;
@@ -27,6 +32,7 @@ mod pipe;
 mod shm;
 mod socket;
 mod task_manager;
+mod testing;
 
 // Re-export all Python classes
 pub use api_server::{PyApiClient, PyApiServerConfig, PyRequest, PyResponse};
@@ -34,6 +40,8 @@ pub use channel::{PyFileChannel, PyIpcChannel};
 pub use cli_bridge::{
     parse_progress, wrap_command, PyCliBridge, PyCliBridgeConfig, PyCommandOutput, PyProgressInfo,
 };
+pub use daemon::PySingleInstance;
+pub use discovery::{PyChannelEntry, PyDiscovery, PyRegistration};
 pub use event_stream::{
     PyEvent, PyEventBus, PyEventBusConfig, PyEventFilter, PyEventPublisher, PyEventSubscriber,
 };
@@ -46,9 +54,10 @@ pub use pipe::{PyAnonymousPipe, PyNamedPipe};
 pub use shm::PySharedMemory;
 pub use socket::{PyLocalSocketListener, PyLocalSocketStream};
 pub use task_manager::{
-    PyCancellationToken, PyTaskBuilder, PyTaskFilter, PyTaskHandle, PyTaskInfo, PyTaskManager,
-    PyTaskManagerConfig, PyTaskStatus,
+    PyCancellationToken, PySharedCancellationToken, PyTaskBuilder, PyTaskFilter, PyTaskHandle,
+    PyTaskInfo, PyTaskManager, PyTaskManagerConfig, PyTaskStatus,
 };
+pub use testing::{loopback_pair, PyChaosEndpoint, PyLoopbackEndpoint};
 
 use pyo3::prelude::*;
 
@@ -79,6 +88,12 @@ pub fn ipckit_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(wrap_command, m)?)?;
     m.add_function(wrap_pyfunction!(parse_progress, m)?)?;
 
+    // Discovery classes (channel registry)
+    m.add_class::<PyDiscovery>()?;
+    m.add_class::<PyChannelEntry>()?;
+    m.add_class::<PyRegistration>()?;
+    m.add_class::<PySingleInstance>()?;
+
     // Metrics classes (Issue #10: ChannelMetrics)
     m.add_class::<PyChannelMetrics>()?;
     m.add_class::<PyMetricsSnapshot>()?;
@@ -101,12 +116,16 @@ pub fn ipckit_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyTaskStatus>()?;
     m.add_class::<PyTaskInfo>()?;
     m.add_class::<PyCancellationToken>()?;
+    m.add_class::<PySharedCancellationToken>()?;
     m.add_class::<PyTaskBuilder>()?;
     m.add_class::<PyTaskFilter>()?;
     m.add_class::<PyTaskHandle>()?;
     m.add_class::<PyTaskManagerConfig>()?;
     m.add_class::<PyTaskManager>()?;
 
+    // Testing utilities (in-memory transports with failure injection)
+    m.add_submodule(&testing::testing_module(m.py())?)?;
+
     // JSON utilities (Rust-native, faster than Python's json module)
     m.add_function(wrap_pyfunction!(json_dumps, m)?)?;
     m.add_function(wrap_pyfunction!(json_dumps_pretty, m)?)?;
@@ -139,6 +158,11 @@ CLI Bridge (for CLI tool integration):
 - wrap_command(): Wrap a subprocess with CLI bridge integration
 - parse_progress(): Parse progress from output lines
 
+Discovery (channel registry):
+- Discovery: Advertise channels and list those served by other processes
+- ChannelEntry: One advertised channel (name, kind, pid, purpose)
+- SingleInstance: Make sure only one daemon of a name runs at a time
+
 Metrics (Performance monitoring):
 - ChannelMetrics: Track message counts, latency, throughput
 - MetricsSnapshot: Point-in-time snapshot of metrics
@@ -156,6 +180,10 @@ Event Stream (Publish-Subscribe):
 - EventPublisher: Publish events to the bus
 - EventSubscriber: Subscribe to and receive events
 
+Testing (ipckit.testing submodule):
+- loopback_pair(): In-memory connected endpoints for unit tests
+- ChaosEndpoint: Inject drops, delays, timeouts and disconnects
+
 JSON utilities (faster than Python's json module):
 - json_dumps(obj): Serialize Python object to JSON string
 - json_dumps_pretty(obj): Serialize with pretty formatting
@@ -201,3 +229,18 @@ Example:
 
     Ok(())
 }
+
+/// Whether an error means the peer has gone away, which ends iteration over
+/// incoming messages instead of raising.
+fn is_end_of_stream(err: &crate::error::IpcError) -> bool {
+    match err {
+        crate::error::IpcError::Closed => true,
+        crate::error::IpcError::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::UnexpectedEof
+                | std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::ConnectionReset
+        ),
+        _ => false,
+    }
+}
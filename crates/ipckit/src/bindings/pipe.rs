@@ -6,6 +6,7 @@ use pyo3::prelude::*;
 use pyo3::types::PyBytes;
 use std::io::{Read, Write};
 
+use super::is_end_of_stream;
 use crate::error::IpcError;
 use crate::pipe::{AnonymousPipe as RustAnonymousPipe, NamedPipe as RustNamedPipe};
 
@@ -102,10 +103,27 @@ impl PyAnonymousPipe {
     }
 }
 
+/// Largest chunk yielded when iterating over a pipe.
+const ITER_CHUNK_SIZE: usize = 64 * 1024;
+
 /// Python wrapper for NamedPipe
+///
+/// Usable as a context manager, which closes the pipe on exit, and as an
+/// iterator over chunks of incoming data, which ends when the peer closes
+/// its end.
 #[pyclass(name = "NamedPipe")]
 pub struct PyNamedPipe {
-    inner: RustNamedPipe,
+    inner: Option<RustNamedPipe>,
+}
+
+impl PyNamedPipe {
+    fn get(&self) -> PyResult<&RustNamedPipe> {
+        Ok(self.inner.as_ref().ok_or(IpcError::Closed)?)
+    }
+
+    fn get_mut(&mut self) -> PyResult<&mut RustNamedPipe> {
+        Ok(self.inner.as_mut().ok_or(IpcError::Closed)?)
+    }
 }
 
 #[pymethods]
@@ -114,63 +132,108 @@ impl PyNamedPipe {
     #[staticmethod]
     fn create(name: &str) -> PyResult<Self> {
         let inner = RustNamedPipe::create(name)?;
-        Ok(Self { inner })
+        Ok(Self { inner: Some(inner) })
     }
 
     /// Connect to an existing named pipe
     #[staticmethod]
     fn connect(name: &str) -> PyResult<Self> {
         let inner = RustNamedPipe::connect(name)?;
-        Ok(Self { inner })
+        Ok(Self { inner: Some(inner) })
     }
 
     /// Get the pipe name
     #[getter]
-    fn name(&self) -> &str {
-        self.inner.name()
+    fn name(&self) -> PyResult<String> {
+        Ok(self.get()?.name().to_string())
     }
 
     /// Check if this is the server end
     #[getter]
-    fn is_server(&self) -> bool {
-        self.inner.is_server()
+    fn is_server(&self) -> PyResult<bool> {
+        Ok(self.get()?.is_server())
+    }
+
+    /// Check if the pipe has been closed
+    #[getter]
+    fn closed(&self) -> bool {
+        self.inner.is_none()
     }
 
     /// Wait for a client to connect (server only)
     fn wait_for_client(&mut self, py: Python<'_>) -> PyResult<()> {
+        let inner = self.get_mut()?;
         // Release GIL to allow other Python threads to run
-        py.detach(|| self.inner.wait_for_client())?;
+        py.detach(|| inner.wait_for_client())?;
         Ok(())
     }
 
     /// Read data from the pipe
     fn read(&mut self, py: Python<'_>, size: usize) -> PyResult<Py<PyBytes>> {
+        let inner = self.get_mut()?;
         let mut buf = vec![0u8; size];
         // Release GIL during blocking read
-        let n = py.detach(|| self.inner.read(&mut buf))?;
+        let n = py.detach(|| inner.read(&mut buf))?;
         buf.truncate(n);
         Ok(PyBytes::new(py, &buf).into())
     }
 
     /// Write data to the pipe
     fn write(&mut self, py: Python<'_>, data: Vec<u8>) -> PyResult<usize> {
+        let inner = self.get_mut()?;
         // Release GIL during write
-        let n = py.detach(|| self.inner.write(&data))?;
+        let n = py.detach(|| inner.write(&data))?;
         Ok(n)
     }
 
     /// Read exact number of bytes
     fn read_exact(&mut self, py: Python<'_>, size: usize) -> PyResult<Py<PyBytes>> {
+        let inner = self.get_mut()?;
         let mut buf = vec![0u8; size];
         // Release GIL during blocking read
-        py.detach(|| self.inner.read_exact(&mut buf))?;
+        py.detach(|| inner.read_exact(&mut buf))?;
         Ok(PyBytes::new(py, &buf).into())
     }
 
     /// Write all data
     fn write_all(&mut self, py: Python<'_>, data: Vec<u8>) -> PyResult<()> {
+        let inner = self.get_mut()?;
         // Release GIL during write
-        py.detach(|| self.inner.write_all(&data))?;
+        py.detach(|| inner.write_all(&data))?;
         Ok(())
     }
+
+    /// Close the pipe, releasing its handle
+    fn close(&mut self) {
+        self.inner = None;
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<Py<PyAny>>,
+        _exc_value: Option<Py<PyAny>>,
+        _traceback: Option<Py<PyAny>>,
+    ) {
+        self.close();
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Read the next chunk of data, stopping once the peer has closed its end
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<Py<PyBytes>>> {
+        let inner = self.get_mut()?;
+        let mut buf = vec![0u8; ITER_CHUNK_SIZE];
+        match py.detach(|| inner.read(&mut buf)).map_err(IpcError::from) {
+            Ok(0) => Ok(None),
+            Ok(n) => Ok(Some(PyBytes::new(py, &buf[..n]).into())),
+            Err(e) if is_end_of_stream(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
 }
@@ -1,9 +1,14 @@
 //! Python bindings for SharedMemory
 //!
 //! This module provides Python bindings for shared memory operations.
+//!
+//! Zero-copy views are built on `ctypes` arrays over the mapped region rather
+//! than the buffer protocol, which is not available to abi3 builds before
+//! Python 3.11. Each view keeps its `SharedMemory` alive.
 
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
-use pyo3::types::PyBytes;
+use pyo3::types::{PyBytes, PyDict, PySlice, PyTuple};
 
 use crate::shm::SharedMemory as RustSharedMemory;
 
@@ -64,4 +69,104 @@ impl PySharedMemory {
         let data = self.inner.read(0, self.inner.size())?;
         Ok(PyBytes::new(py, &data).into())
     }
+
+    /// Get a zero-copy memoryview of the region.
+    ///
+    /// The view covers `size` bytes from `offset` (default: to the end) and
+    /// keeps this object alive for as long as it exists.
+    #[pyo3(signature = (offset=0, size=None, readonly=false))]
+    fn memoryview<'py>(
+        slf: &Bound<'py, Self>,
+        offset: usize,
+        size: Option<usize>,
+        readonly: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let total = slf.borrow().inner.size();
+        let size = size.unwrap_or_else(|| total.saturating_sub(offset));
+        let view = region_view(slf, offset, size)?;
+        if readonly {
+            view.call_method0("toreadonly")
+        } else {
+            Ok(view)
+        }
+    }
+
+    /// Copy from any buffer-protocol object into the region at `offset`.
+    ///
+    /// Data is copied once, straight from the source buffer into shared
+    /// memory. Returns the number of bytes written.
+    fn write_from_buffer(
+        slf: &Bound<'_, Self>,
+        offset: usize,
+        obj: &Bound<'_, PyAny>,
+    ) -> PyResult<usize> {
+        let py = slf.py();
+        let source = py
+            .import("builtins")?
+            .getattr("memoryview")?
+            .call1((obj,))?
+            .call_method1("cast", ("B",))?;
+        let len: usize = source.len()?;
+        let target = region_view(slf, offset, len)?;
+        target.set_item(PySlice::full(py), source)?;
+        Ok(len)
+    }
+
+    /// NumPy array interface, so `numpy.asarray(shm)` maps the region without copying.
+    #[getter]
+    fn __array_interface__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("version", 3)?;
+        dict.set_item("shape", PyTuple::new(py, [self.inner.size()])?)?;
+        dict.set_item("typestr", "|u1")?;
+        dict.set_item("data", (self.inner.as_ptr() as usize, false))?;
+        Ok(dict)
+    }
+
+    /// Copy the current contents into a new shared memory region named `name`.
+    ///
+    /// The returned region owns the copy and removes it when released.
+    fn snapshot(&self, name: &str) -> PyResult<Self> {
+        let snapshot = self.inner.snapshot(name)?;
+        Ok(Self {
+            inner: snapshot.into_inner(),
+        })
+    }
+}
+
+/// Build a writable, byte-formatted memoryview over `len` bytes at `offset`.
+fn region_view<'py>(
+    slf: &Bound<'py, PySharedMemory>,
+    offset: usize,
+    len: usize,
+) -> PyResult<Bound<'py, PyAny>> {
+    let py = slf.py();
+    let (base, size) = {
+        let this = slf.borrow();
+        (this.inner.as_ptr() as usize, this.inner.size())
+    };
+    if offset.checked_add(len).is_none_or(|end| end > size) {
+        return Err(PyValueError::new_err(format!(
+            "range {offset}..{} out of bounds for shared memory of size {size}",
+            offset.saturating_add(len)
+        )));
+    }
+
+    // A Python-level subclass of the ctypes array type has a __dict__, which
+    // lets the array hold a reference back to the SharedMemory that owns the
+    // mapping.
+    let ctypes = py.import("ctypes")?;
+    let array_type = ctypes.getattr("c_ubyte")?.mul(len)?;
+    let view_type = py.import("builtins")?.getattr("type")?.call1((
+        "SharedMemoryView",
+        (array_type,),
+        PyDict::new(py),
+    ))?;
+    let array = view_type.call_method1("from_address", (base + offset,))?;
+    array.setattr("_owner", slf)?;
+
+    py.import("builtins")?
+        .getattr("memoryview")?
+        .call1((array,))?
+        .call_method1("cast", ("B",))
 }
@@ -6,6 +6,7 @@ use pyo3::prelude::*;
 use pyo3::types::PyBytes;
 use std::io::{Read, Write};
 
+use super::is_end_of_stream;
 use super::json_utils::{json_value_to_py, py_to_json_value};
 use crate::error::IpcError;
 use crate::local_socket::{
@@ -54,7 +55,7 @@ impl PyLocalSocketListener {
         let guard = self.inner.lock();
         let stream = guard.accept()?;
         Ok(PyLocalSocketStream {
-            inner: parking_lot::Mutex::new(stream),
+            inner: parking_lot::Mutex::new(Some(stream)),
         })
     }
 
@@ -74,9 +75,41 @@ impl PyLocalSocketListener {
 /// Can be created by:
 /// - Calling LocalSocketListener.accept() on the server side
 /// - Calling LocalSocketStream.connect() on the client side
+///
+/// Usable as a context manager, which closes the connection on exit, and as
+/// an iterator over messages received with the `recv_json` framing, which
+/// ends when the peer disconnects.
 #[pyclass(name = "LocalSocketStream")]
 pub struct PyLocalSocketStream {
-    inner: parking_lot::Mutex<RustLocalSocketStream>,
+    inner: parking_lot::Mutex<Option<RustLocalSocketStream>>,
+}
+
+impl PyLocalSocketStream {
+    /// Run `f` on the open stream.
+    fn with<R>(
+        &self,
+        f: impl FnOnce(&mut RustLocalSocketStream) -> std::io::Result<R>,
+    ) -> Result<R, IpcError> {
+        let mut guard = self.inner.lock();
+        let stream = guard.as_mut().ok_or(IpcError::Closed)?;
+        Ok(f(stream)?)
+    }
+
+    /// Read one length-prefixed JSON message.
+    fn read_json(&self) -> Result<serde_json::Value, IpcError> {
+        let json_bytes = self.with(|stream| {
+            // Read length prefix (4 bytes, big-endian)
+            let mut len_bytes = [0u8; 4];
+            stream.read_exact(&mut len_bytes)?;
+            let len = u32::from_be_bytes(len_bytes) as usize;
+
+            // Read JSON data
+            let mut json_bytes = vec![0u8; len];
+            stream.read_exact(&mut json_bytes)?;
+            Ok(json_bytes)
+        })?;
+        serde_json::from_slice(&json_bytes).map_err(|e| IpcError::deserialization(e.to_string()))
+    }
 }
 
 #[pymethods]
@@ -89,14 +122,20 @@ impl PyLocalSocketStream {
     fn connect(name: &str) -> PyResult<Self> {
         let inner = RustLocalSocketStream::connect(name)?;
         Ok(Self {
-            inner: parking_lot::Mutex::new(inner),
+            inner: parking_lot::Mutex::new(Some(inner)),
         })
     }
 
     /// Get the name of this stream
     #[getter]
-    fn name(&self) -> String {
-        self.inner.lock().name().to_string()
+    fn name(&self) -> PyResult<String> {
+        Ok(self.with(|stream| Ok(stream.name().to_string()))?)
+    }
+
+    /// Check if the connection has been closed
+    #[getter]
+    fn closed(&self) -> bool {
+        self.inner.lock().is_none()
     }
 
     /// Read data from the socket
@@ -108,10 +147,7 @@ impl PyLocalSocketStream {
     ///     bytes: The data read from the socket
     fn read(&self, py: Python<'_>, size: usize) -> PyResult<Py<PyBytes>> {
         let mut buf = vec![0u8; size];
-        let n = {
-            let mut guard = self.inner.lock();
-            guard.read(&mut buf)?
-        };
+        let n = self.with(|stream| stream.read(&mut buf))?;
         buf.truncate(n);
         Ok(PyBytes::new(py, &buf).into())
     }
@@ -124,9 +160,7 @@ impl PyLocalSocketStream {
     /// Returns:
     ///     int: Number of bytes written
     fn write(&self, _py: Python<'_>, data: Vec<u8>) -> PyResult<usize> {
-        let mut guard = self.inner.lock();
-        let n = guard.write(&data)?;
-        Ok(n)
+        Ok(self.with(|stream| stream.write(&data))?)
     }
 
     /// Read exact number of bytes
@@ -138,10 +172,7 @@ impl PyLocalSocketStream {
     ///     bytes: The data read from the socket
     fn read_exact(&self, py: Python<'_>, size: usize) -> PyResult<Py<PyBytes>> {
         let mut buf = vec![0u8; size];
-        {
-            let mut guard = self.inner.lock();
-            guard.read_exact(&mut buf)?;
-        }
+        self.with(|stream| stream.read_exact(&mut buf))?;
         Ok(PyBytes::new(py, &buf).into())
     }
 
@@ -150,16 +181,12 @@ impl PyLocalSocketStream {
     /// Args:
     ///     data: The data to write (all bytes will be written)
     fn write_all(&self, _py: Python<'_>, data: Vec<u8>) -> PyResult<()> {
-        let mut guard = self.inner.lock();
-        guard.write_all(&data)?;
-        Ok(())
+        Ok(self.with(|stream| stream.write_all(&data))?)
     }
 
     /// Flush the socket
     fn flush(&self, _py: Python<'_>) -> PyResult<()> {
-        let mut guard = self.inner.lock();
-        guard.flush()?;
-        Ok(())
+        Ok(self.with(|stream| stream.flush())?)
     }
 
     /// Send a JSON-serializable object
@@ -171,30 +198,49 @@ impl PyLocalSocketStream {
         // Send length prefix (4 bytes, big-endian)
         let len_bytes = (json_bytes.len() as u32).to_be_bytes();
 
-        let mut guard = self.inner.lock();
-        guard.write_all(&len_bytes)?;
-        guard.write_all(&json_bytes)?;
-        guard.flush()?;
+        self.with(|stream| {
+            stream.write_all(&len_bytes)?;
+            stream.write_all(&json_bytes)?;
+            stream.flush()
+        })?;
 
         Ok(())
     }
 
     /// Receive a JSON object
     fn recv_json(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
-        let mut guard = self.inner.lock();
+        let value = self.read_json()?;
+        json_value_to_py(py, &value)
+    }
 
-        // Read length prefix (4 bytes, big-endian)
-        let mut len_bytes = [0u8; 4];
-        guard.read_exact(&mut len_bytes)?;
-        let len = u32::from_be_bytes(len_bytes) as usize;
+    /// Close the connection
+    fn close(&self) {
+        self.inner.lock().take();
+    }
 
-        // Read JSON data
-        let mut json_bytes = vec![0u8; len];
-        guard.read_exact(&mut json_bytes)?;
-        drop(guard);
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
 
-        let value: serde_json::Value = serde_json::from_slice(&json_bytes)
-            .map_err(|e| IpcError::deserialization(e.to_string()))?;
-        json_value_to_py(py, &value)
+    fn __exit__(
+        &self,
+        _exc_type: Option<Py<PyAny>>,
+        _exc_value: Option<Py<PyAny>>,
+        _traceback: Option<Py<PyAny>>,
+    ) {
+        self.close();
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Receive the next JSON message, stopping once the peer has disconnected
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<Py<PyAny>>> {
+        match self.read_json() {
+            Ok(value) => json_value_to_py(py, &value).map(Some),
+            Err(e) if is_end_of_stream(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 }
@@ -2,8 +2,8 @@
 
 use crate::bindings::json_utils::{json_value_to_py, py_to_json_value};
 use crate::task_manager::{
-    CancellationToken, TaskBuilder, TaskFilter, TaskHandle, TaskInfo, TaskManager,
-    TaskManagerConfig, TaskStatus,
+    CancellationToken, SharedCancellationToken, TaskBuilder, TaskFilter, TaskHandle, TaskInfo,
+    TaskManager, TaskManagerConfig, TaskStatus,
 };
 use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
@@ -125,6 +125,12 @@ impl PyTaskInfo {
         &self.inner.task_type
     }
 
+    /// Get the ID of the parent task, if this is a sub-task.
+    #[getter]
+    fn parent_id(&self) -> Option<&str> {
+        self.inner.parent_id.as_deref()
+    }
+
     /// Get the task status.
     #[getter]
     fn status(&self) -> PyTaskStatus {
@@ -255,6 +261,64 @@ impl PyCancellationToken {
     }
 }
 
+/// Python wrapper for SharedCancellationToken.
+#[pyclass(name = "SharedCancellationToken")]
+#[derive(Clone)]
+pub struct PySharedCancellationToken {
+    inner: SharedCancellationToken,
+}
+
+#[pymethods]
+impl PySharedCancellationToken {
+    /// Create a new cancellation flag shared between processes.
+    #[staticmethod]
+    fn create(name: &str) -> PyResult<Self> {
+        Ok(Self {
+            inner: SharedCancellationToken::create(name)?,
+        })
+    }
+
+    /// Open a cancellation flag created by another process.
+    #[staticmethod]
+    fn open(name: &str) -> PyResult<Self> {
+        Ok(Self {
+            inner: SharedCancellationToken::open(name)?,
+        })
+    }
+
+    /// Get the flag name.
+    #[getter]
+    fn name(&self) -> String {
+        self.inner.name().to_string()
+    }
+
+    /// Trigger cancellation in every process sharing the flag.
+    fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    /// Check if any process has cancelled the flag.
+    #[getter]
+    fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    /// Get a CancellationToken backed by this flag.
+    fn token(&self) -> PyCancellationToken {
+        PyCancellationToken {
+            inner: self.inner.token(),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SharedCancellationToken(name={:?}, cancelled={})",
+            self.inner.name(),
+            self.inner.is_cancelled()
+        )
+    }
+}
+
 /// Python wrapper for TaskBuilder.
 #[pyclass(name = "TaskBuilder")]
 #[derive(Clone)]
@@ -287,6 +351,20 @@ impl PyTaskBuilder {
         }
     }
 
+    /// Make the task a sub-task of the task `id`.
+    fn parent(&self, id: &str) -> Self {
+        Self {
+            inner: self.inner.clone().parent(id),
+        }
+    }
+
+    /// Set how much this task counts towards its parent's progress.
+    fn weight(&self, weight: f64) -> Self {
+        Self {
+            inner: self.inner.clone().weight(weight),
+        }
+    }
+
     fn __repr__(&self) -> String {
         "TaskBuilder(...)".to_string()
     }
@@ -330,6 +408,20 @@ impl PyTaskFilter {
         }
     }
 
+    /// Filter by parent task, listing its direct sub-tasks.
+    fn parent(&self, id: &str) -> Self {
+        Self {
+            inner: self.inner.clone().parent(id),
+        }
+    }
+
+    /// Filter by ancestor task, listing its whole subtree.
+    fn children_of(&self, id: &str) -> Self {
+        Self {
+            inner: self.inner.clone().children_of(id),
+        }
+    }
+
     /// Show only active tasks.
     fn active(&self) -> Self {
         Self {
@@ -509,10 +601,12 @@ impl PyTaskManager {
     }
 
     /// Create a new task.
-    fn create(&self, builder: &PyTaskBuilder) -> PyTaskHandle {
-        PyTaskHandle {
-            inner: self.inner.create(builder.inner.clone()),
-        }
+    ///
+    /// Raises if a task with the builder's ID already exists.
+    fn create(&self, builder: &PyTaskBuilder) -> PyResult<PyTaskHandle> {
+        Ok(PyTaskHandle {
+            inner: self.inner.try_create(builder.inner.clone())?,
+        })
     }
 
     /// Create a task with name and type directly.
@@ -0,0 +1,294 @@
+//! Python bindings for the loopback transport and chaos wrapper
+//!
+//! Exposed as the `ipckit.testing` submodule so Python tests can exercise
+//! their IPC handling, including disconnects and timeouts, without a daemon.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use std::time::Duration;
+
+use super::json_utils::{json_value_to_py, py_to_json_value};
+use crate::error::{IpcError, Result};
+use crate::testing::{loopback, ChaosConfig, ChaosEndpoint, LoopbackEndpoint};
+
+/// Python wrapper for one end of an in-memory loopback link
+#[pyclass(name = "LoopbackEndpoint")]
+pub struct PyLoopbackEndpoint {
+    inner: LoopbackEndpoint,
+}
+
+#[pymethods]
+impl PyLoopbackEndpoint {
+    /// Send bytes to the other end
+    fn send(&self, data: Vec<u8>) -> PyResult<()> {
+        self.inner.send(data)?;
+        Ok(())
+    }
+
+    /// Receive bytes, waiting at most `timeout_ms` if given
+    #[pyo3(signature = (timeout_ms=None))]
+    fn recv(&self, py: Python<'_>, timeout_ms: Option<u64>) -> PyResult<Py<PyBytes>> {
+        let data = py.detach(|| {
+            recv_with(
+                timeout_ms,
+                |t| self.inner.recv_timeout(t),
+                || self.inner.recv(),
+            )
+        })?;
+        Ok(PyBytes::new(py, &data).into())
+    }
+
+    /// Receive bytes if a message is queued, otherwise return None
+    fn try_recv(&self, py: Python<'_>) -> PyResult<Option<Py<PyBytes>>> {
+        let data = self.inner.try_recv()?;
+        Ok(data.map(|d| PyBytes::new(py, &d).into()))
+    }
+
+    /// Send a JSON-serializable object (uses Rust serde_json)
+    fn send_json(&self, obj: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.send(encode_json(obj)?)?;
+        Ok(())
+    }
+
+    /// Receive a JSON object, waiting at most `timeout_ms` if given
+    #[pyo3(signature = (timeout_ms=None))]
+    fn recv_json(&self, py: Python<'_>, timeout_ms: Option<u64>) -> PyResult<Py<PyAny>> {
+        let data = py.detach(|| {
+            recv_with(
+                timeout_ms,
+                |t| self.inner.recv_timeout(t),
+                || self.inner.recv(),
+            )
+        })?;
+        decode_json(py, &data)
+    }
+
+    /// Disconnect both ends of the link
+    fn close(&self) {
+        self.inner.close();
+    }
+
+    /// Check whether the link has been disconnected
+    #[getter]
+    fn closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Number of messages waiting to be received
+    #[getter]
+    fn pending(&self) -> usize {
+        self.inner.pending()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "LoopbackEndpoint(pending={}, closed={})",
+            self.inner.pending(),
+            self.inner.is_closed()
+        )
+    }
+}
+
+/// Python wrapper for a loopback endpoint that injects failures
+///
+/// Drops and timeouts are drawn from a generator seeded with `seed`, so a
+/// failing test can be replayed exactly.
+#[pyclass(name = "ChaosEndpoint")]
+pub struct PyChaosEndpoint {
+    inner: ChaosEndpoint,
+}
+
+#[pymethods]
+impl PyChaosEndpoint {
+    /// Wrap a loopback endpoint with failure injection
+    #[new]
+    #[pyo3(signature = (endpoint, drop_rate=0.0, timeout_rate=0.0, delay_ms=0, disconnect_after=None, seed=0))]
+    fn new(
+        endpoint: PyRef<'_, PyLoopbackEndpoint>,
+        drop_rate: f64,
+        timeout_rate: f64,
+        delay_ms: u64,
+        disconnect_after: Option<u64>,
+        seed: u64,
+    ) -> Self {
+        let config = ChaosConfig {
+            drop_rate,
+            timeout_rate,
+            delay: Duration::from_millis(delay_ms),
+            disconnect_after,
+            seed,
+        };
+        Self {
+            inner: ChaosEndpoint::new(endpoint.inner.clone(), config),
+        }
+    }
+
+    /// Send bytes, subject to the configured failures
+    fn send(&self, py: Python<'_>, data: Vec<u8>) -> PyResult<()> {
+        py.detach(|| self.inner.send(data))?;
+        Ok(())
+    }
+
+    /// Receive bytes, waiting at most `timeout_ms` if given
+    #[pyo3(signature = (timeout_ms=None))]
+    fn recv(&self, py: Python<'_>, timeout_ms: Option<u64>) -> PyResult<Py<PyBytes>> {
+        let data = py.detach(|| {
+            recv_with(
+                timeout_ms,
+                |t| self.inner.recv_timeout(t),
+                || self.inner.recv(),
+            )
+        })?;
+        Ok(PyBytes::new(py, &data).into())
+    }
+
+    /// Send a JSON-serializable object, subject to the configured failures
+    fn send_json(&self, py: Python<'_>, obj: &Bound<'_, PyAny>) -> PyResult<()> {
+        let data = encode_json(obj)?;
+        py.detach(|| self.inner.send(data))?;
+        Ok(())
+    }
+
+    /// Receive a JSON object, waiting at most `timeout_ms` if given
+    #[pyo3(signature = (timeout_ms=None))]
+    fn recv_json(&self, py: Python<'_>, timeout_ms: Option<u64>) -> PyResult<Py<PyAny>> {
+        let data = py.detach(|| {
+            recv_with(
+                timeout_ms,
+                |t| self.inner.recv_timeout(t),
+                || self.inner.recv(),
+            )
+        })?;
+        decode_json(py, &data)
+    }
+
+    /// Make the next `count` receives raise TimeoutError
+    #[pyo3(signature = (count=1))]
+    fn inject_timeouts(&self, count: u64) {
+        self.inner.inject_timeouts(count);
+    }
+
+    /// Disconnect the link immediately, as if the peer had crashed
+    fn disconnect(&self) {
+        self.inner.disconnect();
+    }
+
+    /// Disconnect both ends of the link
+    fn close(&self) {
+        self.inner.close();
+    }
+
+    /// Check whether the link has been disconnected
+    #[getter]
+    fn closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Probability that a sent message is dropped
+    #[getter]
+    fn drop_rate(&self) -> f64 {
+        self.inner.config().drop_rate
+    }
+
+    #[setter]
+    fn set_drop_rate(&self, rate: f64) {
+        self.inner.set_config(self.inner.config().drop_rate(rate));
+    }
+
+    /// Probability that a receive times out
+    #[getter]
+    fn timeout_rate(&self) -> f64 {
+        self.inner.config().timeout_rate
+    }
+
+    #[setter]
+    fn set_timeout_rate(&self, rate: f64) {
+        self.inner
+            .set_config(self.inner.config().timeout_rate(rate));
+    }
+
+    /// Delay in milliseconds added before every send
+    #[getter]
+    fn delay_ms(&self) -> u64 {
+        self.inner.config().delay.as_millis() as u64
+    }
+
+    #[setter]
+    fn set_delay_ms(&self, delay_ms: u64) {
+        self.inner
+            .set_config(self.inner.config().delay(Duration::from_millis(delay_ms)));
+    }
+
+    /// Get the injected failure counters as a dict
+    fn stats(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let stats = self.inner.stats();
+        let dict = PyDict::new(py);
+        dict.set_item("sent", stats.sent)?;
+        dict.set_item("dropped", stats.dropped)?;
+        dict.set_item("timeouts", stats.timeouts)?;
+        dict.set_item("disconnected", stats.disconnected)?;
+        Ok(dict.into())
+    }
+
+    fn __repr__(&self) -> String {
+        let config = self.inner.config();
+        format!(
+            "ChaosEndpoint(drop_rate={}, timeout_rate={}, delay_ms={}, closed={})",
+            config.drop_rate,
+            config.timeout_rate,
+            config.delay.as_millis(),
+            self.inner.is_closed()
+        )
+    }
+}
+
+/// Create a connected pair of loopback endpoints
+#[pyfunction]
+pub fn loopback_pair() -> (PyLoopbackEndpoint, PyLoopbackEndpoint) {
+    let (a, b) = loopback();
+    (
+        PyLoopbackEndpoint { inner: a },
+        PyLoopbackEndpoint { inner: b },
+    )
+}
+
+/// Build the `testing` submodule
+pub fn testing_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "testing")?;
+    m.add_class::<PyLoopbackEndpoint>()?;
+    m.add_class::<PyChaosEndpoint>()?;
+    m.add_function(wrap_pyfunction!(loopback_pair, &m)?)?;
+    m.add(
+        "__doc__",
+        "In-memory transports for testing IPC handling without a daemon
+
+- loopback_pair(): Create two connected LoopbackEndpoint objects
+- ChaosEndpoint(endpoint, drop_rate=0.0, timeout_rate=0.0, delay_ms=0,
+  disconnect_after=None, seed=0): Inject drops, delays, timeouts and disconnects
+",
+    )?;
+    Ok(m)
+}
+
+fn recv_with(
+    timeout_ms: Option<u64>,
+    recv_timeout: impl FnOnce(Duration) -> Result<Vec<u8>>,
+    recv: impl FnOnce() -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    match timeout_ms {
+        Some(ms) => recv_timeout(Duration::from_millis(ms)),
+        None => recv(),
+    }
+}
+
+fn encode_json(obj: &Bound<'_, PyAny>) -> PyResult<Vec<u8>> {
+    let value = py_to_json_value(obj)?;
+    serde_json::to_vec(&value)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+}
+
+fn decode_json(py: Python<'_>, data: &[u8]) -> PyResult<Py<PyAny>> {
+    let value: serde_json::Value =
+        serde_json::from_slice(data).map_err(|e| IpcError::deserialization(e.to_string()))?;
+    json_value_to_py(py, &value)
+}
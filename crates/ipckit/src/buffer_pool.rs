@@ -0,0 +1,142 @@
+//! Buffer Pool - Reusable receive buffers for zero-allocation forwarding
+//!
+//! Proxies that receive a frame on one channel and forward it to another
+//! normally allocate a fresh `Vec<u8>` per message. A [`BufferPool`] hands
+//! out [`BytesMut`] buffers to receive into and takes the resulting
+//! [`Bytes`] back once the caller is done with them, so steady-state
+//! forwarding does not allocate at all.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ipckit::{BufferPool, IpcChannel};
+//!
+//! let pool = BufferPool::new(16);
+//! loop {
+//!     let frame = upstream.recv_pooled(&pool)?;
+//!     downstream.send_bytes(&frame)?;
+//!     pool.recycle(frame);
+//! }
+//! ```
+
+use bytes::{Bytes, BytesMut};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Default number of idle buffers a pool keeps.
+const DEFAULT_MAX_IDLE: usize = 32;
+
+/// Default capacity of a freshly allocated buffer.
+const DEFAULT_BUFFER_CAPACITY: usize = 8 * 1024;
+
+/// Thread-safe pool of reusable byte buffers.
+///
+/// Cloning a pool yields another handle to the same buffers.
+#[derive(Clone)]
+pub struct BufferPool {
+    idle: Arc<Mutex<Vec<BytesMut>>>,
+    max_idle: usize,
+    buffer_capacity: usize,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_IDLE)
+    }
+}
+
+impl BufferPool {
+    /// Create a pool that keeps at most `max_idle` buffers around.
+    pub fn new(max_idle: usize) -> Self {
+        Self {
+            idle: Arc::new(Mutex::new(Vec::new())),
+            max_idle,
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+        }
+    }
+
+    /// Set the initial capacity of newly allocated buffers.
+    pub fn with_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = capacity;
+        self
+    }
+
+    /// Take an empty buffer, reusing an idle one if available.
+    pub fn take(&self) -> BytesMut {
+        self.idle
+            .lock()
+            .pop()
+            .unwrap_or_else(|| BytesMut::with_capacity(self.buffer_capacity))
+    }
+
+    /// Return a buffer to the pool.
+    pub fn put(&self, mut buf: BytesMut) {
+        buf.clear();
+        let mut idle = self.idle.lock();
+        if idle.len() < self.max_idle {
+            idle.push(buf);
+        }
+    }
+
+    /// Return the buffer behind a received frame to the pool.
+    ///
+    /// Only succeeds when `bytes` is the last handle to its buffer; returns
+    /// `false` if clones are still alive or the pool is full, in which case
+    /// the memory is simply freed when the last handle drops.
+    pub fn recycle(&self, bytes: Bytes) -> bool {
+        if self.idle.lock().len() >= self.max_idle {
+            return false;
+        }
+        match bytes.try_into_mut() {
+            Ok(buf) => {
+                self.put(buf);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Get the number of idle buffers.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().len()
+    }
+}
+
+impl std::fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("idle", &self.idle())
+            .field("max_idle", &self.max_idle)
+            .field("buffer_capacity", &self.buffer_capacity)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_reuse() {
+        let pool = BufferPool::new(1).with_buffer_capacity(64);
+        let mut buf = pool.take();
+        buf.extend_from_slice(b"frame");
+        let ptr = buf.as_ptr();
+        let frame = buf.freeze();
+
+        // A live clone keeps the buffer from being reclaimed
+        let clone = frame.clone();
+        assert!(!pool.recycle(frame));
+        assert!(pool.recycle(clone));
+        assert_eq!(pool.idle(), 1);
+
+        let buf = pool.take();
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_ptr(), ptr);
+
+        // The pool never grows past its limit
+        pool.put(BytesMut::new());
+        pool.put(BytesMut::new());
+        assert_eq!(pool.idle(), 1);
+    }
+}
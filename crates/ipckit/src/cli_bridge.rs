@@ -8,8 +8,10 @@
 //!
 //! - Minimal invasiveness - existing CLI only needs minimal modifications
 //! - Automatic output capture (stdout/stderr)
-//! - Progress bar parsing
+//! - Progress bar parsing, with presets for cargo, pip, ffmpeg and rsync
 //! - Bidirectional communication (CLI can send events, frontend can send commands)
+//! - Optional on-disk spool so events survive the server being unreachable
+//! - Task trees: wrapped commands that use ipckit attach as sub-tasks
 //!
 //! ## Example
 //!
@@ -21,6 +23,7 @@
 //! bridge.register_task("My CLI Task", "build")?;
 //!
 //! bridge.log("info", "Starting build...");
+//! bridge.on_command(|cmd| eprintln!("frontend sent {}", cmd.command));
 //!
 //! for i in 0..100 {
 //!     if bridge.is_cancelled() {
@@ -36,23 +39,65 @@
 //! let output = WrappedCommand::new("cargo")
 //!     .args(["build", "--release"])
 //!     .task("Build Project", "build")
-//!     .progress_parser(parsers::PercentageParser)
+//!     .progress_parser(parsers::CargoParser)
 //!     .run()?;
 //! ```
+//!
+//! ## Task trees
+//!
+//! [`WrappedCommand`] passes the server address, its task ID and its trace
+//! context to the child through [`ENV_SERVER_URL`], [`ENV_TASK_ID`],
+//! [`ENV_PARENT_PID`] and [`ENV_TRACEPARENT`]. A
+//! child that connects with [`CliBridgeConfig::from_env`] (as
+//! [`CliBridge::connect`] and [`WrappedCommand::new`] do) picks these up and
+//! registers its own task as a sub-task, so a build and its steps show up as
+//! one tree and cancelling the build cancels the steps. Use
+//! [`CliBridge::child_env`] to do the same for children spawned by hand.
+//!
+//! ## Offline spooling
+//!
+//! By default events posted while the API server is unreachable are lost.
+//! With [`CliBridgeConfig::spool`] they are appended to a bounded JSONL file
+//! instead and replayed in order once the server answers again, or when the
+//! bridge is dropped, so the frontend still receives the full task history:
+//!
+//! ```rust,ignore
+//! let config = CliBridgeConfig::from_env().spool("/tmp/my-cli.spool.jsonl");
+//! let bridge = CliBridge::connect_with_config(config)?;
+//! ```
 
 use crate::api_server::ApiClient;
 use crate::error::{IpcError, Result};
+use crate::event_stream::EventId;
 use crate::socket_server::SocketServerConfig;
 use crate::task_manager::CancellationToken;
-use parking_lot::RwLock;
+use crate::trace_context::{TraceContext, ENV_TRACEPARENT};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
 use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, ExitStatus, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
+/// How often a wrapped command checks for cancellation while it runs.
+const CHILD_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Default number of events kept in the offline spool.
+const DEFAULT_SPOOL_MAX_EVENTS: usize = 10_000;
+
+/// Environment variable holding the API server address.
+pub const ENV_SERVER_URL: &str = "IPCKIT_SERVER_URL";
+
+/// Environment variable holding the task ID new tasks attach under.
+pub const ENV_TASK_ID: &str = "IPCKIT_TASK_ID";
+
+/// Environment variable holding the PID of the process owning that task.
+pub const ENV_PARENT_PID: &str = "IPCKIT_PARENT_PID";
+
 /// CLI bridge configuration.
 #[derive(Clone)]
 pub struct CliBridgeConfig {
@@ -72,6 +117,22 @@ pub struct CliBridgeConfig {
     pub retry_count: u32,
     /// Retry delay
     pub retry_delay: Duration,
+    /// How long to wait before polling again after a failed command poll
+    pub cancel_poll_interval: Duration,
+    /// How long each command long-poll is held open by the server
+    pub command_wait: Duration,
+    /// Report the task as stalled after this long without output or progress
+    pub stall_timeout: Option<Duration>,
+    /// JSONL file buffering events while the server is unreachable
+    pub spool_path: Option<PathBuf>,
+    /// Most events kept in the spool; the oldest are dropped beyond this
+    pub spool_max_events: usize,
+    /// Register tasks as sub-tasks of this task
+    pub parent_task_id: Option<String>,
+    /// PID of the process owning the parent task
+    pub parent_pid: Option<u32>,
+    /// Trace the bridge's task continues, if started by another hop
+    pub trace: Option<TraceContext>,
 }
 
 impl std::fmt::Debug for CliBridgeConfig {
@@ -85,6 +146,14 @@ impl std::fmt::Debug for CliBridgeConfig {
             .field("connect_timeout", &self.connect_timeout)
             .field("retry_count", &self.retry_count)
             .field("retry_delay", &self.retry_delay)
+            .field("cancel_poll_interval", &self.cancel_poll_interval)
+            .field("command_wait", &self.command_wait)
+            .field("stall_timeout", &self.stall_timeout)
+            .field("spool_path", &self.spool_path)
+            .field("spool_max_events", &self.spool_max_events)
+            .field("parent_task_id", &self.parent_task_id)
+            .field("parent_pid", &self.parent_pid)
+            .field("trace", &self.trace)
             .finish()
     }
 }
@@ -100,6 +169,14 @@ impl Default for CliBridgeConfig {
             connect_timeout: Duration::from_secs(5),
             retry_count: 3,
             retry_delay: Duration::from_millis(500),
+            cancel_poll_interval: Duration::from_millis(500),
+            command_wait: Duration::from_secs(10),
+            stall_timeout: None,
+            spool_path: None,
+            spool_max_events: DEFAULT_SPOOL_MAX_EVENTS,
+            parent_task_id: None,
+            parent_pid: None,
+            trace: None,
         }
     }
 }
@@ -125,18 +202,48 @@ impl CliBridgeConfig {
         self
     }
 
+    /// Spool events to `path` while the server is unreachable.
+    pub fn spool(mut self, path: impl Into<PathBuf>) -> Self {
+        self.spool_path = Some(path.into());
+        self
+    }
+
+    /// Register tasks as sub-tasks of the task `id`.
+    pub fn parent_task(mut self, id: &str) -> Self {
+        self.parent_task_id = Some(id.to_string());
+        self
+    }
+
     /// Load configuration from environment variables.
+    ///
+    /// Besides `IPCKIT_AUTO_REGISTER` and `IPCKIT_SPOOL`, this reads the
+    /// variables [`WrappedCommand`] sets for its child ([`ENV_SERVER_URL`],
+    /// [`ENV_TASK_ID`], [`ENV_PARENT_PID`], [`ENV_TRACEPARENT`]).
     pub fn from_env() -> Self {
         let mut config = Self::default();
 
-        if let Ok(url) = std::env::var("IPCKIT_SERVER_URL") {
+        if let Ok(url) = std::env::var(ENV_SERVER_URL) {
             config.server_url = url;
         }
 
+        if let Ok(id) = std::env::var(ENV_TASK_ID) {
+            config.parent_task_id = Some(id).filter(|id| !id.is_empty());
+        }
+
+        if let Ok(pid) = std::env::var(ENV_PARENT_PID) {
+            config.parent_pid = pid.parse().ok();
+        }
+
+        config.trace = TraceContext::from_env();
+
         if let Ok(auto_reg) = std::env::var("IPCKIT_AUTO_REGISTER") {
             config.auto_register = auto_reg.to_lowercase() != "false";
         }
 
+        if let Ok(path) = std::env::var("IPCKIT_SPOOL") {
+            config.spool_path = Some(path.into());
+        }
+
         config
     }
 }
@@ -186,6 +293,52 @@ pub trait ProgressParser: Send + Sync {
     fn parse(&self, line: &str) -> Option<ProgressInfo>;
 }
 
+impl<P: ProgressParser + ?Sized> ProgressParser for Arc<P> {
+    fn parse(&self, line: &str) -> Option<ProgressInfo> {
+        (**self).parse(line)
+    }
+}
+
+impl dyn ProgressParser {
+    /// Get the preset parser for a well-known tool.
+    ///
+    /// `name` is a program name or path (`"cargo"`, `"/usr/bin/ffmpeg"`,
+    /// `"pip3.exe"`). Supported tools are cargo, pip, ffmpeg and rsync;
+    /// returns `None` for anything else.
+    ///
+    /// ```
+    /// use ipckit::ProgressParser;
+    ///
+    /// let parser = <dyn ProgressParser>::for_tool("rsync").unwrap();
+    /// let info = parser.parse("  1,024 100%  1.00MB/s  0:00:00 (xfr#3, to-chk=7/10)");
+    /// assert_eq!(info.unwrap().percentage(), 30);
+    /// ```
+    pub fn for_tool(name: &str) -> Option<Arc<dyn ProgressParser>> {
+        let program = std::path::Path::new(name)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(name)
+            .to_lowercase();
+        let program = program.strip_suffix(".exe").unwrap_or(&program);
+
+        let parser: Arc<dyn ProgressParser> = match program {
+            "cargo" => Arc::new(parsers::CargoParser),
+            "ffmpeg" => Arc::new(parsers::FfmpegParser::new()),
+            "rsync" => Arc::new(parsers::RsyncParser),
+            p if p == "pip" || p.strip_prefix("pip").is_some_and(is_version) => {
+                Arc::new(parsers::PipParser)
+            }
+            _ => return None,
+        };
+        Some(parser)
+    }
+}
+
+/// Whether `s` looks like a version suffix such as `3` or `3.12`.
+fn is_version(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
 /// Built-in progress parsers.
 pub mod parsers {
     use super::*;
@@ -244,6 +397,136 @@ pub mod parsers {
         }
     }
 
+    /// Cargo parser - matches cargo's build bar, "Building [====>  ] 45/120: serde, syn"
+    ///
+    /// Progress is counted in compilation units; the crates being built
+    /// become the message.
+    #[derive(Debug, Clone, Default)]
+    pub struct CargoParser;
+
+    impl ProgressParser for CargoParser {
+        fn parse(&self, line: &str) -> Option<ProgressInfo> {
+            static RE: LazyLock<Regex> = LazyLock::new(|| {
+                Regex::new(r"Building \[[^\]]*\]\s*(\d+)/(\d+)(?::\s*(.+))?")
+                    .expect("Invalid regex")
+            });
+
+            let caps = RE.captures(line)?;
+            let current = caps.get(1)?.as_str().parse::<u64>().ok()?;
+            let total = caps.get(2)?.as_str().parse::<u64>().ok()?;
+            Some(match caps.get(3) {
+                Some(units) => ProgressInfo::with_message(current, total, units.as_str().trim()),
+                None => ProgressInfo::new(current, total),
+            })
+        }
+    }
+
+    /// Pip parser - matches pip's download bar, "━━━━━╺━━━━ 4.2/10.5 MB 3.1 MB/s eta 0:00:03"
+    ///
+    /// Progress is counted in bytes.
+    #[derive(Debug, Clone, Default)]
+    pub struct PipParser;
+
+    impl ProgressParser for PipParser {
+        fn parse(&self, line: &str) -> Option<ProgressInfo> {
+            static RE: LazyLock<Regex> = LazyLock::new(|| {
+                Regex::new(r"(\d+(?:\.\d+)?)/(\d+(?:\.\d+)?)\s*(bytes|kB|MB|GB)\b")
+                    .expect("Invalid regex")
+            });
+
+            let caps = RE.captures(line)?;
+            let unit = match caps.get(3)?.as_str() {
+                "kB" => 1e3,
+                "MB" => 1e6,
+                "GB" => 1e9,
+                _ => 1.0,
+            };
+            let bytes = |i: usize| -> Option<u64> {
+                Some((caps.get(i)?.as_str().parse::<f64>().ok()? * unit) as u64)
+            };
+            Some(ProgressInfo::new(bytes(1)?, bytes(2)?))
+        }
+    }
+
+    /// FFmpeg parser - compares "time=00:00:10.00" against the input's
+    /// "Duration: 00:01:23.45"
+    ///
+    /// The duration is remembered from the banner ffmpeg prints before
+    /// encoding starts (the longest input wins), so use one parser per run.
+    /// Progress is counted in milliseconds of media. Lines from
+    /// `-progress` output (`out_time=...`) are understood too.
+    #[derive(Debug, Default)]
+    pub struct FfmpegParser {
+        duration_ms: std::sync::atomic::AtomicU64,
+    }
+
+    impl FfmpegParser {
+        /// Create a parser that learns the duration from ffmpeg's output.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Create a parser for media of a known duration.
+        pub fn with_duration(duration: Duration) -> Self {
+            Self {
+                duration_ms: (duration.as_millis() as u64).into(),
+            }
+        }
+
+        fn timestamp_ms(caps: &regex::Captures<'_>) -> Option<u64> {
+            let hours = caps.get(1)?.as_str().parse::<u64>().ok()?;
+            let minutes = caps.get(2)?.as_str().parse::<u64>().ok()?;
+            let seconds = caps.get(3)?.as_str().parse::<f64>().ok()?;
+            Some((hours * 3600 + minutes * 60) * 1000 + (seconds * 1000.0) as u64)
+        }
+    }
+
+    impl ProgressParser for FfmpegParser {
+        fn parse(&self, line: &str) -> Option<ProgressInfo> {
+            static DURATION_RE: LazyLock<Regex> = LazyLock::new(|| {
+                Regex::new(r"Duration:\s*(\d+):(\d{2}):(\d{2}(?:\.\d+)?)").expect("Invalid regex")
+            });
+            static TIME_RE: LazyLock<Regex> = LazyLock::new(|| {
+                Regex::new(r"(?:^|\s)(?:out_)?time=\s*(\d+):(\d{2}):(\d{2}(?:\.\d+)?)")
+                    .expect("Invalid regex")
+            });
+
+            if let Some(caps) = DURATION_RE.captures(line) {
+                let duration = Self::timestamp_ms(&caps)?;
+                self.duration_ms.fetch_max(duration, Ordering::Relaxed);
+                return None;
+            }
+
+            let total = self.duration_ms.load(Ordering::Relaxed);
+            if total == 0 {
+                return None;
+            }
+            let current = Self::timestamp_ms(&TIME_RE.captures(line)?)?;
+            Some(ProgressInfo::new(current.min(total), total))
+        }
+    }
+
+    /// Rsync parser - matches the file counts in "--progress" output,
+    /// "(xfr#5, to-chk=169/396)"
+    ///
+    /// Progress is counted in files checked, so the per-file percentages
+    /// rsync prints are ignored. With incremental recursion (`ir-chk`) the
+    /// total grows as rsync discovers more files.
+    #[derive(Debug, Clone, Default)]
+    pub struct RsyncParser;
+
+    impl ProgressParser for RsyncParser {
+        fn parse(&self, line: &str) -> Option<ProgressInfo> {
+            static RE: LazyLock<Regex> =
+                LazyLock::new(|| Regex::new(r"(?:to|ir)-chk=(\d+)/(\d+)").expect("Invalid regex"));
+
+            let caps = RE.captures(line)?;
+            let remaining = caps.get(1)?.as_str().parse::<u64>().ok()?;
+            let total = caps.get(2)?.as_str().parse::<u64>().ok()?;
+            Some(ProgressInfo::new(total.saturating_sub(remaining), total))
+        }
+    }
+
     /// Composite parser - tries multiple parsers in order.
     #[derive(Default)]
     pub struct CompositeParser {
@@ -284,6 +567,21 @@ pub mod parsers {
     }
 }
 
+/// A command sent to a registered task by the frontend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BridgeCommand {
+    /// Event ID of the command, increasing per server
+    pub id: EventId,
+    /// Command name (e.g., "pause", "set-verbosity")
+    pub command: String,
+    /// Command arguments
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// Callback invoked for every command received by a [`CliBridge`].
+pub type CommandCallback = Arc<dyn Fn(&BridgeCommand) + Send + Sync>;
+
 /// Internal state for the CLI bridge.
 struct BridgeState {
     task_id: Option<String>,
@@ -293,6 +591,7 @@ struct BridgeState {
     progress_message: Option<String>,
     cancelled: AtomicBool,
     completed: AtomicBool,
+    on_command: Option<CommandCallback>,
 }
 
 impl Default for BridgeState {
@@ -305,14 +604,175 @@ impl Default for BridgeState {
             progress_message: None,
             cancelled: AtomicBool::new(false),
             completed: AtomicBool::new(false),
+            on_command: None,
+        }
+    }
+}
+
+/// A POST that could not be delivered, as stored in the spool.
+#[derive(Serialize, Deserialize)]
+struct SpooledEvent {
+    path: String,
+    body: Option<serde_json::Value>,
+}
+
+/// Bounded JSONL file of events waiting for the server to come back.
+struct Spool {
+    path: PathBuf,
+    max_events: usize,
+    /// How long to wait between delivery attempts while events are pending
+    retry_delay: Duration,
+    state: Mutex<SpoolState>,
+}
+
+struct SpoolState {
+    /// Number of events in the file
+    pending: usize,
+    last_attempt: Option<Instant>,
+}
+
+impl Spool {
+    /// Open the spool at `path`, picking up events left by an earlier run.
+    fn open(path: PathBuf, max_events: usize, retry_delay: Duration) -> Self {
+        let pending = Self::read(&path).len();
+        Self {
+            path,
+            max_events: max_events.max(1),
+            retry_delay,
+            state: Mutex::new(SpoolState {
+                pending,
+                last_attempt: None,
+            }),
+        }
+    }
+
+    /// Deliver an event, spooling it if the server cannot be reached.
+    ///
+    /// Pending events are retried first (at most once per `retry_delay`) so
+    /// the server sees everything in order.
+    fn post(&self, client: &ApiClient, path: &str, body: Option<serde_json::Value>) {
+        let mut state = self.state.lock();
+        let due = state
+            .last_attempt
+            .is_none_or(|at| at.elapsed() >= self.retry_delay);
+        if state.pending > 0 && due {
+            self.deliver(client, &mut state);
+        }
+
+        let event = SpooledEvent {
+            path: path.to_string(),
+            body,
+        };
+        if state.pending == 0 {
+            match client.post(&event.path, event.body.clone()) {
+                Err(e) if is_unreachable(&e) => state.last_attempt = Some(Instant::now()),
+                _ => return,
+            }
+        }
+        self.append(&mut state, &event);
+    }
+
+    /// Replay pending events, returning how many are still spooled.
+    fn flush(&self, client: &ApiClient) -> usize {
+        let mut state = self.state.lock();
+        if state.pending > 0 {
+            self.deliver(client, &mut state);
+        }
+        state.pending
+    }
+
+    /// Post spooled events in order until one cannot reach the server.
+    fn deliver(&self, client: &ApiClient, state: &mut SpoolState) {
+        state.last_attempt = Some(Instant::now());
+        let events = Self::read(&self.path);
+        let delivered = events
+            .iter()
+            .take_while(|event| {
+                !matches!(client.post(&event.path, event.body.clone()), Err(ref e) if is_unreachable(e))
+            })
+            .count();
+        self.rewrite(state, &events[delivered..]);
+    }
+
+    fn append(&self, state: &mut SpoolState, event: &SpooledEvent) {
+        if state.pending >= self.max_events {
+            let events = Self::read(&self.path);
+            let dropped = (events.len() + 1).saturating_sub(self.max_events);
+            tracing::warn!(
+                "Spool {} is full, dropping {} oldest events",
+                self.path.display(),
+                dropped
+            );
+            self.rewrite(state, &events[dropped..]);
+        }
+
+        let written = serde_json::to_vec(event)
+            .map_err(std::io::Error::other)
+            .and_then(|mut line| {
+                line.push(b'\n');
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?
+                    .write_all(&line)
+            });
+        match written {
+            Ok(()) => state.pending += 1,
+            Err(e) => tracing::warn!("Cannot spool event to {}: {}", self.path.display(), e),
+        }
+    }
+
+    /// Replace the file's contents with `events`, removing it when empty.
+    fn rewrite(&self, state: &mut SpoolState, events: &[SpooledEvent]) {
+        let result = if events.is_empty() {
+            match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        } else {
+            let mut data = Vec::new();
+            for event in events {
+                if let Ok(line) = serde_json::to_vec(event) {
+                    data.extend(line);
+                    data.push(b'\n');
+                }
+            }
+            std::fs::write(&self.path, data)
+        };
+        if let Err(e) = result {
+            tracing::warn!("Cannot rewrite spool {}: {}", self.path.display(), e);
         }
+        state.pending = events.len();
+    }
+
+    fn read(path: &Path) -> Vec<SpooledEvent> {
+        let Ok(file) = std::fs::File::open(path) else {
+            return Vec::new();
+        };
+        BufReader::new(file)
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect()
     }
 }
 
+/// Whether a request failed because the server could not be reached.
+fn is_unreachable(err: &IpcError) -> bool {
+    matches!(
+        err,
+        IpcError::Io(_) | IpcError::NotFound(_) | IpcError::Closed | IpcError::Timeout
+    )
+}
+
 /// CLI Bridge for integrating CLI tools with ipckit.
+///
+/// Dropping the bridge makes a last attempt to deliver spooled events.
 pub struct CliBridge {
     config: CliBridgeConfig,
+    trace: TraceContext,
     client: Option<ApiClient>,
+    spool: Option<Arc<Spool>>,
     state: Arc<RwLock<BridgeState>>,
     cancel_token: CancellationToken,
 }
@@ -321,8 +781,10 @@ impl CliBridge {
     /// Create a new CLI bridge with the given configuration.
     pub fn new(config: CliBridgeConfig) -> Result<Self> {
         Ok(Self {
+            trace: Self::first_hop(&config),
             config,
             client: None,
+            spool: None,
             state: Arc::new(RwLock::new(BridgeState::default())),
             cancel_token: CancellationToken::new(),
         })
@@ -336,15 +798,81 @@ impl CliBridge {
     /// Connect with the given configuration.
     pub fn connect_with_config(config: CliBridgeConfig) -> Result<Self> {
         let client = ApiClient::new(&config.server_url);
+        let spool = config.spool_path.clone().map(|path| {
+            Arc::new(Spool::open(
+                path,
+                config.spool_max_events,
+                config.retry_delay,
+            ))
+        });
 
         Ok(Self {
+            trace: Self::first_hop(&config),
             config,
             client: Some(client),
+            spool,
             state: Arc::new(RwLock::new(BridgeState::default())),
             cancel_token: CancellationToken::new(),
         })
     }
 
+    /// The bridge's hop of the trace it continues, or a new trace.
+    fn first_hop(config: &CliBridgeConfig) -> TraceContext {
+        config
+            .trace
+            .or_else(TraceContext::current)
+            .map_or_else(TraceContext::root, |parent| parent.child())
+    }
+
+    /// The trace context the bridge's requests are sent in.
+    ///
+    /// Child processes started through [`child_env`](Self::child_env)
+    /// continue this trace.
+    pub fn trace_context(&self) -> TraceContext {
+        self.trace
+    }
+
+    /// Post an event to the server, spooling it if the server is unreachable.
+    fn post(&self, path: &str, body: Option<serde_json::Value>) {
+        let Some(ref client) = self.client else {
+            return;
+        };
+        let _trace = self.trace.enter();
+        match self.spool {
+            Some(ref spool) => spool.post(client, path, body),
+            None => {
+                let _ = client.post(path, body);
+            }
+        }
+    }
+
+    /// Environment variables that make a child process's tasks sub-tasks of
+    /// this bridge's task.
+    ///
+    /// Empty until a task is registered. [`WrappedCommand`] sets these for
+    /// its child automatically.
+    pub fn child_env(&self) -> Vec<(&'static str, String)> {
+        match (&self.client, self.task_id()) {
+            (Some(_), Some(task_id)) => vec![
+                (ENV_SERVER_URL, self.config.server_url.clone()),
+                (ENV_TASK_ID, task_id),
+                (ENV_PARENT_PID, std::process::id().to_string()),
+                (ENV_TRACEPARENT, self.trace.to_string()),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Try to deliver spooled events now.
+    ///
+    /// Returns the number of events still waiting for the server.
+    pub fn flush_spool(&self) -> usize {
+        match (&self.client, &self.spool) {
+            (Some(client), Some(spool)) => spool.flush(client),
+            _ => 0,
+        }
+    }
+
     /// Register the current process as a task.
     pub fn register_task(&self, name: &str, task_type: &str) -> Result<String> {
         let task_id = format!(
@@ -364,21 +892,110 @@ impl CliBridge {
         }
 
         // If connected, register with the server
-        if let Some(ref client) = self.client {
-            let _ = client.post(
+        if self.client.is_some() {
+            let mut metadata = serde_json::Map::new();
+            if let Some(pid) = self.config.parent_pid {
+                metadata.insert("parent_pid".to_string(), pid.into());
+            }
+            metadata.insert("trace_id".to_string(), self.trace.trace_id().into());
+            self.post(
                 "/v1/tasks",
                 Some(serde_json::json!({
                     "id": task_id,
                     "name": name,
                     "type": task_type,
-                    "status": "running"
+                    "status": "running",
+                    "stall_timeout_ms": self.config.stall_timeout.map(|t| t.as_millis() as u64),
+                    "parent_id": self.config.parent_task_id,
+                    "metadata": metadata,
                 })),
             );
+            self.watch_cancellation(&task_id);
         }
 
         Ok(task_id)
     }
 
+    /// Listen for cancellation of and commands for `task_id` in the background.
+    ///
+    /// The listener long-polls the server's command endpoint, hands each
+    /// command to the `on_command` callback, and trips this bridge's token
+    /// once the task is cancelled. It exits when the task finishes or the
+    /// bridge is dropped. Servers without the command endpoint are polled for
+    /// the task status instead.
+    fn watch_cancellation(&self, task_id: &str) {
+        let client = ApiClient::with_timeout(&self.config.server_url, self.config.connect_timeout);
+        let task_path = format!("/v1/tasks/{}", task_id);
+        let interval = self.config.cancel_poll_interval;
+        let wait_ms = self.config.command_wait.as_millis();
+        let token = self.cancel_token.clone();
+        let state: Weak<RwLock<BridgeState>> = Arc::downgrade(&self.state);
+        let trace = self.trace;
+
+        thread::spawn(move || {
+            let _trace = trace.enter();
+            let mut after: Option<EventId> = None;
+            loop {
+                match state.upgrade() {
+                    Some(state) if !state.read().completed.load(Ordering::SeqCst) => {}
+                    _ => break,
+                }
+
+                let mut path = format!("{}/commands?timeout_ms={}", task_path, wait_ms);
+                if let Some(after) = after {
+                    path.push_str(&format!("&after={}", after));
+                }
+
+                let status = match client.get(&path) {
+                    Ok(reply) if reply["status"].is_string() => {
+                        let commands: Vec<BridgeCommand> =
+                            serde_json::from_value(reply["commands"].clone()).unwrap_or_default();
+                        if let Some(last) = commands.last() {
+                            after = Some(last.id);
+                        }
+
+                        let callback = state.upgrade().and_then(|s| s.read().on_command.clone());
+                        if let Some(callback) = callback {
+                            for command in &commands {
+                                callback(command);
+                            }
+                        }
+                        reply["status"].clone()
+                    }
+                    _ => {
+                        thread::sleep(interval);
+                        match client.get(&task_path) {
+                            Ok(task) => task["status"].clone(),
+                            Err(_) => continue,
+                        }
+                    }
+                };
+
+                if status == "cancelled" {
+                    if let Some(state) = state.upgrade() {
+                        state.read().cancelled.store(true, Ordering::SeqCst);
+                    }
+                    token.cancel();
+                    break;
+                }
+                if status == "completed" || status == "failed" {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Set the callback invoked for each command the frontend sends to the
+    /// registered task.
+    ///
+    /// Commands are delivered in order on the bridge's listener thread.
+    pub fn on_command<F>(&self, callback: F)
+    where
+        F: Fn(&BridgeCommand) + Send + Sync + 'static,
+    {
+        self.state.write().on_command = Some(Arc::new(callback));
+    }
+
     /// Get the current task ID.
     pub fn task_id(&self) -> Option<String> {
         self.state.read().task_id.clone()
@@ -397,8 +1014,8 @@ impl CliBridge {
         }
 
         // Send to server if connected
-        if let (Some(ref client), Some(task_id)) = (&self.client, self.task_id()) {
-            let _ = client.post(
+        if let Some(task_id) = self.task_id() {
+            self.post(
                 &format!("/v1/tasks/{}/progress", task_id),
                 Some(serde_json::json!({
                     "progress": progress,
@@ -414,8 +1031,8 @@ impl CliBridge {
         eprintln!("[{}] {}", level.to_uppercase(), message);
 
         // Send to server if connected
-        if let (Some(ref client), Some(task_id)) = (&self.client, self.task_id()) {
-            let _ = client.post(
+        if let Some(task_id) = self.task_id() {
+            self.post(
                 &format!("/v1/tasks/{}/logs", task_id),
                 Some(serde_json::json!({
                     "level": level,
@@ -429,8 +1046,8 @@ impl CliBridge {
     pub fn stdout(&self, line: &str) {
         println!("{}", line);
 
-        if let (Some(ref client), Some(task_id)) = (&self.client, self.task_id()) {
-            let _ = client.post(
+        if let Some(task_id) = self.task_id() {
+            self.post(
                 &format!("/v1/tasks/{}/stdout", task_id),
                 Some(serde_json::json!({ "line": line })),
             );
@@ -441,14 +1058,24 @@ impl CliBridge {
     pub fn stderr(&self, line: &str) {
         eprintln!("{}", line);
 
-        if let (Some(ref client), Some(task_id)) = (&self.client, self.task_id()) {
-            let _ = client.post(
+        if let Some(task_id) = self.task_id() {
+            self.post(
                 &format!("/v1/tasks/{}/stderr", task_id),
                 Some(serde_json::json!({ "line": line })),
             );
         }
     }
 
+    /// Tell the server the task is still alive during a quiet stretch.
+    ///
+    /// Only needed with [`CliBridgeConfig::stall_timeout`]; progress and
+    /// output already count as activity.
+    pub fn heartbeat(&self) {
+        if let Some(task_id) = self.task_id() {
+            self.post(&format!("/v1/tasks/{}/heartbeat", task_id), None);
+        }
+    }
+
     /// Check if cancellation has been requested.
     pub fn is_cancelled(&self) -> bool {
         self.cancel_token.is_cancelled() || self.state.read().cancelled.load(Ordering::SeqCst)
@@ -463,8 +1090,8 @@ impl CliBridge {
     pub fn complete(&self, result: serde_json::Value) {
         self.state.write().completed.store(true, Ordering::SeqCst);
 
-        if let (Some(ref client), Some(task_id)) = (&self.client, self.task_id()) {
-            let _ = client.post(
+        if let Some(task_id) = self.task_id() {
+            self.post(
                 &format!("/v1/tasks/{}/complete", task_id),
                 Some(serde_json::json!({ "result": result })),
             );
@@ -475,8 +1102,8 @@ impl CliBridge {
     pub fn fail(&self, error: &str) {
         self.state.write().completed.store(true, Ordering::SeqCst);
 
-        if let (Some(ref client), Some(task_id)) = (&self.client, self.task_id()) {
-            let _ = client.post(
+        if let Some(task_id) = self.task_id() {
+            self.post(
                 &format!("/v1/tasks/{}/fail", task_id),
                 Some(serde_json::json!({ "error": error })),
             );
@@ -491,6 +1118,7 @@ impl CliBridge {
             OutputType::Stdout,
             self.config.progress_parser.clone(),
             Arc::clone(&self.state),
+            self.spool.clone(),
         )
     }
 
@@ -502,10 +1130,20 @@ impl CliBridge {
             OutputType::Stderr,
             None,
             Arc::clone(&self.state),
+            self.spool.clone(),
         )
     }
 }
 
+impl Drop for CliBridge {
+    fn drop(&mut self) {
+        let pending = self.flush_spool();
+        if pending > 0 {
+            tracing::warn!("{} events are still spooled for the server", pending);
+        }
+    }
+}
+
 /// Output type for wrapped writers.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputType {
@@ -520,6 +1158,7 @@ pub struct WrappedWriter {
     output_type: OutputType,
     progress_parser: Option<Arc<dyn ProgressParser>>,
     state: Arc<RwLock<BridgeState>>,
+    spool: Option<Arc<Spool>>,
     buffer: Vec<u8>,
 }
 
@@ -530,6 +1169,7 @@ impl WrappedWriter {
         output_type: OutputType,
         progress_parser: Option<Arc<dyn ProgressParser>>,
         state: Arc<RwLock<BridgeState>>,
+        spool: Option<Arc<Spool>>,
     ) -> Self {
         let client = Some(ApiClient::new(&server_url));
         Self {
@@ -538,6 +1178,7 @@ impl WrappedWriter {
             output_type,
             progress_parser,
             state,
+            spool,
             buffer: Vec::new(),
         }
     }
@@ -558,7 +1199,13 @@ impl WrappedWriter {
                 OutputType::Stdout => format!("/v1/tasks/{}/stdout", task_id),
                 OutputType::Stderr => format!("/v1/tasks/{}/stderr", task_id),
             };
-            let _ = client.post(&endpoint, Some(serde_json::json!({ "line": line })));
+            let body = Some(serde_json::json!({ "line": line }));
+            match self.spool {
+                Some(ref spool) => spool.post(client, &endpoint, body),
+                None => {
+                    let _ = client.post(&endpoint, body);
+                }
+            }
         }
     }
 }
@@ -690,13 +1337,15 @@ impl WrappedCommand {
         // Try to connect to bridge
         let bridge = CliBridge::connect_with_config(self.bridge_config.clone()).ok();
 
-        // Register task if connected
+        // Register task if connected, and let the child attach beneath it
         if let Some(ref bridge) = bridge {
             let _ = bridge.register_task(&self.task_name, &self.task_type);
+            self.command.envs(bridge.child_env());
         }
 
         // Spawn the command
-        let mut child = self.command.spawn().map_err(IpcError::Io)?;
+        let mut child =
+            ProcessTree::spawn(&mut self.command, bridge.is_some()).map_err(IpcError::Io)?;
 
         // Capture output
         let stdout = child.stdout.take();
@@ -746,8 +1395,9 @@ impl WrappedCommand {
             })
         });
 
-        // Wait for command to complete
-        let status = child.wait().map_err(IpcError::Io)?;
+        // Wait for command to complete, killing it if the task is cancelled
+        let cancel_token = bridge.as_ref().map(|b| b.cancel_token());
+        let status = wait_or_cancel(&mut child, cancel_token.as_ref())?;
 
         // Collect output
         let stdout_output = stdout_handle
@@ -760,8 +1410,8 @@ impl WrappedCommand {
         let duration = start.elapsed();
         let exit_code = status.code().unwrap_or(-1);
 
-        // Report completion
-        if let Some(ref bridge) = bridge {
+        // Report completion (a cancelled task has already been finalized)
+        if let Some(bridge) = bridge.as_ref().filter(|b| !b.is_cancelled()) {
             if exit_code == 0 {
                 bridge.complete(serde_json::json!({
                     "exit_code": exit_code,
@@ -785,15 +1435,18 @@ impl WrappedCommand {
         // Try to connect to bridge
         let bridge = CliBridge::connect_with_config(self.bridge_config.clone()).ok();
 
-        // Register task if connected
+        // Register task if connected, and let the child attach beneath it
         let task_id = if let Some(ref bridge) = bridge {
-            bridge.register_task(&self.task_name, &self.task_type).ok()
+            let task_id = bridge.register_task(&self.task_name, &self.task_type).ok();
+            self.command.envs(bridge.child_env());
+            task_id
         } else {
             None
         };
 
         // Spawn the command
-        let child = self.command.spawn().map_err(IpcError::Io)?;
+        let child =
+            ProcessTree::spawn(&mut self.command, bridge.is_some()).map_err(IpcError::Io)?;
 
         Ok(WrappedChild {
             child,
@@ -806,7 +1459,7 @@ impl WrappedCommand {
 
 /// A wrapped child process.
 pub struct WrappedChild {
-    child: Child,
+    child: ProcessTree,
     bridge: Option<CliBridge>,
     task_id: Option<String>,
     start_time: Instant,
@@ -814,13 +1467,17 @@ pub struct WrappedChild {
 
 impl WrappedChild {
     /// Wait for the process to complete.
+    ///
+    /// If the task is cancelled on the server meanwhile, the process tree is
+    /// killed.
     pub fn wait(mut self) -> Result<CommandOutput> {
-        let status = self.child.wait().map_err(IpcError::Io)?;
+        let cancel_token = self.bridge.as_ref().map(|b| b.cancel_token());
+        let status = wait_or_cancel(&mut self.child, cancel_token.as_ref())?;
         let duration = self.start_time.elapsed();
         let exit_code = status.code().unwrap_or(-1);
 
-        // Report completion
-        if let Some(ref bridge) = self.bridge {
+        // Report completion (a cancelled task has already been finalized)
+        if let Some(bridge) = self.bridge.as_ref().filter(|b| !b.is_cancelled()) {
             if exit_code == 0 {
                 bridge.complete(serde_json::json!({
                     "exit_code": exit_code,
@@ -839,11 +1496,16 @@ impl WrappedChild {
         })
     }
 
-    /// Send a cancel signal to the process.
+    /// Kill the process and all of its descendants.
     pub fn cancel(&mut self) -> Result<()> {
         self.child.kill().map_err(IpcError::Io)
     }
 
+    /// Get the cancellation token that kills this process when tripped.
+    pub fn cancel_token(&self) -> Option<CancellationToken> {
+        self.bridge.as_ref().map(|b| b.cancel_token())
+    }
+
     /// Get the task ID.
     pub fn task_id(&self) -> Option<&str> {
         self.task_id.as_deref()
@@ -855,6 +1517,366 @@ impl WrappedChild {
     }
 }
 
+/// Wait for `child`, killing its process tree once `cancel` is tripped.
+pub(crate) fn wait_or_cancel(
+    child: &mut ProcessTree,
+    cancel: Option<&CancellationToken>,
+) -> Result<ExitStatus> {
+    let Some(cancel) = cancel else {
+        return child.wait().map_err(IpcError::Io);
+    };
+
+    loop {
+        if let Some(status) = child.try_wait().map_err(IpcError::Io)? {
+            return Ok(status);
+        }
+        if cancel.is_cancelled() {
+            child.kill().map_err(IpcError::Io)?;
+            return child.wait().map_err(IpcError::Io);
+        }
+        thread::sleep(CHILD_POLL_INTERVAL);
+    }
+}
+
+/// A child process, started as the root of its own process tree when it must
+/// be cancellable: a new process group on Unix, a job object on Windows.
+/// Descendants inherit the group or job, so the whole tree can be killed at
+/// once.
+pub(crate) struct ProcessTree {
+    child: Child,
+    #[cfg(unix)]
+    group: Option<group::ForwardedGroup>,
+    #[cfg(windows)]
+    job: Option<job::JobObject>,
+}
+
+impl ProcessTree {
+    /// Spawn `command`, in a new process tree if it is `cancellable`.
+    ///
+    /// Otherwise the child shares the caller's process group (or job), so
+    /// Ctrl+C in the terminal reaches it as usual, and [`kill`](Self::kill)
+    /// only reaches the child itself.
+    ///
+    /// On Unix, SIGINT and SIGTERM sent to this process are forwarded to the
+    /// new group, and a child started from the terminal's foreground group
+    /// takes over the terminal until it exits. On Windows, the child starts
+    /// suspended and only runs once it is in the job, so none of its
+    /// descendants escape; any creation flags set on `command` are replaced.
+    pub(crate) fn spawn(command: &mut Command, cancellable: bool) -> std::io::Result<Self> {
+        if !cancellable {
+            return Ok(Self {
+                child: command.spawn()?,
+                #[cfg(unix)]
+                group: None,
+                #[cfg(windows)]
+                job: None,
+            });
+        }
+
+        #[cfg(unix)]
+        {
+            let foreground = group::prepare(command);
+            let child = command.spawn()?;
+            Ok(Self {
+                group: Some(group::ForwardedGroup::new(child.id(), foreground)),
+                child,
+            })
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            use windows_sys::Win32::System::Threading::CREATE_SUSPENDED;
+
+            command.creation_flags(CREATE_SUSPENDED);
+            let mut child = command.spawn()?;
+            // Without a job the child itself can still be killed
+            let job = job::JobObject::assign(&child).ok();
+            if let Err(e) = job::resume(&child) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+            Ok(Self { child, job })
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        Ok(Self {
+            child: command.spawn()?,
+        })
+    }
+
+    /// Kill the process and all of its descendants.
+    ///
+    /// Descendants that moved to a process group (or job) of their own are
+    /// not reached.
+    pub(crate) fn kill(&mut self) -> std::io::Result<()> {
+        #[cfg(unix)]
+        if self.group.is_some() {
+            unsafe {
+                // The group is named after the child, its leader
+                libc::killpg(self.child.id() as libc::pid_t, libc::SIGKILL);
+            }
+        }
+
+        #[cfg(windows)]
+        if let Some(job) = &self.job {
+            job.terminate();
+        }
+
+        match self.child.kill() {
+            // Already exited
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl std::ops::Deref for ProcessTree {
+    type Target = Child;
+
+    fn deref(&self) -> &Child {
+        &self.child
+    }
+}
+
+impl std::ops::DerefMut for ProcessTree {
+    fn deref_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+}
+
+#[cfg(unix)]
+mod group {
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+    use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+    use std::sync::Once;
+
+    const SIGNALS: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+    /// Process groups SIGINT and SIGTERM are forwarded to; 0 marks a free slot
+    static GROUPS: [AtomicI32; 64] = [const { AtomicI32::new(0) }; 64];
+    /// Dispositions in place before forwarding was installed, per signal
+    static PREVIOUS: [AtomicUsize; 2] = [const { AtomicUsize::new(libc::SIG_DFL) }; 2];
+    static PREVIOUS_FLAGS: [AtomicI32; 2] = [const { AtomicI32::new(0) }; 2];
+    static INSTALL: Once = Once::new();
+
+    /// A process group that receives the signals sent to this process, and
+    /// may hold the terminal
+    pub(super) struct ForwardedGroup {
+        pgid: libc::pid_t,
+        slot: Option<usize>,
+        foreground: bool,
+    }
+
+    impl ForwardedGroup {
+        /// Start forwarding to the group led by `pid`.
+        pub(super) fn new(pid: u32, foreground: bool) -> Self {
+            INSTALL.call_once(install);
+            let pgid = pid as libc::pid_t;
+            // With every slot taken the group is still killable, just not
+            // reached by forwarded signals
+            let slot = GROUPS.iter().position(|slot| {
+                slot.compare_exchange(0, pgid, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            });
+            Self {
+                pgid,
+                slot,
+                foreground,
+            }
+        }
+    }
+
+    impl Drop for ForwardedGroup {
+        fn drop(&mut self) {
+            if let Some(slot) = self.slot {
+                GROUPS[slot].store(0, Ordering::SeqCst);
+            }
+            // Take the terminal back if the child's group still holds it
+            if self.foreground && unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) } == self.pgid {
+                with_sigttou_blocked(|| unsafe {
+                    libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+                });
+            }
+        }
+    }
+
+    /// Put the child started by `command` in a new process group, handing it
+    /// the terminal if this process is in the foreground, so that a child
+    /// reading the TTY is not stopped by SIGTTIN. Returns whether it will
+    /// take the terminal.
+    pub(super) fn prepare(command: &mut Command) -> bool {
+        command.process_group(0);
+        let foreground = unsafe {
+            libc::isatty(libc::STDIN_FILENO) == 1
+                && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp()
+        };
+        if foreground {
+            unsafe {
+                // Runs in the child, after it has joined its new group
+                command.pre_exec(|| {
+                    with_sigttou_blocked(|| {
+                        libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+                    });
+                    Ok(())
+                });
+            }
+        }
+        foreground
+    }
+
+    /// Run `f` with SIGTTOU blocked, which lets a background process change
+    /// the terminal's foreground group; async-signal-safe.
+    fn with_sigttou_blocked(f: impl FnOnce()) {
+        unsafe {
+            let mut block: libc::sigset_t = std::mem::zeroed();
+            let mut old: libc::sigset_t = std::mem::zeroed();
+            libc::sigemptyset(&mut block);
+            libc::sigaddset(&mut block, libc::SIGTTOU);
+            libc::pthread_sigmask(libc::SIG_BLOCK, &block, &mut old);
+            f();
+            libc::pthread_sigmask(libc::SIG_SETMASK, &old, std::ptr::null_mut());
+        }
+    }
+
+    fn install() {
+        for (index, signal) in SIGNALS.into_iter().enumerate() {
+            let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+            action.sa_sigaction = on_signal
+                as extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void)
+                as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART | libc::SA_SIGINFO;
+            let mut previous: libc::sigaction = unsafe { std::mem::zeroed() };
+            unsafe {
+                libc::sigemptyset(&mut action.sa_mask);
+                // Record the previous disposition before the handler can run
+                libc::sigaction(signal, std::ptr::null(), &mut previous);
+            }
+            PREVIOUS[index].store(previous.sa_sigaction, Ordering::SeqCst);
+            PREVIOUS_FLAGS[index].store(previous.sa_flags as i32, Ordering::SeqCst);
+            unsafe { libc::sigaction(signal, &action, std::ptr::null_mut()) };
+        }
+    }
+
+    /// Forward the signal to every registered group, then act as the
+    /// previous disposition would have.
+    extern "C" fn on_signal(
+        signal: libc::c_int,
+        info: *mut libc::siginfo_t,
+        context: *mut libc::c_void,
+    ) {
+        for slot in &GROUPS {
+            let pgid = slot.load(Ordering::Relaxed);
+            if pgid > 0 {
+                unsafe { libc::killpg(pgid, signal) };
+            }
+        }
+
+        let index = usize::from(signal == libc::SIGTERM);
+        match PREVIOUS[index].load(Ordering::Relaxed) {
+            libc::SIG_IGN => {}
+            libc::SIG_DFL => unsafe {
+                // Delivered with the default action once the handler returns
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            },
+            handler => unsafe {
+                if PREVIOUS_FLAGS[index].load(Ordering::Relaxed) & libc::SA_SIGINFO != 0 {
+                    let handler: extern "C" fn(
+                        libc::c_int,
+                        *mut libc::siginfo_t,
+                        *mut libc::c_void,
+                    ) = std::mem::transmute(handler);
+                    handler(signal, info, context);
+                } else {
+                    let handler: extern "C" fn(libc::c_int) = std::mem::transmute(handler);
+                    handler(signal);
+                }
+            },
+        }
+    }
+}
+
+#[cfg(windows)]
+mod job {
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use std::ptr;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, TerminateJobObject,
+    };
+    use windows_sys::Win32::System::Threading::{OpenThread, ResumeThread, THREAD_SUSPEND_RESUME};
+
+    /// Job object holding a process tree
+    pub(super) struct JobObject {
+        handle: HANDLE,
+    }
+
+    // Safety: job handles may be used from any thread
+    unsafe impl Send for JobObject {}
+    unsafe impl Sync for JobObject {}
+
+    impl JobObject {
+        /// Create an anonymous job and put `child` in it.
+        pub(super) fn assign(child: &Child) -> std::io::Result<Self> {
+            let handle = unsafe { CreateJobObjectW(ptr::null(), ptr::null()) };
+            if handle.is_null() {
+                return Err(std::io::Error::last_os_error());
+            }
+            let job = Self { handle };
+            if unsafe { AssignProcessToJobObject(job.handle, child.as_raw_handle() as HANDLE) } == 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(job)
+        }
+
+        /// Kill every process in the job.
+        pub(super) fn terminate(&self) {
+            unsafe { TerminateJobObject(self.handle, 1) };
+        }
+    }
+
+    /// Resume the threads of `child`, started with `CREATE_SUSPENDED`.
+    pub(super) fn resume(child: &Child) -> std::io::Result<()> {
+        let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut entry: THREADENTRY32 = unsafe { std::mem::zeroed() };
+        entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+        let mut result = Ok(());
+        let mut more = unsafe { Thread32First(snapshot, &mut entry) } != 0;
+        while more {
+            if entry.th32OwnerProcessID == child.id() {
+                let thread = unsafe { OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID) };
+                if thread.is_null() || unsafe { ResumeThread(thread) } == u32::MAX {
+                    result = Err(std::io::Error::last_os_error());
+                }
+                if !thread.is_null() {
+                    unsafe { CloseHandle(thread) };
+                }
+            }
+            more = unsafe { Thread32Next(snapshot, &mut entry) } != 0;
+        }
+        unsafe { CloseHandle(snapshot) };
+        result
+    }
+
+    impl Drop for JobObject {
+        fn drop(&mut self) {
+            // Closing the job leaves its processes running
+            unsafe { CloseHandle(self.handle) };
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1017,6 +2039,112 @@ mod tests {
 
     // ==================== ProgressInfo Tests ====================
 
+    #[test]
+    fn test_cargo_parser() {
+        let parser = parsers::CargoParser;
+
+        let info = parser
+            .parse("    Building [=======>                 ] 45/120: serde, syn")
+            .unwrap();
+        assert_eq!((info.current, info.total), (45, 120));
+        assert_eq!(info.message.as_deref(), Some("serde, syn"));
+
+        let info = parser
+            .parse("    Building [=====================> ] 119/120")
+            .unwrap();
+        assert_eq!(info.current, 119);
+        assert!(info.message.is_none());
+
+        // Crate versions are not progress
+        assert!(parser.parse("   Compiling serde v1.0.200").is_none());
+    }
+
+    #[test]
+    fn test_pip_parser() {
+        let parser = parsers::PipParser;
+
+        let info = parser
+            .parse("   ━━━━━━━━━━━━━━━━╺━━━━━━━━━━━━━━━━━━━━━━━ 4.2/10.5 MB 3.1 MB/s eta 0:00:03")
+            .unwrap();
+        assert_eq!(info.current, 4_200_000);
+        assert_eq!(info.total, 10_500_000);
+        assert_eq!(info.percentage(), 40);
+
+        let info = parser
+            .parse("   ━━━━━━━━━━━━━━━━━━━━━ 512.0/512.0 kB 1.2 MB/s")
+            .unwrap();
+        assert_eq!(info.percentage(), 100);
+
+        assert!(parser.parse("Collecting requests==2.31.0").is_none());
+    }
+
+    #[test]
+    fn test_ffmpeg_parser() {
+        let parser = parsers::FfmpegParser::new();
+
+        // No duration seen yet
+        assert!(parser
+            .parse("frame=  240 fps= 48 q=28.0 size=1024kB time=00:00:10.00 bitrate=838.9kbits/s")
+            .is_none());
+
+        assert!(parser
+            .parse("  Duration: 00:01:40.00, start: 0.000000, bitrate: 1205 kb/s")
+            .is_none());
+        let info = parser
+            .parse("frame=  240 fps= 48 q=28.0 size=1024kB time=00:00:10.00 bitrate=838.9kbits/s")
+            .unwrap();
+        assert_eq!((info.current, info.total), (10_000, 100_000));
+        assert_eq!(info.percentage(), 10);
+
+        // -progress output
+        let info = parser.parse("out_time=00:00:50.000000").unwrap();
+        assert_eq!(info.percentage(), 50);
+
+        let parser = parsers::FfmpegParser::with_duration(Duration::from_secs(20));
+        let info = parser
+            .parse("size=512kB time=00:00:05.00 speed=2x")
+            .unwrap();
+        assert_eq!(info.percentage(), 25);
+    }
+
+    #[test]
+    fn test_rsync_parser() {
+        let parser = parsers::RsyncParser;
+
+        // The per-file 100% must not be taken as overall progress
+        let info = parser
+            .parse("      1,238,099 100%  146.38kB/s    0:00:08 (xfr#5, to-chk=169/396)")
+            .unwrap();
+        assert_eq!((info.current, info.total), (227, 396));
+
+        let info = parser
+            .parse("  32,768  50%  1.00MB/s  0:00:00 (xfr#1, ir-chk=1009/1012)")
+            .unwrap();
+        assert_eq!(info.current, 3);
+
+        assert!(parser.parse("sending incremental file list").is_none());
+    }
+
+    #[test]
+    fn test_parser_for_tool() {
+        let cargo = <dyn ProgressParser>::for_tool("cargo").unwrap();
+        assert_eq!(
+            cargo
+                .parse("Building [=>  ] 1/4: foo")
+                .map(|p| p.percentage()),
+            Some(25)
+        );
+        assert!(<dyn ProgressParser>::for_tool("/usr/bin/rsync").is_some());
+        assert!(<dyn ProgressParser>::for_tool("FFMPEG.exe").is_some());
+        assert!(<dyn ProgressParser>::for_tool("pip3.12").is_some());
+        assert!(<dyn ProgressParser>::for_tool("pipx").is_none());
+        assert!(<dyn ProgressParser>::for_tool("make").is_none());
+
+        // Presets plug into the builders
+        let config = CliBridgeConfig::default().progress_parser(cargo);
+        assert!(config.progress_parser.is_some());
+    }
+
     #[test]
     fn test_progress_info() {
         let info = ProgressInfo::new(50, 100);
@@ -1285,6 +2413,7 @@ mod tests {
             OutputType::Stdout,
             Some(Arc::new(parsers::PercentageParser)),
             Arc::clone(&state),
+            None,
         );
 
         // Write a line with progress
@@ -1306,6 +2435,7 @@ mod tests {
             OutputType::Stderr,
             None,
             Arc::clone(&state),
+            None,
         );
 
         let data = b"Error message\n";
@@ -1322,6 +2452,7 @@ mod tests {
             OutputType::Stdout,
             Some(Arc::new(parsers::PercentageParser)),
             Arc::clone(&state),
+            None,
         );
 
         // Write partial line
@@ -1342,6 +2473,7 @@ mod tests {
             OutputType::Stdout,
             Some(Arc::new(parsers::PercentageParser)),
             Arc::clone(&state),
+            None,
         );
 
         // Write without newline
@@ -1367,4 +2499,241 @@ mod tests {
         assert_eq!(format!("{:?}", OutputType::Stdout), "Stdout");
         assert_eq!(format!("{:?}", OutputType::Stderr), "Stderr");
     }
+
+    // ==================== Cancellation Tests ====================
+
+    fn spawn_task_server(name: &str) -> Arc<crate::task_manager::TaskManager> {
+        let manager = Arc::new(crate::task_manager::TaskManager::new(Default::default()));
+        let server = crate::api_server::ApiServer::new(crate::api_server::ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(name),
+            ..Default::default()
+        });
+        server.router().task_routes(Arc::clone(&manager));
+        let _ = server.spawn();
+        thread::sleep(Duration::from_millis(100));
+        manager
+    }
+
+    fn fast_poll_config(name: &str) -> CliBridgeConfig {
+        CliBridgeConfig {
+            cancel_poll_interval: Duration::from_millis(20),
+            ..CliBridgeConfig::with_server(name)
+        }
+    }
+
+    fn wait_until(timeout: Duration, condition: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        while !condition() {
+            if Instant::now() > deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+
+    #[test]
+    fn test_bridge_observes_server_cancellation() {
+        let name = format!("test_bridge_cancel_{}", std::process::id());
+        let manager = spawn_task_server(&name);
+
+        let bridge = CliBridge::connect_with_config(fast_poll_config(&name)).unwrap();
+        let task_id = bridge.register_task("Long job", "test").unwrap();
+        assert!(manager.get(&task_id).is_some());
+        assert!(!bridge.is_cancelled());
+
+        manager.cancel(&task_id).unwrap();
+        assert!(wait_until(Duration::from_secs(3), || bridge.is_cancelled()));
+    }
+
+    #[test]
+    fn test_bridge_receives_commands() {
+        let name = format!("test_bridge_commands_{}", std::process::id());
+        let manager = spawn_task_server(&name);
+
+        let bridge = CliBridge::connect_with_config(fast_poll_config(&name)).unwrap();
+        let received = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&received);
+        bridge.on_command(move |cmd| sink.lock().push(cmd.clone()));
+        let task_id = bridge.register_task("Interactive job", "test").unwrap();
+
+        manager
+            .send_command(&task_id, "set-level", serde_json::json!({"level": 2}))
+            .unwrap();
+        manager
+            .send_command(&task_id, "flush", serde_json::Value::Null)
+            .unwrap();
+        assert!(wait_until(Duration::from_secs(3), || received.lock().len() == 2));
+
+        let received = received.lock().clone();
+        assert_eq!(received[0].command, "set-level");
+        assert_eq!(received[0].args["level"], 2);
+        assert_eq!(received[1].command, "flush");
+        assert!(received[0].id < received[1].id);
+        assert!(!bridge.is_cancelled());
+
+        manager.cancel(&task_id).unwrap();
+        assert!(wait_until(Duration::from_secs(3), || bridge.is_cancelled()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wrapped_command_killed_on_cancel() {
+        let name = format!("test_wrapped_cancel_{}", std::process::id());
+        let manager = spawn_task_server(&name);
+
+        // The background sleep holds stdout open, so `run` only returns
+        // quickly if the whole process tree is killed
+        let start = Instant::now();
+        let runner = thread::spawn({
+            let config = fast_poll_config(&name);
+            move || {
+                WrappedCommand::new("sh")
+                    .args(["-c", "sleep 30 & sleep 30"])
+                    .task("Sleeper", "test")
+                    .bridge_config(config)
+                    .run()
+            }
+        });
+
+        let filter = crate::task_manager::TaskFilter::new().task_type("test");
+        assert!(wait_until(Duration::from_secs(3), || !manager
+            .list(&filter)
+            .is_empty()));
+        let task_id = manager.list(&filter)[0].id.clone();
+        manager.cancel(&task_id).unwrap();
+
+        let output = runner.join().unwrap().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_ne!(output.exit_code, 0);
+        assert_eq!(
+            manager.get(&task_id).unwrap().status,
+            crate::task_manager::TaskStatus::Cancelled
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_only_cancellable_children_get_a_process_group() {
+        let own_group = unsafe { libc::getpgrp() };
+
+        let mut shared = ProcessTree::spawn(Command::new("sleep").arg("30"), false).unwrap();
+        assert_eq!(unsafe { libc::getpgid(shared.id() as libc::pid_t) }, own_group);
+        shared.kill().unwrap();
+        shared.wait().unwrap();
+
+        let mut isolated = ProcessTree::spawn(Command::new("sleep").arg("30"), true).unwrap();
+        let pid = isolated.id() as libc::pid_t;
+        assert_eq!(unsafe { libc::getpgid(pid) }, pid);
+        isolated.kill().unwrap();
+        isolated.wait().unwrap();
+    }
+
+    #[test]
+    fn test_spool_replays_events_after_reconnect() {
+        let name = format!("test_bridge_spool_{}", std::process::id());
+        let dir = tempfile::tempdir().unwrap();
+        let spool_path = dir.path().join("events.jsonl");
+        let config = CliBridgeConfig {
+            retry_delay: Duration::from_millis(10),
+            ..fast_poll_config(&name).spool(&spool_path)
+        };
+
+        // Nothing is listening yet, so every event lands in the spool
+        let bridge = CliBridge::connect_with_config(config).unwrap();
+        let task_id = bridge.register_task("Offline job", "test").unwrap();
+        bridge.set_progress(40, Some("halfway"));
+        bridge.log("info", "written while offline");
+        let spooled = std::fs::read_to_string(&spool_path).unwrap();
+        assert_eq!(spooled.lines().count(), 3);
+
+        let manager = spawn_task_server(&name);
+        assert_eq!(bridge.flush_spool(), 0);
+        assert!(!spool_path.exists());
+
+        let task = manager.get(&task_id).unwrap();
+        assert_eq!(task.progress, 40);
+        let (_, logs) = manager.wait_logs(&task_id, None, Duration::ZERO).unwrap();
+        assert!(logs
+            .iter()
+            .any(|e| e.data["message"] == "written while offline"));
+
+        // Once the server is back, events go straight through
+        bridge.log("info", "online");
+        assert!(!spool_path.exists());
+    }
+
+    #[test]
+    fn test_spool_drops_oldest_when_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let client = ApiClient::new(&format!("test_spool_full_{}", std::process::id()));
+        let spool = Spool::open(path.clone(), 2, Duration::from_secs(60));
+
+        for i in 0..3 {
+            spool.post(&client, &format!("/v1/event/{}", i), None);
+        }
+        let events = Spool::read(&path);
+        let paths: Vec<_> = events.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["/v1/event/1", "/v1/event/2"]);
+
+        // A reopened spool picks up what is left on disk
+        let reopened = Spool::open(path, 2, Duration::from_secs(60));
+        assert_eq!(reopened.state.lock().pending, 2);
+    }
+
+    #[test]
+    fn test_bridge_registers_sub_task() {
+        let name = format!("test_bridge_sub_task_{}", std::process::id());
+        let manager = spawn_task_server(&name);
+
+        let parent = CliBridge::connect_with_config(fast_poll_config(&name)).unwrap();
+        assert!(parent.child_env().is_empty());
+        let parent_id = parent.register_task("Build", "build").unwrap();
+        let env: std::collections::HashMap<_, _> = parent.child_env().into_iter().collect();
+        assert_eq!(env[ENV_SERVER_URL], name);
+        assert_eq!(env[ENV_TASK_ID], parent_id);
+
+        // What a nested tool gets from `CliBridgeConfig::from_env`
+        let config = CliBridgeConfig {
+            parent_pid: env[ENV_PARENT_PID].parse().ok(),
+            trace: env[ENV_TRACEPARENT].parse().ok(),
+            ..fast_poll_config(&name).parent_task(&parent_id)
+        };
+        let step = CliBridge::connect_with_config(config).unwrap();
+        let step_id = step.register_task("Compile", "step").unwrap();
+
+        let info = manager.get(&step_id).unwrap();
+        assert_eq!(info.parent_id.as_deref(), Some(parent_id.as_str()));
+        assert_eq!(info.metadata["parent_pid"], std::process::id());
+        // Both tasks belong to the parent's trace
+        let trace_id = parent.trace_context().trace_id();
+        assert_eq!(step.trace_context().trace_id(), trace_id);
+        assert_eq!(info.metadata["trace_id"], trace_id.as_str());
+
+        // Cancelling the parent reaches the sub-task's bridge
+        manager.cancel(&parent_id).unwrap();
+        assert!(wait_until(Duration::from_secs(3), || step.is_cancelled()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wrapped_command_sets_child_env() {
+        let name = format!("test_wrapped_env_{}", std::process::id());
+        let manager = spawn_task_server(&name);
+
+        let output = WrappedCommand::new("sh")
+            .args([
+                "-c",
+                "echo $IPCKIT_SERVER_URL $IPCKIT_TASK_ID $IPCKIT_PARENT_PID",
+            ])
+            .task("Env", "test")
+            .bridge_config(fast_poll_config(&name))
+            .run()
+            .unwrap();
+
+        let task = &manager.list(&crate::task_manager::TaskFilter::new().task_type("test"))[0];
+        let expected = format!("{} {} {}", name, task.id, std::process::id());
+        assert_eq!(output.stdout.trim(), expected);
+    }
 }
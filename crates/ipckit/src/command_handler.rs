@@ -0,0 +1,494 @@
+//! Command Handler - Transport glue for `#[ipc_handler]` services
+//!
+//! The `#[ipc_handler]` macro turns an impl block into a set of named
+//! commands taking JSON parameters. [`CommandHandler`] is the trait it
+//! implements, and this module provides the adapters that put such a
+//! handler on a transport without hand-written dispatch code:
+//!
+//! - [`Router::mount`](crate::api_server::Router::mount) exposes every command
+//!   as `POST {prefix}/{command}` on an [`ApiServer`](crate::api_server::ApiServer)
+//! - [`CommandService`] answers request messages on a
+//!   [`SocketServer`](crate::socket_server::SocketServer)
+//!
+//! Handlers whose commands take `&mut self` or an injected `&Context`
+//! implement [`StatefulHandler`] instead and are served through [`Stateful`].
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ipckit::{ApiServer, CommandService, SocketServer};
+//! use ipckit_macros::{command, ipc_handler};
+//!
+//! struct Math;
+//!
+//! #[ipc_handler(channel = "math")]
+//! impl Math {
+//!     #[command]
+//!     fn add(&self, a: i32, b: i32) -> i32 {
+//!         a + b
+//!     }
+//!
+//!     #[command]
+//!     async fn slow_add(&self, a: i32, b: i32) -> i32 {
+//!         a + b
+//!     }
+//! }
+//!
+//! // HTTP: POST /v1/math/add {"a": 1, "b": 2}
+//! let server = ApiServer::new(Default::default());
+//! server.router().mount("/v1/math", Math);
+//!
+//! // Socket: Message::request("add", json!({"a": 1, "b": 2}))
+//! SocketServer::with_defaults()?.run(CommandService::new(Math))?;
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::socket_server::{Connection, ConnectionHandler, Message, MessageType};
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use serde_json::{Map, Value};
+use std::future::Future;
+use std::sync::Arc;
+
+/// A set of named commands taking JSON parameters.
+///
+/// Implemented by `#[ipc_handler]`; see the [module docs](self).
+pub trait CommandHandler {
+    /// Get the channel name of this handler.
+    fn channel_name(&self) -> &'static str;
+
+    /// Get the list of available commands.
+    fn commands(&self) -> &'static [&'static str];
+
+    /// Handle a command by name.
+    ///
+    /// Unknown commands fail with [`IpcError::NotFound`].
+    fn handle_command(&self, command: &str, params: Map<String, Value>) -> Result<Value>;
+}
+
+impl<H: CommandHandler + ?Sized> CommandHandler for Arc<H> {
+    fn channel_name(&self) -> &'static str {
+        (**self).channel_name()
+    }
+
+    fn commands(&self) -> &'static [&'static str] {
+        (**self).commands()
+    }
+
+    fn handle_command(&self, command: &str, params: Map<String, Value>) -> Result<Value> {
+        (**self).handle_command(command, params)
+    }
+}
+
+/// Commands that take `&mut self` or an injected context.
+///
+/// `#[ipc_handler]` implements this instead of [`CommandHandler`] when a
+/// command takes `&mut self` or a `&Context` parameter. Serve such a handler
+/// through [`Stateful`], which owns the context and guards the handler with
+/// a read-write lock.
+pub trait StatefulHandler {
+    /// Value passed to commands taking a `&Context` parameter; `()` if none
+    /// does.
+    type Context;
+
+    /// Get the channel name of this handler.
+    fn channel_name(&self) -> &'static str;
+
+    /// Get the list of available commands.
+    fn commands(&self) -> &'static [&'static str];
+
+    /// Check whether a command takes `&mut self`.
+    fn is_mut_command(command: &str) -> bool;
+
+    /// Handle a command taking `&self`.
+    ///
+    /// Commands taking `&mut self` fail with [`IpcError::InvalidState`].
+    fn handle_command_ref(
+        &self,
+        ctx: &Self::Context,
+        command: &str,
+        params: Map<String, Value>,
+    ) -> Result<Value>;
+
+    /// Handle any command.
+    fn handle_command_mut(
+        &mut self,
+        ctx: &Self::Context,
+        command: &str,
+        params: Map<String, Value>,
+    ) -> Result<Value>;
+}
+
+/// A [`StatefulHandler`] with its context, usable as a [`CommandHandler`].
+///
+/// Commands taking `&self` run concurrently under a read lock; commands
+/// taking `&mut self` get the write lock.
+///
+/// ```rust,ignore
+/// struct Ctx {
+///     tasks: TaskManager,
+/// }
+///
+/// #[derive(Default)]
+/// struct Jobs {
+///     created: u64,
+/// }
+///
+/// #[ipc_handler(channel = "jobs")]
+/// impl Jobs {
+///     #[command]
+///     fn create(&mut self, ctx: &Ctx, name: String) -> String {
+///         self.created += 1;
+///         ctx.tasks.create(TaskBuilder::new(&name, "job")).id().to_string()
+///     }
+///
+///     #[command]
+///     fn created(&self) -> u64 {
+///         self.created
+///     }
+/// }
+///
+/// router.mount("/v1/jobs", Stateful::new(Jobs::default(), Ctx { tasks }));
+/// ```
+pub struct Stateful<H: StatefulHandler> {
+    handler: RwLock<H>,
+    context: H::Context,
+}
+
+impl<H: StatefulHandler> Stateful<H> {
+    /// Pair a handler with the context its commands receive.
+    pub fn new(handler: H, context: H::Context) -> Self {
+        Self {
+            handler: RwLock::new(handler),
+            context,
+        }
+    }
+
+    /// Get the context.
+    pub fn context(&self) -> &H::Context {
+        &self.context
+    }
+
+    /// Lock the handler for reading.
+    pub fn handler(&self) -> RwLockReadGuard<'_, H> {
+        self.handler.read()
+    }
+
+    /// Lock the handler for writing.
+    pub fn handler_mut(&self) -> RwLockWriteGuard<'_, H> {
+        self.handler.write()
+    }
+
+    /// Unwrap the handler and context.
+    pub fn into_inner(self) -> (H, H::Context) {
+        (self.handler.into_inner(), self.context)
+    }
+}
+
+impl<H: StatefulHandler> CommandHandler for Stateful<H> {
+    fn channel_name(&self) -> &'static str {
+        self.handler.read().channel_name()
+    }
+
+    fn commands(&self) -> &'static [&'static str] {
+        self.handler.read().commands()
+    }
+
+    fn handle_command(&self, command: &str, params: Map<String, Value>) -> Result<Value> {
+        if H::is_mut_command(command) {
+            self.handler
+                .write()
+                .handle_command_mut(&self.context, command, params)
+        } else {
+            self.handler
+                .read()
+                .handle_command_ref(&self.context, command, params)
+        }
+    }
+}
+
+/// Convert a JSON value into a command parameter map.
+///
+/// `null` is treated as "no parameters"; anything other than an object is
+/// rejected.
+pub fn command_params(value: Option<Value>) -> Result<Map<String, Value>> {
+    match value {
+        None | Some(Value::Null) => Ok(Map::new()),
+        Some(Value::Object(map)) => Ok(map),
+        Some(_) => Err(IpcError::Deserialization(
+            "Command parameters must be a JSON object".to_string(),
+        )),
+    }
+}
+
+/// Socket server handler dispatching request messages to a [`CommandHandler`].
+///
+/// A request's `method` names the command and its `params` object carries
+/// the arguments. Results come back as response messages and failures as
+/// error messages built with [`Message::from_error`]. Pings are answered;
+/// other message types are ignored.
+pub struct CommandService<H> {
+    handler: Arc<H>,
+}
+
+impl<H> Clone for CommandService<H> {
+    fn clone(&self) -> Self {
+        Self {
+            handler: Arc::clone(&self.handler),
+        }
+    }
+}
+
+impl<H: CommandHandler + Send + Sync + 'static> CommandService<H> {
+    /// Wrap a handler for use with a socket server.
+    pub fn new(handler: H) -> Self {
+        Self::from_arc(Arc::new(handler))
+    }
+
+    /// Wrap a handler that is shared with other transports.
+    pub fn from_arc(handler: Arc<H>) -> Self {
+        Self { handler }
+    }
+
+    /// Get the wrapped handler.
+    pub fn handler(&self) -> &Arc<H> {
+        &self.handler
+    }
+
+    /// Dispatch a single message, returning the reply to send, if any.
+    pub fn dispatch(&self, msg: Message) -> Option<Message> {
+        match msg.msg_type {
+            MessageType::Request => {
+                let command = msg.method().unwrap_or_default().to_string();
+                let result = command_params(msg.params().cloned())
+                    .and_then(|params| self.handler.handle_command(&command, params));
+                Some(match result {
+                    Ok(value) => Message::response(value),
+                    Err(e) => Message::from_error(&e),
+                })
+            }
+            MessageType::Ping => Some(Message::pong()),
+            _ => None,
+        }
+    }
+}
+
+impl<H: CommandHandler + Send + Sync + 'static> ConnectionHandler for CommandService<H> {
+    fn on_message(&self, _conn: &mut Connection, msg: Message) -> Result<Option<Message>> {
+        Ok(self.dispatch(msg))
+    }
+}
+
+/// Run a future to completion on the current thread.
+///
+/// This is how the synchronous `handle_command` generated for `async fn`
+/// commands drives them. With the `async` feature enabled the future runs
+/// inside a Tokio runtime (the current one if it is multi-threaded, a shared
+/// background one otherwise), so commands may use Tokio I/O and timers.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    #[cfg(feature = "async")]
+    {
+        use std::sync::OnceLock;
+        use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
+
+        static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+        match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                return tokio::task::block_in_place(|| handle.block_on(future));
+            }
+            // Blocking a current-thread runtime would deadlock on its own
+            // I/O driver, so fall through to the plain executor
+            Ok(_) => {}
+            Err(_) => {
+                let runtime = RUNTIME.get_or_init(|| {
+                    tokio::runtime::Builder::new_multi_thread()
+                        .worker_threads(1)
+                        .thread_name("ipckit-commands")
+                        .enable_all()
+                        .build()
+                        .expect("failed to start command runtime")
+                });
+                return runtime.block_on(future);
+            }
+        }
+    }
+
+    park_on(future)
+}
+
+/// Minimal executor that parks the current thread between polls.
+fn park_on<F: Future>(future: F) -> F::Output {
+    use std::pin::pin;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::Thread;
+
+    struct ThreadUnparker(Thread);
+
+    impl Wake for ThreadUnparker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadUnparker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::{Method, Request, Router};
+
+    struct Math;
+
+    impl CommandHandler for Math {
+        fn channel_name(&self) -> &'static str {
+            "math"
+        }
+
+        fn commands(&self) -> &'static [&'static str] {
+            &["add"]
+        }
+
+        fn handle_command(&self, command: &str, params: Map<String, Value>) -> Result<Value> {
+            match command {
+                "add" => {
+                    let a = params.get("a").and_then(Value::as_i64);
+                    let b = params.get("b").and_then(Value::as_i64);
+                    match (a, b) {
+                        (Some(a), Some(b)) => Ok(Value::from(block_on(async { a + b }))),
+                        _ => Err(IpcError::Deserialization("expected a and b".to_string())),
+                    }
+                }
+                _ => Err(IpcError::NotFound(format!("Unknown command: {}", command))),
+            }
+        }
+    }
+
+    fn post(router: &Router, path: &str, body: Value) -> (u16, Value) {
+        let mut req = Request::new(Method::POST, path);
+        req.body = Some(body);
+        let resp = router.handle(req);
+        let body = match resp.body {
+            crate::api_server::ResponseBody::Json(v) => v,
+            _ => Value::Null,
+        };
+        (resp.status, body)
+    }
+
+    #[test]
+    fn test_mount_on_router() {
+        let mut router = Router::new();
+        router.mount("/v1/math/", Math);
+
+        assert_eq!(
+            post(&router, "/v1/math/add", serde_json::json!({"a": 1, "b": 2})),
+            (200, Value::from(3))
+        );
+        assert_eq!(
+            post(&router, "/v1/math/add", serde_json::json!({"a": 1})).0,
+            400
+        );
+        assert_eq!(post(&router, "/v1/math/sub", Value::Null).0, 404);
+        assert_eq!(
+            post(&router, "/v1/math/add", serde_json::json!([1, 2])).0,
+            400
+        );
+
+        let resp = router.handle(Request::new(Method::GET, "/v1/math"));
+        assert_eq!(resp.status, 200);
+    }
+
+    #[test]
+    fn test_command_service_dispatch() {
+        let service = CommandService::new(Math);
+
+        let reply = service
+            .dispatch(Message::request("add", serde_json::json!({"a": 2, "b": 3})))
+            .unwrap();
+        assert_eq!(reply.result(), Some(&Value::from(5)));
+
+        let reply = service
+            .dispatch(Message::request("missing", Value::Null))
+            .unwrap();
+        assert_eq!(reply.error_code(), Some(crate::error::ErrorCode::NotFound));
+
+        assert_eq!(
+            service.dispatch(Message::ping()).unwrap().msg_type,
+            MessageType::Pong
+        );
+        assert!(service.dispatch(Message::text("hi")).is_none());
+    }
+
+    struct Counter(i64);
+
+    impl StatefulHandler for Counter {
+        type Context = i64;
+
+        fn channel_name(&self) -> &'static str {
+            "counter"
+        }
+
+        fn commands(&self) -> &'static [&'static str] {
+            &["get", "add"]
+        }
+
+        fn is_mut_command(command: &str) -> bool {
+            command == "add"
+        }
+
+        fn handle_command_ref(
+            &self,
+            _ctx: &i64,
+            command: &str,
+            _params: Map<String, Value>,
+        ) -> Result<Value> {
+            match command {
+                "get" => Ok(Value::from(self.0)),
+                _ => Err(IpcError::InvalidState(format!("{} needs &mut", command))),
+            }
+        }
+
+        fn handle_command_mut(
+            &mut self,
+            step: &i64,
+            command: &str,
+            params: Map<String, Value>,
+        ) -> Result<Value> {
+            if command == "add" {
+                self.0 += step;
+                return Ok(Value::from(self.0));
+            }
+            self.handle_command_ref(step, command, params)
+        }
+    }
+
+    #[test]
+    fn test_stateful_handler() {
+        let counter = Arc::new(Stateful::new(Counter(0), 5));
+        let mut router = Router::new();
+        router.mount("/v1/counter", Arc::clone(&counter));
+
+        assert_eq!(
+            post(&router, "/v1/counter/add", Value::Null),
+            (200, Value::from(5))
+        );
+        assert_eq!(
+            post(&router, "/v1/counter/get", Value::Null),
+            (200, Value::from(5))
+        );
+        assert_eq!(counter.handler().0, 5);
+        assert_eq!(*counter.context(), 5);
+    }
+}
@@ -0,0 +1,396 @@
+//! # Config File
+//!
+//! Typed `ipckit.toml` (or YAML/JSON) configuration shared by the CLI and
+//! servers. It names the endpoints a user talks to, so commands don't have to
+//! repeat `--type/--name`, and holds the settings an [`ApiServer`] starts with.
+//!
+//! ## Features
+//!
+//! - Named endpoints with their channel type, name, auth token and codec
+//! - A default endpoint and default codec
+//! - API server settings, turned into an [`ApiServerConfig`]
+//! - Overrides from `IPCKIT_*` environment variables
+//!
+//! TOML support requires the `manifest-toml` feature and YAML support the
+//! `config-yaml` feature; JSON is always available.
+//!
+//! ## Example
+//!
+//! ```toml
+//! [defaults]
+//! endpoint = "daemon"
+//! codec = "msgpack"
+//!
+//! [endpoints.daemon]
+//! type = "socket"
+//! name = "/tmp/my-daemon.sock"
+//! token = "s3cret"
+//!
+//! [endpoints.frames]
+//! type = "shm"
+//! name = "my_frames"
+//!
+//! [server]
+//! socket = "/tmp/my-daemon.sock"
+//! auth_token = "s3cret"
+//! max_body_size = 1048576
+//! health_routes = true
+//! ```
+//!
+//! ## Environment Overrides
+//!
+//! [`IpckitConfig::apply_env`] overrides file values with:
+//!
+//! | Variable | Setting |
+//! |----------|---------|
+//! | `IPCKIT_ENDPOINT` | `defaults.endpoint` |
+//! | `IPCKIT_CODEC` | `defaults.codec` |
+//! | `IPCKIT_SOCKET` | `server.socket` |
+//! | `IPCKIT_AUTH_TOKEN` | `server.auth_token` |
+//! | `IPCKIT_MAX_BODY_SIZE` | `server.max_body_size` |
+//!
+//! [`ApiServer`]: crate::ApiServer
+
+use crate::api_server::{ApiServerConfig, ContentFormat};
+use crate::error::{IpcError, Result};
+use crate::socket_server::SocketServerConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// File name looked up in the working directory when no path is given.
+pub const CONFIG_FILE_NAME: &str = "ipckit.toml";
+
+/// Channel types an endpoint can name.
+const ENDPOINT_TYPES: &[&str] = &["pipe", "shm", "socket", "file", "thread"];
+
+/// Typed contents of an ipckit configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IpckitConfig {
+    /// Defaults applied when a command doesn't say otherwise
+    pub defaults: ConfigDefaults,
+    /// Named endpoints
+    pub endpoints: BTreeMap<String, EndpointConfig>,
+    /// API server settings
+    pub server: ServerSettings,
+}
+
+/// Defaults applied when a command doesn't say otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigDefaults {
+    /// Endpoint used when none is named
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Body codec for API requests: `json` or `msgpack`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+}
+
+/// A named channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointConfig {
+    /// Channel type: `pipe`, `shm`, `socket`, `file` or `thread`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Channel name (socket path for sockets)
+    pub name: String,
+    /// Bearer token sent to an API server on this endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Body codec, overriding `defaults.codec`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+}
+
+/// API server settings; unset fields keep the [`ApiServerConfig`] defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    /// Socket path to listen on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket: Option<String>,
+    /// Bearer token every request must carry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+    /// Largest accepted request body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_body_size: Option<usize>,
+    /// Most headers accepted in one request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_headers: Option<usize>,
+    /// Largest accepted request line plus headers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_header_size: Option<usize>,
+    /// Enable CORS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_cors: Option<bool>,
+    /// CORS allowed origins
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cors_origins: Option<Vec<String>>,
+    /// Serve the connection admin routes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_routes: Option<bool>,
+    /// Serve `/healthz` and `/readyz`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health_routes: Option<bool>,
+}
+
+impl IpckitConfig {
+    /// Load a configuration file, picking the format from its extension
+    /// (`.toml`, `.yaml`/`.yml`, anything else is read as JSON).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml_str(&content),
+            Some("yaml" | "yml") => Self::from_yaml_str(&content),
+            _ => Self::from_json_str(&content),
+        }
+    }
+
+    /// Parse a JSON configuration.
+    pub fn from_json_str(s: &str) -> Result<Self> {
+        let config: Self =
+            serde_json::from_str(s).map_err(|e| IpcError::deserialization(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse a TOML configuration.
+    #[cfg(feature = "manifest-toml")]
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        let config: Self =
+            toml::from_str(s).map_err(|e| IpcError::deserialization(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse a TOML configuration.
+    #[cfg(not(feature = "manifest-toml"))]
+    pub fn from_toml_str(_s: &str) -> Result<Self> {
+        Err(IpcError::Other(
+            "TOML config files require the `manifest-toml` feature".to_string(),
+        ))
+    }
+
+    /// Parse a YAML configuration.
+    #[cfg(feature = "config-yaml")]
+    pub fn from_yaml_str(s: &str) -> Result<Self> {
+        let config: Self =
+            serde_yaml::from_str(s).map_err(|e| IpcError::deserialization(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse a YAML configuration.
+    #[cfg(not(feature = "config-yaml"))]
+    pub fn from_yaml_str(_s: &str) -> Result<Self> {
+        Err(IpcError::Other(
+            "YAML config files require the `config-yaml` feature".to_string(),
+        ))
+    }
+
+    /// Check the configuration for inconsistencies.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(IpcError::Other(format!("invalid config: {}", msg)));
+
+        for (name, endpoint) in &self.endpoints {
+            if !ENDPOINT_TYPES.contains(&endpoint.kind.as_str()) {
+                return invalid(format!(
+                    "endpoint '{}' has unknown type '{}'",
+                    name, endpoint.kind
+                ));
+            }
+            if endpoint.name.is_empty() {
+                return invalid(format!("endpoint '{}' has an empty name", name));
+            }
+            if let Some(codec) = &endpoint.codec {
+                parse_codec(codec)?;
+            }
+        }
+        if let Some(endpoint) = &self.defaults.endpoint {
+            if !self.endpoints.contains_key(endpoint) {
+                return invalid(format!("default endpoint '{}' is not defined", endpoint));
+            }
+        }
+        if let Some(codec) = &self.defaults.codec {
+            parse_codec(codec)?;
+        }
+        Ok(())
+    }
+
+    /// Override settings from `IPCKIT_*` environment variables.
+    pub fn apply_env(&mut self) -> Result<()> {
+        self.apply_env_from(|key| std::env::var(key).ok())
+    }
+
+    /// Override settings from variables looked up with `lookup`.
+    pub fn apply_env_from<F>(&mut self, lookup: F) -> Result<()>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(endpoint) = lookup("IPCKIT_ENDPOINT") {
+            self.defaults.endpoint = Some(endpoint);
+        }
+        if let Some(codec) = lookup("IPCKIT_CODEC") {
+            self.defaults.codec = Some(codec);
+        }
+        if let Some(socket) = lookup("IPCKIT_SOCKET") {
+            self.server.socket = Some(socket);
+        }
+        if let Some(token) = lookup("IPCKIT_AUTH_TOKEN") {
+            self.server.auth_token = Some(token);
+        }
+        if let Some(size) = lookup("IPCKIT_MAX_BODY_SIZE") {
+            let size = size.parse().map_err(|_| {
+                IpcError::Other(format!("invalid IPCKIT_MAX_BODY_SIZE: '{}'", size))
+            })?;
+            self.server.max_body_size = Some(size);
+        }
+        self.validate()
+    }
+
+    /// Look up the endpoint called `name`, or the default endpoint when
+    /// `name` is `None`.
+    ///
+    /// Returns `Ok(None)` when no name is given and there is no default, and
+    /// [`IpcError::NotFound`] for an unknown name.
+    pub fn endpoint(&self, name: Option<&str>) -> Result<Option<&EndpointConfig>> {
+        match name.or(self.defaults.endpoint.as_deref()) {
+            Some(name) => self
+                .endpoints
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| IpcError::NotFound(format!("endpoint '{}'", name))),
+            None => Ok(None),
+        }
+    }
+
+    /// Codec for requests to `endpoint`, falling back to `defaults.codec`
+    /// and then JSON.
+    pub fn codec(&self, endpoint: Option<&EndpointConfig>) -> Result<ContentFormat> {
+        match endpoint
+            .and_then(|e| e.codec.as_deref())
+            .or(self.defaults.codec.as_deref())
+        {
+            Some(codec) => parse_codec(codec),
+            None => Ok(ContentFormat::Json),
+        }
+    }
+
+    /// Build an [`ApiServerConfig`] from the `[server]` settings.
+    pub fn api_server_config(&self) -> ApiServerConfig {
+        let defaults = ApiServerConfig::default();
+        let server = &self.server;
+        ApiServerConfig {
+            socket_config: match &server.socket {
+                Some(socket) => SocketServerConfig::with_path(socket),
+                None => defaults.socket_config,
+            },
+            enable_cors: server.enable_cors.unwrap_or(defaults.enable_cors),
+            cors_origins: server.cors_origins.clone().unwrap_or(defaults.cors_origins),
+            max_body_size: server.max_body_size.unwrap_or(defaults.max_body_size),
+            max_headers: server.max_headers.unwrap_or(defaults.max_headers),
+            max_header_size: server.max_header_size.unwrap_or(defaults.max_header_size),
+            admin_routes: server.admin_routes.unwrap_or(defaults.admin_routes),
+            health_routes: server.health_routes.unwrap_or(defaults.health_routes),
+            auth_token: server.auth_token.clone(),
+            ..defaults
+        }
+    }
+}
+
+/// Parse a codec name (internal)
+fn parse_codec(codec: &str) -> Result<ContentFormat> {
+    match codec.to_ascii_lowercase().as_str() {
+        "json" => Ok(ContentFormat::Json),
+        "msgpack" | "messagepack" => Ok(ContentFormat::MsgPack),
+        _ => Err(IpcError::Other(format!(
+            "invalid config: unknown codec '{}'",
+            codec
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_endpoints_and_env() {
+        let mut config = IpckitConfig::from_json_str(
+            r#"{
+                "defaults": { "endpoint": "daemon", "codec": "msgpack" },
+                "endpoints": {
+                    "daemon": { "type": "socket", "name": "/tmp/d.sock", "token": "t" },
+                    "frames": { "type": "shm", "name": "frames", "codec": "json" }
+                },
+                "server": { "socket": "/tmp/d.sock", "health_routes": true }
+            }"#,
+        )
+        .unwrap();
+
+        let daemon = config.endpoint(None).unwrap().unwrap();
+        assert_eq!(daemon.name, "/tmp/d.sock");
+        assert_eq!(daemon.token.as_deref(), Some("t"));
+        assert_eq!(config.codec(Some(daemon)).unwrap(), ContentFormat::MsgPack);
+        let frames = config.endpoint(Some("frames")).unwrap().unwrap();
+        assert_eq!(config.codec(Some(frames)).unwrap(), ContentFormat::Json);
+        assert!(matches!(
+            config.endpoint(Some("missing")),
+            Err(IpcError::NotFound(_))
+        ));
+
+        let env: BTreeMap<&str, &str> = [
+            ("IPCKIT_AUTH_TOKEN", "secret"),
+            ("IPCKIT_MAX_BODY_SIZE", "1024"),
+            ("IPCKIT_ENDPOINT", "frames"),
+        ]
+        .into();
+        config
+            .apply_env_from(|key| env.get(key).map(|v| v.to_string()))
+            .unwrap();
+        assert_eq!(config.endpoint(None).unwrap().unwrap().kind, "shm");
+
+        let server = config.api_server_config();
+        assert_eq!(server.socket_config.path, "/tmp/d.sock");
+        assert_eq!(server.auth_token.as_deref(), Some("secret"));
+        assert_eq!(server.max_body_size, 1024);
+        assert!(server.health_routes);
+        assert!(!server.admin_routes);
+    }
+
+    #[test]
+    fn test_config_rejects_unknown_endpoint() {
+        let err = IpckitConfig::from_json_str(
+            r#"{ "defaults": { "endpoint": "nope" }, "endpoints": {} }"#,
+        );
+        assert!(err.is_err());
+        let err = IpckitConfig::from_json_str(
+            r#"{ "endpoints": { "a": { "type": "carrier-pigeon", "name": "x" } } }"#,
+        );
+        assert!(err.is_err());
+    }
+
+    #[cfg(feature = "manifest-toml")]
+    #[test]
+    fn test_config_toml() {
+        let config = IpckitConfig::from_toml_str(
+            r#"
+            [endpoints.daemon]
+            type = "socket"
+            name = "/tmp/d.sock"
+
+            [server]
+            max_headers = 32
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.endpoints["daemon"].kind, "socket");
+        assert_eq!(config.api_server_config().max_headers, 32);
+    }
+}
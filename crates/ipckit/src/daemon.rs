@@ -0,0 +1,234 @@
+//! Daemon - Single-instance locking for long-running servers
+//!
+//! [`SingleInstance`] answers "is another daemon already running?" without the
+//! usual races. It holds an OS file lock on `<runtime dir>/<name>.lock` for
+//! as long as it lives and writes the owner's pid into the file. The lock is
+//! released by the OS when the process exits, so a crashed daemon never
+//! leaves a lock behind; a pid left in the file by a dead process is simply
+//! overwritten.
+//!
+//! Holding the lock also makes it safe to remove a stale socket file before
+//! binding: no live daemon can be serving on it.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::daemon::SingleInstance;
+//! use ipckit::{SocketServer, SocketServerConfig};
+//!
+//! let instance = match SingleInstance::acquire("render-daemon") {
+//!     Ok(instance) => instance,
+//!     Err(e) => {
+//!         // Resource already exists: daemon 'render-daemon' is already running (pid 4242)
+//!         eprintln!("{}", e);
+//!         std::process::exit(1);
+//!     }
+//! };
+//!
+//! let config = SocketServerConfig::with_path("/tmp/render.sock");
+//! instance.cleanup_socket(&config.path)?;
+//! let server = SocketServer::new(config)?;
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+
+use crate::discovery::{process_alive, runtime_dir};
+use crate::error::{IpcError, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Exclusive ownership of a daemon name, held until dropped.
+#[derive(Debug)]
+pub struct SingleInstance {
+    name: String,
+    path: PathBuf,
+    file: File,
+}
+
+impl SingleInstance {
+    /// Become the only running instance of `name`.
+    ///
+    /// Fails with [`IpcError::AlreadyExists`] naming the owner's pid when
+    /// another live process holds it.
+    pub fn acquire(name: &str) -> Result<Self> {
+        Self::acquire_in(runtime_dir(), name)
+    }
+
+    /// Like [`acquire`](Self::acquire), keeping the lock file in `dir`.
+    pub fn acquire_in(dir: impl AsRef<Path>, name: &str) -> Result<Self> {
+        validate_name(name)?;
+        fs::create_dir_all(dir.as_ref())?;
+        let path = lock_path(dir.as_ref(), name);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(already_running(name, read_pid(&mut file)));
+            }
+            Err(TryLockError::Error(e)) if e.kind() == std::io::ErrorKind::Unsupported => {
+                // No advisory locks here (some network filesystems): trust the
+                // pid if its process is still alive
+                if let Some(pid) = read_pid(&mut file)
+                    .filter(|&pid| pid != std::process::id() && process_alive(pid))
+                {
+                    return Err(already_running(name, Some(pid)));
+                }
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        writeln!(file, "{}", std::process::id())?;
+        file.flush()?;
+
+        Ok(Self {
+            name: name.to_string(),
+            path,
+            file,
+        })
+    }
+
+    /// The pid of the running instance of `name`, if there is one.
+    pub fn running(name: &str) -> Option<u32> {
+        Self::running_in(runtime_dir(), name)
+    }
+
+    /// Like [`running`](Self::running), looking for the lock file in `dir`.
+    pub fn running_in(dir: impl AsRef<Path>, name: &str) -> Option<u32> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(lock_path(dir.as_ref(), name))
+            .ok()?;
+        match file.try_lock() {
+            Ok(()) => {
+                let _ = file.unlock();
+                None
+            }
+            Err(TryLockError::WouldBlock) => read_pid(&mut file),
+            Err(TryLockError::Error(_)) => read_pid(&mut file).filter(|&pid| process_alive(pid)),
+        }
+    }
+
+    /// The daemon name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Remove a socket file left behind by a previous instance.
+    ///
+    /// `path` is a socket server path in any form [`LocalSocketListener`]
+    /// accepts. Abstract sockets and Windows named pipes need no cleanup.
+    ///
+    /// [`LocalSocketListener`]: crate::local_socket::LocalSocketListener
+    pub fn cleanup_socket(&self, path: &str) -> Result<()> {
+        if path.starts_with('@') || path.starts_with(r"\\.\pipe\") || cfg!(windows) {
+            return Ok(());
+        }
+        // Same mapping as LocalSocketListener::bind
+        let path = if path.starts_with('/') {
+            PathBuf::from(path)
+        } else {
+            PathBuf::from(format!("/tmp/{}.sock", path))
+        };
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                tracing::debug!(daemon = %self.name, path = %path.display(), "removed stale socket");
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Drop for SingleInstance {
+    fn drop(&mut self) {
+        // The file is kept: deleting a lock file lets two processes end up
+        // holding locks on different inodes. Clearing the pid is enough.
+        let _ = self.file.set_len(0);
+    }
+}
+
+fn lock_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.lock", name))
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(IpcError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+fn already_running(name: &str, pid: Option<u32>) -> IpcError {
+    match pid {
+        Some(pid) => IpcError::AlreadyExists(format!(
+            "daemon '{}' is already running (pid {})",
+            name, pid
+        )),
+        None => IpcError::AlreadyExists(format!("daemon '{}' is already running", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_instance_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(SingleInstance::running_in(dir.path(), "svc"), None);
+
+        let first = SingleInstance::acquire_in(dir.path(), "svc").unwrap();
+        let pid = std::process::id();
+        assert_eq!(SingleInstance::running_in(dir.path(), "svc"), Some(pid));
+
+        match SingleInstance::acquire_in(dir.path(), "svc") {
+            Err(IpcError::AlreadyExists(msg)) => assert!(msg.contains(&pid.to_string())),
+            other => panic!("expected AlreadyExists, got {:?}", other),
+        }
+
+        drop(first);
+        assert_eq!(SingleInstance::running_in(dir.path(), "svc"), None);
+        SingleInstance::acquire_in(dir.path(), "svc").unwrap();
+        assert!(SingleInstance::acquire_in(dir.path(), "../svc").is_err());
+    }
+
+    #[test]
+    fn test_stale_lock_and_socket_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        // Left behind by a crashed daemon: a pid but no lock held
+        fs::write(lock_path(dir.path(), "svc"), "4194303\n").unwrap();
+        assert_eq!(SingleInstance::running_in(dir.path(), "svc"), None);
+
+        let instance = SingleInstance::acquire_in(dir.path(), "svc").unwrap();
+        let contents = fs::read_to_string(instance.path()).unwrap();
+        assert_eq!(contents.trim(), std::process::id().to_string());
+
+        let socket = dir.path().join("svc.sock");
+        fs::write(&socket, "").unwrap();
+        instance.cleanup_socket(socket.to_str().unwrap()).unwrap();
+        assert!(!socket.exists() || cfg!(windows));
+        instance.cleanup_socket(socket.to_str().unwrap()).unwrap();
+    }
+}
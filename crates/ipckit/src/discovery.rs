@@ -0,0 +1,290 @@
+//! Discovery - Find the channels other processes are serving
+//!
+//! Servers advertise their channels in a well-known registry directory, one
+//! small JSON file per channel, and clients (or `ipckit ls`) enumerate them.
+//! Entries are removed when their [`Registration`] is dropped; entries left
+//! behind by a crashed process are pruned on the next [`Discovery::list`]
+//! because their pid is no longer alive.
+//!
+//! The registry lives in `$IPCKIT_DISCOVERY_DIR` if set, otherwise in
+//! `$XDG_RUNTIME_DIR/ipckit` or `<temp dir>/ipckit` on systems without one.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::discovery::{ChannelEntry, Discovery};
+//!
+//! let discovery = Discovery::new();
+//! let _registration = discovery.register(
+//!     ChannelEntry::new("render_jobs", "socket").purpose("Render farm job queue"),
+//! )?;
+//!
+//! for entry in discovery.list()? {
+//!     println!("{} ({}) pid {}", entry.name, entry.kind, entry.pid);
+//! }
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+
+use crate::error::{IpcError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Environment variable overriding the registry directory.
+pub const ENV_DISCOVERY_DIR: &str = "IPCKIT_DISCOVERY_DIR";
+
+/// A channel advertised in the registry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelEntry {
+    /// Channel name, as passed to `connect`
+    pub name: String,
+    /// Channel type (`pipe`, `shm`, `socket`, `file`, ...)
+    pub kind: String,
+    /// Process serving the channel
+    pub pid: u32,
+    /// What the channel is for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    /// When the channel was registered
+    pub registered_at: SystemTime,
+}
+
+impl ChannelEntry {
+    /// Describe a channel served by the current process.
+    pub fn new(name: &str, kind: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            pid: std::process::id(),
+            purpose: None,
+            registered_at: SystemTime::now(),
+        }
+    }
+
+    /// Set what the channel is for.
+    pub fn purpose(mut self, purpose: &str) -> Self {
+        self.purpose = Some(purpose.to_string());
+        self
+    }
+}
+
+/// A channel registry in a shared directory.
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    dir: PathBuf,
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self::at(default_dir())
+    }
+}
+
+impl Discovery {
+    /// Open the default registry (see the module docs).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a registry in `dir`.
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The registry directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Advertise a channel until the returned [`Registration`] is dropped.
+    ///
+    /// Registering the same name and kind again from the same process
+    /// replaces the earlier entry.
+    pub fn register(&self, entry: ChannelEntry) -> Result<Registration> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(file_name(&entry));
+        let json = serde_json::to_vec_pretty(&entry)
+            .map_err(|e| IpcError::serialization(e.to_string()))?;
+
+        // Write then rename so listers never see a half-written entry
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, json)?;
+        fs::rename(&temp, &path)?;
+        Ok(Registration { path, entry })
+    }
+
+    /// All channels whose process is still alive, sorted by name.
+    ///
+    /// Entries of dead processes are removed from the registry.
+    pub fn list(&self) -> Result<Vec<ChannelEntry>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+        for file in dir.flatten() {
+            let path = file.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(entry) = fs::read(&path)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<ChannelEntry>(&bytes).ok())
+            else {
+                continue;
+            };
+            if process_alive(entry.pid) {
+                entries.push(entry);
+            } else {
+                let _ = fs::remove_file(&path);
+            }
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.pid.cmp(&b.pid)));
+        Ok(entries)
+    }
+
+    /// Find a live channel by name.
+    pub fn find(&self, name: &str) -> Result<Option<ChannelEntry>> {
+        Ok(self.list()?.into_iter().find(|e| e.name == name))
+    }
+}
+
+/// Keeps a channel advertised; removes the entry when dropped.
+#[derive(Debug)]
+pub struct Registration {
+    path: PathBuf,
+    entry: ChannelEntry,
+}
+
+impl Registration {
+    /// The advertised entry.
+    pub fn entry(&self) -> &ChannelEntry {
+        &self.entry
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn default_dir() -> PathBuf {
+    match std::env::var_os(ENV_DISCOVERY_DIR) {
+        Some(dir) => PathBuf::from(dir),
+        None => runtime_dir(),
+    }
+}
+
+/// Per-user directory for ipckit's runtime files (internal).
+pub(crate) fn runtime_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("ipckit"),
+        None => std::env::temp_dir().join("ipckit"),
+    }
+}
+
+/// `{pid}-{kind}-{name}.json`, with path separators and other unsafe
+/// characters in the name replaced.
+fn file_name(entry: &ChannelEntry) -> String {
+    let safe = |s: &str| -> String {
+        s.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    format!(
+        "{}-{}-{}.json",
+        entry.pid,
+        safe(&entry.kind),
+        safe(&entry.name)
+    )
+}
+
+/// Whether a process with this pid exists (internal).
+#[cfg(unix)]
+pub(crate) fn process_alive(pid: u32) -> bool {
+    // Signal 0 only checks whether the process exists; EPERM means it does
+    // but belongs to another user
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether a process with this pid is still running (internal).
+#[cfg(windows)]
+pub(crate) fn process_alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    const STILL_ACTIVE: u32 = 259;
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let mut code = 0u32;
+        let ok = GetExitCodeProcess(handle, &mut code) != 0;
+        CloseHandle(handle);
+        ok && code == STILL_ACTIVE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_list_and_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let discovery = Discovery::at(dir.path());
+        assert!(discovery.list().unwrap().is_empty());
+
+        let jobs = discovery
+            .register(ChannelEntry::new("jobs/render", "socket").purpose("Render queue"))
+            .unwrap();
+        let _frames = discovery
+            .register(ChannelEntry::new("frames", "shm"))
+            .unwrap();
+
+        let names: Vec<_> = discovery
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, ["frames", "jobs/render"]);
+        let found = discovery.find("jobs/render").unwrap().unwrap();
+        assert_eq!(found.pid, std::process::id());
+        assert_eq!(found.purpose.as_deref(), Some("Render queue"));
+        assert_eq!(jobs.entry(), &found);
+
+        drop(jobs);
+        assert!(discovery.find("jobs/render").unwrap().is_none());
+        assert_eq!(discovery.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_dead_entries_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let discovery = Discovery::at(dir.path());
+
+        // A pid far above any real pid_max stands in for a crashed server
+        let mut stale = ChannelEntry::new("ghost", "pipe");
+        stale.pid = i32::MAX as u32 - 1;
+        let path = dir.path().join(file_name(&stale));
+        fs::write(&path, serde_json::to_vec(&stale).unwrap()).unwrap();
+
+        assert!(discovery.list().unwrap().is_empty());
+        assert!(!path.exists());
+    }
+}
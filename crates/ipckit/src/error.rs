@@ -1,5 +1,12 @@
 //! Error types for ipckit
+//!
+//! Every [`IpcError`] maps to a stable [`ErrorCode`] with a numeric and a
+//! string form, and serializes as `{"code", "kind", "message", "retryable"}`
+//! so clients in other languages can branch on the code rather than the
+//! English message.
 
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
 use std::io;
 use thiserror::Error;
 
@@ -61,11 +68,117 @@ pub enum IpcError {
     #[error("Operation would block")]
     WouldBlock,
 
+    /// The peer lacks a required capability or speaks an unsupported protocol
+    #[error("Incompatible peer: {0}")]
+    Incompatible(String),
+
     /// Other error
     #[error("{0}")]
     Other(String),
 }
 
+/// Stable, language-neutral identifier for an [`IpcError`] variant.
+///
+/// The numeric values are part of the wire format and never change; new
+/// codes are only ever appended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(i32)]
+pub enum ErrorCode {
+    /// Uncategorized error
+    Other = 1000,
+    /// I/O error from the underlying system
+    Io = 1001,
+    /// The pipe or channel is closed
+    Closed = 1002,
+    /// The pipe or channel name is invalid
+    InvalidName = 1003,
+    /// The resource already exists
+    AlreadyExists = 1004,
+    /// The resource was not found
+    NotFound = 1005,
+    /// Permission denied
+    PermissionDenied = 1006,
+    /// The operation timed out
+    Timeout = 1007,
+    /// A buffer or message size limit was exceeded
+    BufferTooSmall = 1008,
+    /// Serialization failed
+    Serialization = 1009,
+    /// Deserialization failed
+    Deserialization = 1010,
+    /// Platform-specific error
+    Platform = 1011,
+    /// The operation is not valid in the current state
+    InvalidState = 1012,
+    /// A non-blocking operation would block
+    WouldBlock = 1013,
+    /// The peer lacks a required capability or protocol version
+    Incompatible = 1014,
+}
+
+impl ErrorCode {
+    const ALL: [ErrorCode; 15] = [
+        Self::Other,
+        Self::Io,
+        Self::Closed,
+        Self::InvalidName,
+        Self::AlreadyExists,
+        Self::NotFound,
+        Self::PermissionDenied,
+        Self::Timeout,
+        Self::BufferTooSmall,
+        Self::Serialization,
+        Self::Deserialization,
+        Self::Platform,
+        Self::InvalidState,
+        Self::WouldBlock,
+        Self::Incompatible,
+    ];
+
+    /// Get the numeric code.
+    pub fn as_i32(self) -> i32 {
+        self as i32
+    }
+
+    /// Get the string code (e.g., `"not_found"`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Other => "other",
+            Self::Io => "io",
+            Self::Closed => "closed",
+            Self::InvalidName => "invalid_name",
+            Self::AlreadyExists => "already_exists",
+            Self::NotFound => "not_found",
+            Self::PermissionDenied => "permission_denied",
+            Self::Timeout => "timeout",
+            Self::BufferTooSmall => "buffer_too_small",
+            Self::Serialization => "serialization",
+            Self::Deserialization => "deserialization",
+            Self::Platform => "platform",
+            Self::InvalidState => "invalid_state",
+            Self::WouldBlock => "would_block",
+            Self::Incompatible => "incompatible",
+        }
+    }
+
+    /// Look up a code by its numeric value.
+    pub fn from_i32(code: i32) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_i32() == code)
+    }
+
+    /// Look up a code by its string value.
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == code)
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl IpcError {
     /// Create a new I/O error
     pub fn io(err: io::Error) -> Self {
@@ -93,6 +206,89 @@ impl IpcError {
         matches!(self, Self::Timeout)
             || matches!(self, Self::Io(e) if e.kind() == io::ErrorKind::TimedOut)
     }
+
+    /// Get the stable error code for this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Io(_) => ErrorCode::Io,
+            Self::Closed => ErrorCode::Closed,
+            Self::InvalidName(_) => ErrorCode::InvalidName,
+            Self::AlreadyExists(_) => ErrorCode::AlreadyExists,
+            Self::NotFound(_) => ErrorCode::NotFound,
+            Self::PermissionDenied(_) => ErrorCode::PermissionDenied,
+            Self::Timeout => ErrorCode::Timeout,
+            Self::BufferTooSmall { .. } => ErrorCode::BufferTooSmall,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::Deserialization(_) => ErrorCode::Deserialization,
+            Self::Platform(_) => ErrorCode::Platform,
+            Self::InvalidState(_) => ErrorCode::InvalidState,
+            Self::WouldBlock => ErrorCode::WouldBlock,
+            Self::Incompatible(_) => ErrorCode::Incompatible,
+            Self::Other(_) => ErrorCode::Other,
+        }
+    }
+
+    /// Check whether the failed operation may succeed if retried, possibly
+    /// after reconnecting.
+    ///
+    /// This covers dropped or refused connections, timeouts and would-block
+    /// conditions; errors in the request itself (bad names, missing
+    /// resources, malformed data) are never retryable.
+    pub fn retryable(&self) -> bool {
+        match self {
+            Self::Closed | Self::Timeout | Self::WouldBlock => true,
+            Self::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::NotConnected
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted
+                    | io::ErrorKind::WouldBlock
+                    | io::ErrorKind::UnexpectedEof
+                    // The server's socket disappears while it restarts
+                    | io::ErrorKind::NotFound
+            ),
+            _ => false,
+        }
+    }
+
+    /// Get the machine-readable representation used on the wire.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+impl Serialize for IpcError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let code = self.code();
+        let mut state = serializer.serialize_struct("IpcError", 4)?;
+        state.serialize_field("code", &code.as_i32())?;
+        state.serialize_field("kind", code.as_str())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("retryable", &self.retryable())?;
+        state.end()
+    }
+}
+
+impl From<IpcError> for io::Error {
+    fn from(err: IpcError) -> io::Error {
+        let kind = match err {
+            IpcError::Io(e) => return e,
+            IpcError::Closed => io::ErrorKind::BrokenPipe,
+            IpcError::NotFound(_) => io::ErrorKind::NotFound,
+            IpcError::AlreadyExists(_) => io::ErrorKind::AlreadyExists,
+            IpcError::PermissionDenied(_) => io::ErrorKind::PermissionDenied,
+            IpcError::Timeout => io::ErrorKind::TimedOut,
+            IpcError::WouldBlock => io::ErrorKind::WouldBlock,
+            IpcError::InvalidName(_) => io::ErrorKind::InvalidInput,
+            IpcError::Serialization(_) | IpcError::Deserialization(_) => io::ErrorKind::InvalidData,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
 }
 
 #[cfg(feature = "python-bindings")]
@@ -115,7 +311,45 @@ impl From<IpcError> for pyo3::PyErr {
             IpcError::Platform(s) => PyOSError::new_err(s),
             IpcError::InvalidState(s) => PyRuntimeError::new_err(s),
             IpcError::WouldBlock => PyBlockingIOError::new_err("Operation would block"),
+            IpcError::Incompatible(s) => PyConnectionError::new_err(s),
             IpcError::Other(s) => PyRuntimeError::new_err(s),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let err = IpcError::NotFound("task-1".to_string());
+        assert_eq!(err.code(), ErrorCode::NotFound);
+        assert_eq!(
+            err.to_json(),
+            serde_json::json!({
+                "code": 1005,
+                "kind": "not_found",
+                "message": "Resource not found: task-1",
+                "retryable": false,
+            })
+        );
+
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_i32(code.as_i32()), Some(code));
+            assert_eq!(ErrorCode::parse(code.as_str()), Some(code));
+            assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+        }
+        assert_eq!(ErrorCode::from_i32(-1), None);
+    }
+
+    #[test]
+    fn test_retryable() {
+        assert!(IpcError::Timeout.retryable());
+        assert!(IpcError::Closed.retryable());
+        assert!(IpcError::Io(io::Error::from(io::ErrorKind::ConnectionRefused)).retryable());
+        assert!(!IpcError::Io(io::Error::from(io::ErrorKind::InvalidData)).retryable());
+        assert!(!IpcError::InvalidName("bad".to_string()).retryable());
+        assert!(!IpcError::NotFound("task-1".to_string()).retryable());
+    }
+}
@@ -0,0 +1,491 @@
+//! Event Bridge - EventBus subscriptions for socket clients
+//!
+//! Serves [`EventBus`] subscriptions to [`SocketServer`] connections, so a
+//! dashboard or another process can follow task events over the socket.
+//! What each connection may receive is decided by an [`EventAcl`]: the
+//! identity a client proves during the capability handshake (see
+//! [`Handshake::identity`]) maps to the event types and resources it is
+//! allowed to see. An identity is only proven against the server's
+//! [`Handshake::credential`]s; a client claiming one without them counts as
+//! anonymous. A client subscribing to `"*"` still only receives the
+//! events its identity is granted, and a connection without any grant is
+//! refused with [`IpcError::PermissionDenied`].
+//!
+//! A client subscribes with an [`EVENTS_SUBSCRIBE_METHOD`] request whose
+//! optional params narrow the stream:
+//!
+//! ```json
+//! {"event_types": ["task.*"], "resource_ids": ["task-1"]}
+//! ```
+//!
+//! Every matching event is then pushed as a JSON message whose payload is
+//! the serialized [`Event`], until the client sends
+//! [`EVENTS_UNSUBSCRIBE_METHOD`] or disconnects. Subscribing again replaces
+//! the previous subscription.
+//!
+//! A reconnecting client catches up with an [`EVENTS_REPLAY_METHOD`]
+//! request, whose params also take the ID of the last event it saw as
+//! `"after"`. It answers with the bus's epoch and the retained events the
+//! client may see, `{"epoch": 1234, "events": [...]}`; event IDs are only
+//! comparable within one epoch (see [`EventBus::epoch`]), which
+//! [`EVENTS_EPOCH_METHOD`] returns on its own.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::{EventAcl, EventBridge, EventBus, EventFilter, Handshake, SocketServer,
+//!     SocketServerConfig};
+//!
+//! let bus = EventBus::default();
+//! let config = SocketServerConfig {
+//!     handshake: Handshake::new()
+//!         .require_handshake(true)
+//!         .credential("alice", "alice-secret"),
+//!     ..SocketServerConfig::with_path("/tmp/events.sock")
+//! };
+//! let server = SocketServer::new(config)?;
+//!
+//! // alice only sees her own task's events
+//! let acl = EventAcl::new().allow("alice", EventFilter::new().event_type("task.*").resource("task-1"));
+//! let bridge = EventBridge::new(&bus, server.broadcaster(), acl);
+//! let _server = server.spawn(bridge);
+//!
+//! // Client:
+//! // SocketClient::connect_with_handshake(path, &Handshake::new().identity("alice", "alice-secret"))
+//! // then request `events.subscribe` and `recv()` events
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+//!
+//! [`SocketServer`]: crate::socket_server::SocketServer
+//! [`Handshake::identity`]: crate::socket_server::Handshake::identity
+//! [`Handshake::credential`]: crate::socket_server::Handshake::credential
+
+use crate::error::{IpcError, Result};
+use crate::event_stream::{Event, EventBus, EventFilter, EventId, EventSubscriber};
+use crate::socket_server::{Broadcaster, Connection, ConnectionHandler, ConnectionId, Message};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Method of the request that subscribes a connection to bus events.
+pub const EVENTS_SUBSCRIBE_METHOD: &str = "events.subscribe";
+
+/// Method of the request that ends a connection's subscription.
+pub const EVENTS_UNSUBSCRIBE_METHOD: &str = "events.unsubscribe";
+
+/// Method of the request that returns retained events after an event ID.
+pub const EVENTS_REPLAY_METHOD: &str = "events.replay";
+
+/// Method of the request that returns the epoch of the bus's event IDs.
+pub const EVENTS_EPOCH_METHOD: &str = "events.epoch";
+
+/// Which events each client identity may receive.
+///
+/// Grants are [`EventFilter`]s: an event is delivered if any grant of the
+/// connection's identity matches it. Identities without grants, and
+/// connections that did not identify themselves unless
+/// [`allow_anonymous`](Self::allow_anonymous) is used, receive nothing.
+#[derive(Debug, Clone, Default)]
+pub struct EventAcl {
+    grants: HashMap<String, Vec<EventFilter>>,
+    anonymous: Vec<EventFilter>,
+}
+
+impl EventAcl {
+    /// Create an ACL that denies everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let `identity` receive the events matching `filter`.
+    pub fn allow(mut self, identity: &str, filter: EventFilter) -> Self {
+        self.grants
+            .entry(identity.to_string())
+            .or_default()
+            .push(filter);
+        self
+    }
+
+    /// Let connections without an identity receive the events matching
+    /// `filter`.
+    pub fn allow_anonymous(mut self, filter: EventFilter) -> Self {
+        self.anonymous.push(filter);
+        self
+    }
+
+    /// Get the grants of `identity`, or of anonymous connections if `None`.
+    pub fn grants(&self, identity: Option<&str>) -> &[EventFilter] {
+        match identity {
+            Some(identity) => self.grants.get(identity).map(Vec::as_slice).unwrap_or(&[]),
+            None => &self.anonymous,
+        }
+    }
+
+    /// Check whether `identity` may receive `event`.
+    pub fn allows(&self, identity: Option<&str>, event: &Event) -> bool {
+        self.grants(identity)
+            .iter()
+            .any(|grant| grant.matches(event))
+    }
+}
+
+/// Optional params of an [`EVENTS_SUBSCRIBE_METHOD`] or
+/// [`EVENTS_REPLAY_METHOD`] request.
+#[derive(Debug, Default, Deserialize)]
+struct SubscribeParams {
+    #[serde(default)]
+    event_types: Option<Vec<String>>,
+    #[serde(default)]
+    resource_ids: Option<Vec<String>>,
+    /// Replay only: the last event the client saw
+    #[serde(default)]
+    after: Option<EventId>,
+}
+
+impl SubscribeParams {
+    fn parse(msg: &Message) -> Result<Self> {
+        match msg.params() {
+            Some(params) if !params.is_null() => serde_json::from_value(params.clone())
+                .map_err(|e| IpcError::deserialization(e.to_string())),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn filter(&self) -> EventFilter {
+        EventFilter {
+            event_types: self.event_types.clone(),
+            resource_ids: self.resource_ids.clone(),
+            ..EventFilter::default()
+        }
+    }
+}
+
+struct BridgeInner {
+    bus: EventBus,
+    broadcaster: Broadcaster,
+    acl: EventAcl,
+    subscriptions: Mutex<HashMap<ConnectionId, Arc<EventSubscriber>>>,
+}
+
+/// Forwards [`EventBus`] events to subscribed socket connections, filtered
+/// by an [`EventAcl`].
+///
+/// Use it as the server's handler, or call [`handle`](Self::handle) and
+/// [`disconnect`](Self::disconnect) from your own [`ConnectionHandler`].
+/// Cheap to clone.
+#[derive(Clone)]
+pub struct EventBridge {
+    inner: Arc<BridgeInner>,
+}
+
+impl EventBridge {
+    /// Create a bridge pushing events from `bus` through `broadcaster`.
+    pub fn new(bus: &EventBus, broadcaster: Broadcaster, acl: EventAcl) -> Self {
+        Self {
+            inner: Arc::new(BridgeInner {
+                bus: bus.clone(),
+                broadcaster,
+                acl,
+                subscriptions: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Get the ACL.
+    pub fn acl(&self) -> &EventAcl {
+        &self.inner.acl
+    }
+
+    /// Get the number of subscribed connections.
+    pub fn subscriptions(&self) -> usize {
+        self.inner.subscriptions.lock().len()
+    }
+
+    /// Answer an events request.
+    ///
+    /// Returns `None` if `msg` is not one of the `events.*` requests, so the
+    /// caller can handle it.
+    pub fn handle(&self, conn: &Connection, msg: &Message) -> Option<Result<Message>> {
+        match msg.method()? {
+            EVENTS_SUBSCRIBE_METHOD => Some(self.subscribe(conn, msg)),
+            EVENTS_REPLAY_METHOD => Some(self.replay(conn, msg)),
+            EVENTS_EPOCH_METHOD => Some(Ok(Message::response(
+                serde_json::json!({ "epoch": self.inner.bus.epoch() }),
+            ))),
+            EVENTS_UNSUBSCRIBE_METHOD => {
+                let unsubscribed = self.disconnect(conn.id());
+                Some(Ok(Message::response(
+                    serde_json::json!({ "unsubscribed": unsubscribed }),
+                )))
+            }
+            _ => None,
+        }
+    }
+
+    /// End a connection's subscription.
+    ///
+    /// Returns `false` if it was not subscribed.
+    pub fn disconnect(&self, conn_id: ConnectionId) -> bool {
+        let subscriber = self.inner.subscriptions.lock().remove(&conn_id);
+        self.inner.broadcaster.unsubscribe(conn_id, &topic(conn_id));
+        match subscriber {
+            Some(subscriber) => {
+                subscriber.unsubscribe();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the identity of a connection allowed to receive events.
+    fn identity(&self, conn: &Connection) -> Result<Option<String>> {
+        let identity = conn.handshake_info().and_then(|info| info.identity.clone());
+        if self.inner.acl.grants(identity.as_deref()).is_empty() {
+            return Err(IpcError::PermissionDenied(match identity {
+                Some(identity) => format!("identity '{}' may not receive events", identity),
+                None => "anonymous connections may not receive events".to_string(),
+            }));
+        }
+        Ok(identity)
+    }
+
+    fn replay(&self, conn: &Connection, msg: &Message) -> Result<Message> {
+        let identity = self.identity(conn)?;
+        let params = SubscribeParams::parse(msg)?;
+        let events: Vec<Event> = self
+            .inner
+            .bus
+            .replay_since(params.after, &params.filter())?
+            .into_iter()
+            .filter(|event| self.inner.acl.allows(identity.as_deref(), event))
+            .collect();
+        Ok(Message::response(serde_json::json!({
+            "epoch": self.inner.bus.epoch(),
+            "events": events,
+        })))
+    }
+
+    fn subscribe(&self, conn: &Connection, msg: &Message) -> Result<Message> {
+        let identity = self.identity(conn)?;
+        let filter = SubscribeParams::parse(msg)?.filter();
+
+        let conn_id = conn.id();
+        self.disconnect(conn_id);
+        let topic = topic(conn_id);
+        self.inner.broadcaster.subscribe(conn_id, &topic)?;
+        let subscriber = Arc::new(self.inner.bus.subscribe(filter));
+        self.inner
+            .subscriptions
+            .lock()
+            .insert(conn_id, Arc::clone(&subscriber));
+
+        let bridge = self.clone();
+        std::thread::spawn(move || {
+            while let Some(event) = subscriber.recv() {
+                if !bridge.inner.acl.allows(identity.as_deref(), &event) {
+                    continue;
+                }
+                let payload = match serde_json::to_value(&event) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        tracing::debug!("Not forwarding event {}: {}", event.id, e);
+                        continue;
+                    }
+                };
+                if bridge
+                    .inner
+                    .broadcaster
+                    .broadcast(&topic, &Message::json(payload))
+                    == 0
+                {
+                    break;
+                }
+            }
+            // Only clean up if the subscription was not replaced meanwhile
+            let mut subscriptions = bridge.inner.subscriptions.lock();
+            if subscriptions
+                .get(&conn_id)
+                .is_some_and(|current| Arc::ptr_eq(current, &subscriber))
+            {
+                subscriptions.remove(&conn_id);
+            }
+        });
+
+        Ok(Message::response(serde_json::json!({
+            "subscribed": true,
+            "epoch": self.inner.bus.epoch(),
+        })))
+    }
+}
+
+impl ConnectionHandler for EventBridge {
+    fn on_message(&self, conn: &mut Connection, msg: Message) -> Result<Option<Message>> {
+        match self.handle(conn, &msg) {
+            Some(result) => result.map(Some),
+            None => Err(IpcError::NotFound(format!(
+                "unknown method: {}",
+                msg.method().unwrap_or("<none>")
+            ))),
+        }
+    }
+
+    fn on_disconnect(&self, conn_id: ConnectionId) {
+        self.disconnect(conn_id);
+    }
+}
+
+/// Broadcast topic carrying a connection's events
+fn topic(conn_id: ConnectionId) -> String {
+    format!("events/{}", conn_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket_server::{Handshake, SocketClient, SocketServer, SocketServerConfig};
+    use std::time::Duration;
+
+    fn recv_event(client: &mut SocketClient) -> Event {
+        serde_json::from_value(client.recv().unwrap().payload).unwrap()
+    }
+
+    #[test]
+    fn test_acl_grants() {
+        let acl = EventAcl::new()
+            .allow("alice", EventFilter::new().resource("task-a"))
+            .allow("alice", EventFilter::new().event_type("system.*"))
+            .allow_anonymous(EventFilter::new().event_type("health"));
+
+        let own = Event::with_resource("task.progress", "task-a", serde_json::json!({}));
+        let other = Event::with_resource("task.progress", "task-b", serde_json::json!({}));
+        let system = Event::new("system.shutdown", serde_json::json!({}));
+        assert!(acl.allows(Some("alice"), &own));
+        assert!(acl.allows(Some("alice"), &system));
+        assert!(!acl.allows(Some("alice"), &other));
+        assert!(!acl.allows(Some("mallory"), &own));
+        assert!(!acl.allows(None, &own));
+        assert!(acl.allows(None, &Event::new("health", serde_json::json!({}))));
+    }
+
+    #[test]
+    fn test_bridge_refuses_unproven_identity() {
+        let socket_name = format!("test_event_bridge_unproven_{}", std::process::id());
+        let bus = EventBus::default();
+        // No credentials are registered, so nobody can prove being alice
+        let server = SocketServer::new(SocketServerConfig::with_path(&socket_name)).unwrap();
+        let acl = EventAcl::new()
+            .allow("alice", EventFilter::new())
+            .allow_anonymous(EventFilter::new().event_type("health"));
+        let bridge = EventBridge::new(&bus, server.broadcaster(), acl);
+        let _server = server.spawn(bridge.clone());
+        std::thread::sleep(Duration::from_millis(100));
+
+        let claimed = Handshake::new().identity("alice", "anything");
+        let mut client = SocketClient::connect_with_handshake(&socket_name, &claimed).unwrap();
+        client
+            .request(
+                EVENTS_SUBSCRIBE_METHOD,
+                serde_json::json!({"event_types": ["*"]}),
+            )
+            .unwrap();
+
+        // The claim is ignored: the connection only gets anonymous grants
+        bus.publish(Event::new(
+            "task.progress",
+            serde_json::json!({"secret": 1}),
+        ));
+        bus.publish(Event::new("health", serde_json::json!({"ok": true})));
+        let event = recv_event(&mut client);
+        assert_eq!(event.event_type, "health");
+    }
+
+    #[test]
+    fn test_bridge_filters_by_identity() {
+        let socket_name = format!("test_event_bridge_{}", std::process::id());
+        let bus = EventBus::default();
+        let config = SocketServerConfig {
+            handshake: Handshake::new()
+                .credential("alice", "a-secret")
+                .credential("bob", "b-secret"),
+            ..SocketServerConfig::with_path(&socket_name)
+        };
+        let server = SocketServer::new(config).unwrap();
+        let acl = EventAcl::new()
+            .allow("alice", EventFilter::new().resource("task-a"))
+            .allow("bob", EventFilter::new().resource("task-b"));
+        let bridge = EventBridge::new(&bus, server.broadcaster(), acl);
+        let _server = server.spawn(bridge.clone());
+        std::thread::sleep(Duration::from_millis(100));
+
+        // A wrong token is rejected during the handshake
+        let forged = Handshake::new().identity("alice", "guess");
+        assert!(matches!(
+            SocketClient::connect_with_handshake(&socket_name, &forged),
+            Err(IpcError::PermissionDenied(_))
+        ));
+
+        // Anonymous connections have no grants
+        let mut anonymous = SocketClient::connect(&socket_name).unwrap();
+        assert!(anonymous
+            .request(EVENTS_SUBSCRIBE_METHOD, serde_json::json!({}))
+            .is_err());
+
+        let alice = Handshake::new().identity("alice", "a-secret");
+        let mut client = SocketClient::connect_with_handshake(&socket_name, &alice).unwrap();
+        let reply = client
+            .request(
+                EVENTS_SUBSCRIBE_METHOD,
+                serde_json::json!({"event_types": ["*"]}),
+            )
+            .unwrap();
+        assert_eq!(reply["subscribed"], true);
+        assert_eq!(bridge.subscriptions(), 1);
+
+        // Subscribing to "*" still only delivers alice's events
+        bus.publish(Event::with_resource(
+            "task.progress",
+            "task-b",
+            serde_json::json!({"secret": 1}),
+        ));
+        bus.publish(Event::with_resource(
+            "task.progress",
+            "task-a",
+            serde_json::json!({"n": 2}),
+        ));
+        let event = recv_event(&mut client);
+        assert_eq!(event.resource_id.as_deref(), Some("task-a"));
+        assert_eq!(event.data["n"], 2);
+
+        let reply = client
+            .request(EVENTS_UNSUBSCRIBE_METHOD, serde_json::Value::Null)
+            .unwrap();
+        assert_eq!(reply["unsubscribed"], true);
+        assert_eq!(bridge.subscriptions(), 0);
+
+        // Replay is filtered by the same grants
+        let reply = client
+            .request(
+                EVENTS_REPLAY_METHOD,
+                serde_json::json!({"event_types": ["*"], "after": null}),
+            )
+            .unwrap();
+        assert_eq!(reply["epoch"], bus.epoch());
+        let events: Vec<Event> = serde_json::from_value(reply["events"].clone()).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, event.id);
+
+        let reply = client
+            .request(EVENTS_REPLAY_METHOD, serde_json::json!({"after": event.id}))
+            .unwrap();
+        assert_eq!(reply["events"], serde_json::json!([]));
+
+        let reply = client
+            .request(EVENTS_EPOCH_METHOD, serde_json::Value::Null)
+            .unwrap();
+        assert_eq!(reply["epoch"], bus.epoch());
+        assert!(anonymous
+            .request(EVENTS_REPLAY_METHOD, serde_json::json!({}))
+            .is_err());
+    }
+}
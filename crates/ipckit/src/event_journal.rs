@@ -0,0 +1,543 @@
+//! Event Journal - Disk-backed event history
+//!
+//! An append-only journal of [`Event`]s that outlives the process, so a
+//! subscriber can ask for everything after an [`EventId`] even when the
+//! daemon has restarted since, or when the events have long fallen out of
+//! the bus's in-memory history.
+//!
+//! Events are written to segment files in a directory, either as JSON lines
+//! or as length-prefixed MessagePack records (see [`JournalFormat`]). A new
+//! segment is started when the current one grows past
+//! [`JournalConfig::max_segment_bytes`] or gets older than
+//! [`JournalConfig::max_segment_age`], and every time the journal is opened,
+//! so a record torn by a crash is never appended to. The oldest segments are
+//! deleted beyond [`JournalConfig::max_segments`].
+//!
+//! Opening a journal also moves the process-wide event ID counter past the
+//! last journaled ID and adopts the epoch the journal was created under (see
+//! [`EventBus::epoch`](crate::event_stream::EventBus::epoch)), so IDs keep
+//! increasing across restarts.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ipckit::{Event, EventBus, EventFilter, EventJournal, JournalConfig};
+//!
+//! let dir = std::env::temp_dir().join(format!("ipckit-journal-doc-{}", std::process::id()));
+//! let journal = EventJournal::open(JournalConfig::new(&dir))?;
+//! let bus = EventBus::with_journal(Default::default(), journal);
+//!
+//! let first = Event::new("task.started", serde_json::json!({}));
+//! let first_id = first.id;
+//! bus.publish(first);
+//! bus.publish(Event::new("task.completed", serde_json::json!({})));
+//!
+//! let missed = bus.replay_since(Some(first_id), &EventFilter::new())?;
+//! assert_eq!(missed.len(), 1);
+//! # std::fs::remove_dir_all(&dir).ok();
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::event_stream::{Event, EventFilter, EventId};
+use parking_lot::Mutex;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// File name prefix of journal segments.
+const SEGMENT_PREFIX: &str = "events-";
+
+/// File holding the epoch of the journaled event IDs.
+const EPOCH_FILE: &str = "epoch";
+
+/// Largest record accepted when reading a binary segment.
+const MAX_RECORD_SIZE: usize = 16 * 1024 * 1024;
+
+/// On-disk encoding of journal segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JournalFormat {
+    /// One JSON object per line (`.jsonl`), easy to inspect and grep
+    #[default]
+    Jsonl,
+    /// MessagePack records each preceded by a big-endian `u32` length (`.bin`)
+    Binary,
+}
+
+impl JournalFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            JournalFormat::Jsonl => "jsonl",
+            JournalFormat::Binary => "bin",
+        }
+    }
+
+    fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "jsonl" => Some(JournalFormat::Jsonl),
+            "bin" => Some(JournalFormat::Binary),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration for an [`EventJournal`].
+#[derive(Debug, Clone)]
+pub struct JournalConfig {
+    /// Directory holding the segment files
+    pub dir: PathBuf,
+    /// Encoding of new segments (existing segments are read in their own format)
+    pub format: JournalFormat,
+    /// Start a new segment once the current one reaches this size
+    pub max_segment_bytes: u64,
+    /// Start a new segment once the current one is this old
+    pub max_segment_age: Option<Duration>,
+    /// Delete the oldest segments beyond this many (`None` keeps all)
+    pub max_segments: Option<usize>,
+}
+
+impl JournalConfig {
+    /// Create a configuration writing JSON lines to `dir`, rotating at 64 MiB
+    /// and keeping every segment.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            format: JournalFormat::Jsonl,
+            max_segment_bytes: 64 * 1024 * 1024,
+            max_segment_age: None,
+            max_segments: None,
+        }
+    }
+
+    /// Set the encoding of new segments.
+    pub fn format(mut self, format: JournalFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the size at which segments are rotated.
+    pub fn max_segment_bytes(mut self, bytes: u64) -> Self {
+        self.max_segment_bytes = bytes;
+        self
+    }
+
+    /// Set the age at which segments are rotated.
+    pub fn max_segment_age(mut self, age: Duration) -> Self {
+        self.max_segment_age = Some(age);
+        self
+    }
+
+    /// Set how many segments to keep.
+    pub fn max_segments(mut self, count: usize) -> Self {
+        self.max_segments = Some(count);
+        self
+    }
+}
+
+/// A segment file on disk.
+#[derive(Debug, Clone)]
+struct Segment {
+    path: PathBuf,
+    /// ID of the first event written to it, from the file name
+    first_id: EventId,
+    format: JournalFormat,
+}
+
+struct ActiveSegment {
+    writer: BufWriter<File>,
+    bytes: u64,
+    opened: Instant,
+}
+
+struct JournalState {
+    segments: Vec<Segment>,
+    active: Option<ActiveSegment>,
+    last_id: Option<EventId>,
+}
+
+/// Append-only, segmented on-disk event log.
+pub struct EventJournal {
+    config: JournalConfig,
+    state: Mutex<JournalState>,
+}
+
+impl std::fmt::Debug for EventJournal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventJournal")
+            .field("config", &self.config)
+            .field("segments", &self.state.lock().segments.len())
+            .finish()
+    }
+}
+
+impl EventJournal {
+    /// Open the journal in `config.dir`, creating the directory if needed.
+    ///
+    /// Existing segments are scanned for the last event ID, and the event ID
+    /// counter is advanced past it. A new journal records the current epoch;
+    /// an existing one makes its epoch current.
+    pub fn open(config: JournalConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+
+        let epoch_path = config.dir.join(EPOCH_FILE);
+        match std::fs::read_to_string(&epoch_path) {
+            Ok(epoch) => {
+                let epoch = epoch.trim().parse().map_err(|_| {
+                    IpcError::deserialization(format!("invalid journal epoch in {:?}", epoch_path))
+                })?;
+                crate::event_stream::adopt_event_epoch(epoch);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                std::fs::write(&epoch_path, crate::event_stream::event_epoch().to_string())?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut segments = Vec::new();
+        for entry in std::fs::read_dir(&config.dir)? {
+            let path = entry?.path();
+            if let Some(segment) = parse_segment_name(&path) {
+                segments.push(segment);
+            }
+        }
+        segments.sort_by_key(|s| s.first_id);
+
+        let mut last_id = None;
+        for segment in &segments {
+            read_segment(segment, |event| {
+                last_id = last_id.max(Some(event.id));
+                true
+            })?;
+        }
+        if let Some(id) = last_id {
+            crate::event_stream::advance_event_ids(id);
+        }
+
+        Ok(Self {
+            config,
+            state: Mutex::new(JournalState {
+                segments,
+                active: None,
+                last_id,
+            }),
+        })
+    }
+
+    /// Get the configuration.
+    pub fn config(&self) -> &JournalConfig {
+        &self.config
+    }
+
+    /// Get the ID of the most recently journaled event.
+    pub fn last_id(&self) -> Option<EventId> {
+        self.state.lock().last_id
+    }
+
+    /// Get the paths of the segment files, oldest first.
+    pub fn segments(&self) -> Vec<PathBuf> {
+        self.state
+            .lock()
+            .segments
+            .iter()
+            .map(|s| s.path.clone())
+            .collect()
+    }
+
+    /// Append an event.
+    pub fn append(&self, event: &Event) -> Result<()> {
+        self.append_batch(std::slice::from_ref(event))
+    }
+
+    /// Append several events, flushing once at the end.
+    pub fn append_batch(&self, events: &[Event]) -> Result<()> {
+        let mut state = self.state.lock();
+        for event in events {
+            let record = encode(event, self.config.format)?;
+            let active = self.writable_segment(&mut state, event.id)?;
+            active.writer.write_all(&record)?;
+            active.bytes += record.len() as u64;
+            state.last_id = state.last_id.max(Some(event.id));
+        }
+        if let Some(active) = state.active.as_mut() {
+            active.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Read the journaled events matching `filter` that have an ID greater
+    /// than `after` (or all of them with `None`), in the order they were
+    /// written.
+    pub fn replay_since(&self, after: Option<EventId>, filter: &EventFilter) -> Result<Vec<Event>> {
+        let segments = {
+            let mut state = self.state.lock();
+            if let Some(active) = state.active.as_mut() {
+                active.writer.flush()?;
+            }
+            state.segments.clone()
+        };
+
+        let mut events = Vec::new();
+        for segment in &segments {
+            read_segment(segment, |event| {
+                if after.is_none_or(|id| event.id > id)
+                    && !event.is_expired()
+                    && filter.matches(&event)
+                {
+                    events.push(event);
+                }
+                true
+            })?;
+        }
+        Ok(events)
+    }
+
+    /// Get the segment to write the event `next_id` to, rotating as needed.
+    fn writable_segment<'a>(
+        &self,
+        state: &'a mut JournalState,
+        next_id: EventId,
+    ) -> Result<&'a mut ActiveSegment> {
+        let rotate = match &state.active {
+            None => true,
+            Some(active) => {
+                active.bytes >= self.config.max_segment_bytes
+                    || self
+                        .config
+                        .max_segment_age
+                        .is_some_and(|age| active.opened.elapsed() >= age)
+            }
+        };
+
+        if rotate {
+            if let Some(mut old) = state.active.take() {
+                old.writer.flush()?;
+            }
+
+            let format = self.config.format;
+            let path = self.config.dir.join(format!(
+                "{}{:020}.{}",
+                SEGMENT_PREFIX,
+                next_id,
+                format.extension()
+            ));
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            let bytes = file.metadata()?.len();
+
+            // Reopening after a restart can land on the newest segment's name
+            // again; keep one entry per file
+            if !state.segments.iter().any(|s| s.path == path) {
+                state.segments.push(Segment {
+                    path,
+                    first_id: next_id,
+                    format,
+                });
+            }
+            state.active = Some(ActiveSegment {
+                writer: BufWriter::new(file),
+                bytes,
+                opened: Instant::now(),
+            });
+
+            self.enforce_retention(state);
+        }
+
+        Ok(state.active.as_mut().expect("segment was just opened"))
+    }
+
+    /// Delete the oldest segments beyond `max_segments`, never the active one.
+    fn enforce_retention(&self, state: &mut JournalState) {
+        let Some(max) = self.config.max_segments else {
+            return;
+        };
+        let excess = state.segments.len().saturating_sub(max.max(1));
+        for segment in state.segments.drain(..excess) {
+            if let Err(e) = std::fs::remove_file(&segment.path) {
+                tracing::warn!("failed to remove journal segment {:?}: {}", segment.path, e);
+            }
+        }
+    }
+}
+
+fn parse_segment_name(path: &Path) -> Option<Segment> {
+    let format = JournalFormat::from_extension(path.extension()?.to_str()?)?;
+    let first_id = path
+        .file_stem()?
+        .to_str()?
+        .strip_prefix(SEGMENT_PREFIX)?
+        .parse()
+        .ok()?;
+    Some(Segment {
+        path: path.to_path_buf(),
+        first_id,
+        format,
+    })
+}
+
+fn encode(event: &Event, format: JournalFormat) -> Result<Vec<u8>> {
+    match format {
+        JournalFormat::Jsonl => {
+            let mut line =
+                serde_json::to_vec(event).map_err(|e| IpcError::serialization(e.to_string()))?;
+            line.push(b'\n');
+            Ok(line)
+        }
+        JournalFormat::Binary => {
+            let value =
+                serde_json::to_value(event).map_err(|e| IpcError::serialization(e.to_string()))?;
+            let body = crate::msgpack::to_vec(&value);
+            let mut record = Vec::with_capacity(4 + body.len());
+            record.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            record.extend_from_slice(&body);
+            Ok(record)
+        }
+    }
+}
+
+/// Call `f` with each event in the segment until it returns `false`.
+///
+/// A truncated or corrupt final record, as left by a crash mid-write, ends
+/// the segment instead of failing the read.
+fn read_segment(segment: &Segment, mut f: impl FnMut(Event) -> bool) -> Result<()> {
+    let file = match File::open(&segment.path) {
+        Ok(file) => file,
+        // Deleted by retention since the segment list was taken
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(IpcError::Io(e)),
+    };
+    let mut reader = BufReader::new(file);
+
+    match segment.format {
+        JournalFormat::Jsonl => {
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                if reader.read_until(b'\n', &mut line)? == 0 {
+                    break;
+                }
+                let Ok(event) = serde_json::from_slice::<Event>(&line) else {
+                    break;
+                };
+                if !f(event) {
+                    break;
+                }
+            }
+        }
+        JournalFormat::Binary => loop {
+            let mut len = [0u8; 4];
+            if reader.read_exact(&mut len).is_err() {
+                break;
+            }
+            let len = u32::from_be_bytes(len) as usize;
+            if len > MAX_RECORD_SIZE {
+                break;
+            }
+            let mut body = vec![0u8; len];
+            if reader.read_exact(&mut body).is_err() {
+                break;
+            }
+            let Some(event) = crate::msgpack::from_slice(&body)
+                .ok()
+                .and_then(|value| serde_json::from_value::<Event>(value).ok())
+            else {
+                break;
+            };
+            if !f(event) {
+                break;
+            }
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ipckit-journal-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_replay_across_reopen() {
+        for format in [JournalFormat::Jsonl, JournalFormat::Binary] {
+            let dir = temp_dir(&format!("reopen-{:?}", format));
+            let config = JournalConfig::new(&dir).format(format);
+
+            let events: Vec<Event> = (0..5)
+                .map(|i| Event::with_resource("task.log", "task-1", serde_json::json!({"i": i})))
+                .collect();
+            let journal = EventJournal::open(config.clone()).unwrap();
+            journal.append_batch(&events).unwrap();
+            drop(journal);
+
+            let journal = EventJournal::open(config).unwrap();
+            assert_eq!(journal.last_id(), Some(events[4].id));
+
+            let replayed = journal
+                .replay_since(Some(events[1].id), &EventFilter::new())
+                .unwrap();
+            let ids: Vec<_> = replayed.iter().map(|e| e.id).collect();
+            assert_eq!(ids, events[2..].iter().map(|e| e.id).collect::<Vec<_>>());
+            assert_eq!(replayed[0].data["i"], 2);
+
+            // The journal keeps the epoch its IDs were handed out under
+            let epoch = std::fs::read_to_string(dir.join(EPOCH_FILE)).unwrap();
+            assert_eq!(epoch, crate::event_stream::event_epoch().to_string());
+
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_rotation_and_retention() {
+        let dir = temp_dir("rotation");
+        let journal = EventJournal::open(
+            JournalConfig::new(&dir)
+                .max_segment_bytes(1)
+                .max_segments(3),
+        )
+        .unwrap();
+
+        let events: Vec<Event> = (0..5)
+            .map(|i| Event::new("tick", serde_json::json!(i)))
+            .collect();
+        for event in &events {
+            journal.append(event).unwrap();
+        }
+
+        // One event per segment, oldest two deleted
+        assert_eq!(journal.segments().len(), 3);
+        let replayed = journal.replay_since(None, &EventFilter::new()).unwrap();
+        assert_eq!(
+            replayed.iter().map(|e| e.id).collect::<Vec<_>>(),
+            events[2..].iter().map(|e| e.id).collect::<Vec<_>>()
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_torn_record_is_ignored() {
+        let dir = temp_dir("torn");
+        let journal = EventJournal::open(JournalConfig::new(&dir)).unwrap();
+        let event = Event::new("ok", serde_json::json!({}));
+        journal.append(&event).unwrap();
+
+        let path = journal.segments()[0].clone();
+        drop(journal);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"id\": 99, \"timest").unwrap();
+        drop(file);
+
+        let journal = EventJournal::open(JournalConfig::new(&dir)).unwrap();
+        let replayed = journal.replay_since(None, &EventFilter::new()).unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].id, event.id);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
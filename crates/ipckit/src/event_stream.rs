@@ -7,8 +7,15 @@
 //!
 //! - Publish-subscribe pattern with multiple publishers and subscribers
 //! - Event filtering by type and resource ID
-//! - Event history with optional replay
+//! - Event history with optional replay, kept on disk across restarts with an
+//!   [`EventJournal`](crate::event_journal::EventJournal)
 //! - Backpressure handling for slow consumers
+//! - Subscriptions end when the [`EventSubscriber`] is dropped, or explicitly
+//!   through a [`SubscriptionHandle`] or [`EventBus::unsubscribe_matching`]
+//! - Event loop integration: subscribers accept an [`EventLoopWaker`] that is
+//!   woken whenever a matching event is enqueued
+//! - Async streams of events with the `async` feature
+//!   ([`EventBus::subscribe_async`], [`EventSubscriber::into_stream`])
 //! - MCP (Model Context Protocol) compatible progress events via [`McpProgressPayload`]
 //!
 //! # Example
@@ -33,6 +40,44 @@
 //! }
 //! ```
 //!
+//! ## Waking a GUI event loop
+//!
+//! ```rust
+//! use ipckit::{CallbackWaker, Event, EventBus, EventFilter};
+//!
+//! let bus = EventBus::new(Default::default());
+//! let subscriber = bus
+//!     .subscribe(EventFilter::new().event_type("task.*"))
+//!     .with_waker(Box::new(CallbackWaker::new(|| {
+//!         // e.g. event_loop_proxy.send_event(UserEvent::Ipc)
+//!     })));
+//!
+//! bus.publish(Event::new("task.started", serde_json::json!({})));
+//!
+//! // In the event loop's wake-up handler, drain without blocking
+//! for event in subscriber.try_iter() {
+//!     println!("{}", event.event_type);
+//! }
+//! ```
+//!
+//! ## Async streams
+//!
+//! With the `async` feature, a subscription can be consumed as a
+//! `futures_core::Stream`, e.g. in `tokio::select!`:
+//!
+//! ```rust,ignore
+//! use ipckit::{EventBus, EventFilter};
+//! use tokio_stream::StreamExt; // or futures::StreamExt
+//!
+//! let bus = EventBus::new(Default::default());
+//! let mut events = bus.subscribe_async(EventFilter::new().event_type("task.*"));
+//!
+//! tokio::select! {
+//!     Some(event) = events.next() => println!("{}", event.event_type),
+//!     _ = shutdown.recv() => {}
+//! }
+//! ```
+//!
 //! ## MCP progress events
 //!
 //! ```rust
@@ -55,17 +100,55 @@
 //! ```
 
 use crate::error::{IpcError, Result};
-use crossbeam_channel::{self, Receiver, Sender, TryRecvError};
+use crate::event_journal::EventJournal;
+use crate::trace_context::TraceContext;
+use crate::waker::{EventLoopWaker, WakeableChannel};
+use crossbeam_channel::{self, Receiver, Sender, TryRecvError, TrySendError};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::{Duration, SystemTime};
 
 /// A unique event identifier.
 pub type EventId = u64;
 
+/// Next ID handed out by [`Event::new`].
+static NEXT_EVENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Epoch of the IDs handed out by [`Event::new`], or 0 until first used.
+static EVENT_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Make sure events created from now on get IDs greater than `id`.
+pub(crate) fn advance_event_ids(id: EventId) {
+    NEXT_EVENT_ID.fetch_max(id.saturating_add(1), Ordering::SeqCst);
+}
+
+/// Get the epoch of this process's event IDs, picking a new one on first use.
+pub(crate) fn event_epoch() -> u64 {
+    let epoch = EVENT_EPOCH.load(Ordering::SeqCst);
+    if epoch != 0 {
+        return epoch;
+    }
+    let nanos = SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    // Kept to 53 bits so JavaScript clients read it exactly
+    let fresh = ((nanos ^ (u64::from(std::process::id()) << 32)) & ((1 << 53) - 1)).max(1);
+    match EVENT_EPOCH.compare_exchange(0, fresh, Ordering::SeqCst, Ordering::SeqCst) {
+        Ok(_) => fresh,
+        Err(existing) => existing,
+    }
+}
+
+/// Continue the event IDs of an earlier run under its epoch.
+pub(crate) fn adopt_event_epoch(epoch: u64) {
+    if epoch != 0 {
+        EVENT_EPOCH.store(epoch, Ordering::SeqCst);
+    }
+}
+
 /// An event that can be published and subscribed to.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -80,6 +163,13 @@ pub struct Event {
     pub resource_id: Option<String>,
     /// Event data
     pub data: serde_json::Value,
+    /// Trace the event was published in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceContext>,
+    /// Milliseconds after `timestamp` at which the event goes stale; stale
+    /// events are skipped by subscribers, history and replay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
 }
 
 mod system_time_serde {
@@ -105,18 +195,34 @@ mod system_time_serde {
 
 impl Event {
     /// Create a new event with the given type and data.
+    ///
+    /// The event belongs to the current [`TraceContext`], or starts a new
+    /// trace if there is none.
     pub fn new(event_type: &str, data: serde_json::Value) -> Self {
-        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
-
         Self {
-            id: NEXT_ID.fetch_add(1, Ordering::SeqCst),
+            id: NEXT_EVENT_ID.fetch_add(1, Ordering::SeqCst),
             timestamp: SystemTime::now(),
             event_type: event_type.to_string(),
             resource_id: None,
             data,
+            trace: Some(TraceContext::current_or_root()),
+            ttl_ms: None,
         }
     }
 
+    /// Set how long the event stays relevant, e.g. a few seconds for
+    /// progress updates that a reconnecting frontend should not replay.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl_ms = Some(ttl.as_millis() as u64);
+        self
+    }
+
+    /// Check whether the event's TTL has passed.
+    pub fn is_expired(&self) -> bool {
+        self.ttl_ms
+            .is_some_and(|ttl| self.timestamp + Duration::from_millis(ttl) < SystemTime::now())
+    }
+
     /// Create an event with a resource ID.
     pub fn with_resource(event_type: &str, resource_id: &str, data: serde_json::Value) -> Self {
         let mut event = Self::new(event_type, data);
@@ -207,6 +313,9 @@ pub mod event_types {
     pub const TASK_CANCELLED: &str = "task.cancelled";
     pub const TASK_PAUSED: &str = "task.paused";
     pub const TASK_RESUMED: &str = "task.resumed";
+    pub const TASK_COMMAND: &str = "task.command";
+    pub const TASK_STALLED: &str = "task.stalled";
+    pub const TASK_TIMEOUT: &str = "task.timeout";
 
     // Logs
     pub const LOG_STDOUT: &str = "log.stdout";
@@ -218,6 +327,8 @@ pub mod event_types {
     // File operations
     pub const FILE_UPLOAD_PROGRESS: &str = "file.upload.progress";
     pub const FILE_DOWNLOAD_PROGRESS: &str = "file.download.progress";
+    pub const FILE_TRANSFER_COMPLETED: &str = "file.transfer.completed";
+    pub const FILE_TRANSFER_FAILED: &str = "file.transfer.failed";
 
     // System
     pub const SYSTEM_SHUTDOWN: &str = "system.shutdown";
@@ -362,32 +473,9 @@ impl EventFilter {
     pub fn matches(&self, event: &Event) -> bool {
         // Check event type
         if let Some(ref patterns) = self.event_types {
-            let matches_type = patterns.iter().any(|pattern| {
-                if pattern.ends_with(".*") {
-                    let prefix = &pattern[..pattern.len() - 2];
-                    event.event_type.starts_with(prefix)
-                } else if pattern.contains('*') {
-                    // Simple glob matching
-                    let parts: Vec<&str> = pattern.split('*').collect();
-                    let mut pos = 0;
-                    for (i, part) in parts.iter().enumerate() {
-                        if part.is_empty() {
-                            continue;
-                        }
-                        if let Some(found) = event.event_type[pos..].find(part) {
-                            if i == 0 && found != 0 {
-                                return false;
-                            }
-                            pos += found + part.len();
-                        } else {
-                            return false;
-                        }
-                    }
-                    true
-                } else {
-                    event.event_type == *pattern
-                }
-            });
+            let matches_type = patterns
+                .iter()
+                .any(|pattern| type_matches(pattern, &event.event_type));
 
             if !matches_type {
                 return false;
@@ -423,7 +511,8 @@ impl EventFilter {
 }
 
 /// Policy for handling slow consumers.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SlowConsumerPolicy {
     /// Drop oldest events when buffer is full
     #[default]
@@ -467,6 +556,17 @@ impl EventPublisher {
         self.inner.publish(event);
     }
 
+    /// Publish a batch of events to the bus.
+    ///
+    /// The history and subscriber locks are acquired once for the whole batch
+    /// and each subscriber receives its matching events in a single burst,
+    /// which is considerably cheaper than calling [`publish`](Self::publish)
+    /// in a loop for bursty producers such as line-by-line log forwarding.
+    /// A subscriber's waker runs once per batch, not once per event.
+    pub fn publish_batch(&self, events: Vec<Event>) {
+        self.inner.publish_batch(events);
+    }
+
     /// Publish a progress event.
     pub fn progress(&self, resource_id: &str, current: u64, total: u64, message: &str) {
         self.publish(Event::progress(resource_id, current, total, message));
@@ -551,19 +651,118 @@ impl EventPublisher {
     }
 }
 
+/// Check an event type against a pattern such as `"task.*"`.
+fn type_matches(pattern: &str, event_type: &str) -> bool {
+    if let Some(prefix) = pattern.strip_suffix(".*") {
+        event_type.starts_with(prefix)
+    } else if pattern.contains('*') {
+        // Simple glob matching
+        let parts: Vec<&str> = pattern.split('*').collect();
+        let mut pos = 0;
+        for (i, part) in parts.iter().enumerate() {
+            if part.is_empty() {
+                continue;
+            }
+            if let Some(found) = event_type[pos..].find(part) {
+                if i == 0 && found != 0 {
+                    return false;
+                }
+                pos += found + part.len();
+            } else {
+                return false;
+            }
+        }
+        true
+    } else {
+        event_type == pattern
+    }
+}
+
+/// Waker slot shared between an [`EventSubscriber`] and the bus.
+type SharedWaker = Arc<RwLock<Option<Box<dyn EventLoopWaker>>>>;
+
 /// Event subscriber for receiving events from the bus.
+///
+/// Implements [`WakeableChannel`]: once a waker is set, the bus wakes it
+/// every time an event matching this subscriber's filter is enqueued, so
+/// GUI loops can drain with [`try_iter`](Self::try_iter) instead of polling.
+///
+/// Dropping the subscriber removes it from the bus.
 pub struct EventSubscriber {
     receiver: Receiver<Event>,
     filter: EventFilter,
+    waker: Option<Box<dyn EventLoopWaker>>,
+    shared_waker: SharedWaker,
+    handle: SubscriptionHandle,
+}
+
+/// Ends a subscription from anywhere, e.g. another thread.
+///
+/// Once unsubscribed, the [`EventSubscriber`] still yields the events already
+/// queued, then its blocking receives return `None` (or [`IpcError::Closed`]).
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    id: u64,
+    bus: Weak<EventBusInner>,
+}
+
+impl SubscriptionHandle {
+    /// Get the subscription ID, unique within its bus.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Remove the subscription from the bus.
+    ///
+    /// Returns `false` if it had already been removed.
+    pub fn unsubscribe(&self) -> bool {
+        self.bus
+            .upgrade()
+            .is_some_and(|bus| bus.unsubscribe(self.id))
+    }
+
+    /// Check whether the subscription is still registered with the bus.
+    pub fn is_active(&self) -> bool {
+        self.bus
+            .upgrade()
+            .is_some_and(|bus| bus.subscribers.read().iter().any(|s| s.id == self.id))
+    }
+}
+
+impl std::fmt::Debug for SubscriptionHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubscriptionHandle")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/// Ready when an event is queued; events the filter rejects make it ready
+/// spuriously, so follow up with [`EventSubscriber::try_recv`].
+impl crate::thread_channel::Selectable for EventSubscriber {
+    fn register<'a>(&'a self, set: &mut crate::thread_channel::ChannelSet<'a>) -> usize {
+        self.receiver.register(set)
+    }
 }
 
 impl EventSubscriber {
+    /// Set the event loop waker and return the subscriber.
+    pub fn with_waker(mut self, waker: Box<dyn EventLoopWaker>) -> Self {
+        self.set_waker(waker);
+        self
+    }
+
+    /// Get the number of events waiting to be received.
+    pub fn pending(&self) -> usize {
+        self.receiver.len()
+    }
+
     /// Receive the next event (blocking).
     pub fn recv(&self) -> Option<Event> {
         loop {
             match self.receiver.recv() {
                 Ok(event) => {
-                    if self.filter.matches(&event) {
+                    if self.accepts(&event) {
                         return Some(event);
                     }
                 }
@@ -577,7 +776,7 @@ impl EventSubscriber {
         loop {
             match self.receiver.try_recv() {
                 Ok(event) => {
-                    if self.filter.matches(&event) {
+                    if self.accepts(&event) {
                         return Some(event);
                     }
                 }
@@ -599,7 +798,7 @@ impl EventSubscriber {
 
             match self.receiver.recv_timeout(remaining) {
                 Ok(event) => {
-                    if self.filter.matches(&event) {
+                    if self.accepts(&event) {
                         return Ok(event);
                     }
                 }
@@ -613,6 +812,15 @@ impl EventSubscriber {
         }
     }
 
+    /// Check whether a received event should be handed out.
+    fn accepts(&self, event: &Event) -> bool {
+        if event.is_expired() {
+            tracing::trace!("dropping expired event {}", event.id);
+            return false;
+        }
+        self.filter.matches(event)
+    }
+
     /// Create an iterator over events.
     pub fn iter(&self) -> impl Iterator<Item = Event> + '_ {
         std::iter::from_fn(move || self.recv())
@@ -627,29 +835,178 @@ impl EventSubscriber {
     pub fn filter(&self) -> &EventFilter {
         &self.filter
     }
+
+    /// Get a handle that can end this subscription.
+    pub fn handle(&self) -> SubscriptionHandle {
+        self.handle.clone()
+    }
+
+    /// Remove this subscriber from the bus, keeping the events already queued.
+    ///
+    /// Equivalent to `self.handle().unsubscribe()`.
+    pub fn unsubscribe(&self) -> bool {
+        self.handle.unsubscribe()
+    }
+
+    /// Turn the subscriber into an async stream of its events.
+    ///
+    /// The stream replaces any waker set on the subscriber. It ends once the
+    /// subscription is removed from the bus, or the bus is dropped, and the
+    /// events already queued have been yielded.
+    #[cfg(feature = "async")]
+    pub fn into_stream(mut self) -> impl futures_core::Stream<Item = Event> + Send + Unpin {
+        let waker = TaskWaker::default();
+        self.set_waker(Box::new(waker.clone()));
+        SubscriberStream {
+            subscriber: self,
+            waker,
+        }
+    }
+}
+
+/// Wakes the async task polling a [`SubscriberStream`].
+#[cfg(feature = "async")]
+#[derive(Clone, Default)]
+struct TaskWaker(Arc<parking_lot::Mutex<Option<std::task::Waker>>>);
+
+#[cfg(feature = "async")]
+impl EventLoopWaker for TaskWaker {
+    fn wake(&self) {
+        if let Some(waker) = self.0.lock().take() {
+            waker.wake();
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        true
+    }
+
+    fn clone_box(&self) -> Box<dyn EventLoopWaker> {
+        Box::new(self.clone())
+    }
+}
+
+/// Stream returned by [`EventSubscriber::into_stream`].
+#[cfg(feature = "async")]
+struct SubscriberStream {
+    subscriber: EventSubscriber,
+    waker: TaskWaker,
+}
+
+#[cfg(feature = "async")]
+impl futures_core::Stream for SubscriberStream {
+    type Item = Event;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Event>> {
+        let this = self.get_mut();
+        // Register before checking so an event published in between wakes us
+        *this.waker.0.lock() = Some(cx.waker().clone());
+
+        if let Some(event) = this.subscriber.try_recv() {
+            return std::task::Poll::Ready(Some(event));
+        }
+        if this.subscriber.handle.is_active() {
+            std::task::Poll::Pending
+        } else {
+            std::task::Poll::Ready(this.subscriber.try_recv())
+        }
+    }
+}
+
+impl Drop for EventSubscriber {
+    fn drop(&mut self) {
+        // Disconnect first so a publisher blocked on our full queue lets go
+        // of the subscriber list
+        drop(std::mem::replace(
+            &mut self.receiver,
+            crossbeam_channel::never(),
+        ));
+        self.handle.unsubscribe();
+    }
+}
+
+impl WakeableChannel for EventSubscriber {
+    fn set_waker(&mut self, waker: Box<dyn EventLoopWaker>) {
+        *self.shared_waker.write() = Some(waker.clone_box());
+        // Events enqueued before the waker was attached would otherwise sit
+        // unnoticed until the next publish
+        if !self.receiver.is_empty() && waker.is_valid() {
+            waker.wake();
+        }
+        self.waker = Some(waker);
+    }
+
+    fn clear_waker(&mut self) {
+        *self.shared_waker.write() = None;
+        self.waker = None;
+    }
+
+    fn waker(&self) -> Option<&dyn EventLoopWaker> {
+        self.waker.as_deref()
+    }
 }
 
 struct Subscriber {
+    id: u64,
     sender: Sender<Event>,
     filter: EventFilter,
+    waker: SharedWaker,
+}
+
+impl Subscriber {
+    fn wake(&self) {
+        if let Some(ref waker) = *self.waker.read() {
+            if waker.is_valid() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        // Lets an async stream waiting on the subscription see that it ended
+        self.wake();
+    }
 }
 
 struct EventBusInner {
     config: EventBusConfig,
+    /// Starts as `config.slow_consumer` but can be changed at runtime
+    slow_consumer: RwLock<SlowConsumerPolicy>,
     subscribers: RwLock<Vec<Subscriber>>,
+    next_subscriber_id: AtomicU64,
     history: RwLock<VecDeque<Event>>,
+    journal: Option<EventJournal>,
 }
 
 impl EventBusInner {
-    fn new(config: EventBusConfig) -> Self {
+    fn new(config: EventBusConfig, journal: Option<EventJournal>) -> Self {
         Self {
+            slow_consumer: RwLock::new(config.slow_consumer),
             config,
             subscribers: RwLock::new(Vec::new()),
+            next_subscriber_id: AtomicU64::new(1),
             history: RwLock::new(VecDeque::new()),
+            journal,
+        }
+    }
+
+    fn journal(&self, events: &[Event]) {
+        if let Some(journal) = &self.journal {
+            // Publishing never fails; losing the disk copy only costs replay depth
+            if let Err(e) = journal.append_batch(events) {
+                tracing::warn!("failed to journal events: {}", e);
+            }
         }
     }
 
     fn publish(&self, event: Event) {
+        self.journal(std::slice::from_ref(&event));
+
         // Add to history
         {
             let mut history = self.history.write();
@@ -662,34 +1019,97 @@ impl EventBusInner {
         }
 
         // Send to subscribers
-        let subscribers = self.subscribers.read();
-        for sub in subscribers.iter() {
+        let mut disconnected = Vec::new();
+        for sub in self.subscribers.read().iter() {
             if sub.filter.matches(&event) {
-                match self.config.slow_consumer {
-                    SlowConsumerPolicy::Block => {
-                        let _ = sub.sender.send(event.clone());
-                    }
-                    SlowConsumerPolicy::DropNewest => {
-                        let _ = sub.sender.try_send(event.clone());
-                    }
-                    SlowConsumerPolicy::DropOldest => {
-                        // Try to send, if full, receive one and try again
-                        if sub.sender.try_send(event.clone()).is_err() {
-                            // Channel is full, we just drop the event for this subscriber
-                            // In a more sophisticated implementation, we could drain old events
-                        }
+                match self.deliver(sub, &event) {
+                    Some(true) => sub.wake(),
+                    Some(false) => {}
+                    None => disconnected.push(sub.id),
+                }
+            }
+        }
+        self.prune(&disconnected);
+    }
+
+    fn publish_batch(&self, events: Vec<Event>) {
+        if events.is_empty() {
+            return;
+        }
+        self.journal(&events);
+
+        // Append the whole batch to history under a single lock
+        {
+            let mut history = self.history.write();
+            history.extend(events.iter().cloned());
+
+            // Trim history if needed
+            let excess = history.len().saturating_sub(self.config.history_size);
+            history.drain(..excess);
+        }
+
+        // Deliver to each subscriber in one burst, waking it once
+        let mut disconnected = Vec::new();
+        for sub in self.subscribers.read().iter() {
+            let mut queued = false;
+            for event in events.iter().filter(|e| sub.filter.matches(e)) {
+                match self.deliver(sub, event) {
+                    Some(sent) => queued |= sent,
+                    None => {
+                        disconnected.push(sub.id);
+                        break;
                     }
                 }
             }
+            if queued {
+                sub.wake();
+            }
+        }
+        self.prune(&disconnected);
+    }
+
+    /// Queue an event for one subscriber without waking it.
+    ///
+    /// Returns whether the event was queued (`false` if a full buffer
+    /// dropped it), or `None` if the subscriber's receiver is gone.
+    fn deliver(&self, sub: &Subscriber, event: &Event) -> Option<bool> {
+        let sent = match *self.slow_consumer.read() {
+            SlowConsumerPolicy::Block => sub
+                .sender
+                .send(event.clone())
+                .map_err(|e| TrySendError::Disconnected(e.0)),
+            SlowConsumerPolicy::DropNewest => sub.sender.try_send(event.clone()),
+            SlowConsumerPolicy::DropOldest => {
+                // If the channel is full, we just drop the event for this subscriber
+                // In a more sophisticated implementation, we could drain old events
+                sub.sender.try_send(event.clone())
+            }
+        };
+
+        match sent {
+            Ok(()) => Some(true),
+            Err(TrySendError::Full(_)) => Some(false),
+            Err(TrySendError::Disconnected(_)) => None,
         }
     }
 
-    fn subscribe(&self, filter: EventFilter) -> EventSubscriber {
+    /// Drop subscribers whose receivers have gone away.
+    fn prune(&self, ids: &[u64]) {
+        if !ids.is_empty() {
+            self.subscribers.write().retain(|s| !ids.contains(&s.id));
+        }
+    }
+
+    fn subscribe(self: &Arc<Self>, filter: EventFilter) -> EventSubscriber {
         let (tx, rx) = crossbeam_channel::bounded(self.config.subscriber_buffer);
 
+        let id = self.next_subscriber_id.fetch_add(1, Ordering::Relaxed);
+        let waker: SharedWaker = Arc::new(RwLock::new(None));
         let subscriber = Subscriber {
+            id,
             sender: tx,
             filter: filter.clone(),
+            waker: Arc::clone(&waker),
         };
 
         self.subscribers.write().push(subscriber);
@@ -697,14 +1117,36 @@ impl EventBusInner {
         EventSubscriber {
             receiver: rx,
             filter,
+            waker: None,
+            shared_waker: waker,
+            handle: SubscriptionHandle {
+                id,
+                bus: Arc::downgrade(self),
+            },
         }
     }
 
+    fn unsubscribe(&self, id: u64) -> bool {
+        let mut subscribers = self.subscribers.write();
+        let before = subscribers.len();
+        subscribers.retain(|s| s.id != id);
+        subscribers.len() != before
+    }
+
     fn history(&self, filter: &EventFilter) -> Vec<Event> {
         let history = self.history.read();
         history
             .iter()
-            .filter(|e| filter.matches(e))
+            .filter(|e| !e.is_expired() && filter.matches(e))
+            .cloned()
+            .collect()
+    }
+
+    fn history_after(&self, after: Option<EventId>, filter: &EventFilter) -> Vec<Event> {
+        let history = self.history.read();
+        history
+            .iter()
+            .filter(|e| after.is_none_or(|id| e.id > id) && !e.is_expired() && filter.matches(e))
             .cloned()
             .collect()
     }
@@ -724,10 +1166,36 @@ impl EventBus {
     /// Create a new event bus with the given configuration.
     pub fn new(config: EventBusConfig) -> Self {
         Self {
-            inner: Arc::new(EventBusInner::new(config)),
+            inner: Arc::new(EventBusInner::new(config, None)),
         }
     }
 
+    /// Create a new event bus that also writes every event to `journal`.
+    ///
+    /// [`replay_since`](Self::replay_since) then reads from the journal, so
+    /// replay reaches past the in-memory history and across restarts.
+    pub fn with_journal(config: EventBusConfig, journal: EventJournal) -> Self {
+        Self {
+            inner: Arc::new(EventBusInner::new(config, Some(journal))),
+        }
+    }
+
+    /// Get the journal, if the bus was created with one.
+    pub fn journal(&self) -> Option<&EventJournal> {
+        self.inner.journal.as_ref()
+    }
+
+    /// Get the epoch of the bus's event IDs.
+    ///
+    /// IDs only increase within one epoch. A restarted process starts its
+    /// IDs again from 1 under a new epoch, unless it opens an
+    /// [`EventJournal`], which keeps the epoch and the IDs of the run that
+    /// created it. An ID from another epoch says nothing about which events
+    /// a client has seen.
+    pub fn epoch(&self) -> u64 {
+        event_epoch()
+    }
+
     /// Create a new publisher for this bus.
     pub fn publisher(&self) -> EventPublisher {
         EventPublisher {
@@ -740,20 +1208,95 @@ impl EventBus {
         self.inner.subscribe(filter)
     }
 
+    /// Subscribe to events as an async stream.
+    ///
+    /// Equivalent to `self.subscribe(filter).into_stream()`.
+    #[cfg(feature = "async")]
+    pub fn subscribe_async(
+        &self,
+        filter: EventFilter,
+    ) -> impl futures_core::Stream<Item = Event> + Send + Unpin {
+        self.subscribe(filter).into_stream()
+    }
+
+    /// Get the number of live subscriptions.
+    pub fn subscriber_count(&self) -> usize {
+        self.inner.subscribers.read().len()
+    }
+
+    /// Remove every subscriber with an event type pattern matching `pattern`,
+    /// returning how many were removed.
+    ///
+    /// `pattern` is matched against the subscribers' patterns themselves, so
+    /// `"task.*"` ends subscriptions to `"task.progress"` and `"task.*"`
+    /// alike. Subscribers without an event type filter are only removed by
+    /// `"*"`.
+    pub fn unsubscribe_matching(&self, pattern: &str) -> usize {
+        let mut subscribers = self.inner.subscribers.write();
+        let before = subscribers.len();
+        subscribers.retain(|s| match &s.filter.event_types {
+            Some(types) => !types.iter().any(|t| type_matches(pattern, t)),
+            None => pattern != "*",
+        });
+        before - subscribers.len()
+    }
+
     /// Get historical events matching the given filter.
+    ///
+    /// Events whose [TTL](Event::with_ttl) has passed are left out.
     pub fn history(&self, filter: &EventFilter) -> Vec<Event> {
         self.inner.history(filter)
     }
 
+    /// Get historical events matching the filter that were published after
+    /// the event with ID `after`.
+    ///
+    /// Event IDs increase monotonically, so this is what a reconnecting
+    /// client uses to catch up on events it missed. With `after = None` the
+    /// whole matching history is returned.
+    pub fn history_after(&self, after: Option<EventId>, filter: &EventFilter) -> Vec<Event> {
+        self.inner.history_after(after, filter)
+    }
+
+    /// Get the events matching the filter that were published after the
+    /// event with ID `after`, from the journal if there is one.
+    ///
+    /// Without a journal this is [`history_after`](Self::history_after).
+    pub fn replay_since(&self, after: Option<EventId>, filter: &EventFilter) -> Result<Vec<Event>> {
+        match &self.inner.journal {
+            Some(journal) => journal.replay_since(after, filter),
+            None => Ok(self.inner.history_after(after, filter)),
+        }
+    }
+
     /// Clear all event history.
+    ///
+    /// The journal, if any, is left alone.
     pub fn clear_history(&self) {
         self.inner.clear_history();
     }
 
+    /// Get the current slow consumer policy.
+    pub fn slow_consumer_policy(&self) -> SlowConsumerPolicy {
+        *self.inner.slow_consumer.read()
+    }
+
+    /// Change the slow consumer policy of a running bus.
+    pub fn set_slow_consumer_policy(&self, policy: SlowConsumerPolicy) {
+        *self.inner.slow_consumer.write() = policy;
+    }
+
     /// Publish an event directly.
     pub fn publish(&self, event: Event) {
         self.inner.publish(event);
     }
+
+    /// Publish a batch of events directly.
+    ///
+    /// See [`EventPublisher::publish_batch`].
+    pub fn publish_batch(&self, events: Vec<Event>) {
+        self.inner.publish_batch(events);
+    }
 }
 
 impl Default for EventBus {
@@ -921,6 +1464,70 @@ mod tests {
         assert_eq!(sub3.try_iter().count(), 2);
     }
 
+    #[test]
+    fn test_subscriber_lifecycle() {
+        let bus = EventBus::new(Default::default());
+        let sub1 = bus.subscribe(EventFilter::new().event_type("task.*"));
+        let sub2 = bus.subscribe(EventFilter::new());
+        assert_eq!(bus.subscriber_count(), 2);
+        assert_ne!(sub1.handle().id(), sub2.handle().id());
+
+        // Dropping a subscriber unregisters it
+        drop(sub2);
+        assert_eq!(bus.subscriber_count(), 1);
+
+        // Unsubscribing from another thread ends a blocked recv after the
+        // queued events
+        bus.publish(Event::new("task.started", serde_json::json!({})));
+        let handle = sub1.handle();
+        let receiver = std::thread::spawn(move || sub1.iter().count());
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(handle.is_active());
+        assert!(handle.unsubscribe());
+        assert!(!handle.unsubscribe());
+        assert!(!handle.is_active());
+        assert_eq!(receiver.join().unwrap(), 1);
+        assert_eq!(bus.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn test_disconnected_subscribers_pruned() {
+        let bus = EventBus::new(Default::default());
+        let _live = bus.subscribe(EventFilter::new());
+
+        // A subscriber whose receiver vanished without unsubscribing
+        let (tx, rx) = crossbeam_channel::bounded(4);
+        drop(rx);
+        bus.inner.subscribers.write().push(Subscriber {
+            id: 0,
+            sender: tx,
+            filter: EventFilter::new(),
+            waker: Arc::new(RwLock::new(None)),
+        });
+        assert_eq!(bus.subscriber_count(), 2);
+
+        bus.publish(Event::new("log.info", serde_json::json!({})));
+        assert_eq!(bus.subscriber_count(), 1);
+    }
+
+    #[test]
+    fn test_unsubscribe_matching() {
+        let bus = EventBus::new(Default::default());
+        let progress = bus.subscribe(EventFilter::new().event_type("task.progress"));
+        let tasks = bus.subscribe(EventFilter::new().event_type("task.*"));
+        let logs = bus.subscribe(EventFilter::new().event_type("log.*"));
+        let all = bus.subscribe(EventFilter::new());
+
+        assert_eq!(bus.unsubscribe_matching("task.*"), 2);
+        assert!(!progress.handle().is_active());
+        assert!(!tasks.handle().is_active());
+        assert!(logs.handle().is_active());
+
+        assert_eq!(bus.unsubscribe_matching("*"), 2);
+        assert!(!all.handle().is_active());
+        assert_eq!(bus.subscriber_count(), 0);
+    }
+
     #[test]
     fn test_publisher_helper_methods() {
         let bus = EventBus::new(Default::default());
@@ -936,6 +1543,39 @@ mod tests {
         assert_eq!(events.len(), 4);
     }
 
+    #[test]
+    fn test_publish_batch() {
+        let bus = EventBus::new(EventBusConfig {
+            history_size: 3,
+            ..Default::default()
+        });
+        let publisher = bus.publisher();
+        let sub_task = bus.subscribe(EventFilter::new().event_type("task.*"));
+        let sub_all = bus.subscribe(EventFilter::new());
+
+        publisher.publish_batch(vec![
+            Event::new("task.started", serde_json::json!({})),
+            Event::stdout("task-1", "line 1"),
+            Event::stdout("task-1", "line 2"),
+            Event::new("task.completed", serde_json::json!({})),
+        ]);
+
+        let task_events: Vec<Event> = sub_task.try_iter().collect();
+        assert_eq!(task_events.len(), 2);
+        assert_eq!(task_events[0].event_type, "task.started");
+        assert_eq!(task_events[1].event_type, "task.completed");
+        assert_eq!(sub_all.try_iter().count(), 4);
+
+        // History keeps only the newest events, in order
+        let history = bus.history(&EventFilter::new());
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].data["message"], "line 1");
+        assert_eq!(history[2].event_type, "task.completed");
+
+        publisher.publish_batch(Vec::new());
+        assert_eq!(bus.history(&EventFilter::new()).len(), 3);
+    }
+
     #[test]
     fn test_event_serialization() {
         let event = Event::with_resource(
@@ -1030,4 +1670,114 @@ mod tests {
         assert_eq!(sub_all.try_iter().count(), 2);
         assert_eq!(sub_mcp.try_iter().count(), 1);
     }
+
+    #[test]
+    fn test_subscriber_waker() {
+        use crate::waker::CallbackWaker;
+        use std::sync::atomic::AtomicUsize;
+
+        let bus = EventBus::new(EventBusConfig::default());
+        bus.publish(Event::new("task.started", serde_json::json!({})));
+
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let wakes_clone = Arc::clone(&wakes);
+        let early = bus.subscribe(EventFilter::new().event_type("task.*"));
+        bus.publish(Event::new("task.progress", serde_json::json!({})));
+
+        // Attaching a waker with events already queued wakes immediately
+        let mut subscriber = early.with_waker(Box::new(CallbackWaker::new(move || {
+            wakes_clone.fetch_add(1, Ordering::SeqCst);
+        })));
+        assert!(subscriber.waker().is_some());
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+
+        bus.publish(Event::new("task.completed", serde_json::json!({})));
+        bus.publish(Event::new("log.info", serde_json::json!({})));
+        assert_eq!(wakes.load(Ordering::SeqCst), 2);
+        assert_eq!(subscriber.pending(), 2);
+        assert_eq!(subscriber.try_iter().count(), 2);
+
+        subscriber.clear_waker();
+        bus.publish(Event::new("task.failed", serde_json::json!({})));
+        assert_eq!(wakes.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_publish_batch_wakes_once() {
+        use crate::waker::CallbackWaker;
+        use std::sync::atomic::AtomicUsize;
+
+        let bus = EventBus::new(EventBusConfig::default());
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&wakes);
+        let subscriber = bus
+            .subscribe(EventFilter::new().event_type("task.*"))
+            .with_waker(Box::new(CallbackWaker::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })));
+
+        bus.publish_batch(
+            (0..10)
+                .map(|i| Event::new("task.progress", serde_json::json!({ "n": i })))
+                .collect(),
+        );
+        assert_eq!(subscriber.pending(), 10);
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+
+        // A batch with nothing for the subscriber does not wake it
+        bus.publish_batch(vec![Event::new("log.info", serde_json::json!({}))]);
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_expired_events_are_skipped() {
+        let bus = EventBus::new(EventBusConfig::default());
+        let subscriber = bus.subscribe(EventFilter::new());
+
+        let mut stale = Event::progress("task-1", 1, 10, "").with_ttl(Duration::from_secs(5));
+        stale.timestamp -= Duration::from_secs(10);
+        assert!(stale.is_expired());
+        bus.publish(stale);
+        bus.publish(Event::progress("task-1", 2, 10, "").with_ttl(Duration::from_secs(5)));
+        bus.publish(Event::new("task.completed", serde_json::json!({})));
+
+        assert_eq!(subscriber.try_iter().count(), 2);
+        assert_eq!(bus.history(&EventFilter::new()).len(), 2);
+        assert_eq!(bus.history_after(None, &EventFilter::new()).len(), 2);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_subscribe_async_stream() {
+        use futures_core::Stream;
+        use std::pin::Pin;
+
+        async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+            std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+        }
+
+        let bus = EventBus::new(Default::default());
+        let mut events = bus.subscribe_async(EventFilter::new().event_type("task.*"));
+
+        let publisher = bus.publisher();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            publisher.publish(Event::new("log.line", serde_json::json!({})));
+            publisher.publish(Event::new("task.started", serde_json::json!({})));
+        });
+
+        let event = tokio::time::timeout(Duration::from_secs(5), next(&mut events))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.event_type, "task.started");
+
+        // Removing the subscription ends the stream
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            bus.unsubscribe_matching("*");
+        });
+        let end = tokio::time::timeout(Duration::from_secs(5), next(&mut events)).await;
+        assert_eq!(end.unwrap().map(|e| e.event_type), None);
+    }
 }
@@ -13,6 +13,10 @@
 //! - Line 3: Message type (request/response/event)
 //! - Line 4+: JSON payload
 //!
+//! Every message also carries a CRC-32 `checksum` of its other fields.
+//! Readers drop messages whose checksum does not match; messages without a
+//! checksum (from older writers) are accepted as-is.
+//!
 //! ## File Structure
 //!
 //! ```text
@@ -21,15 +25,44 @@
 //! ├── frontend_to_backend.json   # Frontend writes, Backend reads
 //! ├── backend_to_frontend.lock   # Lock file for atomic writes
 //! ├── frontend_to_backend.lock   # Lock file for atomic writes
+//! ├── backend_to_frontend.ack    # Last message the frontend has read
+//! ├── frontend_to_backend.ack    # Last message the backend has read
 //! └── .channel_info              # Channel metadata
 //! ```
+//!
+//! ## Durability
+//!
+//! Message files are never modified in place: writers build the new contents
+//! in a temporary file, `fsync` it and rename it over the original, so a
+//! crash mid-write leaves either the old or the new file, never a truncated
+//! one. Each reader records the last message it has read in an `.ack` file,
+//! and [`FileChannel::compact`] uses those to prune messages both sides have
+//! already seen.
+//!
+//! ## Change Notification
+//!
+//! By default [`FileChannel::recv`] reads the inbox on every call, so callers
+//! have to poll. After [`FileChannel::watch`] the channel watches its directory
+//! (inotify on Linux, ReadDirectoryChangesW on Windows, FSEvents on macOS,
+//! with a background metadata check where none is available) so that
+//! [`FileChannel::recv_wait`] blocks until the inbox changes and an
+//! [`EventLoopWaker`] set through [`WakeableChannel`] is woken as soon as the
+//! peer writes.
 
 use crate::error::{IpcError, Result};
+use crate::waker::{EventLoopWaker, WakeableChannel};
+use parking_lot::{Condvar, Mutex};
 use serde::{Deserialize, Serialize};
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Interval used to re-read the inbox when the channel is not watching.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// Message types for file-based IPC
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -61,6 +94,13 @@ pub struct FileMessage {
     /// Error message (for error responses)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// CRC-32 of the message, filled in when it is sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// Milliseconds after `timestamp` at which the message goes stale;
+    /// stale messages are dropped by [`FileChannel::recv`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
 }
 
 impl FileMessage {
@@ -74,6 +114,8 @@ impl FileMessage {
             method: Some(method.to_string()),
             payload,
             error: None,
+            checksum: None,
+            ttl_ms: None,
         }
     }
 
@@ -87,6 +129,8 @@ impl FileMessage {
             method: None,
             payload,
             error: None,
+            checksum: None,
+            ttl_ms: None,
         }
     }
 
@@ -100,6 +144,8 @@ impl FileMessage {
             method: None,
             payload: serde_json::Value::Null,
             error: Some(error.to_string()),
+            checksum: None,
+            ttl_ms: None,
         }
     }
 
@@ -113,10 +159,79 @@ impl FileMessage {
             method: Some(name.to_string()),
             payload,
             error: None,
+            checksum: None,
+            ttl_ms: None,
+        }
+    }
+
+    /// Set how long the message stays relevant, e.g. a few seconds for
+    /// progress updates a reconnecting reader should not replay.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl_ms = Some(ttl.as_millis() as u64);
+        self
+    }
+
+    /// Check whether the message's TTL has passed.
+    pub fn is_expired(&self) -> bool {
+        self.ttl_ms
+            .is_some_and(|ttl| self.timestamp.saturating_add(ttl) < current_timestamp_ms())
+    }
+
+    /// Compute the checksum of this message, ignoring any stored checksum.
+    pub fn compute_checksum(&self) -> String {
+        let unsealed = FileMessage {
+            checksum: None,
+            ..self.clone()
+        };
+        let bytes = serde_json::to_vec(&unsealed).unwrap_or_default();
+        format!("{:08x}", crc32(&bytes))
+    }
+
+    /// Check the stored checksum, if any.
+    ///
+    /// Messages without a checksum are considered valid.
+    pub fn verify_checksum(&self) -> bool {
+        match &self.checksum {
+            Some(checksum) => checksum.eq_ignore_ascii_case(&self.compute_checksum()),
+            None => true,
+        }
+    }
+
+    fn sealed(&self) -> Self {
+        FileMessage {
+            checksum: Some(self.compute_checksum()),
+            ..self.clone()
         }
     }
 }
 
+/// The last inbox message a reader has processed, persisted in `.ack` files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AckCursor {
+    timestamp: u64,
+    id: Option<String>,
+}
+
+impl AckCursor {
+    /// Get the index of the first unread message in a message file.
+    ///
+    /// Writers only ever append and drop from the front, so everything after
+    /// the cursor's message is unread. If that message has already been
+    /// dropped, anything older than the cursor's timestamp is considered read.
+    fn unread_from(&self, messages: &[FileMessage]) -> usize {
+        self.id
+            .as_ref()
+            .and_then(|id| messages.iter().position(|m| &m.id == id))
+            .map(|pos| pos + 1)
+            .unwrap_or_else(|| {
+                messages
+                    .iter()
+                    .take_while(|m| m.timestamp < self.timestamp)
+                    .count()
+            })
+    }
+}
+
 /// File-based IPC channel for backend (Python/Rust) side
 pub struct FileChannel {
     /// Channel directory
@@ -125,10 +240,12 @@ pub struct FileChannel {
     outbox_path: PathBuf,
     /// File for incoming messages (frontend -> backend)
     inbox_path: PathBuf,
-    /// Last processed message ID from inbox
-    last_inbox_id: Option<String>,
-    /// Last processed message timestamp
-    last_inbox_timestamp: u64,
+    /// Last processed message from inbox
+    cursor: AckCursor,
+    /// Inbox watcher, present once [`FileChannel::watch`] has been called
+    watcher: Option<InboxWatcher>,
+    /// Waker notified when the watcher sees the inbox change
+    waker: Option<Box<dyn EventLoopWaker>>,
 }
 
 impl FileChannel {
@@ -164,13 +281,17 @@ impl FileChannel {
                 "created": current_timestamp_ms(),
                 "protocol": "file-ipc"
             });
-            fs::write(&info_path, serde_json::to_string_pretty(&info).unwrap())?;
+            write_atomic(
+                &info_path,
+                serde_json::to_string_pretty(&info).unwrap(),
+                true,
+            )?;
         }
 
         // Initialize empty message files if not exist
         for path in [&outbox_path, &inbox_path] {
             if !path.exists() {
-                fs::write(path, "[]")?;
+                write_atomic(path, "[]", true)?;
             }
         }
 
@@ -178,8 +299,9 @@ impl FileChannel {
             dir,
             outbox_path,
             inbox_path,
-            last_inbox_id: None,
-            last_inbox_timestamp: 0,
+            cursor: AckCursor::default(),
+            watcher: None,
+            waker: None,
         })
     }
 
@@ -198,6 +320,31 @@ impl FileChannel {
         &self.dir
     }
 
+    /// Start watching the inbox for changes.
+    ///
+    /// Once watching, [`recv_wait`](Self::recv_wait), [`wait_response`](Self::wait_response)
+    /// and [`poll`](Self::poll) sleep until the peer writes instead of re-reading
+    /// the inbox on a timer, and the waker set through [`WakeableChannel`] is
+    /// woken on every change. Calling this again is a no-op.
+    pub fn watch(&mut self) -> Result<()> {
+        if self.watcher.is_none() {
+            let watcher = InboxWatcher::spawn(&self.inbox_path)?;
+            *watcher.state.waker.lock() = self.waker.clone();
+            self.watcher = Some(watcher);
+        }
+        Ok(())
+    }
+
+    /// Stop watching the inbox.
+    pub fn unwatch(&mut self) {
+        self.watcher = None;
+    }
+
+    /// Check if the inbox is being watched.
+    pub fn is_watching(&self) -> bool {
+        self.watcher.is_some()
+    }
+
     /// Send a message (write to outbox)
     pub fn send(&self, message: &FileMessage) -> Result<()> {
         let lock_path = self.outbox_path.with_extension("lock");
@@ -207,7 +354,7 @@ impl FileChannel {
         let mut messages = self.read_message_file(&self.outbox_path)?;
 
         // Add new message
-        messages.push(message.clone());
+        messages.push(message.sealed());
 
         // Keep only recent messages (last 100)
         if messages.len() > 100 {
@@ -215,14 +362,7 @@ impl FileChannel {
             messages = messages.into_iter().skip(skip_count).collect();
         }
 
-        // Write back atomically
-        let temp_path = self.outbox_path.with_extension("tmp");
-        let content = serde_json::to_string_pretty(&messages)
-            .map_err(|e| IpcError::serialization(e.to_string()))?;
-        fs::write(&temp_path, &content)?;
-        fs::rename(&temp_path, &self.outbox_path)?;
-
-        Ok(())
+        write_messages(&self.outbox_path, &messages)
     }
 
     /// Send a request and return the message ID
@@ -252,26 +392,58 @@ impl FileChannel {
     }
 
     /// Receive new messages from inbox
+    ///
+    /// Messages that fail their checksum or whose [TTL](FileMessage::with_ttl)
+    /// has passed are dropped. An inbox that cannot be
+    /// parsed (e.g. one written non-atomically by another implementation) is
+    /// treated as having no new messages and is re-read on the next call.
     pub fn recv(&mut self) -> Result<Vec<FileMessage>> {
-        let messages = self.read_message_file(&self.inbox_path)?;
+        let messages = match self.read_message_file(&self.inbox_path) {
+            Ok(messages) => messages,
+            Err(IpcError::Deserialization(e)) => {
+                tracing::debug!("skipping unreadable inbox {:?}: {e}", self.inbox_path);
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(e),
+        };
 
-        // Filter to only new messages
+        // Filter to only new, intact messages
+        let unread = self.cursor.unread_from(&messages);
         let new_messages: Vec<FileMessage> = messages
             .into_iter()
+            .skip(unread)
             .filter(|m| {
-                m.timestamp > self.last_inbox_timestamp
-                    || (m.timestamp == self.last_inbox_timestamp
-                        && self.last_inbox_id.as_ref() != Some(&m.id))
+                let valid = m.verify_checksum();
+                if !valid {
+                    tracing::warn!("dropping message {} with bad checksum", m.id);
+                }
+                valid
             })
             .collect();
 
-        // Update last processed
+        // Update last processed, expired messages included
         if let Some(last) = new_messages.last() {
-            self.last_inbox_timestamp = last.timestamp;
-            self.last_inbox_id = Some(last.id.clone());
+            self.cursor = AckCursor {
+                timestamp: last.timestamp,
+                id: Some(last.id.clone()),
+            };
+            // The ack only drives compaction, so losing it in a crash is harmless.
+            let ack = serde_json::to_string(&self.cursor).unwrap_or_default();
+            if let Err(e) = write_atomic(&self.inbox_path.with_extension("ack"), ack, false) {
+                tracing::warn!("failed to record inbox ack: {e}");
+            }
         }
 
-        Ok(new_messages)
+        Ok(new_messages
+            .into_iter()
+            .filter(|m| {
+                let expired = m.is_expired();
+                if expired {
+                    tracing::debug!("dropping expired message {}", m.id);
+                }
+                !expired
+            })
+            .collect())
     }
 
     /// Receive a single new message (non-blocking)
@@ -280,12 +452,35 @@ impl FileChannel {
         Ok(messages.into_iter().next())
     }
 
+    /// Wait up to `timeout` for new messages.
+    ///
+    /// Returns as soon as at least one new message is available, or an empty
+    /// list if the timeout elapses first. Without [`watch`](Self::watch) this
+    /// falls back to re-reading the inbox every 50ms.
+    pub fn recv_wait(&mut self, timeout: Duration) -> Result<Vec<FileMessage>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let seen = self.watcher.as_ref().map(|w| w.state.generation());
+            let messages = self.recv()?;
+            if !messages.is_empty() {
+                return Ok(messages);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(messages);
+            }
+            self.wait_change(seen, deadline - now, POLL_INTERVAL);
+        }
+    }
+
     /// Wait for a response to a specific request
     pub fn wait_response(&mut self, request_id: &str, timeout: Duration) -> Result<FileMessage> {
-        let start = std::time::Instant::now();
-        let poll_interval = Duration::from_millis(50);
+        let deadline = Instant::now() + timeout;
 
         loop {
+            let seen = self.watcher.as_ref().map(|w| w.state.generation());
             let messages = self.recv()?;
 
             for msg in messages {
@@ -296,20 +491,24 @@ impl FileChannel {
                 }
             }
 
-            if start.elapsed() > timeout {
+            let now = Instant::now();
+            if now >= deadline {
                 return Err(IpcError::Timeout);
             }
-
-            std::thread::sleep(poll_interval);
+            self.wait_change(seen, deadline - now, POLL_INTERVAL);
         }
     }
 
     /// Poll for new messages with a callback
+    ///
+    /// When watching, `interval` is ignored and the callback runs as soon as
+    /// the inbox changes.
     pub fn poll<F>(&mut self, interval: Duration, mut callback: F) -> Result<()>
     where
         F: FnMut(FileMessage) -> bool,
     {
         loop {
+            let seen = self.watcher.as_ref().map(|w| w.state.generation());
             let messages = self.recv()?;
 
             for msg in messages {
@@ -318,17 +517,83 @@ impl FileChannel {
                 }
             }
 
-            std::thread::sleep(interval);
+            self.wait_change(seen, Duration::MAX, interval);
+        }
+    }
+
+    /// Block until the watcher reports a change after `seen`, or sleep for
+    /// `interval` when not watching. Never waits longer than `timeout`.
+    fn wait_change(&self, seen: Option<u64>, timeout: Duration, interval: Duration) {
+        match (&self.watcher, seen) {
+            (Some(watcher), Some(seen)) => watcher.state.wait_past(seen, timeout),
+            _ => std::thread::sleep(interval.min(timeout)),
         }
     }
 
     /// Clear all messages in both inbox and outbox
     pub fn clear(&self) -> Result<()> {
-        fs::write(&self.outbox_path, "[]")?;
-        fs::write(&self.inbox_path, "[]")?;
+        for path in [&self.outbox_path, &self.inbox_path] {
+            let _lock = FileLock::acquire(&path.with_extension("lock"))?;
+            write_atomic(path, "[]", true)?;
+        }
         Ok(())
     }
 
+    /// Prune messages both sides have already read.
+    ///
+    /// Inbox messages up to this side's last [`recv`](Self::recv) and outbox
+    /// messages up to the peer's recorded ack are removed, along with
+    /// temporary files left behind by crashed writers. Returns the number of
+    /// messages removed.
+    pub fn compact(&self) -> Result<usize> {
+        let mut removed = self.prune(&self.inbox_path, &self.cursor)?;
+
+        let peer_ack = fs::read_to_string(self.outbox_path.with_extension("ack"))
+            .ok()
+            .and_then(|s| serde_json::from_str::<AckCursor>(&s).ok());
+        if let Some(peer_ack) = peer_ack {
+            removed += self.prune(&self.outbox_path, &peer_ack)?;
+        }
+
+        self.remove_stale_temp_files();
+        Ok(removed)
+    }
+
+    /// Remove messages at or before `cursor` from a message file.
+    fn prune(&self, path: &Path, cursor: &AckCursor) -> Result<usize> {
+        if cursor.id.is_none() {
+            return Ok(0);
+        }
+        let _lock = FileLock::acquire(&path.with_extension("lock"))?;
+        let messages = self.read_message_file(path)?;
+        let removed = cursor.unread_from(&messages);
+        if removed > 0 {
+            write_messages(path, &messages[removed..])?;
+        }
+        Ok(removed)
+    }
+
+    /// Delete `*.tmp` files in the channel directory older than a minute.
+    fn remove_stale_temp_files(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "tmp") {
+                continue;
+            }
+            let stale = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(|t| t.elapsed().unwrap_or_default() > Duration::from_secs(60))
+                .unwrap_or(false);
+            if stale {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+
     /// Read messages from a file
     fn read_message_file(&self, path: &Path) -> Result<Vec<FileMessage>> {
         if !path.exists() {
@@ -344,6 +609,384 @@ impl FileChannel {
     }
 }
 
+impl WakeableChannel for FileChannel {
+    /// Set the waker notified when the inbox changes.
+    ///
+    /// The waker is only invoked while the channel is [watching](FileChannel::watch).
+    fn set_waker(&mut self, waker: Box<dyn EventLoopWaker>) {
+        if let Some(watcher) = &self.watcher {
+            *watcher.state.waker.lock() = Some(waker.clone());
+        }
+        self.waker = Some(waker);
+    }
+
+    fn clear_waker(&mut self) {
+        if let Some(watcher) = &self.watcher {
+            *watcher.state.waker.lock() = None;
+        }
+        self.waker = None;
+    }
+
+    fn waker(&self) -> Option<&dyn EventLoopWaker> {
+        self.waker.as_deref()
+    }
+}
+
+/// State shared between a [`FileChannel`] and its watcher thread.
+#[derive(Default)]
+struct WatchState {
+    /// Bumped every time the inbox changes
+    generation: Mutex<u64>,
+    changed: Condvar,
+    waker: Mutex<Option<Box<dyn EventLoopWaker>>>,
+}
+
+impl WatchState {
+    fn generation(&self) -> u64 {
+        *self.generation.lock()
+    }
+
+    fn notify(&self) {
+        *self.generation.lock() += 1;
+        self.changed.notify_all();
+        if let Some(waker) = self.waker.lock().as_ref() {
+            if waker.is_valid() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Wait until the generation moves past `seen` or `timeout` elapses.
+    fn wait_past(&self, seen: u64, timeout: Duration) {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut generation = self.generation.lock();
+        while *generation == seen {
+            match deadline {
+                Some(deadline) => {
+                    if self
+                        .changed
+                        .wait_until(&mut generation, deadline)
+                        .timed_out()
+                    {
+                        break;
+                    }
+                }
+                None => self.changed.wait(&mut generation),
+            }
+        }
+    }
+}
+
+/// Background thread watching a channel's inbox file.
+///
+/// The thread stops when the watcher is dropped.
+struct InboxWatcher {
+    state: Arc<WatchState>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    stop: Option<std::os::unix::net::UnixStream>,
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    stop: Arc<std::sync::atomic::AtomicBool>,
+    /// Native change notification, watching until dropped; the thread only
+    /// runs without it
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    _notifier: Option<notify::RecommendedWatcher>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl InboxWatcher {
+    /// Watch the inbox with inotify on its parent directory.
+    ///
+    /// The directory is watched rather than the file itself because writers
+    /// replace the inbox with a rename, which would orphan a file watch.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn spawn(inbox: &Path) -> Result<Self> {
+        use std::ffi::CString;
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::net::UnixStream;
+
+        let dir = inbox.parent().unwrap_or(Path::new("."));
+        let name = inbox
+            .file_name()
+            .ok_or_else(|| IpcError::InvalidName(inbox.display().to_string()))?
+            .to_os_string();
+        let c_dir = CString::new(dir.as_os_str().as_bytes())
+            .map_err(|_| IpcError::InvalidName(dir.display().to_string()))?;
+
+        // SAFETY: inotify_init1 has no preconditions; the result is checked.
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // SAFETY: fd is a freshly created descriptor we exclusively own.
+        let inotify = unsafe { OwnedFd::from_raw_fd(fd) };
+        let mask = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE;
+        // SAFETY: c_dir is a valid NUL-terminated path.
+        if unsafe { libc::inotify_add_watch(inotify.as_raw_fd(), c_dir.as_ptr(), mask) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        let (stop, stop_rx) = UnixStream::pair()?;
+        let state = Arc::new(WatchState::default());
+        let thread_state = Arc::clone(&state);
+        let thread = std::thread::Builder::new()
+            .name("ipckit-file-watch".into())
+            .spawn(move || {
+                let mut buf = [0u8; 4096];
+                loop {
+                    let mut fds = [
+                        libc::pollfd {
+                            fd: inotify.as_raw_fd(),
+                            events: libc::POLLIN,
+                            revents: 0,
+                        },
+                        libc::pollfd {
+                            fd: stop_rx.as_raw_fd(),
+                            events: libc::POLLIN,
+                            revents: 0,
+                        },
+                    ];
+                    // SAFETY: fds is a valid array of two pollfd structs.
+                    let ready = unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) };
+                    if ready < 0 {
+                        if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted
+                        {
+                            continue;
+                        }
+                        break;
+                    }
+                    if fds[1].revents != 0 {
+                        break;
+                    }
+
+                    // SAFETY: buf is valid for writes of buf.len() bytes.
+                    let n = unsafe {
+                        libc::read(inotify.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len())
+                    };
+                    if n <= 0 {
+                        continue;
+                    }
+                    // An overflowed queue may have dropped the inbox's event
+                    if inotify_events(&buf[..n as usize])
+                        .any(|(mask, n)| mask & libc::IN_Q_OVERFLOW != 0 || n == name.as_bytes())
+                    {
+                        thread_state.notify();
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            state,
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+
+    /// Watch the inbox through the platform's change notification
+    /// (ReadDirectoryChangesW on Windows, FSEvents on macOS, kqueue on the
+    /// BSDs), falling back to polling its metadata if that is unavailable.
+    ///
+    /// As with inotify, the parent directory is watched so that renames
+    /// replacing the inbox are seen.
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn spawn(inbox: &Path) -> Result<Self> {
+        use notify::Watcher;
+        use std::sync::atomic::AtomicBool;
+
+        let dir = inbox.parent().unwrap_or(Path::new("."));
+        let name = inbox
+            .file_name()
+            .ok_or_else(|| IpcError::InvalidName(inbox.display().to_string()))?
+            .to_os_string();
+        let state = Arc::new(WatchState::default());
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handler_state = Arc::clone(&state);
+        let notifier = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            // A failed or overflowed watch may have missed the inbox changing
+            let changed = match event {
+                Ok(event) => {
+                    event.need_rescan()
+                        || event
+                            .paths
+                            .iter()
+                            .any(|path| path.file_name() == Some(name.as_os_str()))
+                }
+                Err(_) => true,
+            };
+            if changed {
+                handler_state.notify();
+            }
+        })
+        .and_then(|mut notifier| {
+            notifier.watch(dir, notify::RecursiveMode::NonRecursive)?;
+            Ok(notifier)
+        });
+
+        match notifier {
+            Ok(notifier) => Ok(Self {
+                state,
+                stop,
+                _notifier: Some(notifier),
+                thread: None,
+            }),
+            Err(e) => {
+                tracing::debug!("inbox notification unavailable, polling instead: {}", e);
+                let thread = Self::spawn_polling(inbox, Arc::clone(&state), Arc::clone(&stop))?;
+                Ok(Self {
+                    state,
+                    stop,
+                    _notifier: None,
+                    thread: Some(thread),
+                })
+            }
+        }
+    }
+
+    /// Watch the inbox by checking its modification time and size.
+    ///
+    /// Only metadata is read on each tick; the inbox itself is parsed once
+    /// per change by the channel.
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn spawn_polling(
+        inbox: &Path,
+        state: Arc<WatchState>,
+        stop: Arc<std::sync::atomic::AtomicBool>,
+    ) -> Result<JoinHandle<()>> {
+        let inbox = inbox.to_path_buf();
+        let stamp = |path: &Path| {
+            fs::metadata(path)
+                .ok()
+                .map(|m| (m.modified().ok(), m.len()))
+        };
+        let mut last = stamp(&inbox);
+
+        let thread = std::thread::Builder::new()
+            .name("ipckit-file-watch".into())
+            .spawn(move || {
+                while !stop.load(Ordering::Acquire) {
+                    std::thread::park_timeout(POLL_INTERVAL);
+                    let current = stamp(&inbox);
+                    if current != last {
+                        last = current;
+                        state.notify();
+                    }
+                }
+            })?;
+        Ok(thread)
+    }
+}
+
+impl Drop for InboxWatcher {
+    fn drop(&mut self) {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        drop(self.stop.take());
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        self.stop.store(true, Ordering::Release);
+
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Iterate over the masks and file names (empty for events without one,
+/// such as `IN_Q_OVERFLOW`) in a buffer of raw inotify events.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn inotify_events(buf: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        if offset + HEADER > buf.len() {
+            return None;
+        }
+        // SAFETY: the header lies within buf; read_unaligned handles alignment.
+        let event: libc::inotify_event =
+            unsafe { std::ptr::read_unaligned(buf[offset..].as_ptr().cast()) };
+        let start = offset + HEADER;
+        let end = (start + event.len as usize).min(buf.len());
+        offset = end;
+        let name = &buf[start..end];
+        let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        Some((event.mask, &name[..len]))
+    })
+}
+
+/// Serialize messages and atomically replace `path` with them.
+fn write_messages(path: &Path, messages: &[FileMessage]) -> Result<()> {
+    let content = serde_json::to_string_pretty(messages)
+        .map_err(|e| IpcError::serialization(e.to_string()))?;
+    write_atomic(path, content, true)
+}
+
+/// Replace `path` by writing a sibling temporary file and renaming it over.
+///
+/// With `durable`, the data and the directory entry are flushed to disk
+/// before returning so a crash cannot leave a truncated file behind.
+fn write_atomic(path: &Path, content: impl AsRef<[u8]>, durable: bool) -> Result<()> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+    let temp_path = path.with_extension(format!(
+        "{ext}.{}-{}.tmp",
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let written = (|| -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(content.as_ref())?;
+        if durable {
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, path)
+    })();
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+
+    #[cfg(unix)]
+    if durable {
+        if let Some(parent) = path.parent() {
+            let _ = fs::File::open(parent).and_then(|dir| dir.sync_all());
+        }
+    }
+    Ok(())
+}
+
+/// CRC-32 (IEEE 802.3) of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
 /// Simple file-based lock for atomic operations
 struct FileLock {
     path: PathBuf,
@@ -494,4 +1137,214 @@ mod tests {
 
         handle.join().unwrap();
     }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_checksum_rejects_corrupted_messages() {
+        let dir = tempdir().unwrap();
+        let backend = FileChannel::backend(dir.path()).unwrap();
+        let mut frontend = FileChannel::frontend(dir.path()).unwrap();
+
+        backend
+            .send_event("a", serde_json::json!({"n": 1}))
+            .unwrap();
+        backend
+            .send_event("b", serde_json::json!({"n": 2}))
+            .unwrap();
+
+        // Tamper with the first message's payload on disk.
+        let path = dir.path().join("backend_to_frontend.json");
+        let mut messages: Vec<FileMessage> =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(messages.iter().all(|m| m.checksum.is_some()));
+        messages[0].payload = serde_json::json!({"n": 99});
+        fs::write(&path, serde_json::to_string(&messages).unwrap()).unwrap();
+
+        let received = frontend.recv().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].method.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_expired_messages_are_dropped() {
+        let dir = tempdir().unwrap();
+        let backend = FileChannel::backend(dir.path()).unwrap();
+        let mut frontend = FileChannel::frontend(dir.path()).unwrap();
+
+        let mut stale = FileMessage::event("progress", serde_json::json!({"n": 1}))
+            .with_ttl(Duration::from_secs(5));
+        stale.timestamp -= 10_000;
+        assert!(stale.is_expired());
+        backend.send(&stale).unwrap();
+        backend
+            .send(
+                &FileMessage::event("progress", serde_json::json!({"n": 2}))
+                    .with_ttl(Duration::from_secs(5)),
+            )
+            .unwrap();
+
+        let received = frontend.recv().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].payload["n"], 2);
+        assert!(frontend.recv().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_truncated_inbox_is_retried() {
+        let dir = tempdir().unwrap();
+        let mut frontend = FileChannel::frontend(dir.path()).unwrap();
+        let path = dir.path().join("backend_to_frontend.json");
+
+        fs::write(&path, "[{\"id\": \"x\", \"times").unwrap();
+        assert!(frontend.recv().unwrap().is_empty());
+
+        let backend = FileChannel::backend(dir.path()).unwrap();
+        backend.clear().unwrap();
+        backend.send_event("ok", serde_json::json!(null)).unwrap();
+        assert_eq!(frontend.recv().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_compact_prunes_acknowledged_messages() {
+        let dir = tempdir().unwrap();
+        let mut backend = FileChannel::backend(dir.path()).unwrap();
+        let mut frontend = FileChannel::frontend(dir.path()).unwrap();
+
+        for i in 0..3 {
+            backend.send_event("tick", serde_json::json!(i)).unwrap();
+        }
+        frontend
+            .send_event("hello", serde_json::json!(null))
+            .unwrap();
+
+        // Nothing has been read yet.
+        assert_eq!(backend.compact().unwrap(), 0);
+
+        assert_eq!(frontend.recv().unwrap().len(), 3);
+        assert_eq!(backend.recv().unwrap().len(), 1);
+        backend.send_event("late", serde_json::json!(null)).unwrap();
+
+        // Backend prunes its read inbox and the outbox messages the frontend acked.
+        let stale = dir.path().join("backend_to_frontend.json.999.tmp");
+        fs::write(&stale, "junk").unwrap();
+        let old = SystemTime::now() - Duration::from_secs(120);
+        fs::File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        assert_eq!(backend.compact().unwrap(), 4);
+        assert!(!stale.exists());
+
+        let outbox: Vec<FileMessage> = serde_json::from_str(
+            &fs::read_to_string(dir.path().join("backend_to_frontend.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(outbox.len(), 1);
+        assert_eq!(outbox[0].method.as_deref(), Some("late"));
+
+        let received = frontend.recv().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].method.as_deref(), Some("late"));
+    }
+
+    #[test]
+    fn test_recv_wait_without_watch_times_out() {
+        let dir = tempdir().unwrap();
+        let mut frontend = FileChannel::frontend(dir.path()).unwrap();
+
+        let start = Instant::now();
+        let messages = frontend.recv_wait(Duration::from_millis(100)).unwrap();
+        assert!(messages.is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_recv_wait_watching() {
+        let dir = tempdir().unwrap();
+        let mut frontend = FileChannel::frontend(dir.path()).unwrap();
+        frontend.watch().unwrap();
+        assert!(frontend.is_watching());
+
+        let dir_path = dir.path().to_path_buf();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            let backend = FileChannel::backend(&dir_path).unwrap();
+            backend.send_event("ready", serde_json::json!({})).unwrap();
+        });
+
+        let messages = frontend.recv_wait(Duration::from_secs(5)).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].method.as_deref(), Some("ready"));
+        handle.join().unwrap();
+
+        frontend.unwatch();
+        assert!(!frontend.is_watching());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn test_inotify_events_report_overflow() {
+        let event = |mask: u32, name: &[u8]| {
+            let header = libc::inotify_event {
+                wd: 1,
+                mask,
+                cookie: 0,
+                len: name.len() as u32,
+            };
+            // SAFETY: inotify_event is plain old data
+            let mut bytes = unsafe {
+                std::slice::from_raw_parts(
+                    (&header as *const libc::inotify_event).cast::<u8>(),
+                    std::mem::size_of::<libc::inotify_event>(),
+                )
+            }
+            .to_vec();
+            bytes.extend_from_slice(name);
+            bytes
+        };
+        let mut buf = event(libc::IN_CLOSE_WRITE, b"inbox.json\0\0");
+        buf.extend(event(libc::IN_Q_OVERFLOW, b""));
+
+        let events: Vec<_> = inotify_events(&buf).collect();
+        assert_eq!(
+            events,
+            [
+                (libc::IN_CLOSE_WRITE, &b"inbox.json"[..]),
+                (libc::IN_Q_OVERFLOW, &b""[..])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_watch_wakes_waker() {
+        use crate::waker::CallbackWaker;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let dir = tempdir().unwrap();
+        let mut frontend = FileChannel::frontend(dir.path()).unwrap();
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&wakes);
+        frontend.set_waker(Box::new(CallbackWaker::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })));
+        frontend.watch().unwrap();
+
+        let backend = FileChannel::backend(dir.path()).unwrap();
+        backend.send_event("tick", serde_json::json!({})).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while wakes.load(Ordering::SeqCst) == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(wakes.load(Ordering::SeqCst) > 0);
+        assert_eq!(frontend.recv().unwrap().len(), 1);
+    }
 }
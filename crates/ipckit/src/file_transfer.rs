@@ -0,0 +1,733 @@
+//! File Transfer - Chunked, resumable file streaming over IPC
+//!
+//! Streams files as raw binary chunks instead of base64 inside JSON messages,
+//! so memory use stays bounded by the chunk size regardless of file size.
+//!
+//! # Features
+//!
+//! - Works over any [`FrameTransport`]: an [`IpcChannel`] or any byte stream
+//!   wrapped in [`StreamTransport`] (e.g. a [`LocalSocketStream`])
+//! - SHA-256 verification of the complete file before it is moved into place
+//! - Resume from offset: an interrupted transfer continues from the bytes the
+//!   receiver already has, as long as the file content is unchanged
+//! - Progress events on an [`EventBus`]
+//!
+//! # Protocol
+//!
+//! Every frame starts with a one-byte kind:
+//!
+//! 1. Sender → `OFFER` (JSON [`FileOffer`])
+//! 2. Receiver → `ACCEPT` (u64 resume offset)
+//! 3. Sender → `CHUNK`* (u64 offset + data), then `DONE`
+//! 4. Receiver → `RESULT` (JSON, whether the checksum matched)
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::{FileReceiver, FileSender, IpcChannel};
+//!
+//! // Receiving process
+//! let mut channel = IpcChannel::<Vec<u8>>::create("uploads").unwrap();
+//! channel.wait_for_client().unwrap();
+//! let received = FileReceiver::new("/tmp/inbox").receive(&mut channel).unwrap();
+//! println!("saved {:?}", received.path);
+//!
+//! // Sending process
+//! let mut channel = IpcChannel::<Vec<u8>>::connect("uploads").unwrap();
+//! FileSender::new("scene.usd").send(&mut channel).unwrap();
+//! ```
+//!
+//! [`IpcChannel`]: crate::channel::IpcChannel
+//! [`LocalSocketStream`]: crate::local_socket::LocalSocketStream
+//! [`EventBus`]: crate::event_stream::EventBus
+
+use crate::channel::{read_message, write_message, FrameLimits, IpcChannel};
+use crate::error::{IpcError, Result};
+use crate::event_stream::{event_types, Event, EventPublisher};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Default chunk size (256 KB)
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+const FRAME_OFFER: u8 = 1;
+const FRAME_ACCEPT: u8 = 2;
+const FRAME_CHUNK: u8 = 3;
+const FRAME_DONE: u8 = 4;
+const FRAME_RESULT: u8 = 5;
+
+/// A transport that carries discrete binary frames.
+pub trait FrameTransport {
+    /// Send one frame.
+    fn send_frame(&mut self, data: &[u8]) -> Result<()>;
+
+    /// Receive one frame.
+    fn recv_frame(&mut self) -> Result<Vec<u8>>;
+}
+
+impl FrameTransport for IpcChannel<Vec<u8>> {
+    fn send_frame(&mut self, data: &[u8]) -> Result<()> {
+        self.send_bytes(data)
+    }
+
+    fn recv_frame(&mut self) -> Result<Vec<u8>> {
+        self.recv_bytes()
+    }
+}
+
+/// Adapts any byte stream to [`FrameTransport`] using length-prefixed frames.
+pub struct StreamTransport<S> {
+    stream: S,
+    limits: FrameLimits,
+}
+
+impl<S: Read + Write> StreamTransport<S> {
+    /// Wrap a stream.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            limits: FrameLimits::default(),
+        }
+    }
+
+    /// Get the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Read + Write> FrameTransport for StreamTransport<S> {
+    fn send_frame(&mut self, data: &[u8]) -> Result<()> {
+        write_message(&mut self.stream, data, &self.limits, None)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    fn recv_frame(&mut self) -> Result<Vec<u8>> {
+        read_message(&mut self.stream, &self.limits, None)
+    }
+}
+
+/// Description of a file announced by the sender.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileOffer {
+    /// Transfer identifier, used as the resource ID of progress events
+    pub transfer_id: String,
+    /// File name (without directories)
+    pub name: String,
+    /// File size in bytes
+    pub size: u64,
+    /// Hex-encoded SHA-256 of the file content
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TransferResult {
+    ok: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Outcome of a completed send.
+#[derive(Debug, Clone)]
+pub struct SentFile {
+    /// The offer that was sent
+    pub offer: FileOffer,
+    /// Offset the receiver resumed from (0 for a fresh transfer)
+    pub resumed_from: u64,
+    /// Bytes actually sent in this session
+    pub bytes_sent: u64,
+}
+
+/// Outcome of a completed receive.
+#[derive(Debug, Clone)]
+pub struct ReceivedFile {
+    /// The offer that was received
+    pub offer: FileOffer,
+    /// Where the verified file was stored
+    pub path: PathBuf,
+    /// Offset the transfer resumed from (0 for a fresh transfer)
+    pub resumed_from: u64,
+}
+
+/// Sends a file over a [`FrameTransport`].
+pub struct FileSender {
+    path: PathBuf,
+    name: Option<String>,
+    transfer_id: Option<String>,
+    chunk_size: usize,
+    events: Option<EventPublisher>,
+}
+
+impl FileSender {
+    /// Create a sender for the file at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            name: None,
+            transfer_id: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            events: None,
+        }
+    }
+
+    /// Override the file name announced to the receiver.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Set the transfer ID (defaults to the file name and checksum).
+    pub fn transfer_id(mut self, id: &str) -> Self {
+        self.transfer_id = Some(id.to_string());
+        self
+    }
+
+    /// Set the chunk size.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+
+    /// Publish progress events to an event bus.
+    pub fn events(mut self, publisher: EventPublisher) -> Self {
+        self.events = Some(publisher);
+        self
+    }
+
+    /// Send the file, resuming wherever the receiver left off.
+    pub fn send<T: FrameTransport + ?Sized>(&self, transport: &mut T) -> Result<SentFile> {
+        let mut file = File::open(&self.path)?;
+        let size = file.metadata()?.len();
+        let sha256 = sha256_reader(&mut file)?;
+
+        let name = match self.name {
+            Some(ref name) => name.clone(),
+            None => self
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .ok_or_else(|| IpcError::InvalidName(format!("{:?}", self.path)))?,
+        };
+        let offer = FileOffer {
+            transfer_id: self
+                .transfer_id
+                .clone()
+                .unwrap_or_else(|| format!("{}-{}", name, &sha256[..12])),
+            name,
+            size,
+            sha256,
+        };
+
+        let result = self.stream(transport, &mut file, &offer);
+        if let Err(ref e) = result {
+            publish(
+                &self.events,
+                event_types::FILE_TRANSFER_FAILED,
+                &offer,
+                0,
+                Some(e),
+            );
+        }
+        result
+    }
+
+    fn stream<T: FrameTransport + ?Sized>(
+        &self,
+        transport: &mut T,
+        file: &mut File,
+        offer: &FileOffer,
+    ) -> Result<SentFile> {
+        transport.send_frame(&encode_json(FRAME_OFFER, offer)?)?;
+
+        let accept = expect_frame(transport.recv_frame()?, FRAME_ACCEPT)?;
+        let resumed_from = read_u64(&accept)?;
+        if resumed_from > offer.size {
+            return Err(IpcError::InvalidState(format!(
+                "receiver resumed at {} beyond file size {}",
+                resumed_from, offer.size
+            )));
+        }
+
+        file.seek(SeekFrom::Start(resumed_from))?;
+        let mut offset = resumed_from;
+        let mut frame = Vec::with_capacity(9 + self.chunk_size);
+        let mut buf = vec![0u8; self.chunk_size];
+        loop {
+            let n = read_full(file, &mut buf)?;
+            if n == 0 {
+                break;
+            }
+            frame.clear();
+            frame.push(FRAME_CHUNK);
+            frame.extend_from_slice(&offset.to_le_bytes());
+            frame.extend_from_slice(&buf[..n]);
+            transport.send_frame(&frame)?;
+            offset += n as u64;
+            publish(
+                &self.events,
+                event_types::FILE_UPLOAD_PROGRESS,
+                offer,
+                offset,
+                None,
+            );
+        }
+
+        if offset != offer.size {
+            return Err(IpcError::InvalidState(format!(
+                "{} changed size during transfer",
+                offer.name
+            )));
+        }
+        transport.send_frame(&[FRAME_DONE])?;
+
+        let result: TransferResult =
+            decode_json(&expect_frame(transport.recv_frame()?, FRAME_RESULT)?)?;
+        if !result.ok {
+            return Err(IpcError::Other(format!(
+                "receiver rejected {}: {}",
+                offer.name,
+                result.error.unwrap_or_default()
+            )));
+        }
+
+        publish(
+            &self.events,
+            event_types::FILE_TRANSFER_COMPLETED,
+            offer,
+            offer.size,
+            None,
+        );
+        Ok(SentFile {
+            offer: offer.clone(),
+            resumed_from,
+            bytes_sent: offer.size - resumed_from,
+        })
+    }
+}
+
+/// Receives files sent by a [`FileSender`] into a directory.
+///
+/// Data is written to `<name>.<checksum>.part` next to the destination and
+/// only renamed to `<name>` after the checksum matches. A later transfer of
+/// the same content resumes from the partial file.
+pub struct FileReceiver {
+    dir: PathBuf,
+    resume: bool,
+    events: Option<EventPublisher>,
+}
+
+impl FileReceiver {
+    /// Create a receiver that stores files in `dir`.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            resume: true,
+            events: None,
+        }
+    }
+
+    /// Enable or disable resuming from partial files (enabled by default).
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Publish progress events to an event bus.
+    pub fn events(mut self, publisher: EventPublisher) -> Self {
+        self.events = Some(publisher);
+        self
+    }
+
+    /// Receive one file.
+    pub fn receive<T: FrameTransport + ?Sized>(&self, transport: &mut T) -> Result<ReceivedFile> {
+        let offer: FileOffer = decode_json(&expect_frame(transport.recv_frame()?, FRAME_OFFER)?)?;
+        let result = self.receive_offer(transport, &offer);
+
+        match result {
+            Ok(ref received) => {
+                transport.send_frame(&encode_json(
+                    FRAME_RESULT,
+                    &TransferResult {
+                        ok: true,
+                        error: None,
+                    },
+                )?)?;
+                publish(
+                    &self.events,
+                    event_types::FILE_TRANSFER_COMPLETED,
+                    &received.offer,
+                    offer.size,
+                    None,
+                );
+            }
+            Err(ref e) => {
+                // Best effort: the transport itself may be what failed
+                let _ = transport.send_frame(&encode_json(
+                    FRAME_RESULT,
+                    &TransferResult {
+                        ok: false,
+                        error: Some(e.to_string()),
+                    },
+                )?);
+                publish(
+                    &self.events,
+                    event_types::FILE_TRANSFER_FAILED,
+                    &offer,
+                    0,
+                    Some(e),
+                );
+            }
+        }
+        result
+    }
+
+    fn receive_offer<T: FrameTransport + ?Sized>(
+        &self,
+        transport: &mut T,
+        offer: &FileOffer,
+    ) -> Result<ReceivedFile> {
+        // Never let the sender pick a path outside the target directory
+        let name = Path::new(&offer.name)
+            .file_name()
+            .filter(|n| *n == std::ffi::OsStr::new(&offer.name))
+            .ok_or_else(|| IpcError::InvalidName(offer.name.clone()))?;
+        if offer.sha256.len() != 64 || !offer.sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(IpcError::InvalidState(format!(
+                "invalid checksum '{}'",
+                offer.sha256
+            )));
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(name);
+        let part = self
+            .dir
+            .join(format!("{}.{}.part", offer.name, &offer.sha256[..16]));
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&part)?;
+        let existing = file.metadata()?.len();
+        let resumed_from = if self.resume && existing <= offer.size {
+            existing
+        } else {
+            0
+        };
+        file.set_len(resumed_from)?;
+        file.seek(SeekFrom::Start(resumed_from))?;
+
+        transport.send_frame(&[&[FRAME_ACCEPT][..], &resumed_from.to_le_bytes()].concat())?;
+
+        let mut offset = resumed_from;
+        loop {
+            let frame = transport.recv_frame()?;
+            match frame.first() {
+                Some(&FRAME_CHUNK) => {
+                    let chunk_offset = read_u64(&frame[1..])?;
+                    let data = &frame[9..];
+                    if chunk_offset != offset || offset + data.len() as u64 > offer.size {
+                        return Err(IpcError::InvalidState(format!(
+                            "unexpected chunk at {} ({} bytes), expected offset {}",
+                            chunk_offset,
+                            data.len(),
+                            offset
+                        )));
+                    }
+                    file.write_all(data)?;
+                    offset += data.len() as u64;
+                    publish(
+                        &self.events,
+                        event_types::FILE_DOWNLOAD_PROGRESS,
+                        offer,
+                        offset,
+                        None,
+                    );
+                }
+                Some(&FRAME_DONE) => break,
+                _ => {
+                    return Err(IpcError::InvalidState(
+                        "unexpected frame during transfer".into(),
+                    ))
+                }
+            }
+        }
+
+        if offset != offer.size {
+            return Err(IpcError::InvalidState(format!(
+                "transfer ended at {} of {} bytes",
+                offset, offer.size
+            )));
+        }
+
+        file.flush()?;
+        file.seek(SeekFrom::Start(0))?;
+        let actual = sha256_reader(&mut file)?;
+        drop(file);
+        if !actual.eq_ignore_ascii_case(&offer.sha256) {
+            // The partial data is unusable for a resume too
+            let _ = fs::remove_file(&part);
+            return Err(IpcError::InvalidState(format!(
+                "checksum mismatch for {}: expected {}, got {}",
+                offer.name, offer.sha256, actual
+            )));
+        }
+
+        fs::rename(&part, &path)?;
+        Ok(ReceivedFile {
+            offer: offer.clone(),
+            path,
+            resumed_from,
+        })
+    }
+}
+
+fn publish(
+    events: &Option<EventPublisher>,
+    event_type: &str,
+    offer: &FileOffer,
+    bytes: u64,
+    error: Option<&IpcError>,
+) {
+    if let Some(ref publisher) = events {
+        let mut data = serde_json::json!({
+            "name": offer.name,
+            "bytes": bytes,
+            "total": offer.size,
+        });
+        if let Some(e) = error {
+            data["error"] = serde_json::json!(e.to_string());
+        }
+        publisher.publish(Event::with_resource(event_type, &offer.transfer_id, data));
+    }
+}
+
+fn encode_json<T: Serialize>(kind: u8, value: &T) -> Result<Vec<u8>> {
+    let mut frame = vec![kind];
+    serde_json::to_writer(&mut frame, value).map_err(|e| IpcError::serialization(e.to_string()))?;
+    Ok(frame)
+}
+
+fn decode_json<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T> {
+    serde_json::from_slice(data).map_err(|e| IpcError::deserialization(e.to_string()))
+}
+
+/// Check the frame kind and return the frame body.
+fn expect_frame(frame: Vec<u8>, kind: u8) -> Result<Vec<u8>> {
+    match frame.first() {
+        Some(&k) if k == kind => Ok(frame[1..].to_vec()),
+        Some(&k) => Err(IpcError::InvalidState(format!(
+            "expected frame kind {}, got {}",
+            kind, k
+        ))),
+        None => Err(IpcError::InvalidState("empty frame".into())),
+    }
+}
+
+fn read_u64(data: &[u8]) -> Result<u64> {
+    data.get(..8)
+        .and_then(|b| b.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or_else(|| IpcError::InvalidState("truncated frame".into()))
+}
+
+/// Fill `buf` as far as possible, returning fewer bytes only at end of file.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// Hex-encoded SHA-256 of everything remaining in `reader`.
+fn sha256_reader<R: Read>(reader: &mut R) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = read_full(reader, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_stream::{EventBus, EventFilter};
+    use std::sync::mpsc;
+
+    /// In-memory transport: one end of a pair of frame queues.
+    struct Loopback {
+        tx: mpsc::Sender<Vec<u8>>,
+        rx: mpsc::Receiver<Vec<u8>>,
+        /// Fail after sending this many chunk frames
+        chunk_budget: Option<usize>,
+    }
+
+    fn loopback() -> (Loopback, Loopback) {
+        let (a_tx, a_rx) = mpsc::channel();
+        let (b_tx, b_rx) = mpsc::channel();
+        (
+            Loopback {
+                tx: a_tx,
+                rx: b_rx,
+                chunk_budget: None,
+            },
+            Loopback {
+                tx: b_tx,
+                rx: a_rx,
+                chunk_budget: None,
+            },
+        )
+    }
+
+    impl FrameTransport for Loopback {
+        fn send_frame(&mut self, data: &[u8]) -> Result<()> {
+            if data.first() == Some(&FRAME_CHUNK) {
+                match self.chunk_budget {
+                    Some(0) => return Err(IpcError::Closed),
+                    Some(ref mut n) => *n -= 1,
+                    None => {}
+                }
+            }
+            self.tx.send(data.to_vec()).map_err(|_| IpcError::Closed)
+        }
+
+        fn recv_frame(&mut self) -> Result<Vec<u8>> {
+            self.rx.recv().map_err(|_| IpcError::Closed)
+        }
+    }
+
+    fn hex(data: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    #[test]
+    fn test_sha256_reader() {
+        assert_eq!(
+            sha256_reader(&mut &b"abc"[..]).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        // Input spanning several read buffers hashes like one-shot input
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i * 7) as u8).collect();
+        assert_eq!(sha256_reader(&mut &data[..]).unwrap(), hex(&data));
+    }
+
+    #[test]
+    fn test_transfer_resumes_after_interruption() {
+        let src_dir = tempfile::tempdir().unwrap();
+        let dst_dir = tempfile::tempdir().unwrap();
+        let src = src_dir.path().join("scene.bin");
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 253) as u8).collect();
+        fs::write(&src, &data).unwrap();
+
+        let bus = EventBus::default();
+        let progress = bus.subscribe(EventFilter::new().event_type("file.*"));
+
+        // First attempt dies after three chunks
+        let (mut sender_end, mut receiver_end) = loopback();
+        sender_end.chunk_budget = Some(3);
+        let receiver = FileReceiver::new(dst_dir.path());
+        let handle = std::thread::spawn(move || receiver.receive(&mut receiver_end));
+        let sender = FileSender::new(&src)
+            .chunk_size(1000)
+            .events(bus.publisher());
+        assert!(sender.send(&mut sender_end).is_err());
+        drop(sender_end);
+        assert!(handle.join().unwrap().is_err());
+        assert!(!dst_dir.path().join("scene.bin").exists());
+
+        // Second attempt picks up where the first stopped
+        let (mut sender_end, mut receiver_end) = loopback();
+        let receiver = FileReceiver::new(dst_dir.path()).events(bus.publisher());
+        let handle = std::thread::spawn(move || receiver.receive(&mut receiver_end));
+        let sent = sender.send(&mut sender_end).unwrap();
+        let received = handle.join().unwrap().unwrap();
+
+        assert_eq!(sent.resumed_from, 3000);
+        assert_eq!(sent.bytes_sent, 7000);
+        assert_eq!(received.resumed_from, 3000);
+        assert_eq!(fs::read(&received.path).unwrap(), data);
+        assert_eq!(received.offer.sha256, hex(&data));
+
+        let events: Vec<Event> = progress.try_iter().collect();
+        assert!(events
+            .iter()
+            .any(|e| e.event_type == event_types::FILE_TRANSFER_FAILED));
+        let last_upload = events
+            .iter()
+            .rfind(|e| e.event_type == event_types::FILE_UPLOAD_PROGRESS)
+            .unwrap();
+        assert_eq!(last_upload.data["bytes"], 10_000);
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| e.event_type == event_types::FILE_TRANSFER_COMPLETED)
+                .count(),
+            2,
+            "both ends report completion"
+        );
+    }
+
+    #[test]
+    fn test_receiver_rejects_bad_names_and_checksums() {
+        let dst_dir = tempfile::tempdir().unwrap();
+
+        for (name, sha256) in [
+            ("../escape.txt", "0".repeat(64)),
+            ("data.txt", "0".repeat(64)),
+        ] {
+            let (mut sender_end, mut receiver_end) = loopback();
+            let offer = FileOffer {
+                transfer_id: "t".to_string(),
+                name: name.to_string(),
+                size: 3,
+                sha256,
+            };
+            sender_end
+                .send_frame(&encode_json(FRAME_OFFER, &offer).unwrap())
+                .unwrap();
+            if name == "data.txt" {
+                sender_end
+                    .send_frame(&[FRAME_CHUNK, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3])
+                    .unwrap();
+                sender_end.send_frame(&[FRAME_DONE]).unwrap();
+            }
+
+            let err = FileReceiver::new(dst_dir.path())
+                .receive(&mut receiver_end)
+                .unwrap_err();
+            match name {
+                "data.txt" => assert!(err.to_string().contains("checksum mismatch")),
+                _ => assert!(matches!(err, IpcError::InvalidName(_))),
+            }
+        }
+        assert_eq!(fs::read_dir(dst_dir.path()).unwrap().count(), 0);
+    }
+}
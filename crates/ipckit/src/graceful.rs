@@ -34,6 +34,12 @@
 //! # Ok::<(), IpcError>(())
 //! ```
 //!
+//! # Signal handling
+//!
+//! Daemons can hand a [`ShutdownGroup`] to [`install_signal_handlers`], which
+//! shuts it down with a deadline on SIGINT/SIGTERM (Unix) or console control
+//! events such as Ctrl+C (Windows).
+//!
 //! # Example
 //!
 //! ```rust,no_run
@@ -88,13 +94,27 @@ pub trait GracefulChannel {
     fn shutdown_timeout(&self, timeout: Duration) -> Result<()>;
 }
 
+/// Callback run when shutdown is signaled
+type ShutdownHook = Box<dyn Fn() + Send + Sync>;
+
 /// Shutdown state that can be shared between channel instances
-#[derive(Debug)]
 pub struct ShutdownState {
     /// Whether shutdown has been signaled
     shutdown: AtomicBool,
     /// Number of pending operations
     pending_count: AtomicUsize,
+    /// Callbacks run once when shutdown is signaled
+    hooks: parking_lot::Mutex<Vec<ShutdownHook>>,
+}
+
+impl std::fmt::Debug for ShutdownState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownState")
+            .field("shutdown", &self.shutdown)
+            .field("pending_count", &self.pending_count)
+            .field("hooks", &self.hooks.lock().len())
+            .finish()
+    }
 }
 
 impl Default for ShutdownState {
@@ -109,12 +129,38 @@ impl ShutdownState {
         Self {
             shutdown: AtomicBool::new(false),
             pending_count: AtomicUsize::new(0),
+            hooks: parking_lot::Mutex::new(Vec::new()),
         }
     }
 
     /// Signal shutdown
+    ///
+    /// Runs the [`on_shutdown`](Self::on_shutdown) hooks the first time it
+    /// is called.
     pub fn shutdown(&self) {
-        self.shutdown.store(true, Ordering::SeqCst);
+        let hooks = self.hooks.lock();
+        if !self.shutdown.swap(true, Ordering::SeqCst) {
+            for hook in hooks.iter() {
+                hook();
+            }
+        }
+    }
+
+    /// Register a callback to run when shutdown is signaled, such as one
+    /// that unblocks a thread waiting in a read.
+    ///
+    /// Runs immediately if shutdown has already been signaled.
+    pub fn on_shutdown<F>(&self, hook: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let mut hooks = self.hooks.lock();
+        if self.is_shutdown() {
+            drop(hooks);
+            hook();
+        } else {
+            hooks.push(Box::new(hook));
+        }
     }
 
     /// Check if shutdown has been signaled
@@ -265,15 +311,17 @@ pub struct GracefulNamedPipe {
 
 impl GracefulNamedPipe {
     /// Create a new graceful named pipe wrapper
+    ///
+    /// Signaling shutdown cancels a read blocked on the pipe, which then
+    /// fails with [`ErrorKind::ConnectionAborted`](std::io::ErrorKind::ConnectionAborted).
     pub fn new(pipe: NamedPipe) -> Self {
-        Self {
-            inner: pipe,
-            state: Arc::new(ShutdownState::new()),
-        }
+        Self::with_state(pipe, Arc::new(ShutdownState::new()))
     }
 
     /// Create a new graceful named pipe with a shared shutdown state
     pub fn with_state(pipe: NamedPipe, state: Arc<ShutdownState>) -> Self {
+        let canceller = pipe.canceller();
+        state.on_shutdown(move || canceller.cancel());
         Self { inner: pipe, state }
     }
 
@@ -575,7 +623,9 @@ impl Default for ReentrantDispatch {
 // GracefulIpcChannel - IPC channel with graceful shutdown
 // ============================================================================
 
+use crate::buffer_pool::BufferPool;
 use crate::channel::IpcChannel;
+use bytes::Bytes;
 use serde::{de::DeserializeOwned, Serialize};
 use std::marker::PhantomData;
 
@@ -721,7 +771,7 @@ impl<T> GracefulChannel for GracefulIpcChannel<T> {
 
 impl GracefulIpcChannel<Vec<u8>> {
     /// Send raw bytes
-    pub fn send_bytes(&mut self, data: &[u8]) -> Result<()> {
+    pub fn send_bytes(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
         if self.state.is_shutdown() {
             return Err(IpcError::Closed);
         }
@@ -739,6 +789,16 @@ impl GracefulIpcChannel<Vec<u8>> {
         let _guard = self.state.begin_operation()?;
         self.inner.recv_bytes()
     }
+
+    /// Receive raw bytes into a buffer taken from `pool`
+    pub fn recv_pooled(&mut self, pool: &BufferPool) -> Result<Bytes> {
+        if self.state.is_shutdown() {
+            return Err(IpcError::Closed);
+        }
+
+        let _guard = self.state.begin_operation()?;
+        self.inner.recv_pooled(pool)
+    }
 }
 
 impl<T: Serialize + DeserializeOwned> GracefulIpcChannel<T> {
@@ -763,6 +823,453 @@ impl<T: Serialize + DeserializeOwned> GracefulIpcChannel<T> {
     }
 }
 
+// ============================================================================
+// ShutdownGroup - coordinated shutdown of several channels
+// ============================================================================
+
+/// A resource managed by a [`ShutdownGroup`].
+enum GroupMember {
+    Channel(Arc<dyn GracefulChannel + Send + Sync>),
+    Hook(Box<dyn Fn(Duration) -> Result<()> + Send + Sync>),
+}
+
+/// Outcome of [`ShutdownGroup::shutdown_all`].
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    /// Members that drained within the deadline
+    pub drained: Vec<String>,
+    /// Members that were still busy when the deadline expired
+    pub timed_out: Vec<String>,
+    /// Members whose drain failed, as `(name, error message)` pairs
+    pub failed: Vec<(String, String)>,
+    /// Total time spent shutting down
+    pub elapsed: Duration,
+}
+
+impl ShutdownReport {
+    /// Check whether every member drained cleanly
+    pub fn is_clean(&self) -> bool {
+        self.timed_out.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// Coordinates the shutdown of several graceful channels
+///
+/// Members are grouped into stages. [`shutdown_all`](Self::shutdown_all)
+/// signals and drains each stage in ascending order before moving on to the
+/// next, with all stages sharing a single deadline. Members within a stage
+/// drain concurrently.
+///
+/// ```rust,ignore
+/// let mut group = ShutdownGroup::new();
+/// group
+///     .add("socket", 0, Arc::new(server))   // stop accepting first
+///     .add("pipe", 1, Arc::new(pipe))
+///     .add_hook("events", 2, move |_| { bus.clear_history(); Ok(()) });
+///
+/// let report = group.shutdown_all(Duration::from_secs(5));
+/// for name in &report.timed_out {
+///     eprintln!("{} did not drain in time", name);
+/// }
+/// ```
+#[derive(Default)]
+pub struct ShutdownGroup {
+    members: Vec<(String, u32, GroupMember)>,
+}
+
+impl ShutdownGroup {
+    /// Create an empty group
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a channel to the given stage
+    pub fn add(
+        &mut self,
+        name: &str,
+        stage: u32,
+        channel: Arc<dyn GracefulChannel + Send + Sync>,
+    ) -> &mut Self {
+        self.members
+            .push((name.to_string(), stage, GroupMember::Channel(channel)));
+        self
+    }
+
+    /// Add a shutdown hook to the given stage
+    ///
+    /// The hook receives the time left until the deadline and is useful for
+    /// resources that do not implement [`GracefulChannel`].
+    pub fn add_hook<F>(&mut self, name: &str, stage: u32, hook: F) -> &mut Self
+    where
+        F: Fn(Duration) -> Result<()> + Send + Sync + 'static,
+    {
+        self.members
+            .push((name.to_string(), stage, GroupMember::Hook(Box::new(hook))));
+        self
+    }
+
+    /// Get the number of members
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Check if the group has no members
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Get member names in shutdown order
+    pub fn names(&self) -> Vec<&str> {
+        let mut members: Vec<_> = self.members.iter().collect();
+        members.sort_by_key(|(_, stage, _)| *stage);
+        members.iter().map(|(name, _, _)| name.as_str()).collect()
+    }
+
+    /// Shut down every member, stage by stage, within `timeout`
+    pub fn shutdown_all(&self, timeout: Duration) -> ShutdownReport {
+        let start = Instant::now();
+        let deadline = start + timeout;
+        let mut report = ShutdownReport::default();
+
+        let mut stages: Vec<u32> = self.members.iter().map(|(_, stage, _)| *stage).collect();
+        stages.sort_unstable();
+        stages.dedup();
+
+        for stage in stages {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let results: Vec<(&str, Result<()>)> = std::thread::scope(|scope| {
+                let handles: Vec<_> = self
+                    .members
+                    .iter()
+                    .filter(|(_, s, _)| *s == stage)
+                    .map(|(name, _, member)| {
+                        let handle = scope.spawn(move || match member {
+                            GroupMember::Channel(channel) => channel.shutdown_timeout(remaining),
+                            GroupMember::Hook(hook) => hook(remaining),
+                        });
+                        (name.as_str(), handle)
+                    })
+                    .collect();
+
+                handles
+                    .into_iter()
+                    .map(|(name, handle)| {
+                        let result = handle.join().unwrap_or_else(|_| {
+                            Err(IpcError::Other("shutdown panicked".to_string()))
+                        });
+                        (name, result)
+                    })
+                    .collect()
+            });
+
+            for (name, result) in results {
+                match result {
+                    Ok(()) => report.drained.push(name.to_string()),
+                    Err(IpcError::Timeout) => report.timed_out.push(name.to_string()),
+                    Err(e) => report.failed.push((name.to_string(), e.to_string())),
+                }
+            }
+        }
+
+        report.elapsed = start.elapsed();
+        report
+    }
+}
+
+impl GracefulChannel for ShutdownGroup {
+    /// Signal every channel member at once, without draining or running hooks
+    fn shutdown(&self) {
+        for (_, _, member) in &self.members {
+            if let GroupMember::Channel(channel) = member {
+                channel.shutdown();
+            }
+        }
+    }
+
+    fn is_shutdown(&self) -> bool {
+        self.members.iter().all(|(_, _, member)| match member {
+            GroupMember::Channel(channel) => channel.is_shutdown(),
+            GroupMember::Hook(_) => true,
+        })
+    }
+
+    fn drain(&self) -> Result<()> {
+        for (_, _, member) in &self.members {
+            if let GroupMember::Channel(channel) = member {
+                channel.drain()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn shutdown_timeout(&self, timeout: Duration) -> Result<()> {
+        let report = self.shutdown_all(timeout);
+        if let Some((_, error)) = report.failed.first() {
+            return Err(IpcError::Other(error.clone()));
+        }
+        if !report.timed_out.is_empty() {
+            return Err(IpcError::Timeout);
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Signal handling - shut a ShutdownGroup down on SIGINT/SIGTERM or Ctrl+C
+// ============================================================================
+
+/// Whether [`install_signal_handlers`] has been called in this process
+static SIGNALS_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Shut `group` down when the process is asked to stop
+///
+/// Installs handlers for SIGINT and SIGTERM on Unix, and for console control
+/// events (Ctrl+C, Ctrl+Break, closing the console window, logoff and
+/// system shutdown) on Windows. The first of these runs
+/// [`ShutdownGroup::shutdown_all`] with `deadline` on a background thread and
+/// restores the default handlers, so a second signal terminates the process
+/// as usual.
+///
+/// Handlers can be installed once per process; later calls return
+/// `IpcError::AlreadyExists`.
+///
+/// ```rust,no_run
+/// use ipckit::graceful::{install_signal_handlers, ShutdownGroup};
+/// use ipckit::{FnHandler, SocketServer};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let server = Arc::new(SocketServer::at("my_daemon")?);
+/// let mut group = ShutdownGroup::new();
+/// group.add("socket", 0, server.clone());
+/// let signals = install_signal_handlers(group, Duration::from_secs(10))?;
+///
+/// // Returns once a signal has stopped the server
+/// server.run(FnHandler::new(|_conn, msg| Ok(Some(msg))))?;
+/// let report = signals.wait();
+/// # Ok::<(), ipckit::IpcError>(())
+/// ```
+pub fn install_signal_handlers(group: ShutdownGroup, deadline: Duration) -> Result<SignalShutdown> {
+    if SIGNALS_INSTALLED.swap(true, Ordering::SeqCst) {
+        return Err(IpcError::AlreadyExists(
+            "signal handlers are already installed".to_string(),
+        ));
+    }
+    if let Err(e) = signal_sys::install() {
+        SIGNALS_INSTALLED.store(false, Ordering::SeqCst);
+        return Err(e);
+    }
+
+    let triggered = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&triggered);
+    let thread = std::thread::Builder::new()
+        .name("ipckit-signals".to_string())
+        .spawn(move || {
+            let signal = signal_sys::wait();
+            signal_sys::restore();
+            flag.store(true, Ordering::SeqCst);
+            tracing::info!("Received {}, shutting down", signal_sys::name(signal));
+
+            let report = group.shutdown_all(deadline);
+            if !report.is_clean() {
+                tracing::warn!(
+                    "Shutdown incomplete: timed out {:?}, failed {:?}",
+                    report.timed_out,
+                    report.failed
+                );
+            }
+            signal_sys::finished();
+            report
+        })?;
+
+    Ok(SignalShutdown {
+        triggered,
+        thread: Some(thread),
+    })
+}
+
+/// Handle returned by [`install_signal_handlers`]
+#[derive(Debug)]
+pub struct SignalShutdown {
+    triggered: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<ShutdownReport>>,
+}
+
+impl SignalShutdown {
+    /// Start the shutdown as if a signal had been received
+    pub fn trigger(&self) {
+        signal_sys::notify(0);
+    }
+
+    /// Check whether a signal (or [`trigger`](Self::trigger)) has started
+    /// the shutdown
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+
+    /// Block until a signal has been received and the group has shut down
+    pub fn wait(mut self) -> ShutdownReport {
+        self.thread
+            .take()
+            .and_then(|thread| thread.join().ok())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(unix)]
+mod signal_sys {
+    use crate::error::Result;
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    const SIGNALS: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+    /// Self-pipe the signal handler writes the signal number to
+    static READ_FD: AtomicI32 = AtomicI32::new(-1);
+    static WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+
+    extern "C" fn on_signal(signal: libc::c_int) {
+        notify(signal);
+    }
+
+    /// Wake the signal thread; async-signal-safe.
+    pub fn notify(signal: i32) {
+        let fd = WRITE_FD.load(Ordering::Relaxed);
+        if fd >= 0 {
+            let byte = signal as u8;
+            unsafe { libc::write(fd, &byte as *const u8 as *const libc::c_void, 1) };
+        }
+    }
+
+    pub fn install() -> Result<()> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        for fd in fds {
+            unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+        }
+        READ_FD.store(fds[0], Ordering::SeqCst);
+        WRITE_FD.store(fds[1], Ordering::SeqCst);
+
+        for signal in SIGNALS {
+            let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+            action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            unsafe { libc::sigemptyset(&mut action.sa_mask) };
+            if unsafe { libc::sigaction(signal, &action, std::ptr::null_mut()) } != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Block until a signal arrives, returning its number.
+    pub fn wait() -> i32 {
+        let fd = READ_FD.load(Ordering::SeqCst);
+        let mut byte = 0u8;
+        loop {
+            let n = unsafe { libc::read(fd, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+            if n == 1 {
+                return i32::from(byte);
+            }
+            if n < 0 && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return 0;
+        }
+    }
+
+    pub fn restore() {
+        for signal in SIGNALS {
+            unsafe { libc::signal(signal, libc::SIG_DFL) };
+        }
+    }
+
+    pub fn finished() {}
+
+    pub fn name(signal: i32) -> &'static str {
+        match signal {
+            libc::SIGINT => "SIGINT",
+            libc::SIGTERM => "SIGTERM",
+            _ => "shutdown request",
+        }
+    }
+}
+
+#[cfg(windows)]
+mod signal_sys {
+    use crate::error::Result;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::OnceLock;
+    use std::time::{Duration, Instant};
+    use windows_sys::Win32::System::Console::*;
+
+    /// Control events and the thread waiting for them
+    static EVENTS: OnceLock<(
+        crossbeam_channel::Sender<i32>,
+        crossbeam_channel::Receiver<i32>,
+    )> = OnceLock::new();
+    /// Set once the group has shut down
+    static FINISHED: AtomicBool = AtomicBool::new(false);
+    /// Windows ends the process shortly after a close, logoff or shutdown
+    /// event returns, so the handler waits for the group at most this long
+    const CLOSE_GRACE: Duration = Duration::from_secs(5);
+
+    unsafe extern "system" fn on_ctrl(event: u32) -> i32 {
+        notify(event as i32 + 1);
+        if matches!(
+            event,
+            CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT
+        ) {
+            let start = Instant::now();
+            while !FINISHED.load(Ordering::SeqCst) && start.elapsed() < CLOSE_GRACE {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        1
+    }
+
+    /// Wake the signal thread. Events are passed on offset by one so that
+    /// `0` means [`SignalShutdown::trigger`](super::SignalShutdown::trigger).
+    pub fn notify(signal: i32) {
+        if let Some((tx, _)) = EVENTS.get() {
+            let _ = tx.send(signal);
+        }
+    }
+
+    pub fn install() -> Result<()> {
+        EVENTS.get_or_init(crossbeam_channel::unbounded);
+        if unsafe { SetConsoleCtrlHandler(Some(on_ctrl), 1) } == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Block until a control event arrives, returning its number.
+    pub fn wait() -> i32 {
+        EVENTS.get().and_then(|(_, rx)| rx.recv().ok()).unwrap_or(0)
+    }
+
+    pub fn restore() {
+        unsafe { SetConsoleCtrlHandler(Some(on_ctrl), 0) };
+    }
+
+    pub fn finished() {
+        FINISHED.store(true, Ordering::SeqCst);
+    }
+
+    pub fn name(signal: i32) -> &'static str {
+        match (signal - 1) as u32 {
+            CTRL_C_EVENT => "Ctrl+C",
+            CTRL_BREAK_EVENT => "Ctrl+Break",
+            CTRL_CLOSE_EVENT => "console close",
+            CTRL_LOGOFF_EVENT => "logoff",
+            CTRL_SHUTDOWN_EVENT => "system shutdown",
+            _ => "shutdown request",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -902,6 +1409,63 @@ mod tests {
         handle.join().unwrap();
     }
 
+    #[test]
+    fn test_graceful_named_pipe_shutdown_cancels_read() {
+        let name = format!("test_graceful_cancel_{}", std::process::id());
+        let mut server = GracefulNamedPipe::create(&name).unwrap();
+
+        let client = thread::spawn({
+            let name = name.clone();
+            move || {
+                thread::sleep(Duration::from_millis(50));
+                let client = GracefulNamedPipe::connect(&name).unwrap();
+                // Stay connected without writing
+                thread::sleep(Duration::from_millis(500));
+                drop(client);
+            }
+        });
+
+        server.wait_for_client().unwrap();
+        let state = server.state();
+        let reader = thread::spawn(move || {
+            let mut buf = [0u8; 32];
+            server.read(&mut buf).map_err(|e| e.kind())
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        state.shutdown();
+        state.wait_for_drain(Some(Duration::from_secs(2))).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(400));
+
+        assert_eq!(
+            reader.join().unwrap(),
+            Err(std::io::ErrorKind::ConnectionAborted)
+        );
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_hooks_run_once() {
+        let state = ShutdownState::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let c = Arc::clone(&calls);
+        state.on_shutdown(move || {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+        state.shutdown();
+        state.shutdown();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Registered after shutdown: runs right away
+        let c = Arc::clone(&calls);
+        state.on_shutdown(move || {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
     #[test]
     fn test_graceful_ipc_channel() {
         let name = format!("test_graceful_channel_{}", std::process::id());
@@ -1032,4 +1596,67 @@ mod tests {
         let result = channel.submit_reentrant(|| ());
         assert!(matches!(result, Err(IpcError::Closed)));
     }
+
+    #[test]
+    fn test_shutdown_group_stages_and_timeouts() {
+        use parking_lot::Mutex;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let busy = Arc::new(GracefulWrapper::new(()));
+        let idle = Arc::new(GracefulWrapper::new(()));
+
+        // Simulate an operation that never finishes
+        let busy_state = busy.state();
+        let (started_tx, started_rx) = cb::bounded(0);
+        let worker = thread::spawn(move || {
+            let _guard = busy_state.begin_operation().unwrap();
+            started_tx.send(()).unwrap();
+            thread::sleep(Duration::from_millis(300));
+        });
+        started_rx.recv().unwrap();
+
+        let mut group = ShutdownGroup::new();
+        for (name, stage) in [("events", 2), ("socket", 0)] {
+            let order = Arc::clone(&order);
+            group.add_hook(name, stage, move |_| {
+                order.lock().push(name);
+                Ok(())
+            });
+        }
+        group
+            .add("busy", 1, busy.clone())
+            .add("idle", 1, idle.clone())
+            .add_hook("broken", 2, |_| Err(IpcError::Other("boom".to_string())));
+
+        assert_eq!(group.len(), 5);
+        assert_eq!(group.names()[0], "socket");
+
+        let report = group.shutdown_all(Duration::from_millis(100));
+        assert_eq!(*order.lock(), vec!["socket", "events"]);
+        assert_eq!(report.timed_out, vec!["busy".to_string()]);
+        assert!(report.drained.contains(&"idle".to_string()));
+        assert_eq!(report.failed.len(), 1);
+        assert!(!report.is_clean());
+        assert!(busy.is_shutdown() && idle.is_shutdown());
+        assert!(group.is_shutdown());
+
+        worker.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signal_handlers_shut_down_group() {
+        let channel = Arc::new(GracefulWrapper::new(()));
+        let mut group = ShutdownGroup::new();
+        group.add("channel", 0, channel.clone());
+
+        let signals = install_signal_handlers(group, Duration::from_secs(1)).unwrap();
+        assert!(install_signal_handlers(ShutdownGroup::new(), Duration::ZERO).is_err());
+        assert!(!signals.is_triggered());
+
+        unsafe { libc::raise(libc::SIGTERM) };
+        let report = signals.wait();
+        assert_eq!(report.drained, vec!["channel".to_string()]);
+        assert!(channel.is_shutdown());
+    }
 }
@@ -0,0 +1,357 @@
+//! # GUI Channel
+//!
+//! A channel for feeding a GUI event loop from worker threads without
+//! flooding it. Two kinds of message share one queue:
+//!
+//! - [`GuiSender::send`] queues a message like any other channel.
+//! - [`GuiSender::send_latest`] replaces any undelivered message sent under
+//!   the same key, so high-frequency updates (progress, preview frames)
+//!   collapse to the latest value between two event-loop iterations.
+//!
+//! The event loop's [`EventLoopWaker`] is called once when the channel goes
+//! from empty to non-empty, not once per message. A worker reporting progress
+//! 10,000 times a second wakes the UI at most once per frame it draws.
+//!
+//! ## Example
+//!
+//! With winit, wake the loop through an `EventLoopProxy` and drain the channel
+//! when the user event arrives:
+//!
+//! ```rust,ignore
+//! use ipckit::{CallbackWaker, GuiChannel, WakeableChannel};
+//!
+//! enum UiMessage { Progress(f32), Log(String) }
+//!
+//! let event_loop = EventLoop::<()>::with_user_event().build()?;
+//! let proxy = event_loop.create_proxy();
+//!
+//! let (tx, mut rx) = GuiChannel::<UiMessage>::unbounded();
+//! rx.set_waker(Box::new(CallbackWaker::new(move || {
+//!     let _ = proxy.send_event(());
+//! })));
+//!
+//! std::thread::spawn(move || {
+//!     for i in 0..=10_000 {
+//!         tx.send_latest("progress", UiMessage::Progress(i as f32 / 100.0)).ok();
+//!     }
+//!     tx.send(UiMessage::Log("done".into())).ok();
+//! });
+//!
+//! // In ApplicationHandler::user_event:
+//! for msg in rx.drain() {
+//!     // update the UI
+//! }
+//! ```
+//!
+//! With egui, the waker is simply `ctx.request_repaint()` and
+//! [`GuiReceiver::drain`] is called at the start of each `update`.
+
+use crate::error::{IpcError, Result};
+use crate::waker::{EventLoopWaker, WakeableChannel};
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Entry point for creating GUI channels.
+pub struct GuiChannel<T> {
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T> GuiChannel<T> {
+    /// Create a new unbounded GUI channel.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of (sender, receiver) for the channel.
+    pub fn unbounded() -> (GuiSender<T>, GuiReceiver<T>) {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                entries: BTreeMap::new(),
+                latest: HashMap::new(),
+                next_seq: 0,
+            }),
+            armed: AtomicBool::new(false),
+            waker: Mutex::new(None),
+            senders: AtomicUsize::new(1),
+            receiver_alive: AtomicBool::new(true),
+            coalesced: AtomicU64::new(0),
+        });
+        (
+            GuiSender {
+                shared: Arc::clone(&shared),
+            },
+            GuiReceiver {
+                shared,
+                waker: None,
+            },
+        )
+    }
+}
+
+struct Queue<T> {
+    /// Pending messages in delivery order, keyed by sequence number.
+    entries: BTreeMap<u64, (Option<String>, T)>,
+    /// Sequence number of the pending message for each coalescing key.
+    latest: HashMap<String, u64>,
+    next_seq: u64,
+}
+
+struct Shared<T> {
+    queue: Mutex<Queue<T>>,
+    /// Set once the waker has been called, cleared when the receiver empties
+    /// the queue. Senders only wake when they set it.
+    armed: AtomicBool,
+    waker: Mutex<Option<Box<dyn EventLoopWaker>>>,
+    senders: AtomicUsize,
+    receiver_alive: AtomicBool,
+    coalesced: AtomicU64,
+}
+
+impl<T> Shared<T> {
+    fn push(&self, key: Option<&str>, msg: T) -> Result<()> {
+        if !self.receiver_alive.load(Ordering::SeqCst) {
+            return Err(IpcError::Closed);
+        }
+        let wake = {
+            let mut queue = self.queue.lock();
+            let seq = queue.next_seq;
+            queue.next_seq += 1;
+            if let Some(key) = key {
+                if let Some(old) = queue.latest.insert(key.to_string(), seq) {
+                    queue.entries.remove(&old);
+                    self.coalesced.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            queue.entries.insert(seq, (key.map(str::to_string), msg));
+            !self.armed.swap(true, Ordering::SeqCst)
+        };
+        if wake {
+            self.wake();
+        }
+        Ok(())
+    }
+
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().as_ref() {
+            if waker.is_valid() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Sending half of a [`GuiChannel`], used by worker threads.
+pub struct GuiSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> GuiSender<T> {
+    /// Queue a message. Every message sent this way is delivered.
+    pub fn send(&self, msg: T) -> Result<()> {
+        self.shared.push(None, msg)
+    }
+
+    /// Send a message that supersedes any undelivered message sent under
+    /// the same `key`.
+    ///
+    /// The replacement takes the position of the newest message, so it is
+    /// still delivered after anything queued before it was sent.
+    pub fn send_latest(&self, key: &str, msg: T) -> Result<()> {
+        self.shared.push(Some(key), msg)
+    }
+
+    /// Check if the receiver has been dropped.
+    pub fn is_closed(&self) -> bool {
+        !self.shared.receiver_alive.load(Ordering::SeqCst)
+    }
+}
+
+impl<T> Clone for GuiSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for GuiSender<T> {
+    fn drop(&mut self) {
+        // Wake the loop once more so it notices the channel closing
+        if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shared.armed.store(true, Ordering::SeqCst);
+            self.shared.wake();
+        }
+    }
+}
+
+/// Receiving half of a [`GuiChannel`], owned by the event loop.
+pub struct GuiReceiver<T> {
+    shared: Arc<Shared<T>>,
+    waker: Option<Box<dyn EventLoopWaker>>,
+}
+
+impl<T> GuiReceiver<T> {
+    /// Take the next pending message without blocking.
+    ///
+    /// Returns [`IpcError::WouldBlock`] when nothing is pending and
+    /// [`IpcError::Closed`] once all senders are gone.
+    pub fn try_recv(&self) -> Result<T> {
+        let mut queue = self.shared.queue.lock();
+        match queue.entries.pop_first() {
+            Some((_, (key, msg))) => {
+                if let Some(key) = key {
+                    queue.latest.remove(&key);
+                }
+                if queue.entries.is_empty() {
+                    self.shared.armed.store(false, Ordering::SeqCst);
+                }
+                Ok(msg)
+            }
+            None => {
+                self.shared.armed.store(false, Ordering::SeqCst);
+                if self.shared.senders.load(Ordering::SeqCst) == 0 {
+                    Err(IpcError::Closed)
+                } else {
+                    Err(IpcError::WouldBlock)
+                }
+            }
+        }
+    }
+
+    /// Take every pending message, in delivery order.
+    ///
+    /// Call this once per event-loop iteration after being woken.
+    pub fn drain(&self) -> Vec<T> {
+        let mut queue = self.shared.queue.lock();
+        queue.latest.clear();
+        self.shared.armed.store(false, Ordering::SeqCst);
+        std::mem::take(&mut queue.entries)
+            .into_values()
+            .map(|(_, msg)| msg)
+            .collect()
+    }
+
+    /// Number of pending messages.
+    pub fn len(&self) -> usize {
+        self.shared.queue.lock().entries.len()
+    }
+
+    /// Check if no messages are pending.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check if all senders have been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.shared.senders.load(Ordering::SeqCst) == 0
+    }
+
+    /// Number of messages dropped because a newer one replaced them.
+    pub fn coalesced(&self) -> u64 {
+        self.shared.coalesced.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> WakeableChannel for GuiReceiver<T> {
+    fn set_waker(&mut self, waker: Box<dyn EventLoopWaker>) {
+        *self.shared.waker.lock() = Some(waker.clone_box());
+        self.waker = Some(waker);
+        // Messages that arrived before the waker was installed
+        if !self.is_empty() {
+            self.shared.armed.store(true, Ordering::SeqCst);
+            self.shared.wake();
+        }
+    }
+
+    fn clear_waker(&mut self) {
+        *self.shared.waker.lock() = None;
+        self.waker = None;
+    }
+
+    fn waker(&self) -> Option<&dyn EventLoopWaker> {
+        self.waker.as_deref()
+    }
+}
+
+impl<T> Drop for GuiReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waker::CallbackWaker;
+
+    fn counting_waker() -> (Box<dyn EventLoopWaker>, Arc<AtomicUsize>) {
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&wakes);
+        let waker = CallbackWaker::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        (Box::new(waker), wakes)
+    }
+
+    #[test]
+    fn test_progress_spam_coalesces_to_one_wake() {
+        let (tx, mut rx) = GuiChannel::<(String, u32)>::unbounded();
+        let (waker, wakes) = counting_waker();
+        rx.set_waker(waker);
+
+        tx.send(("log".into(), 0)).unwrap();
+        for i in 0..10_000 {
+            tx.send_latest("progress", ("progress".into(), i)).unwrap();
+        }
+        tx.send(("log".into(), 1)).unwrap();
+
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+        assert_eq!(rx.coalesced(), 9_999);
+        assert_eq!(
+            rx.drain(),
+            vec![
+                ("log".into(), 0),
+                ("progress".into(), 9_999),
+                ("log".into(), 1)
+            ]
+        );
+
+        // Draining re-arms the waker
+        tx.send_latest("progress", ("progress".into(), 10_000))
+            .unwrap();
+        assert_eq!(wakes.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_try_recv_and_close() {
+        let (tx, mut rx) = GuiChannel::<u32>::unbounded();
+        tx.send(1).unwrap();
+        tx.send_latest("a", 2).unwrap();
+        tx.send(3).unwrap();
+        tx.send_latest("a", 4).unwrap();
+
+        // Pending before the waker existed: woken as soon as it is set
+        let (waker, wakes) = counting_waker();
+        rx.set_waker(waker);
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+
+        assert_eq!(rx.try_recv().unwrap(), 1);
+        assert_eq!(rx.try_recv().unwrap(), 3);
+        assert_eq!(rx.try_recv().unwrap(), 4);
+        assert!(matches!(rx.try_recv(), Err(IpcError::WouldBlock)));
+
+        let tx2 = tx.clone();
+        drop(tx);
+        assert!(!rx.is_closed());
+        drop(tx2);
+        assert_eq!(wakes.load(Ordering::SeqCst), 2);
+        assert!(matches!(rx.try_recv(), Err(IpcError::Closed)));
+
+        let (tx, rx) = GuiChannel::<u32>::unbounded();
+        drop(rx);
+        assert!(tx.is_closed());
+        assert!(matches!(tx.send(1), Err(IpcError::Closed)));
+    }
+}
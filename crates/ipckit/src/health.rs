@@ -0,0 +1,381 @@
+//! Health - Liveness and readiness reporting for daemons
+//!
+//! A [`Health`] collects the process uptime, live connection count, task
+//! counts, a metrics summary and any number of [`HealthCheck`]s registered by
+//! subsystems. [`Router::health_routes`] serves it as two cheap endpoints a
+//! desktop frontend can poll:
+//!
+//! | Route          | Answers                       | Status                          |
+//! |----------------|-------------------------------|---------------------------------|
+//! | `GET /healthz` | "is the process alive?"       | always 200                      |
+//! | `GET /readyz`  | "is the backend sane?"        | 503 if any check is unhealthy   |
+//!
+//! `/healthz` never runs the checks; `/readyz` runs all of them and returns
+//! the full [`HealthReport`]. Degraded checks keep the daemon ready.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ipckit::health::{health_check_fn, CheckResult, Health};
+//! use ipckit::Router;
+//!
+//! let health = Health::new();
+//! health.register(health_check_fn("cache", || CheckResult::degraded("cold")));
+//!
+//! let mut router = Router::new();
+//! router.health_routes(health.clone());
+//!
+//! assert!(health.report().is_ready());
+//! ```
+//!
+//! [`Router::health_routes`]: crate::api_server::Router::health_routes
+
+use crate::metrics::MetricsRegistry;
+use crate::socket_server::ConnectionRegistry;
+use crate::task_manager::{TaskFilter, TaskManager};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Outcome of a health check, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// Working normally
+    Healthy,
+    /// Working, but with reduced capacity or performance
+    Degraded,
+    /// Not working; the daemon is not ready
+    Unhealthy,
+}
+
+/// Result of running one [`HealthCheck`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckResult {
+    /// Check outcome
+    pub status: HealthStatus,
+    /// Human-readable detail
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl CheckResult {
+    /// A healthy result without detail.
+    pub fn healthy() -> Self {
+        Self {
+            status: HealthStatus::Healthy,
+            message: None,
+        }
+    }
+
+    /// A degraded result.
+    pub fn degraded(message: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Degraded,
+            message: Some(message.into()),
+        }
+    }
+
+    /// An unhealthy result.
+    pub fn unhealthy(message: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Unhealthy,
+            message: Some(message.into()),
+        }
+    }
+}
+
+/// A subsystem health check run by `/readyz`.
+///
+/// Checks run on the request thread, so they should be cheap. A check that
+/// panics is reported as unhealthy.
+pub trait HealthCheck: Send + Sync {
+    /// Name the result is reported under.
+    fn name(&self) -> &str;
+
+    /// Run the check.
+    fn check(&self) -> CheckResult;
+}
+
+/// A [`HealthCheck`] backed by a closure. See [`health_check_fn`].
+pub struct FnHealthCheck<F> {
+    name: String,
+    check: F,
+}
+
+/// Create a [`HealthCheck`] from a name and a closure.
+pub fn health_check_fn<F>(name: &str, check: F) -> FnHealthCheck<F>
+where
+    F: Fn() -> CheckResult + Send + Sync,
+{
+    FnHealthCheck {
+        name: name.to_string(),
+        check,
+    }
+}
+
+impl<F> HealthCheck for FnHealthCheck<F>
+where
+    F: Fn() -> CheckResult + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self) -> CheckResult {
+        (self.check)()
+    }
+}
+
+/// Totals over all channels in the global [`MetricsRegistry`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSummary {
+    /// Number of registered channels
+    pub channels: usize,
+    /// Messages sent over all channels
+    pub messages_sent: u64,
+    /// Messages received over all channels
+    pub messages_received: u64,
+    /// Send and receive errors over all channels
+    pub errors: u64,
+    /// Process-level gauges, keyed by name
+    pub gauges: BTreeMap<String, i64>,
+}
+
+impl MetricsSummary {
+    fn collect(registry: &MetricsRegistry) -> Self {
+        let mut summary = Self {
+            gauges: registry.gauges(),
+            ..Default::default()
+        };
+        for snapshot in registry.snapshots().values() {
+            summary.channels += 1;
+            summary.messages_sent += snapshot.messages_sent;
+            summary.messages_received += snapshot.messages_received;
+            summary.errors += snapshot.send_errors + snapshot.receive_errors;
+        }
+        summary
+    }
+}
+
+/// Everything `/readyz` reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Worst status over all checks
+    pub status: HealthStatus,
+    /// Seconds since the [`Health`] was created
+    pub uptime_secs: f64,
+    /// Live connections, when a connection registry is attached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connections: Option<usize>,
+    /// Task counts by status, when a task manager is attached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tasks: Option<BTreeMap<String, usize>>,
+    /// Summary of the global metrics registry
+    pub metrics: MetricsSummary,
+    /// Check results, keyed by check name
+    pub checks: BTreeMap<String, CheckResult>,
+}
+
+impl HealthReport {
+    /// Whether no check is unhealthy.
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthStatus::Unhealthy
+    }
+}
+
+struct HealthInner {
+    started: Instant,
+    checks: RwLock<Vec<Arc<dyn HealthCheck>>>,
+    connections: RwLock<Option<ConnectionRegistry>>,
+    tasks: RwLock<Option<Arc<TaskManager>>>,
+}
+
+/// Health state of a daemon, shared by clones.
+#[derive(Clone)]
+pub struct Health {
+    inner: Arc<HealthInner>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(HealthInner {
+                started: Instant::now(),
+                checks: RwLock::new(Vec::new()),
+                connections: RwLock::new(None),
+                tasks: RwLock::new(None),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Debug for Health {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Health")
+            .field("uptime", &self.inner.started.elapsed())
+            .field("checks", &self.check_names())
+            .finish()
+    }
+}
+
+impl Health {
+    /// Create a health state; uptime is counted from now.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a check. A check with the same name is replaced.
+    pub fn register<C: HealthCheck + 'static>(&self, check: C) -> &Self {
+        let mut checks = self.inner.checks.write();
+        checks.retain(|c| c.name() != check.name());
+        checks.push(Arc::new(check));
+        self
+    }
+
+    /// Remove the check registered under `name`.
+    pub fn unregister(&self, name: &str) -> bool {
+        let mut checks = self.inner.checks.write();
+        let before = checks.len();
+        checks.retain(|c| c.name() != name);
+        checks.len() != before
+    }
+
+    /// Names of the registered checks, in registration order.
+    pub fn check_names(&self) -> Vec<String> {
+        self.inner
+            .checks
+            .read()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect()
+    }
+
+    /// Report the live connection count of `registry`.
+    pub fn connections(&self, registry: ConnectionRegistry) -> &Self {
+        *self.inner.connections.write() = Some(registry);
+        self
+    }
+
+    /// Report task counts from `manager`.
+    pub fn tasks(&self, manager: Arc<TaskManager>) -> &Self {
+        *self.inner.tasks.write() = Some(manager);
+        self
+    }
+
+    /// Time since this health state was created.
+    pub fn uptime(&self) -> std::time::Duration {
+        self.inner.started.elapsed()
+    }
+
+    /// Run every check and collect the full report.
+    pub fn report(&self) -> HealthReport {
+        let checks: Vec<_> = self.inner.checks.read().clone();
+        let checks: BTreeMap<String, CheckResult> = checks
+            .iter()
+            .map(|check| {
+                let result = catch_unwind(AssertUnwindSafe(|| check.check()))
+                    .unwrap_or_else(|_| CheckResult::unhealthy("check panicked"));
+                (check.name().to_string(), result)
+            })
+            .collect();
+        let status = checks
+            .values()
+            .map(|r| r.status)
+            .max()
+            .unwrap_or(HealthStatus::Healthy);
+
+        let tasks = self.inner.tasks.read().as_ref().map(|manager| {
+            let mut counts = BTreeMap::new();
+            for task in manager.list(&TaskFilter::new()) {
+                let status = serde_json::to_value(task.status)
+                    .ok()
+                    .and_then(|s| s.as_str().map(str::to_string))
+                    .unwrap_or_default();
+                *counts.entry(status).or_insert(0) += 1;
+            }
+            counts
+        });
+
+        HealthReport {
+            status,
+            uptime_secs: self.uptime().as_secs_f64(),
+            connections: self.inner.connections.read().as_ref().map(|r| r.len()),
+            tasks,
+            metrics: MetricsSummary::collect(MetricsRegistry::global()),
+            checks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::{Method, Request, ResponseBody, Router};
+    use crate::task_manager::TaskBuilder;
+
+    fn get(router: &Router, path: &str) -> (u16, serde_json::Value) {
+        let resp = router.handle(Request::new(Method::GET, path));
+        let body = match resp.body {
+            ResponseBody::Json(v) => v,
+            _ => serde_json::Value::Null,
+        };
+        (resp.status, body)
+    }
+
+    #[test]
+    fn test_report_worst_status_wins() {
+        let health = Health::new();
+        assert_eq!(health.report().status, HealthStatus::Healthy);
+
+        health.register(health_check_fn("db", CheckResult::healthy));
+        health.register(health_check_fn("cache", || CheckResult::degraded("cold")));
+        let report = health.report();
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert!(report.is_ready());
+
+        health.register(health_check_fn("db", || panic!("boom")));
+        let report = health.report();
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert_eq!(
+            report.checks["db"].message.as_deref(),
+            Some("check panicked")
+        );
+        assert_eq!(health.check_names(), ["cache", "db"]);
+
+        assert!(health.unregister("db"));
+        assert!(health.report().is_ready());
+    }
+
+    #[test]
+    fn test_health_routes() {
+        let manager = Arc::new(TaskManager::new(Default::default()));
+        manager.create(TaskBuilder::new("a", "test"));
+        let health = Health::new();
+        health.tasks(Arc::clone(&manager));
+
+        let mut router = Router::new();
+        router.health_routes(health.clone());
+
+        let (status, body) = get(&router, "/healthz");
+        assert_eq!(status, 200);
+        assert_eq!(body["status"], "ok");
+        assert!(body["uptime_secs"].is_number());
+
+        let (status, body) = get(&router, "/readyz");
+        assert_eq!(status, 200);
+        assert_eq!(body["status"], "healthy");
+        assert_eq!(body["tasks"]["pending"], 1);
+
+        health.register(health_check_fn("disk", || CheckResult::unhealthy("full")));
+        let (status, body) = get(&router, "/readyz");
+        assert_eq!(status, 503);
+        assert_eq!(body["checks"]["disk"]["message"], "full");
+
+        // Liveness does not depend on the checks
+        assert_eq!(get(&router, "/healthz").0, 200);
+    }
+}
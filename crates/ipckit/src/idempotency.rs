@@ -0,0 +1,313 @@
+//! # Idempotency
+//!
+//! Duplicate suppression for [`ApiServer`] requests. A client that times out
+//! cannot tell whether its request was handled, so retrying a `POST` may
+//! create a second task. Sending the same `Idempotency-Key` header
+//! ([`IDEMPOTENCY_HEADER`]) with every attempt makes the retry safe:
+//!
+//! - The first request with a key runs the handler, and its response is
+//!   cached for the TTL.
+//! - A retry with the same key gets the cached response, marked with an
+//!   `Idempotent-Replayed: true` header ([`REPLAYED_HEADER`]), and the
+//!   handler does not run again.
+//! - A retry that arrives while the first request is still running waits
+//!   for it and gets its response.
+//! - Reusing a key for a different request (method, path or body) is
+//!   answered with 422.
+//!
+//! `GET`, `HEAD` and `OPTIONS` requests are never cached, and neither are
+//! server errors (5xx), so a retry after one runs the handler again.
+//!
+//! Enable it through `ApiServerConfig::idempotency`, or add it to a route
+//! group only:
+//!
+//! ```rust,ignore
+//! use ipckit::IdempotencyMiddleware;
+//!
+//! let idempotency = IdempotencyMiddleware::new().ttl(Duration::from_secs(60));
+//! router.group("/v1/tasks", |g| {
+//!     g.middleware(move |req, next| idempotency.handle(req, next));
+//!     g.post("", create_task);
+//! });
+//!
+//! // Client: every attempt carries the same key
+//! client.request_idempotent(Method::POST, "/v1/tasks", Some(body), &key)?;
+//! ```
+//!
+//! [`ApiServer`]: crate::api_server::ApiServer
+
+use crate::api_server::{Method, Request, Response};
+use parking_lot::{Condvar, Mutex};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Request header carrying the idempotency key.
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+
+/// Response header marking a response replayed from the cache.
+pub const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// Default time a response stays cached (10 minutes).
+const DEFAULT_TTL: Duration = Duration::from_secs(600);
+
+/// Default number of cached responses.
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// Caches responses by `Idempotency-Key` and replays them on retries.
+///
+/// Clones share the cache.
+#[derive(Clone)]
+pub struct IdempotencyMiddleware {
+    ttl: Duration,
+    max_entries: usize,
+    cache: Arc<Cache>,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: Mutex<HashMap<String, Entry>>,
+    /// Signalled when an in-flight request finishes
+    finished: Condvar,
+}
+
+struct Entry {
+    /// Hash of the method, path and body the key was first used with
+    fingerprint: u64,
+    /// `None` while the first request is still running
+    response: Option<Response>,
+    stored_at: Instant,
+}
+
+impl Default for IdempotencyMiddleware {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_TTL,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            cache: Arc::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for IdempotencyMiddleware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdempotencyMiddleware")
+            .field("ttl", &self.ttl)
+            .field("max_entries", &self.max_entries)
+            .field("cached", &self.len())
+            .finish()
+    }
+}
+
+impl IdempotencyMiddleware {
+    /// Create the middleware with a 10 minute TTL.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep responses for `ttl` (default: 10 minutes).
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Keep at most `max` responses, dropping the oldest (default: 10000).
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// Number of keys currently cached or in flight.
+    pub fn len(&self) -> usize {
+        self.cache.entries.lock().len()
+    }
+
+    /// Check whether nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget every cached response.
+    pub fn clear(&self) {
+        self.cache
+            .entries
+            .lock()
+            .retain(|_, entry| entry.response.is_none());
+    }
+
+    /// Run `req` through the cache, calling `next` unless a response for its
+    /// key is already cached.
+    pub fn handle(&self, req: Request, next: &dyn Fn(Request) -> Response) -> Response {
+        let key = match req.header(IDEMPOTENCY_HEADER) {
+            Some(key) if !matches!(req.method, Method::GET | Method::HEAD | Method::OPTIONS) => {
+                key.to_string()
+            }
+            _ => return next(req),
+        };
+        let fingerprint = fingerprint(&req);
+
+        let mut entries = self.cache.entries.lock();
+        loop {
+            match entries.get(&key) {
+                Some(entry) if entry.fingerprint != fingerprint => {
+                    return Response::new(422).json(serde_json::json!({
+                        "error": "Unprocessable Entity",
+                        "message": "Idempotency-Key was already used for a different request"
+                    }));
+                }
+                Some(entry) => match entry.response {
+                    Some(ref response) if entry.stored_at.elapsed() < self.ttl => {
+                        return response.clone().header(REPLAYED_HEADER, "true");
+                    }
+                    Some(_) => break,
+                    None => self.cache.finished.wait(&mut entries),
+                },
+                None => break,
+            }
+        }
+        self.make_room(&mut entries);
+        entries.insert(
+            key.clone(),
+            Entry {
+                fingerprint,
+                response: None,
+                stored_at: Instant::now(),
+            },
+        );
+        drop(entries);
+
+        // Releases the key even if the handler panics
+        let mut in_flight = InFlight {
+            cache: &self.cache,
+            key,
+            response: None,
+        };
+        let response = next(req);
+        if response.status < 500 {
+            in_flight.response = Some(response.clone());
+        }
+        response
+    }
+
+    /// Drop expired responses, then the oldest ones while over capacity
+    /// (internal)
+    fn make_room(&self, entries: &mut HashMap<String, Entry>) {
+        entries.retain(|_, entry| entry.response.is_none() || entry.stored_at.elapsed() < self.ttl);
+        while entries.len() >= self.max_entries.max(1) {
+            let oldest = entries
+                .iter()
+                .filter(|(_, entry)| entry.response.is_some())
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => entries.remove(&key),
+                // Everything is in flight
+                None => break,
+            };
+        }
+    }
+}
+
+/// Stores the response of an in-flight request, or releases its key, and
+/// wakes the retries waiting for it.
+struct InFlight<'a> {
+    cache: &'a Cache,
+    key: String,
+    response: Option<Response>,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        let mut entries = self.cache.entries.lock();
+        match self.response.take() {
+            Some(response) => {
+                if let Some(entry) = entries.get_mut(&self.key) {
+                    entry.response = Some(response);
+                    entry.stored_at = Instant::now();
+                }
+            }
+            None => {
+                entries.remove(&self.key);
+            }
+        }
+        self.cache.finished.notify_all();
+    }
+}
+
+/// Hash of what makes two requests the same.
+fn fingerprint(req: &Request) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    req.method.as_str().hash(&mut hasher);
+    req.path.hash(&mut hasher);
+    req.raw_body.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::ResponseBody;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn post(key: &str, body: &[u8]) -> Request {
+        let mut req = Request::new(Method::POST, "/v1/tasks");
+        req.headers
+            .insert(IDEMPOTENCY_HEADER.to_lowercase(), key.to_string());
+        req.raw_body = body.to_vec();
+        req
+    }
+
+    #[test]
+    fn test_replays_cached_response() {
+        let idempotency = IdempotencyMiddleware::new();
+        let calls = AtomicUsize::new(0);
+        let handler = |_req: Request| {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            Response::created(serde_json::json!({"id": n}))
+        };
+
+        let first = idempotency.handle(post("k1", b"{}"), &handler);
+        let retry = idempotency.handle(post("k1", b"{}"), &handler);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(retry.status, 201);
+        assert_eq!(retry.headers.get(REPLAYED_HEADER).unwrap(), "true");
+        assert!(matches!(first.body, ResponseBody::Json(ref v) if v["id"] == 0));
+        assert!(matches!(retry.body, ResponseBody::Json(ref v) if v["id"] == 0));
+
+        // Same key, different body
+        let reused = idempotency.handle(post("k1", b"{\"x\":1}"), &handler);
+        assert_eq!(reused.status, 422);
+
+        // Other keys and requests without one run the handler
+        idempotency.handle(post("k2", b"{}"), &handler);
+        idempotency.handle(Request::new(Method::POST, "/v1/tasks"), &handler);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_server_errors_and_expired_entries_rerun() {
+        let idempotency = IdempotencyMiddleware::new().ttl(Duration::from_millis(20));
+        let calls = AtomicUsize::new(0);
+        let failing = |_req: Request| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Response::internal_error("boom")
+        };
+        idempotency.handle(post("k", b""), &failing);
+        idempotency.handle(post("k", b""), &failing);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(idempotency.is_empty());
+
+        let ok = |_req: Request| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Response::no_content()
+        };
+        idempotency.handle(post("k", b""), &ok);
+        idempotency.handle(post("k", b""), &ok);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        std::thread::sleep(Duration::from_millis(30));
+        idempotency.handle(post("k", b""), &ok);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+}
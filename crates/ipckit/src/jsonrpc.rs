@@ -0,0 +1,736 @@
+//! JSON-RPC - JSON-RPC 2.0 over local sockets and stdio
+//!
+//! Editors and developer tools (LSP- and DAP-style integrations) speak
+//! [JSON-RPC 2.0](https://www.jsonrpc.org/specification) rather than ipckit's
+//! own [`Message`](crate::socket_server::Message) protocol. This module
+//! implements it over any byte stream, such as a [`LocalSocketStream`] or
+//! the process's stdin/stdout:
+//!
+//! - [`JsonRpcServer`] dispatches requests, notifications and batches to
+//!   registered methods and to mounted `#[ipc_handler]` command maps
+//! - [`JsonRpcClient`] makes calls and sends notifications
+//! - [`Framing`] selects between LSP-style `Content-Length` headers and one
+//!   message per line
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::jsonrpc::{Framing, JsonRpcClient, JsonRpcServer};
+//! use ipckit::{LocalSocketListener, LocalSocketStream};
+//!
+//! let mut server = JsonRpcServer::new();
+//! server.method("ping", |_params| Ok(serde_json::json!("pong")));
+//! // Commands of an #[ipc_handler] are served as "math/add", ...
+//! // server.mount("math", Math);
+//!
+//! let listener = LocalSocketListener::bind("my_rpc")?;
+//! std::thread::spawn(move || server.serve_listener(&listener, Framing::ContentLength));
+//!
+//! let stream = LocalSocketStream::connect("my_rpc")?;
+//! let mut client = JsonRpcClient::new(stream, Framing::ContentLength);
+//! assert_eq!(client.call("ping", None)?, "pong");
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+
+use crate::command_handler::{command_params, CommandHandler};
+use crate::error::{IpcError, Result};
+use crate::local_socket::LocalSocketListener;
+use crate::transport::StdioStream;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::Arc;
+
+/// Protocol version carried in every message.
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Invalid JSON was received.
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON sent is not a valid request object.
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+/// Internal JSON-RPC error.
+pub const INTERNAL_ERROR: i64 = -32603;
+/// A method failed; `data` carries the [`IpcError`] wire form.
+pub const SERVER_ERROR: i64 = -32000;
+
+/// Largest `Content-Length` accepted, in bytes.
+const MAX_CONTENT_LENGTH: usize = 64 * 1024 * 1024;
+
+/// Request ID, echoed back in the response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Id {
+    /// Numeric ID
+    Number(i64),
+    /// String ID
+    String(String),
+    /// Null, only used in responses to requests whose ID could not be read
+    Null,
+}
+
+/// A request, or a notification if it has no ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    /// Always [`JSONRPC_VERSION`]
+    pub jsonrpc: String,
+    /// Method name
+    pub method: String,
+    /// Positional (array) or named (object) parameters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    /// Request ID; `None` for notifications
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Id>,
+}
+
+impl Request {
+    /// Create a request expecting a response.
+    pub fn new(method: &str, params: Option<Value>, id: Id) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: method.to_string(),
+            params,
+            id: Some(id),
+        }
+    }
+
+    /// Create a notification, which is never answered.
+    pub fn notification(method: &str, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: method.to_string(),
+            params,
+            id: None,
+        }
+    }
+
+    /// Check whether this is a notification.
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+}
+
+/// An error object.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    /// Error code, e.g. [`METHOD_NOT_FOUND`]
+    pub code: i64,
+    /// Short description
+    pub message: String,
+    /// Additional information
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    /// Create an error object.
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Attach additional information.
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl From<&IpcError> for RpcError {
+    /// Map deserialization failures to [`INVALID_PARAMS`] and everything
+    /// else to [`SERVER_ERROR`], with the error's wire form as `data`.
+    fn from(err: &IpcError) -> Self {
+        let code = match err {
+            IpcError::Deserialization(_) => INVALID_PARAMS,
+            _ => SERVER_ERROR,
+        };
+        RpcError::new(code, err.to_string()).with_data(err.to_json())
+    }
+}
+
+impl From<RpcError> for IpcError {
+    fn from(err: RpcError) -> Self {
+        match err.code {
+            METHOD_NOT_FOUND => IpcError::NotFound(err.message),
+            INVALID_PARAMS | PARSE_ERROR | INVALID_REQUEST => {
+                IpcError::Deserialization(err.message)
+            }
+            _ => IpcError::Other(err.message),
+        }
+    }
+}
+
+/// A response to a request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    /// Always [`JSONRPC_VERSION`]
+    pub jsonrpc: String,
+    /// Result of a successful call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Error of a failed call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    /// ID of the request
+    pub id: Id,
+}
+
+impl Response {
+    /// Create a successful response.
+    pub fn success(id: Id, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            result: Some(result),
+            error: None,
+            id,
+        }
+    }
+
+    /// Create an error response.
+    pub fn failure(id: Id, error: RpcError) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            result: None,
+            error: Some(error),
+            id,
+        }
+    }
+
+    /// Get the result, or the error as an [`IpcError`].
+    pub fn into_result(self) -> Result<Value> {
+        match self.error {
+            Some(error) => Err(error.into()),
+            None => Ok(self.result.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+/// How messages are delimited on the stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// `Content-Length: N\r\n\r\n` headers before each message, as in LSP
+    #[default]
+    ContentLength,
+    /// One message per line
+    Newline,
+}
+
+/// Read one message body, or `None` at end of stream.
+pub(crate) fn read_frame<R: BufRead>(reader: &mut R, framing: Framing) -> Result<Option<Vec<u8>>> {
+    match framing {
+        Framing::Newline => loop {
+            let mut line = Vec::new();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(None);
+            }
+            if line.iter().any(|b| !b.is_ascii_whitespace()) {
+                return Ok(Some(line));
+            }
+        },
+        Framing::ContentLength => {
+            let mut length = None;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 {
+                    return match length {
+                        None => Ok(None),
+                        Some(_) => Err(IpcError::Closed),
+                    };
+                }
+                let line = line.trim_end();
+                if line.is_empty() {
+                    if length.is_some() {
+                        break;
+                    }
+                    continue;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.trim().eq_ignore_ascii_case("content-length") {
+                        let value = value.trim().parse::<usize>().map_err(|_| {
+                            IpcError::deserialization(format!("Invalid Content-Length: {}", value))
+                        })?;
+                        length = Some(value);
+                    }
+                }
+            }
+
+            let length = length.unwrap_or_default();
+            if length > MAX_CONTENT_LENGTH {
+                return Err(IpcError::BufferTooSmall {
+                    needed: length,
+                    got: MAX_CONTENT_LENGTH,
+                });
+            }
+            let mut body = vec![0u8; length];
+            reader.read_exact(&mut body)?;
+            Ok(Some(body))
+        }
+    }
+}
+
+/// Write one message body.
+pub(crate) fn write_frame<W: Write>(writer: &mut W, body: &[u8], framing: Framing) -> Result<()> {
+    match framing {
+        Framing::ContentLength => {
+            write!(writer, "Content-Length: {}\r\n\r\n", body.len())?;
+            writer.write_all(body)?;
+        }
+        Framing::Newline => {
+            writer.write_all(body)?;
+            writer.write_all(b"\n")?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A method callable through a [`JsonRpcServer`].
+type MethodFn = Arc<dyn Fn(Option<Value>) -> Result<Value> + Send + Sync>;
+
+/// JSON-RPC 2.0 server dispatching to registered methods.
+///
+/// Requests are answered, notifications are run without a reply and
+/// batches get a batch of the responses to their requests. Cloning is cheap
+/// and shares the registered methods.
+#[derive(Clone, Default)]
+pub struct JsonRpcServer {
+    methods: HashMap<String, MethodFn>,
+}
+
+impl JsonRpcServer {
+    /// Create a server without methods.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a method.
+    pub fn method<F>(&mut self, name: &str, method: F) -> &mut Self
+    where
+        F: Fn(Option<Value>) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.methods.insert(name.to_string(), Arc::new(method));
+        self
+    }
+
+    /// Register every command of a [`CommandHandler`].
+    ///
+    /// Commands are named `{prefix}/{command}`, or just `{command}` if
+    /// `prefix` is empty. They take named parameters: an object, or nothing.
+    pub fn mount<H>(&mut self, prefix: &str, handler: H) -> &mut Self
+    where
+        H: CommandHandler + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        let prefix = prefix.trim_matches('/');
+        for &command in handler.commands() {
+            let name = if prefix.is_empty() {
+                command.to_string()
+            } else {
+                format!("{}/{}", prefix, command)
+            };
+            let handler = Arc::clone(&handler);
+            self.method(&name, move |params| {
+                handler.handle_command(command, command_params(params)?)
+            });
+        }
+        self
+    }
+
+    /// Get the names of the registered methods.
+    pub fn methods(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.methods.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Handle one decoded message, a request or a batch.
+    ///
+    /// Returns the reply to send, if any: notifications and batches of only
+    /// notifications get none.
+    pub fn handle(&self, message: Value) -> Option<Value> {
+        match message {
+            Value::Array(batch) if batch.is_empty() => Some(error_value(
+                Id::Null,
+                RpcError::new(INVALID_REQUEST, "Empty batch"),
+            )),
+            Value::Array(batch) => {
+                let replies: Vec<Value> = batch
+                    .into_iter()
+                    .filter_map(|item| self.handle_single(item))
+                    .collect();
+                (!replies.is_empty()).then_some(Value::Array(replies))
+            }
+            single => self.handle_single(single),
+        }
+    }
+
+    /// Handle one encoded message, answering malformed JSON with
+    /// [`PARSE_ERROR`].
+    pub fn handle_bytes(&self, body: &[u8]) -> Option<Value> {
+        match serde_json::from_slice(body) {
+            Ok(message) => self.handle(message),
+            Err(e) => Some(error_value(
+                Id::Null,
+                RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)),
+            )),
+        }
+    }
+
+    fn handle_single(&self, item: Value) -> Option<Value> {
+        let id = item
+            .get("id")
+            .and_then(|id| serde_json::from_value::<Id>(id.clone()).ok());
+        let request = match serde_json::from_value::<Request>(item) {
+            Ok(request) if request.jsonrpc == JSONRPC_VERSION => request,
+            _ => {
+                return Some(error_value(
+                    id.unwrap_or(Id::Null),
+                    RpcError::new(INVALID_REQUEST, "Invalid request"),
+                ))
+            }
+        };
+
+        let result = match self.methods.get(&request.method) {
+            Some(method) => method(request.params).map_err(|e| RpcError::from(&e)),
+            None => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", request.method),
+            )),
+        };
+
+        let id = request.id?;
+        let response = match result {
+            Ok(value) => Response::success(id, value),
+            Err(error) => Response::failure(id, error),
+        };
+        Some(serde_json::to_value(response).unwrap_or_default())
+    }
+
+    /// Serve a stream until the peer closes it.
+    pub fn serve<S: Read + Write>(&self, stream: S, framing: Framing) -> Result<()> {
+        let mut reader = BufReader::new(stream);
+        while let Some(body) = read_frame(&mut reader, framing)? {
+            if let Some(reply) = self.handle_bytes(&body) {
+                let data = serde_json::to_vec(&reply)
+                    .map_err(|e| IpcError::serialization(e.to_string()))?;
+                write_frame(reader.get_mut(), &data, framing)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Accept connections and serve each on its own thread.
+    pub fn serve_listener(&self, listener: &LocalSocketListener, framing: Framing) -> Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = server.serve(stream, framing) {
+                    tracing::debug!(error = %e, "JSON-RPC connection closed with error");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Serve the process's stdin and stdout until stdin is closed.
+    ///
+    /// Nothing else may write to stdout meanwhile; log to stderr instead.
+    /// To call a server running as a child process, create a
+    /// [`JsonRpcClient`] over [`StdioStream::spawn`].
+    pub fn serve_stdio(&self, framing: Framing) -> Result<()> {
+        self.serve(StdioStream::stdio()?, framing)
+    }
+}
+
+/// Encode an error response.
+fn error_value(id: Id, error: RpcError) -> Value {
+    serde_json::to_value(Response::failure(id, error)).unwrap_or_default()
+}
+
+/// JSON-RPC 2.0 client over a byte stream.
+///
+/// Calls block until their response arrives. Requests and notifications
+/// the server sends in the meantime are queued for
+/// [`take_incoming`](Self::take_incoming).
+pub struct JsonRpcClient<S: Read + Write> {
+    stream: BufReader<S>,
+    framing: Framing,
+    next_id: i64,
+    incoming: VecDeque<Request>,
+}
+
+impl<S: Read + Write> JsonRpcClient<S> {
+    /// Create a client over a connected stream.
+    pub fn new(stream: S, framing: Framing) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+            framing,
+            next_id: 1,
+            incoming: VecDeque::new(),
+        }
+    }
+
+    /// Call a method and wait for its result.
+    pub fn call(&mut self, method: &str, params: Option<Value>) -> Result<Value> {
+        let id = self.next_id();
+        self.send(&Request::new(method, params, id.clone()))?;
+        loop {
+            if let Some(response) = self.recv()?.into_iter().find(|r| r.id == id) {
+                return response.into_result();
+            }
+        }
+    }
+
+    /// Send a notification.
+    pub fn notify(&mut self, method: &str, params: Option<Value>) -> Result<()> {
+        self.send(&Request::notification(method, params))
+    }
+
+    /// Send several calls as one batch and wait for all their results.
+    ///
+    /// Results are returned in the order of `calls`.
+    pub fn batch(&mut self, calls: &[(&str, Option<Value>)]) -> Result<Vec<Result<Value>>> {
+        let requests: Vec<Request> = calls
+            .iter()
+            .map(|(method, params)| Request::new(method, params.clone(), self.next_id()))
+            .collect();
+        self.send(&requests)?;
+
+        let mut pending: HashMap<Id, Response> = HashMap::new();
+        while pending.len() < requests.len() {
+            for response in self.recv()? {
+                if requests.iter().any(|r| r.id.as_ref() == Some(&response.id)) {
+                    pending.insert(response.id.clone(), response);
+                }
+            }
+        }
+
+        Ok(requests
+            .iter()
+            .filter_map(|r| r.id.as_ref().and_then(|id| pending.remove(id)))
+            .map(Response::into_result)
+            .collect())
+    }
+
+    /// Take the requests and notifications the server sent.
+    pub fn take_incoming(&mut self) -> Vec<Request> {
+        self.incoming.drain(..).collect()
+    }
+
+    /// Get the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.stream.get_mut()
+    }
+
+    fn next_id(&mut self) -> Id {
+        let id = Id::Number(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    fn send<T: Serialize + ?Sized>(&mut self, message: &T) -> Result<()> {
+        let data =
+            serde_json::to_vec(message).map_err(|e| IpcError::serialization(e.to_string()))?;
+        write_frame(self.stream.get_mut(), &data, self.framing)
+    }
+
+    /// Read one message, returning its responses and queueing its requests.
+    fn recv(&mut self) -> Result<Vec<Response>> {
+        let body = read_frame(&mut self.stream, self.framing)?.ok_or(IpcError::Closed)?;
+        let message: Value =
+            serde_json::from_slice(&body).map_err(|e| IpcError::deserialization(e.to_string()))?;
+        let items = match message {
+            Value::Array(items) => items,
+            single => vec![single],
+        };
+
+        let mut responses = Vec::new();
+        for item in items {
+            if item.get("method").is_some() {
+                if let Ok(request) = serde_json::from_value(item) {
+                    self.incoming.push_back(request);
+                }
+            } else {
+                let response = serde_json::from_value(item)
+                    .map_err(|e| IpcError::deserialization(e.to_string()))?;
+                responses.push(response);
+            }
+        }
+        Ok(responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_socket::LocalSocketStream;
+    use serde_json::{json, Map};
+    use std::io::Cursor;
+
+    struct Math;
+
+    impl CommandHandler for Math {
+        fn channel_name(&self) -> &'static str {
+            "math"
+        }
+
+        fn commands(&self) -> &'static [&'static str] {
+            &["add"]
+        }
+
+        fn handle_command(&self, command: &str, params: Map<String, Value>) -> Result<Value> {
+            match (command, params.get("a"), params.get("b")) {
+                ("add", Some(a), Some(b)) => {
+                    Ok(json!(a.as_i64().unwrap_or(0) + b.as_i64().unwrap_or(0)))
+                }
+                ("add", _, _) => Err(IpcError::deserialization("expected a and b")),
+                _ => Err(IpcError::NotFound(format!("Unknown command: {}", command))),
+            }
+        }
+    }
+
+    fn server() -> JsonRpcServer {
+        let mut server = JsonRpcServer::new();
+        server
+            .method("echo", |params| Ok(params.unwrap_or(Value::Null)))
+            .mount("math", Math);
+        server
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let server = server();
+        assert_eq!(server.methods(), vec!["echo", "math/add"]);
+
+        let reply = server
+            .handle(json!({"jsonrpc": "2.0", "method": "math/add", "params": {"a": 1, "b": 2}, "id": 1}))
+            .unwrap();
+        assert_eq!(reply, json!({"jsonrpc": "2.0", "result": 3, "id": 1}));
+
+        let reply = server
+            .handle(json!({"jsonrpc": "2.0", "method": "math/add", "params": [1, 2], "id": "a"}))
+            .unwrap();
+        assert_eq!(reply["error"]["code"], INVALID_PARAMS);
+        assert_eq!(reply["id"], "a");
+
+        let reply = server
+            .handle(json!({"jsonrpc": "2.0", "method": "nope", "id": 2}))
+            .unwrap();
+        assert_eq!(reply["error"]["code"], METHOD_NOT_FOUND);
+
+        // Notifications are never answered, even when they fail
+        assert!(server
+            .handle(json!({"jsonrpc": "2.0", "method": "nope"}))
+            .is_none());
+
+        let reply = server.handle(json!({"method": "echo", "id": 3})).unwrap();
+        assert_eq!(reply["error"]["code"], INVALID_REQUEST);
+
+        let reply = server.handle_bytes(b"{not json").unwrap();
+        assert_eq!(reply["error"]["code"], PARSE_ERROR);
+        assert_eq!(reply["id"], Value::Null);
+    }
+
+    #[test]
+    fn test_batch() {
+        let server = server();
+        let reply = server
+            .handle(json!([
+                {"jsonrpc": "2.0", "method": "echo", "params": ["x"], "id": 1},
+                {"jsonrpc": "2.0", "method": "echo", "params": ["y"]},
+                {"jsonrpc": "2.0", "method": "math/add", "params": {"a": 2, "b": 2}, "id": 2},
+                1
+            ]))
+            .unwrap();
+        let replies = reply.as_array().unwrap();
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0]["result"], json!(["x"]));
+        assert_eq!(replies[1]["result"], 4);
+        assert_eq!(replies[2]["error"]["code"], INVALID_REQUEST);
+
+        assert!(server
+            .handle(json!([{"jsonrpc": "2.0", "method": "echo"}]))
+            .is_none());
+        assert_eq!(
+            server.handle(json!([])).unwrap()["error"]["code"],
+            INVALID_REQUEST
+        );
+    }
+
+    #[test]
+    fn test_framing() {
+        for framing in [Framing::ContentLength, Framing::Newline] {
+            let mut data = Vec::new();
+            write_frame(&mut data, br#"{"a":1}"#, framing).unwrap();
+            write_frame(&mut data, br#"{"b":2}"#, framing).unwrap();
+
+            let mut reader = Cursor::new(data);
+            assert_eq!(
+                read_frame(&mut reader, framing)
+                    .unwrap()
+                    .unwrap()
+                    .trim_ascii(),
+                br#"{"a":1}"#
+            );
+            assert_eq!(
+                read_frame(&mut reader, framing)
+                    .unwrap()
+                    .unwrap()
+                    .trim_ascii(),
+                br#"{"b":2}"#
+            );
+            assert!(read_frame(&mut reader, framing).unwrap().is_none());
+        }
+
+        let mut reader = Cursor::new(b"Content-Type: x\r\ncontent-length: 2\r\n\r\n{}".to_vec());
+        assert_eq!(
+            read_frame(&mut reader, Framing::ContentLength).unwrap(),
+            Some(b"{}".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_client_server_over_local_socket() {
+        let name = format!("test_jsonrpc_{}", std::process::id());
+        let listener = LocalSocketListener::bind(&name).unwrap();
+        let server = server();
+        std::thread::spawn(move || server.serve_listener(&listener, Framing::ContentLength));
+
+        let stream = LocalSocketStream::connect(&name).unwrap();
+        let mut client = JsonRpcClient::new(stream, Framing::ContentLength);
+
+        assert_eq!(
+            client
+                .call("math/add", Some(json!({"a": 20, "b": 22})))
+                .unwrap(),
+            42
+        );
+        assert!(matches!(
+            client.call("missing", None),
+            Err(IpcError::NotFound(_))
+        ));
+        client.notify("echo", Some(json!("ignored"))).unwrap();
+
+        let results = client
+            .batch(&[("echo", Some(json!([1]))), ("missing", None)])
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap(), &json!([1]));
+        assert!(results[1].is_err());
+    }
+}
@@ -4,18 +4,49 @@
 //!
 //! ## Features
 //!
-//! - **Pipes**: Anonymous and named pipes for parent-child process communication
+//! - **Pipes**: Anonymous, duplex and named pipes for parent-child process communication
 //! - **Shared Memory**: Fast data sharing between processes using memory-mapped regions
+//! - **Typed Shared Memory**: `#[repr(C)]` structs mapped with schema and version checks
+//! - **Growable Shared Memory**: Buffers that chain segments on demand behind one logical view
+//! - **Shared Memory Queue**: Bounded cross-process work queue with blocking push/pop
+//! - **Shared Memory Broadcast**: One-writer, many-reader telemetry ring with overrun detection
+//! - **Shared Memory Double Buffer**: Lock-free latest-frame streaming for viewports and GUIs
 //! - **Unix Domain Sockets / Named Pipes**: Bidirectional communication channels
 //! - **Message Channels**: High-level message passing with serialization support
+//! - **Frame Hooks**: Transform frames on the wire, e.g. pre-shared key encryption (`encryption` feature)
+//! - **Reliable Channel**: Acknowledged, at-least-once delivery that survives reconnects
 //! - **File Channel**: Simple file-based IPC for frontend-backend communication
-//! - **Thread Channel**: High-performance intra-process thread communication
-//! - **Event Stream**: Real-time publish-subscribe event system
-//! - **Task Manager**: Task lifecycle management with progress tracking
+//! - **File Transfer**: Chunked, resumable, checksum-verified file streaming
+//! - **Thread Channel**: High-performance intra-process thread communication with multi-channel select
+//! - **Event Stream**: Real-time publish-subscribe event system with an optional on-disk journal and async streams
+//! - **Event Bridge**: Event subscriptions for socket clients, filtered per identity by an ACL
+//! - **Task Manager**: Task lifecycle management with progress tracking and recurring schedules
+//! - **Process Host**: Spawn child processes as tasks with their output streamed as events
 //! - **Socket Server**: Multi-client socket server (like Docker's socket)
+//! - **Service**: Typed request/response services with generated clients, timeouts and cancellation
+//! - **Transports**: Run servers and channels over local sockets, named pipes, shared memory, TCP (optionally TLS) or a child process's stdio
+//! - **WSL Bridge**: Reach a Windows named pipe from WSL2, or a WSL socket from Windows
+//! - **Single Instance**: Crash-safe daemon lock with stale socket cleanup
+//! - **Discovery**: Registry of the channels running processes serve, listed by `ipckit ls`
+//! - **Mux**: Named logical streams (control, data, logs) over one pipe or socket
+//! - **Message Stream**: `Read`/`Write` byte streams over message transports
 //! - **API Server**: HTTP-over-Socket RESTful API service
+//! - **Access Log**: Per-request tracing spans for the API and socket servers
+//! - **Idempotency**: `Idempotency-Key` response caching so retried API requests run once
+//! - **Trace Context**: Correlation IDs propagated across requests, messages, events and child processes
+//! - **JSON-RPC**: JSON-RPC 2.0 servers and clients over local sockets and stdio, for LSP-style tools
+//! - **Command Handlers**: Mount `#[ipc_handler]` services on the API or socket server
+//! - **Validation**: Structured field errors for messages validated with `#[ipc(...)]` rules
+//! - **Runtime Config**: Adjust log filters, rate limits and other whitelisted settings live
 //! - **Metrics**: Performance monitoring and metrics collection
-//! - **Waker**: Event loop integration for GUI/async frameworks
+//! - **Metrics Exporter**: Prometheus `/metrics` endpoint and push gateway exporter
+//! - **Health**: `/healthz` and `/readyz` endpoints with pluggable subsystem checks
+//! - **Waker**: Event loop integration for GUI/async frameworks, with optional wake-rate limiting
+//! - **GUI Channel**: Coalesces progress spam so an event loop is woken once per frame
+//! - **Session Resume**: Client-side resynchronization after reconnecting to a daemon
+//! - **Testing**: In-memory loopback transport with failure injection
+//! - **Service Manifest**: Declarative daemon configuration (sockets, routes, tasks, webhooks)
+//! - **Config File**: `ipckit.toml` endpoints, codecs, auth tokens and server settings with env overrides
 //!
 //! ## Example
 //!
@@ -33,23 +64,63 @@
 //! }
 //! ```
 
+pub mod access_log;
 pub mod api_server;
+pub mod buffer_pool;
 pub mod channel;
 pub mod cli_bridge;
+pub mod command_handler;
+pub mod config_file;
+pub mod daemon;
+pub mod discovery;
 pub mod error;
+pub mod event_bridge;
+pub mod event_journal;
 pub mod event_stream;
 pub mod file_channel;
+pub mod file_transfer;
 pub mod graceful;
+pub mod gui_channel;
+pub mod health;
+pub mod idempotency;
+pub mod jsonrpc;
 pub mod local_socket;
+pub mod message_stream;
 pub mod metrics;
+pub mod metrics_exporter;
+pub mod msgpack;
+pub mod mux;
 pub mod pipe;
+pub mod process_host;
+pub mod reliable_channel;
 pub mod resource_link;
+pub mod runtime_config;
+pub mod service;
+pub mod service_manifest;
+pub mod session_resume;
 pub mod shm;
+pub mod shm_broadcast;
+pub mod shm_double_buffer;
+pub mod shm_queue;
 pub mod socket_server;
 pub mod task_manager;
+pub mod task_schedule;
+pub mod testing;
 pub mod thread_channel;
 pub mod thread_pump;
+pub mod trace_context;
+pub mod transport;
+pub mod validation;
 pub mod waker;
+pub mod wsl_bridge;
+
+// TLS for the TCP transport
+#[cfg(feature = "tls")]
+pub mod tls;
+
+// Pre-shared key encryption of channel frames
+#[cfg(feature = "encryption")]
+pub mod encryption;
 
 // Async channel support
 #[cfg(feature = "async")]
@@ -62,56 +133,116 @@ pub mod unix;
 pub mod windows;
 
 // Re-exports
-pub use channel::{IpcChannel, IpcReceiver, IpcSender};
-pub use error::{IpcError, Result};
+pub use access_log::LoggingMiddleware;
+pub use buffer_pool::BufferPool;
+pub use channel::{
+    ChannelTap, FrameHook, FrameHooks, FrameLimits, IpcChannel, IpcReceiver, IpcSender,
+    TapDirection, TransferProgress,
+};
+pub use command_handler::{CommandHandler, CommandService, Stateful, StatefulHandler};
+pub use config_file::{ConfigDefaults, EndpointConfig, IpckitConfig, ServerSettings};
+pub use daemon::SingleInstance;
+pub use discovery::{ChannelEntry, Discovery, Registration};
+pub use error::{ErrorCode, IpcError, Result};
+pub use event_bridge::{
+    EventAcl, EventBridge, EVENTS_EPOCH_METHOD, EVENTS_REPLAY_METHOD, EVENTS_SUBSCRIBE_METHOD,
+    EVENTS_UNSUBSCRIBE_METHOD,
+};
+pub use event_journal::{EventJournal, JournalConfig, JournalFormat};
 pub use event_stream::{
     event_types, Event, EventBus, EventBusConfig, EventFilter, EventPublisher, EventSubscriber,
-    McpProgressPayload,
+    McpProgressPayload, SubscriptionHandle,
 };
 pub use file_channel::{FileChannel, FileMessage, MessageType as FileMessageType};
+pub use file_transfer::{
+    FileOffer, FileReceiver, FileSender, FrameTransport, ReceivedFile, SentFile, StreamTransport,
+};
 pub use graceful::{
-    GracefulChannel, GracefulIpcChannel, GracefulNamedPipe, GracefulWrapper, OperationGuard,
-    ReentrantDispatch, ShutdownState,
+    install_signal_handlers, GracefulChannel, GracefulIpcChannel, GracefulNamedPipe,
+    GracefulWrapper, OperationGuard, ReentrantDispatch, ShutdownGroup, ShutdownReport,
+    ShutdownState, SignalShutdown,
 };
+pub use gui_channel::{GuiChannel, GuiReceiver, GuiSender};
+pub use health::{health_check_fn, CheckResult, Health, HealthCheck, HealthReport, HealthStatus};
+pub use idempotency::IdempotencyMiddleware;
+pub use jsonrpc::{Framing, JsonRpcClient, JsonRpcServer, RpcError};
 pub use local_socket::{LocalSocketListener, LocalSocketStream};
-pub use pipe::{AnonymousPipe, NamedPipe, PipeReader, PipeWriter};
+pub use message_stream::{MessageStream, MessageTransport};
+pub use mux::{Mux, MuxConfig, MuxStream};
+pub use pipe::{AnonymousPipe, DuplexPipe, NamedPipe, PipeCanceller, PipeReader, PipeWriter};
+pub use process_host::{HostedProcess, ProcessHost};
+pub use reliable_channel::{ReliableChannel, ReliableConfig};
 pub use resource_link::{ResourceKind, ResourceLink, ResourceLinkInfo};
-pub use shm::SharedMemory;
+pub use runtime_config::{setting_fn, LogFilter, RateLimit, RateLimiter, RuntimeConfig, Setting};
+pub use service::{CallContext, CallOptions, ServiceClient, ServiceDefinition, ServiceHandler};
+pub use service_manifest::{
+    NamespaceSpec, RouteSpec, RouteTarget, ScheduledTaskSpec, ServiceHandle, ServiceManifest,
+    SocketSpec, WebhookSpec,
+};
+pub use session_resume::{ResumeReport, ResumeSource, SessionResumer};
+pub use shm::{
+    GrowableShm, SharedMemory, SharedMemorySnapshot, ShmArena, ShmMapped, ShmStruct, ShmTicket,
+};
+pub use shm_broadcast::{ShmBroadcast, ShmBroadcastReader, ShmRecord};
+pub use shm_double_buffer::{ShmDoubleBuffer, ShmFrame};
+pub use shm_queue::ShmQueue;
 pub use socket_server::{
-    Connection, ConnectionHandler, ConnectionId, ConnectionMetadata, FnHandler, Message,
-    SocketClient, SocketServer, SocketServerConfig,
+    Broadcaster, Capabilities, Connection, ConnectionHandler, ConnectionId, ConnectionInfo,
+    ConnectionMetadata, ConnectionRegistry, ConnectionTap, FnHandler, FrameCheck, FrameVerdict,
+    Handshake, HandshakeInfo, Listen, Message, SocketClient, SocketServer, SocketServerConfig,
+    TapRecord, ATTACH_METHOD, HANDSHAKE_METHOD, PROTOCOL_VERSION,
 };
 pub use task_manager::{
-    CancellationToken, TaskBuilder, TaskFilter, TaskHandle, TaskInfo, TaskManager,
-    TaskManagerConfig, TaskStatus,
+    CancellationToken, SharedCancellationToken, StallAction, TaskBuilder, TaskFilter, TaskHandle,
+    TaskInfo, TaskManager, TaskManagerConfig, TaskOutputReceiver, TaskOutputSender, TaskStatus,
+};
+pub use task_schedule::{Schedule, ScheduleInfo, ScheduleRun};
+pub use thread_channel::{
+    ChannelSet, Priority, Selectable, ThreadChannel, ThreadReceiver, ThreadSender,
 };
-pub use thread_channel::{ThreadChannel, ThreadReceiver, ThreadSender};
 pub use thread_pump::{MainThreadPump, PumpStats, ThreadAffinity};
+pub use trace_context::{clear_trace_hook, set_trace_hook, TraceContext, TraceGuard};
+pub use transport::{
+    LocalSocketTransport, NamedPipeTransport, ShmTransport, StdioStream, StdioTransport,
+    TcpTransport, Transport, TransportListener, TransportStream,
+};
+pub use validation::{FieldError, ValidationErrors};
 
 // API Server exports
 pub use api_server::{
-    ApiClient, ApiServer, ApiServerConfig, Method, PathPattern, Request, Response, ResponseBody,
-    Router,
+    ApiClient, ApiError, ApiServer, ApiServerConfig, BodyReader, ContentFormat, Deprecation,
+    Extensions, IntoResponse, Json, Method, PathPattern, Request, RequestLimits, Response,
+    ResponseBody, Router, Scope,
 };
 
 // Metrics exports
 pub use metrics::{
-    metered_pair, AggregatedMetrics, ChannelMetrics, IntoMetered, MeteredChannel, MeteredReceiver,
-    MeteredSender, MeteredWrapper, MetricsSnapshot, WithMetrics,
+    metered_pair, AggregatedMetrics, ChannelMetrics, IntoMetered, LatencyHistogram, MeteredChannel,
+    MeteredReceiver, MeteredSender, MeteredWrapper, MetricsRegistry, MetricsSnapshot, WithMetrics,
 };
+pub use metrics_exporter::{MetricsExporter, MetricsExporterConfig, PushGateway};
+
+pub use wsl_bridge::{BridgeHandle, WslBridge, WslTransport};
+
+#[cfg(feature = "encryption")]
+pub use encryption::{EncryptedChannel, FrameCipher};
 
 // Waker exports
 pub use waker::{
-    BroadcastWaker, CallbackWaker, EventLoopWaker, ThreadWaker, WakeableChannel, WakeableWrapper,
+    BroadcastWaker, CallbackWaker, EventLoopWaker, RateLimitedWaker, ThreadWaker, WakeableChannel,
+    WakeableWrapper,
 };
 
 #[cfg(feature = "async")]
 pub use waker::TokioWaker;
 
+#[cfg(feature = "async")]
+pub use message_stream::AsyncMessageStream;
+
 // CLI Bridge exports
 pub use cli_bridge::{
-    parsers, CliBridge, CliBridgeConfig, CommandOutput, OutputType, ProgressInfo, ProgressParser,
-    WrappedChild, WrappedCommand, WrappedWriter,
+    parsers, BridgeCommand, CliBridge, CliBridgeConfig, CommandCallback, CommandOutput, OutputType,
+    ProgressInfo, ProgressParser, WrappedChild, WrappedCommand, WrappedWriter,
 };
 
 // Async channel exports
@@ -126,9 +257,15 @@ pub use async_channel::tokio_channel::{
 #[cfg(feature = "async")]
 pub use async_channel::{broadcast, oneshot};
 
-// Async local socket exports (when both async and backend-interprocess features are enabled)
+// Async local socket exports (the listener needs the backend-interprocess feature)
+#[cfg(feature = "async")]
+pub use local_socket::AsyncLocalSocketStream;
+
 #[cfg(all(feature = "async", feature = "backend-interprocess"))]
-pub use local_socket::{AsyncLocalSocketListener, AsyncLocalSocketStream};
+pub use local_socket::AsyncLocalSocketListener;
+
+#[cfg(feature = "async")]
+pub use pipe::{AsyncNamedPipe, AsyncPipeReader, AsyncPipeWriter};
 
 // Python bindings (organized into submodules for better maintainability)
 #[cfg(feature = "python-bindings")]
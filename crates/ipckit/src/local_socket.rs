@@ -6,9 +6,12 @@
 //!
 //! # Features
 //! - Unix Domain Sockets on Unix systems
+//! - Abstract namespace sockets on Linux and Android for names starting with
+//!   `@` (no filesystem path to create or clean up)
 //! - Named Pipes on Windows
 //! - Server/Client architecture
-//! - Async support (with `async` feature)
+//! - Async support (with `async` feature): `LocalSocketStream::into_async()`
+//!   yields a stream implementing Tokio's `AsyncRead`/`AsyncWrite`
 
 use crate::error::Result;
 use std::io::{Read, Write};
@@ -95,6 +98,169 @@ mod interprocess_backend {
         pub fn name(&self) -> &str {
             &self.name
         }
+
+        /// Check whether the peer has closed the connection, without reading.
+        pub fn is_peer_closed(&self) -> bool {
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+
+                let Stream::UdSocket(stream) = &self.inner;
+                crate::unix::socket_peer_closed(stream.inner().as_raw_fd())
+            }
+            #[cfg(windows)]
+            {
+                use std::os::windows::io::{AsHandle, AsRawHandle};
+
+                let Stream::NamedPipe(pipe) = &self.inner;
+                crate::windows::raw_pipe_peer_closed(pipe.as_handle().as_raw_handle() as _)
+            }
+        }
+
+        /// Read whatever data is available without blocking.
+        ///
+        /// Returns `Ok(0)` at end of stream and an error of kind
+        /// [`WouldBlock`](std::io::ErrorKind::WouldBlock) if nothing is
+        /// available. The stream stays in blocking mode, so clones of it are
+        /// unaffected.
+        pub fn try_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+
+                let Stream::UdSocket(stream) = &self.inner;
+                crate::unix::recv_nonblocking(stream.inner().as_raw_fd(), buf)
+            }
+            #[cfg(windows)]
+            {
+                use std::os::windows::io::{AsHandle, AsRawHandle};
+
+                let Stream::NamedPipe(pipe) = &self.inner;
+                let handle = pipe.as_handle().as_raw_handle();
+                match crate::windows::raw_pipe_bytes_available(handle as _)? {
+                    0 => Err(std::io::ErrorKind::WouldBlock.into()),
+                    available => {
+                        let len = buf.len().min(available);
+                        self.inner.read(&mut buf[..len])
+                    }
+                }
+            }
+        }
+
+        /// Read, waiting at most `timeout` for data to arrive.
+        ///
+        /// Returns `Ok(0)` at end of stream and an error of kind
+        /// [`TimedOut`](std::io::ErrorKind::TimedOut) if nothing arrived in
+        /// time.
+        pub fn read_with_timeout(
+            &mut self,
+            buf: &mut [u8],
+            timeout: std::time::Duration,
+        ) -> std::io::Result<usize> {
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+
+                let Stream::UdSocket(stream) = &self.inner;
+                if !crate::unix::wait_readable(stream.inner().as_raw_fd(), timeout)? {
+                    return Err(std::io::ErrorKind::TimedOut.into());
+                }
+                self.try_read(buf)
+            }
+            #[cfg(windows)]
+            {
+                // Synchronous pipe handles cannot wait with a deadline
+                let deadline = std::time::Instant::now() + timeout;
+                loop {
+                    match self.try_read(buf) {
+                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                        result => return result,
+                    }
+                    if std::time::Instant::now() >= deadline {
+                        return Err(std::io::ErrorKind::TimedOut.into());
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+            }
+        }
+
+        /// Create another handle to the same connection.
+        ///
+        /// Useful for writing from one thread while another blocks on reads.
+        pub fn try_clone(&self) -> Result<Self> {
+            use interprocess::TryClone;
+
+            let inner = self
+                .inner
+                .try_clone()
+                .map_err(|e| IpcError::Io(std::io::Error::other(e)))?;
+            Ok(Self {
+                inner,
+                name: self.name.clone(),
+            })
+        }
+
+        /// Shut down both directions of the connection, waking up any read
+        /// blocked on it or on a clone of it.
+        ///
+        /// Not supported on Windows with this backend.
+        pub fn shutdown(&self) -> std::io::Result<()> {
+            #[cfg(unix)]
+            {
+                let Stream::UdSocket(stream) = &self.inner;
+                stream.inner().shutdown(std::net::Shutdown::Both)
+            }
+            #[cfg(windows)]
+            {
+                Err(std::io::ErrorKind::Unsupported.into())
+            }
+        }
+
+        /// Wrap an already connected Unix stream.
+        #[cfg(unix)]
+        pub(crate) fn from_unix_stream(
+            stream: std::os::unix::net::UnixStream,
+            name: String,
+        ) -> Self {
+            use interprocess::os::unix::uds_local_socket;
+
+            Self {
+                inner: Stream::from(uds_local_socket::Stream::from(stream)),
+                name,
+            }
+        }
+
+        /// Convert into a stream implementing `AsyncRead` and `AsyncWrite`.
+        ///
+        /// Must be called from within a Tokio runtime. Not supported on
+        /// Windows with this backend; connect with
+        /// [`AsyncLocalSocketStream::connect`](super::AsyncLocalSocketStream::connect)
+        /// instead.
+        #[cfg(feature = "async")]
+        pub fn into_async(self) -> Result<super::AsyncLocalSocketStream> {
+            #[cfg(unix)]
+            {
+                use interprocess::os::unix::uds_local_socket;
+                use std::os::unix::io::OwnedFd;
+                use std::os::unix::net::UnixStream;
+
+                let Stream::UdSocket(stream) = self.inner;
+                let stream = UnixStream::from(OwnedFd::from(stream));
+                stream.set_nonblocking(true)?;
+                let stream = uds_local_socket::tokio::Stream::try_from(OwnedFd::from(stream))?;
+                Ok(super::AsyncLocalSocketStream::from_parts(
+                    interprocess::local_socket::tokio::Stream::from(stream),
+                    self.name,
+                ))
+            }
+            #[cfg(windows)]
+            {
+                Err(IpcError::Platform(
+                    "converting interprocess streams to async is not supported on Windows"
+                        .to_string(),
+                ))
+            }
+        }
     }
 
     impl Read for LocalSocketStream {
@@ -115,8 +281,11 @@ mod interprocess_backend {
 
     /// Get the appropriate socket name for the current platform.
     fn get_socket_name(name: &str) -> Result<interprocess::local_socket::Name<'static>> {
-        // Try namespaced name first (works on Linux with abstract sockets and Windows)
-        if let Ok(ns_name) = name.to_string().to_ns_name::<GenericNamespaced>() {
+        // Try namespaced name first (works on Linux with abstract sockets and Windows).
+        // A leading '@' asks for the abstract namespace explicitly, as in the
+        // native backend, and is not part of the name.
+        let ns = name.strip_prefix('@').unwrap_or(name);
+        if let Ok(ns_name) = ns.to_string().to_ns_name::<GenericNamespaced>() {
             return Ok(ns_name);
         }
 
@@ -151,7 +320,7 @@ pub use interprocess_backend::{LocalSocketListener, LocalSocketStream};
 #[cfg(not(feature = "backend-interprocess"))]
 mod native_backend {
     use super::*;
-    #[cfg(unix)]
+    #[cfg(any(unix, feature = "async"))]
     use crate::error::IpcError;
 
     #[cfg(unix)]
@@ -161,8 +330,9 @@ mod native_backend {
     pub struct LocalSocketListener {
         #[cfg(unix)]
         listener: UnixListener,
+        /// Socket file to remove on drop; `None` for abstract sockets
         #[cfg(unix)]
-        path: String,
+        path: Option<String>,
         #[cfg(windows)]
         pipe_name: String,
         name: String,
@@ -182,6 +352,14 @@ mod native_backend {
         pub fn bind(name: &str) -> Result<Self> {
             #[cfg(unix)]
             {
+                if let Some(abstract_name) = name.strip_prefix('@') {
+                    return Ok(Self {
+                        listener: crate::unix::bind_abstract(abstract_name)?,
+                        path: None,
+                        name: name.to_string(),
+                    });
+                }
+
                 let path = if name.starts_with('/') {
                     name.to_string()
                 } else {
@@ -200,7 +378,7 @@ mod native_backend {
 
                 Ok(Self {
                     listener,
-                    path,
+                    path: Some(path),
                     name: name.to_string(),
                 })
             }
@@ -257,7 +435,9 @@ mod native_backend {
     #[cfg(unix)]
     impl Drop for LocalSocketListener {
         fn drop(&mut self) {
-            let _ = std::fs::remove_file(&self.path);
+            if let Some(path) = &self.path {
+                let _ = std::fs::remove_file(path);
+            }
         }
     }
 
@@ -266,6 +446,13 @@ mod native_backend {
         pub fn connect(name: &str) -> Result<Self> {
             #[cfg(unix)]
             {
+                if let Some(abstract_name) = name.strip_prefix('@') {
+                    return Ok(Self {
+                        stream: crate::unix::connect_abstract(abstract_name)?,
+                        name: name.to_string(),
+                    });
+                }
+
                 let path = if name.starts_with('/') {
                     name.to_string()
                 } else {
@@ -310,6 +497,122 @@ mod native_backend {
         pub fn name(&self) -> &str {
             &self.name
         }
+
+        /// Check whether the peer has closed the connection, without reading.
+        pub fn is_peer_closed(&self) -> bool {
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+
+                crate::unix::socket_peer_closed(self.stream.as_raw_fd())
+            }
+            #[cfg(windows)]
+            {
+                crate::windows::pipe_peer_closed(&self.handle)
+            }
+        }
+
+        /// Read whatever data is available without blocking.
+        ///
+        /// Returns `Ok(0)` at end of stream and an error of kind
+        /// [`WouldBlock`](std::io::ErrorKind::WouldBlock) if nothing is
+        /// available.
+        pub fn try_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+
+                crate::unix::recv_nonblocking(self.stream.as_raw_fd(), buf)
+            }
+            #[cfg(windows)]
+            {
+                match crate::windows::pipe_bytes_available(&self.handle)? {
+                    0 => Err(std::io::ErrorKind::WouldBlock.into()),
+                    available => {
+                        let len = buf.len().min(available);
+                        crate::windows::read_pipe(&self.handle, &mut buf[..len])
+                    }
+                }
+            }
+        }
+
+        /// Read, waiting at most `timeout` for data to arrive.
+        ///
+        /// Returns `Ok(0)` at end of stream and an error of kind
+        /// [`TimedOut`](std::io::ErrorKind::TimedOut) if nothing arrived in
+        /// time.
+        pub fn read_with_timeout(
+            &mut self,
+            buf: &mut [u8],
+            timeout: std::time::Duration,
+        ) -> std::io::Result<usize> {
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+
+                if !crate::unix::wait_readable(self.stream.as_raw_fd(), timeout)? {
+                    return Err(std::io::ErrorKind::TimedOut.into());
+                }
+                self.try_read(buf)
+            }
+            #[cfg(windows)]
+            {
+                // Synchronous pipe handles cannot wait with a deadline
+                let deadline = std::time::Instant::now() + timeout;
+                loop {
+                    match self.try_read(buf) {
+                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                        result => return result,
+                    }
+                    if std::time::Instant::now() >= deadline {
+                        return Err(std::io::ErrorKind::TimedOut.into());
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+            }
+        }
+
+        /// Create another handle to the same connection.
+        ///
+        /// Useful for writing from one thread while another blocks on reads.
+        /// Not supported for native Windows pipes, whose synchronous I/O would
+        /// make writes wait behind a pending read.
+        pub fn try_clone(&self) -> Result<Self> {
+            #[cfg(unix)]
+            {
+                Ok(Self {
+                    stream: self.stream.try_clone()?,
+                    name: self.name.clone(),
+                })
+            }
+            #[cfg(windows)]
+            {
+                Err(crate::error::IpcError::Platform(
+                    "cloning native named pipe streams is not supported".to_string(),
+                ))
+            }
+        }
+
+        /// Shut down both directions of the connection, waking up any read
+        /// blocked on it or on a clone of it.
+        ///
+        /// Not supported for native Windows pipes.
+        pub fn shutdown(&self) -> std::io::Result<()> {
+            #[cfg(unix)]
+            {
+                self.stream.shutdown(std::net::Shutdown::Both)
+            }
+            #[cfg(windows)]
+            {
+                Err(std::io::ErrorKind::Unsupported.into())
+            }
+        }
+
+        /// Wrap an already connected Unix stream.
+        #[cfg(unix)]
+        pub(crate) fn from_unix_stream(stream: UnixStream, name: String) -> Self {
+            Self { stream, name }
+        }
     }
 
     impl Read for LocalSocketStream {
@@ -348,11 +651,107 @@ mod native_backend {
             }
         }
     }
+
+    /// A local socket stream for Tokio, from [`LocalSocketStream::into_async`].
+    ///
+    /// On Windows the native pipe handle is synchronous, so I/O runs on
+    /// Tokio's blocking pool like `tokio::fs::File` and writes must be
+    /// flushed.
+    #[cfg(feature = "async")]
+    pub struct AsyncLocalSocketStream {
+        #[cfg(unix)]
+        inner: tokio::net::UnixStream,
+        #[cfg(windows)]
+        inner: tokio::fs::File,
+        name: String,
+    }
+
+    #[cfg(feature = "async")]
+    impl LocalSocketStream {
+        /// Convert into a stream implementing `AsyncRead` and `AsyncWrite`.
+        ///
+        /// Clones made with [`try_clone`](Self::try_clone) share the
+        /// socket's non-blocking mode afterwards. Must be called from within
+        /// a Tokio runtime.
+        pub fn into_async(self) -> Result<AsyncLocalSocketStream> {
+            #[cfg(unix)]
+            {
+                self.stream.set_nonblocking(true)?;
+                Ok(AsyncLocalSocketStream {
+                    inner: tokio::net::UnixStream::from_std(self.stream)?,
+                    name: self.name,
+                })
+            }
+            #[cfg(windows)]
+            {
+                Ok(AsyncLocalSocketStream {
+                    inner: tokio::fs::File::from_std(self.handle.into_owned().into()),
+                    name: self.name,
+                })
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl AsyncLocalSocketStream {
+        /// Connect to a local socket server asynchronously.
+        pub async fn connect(name: &str) -> Result<Self> {
+            let name = name.to_string();
+            tokio::task::spawn_blocking(move || LocalSocketStream::connect(&name))
+                .await
+                .map_err(|e| IpcError::Io(std::io::Error::other(e)))??
+                .into_async()
+        }
+
+        /// Get the name of this stream.
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl tokio::io::AsyncRead for AsyncLocalSocketStream {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::pin::Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl tokio::io::AsyncWrite for AsyncLocalSocketStream {
+        fn poll_write(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            std::pin::Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::pin::Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::pin::Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
 }
 
 #[cfg(not(feature = "backend-interprocess"))]
 pub use native_backend::{LocalSocketListener, LocalSocketStream};
 
+#[cfg(all(feature = "async", not(feature = "backend-interprocess")))]
+pub use native_backend::AsyncLocalSocketStream;
+
 // ============================================================================
 // Async support
 // ============================================================================
@@ -431,6 +830,15 @@ pub mod async_socket {
             })
         }
 
+        /// Wrap a connected Tokio stream (internal).
+        #[cfg(unix)]
+        pub(super) fn from_parts(
+            inner: interprocess::local_socket::tokio::Stream,
+            name: String,
+        ) -> Self {
+            Self { inner, name }
+        }
+
         /// Get the name of this stream.
         pub fn name(&self) -> &str {
             &self.name
@@ -541,4 +949,101 @@ mod tests {
 
         server_thread.join().unwrap();
     }
+
+    #[test]
+    fn test_try_read_leaves_clones_blocking() {
+        let name = format!("test_socket_try_read_{}", std::process::id());
+        let listener = LocalSocketListener::bind(&name).unwrap();
+        let server = thread::spawn(move || listener.accept().unwrap());
+        thread::sleep(std::time::Duration::from_millis(50));
+        let mut client = LocalSocketStream::connect(&name).unwrap();
+        let mut stream = server.join().unwrap();
+
+        let mut buf = [0u8; 16];
+        assert_eq!(
+            stream.try_read(&mut buf).unwrap_err().kind(),
+            std::io::ErrorKind::WouldBlock
+        );
+        assert!(!stream.is_peer_closed());
+
+        // A clone made before a failed try_read still blocks until data comes
+        if let Ok(mut clone) = client.try_clone() {
+            let reader = thread::spawn(move || {
+                let mut buf = [0u8; 5];
+                clone.read_exact(&mut buf).map(|_| buf)
+            });
+            assert!(client.try_read(&mut buf).is_err());
+            thread::sleep(std::time::Duration::from_millis(50));
+            stream.write_all(b"hello").unwrap();
+            assert_eq!(&reader.join().unwrap().unwrap(), b"hello");
+        }
+
+        client.write_all(b"ping").unwrap();
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(stream.try_read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"ping");
+
+        drop(client);
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert!(stream.is_peer_closed());
+        assert_eq!(stream.try_read(&mut buf).unwrap(), 0);
+    }
+
+    #[cfg(all(unix, feature = "async"))]
+    #[tokio::test]
+    async fn test_into_async() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let name = format!("test_socket_async_{}", std::process::id());
+        let listener = LocalSocketListener::bind(&name).unwrap();
+        let server = tokio::task::spawn_blocking(move || listener.accept().unwrap());
+
+        let mut client = AsyncLocalSocketStream::connect(&name).await.unwrap();
+        let mut server = server.await.unwrap().into_async().unwrap();
+        assert_eq!(client.name(), name);
+
+        client.write_all(b"framed").await.unwrap();
+        let mut buf = [0u8; 6];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"framed");
+
+        drop(client);
+        assert_eq!(server.read(&mut buf).await.unwrap(), 0);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn test_abstract_namespace() {
+        let name = format!("@ipckit_test_abstract_{}", std::process::id());
+        let listener = LocalSocketListener::bind(&name).unwrap();
+
+        let server_thread = thread::spawn(move || {
+            let mut stream = listener.accept().unwrap();
+            stream.write_all(b"abstract").unwrap();
+        });
+
+        let mut client = LocalSocketStream::connect(&name).unwrap();
+        let mut buf = [0u8; 8];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"abstract");
+        server_thread.join().unwrap();
+
+        // Nothing was created on disk
+        let bare = name.trim_start_matches('@');
+        assert!(!std::path::Path::new(&format!("/tmp/{}.sock", bare)).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_local_socketpair() {
+        let (mut a, mut b) = crate::unix::local_socketpair().unwrap();
+        a.write_all(b"ping").unwrap();
+
+        let mut buf = [0u8; 4];
+        b.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        drop(a);
+        assert_eq!(b.read(&mut buf).unwrap(), 0);
+    }
 }
@@ -0,0 +1,515 @@
+//! Message Stream - `std::io::Read`/`Write` over message transports
+//!
+//! Socket connections exchange discrete [`Message`]s, but a lot of existing
+//! code (tar and zip writers, streaming serializers, protobuf codecs) wants
+//! a byte stream. [`MessageStream`] bridges the two: writes are chunked into
+//! binary messages and reads reassemble them on the other side. An empty
+//! binary message marks the end of the stream, so the reader sees EOF once
+//! the writer calls [`MessageStream::finish`].
+//!
+//! With the `async` feature, [`AsyncMessageStream`] provides the same over
+//! Tokio's `AsyncRead`/`AsyncWrite`.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ipckit::{MessageStream, SocketClient};
+//! use std::io::Write;
+//!
+//! let client = SocketClient::connect_default()?;
+//! let mut stream = MessageStream::new(client);
+//! let mut archive = tar::Builder::new(&mut stream);
+//! archive.append_dir_all("assets", "./assets")?;
+//! archive.finish()?;
+//! drop(archive);
+//! stream.finish()?;
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::socket_server::{Connection, Message, MessageType, SocketClient};
+use std::io::{self, Read, Write};
+
+/// Default maximum payload carried by a single message.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A bidirectional transport of [`Message`]s.
+pub trait MessageTransport {
+    /// Send a message.
+    fn send_message(&mut self, msg: &Message) -> Result<()>;
+
+    /// Receive the next message.
+    fn recv_message(&mut self) -> Result<Message>;
+}
+
+impl MessageTransport for Connection {
+    fn send_message(&mut self, msg: &Message) -> Result<()> {
+        self.send(msg)
+    }
+
+    fn recv_message(&mut self) -> Result<Message> {
+        self.recv()
+    }
+}
+
+impl MessageTransport for SocketClient {
+    fn send_message(&mut self, msg: &Message) -> Result<()> {
+        self.send(msg)
+    }
+
+    fn recv_message(&mut self) -> Result<Message> {
+        self.recv()
+    }
+}
+
+impl<T: MessageTransport + ?Sized> MessageTransport for &mut T {
+    fn send_message(&mut self, msg: &Message) -> Result<()> {
+        (**self).send_message(msg)
+    }
+
+    fn recv_message(&mut self) -> Result<Message> {
+        (**self).recv_message()
+    }
+}
+
+impl<T: MessageTransport + ?Sized> MessageTransport for Box<T> {
+    fn send_message(&mut self, msg: &Message) -> Result<()> {
+        (**self).send_message(msg)
+    }
+
+    fn recv_message(&mut self) -> Result<Message> {
+        (**self).recv_message()
+    }
+}
+
+/// Read side state shared by the sync and async streams.
+#[derive(Default)]
+struct ReadState {
+    chunk: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl ReadState {
+    /// Copy buffered bytes into `buf`, returning how many were copied.
+    fn copy_to(&mut self, buf: &mut [u8]) -> usize {
+        let n = (self.chunk.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        n
+    }
+
+    fn has_data(&self) -> bool {
+        self.pos < self.chunk.len()
+    }
+
+    /// Take in the next received message.
+    fn accept(&mut self, msg: Message) -> io::Result<()> {
+        if msg.msg_type != MessageType::Binary {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected {:?} message in byte stream", msg.msg_type),
+            ));
+        }
+        let data = msg.as_binary().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "malformed binary message")
+        })?;
+        self.eof = data.is_empty();
+        self.chunk = data;
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+/// A byte stream carried over a [`MessageTransport`].
+///
+/// Writes are buffered up to the chunk size; call [`flush`](Write::flush)
+/// to send a partial chunk and [`finish`](Self::finish) to signal EOF. A
+/// stream that wrote data is finished automatically when dropped, ignoring
+/// errors.
+pub struct MessageStream<T: MessageTransport> {
+    transport: T,
+    chunk_size: usize,
+    write_buf: Vec<u8>,
+    read: ReadState,
+    wrote: bool,
+    finished: bool,
+}
+
+impl<T: MessageTransport> MessageStream<T> {
+    /// Create a stream over `transport` with the default chunk size.
+    pub fn new(transport: T) -> Self {
+        Self::with_chunk_size(transport, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Create a stream that sends at most `chunk_size` bytes per message.
+    pub fn with_chunk_size(transport: T, chunk_size: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+        Self {
+            transport,
+            chunk_size,
+            write_buf: Vec::with_capacity(chunk_size),
+            read: ReadState::default(),
+            wrote: false,
+            finished: false,
+        }
+    }
+
+    /// Get the chunk size.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get a reference to the underlying transport.
+    pub fn get_ref(&self) -> &T {
+        &self.transport
+    }
+
+    /// Get a mutable reference to the underlying transport.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Flush buffered data and signal end-of-stream to the reader.
+    pub fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.send_buffered()?;
+        self.transport.send_message(&Message::binary(Vec::new()))?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Check whether the reader side has reached end-of-stream.
+    pub fn is_eof(&self) -> bool {
+        self.read.eof && !self.read.has_data()
+    }
+
+    fn send_buffered(&mut self) -> Result<()> {
+        if self.write_buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::take(&mut self.write_buf);
+        self.transport.send_message(&Message::binary(chunk))?;
+        self.write_buf.reserve(self.chunk_size);
+        Ok(())
+    }
+}
+
+impl<T: MessageTransport> Read for MessageStream<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.read.has_data() {
+                return Ok(self.read.copy_to(buf));
+            }
+            if self.read.eof {
+                return Ok(0);
+            }
+            let msg = self.transport.recv_message()?;
+            self.read.accept(msg)?;
+        }
+    }
+}
+
+impl<T: MessageTransport> Write for MessageStream<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.finished {
+            return Err(IpcError::Closed.into());
+        }
+        let n = (self.chunk_size - self.write_buf.len()).min(buf.len());
+        self.write_buf.extend_from_slice(&buf[..n]);
+        self.wrote = true;
+        if self.write_buf.len() >= self.chunk_size {
+            self.send_buffered()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffered()?;
+        Ok(())
+    }
+}
+
+impl<T: MessageTransport> Drop for MessageStream<T> {
+    fn drop(&mut self) {
+        if self.wrote && !self.finished {
+            let _ = self.finish();
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use async_stream::AsyncMessageStream;
+
+#[cfg(feature = "async")]
+mod async_stream {
+    use super::{MessageTransport, ReadState, DEFAULT_CHUNK_SIZE};
+    use crate::error::IpcError;
+    use crate::socket_server::Message;
+    use std::future::Future;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{ready, Context, Poll};
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+    use tokio::task::JoinHandle;
+
+    /// Result of a blocking transport operation.
+    enum Op {
+        Recv(io::Result<Message>),
+        Send(io::Result<()>),
+    }
+
+    enum State<T> {
+        Idle(Option<T>),
+        Busy(JoinHandle<(T, Op)>),
+    }
+
+    /// [`MessageStream`](super::MessageStream) for Tokio.
+    ///
+    /// Transport calls run on Tokio's blocking pool, one at a time, so the
+    /// transport must be `Send + 'static`. As with `tokio::fs::File`, a write
+    /// returns once the data is buffered; a failed send is reported by the
+    /// next operation. Call `shutdown()` to signal end-of-stream.
+    pub struct AsyncMessageStream<T> {
+        state: State<T>,
+        chunk_size: usize,
+        write_buf: Vec<u8>,
+        read: ReadState,
+        send_error: Option<io::Error>,
+        finished: bool,
+    }
+
+    // The transport is moved in and out of blocking tasks, never pinned
+    impl<T> Unpin for AsyncMessageStream<T> {}
+
+    impl<T: MessageTransport + Send + 'static> AsyncMessageStream<T> {
+        /// Create a stream over `transport` with the default chunk size.
+        pub fn new(transport: T) -> Self {
+            Self::with_chunk_size(transport, DEFAULT_CHUNK_SIZE)
+        }
+
+        /// Create a stream that sends at most `chunk_size` bytes per message.
+        pub fn with_chunk_size(transport: T, chunk_size: usize) -> Self {
+            let chunk_size = chunk_size.max(1);
+            Self {
+                state: State::Idle(Some(transport)),
+                chunk_size,
+                write_buf: Vec::with_capacity(chunk_size),
+                read: ReadState::default(),
+                send_error: None,
+                finished: false,
+            }
+        }
+
+        /// Get the chunk size.
+        pub fn chunk_size(&self) -> usize {
+            self.chunk_size
+        }
+
+        /// Wait for the in-flight operation, if any, and apply its result.
+        fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            if let State::Busy(ref mut handle) = self.state {
+                let (transport, op) = ready!(Pin::new(handle).poll(cx))
+                    .map_err(|e| io::Error::other(e.to_string()))?;
+                self.state = State::Idle(Some(transport));
+                match op {
+                    Op::Recv(Ok(msg)) => self.read.accept(msg)?,
+                    Op::Recv(Err(e)) => return Poll::Ready(Err(e)),
+                    Op::Send(Ok(())) => {}
+                    Op::Send(Err(e)) => self.send_error = Some(e),
+                }
+            }
+            match self.send_error.take() {
+                Some(e) => Poll::Ready(Err(e)),
+                None => Poll::Ready(Ok(())),
+            }
+        }
+
+        fn take_transport(&mut self) -> T {
+            match self.state {
+                State::Idle(ref mut transport) => transport.take().expect("transport in use"),
+                State::Busy(_) => unreachable!("take_transport called while busy"),
+            }
+        }
+
+        fn start_recv(&mut self) {
+            let mut transport = self.take_transport();
+            self.state = State::Busy(tokio::task::spawn_blocking(move || {
+                let result = transport.recv_message().map_err(io::Error::from);
+                (transport, Op::Recv(result))
+            }));
+        }
+
+        fn start_send(&mut self, data: Vec<u8>) {
+            let mut transport = self.take_transport();
+            self.state = State::Busy(tokio::task::spawn_blocking(move || {
+                let result = transport
+                    .send_message(&Message::binary(data))
+                    .map_err(io::Error::from);
+                (transport, Op::Send(result))
+            }));
+        }
+    }
+
+    impl<T: MessageTransport + Send + 'static> AsyncRead for AsyncMessageStream<T> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            loop {
+                ready!(this.poll_idle(cx))?;
+                if this.read.has_data() {
+                    let n = this.read.copy_to(buf.initialize_unfilled());
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                if this.read.eof || buf.remaining() == 0 {
+                    return Poll::Ready(Ok(()));
+                }
+                this.start_recv();
+            }
+        }
+    }
+
+    impl<T: MessageTransport + Send + 'static> AsyncWrite for AsyncMessageStream<T> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            data: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            ready!(this.poll_idle(cx))?;
+            if this.finished {
+                return Poll::Ready(Err(IpcError::Closed.into()));
+            }
+            let n = (this.chunk_size - this.write_buf.len()).min(data.len());
+            this.write_buf.extend_from_slice(&data[..n]);
+            if this.write_buf.len() >= this.chunk_size {
+                let chunk =
+                    std::mem::replace(&mut this.write_buf, Vec::with_capacity(this.chunk_size));
+                this.start_send(chunk);
+            }
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            loop {
+                ready!(this.poll_idle(cx))?;
+                if this.write_buf.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                let chunk =
+                    std::mem::replace(&mut this.write_buf, Vec::with_capacity(this.chunk_size));
+                this.start_send(chunk);
+            }
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            loop {
+                ready!(Pin::new(&mut *this).poll_flush(cx))?;
+                if this.finished {
+                    return Poll::Ready(Ok(()));
+                }
+                this.finished = true;
+                this.start_send(Vec::new());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thread_channel::{ThreadChannel, ThreadReceiver, ThreadSender};
+
+    struct ThreadTransport {
+        tx: ThreadSender<Message>,
+        rx: ThreadReceiver<Message>,
+    }
+
+    impl MessageTransport for ThreadTransport {
+        fn send_message(&mut self, msg: &Message) -> Result<()> {
+            self.tx.send(msg.clone())
+        }
+
+        fn recv_message(&mut self) -> Result<Message> {
+            self.rx.recv()
+        }
+    }
+
+    fn transport_pair() -> (ThreadTransport, ThreadTransport) {
+        let (tx_a, rx_a) = ThreadChannel::unbounded();
+        let (tx_b, rx_b) = ThreadChannel::unbounded();
+        (
+            ThreadTransport { tx: tx_a, rx: rx_b },
+            ThreadTransport { tx: tx_b, rx: rx_a },
+        )
+    }
+
+    #[test]
+    fn test_stream_round_trip() {
+        let (a, b) = transport_pair();
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+
+        let expected = data.clone();
+        let writer = std::thread::spawn(move || {
+            let mut stream = MessageStream::with_chunk_size(a, 1024);
+            stream.write_all(&data).unwrap();
+            stream.finish().unwrap();
+            // Writing after finish fails
+            assert!(stream.write(b"late").is_err());
+        });
+
+        let mut stream = MessageStream::new(b);
+        let mut received = Vec::new();
+        stream.read_to_end(&mut received).unwrap();
+        assert_eq!(received, expected);
+        assert!(stream.is_eof());
+        writer.join().unwrap();
+
+        // A stream dropped after writing finishes itself
+        let (a, b) = transport_pair();
+        {
+            let mut stream = MessageStream::new(a);
+            stream.write_all(b"bye").unwrap();
+        }
+        let mut received = String::new();
+        MessageStream::new(b).read_to_string(&mut received).unwrap();
+        assert_eq!(received, "bye");
+    }
+
+    #[test]
+    fn test_stream_rejects_non_binary() {
+        let (mut a, b) = transport_pair();
+        a.send_message(&Message::text("hello")).unwrap();
+        let mut stream = MessageStream::new(b);
+        let err = stream.read(&mut [0u8; 16]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_stream_round_trip() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (a, b) = transport_pair();
+        let data: Vec<u8> = (0..5000u32).map(|i| (i % 13) as u8).collect();
+
+        let mut writer = AsyncMessageStream::with_chunk_size(a, 512);
+        writer.write_all(&data).await.unwrap();
+        writer.shutdown().await.unwrap();
+
+        let mut reader = AsyncMessageStream::new(b);
+        let mut received = Vec::new();
+        reader.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, data);
+    }
+}
@@ -20,9 +20,17 @@
 //! log::info!("IPC metrics: {}", metrics.to_json());
 //! ```
 
+use crate::buffer_pool::BufferPool;
+use crate::channel::{IpcChannel, IpcReceiver, IpcSender};
+use crate::error::{IpcError, Result};
+use crate::thread_channel::{ThreadReceiver, ThreadSender};
+use bytes::Bytes;
 use parking_lot::RwLock;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 
 /// Atomic metrics counters for thread-safe updates.
@@ -218,6 +226,16 @@ impl ChannelMetrics {
         self.latency_histogram.read().percentile(percentile)
     }
 
+    /// Get latency at a quantile in `0.0..=1.0` (e.g., 0.999 for p99.9).
+    pub fn latency_quantile(&self, q: f64) -> u64 {
+        self.latency_histogram.read().quantile(q)
+    }
+
+    /// Get a copy of the latency histogram.
+    pub fn latency_histogram(&self) -> LatencyHistogram {
+        self.latency_histogram.read().clone()
+    }
+
     /// Get elapsed time since metrics started.
     pub fn elapsed(&self) -> Duration {
         self.start_time
@@ -297,6 +315,7 @@ impl ChannelMetrics {
             p50_latency_us: self.latency_percentile(50),
             p95_latency_us: self.latency_percentile(95),
             p99_latency_us: self.latency_percentile(99),
+            p999_latency_us: self.latency_quantile(0.999),
             elapsed_secs: self.elapsed().as_secs_f64(),
             send_throughput: self.send_throughput(),
             recv_throughput: self.recv_throughput(),
@@ -385,19 +404,13 @@ impl ChannelMetrics {
         output.push_str(&format!(
             "# HELP {prefix}_latency_microseconds Latency in microseconds\n"
         ));
-        output.push_str(&format!("# TYPE {prefix}_latency_microseconds summary\n"));
-        output.push_str(&format!(
-            "{prefix}_latency_microseconds{{quantile=\"0.5\"}} {}\n",
-            snapshot.p50_latency_us
-        ));
-        output.push_str(&format!(
-            "{prefix}_latency_microseconds{{quantile=\"0.95\"}} {}\n",
-            snapshot.p95_latency_us
-        ));
-        output.push_str(&format!(
-            "{prefix}_latency_microseconds{{quantile=\"0.99\"}} {}\n",
-            snapshot.p99_latency_us
-        ));
+        output.push_str(&format!("# TYPE {prefix}_latency_microseconds histogram\n"));
+        self.latency_histogram.read().write_prometheus(
+            &mut output,
+            &format!("{prefix}_latency_microseconds"),
+            "",
+            DEFAULT_LATENCY_BOUNDS_US,
+        );
 
         output.push_str(&format!(
             "# HELP {prefix}_throughput_messages_per_second Message throughput\n"
@@ -456,6 +469,9 @@ pub struct MetricsSnapshot {
     pub p95_latency_us: u64,
     /// 99th percentile latency
     pub p99_latency_us: u64,
+    /// 99.9th percentile latency
+    #[serde(default)]
+    pub p999_latency_us: u64,
     /// Elapsed time in seconds
     pub elapsed_secs: f64,
     /// Send throughput (messages/second)
@@ -468,74 +484,229 @@ pub struct MetricsSnapshot {
     pub recv_bandwidth: f64,
 }
 
-/// A simple histogram for latency distribution.
-#[derive(Debug, Default)]
-struct LatencyHistogram {
-    // Buckets: 0-10us, 10-100us, 100us-1ms, 1-10ms, 10-100ms, 100ms-1s, 1s+
-    buckets: [u64; 7],
-    // For percentile calculation, keep sorted samples (up to a limit)
-    samples: Vec<u64>,
-    max_samples: usize,
+/// Number of bits of sub-bucket precision within each power of two.
+///
+/// 7 bits gives 128 sub-buckets per octave, so every recorded value is
+/// reproduced to within 1/128 (< 0.8%) of its true value.
+const SUB_BUCKET_BITS: u32 = 7;
+const SUB_BUCKET_COUNT: u64 = 1 << SUB_BUCKET_BITS;
+/// Values below this are stored exactly, one bucket per value.
+const LINEAR_LIMIT: u64 = SUB_BUCKET_COUNT << 1;
+
+/// Default `le` bounds (in microseconds) used for Prometheus histogram export.
+pub const DEFAULT_LATENCY_BOUNDS_US: &[u64] = &[
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000,
+    500_000, 1_000_000, 2_500_000, 5_000_000, 10_000_000,
+];
+
+/// A log-scale bucketed latency histogram.
+///
+/// Values are grouped HdrHistogram-style: each power-of-two range is split
+/// into 128 linear sub-buckets, so quantiles such as p99.9 are accurate to
+/// better than 1% regardless of how many values are recorded. Histograms
+/// from different channels can be combined with [`merge`](Self::merge).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// Bucket counts, grown on demand up to the highest recorded bucket.
+    counts: Vec<u64>,
+    count: u64,
+    sum: u64,
+    min: u64,
+    max: u64,
 }
 
 impl LatencyHistogram {
-    #[allow(dead_code)]
-    fn new() -> Self {
-        Self {
-            buckets: [0; 7],
-            samples: Vec::new(),
-            max_samples: 10000,
-        }
-    }
-
-    fn record(&mut self, latency_us: u64) {
-        // Update bucket
-        let bucket = match latency_us {
-            0..=10 => 0,
-            11..=100 => 1,
-            101..=1000 => 2,
-            1001..=10000 => 3,
-            10001..=100000 => 4,
-            100001..=1000000 => 5,
-            _ => 6,
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a single value.
+    pub fn record(&mut self, value: u64) {
+        self.record_n(value, 1);
+    }
+
+    /// Record a value `n` times.
+    pub fn record_n(&mut self, value: u64, n: u64) {
+        if n == 0 {
+            return;
+        }
+        let index = bucket_index(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += n;
+        self.min = if self.count == 0 {
+            value
+        } else {
+            self.min.min(value)
         };
-        self.buckets[bucket] += 1;
+        self.max = self.max.max(value);
+        self.count += n;
+        self.sum = self.sum.saturating_add(value.saturating_mul(n));
+    }
 
-        // Store sample for percentile calculation
-        if self.samples.len() < self.max_samples {
-            self.samples.push(latency_us);
+    /// Add every value recorded in `other` to this histogram.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        if other.count == 0 {
+            return;
+        }
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (mine, theirs) in self.counts.iter_mut().zip(&other.counts) {
+            *mine += theirs;
+        }
+        self.min = if self.count == 0 {
+            other.min
         } else {
-            // Reservoir sampling
-            let idx = rand_usize() % (self.samples.len() + 1);
-            if idx < self.samples.len() {
-                self.samples[idx] = latency_us;
+            self.min.min(other.min)
+        };
+        self.max = self.max.max(other.max);
+        self.count += other.count;
+        self.sum = self.sum.saturating_add(other.sum);
+    }
+
+    /// Get the number of recorded values.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Get the sum of all recorded values.
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    /// Get the smallest recorded value.
+    pub fn min(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    /// Get the largest recorded value.
+    pub fn max(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Check if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Get the value at a quantile in `0.0..=1.0` (e.g. `0.999` for p99.9).
+    ///
+    /// Returns 0 for an empty histogram.
+    pub fn quantile(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let q = q.clamp(0.0, 1.0);
+        let rank = ((q * self.count as f64).ceil() as u64).clamp(1, self.count);
+
+        let mut seen = 0;
+        for (index, &n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return bucket_high(index).clamp(self.min, self.max);
             }
         }
+        self.max
     }
 
-    fn percentile(&self, p: u8) -> u64 {
-        if self.samples.is_empty() {
+    /// Get a percentile (e.g. 99 for p99).
+    pub fn percentile(&self, p: u8) -> u64 {
+        self.quantile(p as f64 / 100.0)
+    }
+
+    /// Get the number of recorded values less than or equal to `bound`.
+    ///
+    /// Exact when `bound` falls on a bucket edge, otherwise accurate to the
+    /// histogram's precision.
+    pub fn count_le(&self, bound: u64) -> u64 {
+        if self.count == 0 || bound < self.min {
             return 0;
         }
+        if bound >= self.max {
+            return self.count;
+        }
+        let last = bucket_index(bound);
+        let mut total: u64 = self.counts.iter().take(last).sum();
+        if bucket_high(last) <= bound {
+            total += self.counts.get(last).copied().unwrap_or(0);
+        }
+        total
+    }
 
-        let mut sorted = self.samples.clone();
-        sorted.sort_unstable();
+    /// Iterate over non-empty buckets as `(low, high, count)`, where `low` and
+    /// `high` are the inclusive value range covered by the bucket.
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, u64, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &n)| n > 0)
+            .map(|(index, &n)| (bucket_low(index), bucket_high(index), n))
+    }
+
+    /// Clear all recorded values.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Write this histogram as a Prometheus `histogram` family sample set.
+    ///
+    /// `labels` is an already-formatted label list (e.g. `channel="a"`)
+    /// prepended to the `le` label; pass an empty string for none.
+    fn write_prometheus(&self, output: &mut String, name: &str, labels: &str, bounds: &[u64]) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        for &bound in bounds {
+            output.push_str(&format!(
+                "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {}\n",
+                self.count_le(bound)
+            ));
+        }
+        output.push_str(&format!(
+            "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}\n",
+            self.count
+        ));
+        let braces = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        output.push_str(&format!("{name}_sum{braces} {}\n", self.sum));
+        output.push_str(&format!("{name}_count{braces} {}\n", self.count));
+    }
+}
 
-        let idx = ((p as f64 / 100.0) * (sorted.len() - 1) as f64) as usize;
-        sorted[idx]
+/// Map a value to its bucket index.
+fn bucket_index(value: u64) -> usize {
+    if value < LINEAR_LIMIT {
+        return value as usize;
     }
+    let exp = 63 - value.leading_zeros();
+    let sub = (value >> (exp - SUB_BUCKET_BITS)) - SUB_BUCKET_COUNT;
+    (LINEAR_LIMIT + (exp - SUB_BUCKET_BITS - 1) as u64 * SUB_BUCKET_COUNT + sub) as usize
+}
 
-    fn reset(&mut self) {
-        self.buckets = [0; 7];
-        self.samples.clear();
+/// Get the smallest value that maps to a bucket.
+fn bucket_low(index: usize) -> u64 {
+    let index = index as u64;
+    if index < LINEAR_LIMIT {
+        return index;
     }
+    let offset = index - LINEAR_LIMIT;
+    let exp = (offset / SUB_BUCKET_COUNT) as u32 + SUB_BUCKET_BITS + 1;
+    let sub = offset % SUB_BUCKET_COUNT;
+    (SUB_BUCKET_COUNT + sub) << (exp - SUB_BUCKET_BITS)
 }
 
-/// Simple pseudo-random number for reservoir sampling.
-fn rand_usize() -> usize {
-    use std::collections::hash_map::RandomState;
-    use std::hash::{BuildHasher, Hasher};
-    RandomState::new().build_hasher().finish() as usize
+/// Get the largest value that maps to a bucket.
+fn bucket_high(index: usize) -> u64 {
+    let index_u64 = index as u64;
+    if index_u64 < LINEAR_LIMIT {
+        return index_u64;
+    }
+    let exp = ((index_u64 - LINEAR_LIMIT) / SUB_BUCKET_COUNT) as u32 + SUB_BUCKET_BITS + 1;
+    bucket_low(index) + ((1u64 << (exp - SUB_BUCKET_BITS)) - 1)
 }
 
 /// Trait for channels that support metrics.
@@ -547,7 +718,7 @@ pub trait MeteredChannel {
 /// A wrapper that adds metrics to any channel.
 pub struct MeteredWrapper<C> {
     inner: C,
-    metrics: ChannelMetrics,
+    metrics: Arc<ChannelMetrics>,
 }
 
 impl<C> MeteredWrapper<C> {
@@ -555,10 +726,22 @@ impl<C> MeteredWrapper<C> {
     pub fn new(channel: C) -> Self {
         Self {
             inner: channel,
-            metrics: ChannelMetrics::new(),
+            metrics: Arc::new(ChannelMetrics::new()),
         }
     }
 
+    /// Register this wrapper's metrics under `name` in the global
+    /// [`MetricsRegistry`].
+    pub fn registered(self, name: &str) -> Self {
+        MetricsRegistry::global().register(name, Arc::clone(&self.metrics));
+        self
+    }
+
+    /// Get a shared handle to the metrics, e.g. for registering elsewhere.
+    pub fn shared_metrics(&self) -> Arc<ChannelMetrics> {
+        Arc::clone(&self.metrics)
+    }
+
     /// Get a reference to the inner channel.
     pub fn inner(&self) -> &C {
         &self.inner
@@ -581,6 +764,241 @@ impl<C> MeteredChannel for MeteredWrapper<C> {
     }
 }
 
+impl<C> MeteredWrapper<C> {
+    /// Record the outcome of a send of `bytes` bytes.
+    fn track_send<R>(&self, bytes: usize, result: Result<R>) -> Result<R> {
+        match &result {
+            Ok(_) => self.metrics.record_send(bytes),
+            Err(_) => self.metrics.record_send_error(),
+        }
+        result
+    }
+
+    /// Record the outcome of a receive, using `len` to size successful results.
+    fn track_recv<R>(&self, result: Result<R>, len: impl FnOnce(&R) -> usize) -> Result<R> {
+        match &result {
+            Ok(value) => self.metrics.record_recv(len(value)),
+            // Empty non-blocking polls and timeouts are not failures
+            Err(e) if e.is_would_block() || e.is_timeout() => {}
+            Err(_) => self.metrics.record_recv_error(),
+        }
+        result
+    }
+}
+
+impl<C: Read> Read for MeteredWrapper<C> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(n) => {
+                if n > 0 {
+                    self.metrics.record_recv(n);
+                }
+                Ok(n)
+            }
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::WouldBlock {
+                    self.metrics.record_recv_error();
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<C: Write> Write for MeteredWrapper<C> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.inner.write(buf) {
+            Ok(n) => {
+                self.metrics.record_send(n);
+                Ok(n)
+            }
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::WouldBlock {
+                    self.metrics.record_send_error();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl MeteredWrapper<IpcChannel<Vec<u8>>> {
+    /// Send raw bytes, recording the message in the metrics.
+    pub fn send_bytes(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        let data = data.as_ref();
+        let result = self.inner.send_bytes(data);
+        self.track_send(data.len(), result)
+    }
+
+    /// Receive raw bytes, recording the message in the metrics.
+    pub fn recv_bytes(&mut self) -> Result<Vec<u8>> {
+        let result = self.inner.recv_bytes();
+        self.track_recv(result, Vec::len)
+    }
+
+    /// Receive raw bytes into a pooled buffer, recording the message in the
+    /// metrics.
+    pub fn recv_pooled(&mut self, pool: &BufferPool) -> Result<Bytes> {
+        let result = self.inner.recv_pooled(pool);
+        self.track_recv(result, Bytes::len)
+    }
+
+    /// Measure round-trip latency with an echo frame.
+    ///
+    /// Sends `payload` and waits for the peer to send it back (see
+    /// [`echo`](Self::echo)). The elapsed time is recorded with
+    /// [`ChannelMetrics::record_latency`] and returned.
+    pub fn round_trip(&mut self, payload: &[u8]) -> Result<Duration> {
+        let start = Instant::now();
+        self.send_bytes(payload)?;
+        let echoed = self.recv_bytes()?;
+        let elapsed = start.elapsed();
+
+        if echoed != payload {
+            self.metrics.record_recv_error();
+            return Err(IpcError::InvalidState(
+                "Echo frame does not match the sent payload".into(),
+            ));
+        }
+
+        self.metrics.record_latency(elapsed);
+        Ok(elapsed)
+    }
+
+    /// Answer one [`round_trip`](Self::round_trip) probe from the peer.
+    ///
+    /// Receives a single frame and sends it back unchanged. Returns the size
+    /// of the echoed frame.
+    pub fn echo(&mut self) -> Result<usize> {
+        let frame = self.recv_bytes()?;
+        self.send_bytes(&frame)?;
+        Ok(frame.len())
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> MeteredWrapper<IpcChannel<T>> {
+    /// Send a typed message, recording its serialized size in the metrics.
+    pub fn send(&mut self, msg: &T) -> Result<()> {
+        let data = match serde_json::to_vec(msg) {
+            Ok(data) => data,
+            Err(e) => {
+                self.metrics.record_send_error();
+                return Err(IpcError::serialization(e.to_string()));
+            }
+        };
+        let result = self.inner.send_raw(&data);
+        self.track_send(data.len(), result)
+    }
+
+    /// Receive a typed message, recording its serialized size in the metrics.
+    pub fn recv(&mut self) -> Result<T> {
+        let result = self.inner.recv_raw();
+        let data = self.track_recv(result, Vec::len)?;
+        serde_json::from_slice(&data).map_err(|e| IpcError::deserialization(e.to_string()))
+    }
+}
+
+impl MeteredWrapper<IpcSender<Vec<u8>>> {
+    /// Send raw bytes, recording the message in the metrics.
+    pub fn send_bytes(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        let data = data.as_ref();
+        let result = self.inner.send_bytes(data);
+        self.track_send(data.len(), result)
+    }
+}
+
+impl<T: Serialize> MeteredWrapper<IpcSender<T>> {
+    /// Send a typed message, recording its serialized size in the metrics.
+    pub fn send(&mut self, msg: &T) -> Result<()> {
+        let data = match serde_json::to_vec(msg) {
+            Ok(data) => data,
+            Err(e) => {
+                self.metrics.record_send_error();
+                return Err(IpcError::serialization(e.to_string()));
+            }
+        };
+        let result = self.inner.send_raw(&data);
+        self.track_send(data.len(), result)
+    }
+}
+
+impl MeteredWrapper<IpcReceiver<Vec<u8>>> {
+    /// Receive raw bytes, recording the message in the metrics.
+    pub fn recv_bytes(&mut self) -> Result<Vec<u8>> {
+        let result = self.inner.recv_bytes();
+        self.track_recv(result, Vec::len)
+    }
+
+    /// Receive raw bytes into a pooled buffer, recording the message in the
+    /// metrics.
+    pub fn recv_pooled(&mut self, pool: &BufferPool) -> Result<Bytes> {
+        let result = self.inner.recv_pooled(pool);
+        self.track_recv(result, Bytes::len)
+    }
+}
+
+impl<T: DeserializeOwned> MeteredWrapper<IpcReceiver<T>> {
+    /// Receive a typed message, recording its serialized size in the metrics.
+    pub fn recv(&mut self) -> Result<T> {
+        let result = self.inner.recv_raw();
+        let data = self.track_recv(result, Vec::len)?;
+        serde_json::from_slice(&data).map_err(|e| IpcError::deserialization(e.to_string()))
+    }
+}
+
+// Thread channels move values rather than bytes, so message sizes are
+// reported as `size_of::<T>()` and the queue depth is sampled on each call.
+
+impl<T> MeteredWrapper<ThreadSender<T>> {
+    /// Send a message, blocking if the channel is full.
+    pub fn send(&self, msg: T) -> Result<()> {
+        let result = self.inner.send(msg);
+        self.metrics.set_queue_depth(self.inner.len() as u64);
+        self.track_send(std::mem::size_of::<T>(), result)
+    }
+
+    /// Try to send a message without blocking.
+    pub fn try_send(&self, msg: T) -> Result<()> {
+        let result = self.inner.try_send(msg);
+        self.metrics.set_queue_depth(self.inner.len() as u64);
+        self.track_send(std::mem::size_of::<T>(), result)
+    }
+
+    /// Send a message with a timeout.
+    pub fn send_timeout(&self, msg: T, timeout: Duration) -> Result<()> {
+        let result = self.inner.send_timeout(msg, timeout);
+        self.metrics.set_queue_depth(self.inner.len() as u64);
+        self.track_send(std::mem::size_of::<T>(), result)
+    }
+}
+
+impl<T> MeteredWrapper<ThreadReceiver<T>> {
+    /// Receive a message, blocking until one is available.
+    pub fn recv(&self) -> Result<T> {
+        let result = self.inner.recv();
+        self.metrics.set_queue_depth(self.inner.len() as u64);
+        self.track_recv(result, |_| std::mem::size_of::<T>())
+    }
+
+    /// Try to receive a message without blocking.
+    pub fn try_recv(&self) -> Result<T> {
+        let result = self.inner.try_recv();
+        self.metrics.set_queue_depth(self.inner.len() as u64);
+        self.track_recv(result, |_| std::mem::size_of::<T>())
+    }
+
+    /// Receive a message with a timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T> {
+        let result = self.inner.recv_timeout(timeout);
+        self.metrics.set_queue_depth(self.inner.len() as u64);
+        self.track_recv(result, |_| std::mem::size_of::<T>())
+    }
+}
+
 /// Extension trait for adding metrics to channels.
 pub trait WithMetrics: Sized {
     /// Wrap this channel with metrics tracking.
@@ -765,6 +1183,15 @@ impl AggregatedMetrics {
         self.channels.read().len()
     }
 
+    /// Get the latency histograms of all channels merged into one.
+    pub fn latency_histogram(&self) -> LatencyHistogram {
+        let mut merged = LatencyHistogram::new();
+        for metrics in self.channels.read().iter() {
+            merged.merge(&metrics.latency_histogram.read());
+        }
+        merged
+    }
+
     /// Get snapshots from all channels.
     pub fn snapshots(&self) -> Vec<MetricsSnapshot> {
         self.channels.read().iter().map(|m| m.snapshot()).collect()
@@ -836,10 +1263,245 @@ impl AggregatedMetrics {
             self.total_bytes_received()
         ));
 
+        output.push_str(&format!(
+            "# HELP {prefix}_latency_microseconds Latency in microseconds across all channels\n"
+        ));
+        output.push_str(&format!("# TYPE {prefix}_latency_microseconds histogram\n"));
+        self.latency_histogram().write_prometheus(
+            &mut output,
+            &format!("{prefix}_latency_microseconds"),
+            "",
+            DEFAULT_LATENCY_BOUNDS_US,
+        );
+
+        output
+    }
+}
+
+/// A registry of named channel metrics with aggregated export.
+///
+/// Channels register their [`ChannelMetrics`] under a name, and exporters
+/// read everything back from one place instead of plumbing each metrics
+/// instance by hand. A process-wide instance is available through
+/// [`MetricsRegistry::global`].
+#[derive(Debug, Default)]
+pub struct MetricsRegistry {
+    channels: RwLock<BTreeMap<String, Arc<ChannelMetrics>>>,
+    gauges: RwLock<BTreeMap<String, i64>>,
+}
+
+impl MetricsRegistry {
+    /// Create a new, empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the process-wide registry.
+    pub fn global() -> &'static MetricsRegistry {
+        static GLOBAL: OnceLock<MetricsRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(MetricsRegistry::new)
+    }
+
+    /// Register metrics under a name, replacing any previous entry.
+    ///
+    /// Returns the metrics previously registered under that name.
+    pub fn register(
+        &self,
+        name: &str,
+        metrics: Arc<ChannelMetrics>,
+    ) -> Option<Arc<ChannelMetrics>> {
+        self.channels.write().insert(name.to_string(), metrics)
+    }
+
+    /// Get the metrics registered under a name, creating them if missing.
+    pub fn get_or_create(&self, name: &str) -> Arc<ChannelMetrics> {
+        if let Some(metrics) = self.get(name) {
+            return metrics;
+        }
+        Arc::clone(
+            self.channels
+                .write()
+                .entry(name.to_string())
+                .or_insert_with(|| Arc::new(ChannelMetrics::new())),
+        )
+    }
+
+    /// Get the metrics registered under a name.
+    pub fn get(&self, name: &str) -> Option<Arc<ChannelMetrics>> {
+        self.channels.read().get(name).cloned()
+    }
+
+    /// Remove the metrics registered under a name.
+    pub fn unregister(&self, name: &str) -> Option<Arc<ChannelMetrics>> {
+        self.channels.write().remove(name)
+    }
+
+    /// Get the registered channel names, in sorted order.
+    pub fn names(&self) -> Vec<String> {
+        self.channels.read().keys().cloned().collect()
+    }
+
+    /// Get the number of registered channels.
+    pub fn len(&self) -> usize {
+        self.channels.read().len()
+    }
+
+    /// Check if no channels are registered.
+    pub fn is_empty(&self) -> bool {
+        self.channels.read().is_empty()
+    }
+
+    /// Remove all registered channels and gauges.
+    pub fn clear(&self) {
+        self.channels.write().clear();
+        self.gauges.write().clear();
+    }
+
+    /// Add `delta` to a process-level gauge (such as live connections),
+    /// creating it at zero if missing. Returns the new value.
+    pub fn add_gauge(&self, name: &str, delta: i64) -> i64 {
+        let mut gauges = self.gauges.write();
+        let value = gauges.entry(name.to_string()).or_insert(0);
+        *value += delta;
+        *value
+    }
+
+    /// Set a gauge to `value`.
+    pub fn set_gauge(&self, name: &str, value: i64) {
+        self.gauges.write().insert(name.to_string(), value);
+    }
+
+    /// Get the value of a gauge.
+    pub fn gauge(&self, name: &str) -> Option<i64> {
+        self.gauges.read().get(name).copied()
+    }
+
+    /// Get all gauges, keyed by name.
+    pub fn gauges(&self) -> BTreeMap<String, i64> {
+        self.gauges.read().clone()
+    }
+
+    /// Get the latency histograms of all registered channels merged into one.
+    pub fn latency_histogram(&self) -> LatencyHistogram {
+        let mut merged = LatencyHistogram::new();
+        for metrics in self.channels.read().values() {
+            merged.merge(&metrics.latency_histogram.read());
+        }
+        merged
+    }
+
+    /// Get snapshots of all registered channels, keyed by name.
+    pub fn snapshots(&self) -> BTreeMap<String, MetricsSnapshot> {
+        self.channels
+            .read()
+            .iter()
+            .map(|(name, m)| (name.clone(), m.snapshot()))
+            .collect()
+    }
+
+    /// Export all registered channels as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "channel_count": self.len(),
+            "channels": self.snapshots(),
+            "gauges": self.gauges(),
+        }))
+        .unwrap_or_default()
+    }
+
+    /// Export all registered channels in Prometheus format.
+    ///
+    /// Each metric family is emitted once, with one sample per channel
+    /// labelled `channel="<name>"`. Gauges are emitted as `<prefix>_<name>`.
+    pub fn to_prometheus(&self, prefix: &str) -> String {
+        let snapshots = self.snapshots();
+        let mut output = String::new();
+
+        type Field = fn(&MetricsSnapshot) -> f64;
+        let families: [(&str, &str, &str, Field); 8] = [
+            (
+                "messages_sent_total",
+                "counter",
+                "Total messages sent",
+                |s| s.messages_sent as f64,
+            ),
+            (
+                "messages_received_total",
+                "counter",
+                "Total messages received",
+                |s| s.messages_received as f64,
+            ),
+            ("bytes_sent_total", "counter", "Total bytes sent", |s| {
+                s.bytes_sent as f64
+            }),
+            (
+                "bytes_received_total",
+                "counter",
+                "Total bytes received",
+                |s| s.bytes_received as f64,
+            ),
+            ("send_errors_total", "counter", "Total send errors", |s| {
+                s.send_errors as f64
+            }),
+            (
+                "receive_errors_total",
+                "counter",
+                "Total receive errors",
+                |s| s.receive_errors as f64,
+            ),
+            ("queue_depth", "gauge", "Current queue depth", |s| {
+                s.queue_depth as f64
+            }),
+            (
+                "avg_latency_microseconds",
+                "gauge",
+                "Average latency in microseconds",
+                |s| s.avg_latency_us as f64,
+            ),
+        ];
+
+        for (name, kind, help, field) in families {
+            output.push_str(&format!("# HELP {prefix}_{name} {help}\n"));
+            output.push_str(&format!("# TYPE {prefix}_{name} {kind}\n"));
+            for (channel, snapshot) in &snapshots {
+                output.push_str(&format!(
+                    "{prefix}_{name}{{channel=\"{}\"}} {}\n",
+                    escape_label(channel),
+                    field(snapshot)
+                ));
+            }
+        }
+
+        output.push_str(&format!(
+            "# HELP {prefix}_latency_microseconds Latency in microseconds\n"
+        ));
+        output.push_str(&format!("# TYPE {prefix}_latency_microseconds histogram\n"));
+        for (channel, metrics) in self.channels.read().iter() {
+            metrics.latency_histogram.read().write_prometheus(
+                &mut output,
+                &format!("{prefix}_latency_microseconds"),
+                &format!("channel=\"{}\"", escape_label(channel)),
+                DEFAULT_LATENCY_BOUNDS_US,
+            );
+        }
+
+        for (name, value) in self.gauges.read().iter() {
+            output.push_str(&format!("# TYPE {prefix}_{name} gauge\n"));
+            output.push_str(&format!("{prefix}_{name} {value}\n"));
+        }
+
         output
     }
 }
 
+/// Escape a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -955,6 +1617,74 @@ mod tests {
         assert_eq!(wrapped.metrics().messages_sent(), 1);
     }
 
+    #[test]
+    fn test_metered_wrapper_read_write() {
+        let mut writer = Vec::new().with_metrics();
+        writer.write_all(b"hello").unwrap();
+        writer.write_all(b" world").unwrap();
+        assert_eq!(writer.metrics().messages_sent(), 2);
+        assert_eq!(writer.metrics().bytes_sent(), 11);
+
+        let mut reader = std::io::Cursor::new(writer.into_inner()).with_metrics();
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "hello world");
+        assert_eq!(reader.metrics().bytes_received(), 11);
+    }
+
+    #[test]
+    fn test_metered_thread_channel() {
+        let (tx, rx) = crate::ThreadChannel::<u64>::bounded(4);
+        let tx = tx.with_metrics();
+        let rx = rx.with_metrics();
+
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(tx.metrics().messages_sent(), 2);
+        assert_eq!(tx.metrics().bytes_sent(), 16);
+        assert_eq!(tx.metrics().peak_queue_depth(), 2);
+
+        assert_eq!(rx.recv().unwrap(), 1);
+        assert_eq!(rx.try_recv().unwrap(), 2);
+        // An empty poll is not counted as an error
+        assert!(rx.try_recv().is_err());
+        assert_eq!(rx.metrics().messages_received(), 2);
+        assert_eq!(rx.metrics().receive_errors(), 0);
+    }
+
+    #[test]
+    fn test_metered_ipc_channel_round_trip() {
+        let name = format!("test_metered_rtt_{}", std::process::id());
+
+        let server = std::thread::spawn({
+            let name = name.clone();
+            move || {
+                let mut channel = IpcChannel::<Vec<u8>>::create(&name).unwrap().with_metrics();
+                channel.inner_mut().wait_for_client().ok();
+                for _ in 0..3 {
+                    channel.echo().unwrap();
+                }
+                channel.metrics().messages_received()
+            }
+        });
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        let mut client = IpcChannel::<Vec<u8>>::connect(&name)
+            .unwrap()
+            .with_metrics();
+        for _ in 0..3 {
+            client.round_trip(b"ping").unwrap();
+        }
+
+        assert_eq!(server.join().unwrap(), 3);
+        let snapshot = client.metrics().snapshot();
+        assert_eq!(snapshot.messages_sent, 3);
+        assert_eq!(snapshot.messages_received, 3);
+        assert_eq!(snapshot.bytes_sent, 12);
+        assert!(snapshot.min_latency_us.is_some());
+    }
+
     #[test]
     fn test_metered_sender_receiver() {
         struct DummySender;
@@ -968,6 +1698,116 @@ mod tests {
         assert_eq!(metrics.messages_sent(), 1);
     }
 
+    #[test]
+    fn test_metrics_registry() {
+        let registry = MetricsRegistry::new();
+        let pipe = registry.get_or_create("pipe");
+        assert!(Arc::ptr_eq(&pipe, &registry.get_or_create("pipe")));
+
+        let wrapped = Vec::<u8>::new().with_metrics();
+        registry.register("file \"a\"", wrapped.shared_metrics());
+
+        pipe.record_send(10);
+        pipe.record_send(20);
+        wrapped.metrics().record_recv(5);
+
+        assert_eq!(registry.names(), vec!["file \"a\"", "pipe"]);
+        assert_eq!(registry.snapshots()["pipe"].bytes_sent, 30);
+
+        let prom = registry.to_prometheus("ipckit");
+        assert_eq!(prom.matches("# TYPE ipckit_messages_sent_total").count(), 1);
+        assert!(prom.contains("ipckit_messages_sent_total{channel=\"pipe\"} 2"));
+        assert!(prom.contains("ipckit_bytes_received_total{channel=\"file \\\"a\\\"\"} 5"));
+
+        assert!(registry.unregister("pipe").is_some());
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_registry_gauges() {
+        let registry = MetricsRegistry::new();
+        assert_eq!(registry.add_gauge("socket_connections", 1), 1);
+        assert_eq!(registry.add_gauge("socket_connections", 1), 2);
+        assert_eq!(registry.add_gauge("socket_connections", -1), 1);
+        registry.set_gauge("workers", 4);
+
+        assert_eq!(registry.gauge("workers"), Some(4));
+        assert_eq!(registry.gauges().len(), 2);
+        assert!(registry
+            .to_prometheus("ipckit")
+            .contains("# TYPE ipckit_socket_connections gauge\nipckit_socket_connections 1\n"));
+
+        registry.clear();
+        assert_eq!(registry.gauge("workers"), None);
+    }
+
+    #[test]
+    fn test_histogram_bucket_bounds() {
+        for value in [0, 1, 255, 256, 257, 1_000, 123_456, u64::MAX / 3, u64::MAX] {
+            let index = bucket_index(value);
+            assert!(bucket_low(index) <= value && value <= bucket_high(index));
+            if index > 0 {
+                assert_eq!(bucket_high(index - 1) + 1, bucket_low(index));
+            }
+        }
+    }
+
+    #[test]
+    fn test_histogram_quantiles() {
+        let mut hist = LatencyHistogram::new();
+        for us in 1..=100_000 {
+            hist.record(us);
+        }
+
+        assert_eq!(hist.count(), 100_000);
+        assert_eq!(hist.min(), Some(1));
+        assert_eq!(hist.max(), Some(100_000));
+        for (q, expected) in [(0.5, 50_000.0), (0.99, 99_000.0), (0.999, 99_900.0)] {
+            let got = hist.quantile(q) as f64;
+            assert!((got - expected).abs() / expected < 0.01, "q={q} got={got}");
+        }
+        assert_eq!(hist.quantile(1.0), 100_000);
+        assert_eq!(LatencyHistogram::new().quantile(0.5), 0);
+    }
+
+    #[test]
+    fn test_histogram_merge() {
+        let mut a = LatencyHistogram::new();
+        let mut b = LatencyHistogram::new();
+        a.record_n(10, 3);
+        b.record(5);
+        b.record(5_000);
+
+        a.merge(&b);
+        assert_eq!(a.count(), 5);
+        assert_eq!(a.sum(), 5_035);
+        assert_eq!(a.min(), Some(5));
+        assert_eq!(a.max(), Some(5_000));
+        assert_eq!(a.count_le(10), 4);
+        assert_eq!(a.buckets().count(), 3);
+    }
+
+    #[test]
+    fn test_prometheus_histogram_export() {
+        let metrics = ChannelMetrics::new();
+        metrics.record_latency(Duration::from_micros(40));
+        metrics.record_latency(Duration::from_micros(2_000));
+
+        let prom = metrics.to_prometheus("ipckit");
+        assert!(prom.contains("# TYPE ipckit_latency_microseconds histogram"));
+        assert!(prom.contains("ipckit_latency_microseconds_bucket{le=\"25\"} 0"));
+        assert!(prom.contains("ipckit_latency_microseconds_bucket{le=\"50\"} 1"));
+        assert!(prom.contains("ipckit_latency_microseconds_bucket{le=\"+Inf\"} 2"));
+        assert!(prom.contains("ipckit_latency_microseconds_sum 2040"));
+        assert!(prom.contains("ipckit_latency_microseconds_count 2"));
+
+        let registry = MetricsRegistry::new();
+        registry.register("a", Arc::new(metrics));
+        let prom = registry.to_prometheus("ipckit");
+        assert!(prom.contains("ipckit_latency_microseconds_bucket{channel=\"a\",le=\"+Inf\"} 2"));
+        assert!(prom.contains("ipckit_latency_microseconds_count{channel=\"a\"} 2"));
+    }
+
     #[test]
     fn test_aggregated_metrics() {
         let agg = AggregatedMetrics::new();
@@ -986,4 +1826,20 @@ mod tests {
         assert_eq!(agg.total_messages_sent(), 3);
         assert_eq!(agg.total_bytes_sent(), 350);
     }
+
+    #[test]
+    fn test_aggregated_latency_histogram() {
+        let agg = AggregatedMetrics::new();
+        let m1 = Arc::new(ChannelMetrics::new());
+        let m2 = Arc::new(ChannelMetrics::new());
+        m1.record_latency(Duration::from_micros(100));
+        m2.record_latency(Duration::from_micros(300));
+        agg.register(m1);
+        agg.register(m2);
+
+        let merged = agg.latency_histogram();
+        assert_eq!(merged.count(), 2);
+        assert_eq!(merged.min(), Some(100));
+        assert_eq!(merged.max(), Some(300));
+    }
 }
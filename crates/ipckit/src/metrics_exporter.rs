@@ -0,0 +1,409 @@
+//! # Metrics Exporter
+//!
+//! Publishes the global [`MetricsRegistry`] to Prometheus without a separate
+//! exporter process. The exporter can do either or both of:
+//!
+//! - serve `GET /metrics` on a local TCP port for Prometheus to scrape
+//! - push the same text to a Prometheus push gateway at a fixed interval,
+//!   for short-lived or firewalled processes
+//!
+//! Both run on background threads until the [`MetricsExporter`] is stopped
+//! or dropped.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ipckit::{MetricsExporter, MetricsExporterConfig, PushGateway};
+//! use std::time::Duration;
+//!
+//! let exporter = MetricsExporter::start(MetricsExporterConfig {
+//!     listen: Some("127.0.0.1:9464".parse().unwrap()),
+//!     push: Some(
+//!         PushGateway::new("http://pushgateway:9091", "render_daemon")
+//!             .interval(Duration::from_secs(30)),
+//!     ),
+//!     ..Default::default()
+//! })?;
+//! println!("scrape http://{}/metrics", exporter.local_addr().unwrap());
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::metrics::MetricsRegistry;
+use parking_lot::{Condvar, Mutex};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Default address of the `/metrics` endpoint.
+pub const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:9464";
+
+/// Content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// Longest accepted scrape request head.
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+/// Socket timeout for scrapes and pushes.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Metrics exporter configuration.
+#[derive(Debug, Clone)]
+pub struct MetricsExporterConfig {
+    /// Metric name prefix (default: `ipckit`)
+    pub prefix: String,
+    /// Serve `GET /metrics` on this address (default: `127.0.0.1:9464`);
+    /// port 0 picks a free port
+    pub listen: Option<SocketAddr>,
+    /// Push to a gateway as well (disabled by default)
+    pub push: Option<PushGateway>,
+}
+
+impl Default for MetricsExporterConfig {
+    fn default() -> Self {
+        Self {
+            prefix: "ipckit".to_string(),
+            listen: Some(DEFAULT_LISTEN_ADDR.parse().unwrap()),
+            push: None,
+        }
+    }
+}
+
+/// A Prometheus push gateway to push metrics to.
+#[derive(Debug, Clone)]
+pub struct PushGateway {
+    /// Gateway base URL, e.g. `http://pushgateway:9091` (plain HTTP only)
+    pub url: String,
+    /// `job` label of the pushed group
+    pub job: String,
+    /// Optional `instance` label of the pushed group
+    pub instance: Option<String>,
+    /// Time between pushes (default: 15 s)
+    pub interval: Duration,
+}
+
+impl PushGateway {
+    /// Push to the gateway at `url` under `job`.
+    pub fn new(url: &str, job: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            job: job.to_string(),
+            instance: None,
+            interval: Duration::from_secs(15),
+        }
+    }
+
+    /// Set the `instance` label.
+    pub fn instance(mut self, instance: &str) -> Self {
+        self.instance = Some(instance.to_string());
+        self
+    }
+
+    /// Set the time between pushes.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Replace the job's metrics on the gateway with `body`.
+    pub fn push(&self, body: &str) -> Result<()> {
+        let (host, base) = parse_http_url(&self.url)?;
+        let mut path = format!("{}/metrics/job/{}", base, encode_segment(&self.job));
+        if let Some(instance) = &self.instance {
+            path.push_str(&format!("/instance/{}", encode_segment(instance)));
+        }
+
+        let addr = host
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| IpcError::NotFound(format!("push gateway host '{}'", host)))?;
+        let mut stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT)?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        write!(
+            stream,
+            "PUT {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            host,
+            CONTENT_TYPE,
+            body.len(),
+            body
+        )?;
+        stream.flush()?;
+
+        let mut status_line = Vec::new();
+        let mut byte = [0u8; 1];
+        while stream.read(&mut byte)? == 1 && byte[0] != b'\n' && status_line.len() < 256 {
+            status_line.push(byte[0]);
+        }
+        let status_line = String::from_utf8_lossy(&status_line);
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|s| s.parse::<u16>().ok())
+            .unwrap_or(0);
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(IpcError::Other(format!(
+                "push gateway {} answered '{}'",
+                self.url,
+                status_line.trim()
+            )))
+        }
+    }
+}
+
+/// Serves and/or pushes the global metrics registry until stopped.
+pub struct MetricsExporter {
+    local_addr: Option<SocketAddr>,
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl MetricsExporter {
+    /// Start exporting [`MetricsRegistry::global`].
+    pub fn start(config: MetricsExporterConfig) -> Result<Self> {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let mut threads = Vec::new();
+        let mut local_addr = None;
+
+        if let Some(addr) = config.listen {
+            let listener = TcpListener::bind(addr)?;
+            local_addr = Some(listener.local_addr()?);
+            let prefix = config.prefix.clone();
+            let stop = Arc::clone(&stop);
+            threads.push(
+                std::thread::Builder::new()
+                    .name("ipckit-metrics-http".to_string())
+                    .spawn(move || serve(listener, &prefix, &stop))?,
+            );
+        }
+
+        if let Some(gateway) = config.push {
+            let prefix = config.prefix.clone();
+            let stop = Arc::clone(&stop);
+            threads.push(
+                std::thread::Builder::new()
+                    .name("ipckit-metrics-push".to_string())
+                    .spawn(move || push_loop(&gateway, &prefix, &stop))?,
+            );
+        }
+
+        Ok(Self {
+            local_addr,
+            stop,
+            threads,
+        })
+    }
+
+    /// Get the address `/metrics` is served on, if serving.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Stop serving and pushing, and wait for the background threads.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        *self.stop.0.lock() = true;
+        self.stop.1.notify_all();
+        // Wake the accept loop
+        if let Some(addr) = self.local_addr {
+            let _ = TcpStream::connect_timeout(&wake_addr(addr), IO_TIMEOUT);
+        }
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MetricsExporter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn serve(listener: TcpListener, prefix: &str, stop: &(Mutex<bool>, Condvar)) {
+    for stream in listener.incoming() {
+        if *stop.0.lock() {
+            break;
+        }
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = answer_scrape(stream, prefix) {
+                    tracing::debug!(error = %e, "metrics scrape failed");
+                }
+            }
+            Err(e) => tracing::debug!(error = %e, "metrics accept failed"),
+        }
+    }
+}
+
+fn answer_scrape(mut stream: TcpStream, prefix: &str) -> Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..n]);
+        if head.len() > MAX_REQUEST_HEAD {
+            return respond(&mut stream, "431 Request Header Fields Too Large", "");
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+
+    match (method, path) {
+        ("GET", "/metrics") => respond(
+            &mut stream,
+            "200 OK",
+            &MetricsRegistry::global().to_prometheus(prefix),
+        ),
+        (_, "/metrics") => respond(&mut stream, "405 Method Not Allowed", ""),
+        _ => respond(&mut stream, "404 Not Found", ""),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        CONTENT_TYPE,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn push_loop(gateway: &PushGateway, prefix: &str, stop: &(Mutex<bool>, Condvar)) {
+    let mut stopped = stop.0.lock();
+    while !*stopped {
+        drop(stopped);
+        if let Err(e) = gateway.push(&MetricsRegistry::global().to_prometheus(prefix)) {
+            tracing::warn!(gateway = %gateway.url, error = %e, "metrics push failed");
+        }
+        stopped = stop.0.lock();
+        if !*stopped {
+            stop.1.wait_for(&mut stopped, gateway.interval);
+        }
+    }
+}
+
+/// Where to connect to reach a listener bound to `addr`.
+fn wake_addr(addr: SocketAddr) -> SocketAddr {
+    let mut wake = addr;
+    if addr.ip().is_unspecified() {
+        match addr {
+            SocketAddr::V4(_) => wake.set_ip([127, 0, 0, 1].into()),
+            SocketAddr::V6(_) => wake.set_ip(std::net::Ipv6Addr::LOCALHOST.into()),
+        }
+    }
+    wake
+}
+
+/// Split `http://host:port/base` into `host:port` and `/base`.
+fn parse_http_url(url: &str) -> Result<(String, String)> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        IpcError::InvalidName(format!("push gateway URL must start with http://: {}", url))
+    })?;
+    let (host, base) = match rest.find('/') {
+        Some(i) => (&rest[..i], rest[i..].trim_end_matches('/')),
+        None => (rest, ""),
+    };
+    if host.is_empty() {
+        return Err(IpcError::InvalidName(url.to_string()));
+    }
+    let host = if host.contains(':') && !host.ends_with(']') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    Ok((host, base.to_string()))
+}
+
+/// Percent-encode a label value for use as a URL path segment.
+fn encode_segment(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http_get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serves_metrics_endpoint() {
+        MetricsRegistry::global().set_gauge("exporter_test_gauge", 7);
+        let exporter = MetricsExporter::start(MetricsExporterConfig {
+            listen: Some("127.0.0.1:0".parse().unwrap()),
+            ..Default::default()
+        })
+        .unwrap();
+        let addr = exporter.local_addr().unwrap();
+
+        let response = http_get(addr, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("ipckit_exporter_test_gauge 7"));
+        assert!(http_get(addr, "/other").starts_with("HTTP/1.1 404"));
+
+        exporter.stop();
+        assert!(TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn test_pushes_to_gateway() {
+        // A fake gateway that records one request
+        let gateway = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/prefix", gateway.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = gateway.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            while !String::from_utf8_lossy(&request).contains("ipckit_") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        PushGateway::new(&url, "render daemon")
+            .instance("host-1")
+            .push("ipckit_up 1\n")
+            .unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("PUT /prefix/metrics/job/render%20daemon/instance/host-1 "));
+        assert!(request.ends_with("ipckit_up 1\n"));
+
+        assert!(PushGateway::new("https://gw", "job").push("").is_err());
+    }
+}
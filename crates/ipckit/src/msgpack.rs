@@ -0,0 +1,289 @@
+//! MessagePack - Compact binary encoding for JSON values
+//!
+//! A small codec between [`serde_json::Value`] and
+//! [MessagePack](https://msgpack.org), used by the API server when a client
+//! asks for `application/msgpack`. Binary strings decode to arrays of bytes
+//! and extension types are rejected, since neither has a JSON counterpart.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ipckit::msgpack;
+//!
+//! let value = serde_json::json!({"id": 7, "tags": ["a", "b"]});
+//! let bytes = msgpack::to_vec(&value);
+//! assert_eq!(msgpack::from_slice(&bytes).unwrap(), value);
+//! ```
+
+use crate::error::{IpcError, Result};
+use serde_json::{Map, Number, Value as JsonValue};
+
+/// MIME type for MessagePack bodies.
+pub const CONTENT_TYPE: &str = "application/msgpack";
+
+/// Deepest nesting accepted when decoding, matching serde_json's limit.
+const MAX_DEPTH: usize = 128;
+
+/// Encode a JSON value as MessagePack.
+pub fn to_vec(value: &JsonValue) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode(value, &mut out);
+    out
+}
+
+/// Decode a MessagePack document into a JSON value.
+///
+/// Fails with [`IpcError::Deserialization`] on truncated or trailing data,
+/// non-string map keys, extension types and non-finite floats.
+pub fn from_slice(data: &[u8]) -> Result<JsonValue> {
+    let mut decoder = Decoder { data, pos: 0 };
+    let value = decoder.value(0)?;
+    if decoder.pos != data.len() {
+        return Err(IpcError::deserialization(
+            "trailing bytes after MessagePack value",
+        ));
+    }
+    Ok(value)
+}
+
+fn encode(value: &JsonValue, out: &mut Vec<u8>) {
+    match value {
+        JsonValue::Null => out.push(0xc0),
+        JsonValue::Bool(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+        JsonValue::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                encode_uint(u, out);
+            } else if let Some(i) = n.as_i64() {
+                encode_int(i, out);
+            } else {
+                out.push(0xcb);
+                out.extend_from_slice(&n.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        }
+        JsonValue::String(s) => {
+            encode_len(s.len(), 0xa0, 32, [0xd9, 0xda, 0xdb], out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        JsonValue::Array(items) => {
+            encode_len(items.len(), 0x90, 16, [0, 0xdc, 0xdd], out);
+            for item in items {
+                encode(item, out);
+            }
+        }
+        JsonValue::Object(map) => {
+            encode_len(map.len(), 0x80, 16, [0, 0xde, 0xdf], out);
+            for (key, item) in map {
+                encode(&JsonValue::String(key.clone()), out);
+                encode(item, out);
+            }
+        }
+    }
+}
+
+fn encode_uint(u: u64, out: &mut Vec<u8>) {
+    if u < 0x80 {
+        out.push(u as u8);
+    } else if u <= u8::MAX as u64 {
+        out.extend_from_slice(&[0xcc, u as u8]);
+    } else if u <= u16::MAX as u64 {
+        out.push(0xcd);
+        out.extend_from_slice(&(u as u16).to_be_bytes());
+    } else if u <= u32::MAX as u64 {
+        out.push(0xce);
+        out.extend_from_slice(&(u as u32).to_be_bytes());
+    } else {
+        out.push(0xcf);
+        out.extend_from_slice(&u.to_be_bytes());
+    }
+}
+
+fn encode_int(i: i64, out: &mut Vec<u8>) {
+    if i >= -32 {
+        out.push(i as i8 as u8);
+    } else if i >= i8::MIN as i64 {
+        out.extend_from_slice(&[0xd0, i as i8 as u8]);
+    } else if i >= i16::MIN as i64 {
+        out.push(0xd1);
+        out.extend_from_slice(&(i as i16).to_be_bytes());
+    } else if i >= i32::MIN as i64 {
+        out.push(0xd2);
+        out.extend_from_slice(&(i as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&i.to_be_bytes());
+    }
+}
+
+/// Write a length header: the fix form below `fix_limit`, otherwise the
+/// 8/16/32-bit form (`markers[0] == 0` means there is no 8-bit form).
+fn encode_len(len: usize, fix: u8, fix_limit: usize, markers: [u8; 3], out: &mut Vec<u8>) {
+    if len < fix_limit {
+        out.push(fix | len as u8);
+    } else if markers[0] != 0 && len <= u8::MAX as usize {
+        out.extend_from_slice(&[markers[0], len as u8]);
+    } else if len <= u16::MAX as usize {
+        out.push(markers[1]);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(markers[2]);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| IpcError::deserialization("truncated MessagePack data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(self.take(N)?);
+        Ok(buf)
+    }
+
+    fn len(&mut self, width: usize) -> Result<usize> {
+        Ok(match width {
+            1 => self.array::<1>()?[0] as usize,
+            2 => u16::from_be_bytes(self.array()?) as usize,
+            _ => u32::from_be_bytes(self.array()?) as usize,
+        })
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue> {
+        if depth > MAX_DEPTH {
+            return Err(IpcError::deserialization("MessagePack nesting too deep"));
+        }
+
+        let marker = self.array::<1>()?[0];
+        let value = match marker {
+            0x00..=0x7f => JsonValue::from(marker),
+            0x80..=0x8f => self.map((marker & 0x0f) as usize, depth)?,
+            0x90..=0x9f => self.seq((marker & 0x0f) as usize, depth)?,
+            0xa0..=0xbf => self.string((marker & 0x1f) as usize)?,
+            0xc0 => JsonValue::Null,
+            0xc2 => JsonValue::Bool(false),
+            0xc3 => JsonValue::Bool(true),
+            0xc4..=0xc6 => {
+                let len = self.len(1 << (marker - 0xc4))?;
+                JsonValue::from(self.take(len)?.to_vec())
+            }
+            0xca => float(f32::from_be_bytes(self.array()?) as f64)?,
+            0xcb => float(f64::from_be_bytes(self.array()?))?,
+            0xcc => JsonValue::from(self.array::<1>()?[0]),
+            0xcd => JsonValue::from(u16::from_be_bytes(self.array()?)),
+            0xce => JsonValue::from(u32::from_be_bytes(self.array()?)),
+            0xcf => JsonValue::from(u64::from_be_bytes(self.array()?)),
+            0xd0 => JsonValue::from(self.array::<1>()?[0] as i8),
+            0xd1 => JsonValue::from(i16::from_be_bytes(self.array()?)),
+            0xd2 => JsonValue::from(i32::from_be_bytes(self.array()?)),
+            0xd3 => JsonValue::from(i64::from_be_bytes(self.array()?)),
+            0xd9..=0xdb => {
+                let len = self.len(1 << (marker - 0xd9))?;
+                self.string(len)?
+            }
+            0xdc | 0xdd => {
+                let len = self.len(2 << (marker - 0xdc))?;
+                self.seq(len, depth)?
+            }
+            0xde | 0xdf => {
+                let len = self.len(2 << (marker - 0xde))?;
+                self.map(len, depth)?
+            }
+            0xe0..=0xff => JsonValue::from(marker as i8),
+            _ => {
+                return Err(IpcError::deserialization(format!(
+                    "unsupported MessagePack type 0x{:02x}",
+                    marker
+                )))
+            }
+        };
+        Ok(value)
+    }
+
+    fn string(&mut self, len: usize) -> Result<JsonValue> {
+        let bytes = self.take(len)?;
+        std::str::from_utf8(bytes)
+            .map(|s| JsonValue::String(s.to_string()))
+            .map_err(|e| IpcError::deserialization(e.to_string()))
+    }
+
+    fn seq(&mut self, len: usize, depth: usize) -> Result<JsonValue> {
+        // Every element takes at least one byte, so don't trust huge lengths
+        let mut items = Vec::with_capacity(len.min(self.data.len() - self.pos));
+        for _ in 0..len {
+            items.push(self.value(depth + 1)?);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn map(&mut self, len: usize, depth: usize) -> Result<JsonValue> {
+        let mut map = Map::new();
+        for _ in 0..len {
+            let JsonValue::String(key) = self.value(depth + 1)? else {
+                return Err(IpcError::deserialization(
+                    "MessagePack map keys must be strings",
+                ));
+            };
+            map.insert(key, self.value(depth + 1)?);
+        }
+        Ok(JsonValue::Object(map))
+    }
+}
+
+fn float(f: f64) -> Result<JsonValue> {
+    Number::from_f64(f)
+        .map(JsonValue::Number)
+        .ok_or_else(|| IpcError::deserialization("non-finite float in MessagePack data"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let long = "x".repeat(300);
+        let value = serde_json::json!({
+            "null": null,
+            "flags": [true, false],
+            "ints": [0, 127, 128, 65535, 65536, u64::MAX, -1, -32, -33, -129, -40000, i64::MIN],
+            "float": 1.5,
+            "strings": ["", "short", long],
+            "nested": {"list": (0..20).collect::<Vec<_>>()},
+        });
+        let bytes = to_vec(&value);
+        assert_eq!(from_slice(&bytes).unwrap(), value);
+
+        // Small values use the compact fix forms
+        assert_eq!(
+            to_vec(&serde_json::json!({"a": 1})),
+            [0x81, 0xa1, b'a', 0x01]
+        );
+        assert_eq!(to_vec(&serde_json::json!(-1)), [0xff]);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert!(from_slice(&[0x92, 0x01]).is_err(), "truncated array");
+        assert!(from_slice(&[0x01, 0x02]).is_err(), "trailing data");
+        assert!(from_slice(&[0x81, 0x01, 0x01]).is_err(), "integer key");
+        assert!(from_slice(&[0xd4, 0x01, 0x00]).is_err(), "extension type");
+        assert!(from_slice(&[0x91; 200]).is_err(), "too deep");
+        assert_eq!(
+            from_slice(&[0xc4, 0x02, 0xde, 0xad]).unwrap(),
+            serde_json::json!([0xde, 0xad])
+        );
+    }
+}
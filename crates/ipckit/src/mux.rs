@@ -0,0 +1,630 @@
+//! Mux - Multiple logical streams over one transport
+//!
+//! A [`Mux`] carries any number of named logical streams (say `control`,
+//! `data` and `logs`) over a single pipe or socket, so an application no
+//! longer needs one OS pipe per stream and has only one connection whose
+//! lifetime to manage.
+//!
+//! Both ends open streams by name; data written to a stream on one side is
+//! read from the stream of the same name on the other. Each stream has its
+//! own flow control window: a writer blocks once it is a window ahead of the
+//! reader, without stalling the other streams sharing the transport.
+//!
+//! [`Mux::open_stream`] returns an ordinary [`IpcSender`]/[`IpcReceiver`]
+//! pair, and [`Mux::stream`] returns the raw [`MuxStream`] (`Read + Write`)
+//! for byte-oriented protocols.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::{LocalSocketStream, Mux};
+//!
+//! let mux = Mux::over_socket(LocalSocketStream::connect("render_daemon")?)?;
+//! let (mut control, _) = mux.open_stream::<serde_json::Value>("control")?;
+//! let (_, mut logs) = mux.open_stream::<String>("logs")?;
+//!
+//! control.send(&serde_json::json!({"cmd": "render", "frame": 12}))?;
+//! while let Ok(line) = logs.recv() {
+//!     println!("{}", line);
+//! }
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+//!
+//! # Wire format
+//!
+//! Every frame is a 1-byte kind, a 4-byte little-endian stream id and a
+//! 4-byte little-endian payload length, followed by the payload. Stream ids
+//! are chosen by the sending side and announced with an `OPEN` frame
+//! carrying the stream name.
+
+use crate::channel::{Endpoint, IpcReceiver, IpcSender};
+use crate::error::{IpcError, Result};
+use crate::local_socket::LocalSocketStream;
+use parking_lot::{Condvar, Mutex};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// Default per-stream flow control window in bytes.
+pub const DEFAULT_WINDOW: usize = 256 * 1024;
+
+/// Largest payload carried by one data frame.
+const MAX_DATA: usize = 64 * 1024;
+/// Longest accepted stream name.
+const MAX_NAME: usize = 4096;
+
+// Frame kinds
+const FRAME_HELLO: u8 = 1;
+const FRAME_OPEN: u8 = 2;
+const FRAME_DATA: u8 = 3;
+const FRAME_CREDIT: u8 = 4;
+const FRAME_CLOSE: u8 = 5;
+const FRAME_GOAWAY: u8 = 6;
+
+/// Mux configuration.
+#[derive(Debug, Clone)]
+pub struct MuxConfig {
+    /// Bytes the peer may send on a stream before we have read them
+    /// (default: 256 KiB)
+    pub window: usize,
+}
+
+impl Default for MuxConfig {
+    fn default() -> Self {
+        Self {
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+/// State shared by the mux, its streams and the reader thread.
+struct Shared {
+    writer: Mutex<Option<Box<dyn Write + Send>>>,
+    state: Mutex<State>,
+    changed: Condvar,
+    window: usize,
+}
+
+#[derive(Default)]
+struct State {
+    /// The peer's window, known once its hello arrives
+    peer_window: Option<usize>,
+    closed: bool,
+    next_id: u32,
+    streams: HashMap<String, StreamState>,
+    /// Peer stream ids to names
+    remote_ids: HashMap<u32, String>,
+    /// Our stream ids to names
+    local_ids: HashMap<u32, String>,
+}
+
+#[derive(Default)]
+struct StreamState {
+    /// Opened on this side
+    opened: bool,
+    local_id: u32,
+    /// Bytes sent, and extra bytes the peer allowed beyond its window
+    sent: usize,
+    granted: usize,
+    write_closed: bool,
+    inbound: VecDeque<u8>,
+    remote_id: Option<u32>,
+    remote_closed: bool,
+    /// Bytes read but not yet credited back to the peer
+    unacked: usize,
+    reader_gone: bool,
+}
+
+impl Shared {
+    fn send(&self, kind: u8, id: u32, payload: &[u8]) -> io::Result<()> {
+        let mut writer = self.writer.lock();
+        match writer.as_mut() {
+            Some(w) => write_frame(w, kind, id, payload),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    /// Say goodbye to the peer and stop all streams.
+    fn close(&self) {
+        if let Some(mut w) = self.writer.lock().take() {
+            let _ = write_frame(&mut w, FRAME_GOAWAY, 0, &[]);
+        }
+        self.state.lock().closed = true;
+        self.changed.notify_all();
+    }
+
+    /// Handle one frame from the peer; `Ok(false)` ends the reader.
+    fn handle(&self, kind: u8, id: u32, payload: Vec<u8>) -> Result<bool> {
+        let mut credit = None;
+        {
+            let mut state = self.state.lock();
+            match kind {
+                FRAME_HELLO => {
+                    state.peer_window = Some(read_u32(&payload)? as usize);
+                }
+                FRAME_OPEN => {
+                    let name = String::from_utf8(payload)
+                        .map_err(|_| protocol_error("stream name is not UTF-8"))?;
+                    state.streams.entry(name.clone()).or_default().remote_id = Some(id);
+                    state.remote_ids.insert(id, name);
+                }
+                FRAME_DATA => {
+                    let window = self.window;
+                    let stream = remote_stream(&mut state, id)?;
+                    if stream.reader_gone {
+                        credit = Some(payload.len());
+                    } else if stream.inbound.len() + stream.unacked + payload.len() > window {
+                        return Err(protocol_error("peer overran the stream window"));
+                    } else {
+                        stream.inbound.extend(payload);
+                    }
+                }
+                FRAME_CREDIT => {
+                    let n = read_u32(&payload)? as usize;
+                    let name = state
+                        .local_ids
+                        .get(&id)
+                        .cloned()
+                        .ok_or_else(|| protocol_error("credit for an unknown stream"))?;
+                    if let Some(stream) = state.streams.get_mut(&name) {
+                        stream.granted += n;
+                    }
+                }
+                FRAME_CLOSE => {
+                    remote_stream(&mut state, id)?.remote_closed = true;
+                }
+                FRAME_GOAWAY => return Ok(false),
+                _ => return Err(protocol_error("unknown frame kind")),
+            }
+        }
+        self.changed.notify_all();
+        if let Some(n) = credit {
+            let _ = self.send(FRAME_CREDIT, id, &(n as u32).to_le_bytes());
+        }
+        Ok(true)
+    }
+}
+
+fn remote_stream(state: &mut State, id: u32) -> Result<&mut StreamState> {
+    let name = state
+        .remote_ids
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| protocol_error("frame for an unopened stream"))?;
+    Ok(state.streams.entry(name).or_default())
+}
+
+/// Multiplexes named logical streams over one transport.
+///
+/// A background thread reads the transport and routes frames to streams.
+/// Dropping the mux (or calling [`close`](Self::close)) tells the peer to
+/// close its side as well, after which both reader threads exit.
+pub struct Mux {
+    shared: Arc<Shared>,
+}
+
+impl Mux {
+    /// Multiplex over a transport split into its reading and writing halves.
+    pub fn new<R, W>(reader: R, writer: W) -> Result<Self>
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        Self::with_config(reader, writer, MuxConfig::default())
+    }
+
+    /// Multiplex with a custom configuration.
+    pub fn with_config<R, W>(mut reader: R, writer: W, config: MuxConfig) -> Result<Self>
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        if config.window < MAX_DATA || config.window > u32::MAX as usize {
+            return Err(IpcError::InvalidState(format!(
+                "mux window must be between {} and {} bytes",
+                MAX_DATA,
+                u32::MAX
+            )));
+        }
+        let shared = Arc::new(Shared {
+            writer: Mutex::new(Some(Box::new(writer))),
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+            window: config.window,
+        });
+        shared.send(FRAME_HELLO, 0, &(config.window as u32).to_le_bytes())?;
+
+        let reader_shared = Arc::clone(&shared);
+        std::thread::Builder::new()
+            .name("ipckit-mux".to_string())
+            .spawn(move || {
+                let shared = reader_shared;
+                loop {
+                    let outcome = read_frame(&mut reader)
+                        .map_err(IpcError::from)
+                        .and_then(|(kind, id, payload)| shared.handle(kind, id, payload));
+                    match outcome {
+                        Ok(true) => {}
+                        Ok(false) => break,
+                        Err(e) => {
+                            if !shared.state.lock().closed {
+                                tracing::debug!(error = %e, "mux transport closed");
+                            }
+                            break;
+                        }
+                    }
+                }
+                shared.close();
+            })?;
+
+        Ok(Self { shared })
+    }
+
+    /// Multiplex over a connected local socket.
+    pub fn over_socket(stream: LocalSocketStream) -> Result<Self> {
+        let reader = stream.try_clone()?;
+        Self::new(reader, stream)
+    }
+
+    /// Open the stream `name` as a typed sender/receiver pair.
+    ///
+    /// Each stream can be opened once per side; dropping the sender tells
+    /// the peer's receiver the stream has ended.
+    pub fn open_stream<T>(&self, name: &str) -> Result<(IpcSender<T>, IpcReceiver<T>)> {
+        let (writer, reader) = self.stream(name)?.split();
+        Ok((
+            IpcSender::from_endpoint(Endpoint::Mux(writer)),
+            IpcReceiver::from_endpoint(Endpoint::Mux(reader)),
+        ))
+    }
+
+    /// Open the stream `name` as a raw byte stream.
+    pub fn stream(&self, name: &str) -> Result<MuxStream> {
+        if name.is_empty() || name.len() > MAX_NAME {
+            return Err(IpcError::InvalidName(name.to_string()));
+        }
+
+        // Hold the writer so the OPEN frame precedes any data for the id
+        let mut writer = self.shared.writer.lock();
+        let id = {
+            let mut state = self.shared.state.lock();
+            if state.closed {
+                return Err(IpcError::Closed);
+            }
+            let id = state.next_id;
+            let stream = state.streams.entry(name.to_string()).or_default();
+            if stream.opened {
+                return Err(IpcError::AlreadyExists(format!("mux stream '{}'", name)));
+            }
+            stream.opened = true;
+            stream.local_id = id;
+            state.next_id += 1;
+            state.local_ids.insert(id, name.to_string());
+            id
+        };
+        let w = writer.as_mut().ok_or(IpcError::Closed)?;
+        write_frame(w, FRAME_OPEN, id, name.as_bytes())?;
+
+        Ok(MuxStream {
+            shared: Arc::clone(&self.shared),
+            name: name.into(),
+            readable: true,
+            writable: true,
+        })
+    }
+
+    /// Names of the streams opened on this side.
+    pub fn stream_names(&self) -> Vec<String> {
+        let state = self.shared.state.lock();
+        let mut names: Vec<String> = state
+            .streams
+            .iter()
+            .filter(|(_, s)| s.opened)
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Check if the transport has closed.
+    pub fn is_closed(&self) -> bool {
+        self.shared.state.lock().closed
+    }
+
+    /// Close the mux and all of its streams.
+    pub fn close(&self) {
+        self.shared.close();
+    }
+}
+
+impl Drop for Mux {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// One logical stream of a [`Mux`].
+///
+/// Reads return end-of-file once the peer's side of the stream is dropped;
+/// writes block while the peer's window is full.
+pub struct MuxStream {
+    shared: Arc<Shared>,
+    name: Arc<str>,
+    readable: bool,
+    writable: bool,
+}
+
+impl MuxStream {
+    /// Get the stream name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Split into a write-only and a read-only handle.
+    pub fn split(mut self) -> (MuxStream, MuxStream) {
+        let writer = MuxStream {
+            shared: Arc::clone(&self.shared),
+            name: Arc::clone(&self.name),
+            readable: false,
+            writable: self.writable,
+        };
+        let reader = MuxStream {
+            shared: Arc::clone(&self.shared),
+            name: Arc::clone(&self.name),
+            readable: self.readable,
+            writable: false,
+        };
+        // The halves take over closing the stream
+        self.readable = false;
+        self.writable = false;
+        (writer, reader)
+    }
+}
+
+impl Read for MuxStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.readable {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "write-only mux stream",
+            ));
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let (n, credit) = {
+            let mut state = self.shared.state.lock();
+            loop {
+                let closed = state.closed;
+                let stream = state.streams.entry(self.name.to_string()).or_default();
+                if !stream.inbound.is_empty() {
+                    break;
+                }
+                if stream.remote_closed || closed {
+                    return Ok(0);
+                }
+                self.shared.changed.wait(&mut state);
+            }
+
+            let stream = state.streams.get_mut(&*self.name).unwrap();
+            let n = buf.len().min(stream.inbound.len());
+            for (dst, src) in buf.iter_mut().zip(stream.inbound.drain(..n)) {
+                *dst = src;
+            }
+            stream.unacked += n;
+            let credit = match stream.remote_id {
+                Some(id) if stream.unacked >= self.shared.window / 2 => {
+                    let credit = (id, stream.unacked);
+                    stream.unacked = 0;
+                    Some(credit)
+                }
+                _ => None,
+            };
+            (n, credit)
+        };
+
+        if let Some((id, bytes)) = credit {
+            let _ = self
+                .shared
+                .send(FRAME_CREDIT, id, &(bytes as u32).to_le_bytes());
+        }
+        Ok(n)
+    }
+}
+
+impl Write for MuxStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.writable {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "read-only mux stream",
+            ));
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let (n, id) = {
+            let mut state = self.shared.state.lock();
+            loop {
+                if state.closed {
+                    return Err(io::ErrorKind::BrokenPipe.into());
+                }
+                let peer_window = state.peer_window;
+                let stream = state.streams.get(&*self.name).unwrap();
+                if let Some(window) = peer_window {
+                    let available = (window + stream.granted).saturating_sub(stream.sent);
+                    if available > 0 {
+                        break;
+                    }
+                }
+                self.shared.changed.wait(&mut state);
+            }
+
+            let window = state.peer_window.unwrap_or_default();
+            let stream = state.streams.get_mut(&*self.name).unwrap();
+            let available = (window + stream.granted).saturating_sub(stream.sent);
+            let n = buf.len().min(available).min(MAX_DATA);
+            stream.sent += n;
+            (n, stream.local_id)
+        };
+
+        self.shared.send(FRAME_DATA, id, &buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for MuxStream {
+    fn drop(&mut self) {
+        let mut close = None;
+        let mut credit = None;
+        {
+            let mut state = self.shared.state.lock();
+            if let Some(stream) = state.streams.get_mut(&*self.name) {
+                if self.writable && !stream.write_closed {
+                    stream.write_closed = true;
+                    close = Some(stream.local_id);
+                }
+                if self.readable {
+                    // Nobody will read the rest; let the peer keep writing
+                    stream.reader_gone = true;
+                    let pending = stream.inbound.len() + stream.unacked;
+                    stream.inbound.clear();
+                    stream.unacked = 0;
+                    if let (Some(id), true) = (stream.remote_id, pending > 0) {
+                        credit = Some((id, pending));
+                    }
+                }
+            }
+        }
+        if let Some(id) = close {
+            let _ = self.shared.send(FRAME_CLOSE, id, &[]);
+        }
+        if let Some((id, bytes)) = credit {
+            let _ = self
+                .shared
+                .send(FRAME_CREDIT, id, &(bytes as u32).to_le_bytes());
+        }
+    }
+}
+
+fn write_frame(w: &mut dyn Write, kind: u8, id: u32, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(9 + payload.len());
+    frame.push(kind);
+    frame.extend_from_slice(&id.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload);
+    w.write_all(&frame)?;
+    w.flush()
+}
+
+fn read_frame(r: &mut impl Read) -> io::Result<(u8, u32, Vec<u8>)> {
+    let mut header = [0u8; 9];
+    r.read_exact(&mut header)?;
+    let id = u32::from_le_bytes(header[1..5].try_into().unwrap());
+    let len = u32::from_le_bytes(header[5..9].try_into().unwrap()) as usize;
+    if len > MAX_DATA.max(MAX_NAME) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("mux frame of {} bytes exceeds the limit", len),
+        ));
+    }
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    Ok((header[0], id, payload))
+}
+
+fn read_u32(payload: &[u8]) -> Result<u32> {
+    let bytes: [u8; 4] = payload
+        .try_into()
+        .map_err(|_| protocol_error("malformed frame payload"))?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn protocol_error(msg: &str) -> IpcError {
+    IpcError::Deserialization(format!("mux protocol error: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_socket::LocalSocketListener;
+    use std::time::Duration;
+
+    fn mux_pair(name: &str, config: MuxConfig) -> (Mux, Mux) {
+        let name = format!("test_mux_{}_{}", name, std::process::id());
+        let listener = LocalSocketListener::bind(&name).unwrap();
+        let client = std::thread::spawn({
+            let name = name.clone();
+            let config = config.clone();
+            move || {
+                let stream = LocalSocketStream::connect(&name).unwrap();
+                let reader = stream.try_clone().unwrap();
+                Mux::with_config(reader, stream, config).unwrap()
+            }
+        });
+        let stream = listener.accept().unwrap();
+        let reader = stream.try_clone().unwrap();
+        let server = Mux::with_config(reader, stream, config).unwrap();
+        (client.join().unwrap(), server)
+    }
+
+    #[test]
+    fn test_streams_are_routed_by_name() {
+        let (client, server) = mux_pair("routing", MuxConfig::default());
+
+        let (mut control, _) = client.open_stream::<String>("control").unwrap();
+        let (mut logs, _) = client.open_stream::<String>("logs").unwrap();
+        logs.send(&"first log line".to_string()).unwrap();
+        control.send(&"start".to_string()).unwrap();
+
+        // The server opens the streams after the data arrived
+        let (_, mut server_control) = server.open_stream::<String>("control").unwrap();
+        let (_, mut server_logs) = server.open_stream::<String>("logs").unwrap();
+        assert_eq!(server_control.recv().unwrap(), "start");
+        assert_eq!(server_logs.recv().unwrap(), "first log line");
+        assert_eq!(server.stream_names(), ["control", "logs"]);
+        assert!(matches!(
+            server.stream("logs"),
+            Err(IpcError::AlreadyExists(_))
+        ));
+
+        // Dropping the sender ends the peer's stream
+        drop(logs);
+        assert!(server_logs.recv().is_err());
+
+        drop(client);
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(server.is_closed());
+    }
+
+    #[test]
+    fn test_full_stream_does_not_block_others() {
+        let config = MuxConfig { window: MAX_DATA };
+        let (client, server) = mux_pair("flow", config);
+
+        let mut bulk = client.stream("bulk").unwrap();
+        let writer = std::thread::spawn(move || {
+            bulk.write_all(&vec![7u8; MAX_DATA * 4]).unwrap();
+            bulk
+        });
+
+        // The bulk writer is stuck on its window, but control still flows
+        let (mut control, _) = client.open_stream::<u32>("control").unwrap();
+        let (_, mut server_control) = server.open_stream::<u32>("control").unwrap();
+        control.send(&42).unwrap();
+        assert_eq!(server_control.recv().unwrap(), 42);
+        assert!(!writer.is_finished());
+
+        let mut server_bulk = server.stream("bulk").unwrap();
+        let mut received = vec![0u8; MAX_DATA * 4];
+        server_bulk.read_exact(&mut received).unwrap();
+        assert!(received.iter().all(|&b| b == 7));
+        drop(writer.join().unwrap());
+    }
+}
@@ -2,9 +2,49 @@
 //!
 //! This module provides both anonymous pipes (for parent-child communication)
 //! and named pipes (for unrelated process communication).
+//!
+//! Blocking reads on a [`NamedPipe`] can be aborted from another thread with
+//! a [`PipeCanceller`]. On Windows the pipe uses overlapped I/O so a pending
+//! `ReadFile` or `ConnectNamedPipe` can be cancelled with `CancelIoEx`.
+//!
+//! [`AnonymousPipe::duplex`] wires two pipes into a pair of bidirectional
+//! [`DuplexPipe`] endpoints. One end can be handed to a child process with
+//! [`DuplexPipe::spawn`], which finds it again with [`DuplexPipe::from_env`]:
+//!
+//! ```rust,no_run
+//! use ipckit::{AnonymousPipe, DuplexPipe};
+//! use std::io::{BufRead, BufReader, Write};
+//! use std::process::Command;
+//!
+//! // Parent
+//! let (mut parent, child) = AnonymousPipe::duplex()?;
+//! let mut worker = child.spawn(&mut Command::new("worker"))?;
+//! parent.write_all(b"render 12\n")?;
+//!
+//! // Child ("worker")
+//! let pipe = DuplexPipe::from_env()?;
+//! let (reader, mut writer) = pipe.split();
+//! for line in BufReader::new(reader).lines() {
+//!     writeln!(writer, "done: {}", line?)?;
+//! }
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+//!
+//! [`NamedPipe::split`] turns a connected named pipe into a [`PipeReader`]
+//! and a [`PipeWriter`] that can be used from two threads at once, so a
+//! full-duplex protocol needs no lock around the pipe.
+//!
+//! With the `async` feature, `into_async()` converts pipes into
+//! [`AsyncNamedPipe`], [`AsyncPipeReader`] and [`AsyncPipeWriter`], which
+//! implement Tokio's `AsyncRead`/`AsyncWrite` and plug into codec stacks such
+//! as `LengthDelimitedCodec`.
 
 use crate::error::{IpcError, Result};
+use parking_lot::Mutex;
 use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 /// Pipe reader end
 pub struct PipeReader {
@@ -12,6 +52,8 @@ pub struct PipeReader {
     inner: std::os::unix::io::OwnedFd,
     #[cfg(windows)]
     inner: windows::PipeHandle,
+    /// Cancellation state of the [`NamedPipe`] this end was split from
+    named: Option<Arc<CancelState>>,
 }
 
 /// Pipe writer end
@@ -20,6 +62,9 @@ pub struct PipeWriter {
     inner: std::os::unix::io::OwnedFd,
     #[cfg(windows)]
     inner: windows::PipeHandle,
+    /// Split from a [`NamedPipe`], whose handle needs overlapped writes
+    #[cfg(windows)]
+    overlapped: bool,
 }
 
 /// Anonymous pipe pair for parent-child process communication
@@ -67,6 +112,153 @@ impl AnonymousPipe {
     }
 }
 
+impl AnonymousPipe {
+    /// Create two connected endpoints that can each read and write
+    ///
+    /// Bytes written to one endpoint are read from the other. Neither end is
+    /// inherited by child processes unless passed with
+    /// [`DuplexPipe::pass_to`] or [`DuplexPipe::spawn`].
+    pub fn duplex() -> Result<(DuplexPipe, DuplexPipe)> {
+        let (a_reader, b_writer) = Self::private()?.split();
+        let (b_reader, a_writer) = Self::private()?.split();
+        Ok((
+            DuplexPipe {
+                reader: a_reader,
+                writer: a_writer,
+            },
+            DuplexPipe {
+                reader: b_reader,
+                writer: b_writer,
+            },
+        ))
+    }
+
+    /// A pipe whose ends are not inherited by child processes (internal)
+    fn private() -> Result<Self> {
+        let pipe = Self::new()?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+            unix::set_inheritable(pipe.reader.as_raw_fd(), false)?;
+            unix::set_inheritable(pipe.writer.as_raw_fd(), false)?;
+        }
+        Ok(pipe)
+    }
+}
+
+/// Environment variable carrying a [`DuplexPipe`] to a child process.
+pub const ENV_PIPE: &str = "IPCKIT_PIPE";
+
+/// One endpoint of a bidirectional anonymous pipe
+///
+/// Created in pairs by [`AnonymousPipe::duplex`].
+pub struct DuplexPipe {
+    reader: PipeReader,
+    writer: PipeWriter,
+}
+
+impl DuplexPipe {
+    /// Open the endpoint a parent passed with [`pass_to`](Self::pass_to)
+    ///
+    /// # Errors
+    ///
+    /// Returns `IpcError::NotFound` if this process was not given a pipe.
+    pub fn from_env() -> Result<Self> {
+        let value = std::env::var(ENV_PIPE)
+            .map_err(|_| IpcError::NotFound(format!("{} is not set", ENV_PIPE)))?;
+        let handles = value
+            .split_once(',')
+            .and_then(|(r, w)| Some((r.parse::<u64>().ok()?, w.parse::<u64>().ok()?)));
+        let Some((reader, writer)) = handles else {
+            return Err(IpcError::InvalidName(format!("{}={}", ENV_PIPE, value)));
+        };
+
+        #[cfg(unix)]
+        {
+            unix::duplex_from_fds(reader as i32, writer as i32)
+        }
+        #[cfg(windows)]
+        {
+            windows::duplex_from_handles(reader as usize, writer as usize)
+        }
+    }
+
+    /// Let the child spawned next from `cmd` inherit this endpoint
+    ///
+    /// Sets [`ENV_PIPE`] for the child. Drop this endpoint once the child
+    /// is spawned, or the other end will not see end-of-file when the child
+    /// exits; [`spawn`](Self::spawn) does both steps.
+    ///
+    /// On Windows the handles become inheritable by any process spawned
+    /// until this endpoint is dropped.
+    pub fn pass_to(&self, cmd: &mut std::process::Command) -> Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+            use std::os::unix::process::CommandExt;
+
+            let (reader, writer) = (self.reader.as_raw_fd(), self.writer.as_raw_fd());
+            cmd.env(ENV_PIPE, format!("{},{}", reader, writer));
+            // Runs in the forked child, where the descriptor numbers are the same
+            unsafe {
+                cmd.pre_exec(move || {
+                    unix::set_inheritable(reader, true)
+                        .and_then(|_| unix::set_inheritable(writer, true))
+                        .map_err(|e| match e {
+                            IpcError::Io(e) => e,
+                            other => std::io::Error::other(other.to_string()),
+                        })
+                });
+            }
+        }
+        #[cfg(windows)]
+        {
+            let (reader, writer) = (self.reader.inner.as_raw(), self.writer.inner.as_raw());
+            windows::set_inheritable(reader, true)?;
+            windows::set_inheritable(writer, true)?;
+            cmd.env(ENV_PIPE, format!("{},{}", reader as usize, writer as usize));
+        }
+        Ok(())
+    }
+
+    /// Spawn `cmd` with this endpoint, closing the parent's copy
+    pub fn spawn(self, cmd: &mut std::process::Command) -> Result<std::process::Child> {
+        self.pass_to(cmd)?;
+        Ok(cmd.spawn()?)
+    }
+
+    /// Split into reader and writer
+    pub fn split(self) -> (PipeReader, PipeWriter) {
+        (self.reader, self.writer)
+    }
+
+    /// Get a mutable reference to the reader
+    pub fn reader_mut(&mut self) -> &mut PipeReader {
+        &mut self.reader
+    }
+
+    /// Get a mutable reference to the writer
+    pub fn writer_mut(&mut self) -> &mut PipeWriter {
+        &mut self.writer
+    }
+}
+
+impl Read for DuplexPipe {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Write for DuplexPipe {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
 /// Named pipe for communication between unrelated processes
 ///
 /// On Windows, this uses native named pipes with duplex support.
@@ -76,8 +268,9 @@ pub struct NamedPipe {
     #[cfg(unix)]
     inner: unix::UnixPipeInner,
     #[cfg(windows)]
-    inner: windows::PipeHandle,
+    inner: Arc<windows::PipeHandle>,
     is_server: bool,
+    cancel: Arc<CancelState>,
 }
 
 impl NamedPipe {
@@ -131,7 +324,68 @@ impl NamedPipe {
         }
         #[cfg(windows)]
         {
-            windows::wait_for_client(&self.inner)
+            windows::wait_for_client(&self.inner, &self.cancel)
+        }
+    }
+
+    /// Accept a client on a connection of its own, leaving this pipe
+    /// listening for the next one (server only, internal)
+    pub(crate) fn accept(&mut self) -> Result<NamedPipe> {
+        if !self.is_server {
+            return Err(IpcError::InvalidState(
+                "Only server can accept clients".into(),
+            ));
+        }
+        #[cfg(unix)]
+        {
+            unix::accept(self)
+        }
+        #[cfg(windows)]
+        {
+            // Every client gets its own pipe instance
+            let next = windows::create_named_pipe(&self.name)?;
+            self.wait_for_client()?;
+            Ok(std::mem::replace(self, next))
+        }
+    }
+
+    /// Wait up to `timeout` for data (or end of stream) to become readable.
+    ///
+    /// Returns `Ok(false)` if nothing arrived in time; a zero timeout only
+    /// checks what is already there.
+    pub(crate) fn wait_readable(&mut self, timeout: Duration) -> std::io::Result<bool> {
+        #[cfg(unix)]
+        {
+            unix::wait_readable(self, timeout)
+        }
+        #[cfg(windows)]
+        {
+            windows::wait_readable(&self.inner, timeout)
+        }
+    }
+
+    /// Get a handle that cancels blocked reads on this pipe from another thread.
+    pub fn canceller(&self) -> PipeCanceller {
+        PipeCanceller {
+            state: Arc::downgrade(&self.cancel),
+        }
+    }
+
+    /// Split a connected pipe into independent reader and writer ends
+    ///
+    /// The ends can be moved to different threads, so one can block in a
+    /// read while the other writes. Cancellers obtained from this pipe keep
+    /// working on the reader. The peer sees end of stream once both ends are
+    /// dropped. Servers must have accepted a client with
+    /// [`wait_for_client`](Self::wait_for_client) first.
+    pub fn split(self) -> Result<(PipeReader, PipeWriter)> {
+        #[cfg(unix)]
+        {
+            unix::split(self)
+        }
+        #[cfg(windows)]
+        {
+            windows::split(self)
         }
     }
 
@@ -147,6 +401,116 @@ impl NamedPipe {
     }
 }
 
+/// Cancels blocked reads on a [`NamedPipe`] from another thread
+///
+/// Obtained from [`NamedPipe::canceller`]. After [`cancel`](Self::cancel), a
+/// read in progress and every later read fail with
+/// [`ErrorKind::ConnectionAborted`](std::io::ErrorKind::ConnectionAborted);
+/// on Windows a pending [`NamedPipe::wait_for_client`] is aborted too. Writes
+/// are unaffected so pending output can still be flushed.
+#[derive(Clone)]
+pub struct PipeCanceller {
+    state: Weak<CancelState>,
+}
+
+impl PipeCanceller {
+    /// Cancel reads on the pipe. Does nothing if the pipe has been dropped.
+    pub fn cancel(&self) {
+        if let Some(state) = self.state.upgrade() {
+            state.cancel();
+        }
+    }
+
+    /// Check if the pipe has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.state
+            .upgrade()
+            .is_some_and(|state| state.cancelled.load(Ordering::SeqCst))
+    }
+}
+
+impl std::fmt::Debug for PipeCanceller {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipeCanceller")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Read cancellation state shared between a [`NamedPipe`] and its cancellers
+struct CancelState {
+    cancelled: AtomicBool,
+    /// Clone of the connected stream, shut down for reading on cancel
+    #[cfg(unix)]
+    stream: Mutex<Option<std::os::unix::net::UnixStream>>,
+    #[cfg(windows)]
+    handle: Arc<windows::PipeHandle>,
+    /// Address of the `OVERLAPPED` of the cancellable operation in flight
+    #[cfg(windows)]
+    pending: Mutex<usize>,
+}
+
+impl CancelState {
+    fn cancel(&self) {
+        #[cfg(unix)]
+        {
+            self.cancelled.store(true, Ordering::SeqCst);
+            if let Some(stream) = self.stream.lock().as_ref() {
+                let _ = stream.shutdown(std::net::Shutdown::Read);
+            }
+        }
+        #[cfg(windows)]
+        {
+            // Holding the lock keeps the pending OVERLAPPED alive until
+            // CancelIoEx has seen it
+            let pending = self.pending.lock();
+            self.cancelled.store(true, Ordering::SeqCst);
+            if *pending != 0 {
+                windows::cancel_io(&self.handle, *pending);
+            }
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+fn cancelled_error() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::ConnectionAborted,
+        "Pipe read was cancelled",
+    )
+}
+
+/// Use the read end as a child process's stdin.
+impl From<PipeReader> for std::process::Stdio {
+    fn from(reader: PipeReader) -> Self {
+        #[cfg(unix)]
+        {
+            Self::from(reader.inner)
+        }
+        #[cfg(windows)]
+        {
+            Self::from(reader.inner.into_owned())
+        }
+    }
+}
+
+/// Use the write end as a child process's stdout or stderr.
+impl From<PipeWriter> for std::process::Stdio {
+    fn from(writer: PipeWriter) -> Self {
+        #[cfg(unix)]
+        {
+            Self::from(writer.inner)
+        }
+        #[cfg(windows)]
+        {
+            Self::from(writer.inner.into_owned())
+        }
+    }
+}
+
 #[cfg(unix)]
 impl std::os::unix::io::AsRawFd for PipeReader {
     fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
@@ -166,17 +530,27 @@ impl Read for PipeReader {
         #[cfg(unix)]
         {
             use std::os::unix::io::AsRawFd;
+            let cancelled = || self.named.as_ref().is_some_and(|c| c.is_cancelled());
+            if cancelled() {
+                return Err(cancelled_error());
+            }
             let fd = self.inner.as_raw_fd();
             let ret = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut _, buf.len()) };
             if ret < 0 {
                 Err(std::io::Error::last_os_error())
+            } else if ret == 0 && !buf.is_empty() && cancelled() {
+                // A cancelled read wakes up as end of stream
+                Err(cancelled_error())
             } else {
                 Ok(ret as usize)
             }
         }
         #[cfg(windows)]
         {
-            windows::read_pipe(&self.inner, buf)
+            match self.named {
+                Some(ref cancel) => windows::read_overlapped(&self.inner, cancel, buf),
+                None => windows::read_pipe(&self.inner, buf),
+            }
         }
     }
 }
@@ -196,7 +570,11 @@ impl Write for PipeWriter {
         }
         #[cfg(windows)]
         {
-            windows::write_pipe(&self.inner, buf)
+            if self.overlapped {
+                windows::write_overlapped(&self.inner, buf)
+            } else {
+                windows::write_pipe(&self.inner, buf)
+            }
         }
     }
 
@@ -213,7 +591,7 @@ impl Read for NamedPipe {
         }
         #[cfg(windows)]
         {
-            windows::read_pipe(&self.inner, buf)
+            windows::read_overlapped(&self.inner, &self.cancel, buf)
         }
     }
 }
@@ -226,7 +604,7 @@ impl Write for NamedPipe {
         }
         #[cfg(windows)]
         {
-            windows::write_pipe(&self.inner, buf)
+            windows::write_overlapped(&self.inner, buf)
         }
     }
 
@@ -242,6 +620,286 @@ impl Write for NamedPipe {
     }
 }
 
+// Tokio adapters
+
+#[cfg(feature = "async")]
+pub use async_pipe::{AsyncNamedPipe, AsyncPipeReader, AsyncPipeWriter};
+
+#[cfg(feature = "async")]
+mod async_pipe {
+    //! `into_async()` conversions to Tokio's `AsyncRead`/`AsyncWrite`.
+    //!
+    //! On Unix the descriptors are switched to non-blocking mode and
+    //! registered with Tokio's reactor. On Windows named pipes already use
+    //! overlapped I/O and are handed to Tokio's named pipe types; anonymous
+    //! pipes do not support overlapped I/O and run on Tokio's blocking pool,
+    //! like `tokio::fs::File`, so writes must be flushed.
+
+    use super::*;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+    #[cfg(windows)]
+    use tokio::net::windows::named_pipe::{NamedPipeClient, NamedPipeServer};
+
+    /// A connected [`NamedPipe`] for Tokio.
+    pub struct AsyncNamedPipe {
+        #[cfg(unix)]
+        inner: tokio::net::UnixStream,
+        #[cfg(windows)]
+        inner: WindowsPipe,
+        name: String,
+        is_server: bool,
+    }
+
+    /// Pipe reader end for Tokio.
+    pub struct AsyncPipeReader {
+        #[cfg(unix)]
+        inner: tokio::net::unix::pipe::Receiver,
+        #[cfg(windows)]
+        inner: tokio::fs::File,
+    }
+
+    /// Pipe writer end for Tokio.
+    pub struct AsyncPipeWriter {
+        #[cfg(unix)]
+        inner: tokio::net::unix::pipe::Sender,
+        #[cfg(windows)]
+        inner: tokio::fs::File,
+    }
+
+    impl NamedPipe {
+        /// Convert a connected pipe into one implementing `AsyncRead` and
+        /// `AsyncWrite`.
+        ///
+        /// Servers must have accepted a client with
+        /// [`wait_for_client`](Self::wait_for_client) first. Cancellers of
+        /// this pipe no longer have any effect. Must be called from within a
+        /// Tokio runtime.
+        pub fn into_async(self) -> Result<AsyncNamedPipe> {
+            #[cfg(unix)]
+            {
+                let stream = match &self.inner {
+                    unix::UnixPipeInner::Connected(stream) => stream.try_clone()?,
+                    unix::UnixPipeInner::Listener { .. } => {
+                        return Err(IpcError::InvalidState("Pipe not connected".into()))
+                    }
+                };
+                stream.set_nonblocking(true)?;
+                Ok(AsyncNamedPipe {
+                    inner: tokio::net::UnixStream::from_std(stream)?,
+                    name: self.name,
+                    is_server: self.is_server,
+                })
+            }
+            #[cfg(windows)]
+            {
+                use std::os::windows::io::IntoRawHandle;
+
+                let NamedPipe {
+                    name,
+                    inner,
+                    is_server,
+                    cancel,
+                } = self;
+                // The cancel state holds the other reference to the handle
+                drop(cancel);
+                let handle = Arc::try_unwrap(inner)
+                    .map_err(|_| IpcError::InvalidState("Pipe handle is in use".into()))?
+                    .into_owned()
+                    .into_raw_handle();
+                let inner = if is_server {
+                    WindowsPipe::Server(unsafe { NamedPipeServer::from_raw_handle(handle)? })
+                } else {
+                    WindowsPipe::Client(unsafe { NamedPipeClient::from_raw_handle(handle)? })
+                };
+                Ok(AsyncNamedPipe {
+                    inner,
+                    name,
+                    is_server,
+                })
+            }
+        }
+    }
+
+    impl AnonymousPipe {
+        /// Split into reader and writer ends for Tokio.
+        ///
+        /// Must be called from within a Tokio runtime.
+        pub fn into_async(self) -> Result<(AsyncPipeReader, AsyncPipeWriter)> {
+            Ok((self.reader.into_async()?, self.writer.into_async()?))
+        }
+    }
+
+    impl PipeReader {
+        /// Convert into a reader implementing `AsyncRead`.
+        ///
+        /// On Unix this puts the pipe in non-blocking mode, which a child
+        /// process sharing it would also see. Must be called from within a
+        /// Tokio runtime.
+        pub fn into_async(self) -> Result<AsyncPipeReader> {
+            #[cfg(unix)]
+            {
+                Ok(AsyncPipeReader {
+                    inner: tokio::net::unix::pipe::Receiver::from_owned_fd(self.inner)?,
+                })
+            }
+            #[cfg(windows)]
+            {
+                Ok(AsyncPipeReader {
+                    inner: tokio::fs::File::from_std(self.inner.into_owned().into()),
+                })
+            }
+        }
+    }
+
+    impl PipeWriter {
+        /// Convert into a writer implementing `AsyncWrite`.
+        ///
+        /// On Unix this puts the pipe in non-blocking mode, which a child
+        /// process sharing it would also see. Must be called from within a
+        /// Tokio runtime.
+        pub fn into_async(self) -> Result<AsyncPipeWriter> {
+            #[cfg(unix)]
+            {
+                Ok(AsyncPipeWriter {
+                    inner: tokio::net::unix::pipe::Sender::from_owned_fd(self.inner)?,
+                })
+            }
+            #[cfg(windows)]
+            {
+                Ok(AsyncPipeWriter {
+                    inner: tokio::fs::File::from_std(self.inner.into_owned().into()),
+                })
+            }
+        }
+    }
+
+    impl AsyncNamedPipe {
+        /// Get the pipe name
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        /// Check if this is the server end
+        pub fn is_server(&self) -> bool {
+            self.is_server
+        }
+    }
+
+    impl AsyncRead for AsyncNamedPipe {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for AsyncNamedPipe {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    impl AsyncRead for AsyncPipeReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for AsyncPipeWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    /// Server and client ends of a Windows named pipe.
+    #[cfg(windows)]
+    enum WindowsPipe {
+        Server(NamedPipeServer),
+        Client(NamedPipeClient),
+    }
+
+    #[cfg(windows)]
+    impl AsyncRead for WindowsPipe {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            match self.get_mut() {
+                WindowsPipe::Server(pipe) => Pin::new(pipe).poll_read(cx, buf),
+                WindowsPipe::Client(pipe) => Pin::new(pipe).poll_read(cx, buf),
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    impl AsyncWrite for WindowsPipe {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            match self.get_mut() {
+                WindowsPipe::Server(pipe) => Pin::new(pipe).poll_write(cx, buf),
+                WindowsPipe::Client(pipe) => Pin::new(pipe).poll_write(cx, buf),
+            }
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            match self.get_mut() {
+                WindowsPipe::Server(pipe) => Pin::new(pipe).poll_flush(cx),
+                WindowsPipe::Client(pipe) => Pin::new(pipe).poll_flush(cx),
+            }
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            match self.get_mut() {
+                WindowsPipe::Server(pipe) => Pin::new(pipe).poll_shutdown(cx),
+                WindowsPipe::Client(pipe) => Pin::new(pipe).poll_shutdown(cx),
+            }
+        }
+    }
+}
+
 // Platform-specific implementations
 #[cfg(unix)]
 mod unix {
@@ -278,6 +936,7 @@ mod unix {
 
         let reader = PipeReader {
             inner: unsafe { OwnedFd::from_raw_fd(fds[0]) },
+            named: None,
         };
         let writer = PipeWriter {
             inner: unsafe { OwnedFd::from_raw_fd(fds[1]) },
@@ -286,6 +945,45 @@ mod unix {
         Ok(AnonymousPipe { reader, writer })
     }
 
+    /// Set or clear `FD_CLOEXEC` on `fd`.
+    pub fn set_inheritable(fd: i32, inheritable: bool) -> Result<()> {
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            if flags < 0 {
+                return Err(IpcError::Io(std::io::Error::last_os_error()));
+            }
+            let flags = if inheritable {
+                flags & !libc::FD_CLOEXEC
+            } else {
+                flags | libc::FD_CLOEXEC
+            };
+            if libc::fcntl(fd, libc::F_SETFD, flags) < 0 {
+                return Err(IpcError::Io(std::io::Error::last_os_error()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn duplex_from_fds(reader: i32, writer: i32) -> Result<DuplexPipe> {
+        for fd in [reader, writer] {
+            if fd < 0 || unsafe { libc::fcntl(fd, libc::F_GETFD) } < 0 {
+                return Err(IpcError::NotFound(format!("inherited pipe fd {}", fd)));
+            }
+        }
+        // Don't pass the pipe on to our own children
+        set_inheritable(reader, false)?;
+        set_inheritable(writer, false)?;
+        Ok(DuplexPipe {
+            reader: PipeReader {
+                inner: unsafe { OwnedFd::from_raw_fd(reader) },
+                named: None,
+            },
+            writer: PipeWriter {
+                inner: unsafe { OwnedFd::from_raw_fd(writer) },
+            },
+        })
+    }
+
     pub fn create_named_pipe(name: &str) -> Result<NamedPipe> {
         let path = if name.starts_with('/') {
             name.to_string()
@@ -306,6 +1004,10 @@ mod unix {
             name: path.clone(),
             inner: UnixPipeInner::Listener { listener, path },
             is_server: true,
+            cancel: Arc::new(CancelState {
+                cancelled: AtomicBool::new(false),
+                stream: Mutex::new(None),
+            }),
         })
     }
 
@@ -326,10 +1028,16 @@ mod unix {
             _ => IpcError::Io(e),
         })?;
 
+        let cancel = Arc::new(CancelState {
+            cancelled: AtomicBool::new(false),
+            stream: Mutex::new(Some(stream.try_clone()?)),
+        });
+
         Ok(NamedPipe {
             name: path,
             inner: UnixPipeInner::Connected(stream),
             is_server: false,
+            cancel,
         })
     }
 
@@ -337,6 +1045,15 @@ mod unix {
         match &pipe.inner {
             UnixPipeInner::Listener { listener, path: _ } => {
                 let (stream, _) = listener.accept()?;
+                let clone = stream.try_clone()?;
+                // Register under the lock so a concurrent cancel either sees
+                // the stream or is seen here
+                let mut slot = pipe.cancel.stream.lock();
+                if pipe.cancel.is_cancelled() {
+                    let _ = clone.shutdown(std::net::Shutdown::Read);
+                }
+                *slot = Some(clone);
+                drop(slot);
                 pipe.inner = UnixPipeInner::Connected(stream);
                 Ok(())
             }
@@ -347,14 +1064,66 @@ mod unix {
         }
     }
 
+    pub fn accept(pipe: &NamedPipe) -> Result<NamedPipe> {
+        let UnixPipeInner::Listener { listener, .. } = &pipe.inner else {
+            return Err(IpcError::InvalidState("Pipe already connected".into()));
+        };
+        let (stream, _) = listener.accept()?;
+        Ok(NamedPipe {
+            name: pipe.name.clone(),
+            cancel: Arc::new(CancelState {
+                cancelled: AtomicBool::new(false),
+                stream: Mutex::new(Some(stream.try_clone()?)),
+            }),
+            inner: UnixPipeInner::Connected(stream),
+            is_server: true,
+        })
+    }
+
+    pub fn split(pipe: NamedPipe) -> Result<(PipeReader, PipeWriter)> {
+        let UnixPipeInner::Connected(stream) = &pipe.inner else {
+            return Err(IpcError::InvalidState("Pipe not connected".into()));
+        };
+        let reader = PipeReader {
+            inner: OwnedFd::from(stream.try_clone()?),
+            named: Some(Arc::clone(&pipe.cancel)),
+        };
+        let writer = PipeWriter {
+            inner: OwnedFd::from(stream.try_clone()?),
+        };
+        Ok((reader, writer))
+    }
+
     pub fn read_pipe(pipe: &mut NamedPipe, buf: &mut [u8]) -> std::io::Result<usize> {
-        match pipe.inner.as_stream_mut() {
-            Some(stream) => stream.read(buf),
-            None => Err(std::io::Error::new(
+        if pipe.cancel.is_cancelled() {
+            return Err(cancelled_error());
+        }
+        let n = match pipe.inner.as_stream_mut() {
+            Some(stream) => stream.read(buf)?,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotConnected,
+                    "Pipe not connected",
+                ))
+            }
+        };
+        // A cancelled read wakes up as end of stream
+        if n == 0 && !buf.is_empty() && pipe.cancel.is_cancelled() {
+            return Err(cancelled_error());
+        }
+        Ok(n)
+    }
+
+    pub fn wait_readable(pipe: &mut NamedPipe, timeout: Duration) -> std::io::Result<bool> {
+        use std::os::unix::io::AsRawFd;
+
+        let Some(stream) = pipe.inner.as_stream_mut() else {
+            return Err(std::io::Error::new(
                 std::io::ErrorKind::NotConnected,
                 "Pipe not connected",
-            )),
-        }
+            ));
+        };
+        crate::unix::wait_readable(stream.as_raw_fd(), timeout)
     }
 
     pub fn write_pipe(pipe: &mut NamedPipe, buf: &[u8]) -> std::io::Result<usize> {
@@ -395,6 +1164,8 @@ mod windows {
     use windows_sys::Win32::Foundation::*;
     use windows_sys::Win32::Storage::FileSystem::*;
     use windows_sys::Win32::System::Pipes::*;
+    use windows_sys::Win32::System::Threading::CreateEventW;
+    use windows_sys::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
 
     pub struct PipeHandle {
         handle: HANDLE,
@@ -408,6 +1179,15 @@ mod windows {
         pub fn as_raw(&self) -> HANDLE {
             self.handle
         }
+
+        /// Transfer ownership of the handle to the standard library.
+        pub fn into_owned(self) -> std::os::windows::io::OwnedHandle {
+            use std::os::windows::io::FromRawHandle;
+
+            let handle = self.handle;
+            std::mem::forget(self);
+            unsafe { std::os::windows::io::OwnedHandle::from_raw_handle(handle) }
+        }
     }
 
     impl Drop for PipeHandle {
@@ -439,9 +1219,46 @@ mod windows {
         Ok(AnonymousPipe {
             reader: PipeReader {
                 inner: PipeHandle::new(read_handle),
+                named: None,
             },
             writer: PipeWriter {
                 inner: PipeHandle::new(write_handle),
+                overlapped: false,
+            },
+        })
+    }
+
+    /// Allow or prevent child processes inheriting `handle`.
+    pub fn set_inheritable(handle: HANDLE, inheritable: bool) -> Result<()> {
+        let flags = if inheritable { HANDLE_FLAG_INHERIT } else { 0 };
+        if unsafe { SetHandleInformation(handle, HANDLE_FLAG_INHERIT, flags) } == 0 {
+            return Err(IpcError::Io(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    pub fn duplex_from_handles(reader: usize, writer: usize) -> Result<DuplexPipe> {
+        let (reader, writer) = (reader as HANDLE, writer as HANDLE);
+        for handle in [reader, writer] {
+            let mut flags = 0u32;
+            if unsafe { GetHandleInformation(handle, &mut flags) } == 0 {
+                return Err(IpcError::NotFound(format!(
+                    "inherited pipe handle {}",
+                    handle as usize
+                )));
+            }
+        }
+        // Don't pass the pipe on to our own children
+        set_inheritable(reader, false)?;
+        set_inheritable(writer, false)?;
+        Ok(DuplexPipe {
+            reader: PipeReader {
+                inner: PipeHandle::new(reader),
+                named: None,
+            },
+            writer: PipeWriter {
+                inner: PipeHandle::new(writer),
+                overlapped: false,
             },
         })
     }
@@ -458,7 +1275,7 @@ mod windows {
         let handle = unsafe {
             CreateNamedPipeW(
                 wide_name.as_ptr(),
-                PIPE_ACCESS_DUPLEX,
+                PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED,
                 PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
                 PIPE_UNLIMITED_INSTANCES,
                 4096,
@@ -472,11 +1289,7 @@ mod windows {
             return Err(IpcError::Io(std::io::Error::last_os_error()));
         }
 
-        Ok(NamedPipe {
-            name: pipe_name,
-            inner: PipeHandle::new(handle),
-            is_server: true,
-        })
+        Ok(named_pipe(pipe_name, handle, true))
     }
 
     pub fn connect_named_pipe(name: &str) -> Result<NamedPipe> {
@@ -495,7 +1308,7 @@ mod windows {
                 0,
                 ptr::null(),
                 OPEN_EXISTING,
-                0,
+                FILE_FLAG_OVERLAPPED,
                 INVALID_HANDLE_VALUE,
             )
         };
@@ -509,25 +1322,190 @@ mod windows {
             });
         }
 
-        Ok(NamedPipe {
-            name: pipe_name,
-            inner: PipeHandle::new(handle),
-            is_server: false,
+        Ok(named_pipe(pipe_name, handle, false))
+    }
+
+    fn named_pipe(name: String, handle: HANDLE, is_server: bool) -> NamedPipe {
+        let inner = Arc::new(PipeHandle::new(handle));
+        NamedPipe {
+            name,
+            cancel: Arc::new(CancelState {
+                cancelled: AtomicBool::new(false),
+                handle: Arc::clone(&inner),
+                pending: Mutex::new(0),
+            }),
+            inner,
+            is_server,
+        }
+    }
+
+    /// Give each end its own handle to the pipe. Both still refer to the
+    /// same overlapped pipe instance, so `CancelIoEx` on the pipe's handle
+    /// reaches reads on the reader's.
+    pub fn split(pipe: NamedPipe) -> Result<(PipeReader, PipeWriter)> {
+        let reader = PipeReader {
+            inner: duplicate(&pipe.inner)?,
+            named: Some(Arc::clone(&pipe.cancel)),
+        };
+        let writer = PipeWriter {
+            inner: duplicate(&pipe.inner)?,
+            overlapped: true,
+        };
+        Ok((reader, writer))
+    }
+
+    fn duplicate(handle: &PipeHandle) -> Result<PipeHandle> {
+        use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+        let mut duplicate: HANDLE = INVALID_HANDLE_VALUE;
+        let ret = unsafe {
+            DuplicateHandle(
+                GetCurrentProcess(),
+                handle.as_raw(),
+                GetCurrentProcess(),
+                &mut duplicate,
+                0,
+                0,
+                DUPLICATE_SAME_ACCESS,
+            )
+        };
+        if ret == 0 {
+            return Err(IpcError::Io(std::io::Error::last_os_error()));
+        }
+        Ok(PipeHandle::new(duplicate))
+    }
+
+    pub fn wait_for_client(handle: &PipeHandle, cancel: &CancelState) -> Result<()> {
+        let result = cancellable(handle, cancel, |ov| unsafe {
+            ConnectNamedPipe(handle.as_raw(), ov)
+        });
+        match result {
+            Ok(_) => Ok(()),
+            // ERROR_PIPE_CONNECTED means client is already connected
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32) => Ok(()),
+            Err(e) => Err(IpcError::Io(e)),
+        }
+    }
+
+    /// Read from a named pipe opened with `FILE_FLAG_OVERLAPPED`.
+    pub fn read_overlapped(
+        handle: &PipeHandle,
+        cancel: &CancelState,
+        buf: &mut [u8],
+    ) -> std::io::Result<usize> {
+        cancellable(handle, cancel, |ov| unsafe {
+            ReadFile(
+                handle.as_raw(),
+                buf.as_mut_ptr() as *mut _,
+                buf.len() as u32,
+                ptr::null_mut(),
+                ov,
+            )
+        })
+    }
+
+    /// Write to a named pipe opened with `FILE_FLAG_OVERLAPPED`.
+    pub fn write_overlapped(handle: &PipeHandle, buf: &[u8]) -> std::io::Result<usize> {
+        let ov = Overlapped::new()?;
+        let ret = unsafe {
+            WriteFile(
+                handle.as_raw(),
+                buf.as_ptr() as *const _,
+                buf.len() as u32,
+                ptr::null_mut(),
+                ov.as_ptr(),
+            )
+        };
+        started(ret)?;
+        ov.wait(handle)
+    }
+
+    /// Abort the operation using the `OVERLAPPED` at address `pending`.
+    pub fn cancel_io(handle: &PipeHandle, pending: usize) {
+        unsafe { CancelIoEx(handle.as_raw(), pending as *const OVERLAPPED) };
+    }
+
+    /// Start an overlapped operation that [`CancelState::cancel`] can abort,
+    /// and wait for it to finish.
+    fn cancellable(
+        handle: &PipeHandle,
+        cancel: &CancelState,
+        start: impl FnOnce(*mut OVERLAPPED) -> i32,
+    ) -> std::io::Result<usize> {
+        let ov = Overlapped::new()?;
+
+        let mut pending = cancel.pending.lock();
+        if cancel.is_cancelled() {
+            return Err(cancelled_error());
+        }
+        started(start(ov.as_ptr()))?;
+        *pending = ov.as_ptr() as usize;
+        drop(pending);
+
+        let result = ov.wait(handle);
+        *cancel.pending.lock() = 0;
+
+        result.map_err(|e| match e.raw_os_error() {
+            Some(code) if code == ERROR_OPERATION_ABORTED as i32 => cancelled_error(),
+            _ => e,
         })
     }
 
-    pub fn wait_for_client(handle: &PipeHandle) -> Result<()> {
-        let ret = unsafe { ConnectNamedPipe(handle.as_raw(), ptr::null_mut()) };
+    /// Check the return value of a call that started overlapped I/O.
+    fn started(ret: i32) -> std::io::Result<()> {
         if ret == 0 {
             let err = std::io::Error::last_os_error();
-            // ERROR_PIPE_CONNECTED means client is already connected
-            if err.raw_os_error() != Some(535) {
-                return Err(IpcError::Io(err));
+            if err.raw_os_error() != Some(ERROR_IO_PENDING as i32) {
+                return Err(err);
             }
         }
         Ok(())
     }
 
+    /// A heap-allocated `OVERLAPPED` with its own manual-reset event
+    struct Overlapped {
+        inner: *mut OVERLAPPED,
+    }
+
+    impl Overlapped {
+        fn new() -> std::io::Result<Self> {
+            let event = unsafe { CreateEventW(ptr::null(), 1, 0, ptr::null()) };
+            if event.is_null() {
+                return Err(std::io::Error::last_os_error());
+            }
+            let mut ov: OVERLAPPED = unsafe { std::mem::zeroed() };
+            ov.hEvent = event;
+            Ok(Self {
+                inner: Box::into_raw(Box::new(ov)),
+            })
+        }
+
+        fn as_ptr(&self) -> *mut OVERLAPPED {
+            self.inner
+        }
+
+        /// Block until the operation finishes, returning the bytes transferred.
+        fn wait(&self, handle: &PipeHandle) -> std::io::Result<usize> {
+            let mut transferred: u32 = 0;
+            let ok =
+                unsafe { GetOverlappedResult(handle.as_raw(), self.inner, &mut transferred, 1) };
+            if ok == 0 {
+                Err(std::io::Error::last_os_error())
+            } else {
+                Ok(transferred as usize)
+            }
+        }
+    }
+
+    impl Drop for Overlapped {
+        fn drop(&mut self) {
+            // Every started operation has been waited on, so the kernel no
+            // longer references the OVERLAPPED
+            let ov = unsafe { Box::from_raw(self.inner) };
+            unsafe { CloseHandle(ov.hEvent) };
+        }
+    }
+
     pub fn disconnect_named_pipe(handle: &PipeHandle) -> Result<()> {
         let ret = unsafe { DisconnectNamedPipe(handle.as_raw()) };
         if ret == 0 {
@@ -554,6 +1532,32 @@ mod windows {
         }
     }
 
+    /// Poll the pipe until data is available or `timeout` passes.
+    pub fn wait_readable(handle: &PipeHandle, timeout: Duration) -> std::io::Result<bool> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            let mut available: u32 = 0;
+            let ret = unsafe {
+                PeekNamedPipe(
+                    handle.as_raw(),
+                    ptr::null_mut(),
+                    0,
+                    ptr::null_mut(),
+                    &mut available,
+                    ptr::null_mut(),
+                )
+            };
+            // A broken pipe is readable: the read reports end of stream
+            if ret == 0 || available > 0 {
+                return Ok(true);
+            }
+            if std::time::Instant::now() >= deadline {
+                return Ok(false);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
     pub fn write_pipe(handle: &PipeHandle, buf: &[u8]) -> std::io::Result<usize> {
         let mut bytes_written: u32 = 0;
         let ret = unsafe {
@@ -589,4 +1593,110 @@ mod tests {
         let n = reader.read(&mut buf).unwrap();
         assert_eq!(&buf[..n], msg);
     }
+
+    #[test]
+    fn test_duplex_pipe_with_child() {
+        use std::io::{BufRead, BufReader};
+
+        // Re-run as the child: echo lines back in upper case
+        if std::env::var_os(ENV_PIPE).is_some() {
+            let (reader, mut writer) = DuplexPipe::from_env().unwrap().split();
+            for line in BufReader::new(reader).lines() {
+                writeln!(writer, "{}", line.unwrap().to_uppercase()).unwrap();
+            }
+            return;
+        }
+
+        let (mut a, mut b) = AnonymousPipe::duplex().unwrap();
+        a.write_all(b"ping").unwrap();
+        let mut buf = [0u8; 4];
+        b.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        let (parent, child) = AnonymousPipe::duplex().unwrap();
+        let mut cmd = std::process::Command::new(std::env::current_exe().unwrap());
+        cmd.args(["pipe::tests::test_duplex_pipe_with_child", "--exact"])
+            .stdout(std::process::Stdio::null());
+        let mut process = child.spawn(&mut cmd).unwrap();
+
+        let (reader, mut writer) = parent.split();
+        writeln!(writer, "hello child").unwrap();
+        let mut lines = BufReader::new(reader).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "HELLO CHILD");
+
+        // Closing our end lets the child finish, which closes its end
+        drop(writer);
+        assert!(lines.next().is_none());
+        assert!(process.wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_named_pipe_split() {
+        let name = format!("ipckit_split_pipe_{}", std::process::id());
+        let server = NamedPipe::create(&name).unwrap();
+        assert!(matches!(server.split(), Err(IpcError::InvalidState(_))));
+
+        let mut server = NamedPipe::create(&name).unwrap();
+        let client = std::thread::spawn({
+            let name = name.clone();
+            move || NamedPipe::connect(&name).unwrap()
+        });
+        server.wait_for_client().unwrap();
+        let canceller = server.canceller();
+        let (mut reader, mut writer) = server.split().unwrap();
+
+        // Echo everything back from a client thread
+        let echo = std::thread::spawn(move || {
+            let (mut reader, mut writer) = client.join().unwrap().split().unwrap();
+            std::io::copy(&mut reader, &mut writer).unwrap();
+        });
+
+        // Read and write at the same time from two threads
+        let received = std::thread::spawn(move || {
+            let mut buf = vec![0u8; 64 * 1024];
+            reader.read_exact(&mut buf).unwrap();
+            // Blocks until cancelled
+            let err = reader.read(&mut [0u8; 1]).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::ConnectionAborted);
+            buf
+        });
+        let data: Vec<u8> = (0..64 * 1024).map(|i| i as u8).collect();
+        writer.write_all(&data).unwrap();
+
+        std::thread::sleep(Duration::from_millis(50));
+        canceller.cancel();
+        assert_eq!(received.join().unwrap(), data);
+
+        drop(writer);
+        echo.join().unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_pipes() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut reader, mut writer) = AnonymousPipe::new().unwrap().into_async().unwrap();
+        writer.write_all(b"anonymous").await.unwrap();
+        writer.flush().await.unwrap();
+        let mut buf = [0u8; 9];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"anonymous");
+
+        let name = format!("ipckit_async_pipe_{}", std::process::id());
+        let mut server = NamedPipe::create(&name).unwrap();
+        let client = tokio::task::spawn_blocking(move || NamedPipe::connect(&name));
+        tokio::task::block_in_place(|| server.wait_for_client()).unwrap();
+        let mut server = server.into_async().unwrap();
+        let mut client = client.await.unwrap().unwrap().into_async().unwrap();
+        assert!(server.is_server() && !client.is_server());
+
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        server.write_all(b"pong").await.unwrap();
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+    }
 }
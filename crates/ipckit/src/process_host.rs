@@ -0,0 +1,475 @@
+//! # Process Host
+//!
+//! Server-side counterpart of [`WrappedCommand`]: spawns a child process whose
+//! stdio is wired through ipckit [`AnonymousPipe`]s, registers it as a task in
+//! a [`TaskManager`], and streams its output to the task manager's
+//! [`EventBus`] as `log.stdout` / `log.stderr` events.
+//!
+//! The task tracks the process for its whole life:
+//!
+//! - it is marked running once the process has started
+//! - cancelling it (e.g. `DELETE /v1/tasks/{id}` or [`TaskManager::cancel`])
+//!   kills the process tree
+//! - it completes with `{"exit_code", "duration_ms"}` on exit code 0 and fails
+//!   otherwise
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ipckit::{ProcessHost, TaskManager, TaskManagerConfig};
+//! use std::sync::Arc;
+//!
+//! let manager = Arc::new(TaskManager::new(TaskManagerConfig::default()));
+//!
+//! let process = ProcessHost::new(Arc::clone(&manager), "python")
+//!     .args(["worker.py", "--batch", "7"])
+//!     .task("Render batch 7", "render")
+//!     .label("batch", "7")
+//!     .spawn()?;
+//!
+//! println!("task {} is pid {}", process.task().id(), process.pid());
+//! let output = process.wait()?;
+//! println!("exited with {}", output.exit_code);
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+//!
+//! [`WrappedCommand`]: crate::cli_bridge::WrappedCommand
+//! [`EventBus`]: crate::event_stream::EventBus
+
+use crate::cli_bridge::{wait_or_cancel, CommandOutput, ProcessTree, ProgressParser};
+use crate::error::{IpcError, Result};
+use crate::pipe::{AnonymousPipe, PipeReader, PipeWriter};
+use crate::task_manager::{TaskBuilder, TaskHandle, TaskManager};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Builder for a child process run as a task.
+pub struct ProcessHost {
+    manager: Arc<TaskManager>,
+    command: Command,
+    task_name: String,
+    task_type: String,
+    labels: Vec<(String, String)>,
+    piped_stdin: bool,
+    progress_parser: Option<Arc<dyn ProgressParser>>,
+    timeout: Option<Duration>,
+}
+
+impl ProcessHost {
+    /// Create a new process host for `program`, reporting to `manager`.
+    ///
+    /// The task is named after the program with type `"process"` until
+    /// [`task`](Self::task) says otherwise.
+    pub fn new(manager: Arc<TaskManager>, program: &str) -> Self {
+        Self {
+            manager,
+            command: Command::new(program),
+            task_name: program.to_string(),
+            task_type: "process".to_string(),
+            labels: Vec::new(),
+            piped_stdin: false,
+            progress_parser: None,
+            timeout: None,
+        }
+    }
+
+    /// Set the task name and type.
+    pub fn task(mut self, name: &str, task_type: &str) -> Self {
+        self.task_name = name.to_string();
+        self.task_type = task_type.to_string();
+        self
+    }
+
+    /// Add a label to the task.
+    pub fn label(mut self, key: &str, value: &str) -> Self {
+        self.labels.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add an argument.
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.command.arg(arg);
+        self
+    }
+
+    /// Add multiple arguments.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        self.command.args(args);
+        self
+    }
+
+    /// Set the working directory.
+    pub fn current_dir(mut self, dir: &std::path::Path) -> Self {
+        self.command.current_dir(dir);
+        self
+    }
+
+    /// Set an environment variable.
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.command.env(key, value);
+        self
+    }
+
+    /// Give the child a stdin pipe, available from [`HostedProcess::take_stdin`].
+    ///
+    /// Without this the child's stdin is empty.
+    pub fn piped_stdin(mut self) -> Self {
+        self.piped_stdin = true;
+        self
+    }
+
+    /// Update the task progress from stdout lines using `parser`.
+    pub fn progress_parser<P: ProgressParser + 'static>(mut self, parser: P) -> Self {
+        self.progress_parser = Some(Arc::new(parser));
+        self
+    }
+
+    /// Kill the process and fail the task with `"timeout"` if it runs longer
+    /// than `timeout` (see [`TaskBuilder::timeout`]).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Register the task and start the process.
+    ///
+    /// If the process cannot be started, the task is marked failed and the
+    /// error is returned.
+    pub fn spawn(mut self) -> Result<HostedProcess> {
+        let mut builder = self.labels.iter().fold(
+            TaskBuilder::new(&self.task_name, &self.task_type),
+            |b, (k, v)| b.label(k, v),
+        );
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let handle = self.manager.create(builder);
+
+        let started = wire_stdio(&mut self.command, self.piped_stdin).and_then(|pipes| {
+            let child = ProcessTree::spawn(&mut self.command, true).map_err(IpcError::Io)?;
+            Ok((pipes, child))
+        });
+        // Close the child's pipe ends, which the command still holds
+        drop(self.command);
+        let ((stdin, out_reader, err_reader), mut child) = match started {
+            Ok(started) => started,
+            Err(e) => {
+                handle.fail(&format!("Failed to start process: {}", e));
+                return Err(e);
+            }
+        };
+
+        let start = Instant::now();
+        let pid = child.id();
+        handle.start();
+
+        let stdout = forward_lines(out_reader, handle.clone(), false, self.progress_parser);
+        let stderr = forward_lines(err_reader, handle.clone(), true, None);
+
+        let task = handle.clone();
+        let monitor = thread::spawn(move || {
+            let status = wait_or_cancel(&mut child, Some(&task.cancel_token()));
+            let stdout = stdout.join().unwrap_or_default();
+            let stderr = stderr.join().unwrap_or_default();
+            let duration = start.elapsed();
+
+            let status = match status {
+                Ok(status) => status,
+                Err(e) => {
+                    task.fail(&e.to_string());
+                    return Err(e);
+                }
+            };
+            let exit_code = status.code().unwrap_or(-1);
+
+            // A cancelled task has already been finalized
+            if !task.is_cancelled() {
+                if exit_code == 0 {
+                    task.complete(serde_json::json!({
+                        "exit_code": exit_code,
+                        "duration_ms": duration.as_millis(),
+                    }));
+                } else {
+                    task.fail(&format!("Process exited with code {}", exit_code));
+                }
+            }
+
+            Ok(CommandOutput {
+                exit_code,
+                stdout,
+                stderr,
+                duration,
+            })
+        });
+
+        Ok(HostedProcess {
+            manager: self.manager,
+            handle,
+            pid,
+            stdin,
+            monitor,
+        })
+    }
+}
+
+/// A running child process registered as a task.
+pub struct HostedProcess {
+    manager: Arc<TaskManager>,
+    handle: TaskHandle,
+    pid: u32,
+    stdin: Option<PipeWriter>,
+    monitor: JoinHandle<Result<CommandOutput>>,
+}
+
+impl HostedProcess {
+    /// Get the task tracking this process.
+    pub fn task(&self) -> &TaskHandle {
+        &self.handle
+    }
+
+    /// Get the OS process id.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Take the write end of the child's stdin.
+    ///
+    /// Only available with [`ProcessHost::piped_stdin`]. Dropping it closes
+    /// the child's stdin.
+    pub fn take_stdin(&mut self) -> Option<PipeWriter> {
+        self.stdin.take()
+    }
+
+    /// Write to the child's stdin.
+    pub fn write_stdin(&mut self, data: &[u8]) -> Result<()> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| IpcError::InvalidState("stdin is not piped".into()))?;
+        stdin.write_all(data)?;
+        Ok(())
+    }
+
+    /// Check whether the process has exited and its task been finalized.
+    pub fn is_finished(&self) -> bool {
+        self.monitor.is_finished()
+    }
+
+    /// Cancel the task, killing the process and all of its descendants.
+    pub fn kill(&self) -> Result<()> {
+        self.manager.cancel(self.handle.id())
+    }
+
+    /// Wait for the process to exit and collect its output.
+    pub fn wait(mut self) -> Result<CommandOutput> {
+        // Let the child see end of input
+        drop(self.stdin.take());
+        self.monitor
+            .join()
+            .map_err(|_| IpcError::Other("process monitor thread panicked".into()))?
+    }
+}
+
+/// Point the command's stdio at new pipes, returning the parent's ends.
+fn wire_stdio(
+    command: &mut Command,
+    piped_stdin: bool,
+) -> Result<(Option<PipeWriter>, PipeReader, PipeReader)> {
+    let stdin = if piped_stdin {
+        let (reader, writer) = AnonymousPipe::new()?.split();
+        keep_in_parent(&writer)?;
+        command.stdin(Stdio::from(reader));
+        Some(writer)
+    } else {
+        command.stdin(Stdio::null());
+        None
+    };
+
+    let (out_reader, out_writer) = AnonymousPipe::new()?.split();
+    keep_in_parent(&out_reader)?;
+    command.stdout(Stdio::from(out_writer));
+
+    let (err_reader, err_writer) = AnonymousPipe::new()?.split();
+    keep_in_parent(&err_reader)?;
+    command.stderr(Stdio::from(err_writer));
+
+    Ok((stdin, out_reader, err_reader))
+}
+
+/// Stop the parent's end of a pipe from leaking into the child, which would
+/// keep the pipe open after either side is done with it.
+#[cfg(unix)]
+fn keep_in_parent(end: &impl std::os::unix::io::AsRawFd) -> Result<()> {
+    let ret = unsafe { libc::fcntl(end.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+    if ret < 0 {
+        return Err(IpcError::Io(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Anonymous pipe handles are created non-inheritable on Windows.
+#[cfg(windows)]
+fn keep_in_parent<T>(_end: &T) -> Result<()> {
+    Ok(())
+}
+
+/// Publish each line read from `reader` as task output, returning everything
+/// read once the pipe closes.
+fn forward_lines(
+    reader: PipeReader,
+    task: TaskHandle,
+    is_stderr: bool,
+    parser: Option<Arc<dyn ProgressParser>>,
+) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = String::new();
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let text = String::from_utf8_lossy(&buf);
+            output.push_str(&text);
+
+            let line = text.trim_end_matches(['\r', '\n']);
+            if is_stderr {
+                task.stderr(line);
+            } else {
+                task.stdout(line);
+                if let Some(info) = parser.as_ref().and_then(|p| p.parse(line)) {
+                    task.set_progress(info.percentage(), info.message.as_deref());
+                }
+            }
+        }
+        output
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_stream::{event_types, EventFilter};
+    use crate::task_manager::{TaskManagerConfig, TaskStatus};
+
+    fn manager() -> Arc<TaskManager> {
+        Arc::new(TaskManager::new(TaskManagerConfig::default()))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_streams_to_events() {
+        let manager = manager();
+        let events = manager
+            .event_bus()
+            .subscribe(EventFilter::new().event_type("log.std*").resource("task-1"));
+
+        let process = ProcessHost::new(Arc::clone(&manager), "sh")
+            .args(["-c", "echo one; echo two >&2; echo 50%"])
+            .task("Echo", "test")
+            .progress_parser(crate::cli_bridge::parsers::PercentageParser)
+            .spawn()
+            .unwrap();
+        let id = process.task().id().to_string();
+
+        let output = process.wait().unwrap();
+        assert_eq!(output.exit_code, 0);
+        assert_eq!(output.stdout, "one\n50%\n");
+        assert_eq!(output.stderr, "two\n");
+
+        let info = manager.get(&id).unwrap();
+        assert_eq!(info.status, TaskStatus::Completed);
+        assert_eq!(info.name, "Echo");
+
+        let lines: Vec<_> = events
+            .try_iter()
+            .map(|e| {
+                (
+                    e.event_type,
+                    e.data["message"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert!(lines.contains(&(event_types::LOG_STDOUT.to_string(), "one".to_string())));
+        assert!(lines.contains(&(event_types::LOG_STDERR.to_string(), "two".to_string())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stdin_and_failure() {
+        let manager = manager();
+        let mut process = ProcessHost::new(Arc::clone(&manager), "sh")
+            .args(["-c", "read line; echo got $line; exit 3"])
+            .piped_stdin()
+            .spawn()
+            .unwrap();
+        process.write_stdin(b"hello\n").unwrap();
+
+        let id = process.task().id().to_string();
+        let output = process.wait().unwrap();
+        assert_eq!(output.stdout, "got hello\n");
+        assert_eq!(output.exit_code, 3);
+
+        let info = manager.get(&id).unwrap();
+        assert_eq!(info.status, TaskStatus::Failed);
+        assert_eq!(info.error.as_deref(), Some("Process exited with code 3"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancel_kills_process() {
+        let manager = manager();
+        let process = ProcessHost::new(Arc::clone(&manager), "sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let id = process.task().id().to_string();
+        assert_eq!(manager.get(&id).unwrap().status, TaskStatus::Running);
+
+        let start = Instant::now();
+        manager.cancel(&id).unwrap();
+        process.wait().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(manager.get(&id).unwrap().status, TaskStatus::Cancelled);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_process() {
+        let manager = manager();
+        let process = ProcessHost::new(Arc::clone(&manager), "sleep")
+            .arg("30")
+            .timeout(Duration::from_millis(100))
+            .spawn()
+            .unwrap();
+        let id = process.task().id().to_string();
+
+        let start = Instant::now();
+        process.wait().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let info = manager.get(&id).unwrap();
+        assert_eq!(info.status, TaskStatus::Failed);
+        assert_eq!(info.error.as_deref(), Some("timeout"));
+    }
+
+    #[test]
+    fn test_spawn_failure_fails_task() {
+        let manager = manager();
+        let result = ProcessHost::new(Arc::clone(&manager), "ipckit-no-such-program").spawn();
+        assert!(result.is_err());
+
+        let tasks = manager.list(&Default::default());
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].status, TaskStatus::Failed);
+    }
+}
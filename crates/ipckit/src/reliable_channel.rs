@@ -0,0 +1,451 @@
+//! Reliable Channel - Acknowledged, at-least-once delivery over an IpcChannel
+//!
+//! A plain [`IpcChannel`] loses whatever was in flight when the peer goes
+//! away. [`ReliableChannel`] adds a thin reliability layer for command
+//! channels that need "the message definitely arrived" semantics, even across
+//! a brief daemon restart:
+//!
+//! - Every message carries a sequence number and stays buffered until the
+//!   peer acknowledges it. Acknowledgements are cumulative and sent as soon
+//!   as a message is received.
+//! - When the connection breaks, the channel reconnects and retransmits
+//!   everything still unacknowledged, in order.
+//! - Receivers drop retransmitted messages they have already seen. If the
+//!   peer itself restarted it cannot know what it had seen, so delivery is
+//!   at-least-once rather than exactly-once.
+//! - At most [`ReliableConfig::max_unacked`] messages are buffered; `send`
+//!   blocks for acknowledgements once the buffer is full.
+//!
+//! Both ends must use a `ReliableChannel`; the frames carry a small header
+//! that a plain `IpcChannel` would pass through as payload.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::{ReliableChannel, ReliableConfig};
+//! use std::time::Duration;
+//!
+//! // Client: reconnects to "commands" on its own if the daemon restarts
+//! let mut channel = ReliableChannel::<Vec<u8>>::connect("commands", ReliableConfig::default())?;
+//! channel.send_bytes(b"rebuild")?;
+//!
+//! // Block until the daemon has the command
+//! channel.wait_acked(Duration::from_secs(5))?;
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+
+use crate::channel::{decode, encode, IpcChannel};
+use crate::error::{IpcError, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+/// Frame announcing the sender's session after (re)connecting
+const KIND_HELLO: u8 = 0;
+
+/// Frame carrying a sequenced message
+const KIND_DATA: u8 = 1;
+
+/// Frame acknowledging every message up to a sequence number
+const KIND_ACK: u8 = 2;
+
+/// Frame header size (1 byte kind, 8 bytes sequence number or session)
+const FRAME_HEADER_SIZE: usize = 9;
+
+/// Opens a fresh connection for [`ReliableChannel::reconnect`].
+type Connector = Box<dyn FnMut() -> Result<IpcChannel<Vec<u8>>> + Send>;
+
+/// Configuration for a [`ReliableChannel`].
+#[derive(Debug, Clone)]
+pub struct ReliableConfig {
+    /// Maximum number of unacknowledged messages kept for retransmission
+    pub max_unacked: usize,
+    /// How long a client keeps retrying to reconnect before giving up
+    pub reconnect_timeout: Duration,
+    /// Delay between reconnect attempts
+    pub retry_interval: Duration,
+}
+
+impl Default for ReliableConfig {
+    fn default() -> Self {
+        Self {
+            max_unacked: 256,
+            reconnect_timeout: Duration::from_secs(5),
+            retry_interval: Duration::from_millis(100),
+        }
+    }
+}
+
+/// IPC channel with acknowledgements and retransmission after reconnect
+pub struct ReliableChannel<T = Vec<u8>> {
+    channel: Option<IpcChannel<Vec<u8>>>,
+    connector: Option<Connector>,
+    config: ReliableConfig,
+    /// Identifies this end to the peer, so it can tell a restart apart
+    session: u64,
+    next_seq: u64,
+    unacked: VecDeque<(u64, Vec<u8>)>,
+    /// Session of the peer the `received` counter refers to
+    peer_session: Option<u64>,
+    /// Highest sequence number received from the peer session
+    received: u64,
+    /// Messages read while waiting for acknowledgements
+    inbox: VecDeque<Vec<u8>>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> ReliableChannel<T> {
+    /// Create a reliable channel server
+    ///
+    /// Call [`wait_for_client`](Self::wait_for_client) before using it. When
+    /// the client goes away, the server recreates the channel and waits for
+    /// it to come back.
+    pub fn create(name: &str, config: ReliableConfig) -> Result<Self> {
+        let channel = IpcChannel::create(name)?;
+        let name = name.to_string();
+        let mut reliable = Self::new(config);
+        reliable.channel = Some(channel);
+        reliable.connector = Some(Box::new(move || {
+            let mut channel = IpcChannel::create(&name)?;
+            channel.wait_for_client()?;
+            Ok(channel)
+        }));
+        Ok(reliable)
+    }
+
+    /// Connect to a reliable channel server
+    ///
+    /// When the connection breaks, the client reconnects to `name`, retrying
+    /// for up to [`ReliableConfig::reconnect_timeout`].
+    pub fn connect(name: &str, config: ReliableConfig) -> Result<Self> {
+        let channel = IpcChannel::connect(name)?;
+        let name = name.to_string();
+        let mut reliable = Self::new(config).with_reconnect(move || IpcChannel::connect(&name));
+        reliable.resume_with(channel)?;
+        Ok(reliable)
+    }
+
+    /// Wrap a connected channel, e.g. one made on another transport
+    ///
+    /// Without [`with_reconnect`](Self::with_reconnect), a broken connection
+    /// is reported as an error and can be replaced with
+    /// [`resume_with`](Self::resume_with).
+    pub fn from_channel(channel: IpcChannel<Vec<u8>>, config: ReliableConfig) -> Result<Self> {
+        let mut reliable = Self::new(config);
+        reliable.resume_with(channel)?;
+        Ok(reliable)
+    }
+
+    fn new(config: ReliableConfig) -> Self {
+        Self {
+            channel: None,
+            connector: None,
+            config,
+            session: session_id(),
+            next_seq: 1,
+            unacked: VecDeque::new(),
+            peer_session: None,
+            received: 0,
+            inbox: VecDeque::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set how a broken connection is re-established
+    pub fn with_reconnect<F>(mut self, connect: F) -> Self
+    where
+        F: FnMut() -> Result<IpcChannel<Vec<u8>>> + Send + 'static,
+    {
+        self.connector = Some(Box::new(connect));
+        self
+    }
+
+    /// Get the channel name
+    pub fn name(&self) -> Option<&str> {
+        self.channel.as_ref().map(IpcChannel::name)
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &ReliableConfig {
+        &self.config
+    }
+
+    /// Number of sent messages the peer has not acknowledged yet
+    pub fn unacked(&self) -> usize {
+        self.unacked.len()
+    }
+
+    /// Wait for a client to connect (server only)
+    pub fn wait_for_client(&mut self) -> Result<()> {
+        self.link()?.wait_for_client()?;
+        self.hello()
+    }
+
+    /// Re-establish the connection and retransmit unacknowledged messages
+    ///
+    /// Called automatically when a send or receive finds the connection
+    /// broken; fails if no reconnect method is set.
+    pub fn reconnect(&mut self) -> Result<()> {
+        let connector = self.connector.as_mut().ok_or_else(|| {
+            IpcError::InvalidState("Reliable channel has no reconnect method".into())
+        })?;
+        // Release the old connection first, so a server can bind the name again
+        self.channel = None;
+        let deadline = Instant::now() + self.config.reconnect_timeout;
+        let channel = loop {
+            match connector() {
+                Ok(channel) => break channel,
+                Err(e) if Instant::now() >= deadline => return Err(e),
+                Err(_) => std::thread::sleep(self.config.retry_interval),
+            }
+        };
+        tracing::debug!(unacked = self.unacked.len(), "reliable channel reconnected");
+        self.resume_with(channel)
+    }
+
+    /// Continue over a new connection, retransmitting unacknowledged messages
+    pub fn resume_with(&mut self, channel: IpcChannel<Vec<u8>>) -> Result<()> {
+        self.channel = Some(channel);
+        self.hello()
+    }
+
+    /// Announce this end on a new connection and retransmit unacknowledged
+    /// messages (internal)
+    fn hello(&mut self) -> Result<()> {
+        let channel = self.channel.as_mut().ok_or(IpcError::Closed)?;
+        channel.send_bytes(frame(KIND_HELLO, self.session, &[]))?;
+        for (seq, data) in &self.unacked {
+            channel.send_bytes(frame(KIND_DATA, *seq, data))?;
+        }
+        Ok(())
+    }
+
+    /// Block until every sent message has been acknowledged
+    ///
+    /// Messages received meanwhile are kept for the next receive.
+    pub fn wait_acked(&mut self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        while !self.unacked.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(IpcError::Timeout);
+            }
+            self.pump(Some(remaining))?;
+        }
+        Ok(())
+    }
+
+    fn link(&mut self) -> Result<&mut IpcChannel<Vec<u8>>> {
+        self.channel.as_mut().ok_or(IpcError::Closed)
+    }
+
+    /// Reconnect after a broken connection, or pass the error on (internal)
+    fn recover(&mut self, err: IpcError) -> Result<()> {
+        if self.connector.is_none() || !is_disconnect(&err) {
+            return Err(err);
+        }
+        tracing::debug!(error = %err, "reliable channel connection lost");
+        self.reconnect()
+    }
+
+    /// Buffer and send a message (internal)
+    fn push(&mut self, data: Vec<u8>) -> Result<()> {
+        while self.unacked.len() >= self.config.max_unacked.max(1) {
+            self.pump(None)?;
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let frame = frame(KIND_DATA, seq, &data);
+        self.unacked.push_back((seq, data));
+        match self.link().and_then(|channel| channel.send_bytes(frame)) {
+            Ok(()) => Ok(()),
+            // Reconnecting retransmits the message
+            Err(e) => self.recover(e),
+        }
+    }
+
+    /// Receive the next message (internal)
+    fn pull(&mut self) -> Result<Vec<u8>> {
+        loop {
+            if let Some(data) = self.inbox.pop_front() {
+                return Ok(data);
+            }
+            self.pump(None)?;
+        }
+    }
+
+    /// Read and handle one frame, waiting at most `timeout` if given
+    /// (internal)
+    fn pump(&mut self, timeout: Option<Duration>) -> Result<()> {
+        let read = self.link().and_then(|channel| match timeout {
+            Some(timeout) => channel.recv_bytes_timeout(timeout),
+            None => channel.recv_bytes().map(Some),
+        });
+        let data = match read {
+            Ok(Some(data)) => data,
+            Ok(None) => return Ok(()),
+            Err(e) => return self.recover(e),
+        };
+        if data.len() < FRAME_HEADER_SIZE {
+            return Err(IpcError::deserialization("reliable frame too short"));
+        }
+        let value = u64::from_le_bytes(data[1..FRAME_HEADER_SIZE].try_into().unwrap());
+        match data[0] {
+            KIND_HELLO => {
+                if self.peer_session != Some(value) {
+                    // A new peer numbers its messages from scratch
+                    self.peer_session = Some(value);
+                    self.received = 0;
+                }
+            }
+            KIND_ACK => {
+                while self.unacked.front().is_some_and(|(seq, _)| *seq <= value) {
+                    self.unacked.pop_front();
+                }
+            }
+            KIND_DATA => {
+                if value > self.received {
+                    self.received = value;
+                    self.inbox.push_back(data[FRAME_HEADER_SIZE..].to_vec());
+                }
+                // Acknowledge duplicates too, their first ack may have been lost
+                let ack = frame(KIND_ACK, self.received, &[]);
+                if let Err(e) = self.link().and_then(|channel| channel.send_bytes(ack)) {
+                    self.recover(e)?;
+                }
+            }
+            kind => {
+                return Err(IpcError::deserialization(format!(
+                    "unknown reliable frame kind {}",
+                    kind
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ReliableChannel<Vec<u8>> {
+    /// Send raw bytes
+    pub fn send_bytes(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        self.push(data.as_ref().to_vec())
+    }
+
+    /// Receive raw bytes
+    pub fn recv_bytes(&mut self) -> Result<Vec<u8>> {
+        self.pull()
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> ReliableChannel<T> {
+    /// Send a typed message (serialized as JSON)
+    pub fn send(&mut self, msg: &T) -> Result<()> {
+        let data = encode(msg)?;
+        self.push(data)
+    }
+
+    /// Receive a typed message (deserialized from JSON)
+    pub fn recv(&mut self) -> Result<T> {
+        let data = self.pull()?;
+        decode(&data)
+    }
+}
+
+/// Build a frame with the given header and payload.
+fn frame(kind: u8, value: u64, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+    frame.push(kind);
+    frame.extend_from_slice(&value.to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Whether an error means the connection is gone rather than the message
+/// being bad.
+fn is_disconnect(err: &IpcError) -> bool {
+    match err {
+        IpcError::Closed => true,
+        IpcError::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::UnexpectedEof
+                | std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::NotConnected
+        ),
+        _ => false,
+    }
+}
+
+/// A random session ID.
+fn session_id() -> u64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_frame_layout() {
+        let data = frame(KIND_DATA, 7, b"hi");
+        assert_eq!(data.len(), FRAME_HEADER_SIZE + 2);
+        assert_eq!(data[0], KIND_DATA);
+        assert_eq!(u64::from_le_bytes(data[1..9].try_into().unwrap()), 7);
+        assert_eq!(&data[9..], b"hi");
+        assert!(is_disconnect(&IpcError::Io(
+            std::io::ErrorKind::BrokenPipe.into()
+        )));
+        assert!(!is_disconnect(&IpcError::Timeout));
+    }
+
+    #[test]
+    fn test_retransmits_after_server_restart() {
+        let name = format!("test_reliable_{}", std::process::id());
+
+        // The first server acknowledges one message and goes away
+        let server = thread::spawn({
+            let name = name.clone();
+            move || {
+                let mut server =
+                    ReliableChannel::<Vec<u8>>::create(&name, ReliableConfig::default()).unwrap();
+                server.wait_for_client().unwrap();
+                assert_eq!(server.recv_bytes().unwrap(), b"one");
+            }
+        });
+        thread::sleep(Duration::from_millis(100));
+
+        let mut client =
+            ReliableChannel::<Vec<u8>>::connect(&name, ReliableConfig::default()).unwrap();
+        client.send_bytes(b"one").unwrap();
+        server.join().unwrap();
+
+        // The restarted server gets the unacknowledged message
+        let server = thread::spawn({
+            let name = name.clone();
+            move || {
+                let mut server =
+                    ReliableChannel::<Vec<u8>>::create(&name, ReliableConfig::default()).unwrap();
+                server.wait_for_client().unwrap();
+                let mut received = Vec::new();
+                // "one" may arrive again if its ack was never read
+                while received.last().map(Vec::as_slice) != Some(b"two".as_slice()) {
+                    received.push(server.recv_bytes().unwrap());
+                }
+            }
+        });
+
+        client.send_bytes(b"two").unwrap();
+        client.wait_acked(Duration::from_secs(5)).unwrap();
+        assert_eq!(client.unacked(), 0);
+        drop(client);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,617 @@
+//! Runtime Config - Live adjustment of whitelisted daemon settings
+//!
+//! Reproducing an intermittent IPC bug usually means turning on debug
+//! logging or loosening a limit on a daemon that is already running.
+//! [`RuntimeConfig`] holds the set of settings that may be changed at
+//! runtime; anything not registered is rejected. Updates touching several
+//! settings are all-or-nothing: every value is validated before any is
+//! applied, and if applying one fails the ones already applied are rolled
+//! back.
+//!
+//! Built-in tunables:
+//!
+//! - [`LogFilter`]: a `target=level` filter string for tracing output
+//! - [`RateLimiter`]: a token bucket usable as router middleware
+//! - [`setting_fn`]: any getter/setter pair, e.g. an event bus's
+//!   [`SlowConsumerPolicy`](crate::event_stream::SlowConsumerPolicy) or a
+//!   heartbeat interval
+//!
+//! [`Router::config_routes`](crate::api_server::Router::config_routes)
+//! serves the config at `GET`/`PUT /v1/_admin/config`.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ipckit::{setting_fn, EventBus, LogFilter, RateLimiter, RuntimeConfig};
+//! use std::sync::Arc;
+//!
+//! let config = Arc::new(RuntimeConfig::new());
+//! config.register("log_filter", LogFilter::global().clone());
+//! config.register("rate_limit", limiter.clone());
+//! let bus2 = bus.clone();
+//! config.register(
+//!     "slow_consumer",
+//!     setting_fn(move || bus.slow_consumer_policy(), move |p| {
+//!         bus2.set_slow_consumer_policy(p);
+//!         Ok(())
+//!     }),
+//! );
+//!
+//! server.router().config_routes(config);
+//! // PUT /v1/_admin/config {"log_filter": "info,ipckit::channel=debug"}
+//! ```
+
+use crate::error::{IpcError, Result};
+use parking_lot::{Mutex, RwLock};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use tracing::level_filters::LevelFilter;
+
+/// A setting that can be read and changed at runtime.
+pub trait Setting: Send + Sync {
+    /// Get the current value.
+    fn get(&self) -> JsonValue;
+
+    /// Check whether `value` would be accepted, without applying it.
+    fn validate(&self, value: &JsonValue) -> Result<()>;
+
+    /// Apply a new value.
+    fn set(&self, value: &JsonValue) -> Result<()>;
+}
+
+/// Registry of the settings that may be changed at runtime.
+#[derive(Default)]
+pub struct RuntimeConfig {
+    settings: RwLock<BTreeMap<String, Arc<dyn Setting>>>,
+    /// Serializes updates so concurrent PUTs cannot interleave
+    update_lock: Mutex<()>,
+}
+
+impl RuntimeConfig {
+    /// Create an empty config.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a runtime-tunable setting, replacing any with the same name.
+    pub fn register<S: Setting + 'static>(&self, name: &str, setting: S) -> &Self {
+        self.settings
+            .write()
+            .insert(name.to_string(), Arc::new(setting));
+        self
+    }
+
+    /// Get the names of the registered settings.
+    pub fn names(&self) -> Vec<String> {
+        self.settings.read().keys().cloned().collect()
+    }
+
+    /// Get the current value of one setting.
+    pub fn get(&self, name: &str) -> Option<JsonValue> {
+        self.settings.read().get(name).map(|s| s.get())
+    }
+
+    /// Get the current values of all settings as a JSON object.
+    pub fn snapshot(&self) -> JsonValue {
+        let settings = self.settings.read();
+        JsonValue::Object(
+            settings
+                .iter()
+                .map(|(name, setting)| (name.clone(), setting.get()))
+                .collect(),
+        )
+    }
+
+    /// Apply a JSON object of `name: value` changes atomically.
+    ///
+    /// Fails with [`IpcError::PermissionDenied`] if any name is not a
+    /// registered setting, and with the setting's own error if a value is
+    /// invalid; in both cases nothing is changed. Returns the new snapshot.
+    pub fn update(&self, changes: &JsonValue) -> Result<JsonValue> {
+        let changes = changes.as_object().ok_or_else(|| {
+            IpcError::deserialization("Config update must be a JSON object".to_string())
+        })?;
+
+        let _guard = self.update_lock.lock();
+        let planned: Vec<(&String, Arc<dyn Setting>, &JsonValue)> = {
+            let settings = self.settings.read();
+            changes
+                .iter()
+                .map(|(name, value)| {
+                    settings
+                        .get(name)
+                        .map(|s| (name, Arc::clone(s), value))
+                        .ok_or_else(|| {
+                            IpcError::PermissionDenied(format!(
+                                "Setting '{}' is not runtime-tunable",
+                                name
+                            ))
+                        })
+                })
+                .collect::<Result<_>>()?
+        };
+
+        for (name, setting, value) in &planned {
+            setting
+                .validate(value)
+                .map_err(|e| with_setting_name(name, e))?;
+        }
+
+        let mut applied: Vec<(&Arc<dyn Setting>, JsonValue)> = Vec::new();
+        for (name, setting, value) in &planned {
+            let previous = setting.get();
+            if let Err(e) = setting.set(value) {
+                for (setting, previous) in applied.into_iter().rev() {
+                    let _ = setting.set(&previous);
+                }
+                return Err(with_setting_name(name, e));
+            }
+            applied.push((setting, previous));
+        }
+
+        for (name, _, value) in &planned {
+            tracing::info!(setting = %name, value = %value, "runtime config changed");
+        }
+
+        Ok(self.snapshot())
+    }
+}
+
+fn with_setting_name(name: &str, err: IpcError) -> IpcError {
+    match err {
+        IpcError::Deserialization(msg) => {
+            IpcError::Deserialization(format!("Invalid value for '{}': {}", name, msg))
+        }
+        other => other,
+    }
+}
+
+/// Setting backed by a getter and a setter.
+pub struct FnSetting<T, G, S> {
+    getter: G,
+    setter: S,
+    _value: std::marker::PhantomData<fn() -> T>,
+}
+
+/// Create a [`Setting`] from a getter and a setter of a serde type.
+///
+/// Values are validated by deserializing them into `T`; the setter may
+/// reject a well-formed value by returning an error.
+pub fn setting_fn<T, G, S>(getter: G, setter: S) -> FnSetting<T, G, S>
+where
+    T: Serialize + DeserializeOwned,
+    G: Fn() -> T + Send + Sync,
+    S: Fn(T) -> Result<()> + Send + Sync,
+{
+    FnSetting {
+        getter,
+        setter,
+        _value: std::marker::PhantomData,
+    }
+}
+
+impl<T, G, S> Setting for FnSetting<T, G, S>
+where
+    T: Serialize + DeserializeOwned,
+    G: Fn() -> T + Send + Sync,
+    S: Fn(T) -> Result<()> + Send + Sync,
+{
+    fn get(&self) -> JsonValue {
+        serde_json::to_value((self.getter)()).unwrap_or(JsonValue::Null)
+    }
+
+    fn validate(&self, value: &JsonValue) -> Result<()> {
+        T::deserialize(value)
+            .map(|_| ())
+            .map_err(|e| IpcError::deserialization(e.to_string()))
+    }
+
+    fn set(&self, value: &JsonValue) -> Result<()> {
+        let value = T::deserialize(value).map_err(|e| IpcError::deserialization(e.to_string()))?;
+        (self.setter)(value)
+    }
+}
+
+/// Parsed form of a [`LogFilter`] spec.
+#[derive(Debug, Clone)]
+struct FilterDirectives {
+    spec: String,
+    default: LevelFilter,
+    /// `(target, level)` pairs, most specific target first
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl FilterDirectives {
+    fn parse(spec: &str) -> Result<Self> {
+        let mut default = LevelFilter::ERROR;
+        let mut targets = Vec::new();
+
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let (target, level) = match directive.split_once('=') {
+                Some((target, level)) => (Some(target.trim()), level.trim()),
+                None => (None, directive),
+            };
+            let level: LevelFilter = level.parse().map_err(|_| {
+                IpcError::deserialization(format!("invalid log level in '{}'", directive))
+            })?;
+            match target {
+                Some("") => {
+                    return Err(IpcError::deserialization(format!(
+                        "empty target in '{}'",
+                        directive
+                    )))
+                }
+                Some(target) => targets.push((target.to_string(), level)),
+                None => default = level,
+            }
+        }
+
+        targets.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(Self {
+            spec: spec.to_string(),
+            default,
+            targets,
+        })
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| {
+                target == prefix
+                    || (target.starts_with(prefix.as_str())
+                        && target[prefix.len()..].starts_with("::"))
+            })
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, |a, b| a.max(b))
+    }
+}
+
+/// A runtime-adjustable tracing filter.
+///
+/// The spec uses the familiar `RUST_LOG` directive syntax: a default level
+/// plus `target=level` overrides, e.g. `"warn,ipckit::channel=debug"`.
+/// Plug [`enabled`](Self::enabled) into the application's subscriber, for
+/// example with `tracing_subscriber::filter::filter_fn`.
+#[derive(Clone)]
+pub struct LogFilter {
+    directives: Arc<RwLock<FilterDirectives>>,
+}
+
+impl LogFilter {
+    /// Create a filter from a directive spec.
+    pub fn new(spec: &str) -> Result<Self> {
+        Ok(Self {
+            directives: Arc::new(RwLock::new(FilterDirectives::parse(spec)?)),
+        })
+    }
+
+    /// Get the process-wide filter, initially `"info"`.
+    pub fn global() -> &'static LogFilter {
+        static GLOBAL: OnceLock<LogFilter> = OnceLock::new();
+        GLOBAL.get_or_init(|| LogFilter::new("info").expect("valid default filter"))
+    }
+
+    /// Get the current spec.
+    pub fn spec(&self) -> String {
+        self.directives.read().spec.clone()
+    }
+
+    /// Replace the spec.
+    pub fn set_spec(&self, spec: &str) -> Result<()> {
+        let directives = FilterDirectives::parse(spec)?;
+        *self.directives.write() = directives;
+        // Callsites cache whether they are enabled; make them ask again
+        tracing::callsite::rebuild_interest_cache();
+        Ok(())
+    }
+
+    /// Check whether events at `level` from `target` pass the filter.
+    pub fn enabled(&self, target: &str, level: &tracing::Level) -> bool {
+        *level <= self.directives.read().level_for(target)
+    }
+
+    /// Check whether a span or event passes the filter.
+    pub fn enabled_metadata(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        self.enabled(metadata.target(), metadata.level())
+    }
+
+    /// Get the most verbose level any directive enables.
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives.read().max_level()
+    }
+}
+
+impl std::fmt::Debug for LogFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LogFilter").field(&self.spec()).finish()
+    }
+}
+
+impl Setting for LogFilter {
+    fn get(&self) -> JsonValue {
+        JsonValue::String(self.spec())
+    }
+
+    fn validate(&self, value: &JsonValue) -> Result<()> {
+        let spec = value
+            .as_str()
+            .ok_or_else(|| IpcError::deserialization("expected a filter string".to_string()))?;
+        FilterDirectives::parse(spec).map(|_| ())
+    }
+
+    fn set(&self, value: &JsonValue) -> Result<()> {
+        self.validate(value)?;
+        self.set_spec(value.as_str().unwrap_or_default())
+    }
+}
+
+/// Token bucket parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Sustained requests per second
+    pub per_second: f64,
+    /// Maximum burst size
+    pub burst: u32,
+}
+
+impl RateLimit {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if !(self.per_second.is_finite() && self.per_second > 0.0) || self.burst == 0 {
+            return Err(IpcError::deserialization(
+                "rate limit needs per_second > 0 and burst >= 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+struct Bucket {
+    limit: Option<RateLimit>,
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// A shared token bucket rate limiter.
+///
+/// Install it on a router with
+/// [`Router::rate_limit`](crate::api_server::Router::rate_limit); as a
+/// [`Setting`] its value is a [`RateLimit`] object, or `null` for unlimited.
+#[derive(Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter that lets everything through until a limit is set.
+    pub fn unlimited() -> Self {
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                limit: None,
+                tokens: 0.0,
+                refilled: Instant::now(),
+            })),
+        }
+    }
+
+    /// Create a limiter with the given limit.
+    pub fn new(limit: RateLimit) -> Result<Self> {
+        let limiter = Self::unlimited();
+        limiter.set_limit(Some(limit))?;
+        Ok(limiter)
+    }
+
+    /// Get the current limit.
+    pub fn limit(&self) -> Option<RateLimit> {
+        self.bucket.lock().limit
+    }
+
+    /// Change the limit; `None` removes it. The bucket starts full.
+    pub fn set_limit(&self, limit: Option<RateLimit>) -> Result<()> {
+        if let Some(ref limit) = limit {
+            limit.validate()?;
+        }
+        let mut bucket = self.bucket.lock();
+        bucket.limit = limit;
+        bucket.tokens = limit.map(|l| l.burst as f64).unwrap_or(0.0);
+        bucket.refilled = Instant::now();
+        Ok(())
+    }
+
+    /// Take one token, returning `false` if the caller is over the limit.
+    pub fn try_acquire(&self) -> bool {
+        let mut bucket = self.bucket.lock();
+        let Some(limit) = bucket.limit else {
+            return true;
+        };
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        bucket.refilled = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("limit", &self.limit())
+            .finish()
+    }
+}
+
+impl Setting for RateLimiter {
+    fn get(&self) -> JsonValue {
+        serde_json::to_value(self.limit()).unwrap_or(JsonValue::Null)
+    }
+
+    fn validate(&self, value: &JsonValue) -> Result<()> {
+        let limit: Option<RateLimit> = serde_json::from_value(value.clone())
+            .map_err(|e| IpcError::deserialization(e.to_string()))?;
+        limit.as_ref().map_or(Ok(()), RateLimit::validate)
+    }
+
+    fn set(&self, value: &JsonValue) -> Result<()> {
+        let limit: Option<RateLimit> = serde_json::from_value(value.clone())
+            .map_err(|e| IpcError::deserialization(e.to_string()))?;
+        self.set_limit(limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_stream::{EventBus, SlowConsumerPolicy};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn test_log_filter() {
+        let filter = LogFilter::new("warn,ipckit=info,ipckit::channel=trace").unwrap();
+        assert!(filter.enabled("ipckit::channel", &tracing::Level::TRACE));
+        assert!(filter.enabled("ipckit::channel::pipe", &tracing::Level::DEBUG));
+        assert!(!filter.enabled("ipckit::shm", &tracing::Level::DEBUG));
+        assert!(filter.enabled("ipckit::shm", &tracing::Level::INFO));
+        assert!(!filter.enabled("ipckitx", &tracing::Level::INFO));
+        assert!(filter.enabled("other", &tracing::Level::WARN));
+        assert_eq!(filter.max_level(), LevelFilter::TRACE);
+
+        assert!(filter.set_spec("ipckit=loud").is_err());
+        assert!(filter.set_spec("=debug").is_err());
+        assert_eq!(filter.spec(), "warn,ipckit=info,ipckit::channel=trace");
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(RateLimit {
+            per_second: 0.001,
+            burst: 2,
+        })
+        .unwrap();
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        limiter.set_limit(None).unwrap();
+        assert!(limiter.try_acquire());
+        assert!(RateLimiter::new(RateLimit {
+            per_second: 0.0,
+            burst: 1
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_update_is_atomic() {
+        let config = RuntimeConfig::new();
+        let filter = LogFilter::new("info").unwrap();
+        let limiter = RateLimiter::unlimited();
+        let bus = EventBus::default();
+        let interval = Arc::new(AtomicU64::new(1000));
+
+        let (getter_bus, setter_bus) = (bus.clone(), bus.clone());
+        let (getter_interval, setter_interval) = (Arc::clone(&interval), Arc::clone(&interval));
+        config
+            .register("log_filter", filter.clone())
+            .register("rate_limit", limiter.clone())
+            .register(
+                "slow_consumer",
+                setting_fn(
+                    move || getter_bus.slow_consumer_policy(),
+                    move |policy| {
+                        setter_bus.set_slow_consumer_policy(policy);
+                        Ok(())
+                    },
+                ),
+            )
+            .register(
+                "heartbeat_interval_ms",
+                setting_fn(
+                    move || getter_interval.load(Ordering::SeqCst),
+                    move |ms: u64| {
+                        if ms == 0 {
+                            return Err(IpcError::InvalidState("interval must be > 0".into()));
+                        }
+                        setter_interval.store(ms, Ordering::SeqCst);
+                        Ok(())
+                    },
+                ),
+            );
+
+        let snapshot = config
+            .update(&serde_json::json!({
+                "log_filter": "debug",
+                "rate_limit": {"per_second": 10.0, "burst": 5},
+                "slow_consumer": "block",
+            }))
+            .unwrap();
+        assert_eq!(snapshot["log_filter"], "debug");
+        assert_eq!(snapshot["heartbeat_interval_ms"], 1000);
+        assert_eq!(bus.slow_consumer_policy(), SlowConsumerPolicy::Block);
+        assert_eq!(limiter.limit().unwrap().burst, 5);
+
+        // Unknown settings are rejected before anything changes
+        let err = config
+            .update(&serde_json::json!({"log_filter": "trace", "max_connections": 1}))
+            .unwrap_err();
+        assert!(matches!(err, IpcError::PermissionDenied(_)));
+        assert_eq!(filter.spec(), "debug");
+
+        // So are invalid values
+        let err = config
+            .update(&serde_json::json!({"log_filter": "trace", "slow_consumer": "panic"}))
+            .unwrap_err();
+        assert!(matches!(err, IpcError::Deserialization(_)));
+        assert_eq!(filter.spec(), "debug");
+
+        // A setter failing after others were applied rolls them back
+        assert!(config
+            .update(&serde_json::json!({
+                "log_filter": "trace",
+                "slow_consumer": "drop_newest",
+                "heartbeat_interval_ms": 0,
+            }))
+            .is_err());
+        assert_eq!(filter.spec(), "debug");
+        assert_eq!(bus.slow_consumer_policy(), SlowConsumerPolicy::Block);
+        assert_eq!(interval.load(Ordering::SeqCst), 1000);
+    }
+
+    #[test]
+    fn test_config_routes() {
+        use crate::api_server::{Method, Request, Router, ADMIN_CONFIG_PATH};
+
+        let config = Arc::new(RuntimeConfig::new());
+        config.register("log_filter", LogFilter::new("info").unwrap());
+        let mut router = Router::new();
+        router.config_routes(Arc::clone(&config));
+
+        let mut req = Request::new(Method::PUT, ADMIN_CONFIG_PATH);
+        req.body = Some(serde_json::json!({"log_filter": "debug"}));
+        let resp = router.handle(req);
+        assert_eq!(resp.status, 200);
+        assert_eq!(config.get("log_filter").unwrap(), "debug");
+
+        let mut req = Request::new(Method::PUT, ADMIN_CONFIG_PATH);
+        req.body = Some(serde_json::json!({"workers": 8}));
+        assert_eq!(router.handle(req).status, 403);
+
+        let resp = router.handle(Request::new(Method::GET, ADMIN_CONFIG_PATH));
+        assert_eq!(resp.status, 200);
+    }
+}
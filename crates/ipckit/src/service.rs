@@ -0,0 +1,666 @@
+//! Service - Typed request/response services over the socket server
+//!
+//! [`Message::request`] takes a method name and a JSON blob, so nothing
+//! stops a client from sending the wrong parameters or misreading the
+//! result. The `ipckit_macros::service!` macro declares the methods of a
+//! service together with their request and response types once, and
+//! generates from that declaration:
+//!
+//! - a trait the server implements, one method per call
+//! - `{Service}Server<T>`, a [`ServiceDefinition`] dispatching calls to that
+//!   trait (serve it with [`ServiceHandler`])
+//! - `{Service}Client`, with one typed method per call
+//!
+//! Calls travel as regular [`SocketServer`](crate::socket_server::SocketServer)
+//! frames, not HTTP. Each carries a call ID and, if the method has a
+//! timeout, a deadline: the server cancels the call's [`CallContext`] when
+//! the deadline passes, and a client that gives up on a call, or whose
+//! [`CallOptions::cancel`] token fires, tells the server to cancel it too.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ipckit::service::{CallContext, ServiceHandler};
+//! use ipckit::{Result, SocketServer};
+//! use ipckit_macros::service;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! pub struct AddRequest { pub a: i64, pub b: i64 }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! pub struct AddResponse { pub sum: i64 }
+//!
+//! service! {
+//!     /// Arithmetic for the render farm.
+//!     pub service Calculator {
+//!         /// Add two numbers.
+//!         fn add(AddRequest) -> AddResponse;
+//!         /// Add two numbers, giving up after five seconds.
+//!         fn slow_add(AddRequest) -> AddResponse, timeout_ms = 5000;
+//!     }
+//! }
+//!
+//! struct Calc;
+//!
+//! impl Calculator for Calc {
+//!     fn add(&self, _ctx: &CallContext, req: AddRequest) -> Result<AddResponse> {
+//!         Ok(AddResponse { sum: req.a + req.b })
+//!     }
+//!
+//!     fn slow_add(&self, ctx: &CallContext, req: AddRequest) -> Result<AddResponse> {
+//!         for _ in 0..100 {
+//!             ctx.check()?;
+//!             // ... one step of the work
+//!         }
+//!         Ok(AddResponse { sum: req.a + req.b })
+//!     }
+//! }
+//!
+//! // Server
+//! let server = SocketServer::at("/tmp/calc.sock")?;
+//! server.spawn(ServiceHandler::new(CalculatorServer::new(Calc)));
+//!
+//! // Client
+//! let mut client = CalculatorClient::connect("/tmp/calc.sock")?;
+//! assert_eq!(client.add(AddRequest { a: 1, b: 2 })?.sum, 3);
+//! ```
+
+use crate::error::{ErrorCode, IpcError, Result};
+use crate::socket_server::{Connection, ConnectionHandler, Message, MessageType, SocketClient};
+use crate::task_manager::CancellationToken;
+use crate::trace_context::TraceContext;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Method of the message a client sends to cancel one of its calls.
+pub const CANCEL_METHOD: &str = "ipckit.service.cancel";
+
+/// How often a waiting client or server checks for replies and cancels.
+const CALL_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A method of a service, as declared with `service!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodInfo {
+    /// Method name, without the service prefix
+    pub name: &'static str,
+    /// How long a call may take, if limited
+    pub timeout: Option<Duration>,
+}
+
+/// A typed service the server side can dispatch calls to.
+///
+/// Implemented by the `{Service}Server` type generated by `service!`; see
+/// the [module docs](self).
+pub trait ServiceDefinition: Send + Sync + 'static {
+    /// Name of the service, the prefix of its wire method names.
+    const NAME: &'static str;
+
+    /// Get the methods of the service.
+    fn methods() -> &'static [MethodInfo];
+
+    /// Decode a call, run it and encode its result.
+    ///
+    /// Unknown methods fail with [`IpcError::NotFound`].
+    fn dispatch(&self, ctx: &CallContext, method: &str, params: Value) -> Result<Value>;
+}
+
+/// What a server-side method knows about the call it is serving.
+#[derive(Debug, Clone)]
+pub struct CallContext {
+    method: String,
+    connection_id: u64,
+    deadline: Option<Instant>,
+    token: CancellationToken,
+}
+
+impl CallContext {
+    /// Create a context for calling `method` directly, e.g. in tests.
+    pub fn new(method: &str) -> Self {
+        Self {
+            method: method.to_string(),
+            connection_id: 0,
+            deadline: None,
+            token: CancellationToken::new(),
+        }
+    }
+
+    /// Set the deadline of the call.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Get the method name, without the service prefix.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Get the ID of the connection the call arrived on.
+    pub fn connection_id(&self) -> u64 {
+        self.connection_id
+    }
+
+    /// Get the time by which the call must finish, if limited.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Get the time left until the deadline, if limited.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Get the token cancelled when the call times out, the client cancels
+    /// it or the client disconnects.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Check whether the call has been cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled() || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Fail with [`IpcError::Timeout`] if the call has been cancelled.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(IpcError::Timeout)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Options for a single client call.
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    /// How long to wait for the reply; overrides the method's timeout
+    pub timeout: Option<Duration>,
+    /// Token that abandons the call, and cancels it on the server, when
+    /// cancelled
+    pub cancel: Option<CancellationToken>,
+}
+
+impl CallOptions {
+    /// Create options using the method's defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how long to wait for the reply.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Abandon the call when `token` is cancelled.
+    pub fn cancel(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+}
+
+/// Socket server handler serving a [`ServiceDefinition`].
+///
+/// Each call runs on a worker thread while the connection thread watches
+/// for its deadline, a [`CANCEL_METHOD`] message from the client and the
+/// client disconnecting, any of which cancels the call's [`CallContext`].
+/// A cancelled call is answered with [`IpcError::Timeout`] once its method
+/// returns, so long-running methods should check
+/// [`CallContext::is_cancelled`]. Pings are answered; other message types
+/// are ignored.
+pub struct ServiceHandler<S> {
+    service: Arc<S>,
+}
+
+impl<S> Clone for ServiceHandler<S> {
+    fn clone(&self) -> Self {
+        Self {
+            service: Arc::clone(&self.service),
+        }
+    }
+}
+
+impl<S: ServiceDefinition> ServiceHandler<S> {
+    /// Wrap a service for use with a socket server.
+    pub fn new(service: S) -> Self {
+        Self::from_arc(Arc::new(service))
+    }
+
+    /// Wrap a service that is shared with other servers.
+    pub fn from_arc(service: Arc<S>) -> Self {
+        Self { service }
+    }
+
+    /// Get the wrapped service.
+    pub fn service(&self) -> &Arc<S> {
+        &self.service
+    }
+
+    /// Serve one call, returning the reply to send.
+    fn call(&self, conn: &mut Connection, msg: Message) -> Message {
+        let id = msg
+            .params()
+            .and_then(|p| p.get("id"))
+            .and_then(Value::as_u64);
+        let Some(id) = id else {
+            return Message::from_error(&IpcError::deserialization(
+                "Service call without an id".to_string(),
+            ));
+        };
+
+        let full = msg.method().unwrap_or_default();
+        let method = match full.strip_prefix(S::NAME).and_then(|m| m.strip_prefix('.')) {
+            Some(method) => method.to_string(),
+            None => {
+                return error_reply(
+                    id,
+                    &IpcError::NotFound(format!("Unknown service method: {}", full)),
+                )
+            }
+        };
+
+        let params = msg.params().and_then(|p| p.get("body")).cloned();
+        let timeout = msg
+            .params()
+            .and_then(|p| p.get("timeout_ms"))
+            .and_then(Value::as_u64)
+            .map(Duration::from_millis);
+
+        let ctx = CallContext {
+            method,
+            connection_id: conn.id(),
+            deadline: timeout.map(|t| Instant::now() + t),
+            token: CancellationToken::new(),
+        };
+
+        let result = self.run(conn, id, &ctx, params.unwrap_or(Value::Null));
+        match result {
+            Ok(value) => Message::response(serde_json::json!({ "id": id, "body": value })),
+            Err(e) => error_reply(id, &e),
+        }
+    }
+
+    /// Run a call on a worker thread, cancelling it when asked to.
+    fn run(
+        &self,
+        conn: &mut Connection,
+        id: u64,
+        ctx: &CallContext,
+        params: Value,
+    ) -> Result<Value> {
+        let trace = TraceContext::current();
+        let (tx, rx) = crossbeam_channel::bounded(1);
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let _trace = trace.as_ref().map(TraceContext::enter);
+                let _ = tx.send(self.service.dispatch(ctx, ctx.method(), params));
+            });
+
+            loop {
+                match rx.recv_timeout(CALL_POLL_INTERVAL) {
+                    Ok(result) => {
+                        return if ctx.is_cancelled() {
+                            Err(IpcError::Timeout)
+                        } else {
+                            result
+                        };
+                    }
+                    Err(crossbeam_channel::RecvTimeoutError::Timeout) => {}
+                    Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+                        return Err(IpcError::Other("Service method panicked".to_string()));
+                    }
+                }
+
+                if ctx.token.is_cancelled() {
+                    continue;
+                }
+                if ctx.is_cancelled() || conn.is_peer_closed() {
+                    ctx.token.cancel();
+                    continue;
+                }
+                // The client blocks on its call, so the only message it can
+                // send meanwhile is a cancel
+                match conn.try_recv() {
+                    Ok(Some(msg)) if is_cancel_of(&msg, id) => ctx.token.cancel(),
+                    Ok(Some(msg)) => {
+                        tracing::warn!(
+                            conn_id = conn.id(),
+                            msg_type = ?msg.msg_type,
+                            "dropping message received during a service call"
+                        );
+                    }
+                    Ok(None) => {}
+                    Err(_) => ctx.token.cancel(),
+                }
+            }
+        })
+    }
+}
+
+impl<S: ServiceDefinition> ConnectionHandler for ServiceHandler<S> {
+    fn on_message(&self, conn: &mut Connection, msg: Message) -> Result<Option<Message>> {
+        match msg.msg_type {
+            // A cancel for a call that already finished
+            MessageType::Request if msg.method() == Some(CANCEL_METHOD) => Ok(None),
+            MessageType::Request => Ok(Some(self.call(conn, msg))),
+            MessageType::Ping => Ok(Some(Message::pong())),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Check whether `msg` cancels call `id`.
+fn is_cancel_of(msg: &Message, id: u64) -> bool {
+    msg.msg_type == MessageType::Request
+        && msg.method() == Some(CANCEL_METHOD)
+        && msg
+            .params()
+            .and_then(|p| p.get("id"))
+            .and_then(Value::as_u64)
+            == Some(id)
+}
+
+/// Build the error reply to call `id`.
+fn error_reply(id: u64, err: &IpcError) -> Message {
+    let mut reply = Message::from_error(err);
+    if let Value::Object(payload) = &mut reply.payload {
+        payload.insert("id".to_string(), Value::from(id));
+    }
+    reply
+}
+
+/// Rebuild the error carried by an error reply.
+fn reply_error(reply: &Message) -> IpcError {
+    let message = reply
+        .payload
+        .get("message")
+        .and_then(Value::as_str)
+        .unwrap_or("Unknown error")
+        .to_string();
+    match reply.error_code() {
+        Some(ErrorCode::Timeout) => IpcError::Timeout,
+        Some(ErrorCode::Closed) => IpcError::Closed,
+        Some(ErrorCode::WouldBlock) => IpcError::WouldBlock,
+        Some(ErrorCode::NotFound) => IpcError::NotFound(message),
+        Some(ErrorCode::PermissionDenied) => IpcError::PermissionDenied(message),
+        Some(ErrorCode::InvalidName) => IpcError::InvalidName(message),
+        Some(ErrorCode::AlreadyExists) => IpcError::AlreadyExists(message),
+        Some(ErrorCode::Serialization) => IpcError::Serialization(message),
+        Some(ErrorCode::Deserialization) => IpcError::Deserialization(message),
+        Some(ErrorCode::Platform) => IpcError::Platform(message),
+        Some(ErrorCode::InvalidState) => IpcError::InvalidState(message),
+        Some(ErrorCode::Incompatible) => IpcError::Incompatible(message),
+        _ => IpcError::Other(message),
+    }
+}
+
+/// Untyped client side of a service, used by the generated
+/// `{Service}Client`.
+pub struct ServiceClient {
+    client: SocketClient,
+    service: &'static str,
+    next_id: u64,
+    default_timeout: Option<Duration>,
+}
+
+impl ServiceClient {
+    /// Call the methods of `service` over an existing client.
+    pub fn new(client: SocketClient, service: &'static str) -> Self {
+        Self {
+            client,
+            service,
+            next_id: 1,
+            default_timeout: None,
+        }
+    }
+
+    /// Connect to the server at `path` and call the methods of `service`.
+    pub fn connect(path: &str, service: &'static str) -> Result<Self> {
+        Ok(Self::new(SocketClient::connect(path)?, service))
+    }
+
+    /// Set the timeout of methods declared without one.
+    pub fn default_timeout(&mut self, timeout: Option<Duration>) {
+        self.default_timeout = timeout;
+    }
+
+    /// Get the underlying socket client.
+    pub fn socket(&mut self) -> &mut SocketClient {
+        &mut self.client
+    }
+
+    /// Call `method` with an encoded request, returning the encoded response.
+    ///
+    /// Fails with [`IpcError::Timeout`] if no reply arrives within the
+    /// timeout, and with [`IpcError::Other`] if `options.cancel` is
+    /// cancelled first; in both cases the server is asked to cancel the
+    /// call and its late reply is discarded.
+    pub fn call_raw(
+        &mut self,
+        method: &MethodInfo,
+        params: Value,
+        options: &CallOptions,
+    ) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let timeout = options.timeout.or(method.timeout).or(self.default_timeout);
+        let mut request = serde_json::json!({ "id": id, "body": params });
+        if let Some(timeout) = timeout {
+            request["timeout_ms"] = Value::from(timeout.as_millis() as u64);
+        }
+        let wire_method = format!("{}.{}", self.service, method.name);
+        self.client.send(&Message::request(&wire_method, request))?;
+
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            if let Some(reply) = self.client.try_recv()? {
+                let reply_id = match reply.msg_type {
+                    MessageType::Response => reply.result().and_then(|r| r.get("id")),
+                    MessageType::Error => reply.payload.get("id"),
+                    _ => None,
+                }
+                .and_then(Value::as_u64);
+                // Late replies to abandoned calls
+                if reply_id != Some(id) {
+                    continue;
+                }
+                return match reply.msg_type {
+                    MessageType::Response => Ok(reply
+                        .result()
+                        .and_then(|r| r.get("body"))
+                        .cloned()
+                        .unwrap_or(Value::Null)),
+                    _ => Err(reply_error(&reply)),
+                };
+            }
+
+            if options.cancel.as_ref().is_some_and(|t| t.is_cancelled()) {
+                self.cancel(id)?;
+                return Err(IpcError::Other(format!(
+                    "Call to {} cancelled",
+                    wire_method
+                )));
+            }
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                self.cancel(id)?;
+                return Err(IpcError::Timeout);
+            }
+            std::thread::sleep(CALL_POLL_INTERVAL);
+        }
+    }
+
+    /// Call `method` with a typed request.
+    pub fn call<Req, Resp>(
+        &mut self,
+        method: &MethodInfo,
+        request: &Req,
+        options: &CallOptions,
+    ) -> Result<Resp>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let params = encode(request)?;
+        decode(self.call_raw(method, params, options)?)
+    }
+
+    /// Ask the server to cancel call `id`.
+    fn cancel(&mut self, id: u64) -> Result<()> {
+        self.client.send(&Message::request(
+            CANCEL_METHOD,
+            serde_json::json!({ "id": id }),
+        ))
+    }
+}
+
+/// Encode a request or response for the wire.
+#[doc(hidden)]
+pub fn encode<T: Serialize>(value: &T) -> Result<Value> {
+    serde_json::to_value(value).map_err(|e| IpcError::serialization(e.to_string()))
+}
+
+/// Decode a request or response from the wire.
+#[doc(hidden)]
+pub fn decode<T: DeserializeOwned>(value: Value) -> Result<T> {
+    serde_json::from_value(value).map_err(|e| IpcError::deserialization(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket_server::SocketServer;
+    use std::thread;
+
+    /// What `service!` generates for `fn add(i64) -> i64; fn wait(u64) -> bool`.
+    struct Math;
+
+    const METHODS: &[MethodInfo] = &[
+        MethodInfo {
+            name: "add",
+            timeout: None,
+        },
+        MethodInfo {
+            name: "wait",
+            timeout: Some(Duration::from_millis(100)),
+        },
+    ];
+
+    impl ServiceDefinition for Math {
+        const NAME: &'static str = "Math";
+
+        fn methods() -> &'static [MethodInfo] {
+            METHODS
+        }
+
+        fn dispatch(&self, ctx: &CallContext, method: &str, params: Value) -> Result<Value> {
+            match method {
+                "add" => {
+                    let (a, b): (i64, i64) = decode(params)?;
+                    encode(&(a + b))
+                }
+                "wait" => {
+                    let ms: u64 = decode(params)?;
+                    let deadline = Instant::now() + Duration::from_millis(ms);
+                    while Instant::now() < deadline {
+                        ctx.check()?;
+                        thread::sleep(Duration::from_millis(5));
+                    }
+                    encode(&true)
+                }
+                _ => Err(IpcError::NotFound(format!(
+                    "Unknown service method: {}",
+                    method
+                ))),
+            }
+        }
+    }
+
+    fn serve(name: &str) -> ServiceClient {
+        let server = SocketServer::at(name).unwrap();
+        server.spawn(ServiceHandler::new(Math));
+        thread::sleep(Duration::from_millis(100));
+        ServiceClient::connect(name, "Math").unwrap()
+    }
+
+    #[test]
+    fn test_typed_call() {
+        let mut client = serve(&format!("test_service_call_{}", std::process::id()));
+
+        let sum: i64 = client
+            .call(&METHODS[0], &(2, 3), &CallOptions::new())
+            .unwrap();
+        assert_eq!(sum, 5);
+
+        let missing = MethodInfo {
+            name: "sub",
+            timeout: None,
+        };
+        let err = client
+            .call::<_, i64>(&missing, &(2, 3), &CallOptions::new())
+            .unwrap_err();
+        assert!(matches!(err, IpcError::NotFound(_)));
+
+        let err = client
+            .call::<_, i64>(&METHODS[0], &"not a pair", &CallOptions::new())
+            .unwrap_err();
+        assert!(matches!(err, IpcError::Deserialization(_)));
+    }
+
+    #[test]
+    fn test_method_timeout_and_cancel() {
+        let mut client = serve(&format!("test_service_timeout_{}", std::process::id()));
+
+        // The declared timeout cancels the call on both sides
+        let start = Instant::now();
+        let err = client
+            .call::<_, bool>(&METHODS[1], &5_000u64, &CallOptions::new())
+            .unwrap_err();
+        assert!(err.is_timeout());
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // Cancelling from the client abandons the call
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            canceller.cancel();
+        });
+        let options = CallOptions::new()
+            .timeout(Duration::from_secs(5))
+            .cancel(token);
+        let err = client
+            .call::<_, bool>(&METHODS[1], &5_000u64, &options)
+            .unwrap_err();
+        assert!(matches!(err, IpcError::Other(_)));
+
+        // Late replies to the abandoned calls are skipped
+        let done: bool = client
+            .call(&METHODS[1], &10u64, &CallOptions::new())
+            .unwrap();
+        assert!(done);
+        let sum: i64 = client
+            .call(&METHODS[0], &(1, 1), &CallOptions::new())
+            .unwrap();
+        assert_eq!(sum, 2);
+    }
+
+    #[test]
+    fn test_call_context_deadline() {
+        let ctx = CallContext::new("wait").with_deadline(Instant::now());
+        assert!(ctx.is_cancelled());
+        assert!(ctx.check().unwrap_err().is_timeout());
+        assert_eq!(ctx.remaining(), Some(Duration::ZERO));
+
+        let ctx = CallContext::new("add");
+        assert!(!ctx.is_cancelled());
+        ctx.cancellation_token().cancel();
+        assert!(ctx.is_cancelled());
+    }
+}
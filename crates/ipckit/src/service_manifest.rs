@@ -0,0 +1,775 @@
+//! # Service Manifest
+//!
+//! Declarative description of an ipckit daemon, loaded from TOML or JSON.
+//! A manifest lists the sockets to bind, route namespaces, static routes,
+//! predefined scheduled tasks and webhook targets, so that the common cases
+//! can be stood up from configuration without writing Rust.
+//!
+//! ## Features
+//!
+//! - Sockets that each mount a selection of namespaces
+//! - Routes answering with a fixed body, serving a directory, or proxying to
+//!   another API server
+//! - Tasks run once at startup or on a fixed interval, tracked by a
+//!   [`TaskManager`]
+//! - Webhooks forwarding matching events to another API server
+//!
+//! TOML support requires the `manifest-toml` feature.
+//!
+//! ## Example
+//!
+//! ```toml
+//! name = "my-daemon"
+//!
+//! [[sockets]]
+//! path = "/tmp/my-daemon.sock"
+//!
+//! [[routes]]
+//! path = "/v1/version"
+//! kind = "static"
+//! body = { version = "1.0.0" }
+//!
+//! [[namespaces]]
+//! name = "ui"
+//! prefix = "/ui"
+//! routes = [{ path = "/", kind = "dir", dir = "./dist" }]
+//!
+//! [[tasks]]
+//! name = "cleanup"
+//! command = ["sh", "-c", "rm -rf /tmp/my-daemon-cache"]
+//! interval_secs = 3600
+//!
+//! [[webhooks]]
+//! socket = "/tmp/notifier.sock"
+//! path = "/hooks/tasks"
+//! events = ["task.completed", "task.failed"]
+//! ```
+//!
+//! ```rust,ignore
+//! use ipckit::ServiceManifest;
+//!
+//! let manifest = ServiceManifest::load("app.toml")?;
+//! let service = manifest.start(Default::default())?;
+//! service.join()?;
+//! ```
+
+use crate::api_server::{ApiClient, ApiServer, ApiServerConfig, Method, Request, Response, Router};
+use crate::cli_bridge::{wait_or_cancel, ProcessTree};
+use crate::error::{IpcError, Result};
+use crate::event_stream::EventFilter;
+use crate::socket_server::SocketServerConfig;
+use crate::task_manager::{TaskBuilder, TaskHandle, TaskManager, TaskManagerConfig};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often background workers check for a stop request.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Declarative description of a daemon.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServiceManifest {
+    /// Service name, used in logs and task labels
+    pub name: String,
+    /// Sockets to bind
+    pub sockets: Vec<SocketSpec>,
+    /// Route groups mounted under a common prefix
+    pub namespaces: Vec<NamespaceSpec>,
+    /// Routes mounted on every socket
+    pub routes: Vec<RouteSpec>,
+    /// Predefined tasks
+    pub tasks: Vec<ScheduledTaskSpec>,
+    /// Event forwarding targets
+    pub webhooks: Vec<WebhookSpec>,
+}
+
+/// A socket the service listens on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SocketSpec {
+    /// Socket path (or pipe name on Windows)
+    pub path: String,
+    /// Namespaces mounted on this socket (empty = all)
+    #[serde(default)]
+    pub namespaces: Vec<String>,
+}
+
+/// A group of routes sharing a path prefix.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamespaceSpec {
+    /// Namespace name, referenced from [`SocketSpec::namespaces`]
+    pub name: String,
+    /// Path prefix prepended to every route
+    #[serde(default)]
+    pub prefix: String,
+    /// Routes in this namespace
+    #[serde(default)]
+    pub routes: Vec<RouteSpec>,
+}
+
+/// A single route.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteSpec {
+    /// Path pattern (see [`PathPattern`](crate::api_server::PathPattern))
+    pub path: String,
+    /// HTTP method (ignored for `dir` routes, which are always GET)
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// What the route does
+    #[serde(flatten)]
+    pub target: RouteTarget,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// Behaviour of a [`RouteSpec`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RouteTarget {
+    /// Respond with a fixed body.
+    Static {
+        /// Status code
+        #[serde(default = "default_status")]
+        status: u16,
+        /// Response body; strings are sent as text, anything else as JSON
+        #[serde(default)]
+        body: JsonValue,
+        /// Content type for string bodies
+        #[serde(default)]
+        content_type: Option<String>,
+    },
+    /// Serve files from a directory below the route path.
+    Dir {
+        /// Directory to serve
+        dir: PathBuf,
+    },
+    /// Forward the request to another API server, relaying its response
+    /// (status, headers and body) unchanged.
+    Proxy {
+        /// Socket path of the upstream server
+        socket: String,
+        /// Path on the upstream server (defaults to the request path)
+        #[serde(default)]
+        path: Option<String>,
+        /// Timeout in milliseconds
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// A task started by the service.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTaskSpec {
+    /// Task name
+    pub name: String,
+    /// Task type
+    #[serde(default = "default_task_type")]
+    pub task_type: String,
+    /// Program and arguments
+    pub command: Vec<String>,
+    /// Run every `interval_secs` seconds (None = once at startup)
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+    /// Labels attached to every run
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+fn default_task_type() -> String {
+    "scheduled".to_string()
+}
+
+/// Forwards matching events to another API server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookSpec {
+    /// Socket path of the receiving server
+    pub socket: String,
+    /// Path events are POSTed to
+    pub path: String,
+    /// Event type patterns (empty = all events)
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl ServiceManifest {
+    /// Load a manifest from a file, choosing the format by extension.
+    ///
+    /// `.toml` files require the `manifest-toml` feature; anything else is
+    /// parsed as JSON.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml_str(&content),
+            _ => Self::from_json_str(&content),
+        }
+    }
+
+    /// Parse a JSON manifest.
+    pub fn from_json_str(s: &str) -> Result<Self> {
+        let manifest: Self =
+            serde_json::from_str(s).map_err(|e| IpcError::deserialization(e.to_string()))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Parse a TOML manifest.
+    #[cfg(feature = "manifest-toml")]
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        let manifest: Self =
+            toml::from_str(s).map_err(|e| IpcError::deserialization(e.to_string()))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Parse a TOML manifest.
+    #[cfg(not(feature = "manifest-toml"))]
+    pub fn from_toml_str(_s: &str) -> Result<Self> {
+        Err(IpcError::Other(
+            "TOML manifests require the `manifest-toml` feature".to_string(),
+        ))
+    }
+
+    /// Check the manifest for inconsistencies.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(IpcError::Other(format!("invalid manifest: {}", msg)));
+
+        if self.sockets.is_empty() {
+            return invalid("at least one socket is required".to_string());
+        }
+
+        for socket in &self.sockets {
+            for ns in &socket.namespaces {
+                if !self.namespaces.iter().any(|n| &n.name == ns) {
+                    return invalid(format!(
+                        "socket '{}' references unknown namespace '{}'",
+                        socket.path, ns
+                    ));
+                }
+            }
+        }
+
+        let routes = self
+            .routes
+            .iter()
+            .chain(self.namespaces.iter().flat_map(|n| n.routes.iter()));
+        for route in routes {
+            if Method::parse(&route.method).is_none() {
+                return invalid(format!(
+                    "route '{}' has unknown method '{}'",
+                    route.path, route.method
+                ));
+            }
+        }
+
+        for task in &self.tasks {
+            if task.command.is_empty() {
+                return invalid(format!("task '{}' has an empty command", task.name));
+            }
+            if task.interval_secs == Some(0) {
+                return invalid(format!("task '{}' has a zero interval", task.name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Register the routes visible on `socket` into `router`.
+    pub fn apply_routes(&self, socket: &SocketSpec, router: &mut Router) {
+        for route in &self.routes {
+            register_route(router, "", route);
+        }
+
+        for ns in &self.namespaces {
+            if socket.namespaces.is_empty() || socket.namespaces.contains(&ns.name) {
+                for route in &ns.routes {
+                    register_route(router, &ns.prefix, route);
+                }
+            }
+        }
+    }
+
+    /// Start the service: bind every socket, schedule tasks and deliver webhooks.
+    ///
+    /// Each socket also serves `GET /v1/health`, the task API (see
+    /// [`Router::task_routes`]) and the route index at
+    /// [`ROUTES_PATH`](crate::api_server::ROUTES_PATH).
+    pub fn start(self, config: TaskManagerConfig) -> Result<ServiceHandle> {
+        let task_manager = Arc::new(TaskManager::new(config));
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut servers = Vec::new();
+        let mut workers = Vec::new();
+
+        for socket in &self.sockets {
+            let server = ApiServer::new(ApiServerConfig {
+                socket_config: SocketServerConfig::with_path(&socket.path),
+                ..Default::default()
+            });
+
+            {
+                let mut router = server.router();
+                router
+                    .get("/v1/health", |_req| {
+                        Response::ok(serde_json::json!({"status": "ok"}))
+                    })
+                    .task_routes(Arc::clone(&task_manager))
+                    .route_index(crate::api_server::ROUTES_PATH);
+                self.apply_routes(socket, &mut router);
+            }
+
+            servers.push(server.spawn());
+        }
+
+        for task in self.tasks.clone() {
+            let tm = Arc::clone(&task_manager);
+            let stop = Arc::clone(&stop);
+            let service = self.name.clone();
+            workers.push(std::thread::spawn(move || {
+                schedule_task(&tm, &service, &task, &stop)
+            }));
+        }
+
+        for webhook in self.webhooks.clone() {
+            let filter = webhook
+                .events
+                .iter()
+                .fold(EventFilter::new(), |f, pattern| f.event_type(pattern));
+            let subscriber = task_manager.event_bus().subscribe(filter);
+            let stop = Arc::clone(&stop);
+            workers.push(std::thread::spawn(move || {
+                let client = ApiClient::with_timeout(&webhook.socket, Duration::from_secs(5));
+                while !stop.load(Ordering::SeqCst) {
+                    if let Ok(event) = subscriber.recv_timeout(POLL_INTERVAL) {
+                        let body = serde_json::to_value(&event).unwrap_or_default();
+                        if let Err(e) = client.post(&webhook.path, Some(body)) {
+                            tracing::warn!("webhook {} failed: {}", webhook.path, e);
+                        }
+                    }
+                }
+            }));
+        }
+
+        Ok(ServiceHandle {
+            manifest: self,
+            task_manager,
+            stop,
+            servers,
+            workers,
+        })
+    }
+}
+
+/// A running service started from a [`ServiceManifest`].
+pub struct ServiceHandle {
+    manifest: ServiceManifest,
+    task_manager: Arc<TaskManager>,
+    stop: Arc<AtomicBool>,
+    servers: Vec<JoinHandle<Result<()>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ServiceHandle {
+    /// Get the manifest the service was started from.
+    pub fn manifest(&self) -> &ServiceManifest {
+        &self.manifest
+    }
+
+    /// Get the task manager tracking scheduled tasks.
+    pub fn task_manager(&self) -> &Arc<TaskManager> {
+        &self.task_manager
+    }
+
+    /// Stop scheduling tasks and delivering webhooks.
+    ///
+    /// Already running tasks are left to finish; sockets keep serving.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// Wait for every socket server to exit.
+    pub fn join(self) -> Result<()> {
+        for server in self.servers {
+            server
+                .join()
+                .map_err(|_| IpcError::Other("server thread panicked".to_string()))??;
+        }
+        self.stop.store(true, Ordering::SeqCst);
+        for worker in self.workers {
+            let _ = worker.join();
+        }
+        Ok(())
+    }
+}
+
+fn register_route(router: &mut Router, prefix: &str, route: &RouteSpec) {
+    let path = join_path(prefix, &route.path);
+    let method = Method::parse(&route.method).unwrap_or(Method::GET);
+
+    match route.target.clone() {
+        RouteTarget::Static {
+            status,
+            body,
+            content_type,
+        } => {
+            router.route(method, &path, move |_req| match &body {
+                JsonValue::String(text) => Response::new(status).bytes(
+                    text.as_bytes().to_vec(),
+                    content_type.as_deref().unwrap_or("text/plain"),
+                ),
+                JsonValue::Null => Response::new(status),
+                other => Response::new(status).json(other.clone()),
+            });
+        }
+        RouteTarget::Dir { dir } => {
+            let pattern = join_path(&path, "{*file}");
+            router.get(&pattern, move |req| {
+                serve_file(&dir, req.path_param("file").unwrap_or(""))
+            });
+        }
+        RouteTarget::Proxy {
+            socket,
+            path: upstream,
+            timeout_ms,
+        } => {
+            let client = match timeout_ms {
+                Some(ms) => ApiClient::with_timeout(&socket, Duration::from_millis(ms)),
+                None => ApiClient::new(&socket),
+            };
+            router.route(method, &path, move |req: Request| {
+                let target = upstream.as_deref().unwrap_or(&req.path);
+                match client.forward(&req, target) {
+                    Ok(response) => response,
+                    Err(e) => Response::new(502).json(serde_json::json!({"error": e.to_string()})),
+                }
+            });
+        }
+    }
+}
+
+fn join_path(prefix: &str, path: &str) -> String {
+    let joined = format!(
+        "{}/{}",
+        prefix.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    );
+    if joined.starts_with('/') {
+        joined
+    } else {
+        format!("/{}", joined)
+    }
+}
+
+fn serve_file(dir: &Path, file: &str) -> Response {
+    let relative = Path::new(file);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Response::forbidden("Invalid path");
+    }
+
+    let mut path = dir.join(relative);
+    if path.is_dir() {
+        path.push("index.html");
+    }
+
+    match std::fs::read(&path) {
+        Ok(data) => Response::new(200).bytes(data, content_type_for(&path)),
+        Err(_) => Response::not_found(),
+    }
+}
+
+fn content_type_for(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("html") | Some("htm") => "text/html",
+        Some("css") => "text/css",
+        Some("js") => "application/javascript",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("txt") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+fn schedule_task(tm: &TaskManager, service: &str, spec: &ScheduledTaskSpec, stop: &AtomicBool) {
+    loop {
+        let mut builder = TaskBuilder::new(&spec.name, &spec.task_type).label("service", service);
+        for (key, value) in &spec.labels {
+            builder = builder.label(key, value);
+        }
+        let handle = tm.create(builder);
+        run_task(&handle, &spec.command);
+
+        let Some(interval) = spec.interval_secs.map(Duration::from_secs) else {
+            return;
+        };
+        let next = Instant::now() + interval;
+        while Instant::now() < next {
+            if stop.load(Ordering::SeqCst) {
+                return;
+            }
+            std::thread::sleep(POLL_INTERVAL.min(next - Instant::now()));
+        }
+        if stop.load(Ordering::SeqCst) {
+            return;
+        }
+    }
+}
+
+fn run_task(handle: &TaskHandle, command: &[String]) {
+    handle.start();
+
+    let child = ProcessTree::spawn(
+        Command::new(&command[0])
+            .args(&command[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+        true,
+    );
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            handle.fail(&format!("failed to start {}: {}", command[0], e));
+            return;
+        }
+    };
+
+    let readers = [
+        child
+            .stdout
+            .take()
+            .map(|out| forward_lines(handle, out, TaskHandle::stdout)),
+        child
+            .stderr
+            .take()
+            .map(|err| forward_lines(handle, err, TaskHandle::stderr)),
+    ];
+
+    // Cancelling the task (e.g. DELETE /v1/tasks/{id}) kills the process tree
+    let status = wait_or_cancel(&mut child, Some(&handle.cancel_token()));
+    for reader in readers.into_iter().flatten() {
+        let _ = reader.join();
+    }
+    if handle.is_cancelled() {
+        return;
+    }
+
+    match status {
+        Ok(status) if status.success() => {
+            handle.complete(serde_json::json!({"exit_code": status.code()}))
+        }
+        Ok(status) => handle.fail(&format!("exited with {}", status)),
+        Err(e) => handle.fail(&e.to_string()),
+    }
+}
+
+fn forward_lines<R: Read + Send + 'static>(
+    handle: &TaskHandle,
+    pipe: R,
+    emit: fn(&TaskHandle, &str),
+) -> JoinHandle<()> {
+    let handle = handle.clone();
+    std::thread::spawn(move || {
+        for line in BufReader::new(pipe).lines().map_while(|l| l.ok()) {
+            emit(&handle, &line);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::ResponseBody;
+
+    const MANIFEST: &str = r#"{
+        "name": "test",
+        "sockets": [
+            {"path": "/tmp/a.sock"},
+            {"path": "/tmp/b.sock", "namespaces": ["admin"]}
+        ],
+        "routes": [
+            {"path": "/v1/version", "kind": "static", "body": {"version": "1.0"}}
+        ],
+        "namespaces": [
+            {"name": "admin", "prefix": "/admin", "routes": [
+                {"path": "/ping", "method": "POST", "kind": "static", "status": 202, "body": "pong"}
+            ]},
+            {"name": "ui", "prefix": "/ui", "routes": [
+                {"path": "/", "kind": "dir", "dir": "/nonexistent"}
+            ]}
+        ],
+        "tasks": [{"name": "hello", "command": ["echo", "hi"]}],
+        "webhooks": [{"socket": "/tmp/hook.sock", "path": "/hook", "events": ["task.*"]}]
+    }"#;
+
+    #[test]
+    fn test_manifest_parse_and_routes() {
+        let manifest = ServiceManifest::from_json_str(MANIFEST).unwrap();
+        assert_eq!(manifest.sockets.len(), 2);
+        assert_eq!(manifest.tasks[0].task_type, "scheduled");
+        assert_eq!(manifest.namespaces[0].routes[0].method, "POST");
+
+        let mut router = Router::new();
+        manifest.apply_routes(&manifest.sockets[1], &mut router);
+
+        let resp = router.handle(Request::new(Method::GET, "/v1/version"));
+        assert_eq!(resp.status, 200);
+        assert!(matches!(resp.body, ResponseBody::Json(ref v) if v["version"] == "1.0"));
+
+        let resp = router.handle(Request::new(Method::POST, "/admin/ping"));
+        assert_eq!(resp.status, 202);
+        assert!(matches!(resp.body, ResponseBody::Bytes(ref b) if b == b"pong"));
+
+        // "ui" is not mounted on the second socket
+        let resp = router.handle(Request::new(Method::GET, "/ui/index.html"));
+        assert_eq!(resp.status, 404);
+    }
+
+    #[test]
+    fn test_manifest_validation() {
+        assert!(ServiceManifest::from_json_str("{}").is_err());
+        assert!(ServiceManifest::from_json_str(
+            r#"{"sockets": [{"path": "/tmp/a.sock", "namespaces": ["missing"]}]}"#
+        )
+        .is_err());
+        assert!(ServiceManifest::from_json_str(
+            r#"{"sockets": [{"path": "/tmp/a.sock"}], "tasks": [{"name": "t", "command": []}]}"#
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "manifest-toml")]
+    #[test]
+    fn test_manifest_toml() {
+        let manifest = ServiceManifest::from_toml_str(
+            r#"
+            name = "my-daemon"
+
+            [[sockets]]
+            path = "/tmp/my-daemon.sock"
+
+            [[routes]]
+            path = "/v1/version"
+            kind = "proxy"
+            socket = "/tmp/upstream.sock"
+
+            [[tasks]]
+            name = "cleanup"
+            command = ["true"]
+            interval_secs = 3600
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.name, "my-daemon");
+        assert!(matches!(
+            manifest.routes[0].target,
+            RouteTarget::Proxy { ref socket, .. } if socket == "/tmp/upstream.sock"
+        ));
+        assert_eq!(manifest.tasks[0].interval_secs, Some(3600));
+    }
+
+    #[test]
+    fn test_dir_route() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "<h1>hi</h1>").unwrap();
+
+        let mut router = Router::new();
+        register_route(
+            &mut router,
+            "/ui",
+            &RouteSpec {
+                path: "/".to_string(),
+                method: default_method(),
+                target: RouteTarget::Dir {
+                    dir: dir.path().to_path_buf(),
+                },
+            },
+        );
+
+        let resp = router.handle(Request::new(Method::GET, "/ui/"));
+        assert_eq!(resp.status, 200);
+        assert_eq!(
+            resp.headers.get("Content-Type").map(String::as_str),
+            Some("text/html")
+        );
+
+        let resp = router.handle(Request::new(Method::GET, "/ui/../secret"));
+        assert_eq!(resp.status, 403);
+    }
+
+    #[test]
+    fn test_proxy_route_relays_upstream_response() {
+        use crate::api_server::{ApiServer, ApiServerConfig};
+        use crate::socket_server::SocketServerConfig;
+
+        let upstream = format!("test_manifest_proxy_{}", std::process::id());
+        let server = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&upstream),
+            ..Default::default()
+        });
+        server.router().get("/v1/items/{id}", |req| {
+            Response::not_found()
+                .text(&format!(
+                    "no item {} (page {}, auth {})",
+                    req.path_param("id").unwrap_or_default(),
+                    req.query_param("page").unwrap_or_default(),
+                    req.header("authorization").unwrap_or_default(),
+                ))
+                .header("X-Upstream", "yes")
+        });
+        let _server = server.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let mut router = Router::new();
+        register_route(
+            &mut router,
+            "",
+            &RouteSpec {
+                path: "/v1/items/{id}".to_string(),
+                method: default_method(),
+                target: RouteTarget::Proxy {
+                    socket: upstream,
+                    path: None,
+                    timeout_ms: Some(1000),
+                },
+            },
+        );
+
+        let mut req = Request::new(Method::GET, "/v1/items/7");
+        req.query.insert("page".to_string(), "2 of 3".to_string());
+        req.headers
+            .insert("authorization".to_string(), "Bearer s3cret".to_string());
+        let resp = router.handle(req);
+        assert_eq!(resp.status, 404);
+        assert_eq!(
+            resp.headers.get("X-Upstream").map(String::as_str),
+            Some("yes")
+        );
+        assert_eq!(
+            resp.headers.get("Content-Type").map(String::as_str),
+            Some("text/plain")
+        );
+        assert!(matches!(
+            resp.body,
+            ResponseBody::Bytes(ref b) if b == b"no item 7 (page 2 of 3, auth Bearer s3cret)"
+        ));
+    }
+}
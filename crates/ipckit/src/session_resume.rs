@@ -0,0 +1,542 @@
+//! Session Resume - Client-side state resynchronization after reconnect
+//!
+//! GUIs talking to a long-running daemon lose their connection whenever the
+//! machine sleeps or the daemon restarts. Getting back to a consistent view
+//! means re-subscribing every event filter, replaying the events that were
+//! published while disconnected, and re-fetching any resources the UI is
+//! displaying. [`SessionResumer`] records that state while the session is
+//! healthy and performs the whole resync in one call after a reconnect.
+//!
+//! The transport is abstracted behind [`ResumeSource`], so the same resumer
+//! works for socket clients, API clients or an in-process [`EventBus`].
+//! [`SocketClient`] implements it against a server with an
+//! [`EventBridge`](crate::event_bridge::EventBridge), and [`ApiClient`]
+//! against [`Router::event_routes`](crate::api_server::Router::event_routes).
+//!
+//! Event IDs restart when the daemon restarts without a journal, so sources
+//! report the epoch the IDs belong to. Call [`SessionResumer::resume`] once
+//! after the first connect as well, so the resumer knows the epoch; when it
+//! has changed by a later resume, the resumer replays everything retained
+//! instead of comparing IDs across epochs.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ipckit::{EventFilter, SessionResumer};
+//!
+//! let mut resumer = SessionResumer::new();
+//! resumer.track_filter(EventFilter::new().event_type("task.*"));
+//! resumer.watch("/v1/tasks");
+//! resumer.on_resumed(|report| {
+//!     println!("missed {} events", report.missed_events.len());
+//! });
+//!
+//! // While connected, feed every received event to the resumer
+//! resumer.observe(&event);
+//!
+//! // After reconnecting
+//! let report = resumer.resume(&mut source)?;
+//! ```
+//!
+//! [`EventBus`]: crate::event_stream::EventBus
+
+use crate::api_server::ApiClient;
+use crate::error::{IpcError, Result};
+use crate::event_bridge::{EVENTS_EPOCH_METHOD, EVENTS_REPLAY_METHOD, EVENTS_SUBSCRIBE_METHOD};
+use crate::event_stream::{Event, EventFilter, EventId};
+use crate::socket_server::{MessageType, SocketClient};
+use std::collections::HashMap;
+
+/// Transport used by [`SessionResumer`] to resynchronize with a daemon.
+pub trait ResumeSource {
+    /// Re-establish a subscription for the given filter.
+    fn resubscribe(&mut self, filter: &EventFilter) -> Result<()>;
+
+    /// Fetch events matching `filter` that were published after `after`.
+    ///
+    /// `None` means no event has been seen yet; sources may return their
+    /// whole retained history in that case.
+    fn missed_events(&mut self, filter: &EventFilter, after: Option<EventId>)
+        -> Result<Vec<Event>>;
+
+    /// Fetch the current state of a watched resource.
+    fn fetch_resource(&mut self, resource: &str) -> Result<serde_json::Value>;
+
+    /// Get the epoch the source's event IDs belong to, if it has one.
+    ///
+    /// IDs from different epochs are not comparable. The default reports
+    /// none, which makes the resumer trust IDs across reconnects.
+    fn epoch(&mut self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+}
+
+/// Resumes over an event bridge.
+///
+/// The bridge keeps one subscription per connection, so re-subscribing
+/// several filters leaves the last one active; track a single filter per
+/// socket connection. Events pushed while a replay request is in flight are
+/// returned with the missed events, and later ones are left for
+/// [`SocketClient::recv`].
+impl ResumeSource for SocketClient {
+    fn resubscribe(&mut self, filter: &EventFilter) -> Result<()> {
+        self.request(
+            EVENTS_SUBSCRIBE_METHOD,
+            serde_json::json!({
+                "event_types": filter.event_types,
+                "resource_ids": filter.resource_ids,
+            }),
+        )
+        .map(|_| ())
+    }
+
+    fn missed_events(
+        &mut self,
+        filter: &EventFilter,
+        after: Option<EventId>,
+    ) -> Result<Vec<Event>> {
+        let mut pushed = Vec::new();
+        let reply = self.request_with(
+            EVENTS_REPLAY_METHOD,
+            serde_json::json!({
+                "event_types": filter.event_types,
+                "resource_ids": filter.resource_ids,
+                "after": after,
+            }),
+            |msg| {
+                if msg.msg_type == MessageType::Text {
+                    if let Ok(event) = serde_json::from_value::<Event>(msg.payload) {
+                        pushed.push(event);
+                    }
+                }
+            },
+        )?;
+        let mut events = events_of(reply)?;
+        events.extend(pushed);
+        Ok(events)
+    }
+
+    fn fetch_resource(&mut self, resource: &str) -> Result<serde_json::Value> {
+        self.request(resource, serde_json::Value::Null)
+    }
+
+    fn epoch(&mut self) -> Result<Option<u64>> {
+        let reply = self.request(EVENTS_EPOCH_METHOD, serde_json::Value::Null)?;
+        epoch_of(&reply).map(Some)
+    }
+}
+
+/// Resumes over the `/v1/events` routes.
+///
+/// HTTP has no subscriptions to restore, so re-subscribing does nothing;
+/// watched resources are fetched with `GET`.
+impl ResumeSource for ApiClient {
+    fn resubscribe(&mut self, _filter: &EventFilter) -> Result<()> {
+        Ok(())
+    }
+
+    fn missed_events(
+        &mut self,
+        filter: &EventFilter,
+        after: Option<EventId>,
+    ) -> Result<Vec<Event>> {
+        let mut query = Vec::new();
+        if let Some(after) = after {
+            query.push(format!("after={}", after));
+        }
+        if let Some(types) = &filter.event_types {
+            query.push(format!("type={}", types.join(",")));
+        }
+        if let Some(ids) = &filter.resource_ids {
+            query.push(format!("resource={}", ids.join(",")));
+        }
+        let path = match query.is_empty() {
+            true => "/v1/events".to_string(),
+            false => format!("/v1/events?{}", query.join("&")),
+        };
+        events_of(api_result(self.get(&path)?)?)
+    }
+
+    fn fetch_resource(&mut self, resource: &str) -> Result<serde_json::Value> {
+        api_result(self.get(resource)?)
+    }
+
+    fn epoch(&mut self) -> Result<Option<u64>> {
+        epoch_of(&api_result(self.get("/v1/events/epoch")?)?).map(Some)
+    }
+}
+
+/// Turn an API error body into an error.
+fn api_result(body: serde_json::Value) -> Result<serde_json::Value> {
+    match body.get("error").and_then(|e| e.as_str()) {
+        Some(error) => Err(IpcError::Other(error.to_string())),
+        None => Ok(body),
+    }
+}
+
+/// Get the events of a replay reply.
+fn events_of(mut reply: serde_json::Value) -> Result<Vec<Event>> {
+    serde_json::from_value(reply["events"].take())
+        .map_err(|e| IpcError::deserialization(e.to_string()))
+}
+
+/// Get the epoch of a replay or epoch reply.
+fn epoch_of(reply: &serde_json::Value) -> Result<u64> {
+    reply["epoch"]
+        .as_u64()
+        .ok_or_else(|| IpcError::deserialization("missing event epoch"))
+}
+
+/// Outcome of a [`SessionResumer::resume`] call.
+#[derive(Debug, Default)]
+pub struct ResumeReport {
+    /// Number of filters that were re-subscribed.
+    pub resubscribed: usize,
+    /// Events missed while disconnected, ordered by event ID without duplicates.
+    pub missed_events: Vec<Event>,
+    /// Current state of every watched resource that could be fetched.
+    pub resources: HashMap<String, serde_json::Value>,
+    /// Steps that failed, as `(what, error message)` pairs.
+    pub failures: Vec<(String, String)>,
+    /// Whether the source's event IDs restarted since the last resume, in
+    /// which case `missed_events` holds everything the source retains.
+    pub reset: bool,
+}
+
+impl ResumeReport {
+    /// Check whether every resync step succeeded.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Callback invoked once per successful resume.
+pub type ResumedCallback = Box<dyn Fn(&ResumeReport) + Send + Sync>;
+
+/// Tracks subscription state and replays it after a reconnect.
+#[derive(Default)]
+pub struct SessionResumer {
+    filters: Vec<EventFilter>,
+    watched: Vec<String>,
+    last_event_id: Option<EventId>,
+    epoch: Option<u64>,
+    on_resumed: Option<ResumedCallback>,
+}
+
+impl SessionResumer {
+    /// Create an empty resumer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember an event filter to re-subscribe after reconnecting.
+    pub fn track_filter(&mut self, filter: EventFilter) -> &mut Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Remember a resource to re-fetch after reconnecting.
+    pub fn watch(&mut self, resource: &str) -> &mut Self {
+        if !self.watched.iter().any(|r| r == resource) {
+            self.watched.push(resource.to_string());
+        }
+        self
+    }
+
+    /// Stop re-fetching a resource after reconnecting.
+    pub fn unwatch(&mut self, resource: &str) -> &mut Self {
+        self.watched.retain(|r| r != resource);
+        self
+    }
+
+    /// Set the callback invoked with the consolidated result of each resume.
+    pub fn on_resumed<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&ResumeReport) + Send + Sync + 'static,
+    {
+        self.on_resumed = Some(Box::new(callback));
+        self
+    }
+
+    /// Record an event received during normal operation.
+    pub fn observe(&mut self, event: &Event) {
+        if self.last_event_id.is_none_or(|last| event.id > last) {
+            self.last_event_id = Some(event.id);
+        }
+    }
+
+    /// Get the ID of the newest event seen so far.
+    pub fn last_event_id(&self) -> Option<EventId> {
+        self.last_event_id
+    }
+
+    /// Get the epoch of the source's event IDs as of the last resume.
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch
+    }
+
+    /// Get the tracked filters.
+    pub fn filters(&self) -> &[EventFilter] {
+        &self.filters
+    }
+
+    /// Get the watched resources.
+    pub fn watched(&self) -> &[String] {
+        &self.watched
+    }
+
+    /// Resynchronize with `source` after a reconnect.
+    ///
+    /// Re-subscribes every tracked filter, collects missed events since the
+    /// last observed event, and re-fetches watched resources. Individual step
+    /// failures are collected into the report rather than aborting the
+    /// resume; the `resumed` callback is invoked exactly once at the end.
+    ///
+    /// If the source's epoch differs from the one seen by the previous
+    /// resume, the last observed event ID is discarded and
+    /// [`ResumeReport::reset`] is set.
+    pub fn resume<S: ResumeSource + ?Sized>(&mut self, source: &mut S) -> Result<ResumeReport> {
+        let mut report = ResumeReport::default();
+
+        match source.epoch() {
+            Ok(Some(epoch)) => {
+                report.reset = self.epoch.is_some_and(|old| old != epoch);
+                self.epoch = Some(epoch);
+            }
+            Ok(None) => {}
+            Err(e) => report.failures.push(("epoch".to_string(), e.to_string())),
+        }
+        if report.reset {
+            self.last_event_id = None;
+        }
+        let after = self.last_event_id;
+
+        for filter in &self.filters {
+            match source.resubscribe(filter) {
+                Ok(()) => report.resubscribed += 1,
+                Err(e) => report.failures.push((
+                    format!("resubscribe {:?}", filter.event_types),
+                    e.to_string(),
+                )),
+            }
+
+            match source.missed_events(filter, after) {
+                Ok(events) => report.missed_events.extend(
+                    events
+                        .into_iter()
+                        .filter(|e| after.is_none_or(|last| e.id > last)),
+                ),
+                Err(e) => report
+                    .failures
+                    .push((format!("replay {:?}", filter.event_types), e.to_string())),
+            }
+        }
+
+        // Overlapping filters may return the same event more than once
+        report.missed_events.sort_by_key(|e| e.id);
+        report.missed_events.dedup_by_key(|e| e.id);
+        if let Some(newest) = report.missed_events.last() {
+            self.last_event_id = Some(newest.id);
+        }
+
+        for resource in &self.watched {
+            match source.fetch_resource(resource) {
+                Ok(value) => {
+                    report.resources.insert(resource.clone(), value);
+                }
+                Err(e) => report
+                    .failures
+                    .push((format!("fetch {}", resource), e.to_string())),
+            }
+        }
+
+        if let Some(ref callback) = self.on_resumed {
+            callback(&report);
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::IpcError;
+    use crate::event_stream::{EventBus, EventSubscriber};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct BusSource {
+        bus: EventBus,
+        subscribers: Vec<EventSubscriber>,
+        epoch: Option<u64>,
+    }
+
+    impl ResumeSource for BusSource {
+        fn resubscribe(&mut self, filter: &EventFilter) -> Result<()> {
+            self.subscribers.push(self.bus.subscribe(filter.clone()));
+            Ok(())
+        }
+
+        fn missed_events(
+            &mut self,
+            filter: &EventFilter,
+            after: Option<EventId>,
+        ) -> Result<Vec<Event>> {
+            Ok(self.bus.history_after(after, filter))
+        }
+
+        fn fetch_resource(&mut self, resource: &str) -> Result<serde_json::Value> {
+            match resource {
+                "/v1/tasks" => Ok(serde_json::json!([{"id": "task-1"}])),
+                _ => Err(IpcError::NotFound(resource.to_string())),
+            }
+        }
+
+        fn epoch(&mut self) -> Result<Option<u64>> {
+            Ok(self.epoch)
+        }
+    }
+
+    #[test]
+    fn test_resume_replays_missed_events() {
+        let bus = EventBus::default();
+        let seen = Event::new("task.started", serde_json::json!({}));
+        bus.publish(seen.clone());
+
+        let mut resumer = SessionResumer::new();
+        resumer
+            .track_filter(EventFilter::new().event_type("task.*"))
+            .track_filter(EventFilter::new().resource("task-1"))
+            .watch("/v1/tasks")
+            .watch("/v1/missing");
+        resumer.observe(&seen);
+
+        // Published while "disconnected"
+        bus.publish(Event::with_resource(
+            "task.progress",
+            "task-1",
+            serde_json::json!({}),
+        ));
+        bus.publish(Event::new("log.info", serde_json::json!({})));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = Arc::clone(&calls);
+        resumer.on_resumed(move |_| {
+            calls_clone.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut source = BusSource {
+            bus: bus.clone(),
+            subscribers: Vec::new(),
+            epoch: None,
+        };
+        let report = resumer.resume(&mut source).unwrap();
+
+        assert_eq!(report.resubscribed, 2);
+        assert_eq!(source.subscribers.len(), 2);
+        // Matched by both filters but reported once
+        assert_eq!(report.missed_events.len(), 1);
+        assert_eq!(report.missed_events[0].event_type, "task.progress");
+        assert_eq!(report.resources["/v1/tasks"][0]["id"], "task-1");
+        assert_eq!(report.failures.len(), 1);
+        assert!(!report.is_complete());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(resumer.last_event_id(), Some(report.missed_events[0].id));
+
+        // A second resume with nothing new reports no missed events
+        let report = resumer.resume(&mut source).unwrap();
+        assert!(report.missed_events.is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_resume_after_epoch_change() {
+        let bus = EventBus::default();
+        let mut source = BusSource {
+            bus: bus.clone(),
+            subscribers: Vec::new(),
+            epoch: Some(1),
+        };
+        let mut resumer = SessionResumer::new();
+        resumer.track_filter(EventFilter::new().event_type("task.*"));
+
+        let first = Event::new("task.started", serde_json::json!({}));
+        bus.publish(first.clone());
+        let report = resumer.resume(&mut source).unwrap();
+        assert!(!report.reset);
+        assert_eq!(resumer.epoch(), Some(1));
+        resumer.observe(&first);
+
+        // The daemon restarted and handed out an older ID again
+        bus.clear_history();
+        let mut restarted = Event::new("task.started", serde_json::json!({}));
+        restarted.id = first.id - 1;
+        bus.publish(restarted.clone());
+        source.epoch = Some(2);
+
+        let report = resumer.resume(&mut source).unwrap();
+        assert!(report.reset);
+        assert_eq!(report.missed_events.len(), 1);
+        assert_eq!(report.missed_events[0].id, restarted.id);
+        assert_eq!(resumer.last_event_id(), Some(restarted.id));
+    }
+
+    #[test]
+    fn test_socket_and_api_sources() {
+        use crate::api_server::{ApiServer, ApiServerConfig};
+        use crate::event_bridge::{EventAcl, EventBridge};
+        use crate::socket_server::{SocketServer, SocketServerConfig};
+        use std::time::Duration;
+
+        let bus = EventBus::default();
+        let socket_name = format!("test_resume_socket_{}", std::process::id());
+        let server = SocketServer::new(SocketServerConfig::with_path(&socket_name)).unwrap();
+        let acl = EventAcl::new().allow_anonymous(EventFilter::new().resource("task-1"));
+        let bridge = EventBridge::new(&bus, server.broadcaster(), acl);
+        let _server = server.spawn(bridge);
+
+        let api_name = format!("test_resume_api_{}", std::process::id());
+        let api = ApiServer::new(ApiServerConfig {
+            socket_config: SocketServerConfig::with_path(&api_name),
+            ..Default::default()
+        });
+        api.router().event_routes(bus.clone());
+        let _api = api.spawn();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let seen = Event::with_resource("task.started", "task-1", serde_json::json!({}));
+        bus.publish(seen.clone());
+        bus.publish(Event::with_resource(
+            "task.progress",
+            "task-1",
+            serde_json::json!({}),
+        ));
+        bus.publish(Event::with_resource(
+            "task.progress",
+            "task-2",
+            serde_json::json!({}),
+        ));
+
+        let mut socket = SocketClient::connect(&socket_name).unwrap();
+        let mut api = ApiClient::new(&api_name);
+        // The ACL hides task-2 from the socket; the API serves every event
+        let sources: [(&mut dyn ResumeSource, usize); 2] = [(&mut socket, 1), (&mut api, 2)];
+        for (source, missed) in sources {
+            let mut resumer = SessionResumer::new();
+            resumer.track_filter(EventFilter::new().event_type("task.*"));
+            resumer.observe(&seen);
+
+            let report = resumer.resume(source).unwrap();
+            assert!(report.is_complete(), "{:?}", report.failures);
+            assert!(!report.reset);
+            assert_eq!(resumer.epoch(), Some(bus.epoch()));
+            assert_eq!(report.missed_events.len(), missed);
+            assert_eq!(report.missed_events[0].event_type, "task.progress");
+            assert_eq!(
+                report.missed_events[0].resource_id.as_deref(),
+                Some("task-1")
+            );
+        }
+    }
+}
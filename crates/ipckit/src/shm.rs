@@ -1,9 +1,20 @@
 //! Shared Memory implementation for IPC
 //!
 //! Provides memory-mapped shared memory regions for fast data exchange between processes.
+//!
+//! [`ShmArena`] carves many short-lived blobs out of a single segment, so
+//! producers don't create and unlink one OS segment per payload.
+//!
+//! [`ShmMapped`] maps a `#[repr(C)]` struct into a segment behind a layout
+//! header, so both sides agree on its schema before reading it.
+//!
+//! [`GrowableShm`] chains segments behind one logical buffer, so producers
+//! whose payload sizes vary widely don't have to allocate for the worst case.
 
 use crate::error::{IpcError, Result};
+use serde::{Deserialize, Serialize};
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 /// Shared memory region for inter-process communication
 pub struct SharedMemory {
@@ -153,6 +164,1004 @@ impl SharedMemory {
         }
         Ok(())
     }
+
+    /// Take a point-in-time copy of this region into a new segment.
+    ///
+    /// The whole region is copied in a single pass into a freshly created
+    /// segment named `name`, which other processes can [`open`](Self::open)
+    /// while the producer keeps mutating the live segment. The copy is only
+    /// as consistent as the producer's own synchronization: callers that need
+    /// a tear-free frame should snapshot between writes (e.g. under the same
+    /// lock or sequence counter the producer uses).
+    pub fn snapshot(&self, name: &str) -> Result<SharedMemorySnapshot> {
+        let mut copy = Self::create(name, self.size)?;
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.ptr.as_ptr(), copy.as_mut_ptr(), self.size);
+        }
+        Ok(SharedMemorySnapshot {
+            inner: copy,
+            taken_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+/// An immutable point-in-time copy of a [`SharedMemory`] region.
+///
+/// Created by [`SharedMemory::snapshot`]. The snapshot owns its segment, which
+/// is removed when the snapshot is dropped; only read access is exposed.
+pub struct SharedMemorySnapshot {
+    inner: SharedMemory,
+    taken_at: std::time::SystemTime,
+}
+
+impl SharedMemorySnapshot {
+    /// Get the name of the snapshot segment
+    pub fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    /// Get the size of the snapshot
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    /// Get the time at which the snapshot was taken
+    pub fn taken_at(&self) -> std::time::SystemTime {
+        self.taken_at
+    }
+
+    /// Read data from the snapshot at the given offset
+    pub fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>> {
+        self.inner.read(offset, len)
+    }
+
+    /// Read data from the snapshot into an existing buffer
+    pub fn read_into(&self, offset: usize, buf: &mut [u8]) -> Result<()> {
+        self.inner.read_into(offset, buf)
+    }
+
+    /// Get a slice view of the snapshot
+    ///
+    /// # Safety
+    /// The caller must ensure no other process has opened the snapshot
+    /// segment by name and is writing to it.
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        self.inner.as_slice()
+    }
+
+    /// Consume the snapshot and return the underlying segment.
+    pub fn into_inner(self) -> SharedMemory {
+        self.inner
+    }
+}
+
+/// Plain-old-data types that can be mapped into shared memory with
+/// [`SharedMemory::map_struct`].
+///
+/// `SCHEMA` and `VERSION` are hashed into the segment's layout header, so a
+/// process built against a different definition of the struct is refused
+/// instead of reading garbage. Derive it with `ipckit_macros::ShmStruct`,
+/// which builds `SCHEMA` from the field names and types.
+///
+/// # Safety
+///
+/// The type must be `#[repr(C)]` (or `#[repr(transparent)]`), contain no
+/// pointers or references, and be valid for any bit pattern, including all
+/// zeroes: every field must itself be `ShmStruct`.
+pub unsafe trait ShmStruct: Copy + Send + Sync + 'static {
+    /// Layout version; bump it when the meaning of a field changes
+    const VERSION: u32 = 1;
+    /// Description of the layout (conventionally `Name{field:type,...}`)
+    const SCHEMA: &'static str;
+
+    /// Hash of the schema, size and alignment stored in the layout header.
+    fn schema_hash() -> u64 {
+        // FNV-1a, stable across builds and platforms
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let size = std::mem::size_of::<Self>() as u64;
+        let align = std::mem::align_of::<Self>() as u64;
+        for byte in Self::SCHEMA
+            .bytes()
+            .chain(size.to_le_bytes())
+            .chain(align.to_le_bytes())
+        {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+}
+
+macro_rules! impl_shm_struct {
+    ($($ty:ty),*) => {
+        $(unsafe impl ShmStruct for $ty {
+            const SCHEMA: &'static str = stringify!($ty);
+        })*
+    };
+}
+
+impl_shm_struct!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+unsafe impl<T: ShmStruct, const N: usize> ShmStruct for [T; N] {
+    const VERSION: u32 = T::VERSION;
+    const SCHEMA: &'static str = T::SCHEMA;
+}
+
+/// Magic number identifying a struct segment ("IPKS")
+const STRUCT_MAGIC: u32 = 0x534B_5049;
+/// Size of the layout header preceding the struct
+const STRUCT_HEADER: usize = 64;
+
+// Struct header field offsets
+const OFF_STRUCT_MAGIC: usize = 0;
+const OFF_STRUCT_VERSION: usize = 4;
+const OFF_STRUCT_HASH: usize = 8;
+const OFF_STRUCT_SIZE: usize = 16;
+
+/// A [`ShmStruct`] living in shared memory behind a layout header.
+///
+/// Created by [`SharedMemory::map_struct`]. Reads and writes are plain
+/// memory accesses: a reader can observe a half-written value unless the
+/// struct carries its own synchronization (atomics, a sequence counter).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use ipckit::{SharedMemory, ShmMapped, ShmStruct};
+///
+/// #[repr(C)]
+/// #[derive(Clone, Copy)]
+/// struct Viewport {
+///     width: u32,
+///     height: u32,
+///     zoom: f32,
+/// }
+///
+/// // Normally `#[derive(ipckit_macros::ShmStruct)]`
+/// unsafe impl ShmStruct for Viewport {
+///     const SCHEMA: &'static str = "Viewport{width:u32,height:u32,zoom:f32}";
+/// }
+///
+/// let writer = ShmMapped::<Viewport>::create("viewport")?;
+/// writer.set(Viewport { width: 1920, height: 1080, zoom: 1.0 });
+///
+/// // In another process; fails if its `Viewport` has a different layout
+/// let reader = ShmMapped::<Viewport>::open("viewport")?;
+/// assert_eq!(reader.get().width, 1920);
+/// # Ok::<(), ipckit::IpcError>(())
+/// ```
+pub struct ShmMapped<T: ShmStruct> {
+    shm: SharedMemory,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl SharedMemory {
+    /// Map a [`ShmStruct`] onto this region.
+    ///
+    /// The creator of the region writes the layout header (the struct starts
+    /// zeroed); everyone else validates it and gets [`IpcError::Incompatible`]
+    /// if the segment was written with a different schema or version.
+    pub fn map_struct<T: ShmStruct>(self) -> Result<ShmMapped<T>> {
+        let needed = STRUCT_HEADER + std::mem::size_of::<T>();
+        if self.size < needed {
+            return Err(IpcError::BufferTooSmall {
+                needed,
+                got: self.size,
+            });
+        }
+        if std::mem::align_of::<T>() > STRUCT_HEADER {
+            return Err(IpcError::InvalidState(format!(
+                "alignment {} exceeds the {}-byte layout header",
+                std::mem::align_of::<T>(),
+                STRUCT_HEADER
+            )));
+        }
+
+        let mapped = ShmMapped {
+            shm: self,
+            _marker: std::marker::PhantomData,
+        };
+        unsafe {
+            let magic = mapped.header_u32(OFF_STRUCT_MAGIC);
+            if mapped.shm.is_owner && magic == 0 {
+                mapped.set_header_u32(OFF_STRUCT_VERSION, T::VERSION);
+                mapped.set_header_u64(OFF_STRUCT_HASH, T::schema_hash());
+                mapped.set_header_u64(OFF_STRUCT_SIZE, std::mem::size_of::<T>() as u64);
+                // Publish the magic last so openers never see a half-written header
+                mapped.set_header_u32(OFF_STRUCT_MAGIC, STRUCT_MAGIC);
+            } else if magic != STRUCT_MAGIC {
+                return Err(IpcError::InvalidState(format!(
+                    "'{}' is not an ipckit struct segment",
+                    mapped.shm.name
+                )));
+            } else {
+                let version = mapped.header_u32(OFF_STRUCT_VERSION);
+                let hash = mapped.header_u64(OFF_STRUCT_HASH);
+                if version != T::VERSION || hash != T::schema_hash() {
+                    return Err(IpcError::Incompatible(format!(
+                        "'{}' holds layout version {} (schema {:016x}), expected version {} (schema {:016x}) for {}",
+                        mapped.shm.name,
+                        version,
+                        hash,
+                        T::VERSION,
+                        T::schema_hash(),
+                        T::SCHEMA
+                    )));
+                }
+            }
+        }
+        Ok(mapped)
+    }
+}
+
+impl<T: ShmStruct> ShmMapped<T> {
+    /// Create a region named `name` holding a zeroed `T`.
+    pub fn create(name: &str) -> Result<Self> {
+        SharedMemory::create(name, STRUCT_HEADER + std::mem::size_of::<T>())?.map_struct()
+    }
+
+    /// Open a region created by another process, validating its layout.
+    pub fn open(name: &str) -> Result<Self> {
+        SharedMemory::open(name)?.map_struct()
+    }
+
+    /// Copy the current value out of shared memory.
+    pub fn get(&self) -> T {
+        unsafe { std::ptr::read_volatile(self.as_ptr()) }
+    }
+
+    /// Overwrite the value in shared memory.
+    pub fn set(&self, value: T) {
+        unsafe { std::ptr::write_volatile(self.as_ptr(), value) }
+    }
+
+    /// Get a pointer to the mapped struct.
+    pub fn as_ptr(&self) -> *mut T {
+        unsafe { self.shm.as_ptr().add(STRUCT_HEADER) as *mut T }
+    }
+
+    /// Get a reference to the mapped struct.
+    ///
+    /// # Safety
+    /// The caller must ensure no other process is writing to the struct.
+    pub unsafe fn as_ref(&self) -> &T {
+        &*self.as_ptr()
+    }
+
+    /// Get a mutable reference to the mapped struct.
+    ///
+    /// # Safety
+    /// The caller must ensure exclusive access to the struct.
+    pub unsafe fn as_mut(&mut self) -> &mut T {
+        &mut *self.as_ptr()
+    }
+
+    /// Get the underlying region.
+    pub fn shm(&self) -> &SharedMemory {
+        &self.shm
+    }
+
+    /// Consume the mapping and return the underlying region.
+    pub fn into_inner(self) -> SharedMemory {
+        self.shm
+    }
+
+    unsafe fn header_u32(&self, offset: usize) -> u32 {
+        std::ptr::read_volatile(self.shm.as_ptr().add(offset) as *const u32)
+    }
+
+    unsafe fn set_header_u32(&self, offset: usize, value: u32) {
+        std::ptr::write_volatile(self.shm.as_ptr().add(offset) as *mut u32, value)
+    }
+
+    unsafe fn header_u64(&self, offset: usize) -> u64 {
+        std::ptr::read_volatile(self.shm.as_ptr().add(offset) as *const u64)
+    }
+
+    unsafe fn set_header_u64(&self, offset: usize, value: u64) {
+        std::ptr::write_volatile(self.shm.as_ptr().add(offset) as *mut u64, value)
+    }
+}
+
+/// Magic number identifying an arena segment ("IPKA")
+const ARENA_MAGIC: u32 = 0x414B_5049;
+/// Arena layout version
+const ARENA_VERSION: u32 = 1;
+/// Size of the arena header at the start of the segment
+const ARENA_HEADER: usize = 64;
+/// Size of the header preceding every block
+const BLOCK_HEADER: usize = 16;
+/// Block alignment and allocation granularity
+const BLOCK_ALIGN: usize = 16;
+/// Smallest block: header plus room for the free-list link
+const MIN_BLOCK: usize = BLOCK_HEADER + BLOCK_ALIGN;
+/// Block state marker for allocated blocks
+const BLOCK_USED: u32 = 0x5553_4544;
+/// Block state marker for free blocks
+const BLOCK_FREE: u32 = 0;
+
+// Arena header field offsets
+const OFF_MAGIC: usize = 0;
+const OFF_VERSION: usize = 4;
+const OFF_LOCK: usize = 8;
+const OFF_GENERATION: usize = 12;
+const OFF_CAPACITY: usize = 16;
+const OFF_FREE_HEAD: usize = 24;
+const OFF_LIVE: usize = 32;
+const OFF_USED: usize = 40;
+
+/// Handle to a blob allocated in a [`ShmArena`]
+///
+/// Tickets are plain data and can be sent to other processes, which read
+/// the blob through their own [`ShmArena::open`] handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ShmTicket {
+    offset: u64,
+    len: u64,
+    generation: u32,
+}
+
+impl ShmTicket {
+    /// Get the offset of the blob within the arena segment
+    pub fn offset(&self) -> usize {
+        self.offset as usize
+    }
+
+    /// Get the requested length of the blob
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Check if the blob is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Allocator for many short-lived blobs inside one shared memory segment
+///
+/// The allocator state (an address-ordered free list with coalescing) lives
+/// in the segment itself and is guarded by a spinlock in the segment header,
+/// so any process that opened the arena can allocate and free. A process that
+/// crashes while holding the lock leaves the arena locked; the segment is
+/// still removed when its creator drops the arena.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use ipckit::ShmArena;
+///
+/// let arena = ShmArena::create("frames", 64 * 1024 * 1024).unwrap();
+/// let ticket = arena.store(b"frame data").unwrap();
+/// // send `ticket` to the consumer, which opens the arena and reads it:
+/// let consumer = ShmArena::open("frames").unwrap();
+/// assert_eq!(consumer.read(&ticket).unwrap(), b"frame data");
+/// consumer.free(ticket).unwrap();
+/// ```
+pub struct ShmArena {
+    shm: SharedMemory,
+}
+
+/// Releases the arena spinlock on drop
+struct ArenaGuard<'a> {
+    lock: &'a AtomicU32,
+}
+
+impl Drop for ArenaGuard<'_> {
+    fn drop(&mut self) {
+        self.lock.store(0, Ordering::Release);
+    }
+}
+
+impl ShmArena {
+    /// Create a new arena backed by a segment of `total_size` bytes
+    pub fn create(name: &str, total_size: usize) -> Result<Self> {
+        if total_size < ARENA_HEADER + MIN_BLOCK {
+            return Err(IpcError::BufferTooSmall {
+                needed: ARENA_HEADER + MIN_BLOCK,
+                got: total_size,
+            });
+        }
+
+        let arena = Self {
+            shm: SharedMemory::create(name, total_size)?,
+        };
+
+        let block_size = (total_size - ARENA_HEADER) / BLOCK_ALIGN * BLOCK_ALIGN;
+        unsafe {
+            arena.set_u32(OFF_VERSION, ARENA_VERSION);
+            arena.set_u32(OFF_GENERATION, 0);
+            arena.set_u64(OFF_CAPACITY, total_size as u64);
+            arena.set_u64(OFF_FREE_HEAD, ARENA_HEADER as u64);
+            arena.set_u64(OFF_LIVE, 0);
+            arena.set_u64(OFF_USED, 0);
+            arena.set_u64(ARENA_HEADER, block_size as u64);
+            arena.set_u32(ARENA_HEADER + 8, BLOCK_FREE);
+            arena.set_u64(ARENA_HEADER + BLOCK_HEADER, 0);
+            // Publish the magic last so openers never see a half-initialized arena
+            arena.set_u32(OFF_MAGIC, ARENA_MAGIC);
+        }
+
+        Ok(arena)
+    }
+
+    /// Open an existing arena created by another process
+    pub fn open(name: &str) -> Result<Self> {
+        let shm = SharedMemory::open(name)?;
+        let arena = Self { shm };
+        if arena.shm.size() < ARENA_HEADER
+            || unsafe { arena.u32_at(OFF_MAGIC) } != ARENA_MAGIC
+            || unsafe { arena.u32_at(OFF_VERSION) } != ARENA_VERSION
+        {
+            return Err(IpcError::InvalidState(format!(
+                "'{}' is not an ipckit arena segment",
+                name
+            )));
+        }
+        Ok(arena)
+    }
+
+    /// Get the name of the underlying segment
+    pub fn name(&self) -> &str {
+        self.shm.name()
+    }
+
+    /// Get the number of bytes usable for blocks (excluding the arena header)
+    pub fn capacity(&self) -> usize {
+        self.shm.size() - ARENA_HEADER
+    }
+
+    /// Get the number of bytes not currently allocated
+    pub fn available(&self) -> usize {
+        let _guard = self.lock();
+        self.capacity() - unsafe { self.u64_at(OFF_USED) } as usize
+    }
+
+    /// Get the number of live allocations
+    pub fn allocations(&self) -> usize {
+        let _guard = self.lock();
+        unsafe { self.u64_at(OFF_LIVE) as usize }
+    }
+
+    /// Allocate a blob of `len` bytes
+    ///
+    /// Fails with [`IpcError::BufferTooSmall`] if no free block is large
+    /// enough; `got` reports the largest blob that could be allocated.
+    pub fn alloc(&self, len: usize) -> Result<ShmTicket> {
+        let need = (len.div_ceil(BLOCK_ALIGN) * BLOCK_ALIGN + BLOCK_HEADER).max(MIN_BLOCK);
+        let _guard = self.lock();
+
+        unsafe {
+            let mut prev = 0usize;
+            let mut cur = self.u64_at(OFF_FREE_HEAD) as usize;
+            let mut largest = 0usize;
+
+            while cur != 0 {
+                let size = self.u64_at(cur) as usize;
+                let next = self.u64_at(cur + BLOCK_HEADER) as usize;
+
+                if size >= need {
+                    let (block, block_size) = if size - need >= MIN_BLOCK {
+                        // Carve the allocation off the end, leaving the free link intact
+                        self.set_u64(cur, (size - need) as u64);
+                        (cur + size - need, need)
+                    } else {
+                        self.link(prev, next);
+                        (cur, size)
+                    };
+
+                    let generation = self.u32_at(OFF_GENERATION).wrapping_add(1);
+                    self.set_u32(OFF_GENERATION, generation);
+                    self.set_u64(block, block_size as u64);
+                    self.set_u32(block + 8, BLOCK_USED);
+                    self.set_u32(block + 12, generation);
+                    self.set_u64(OFF_LIVE, self.u64_at(OFF_LIVE) + 1);
+                    self.set_u64(OFF_USED, self.u64_at(OFF_USED) + block_size as u64);
+
+                    return Ok(ShmTicket {
+                        offset: (block + BLOCK_HEADER) as u64,
+                        len: len as u64,
+                        generation,
+                    });
+                }
+
+                largest = largest.max(size);
+                prev = cur;
+                cur = next;
+            }
+
+            Err(IpcError::BufferTooSmall {
+                needed: len,
+                got: largest.saturating_sub(BLOCK_HEADER),
+            })
+        }
+    }
+
+    /// Allocate a blob and copy `data` into it
+    pub fn store(&self, data: &[u8]) -> Result<ShmTicket> {
+        let ticket = self.alloc(data.len())?;
+        self.write(&ticket, data)?;
+        Ok(ticket)
+    }
+
+    /// Write `data` to the start of an allocated blob
+    pub fn write(&self, ticket: &ShmTicket, data: &[u8]) -> Result<()> {
+        self.check(ticket)?;
+        if data.len() > ticket.len() {
+            return Err(IpcError::BufferTooSmall {
+                needed: data.len(),
+                got: ticket.len(),
+            });
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                self.base().add(ticket.offset()),
+                data.len(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Read the contents of an allocated blob
+    pub fn read(&self, ticket: &ShmTicket) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; ticket.len()];
+        self.read_into(ticket, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Read the start of an allocated blob into an existing buffer
+    pub fn read_into(&self, ticket: &ShmTicket, buf: &mut [u8]) -> Result<()> {
+        self.check(ticket)?;
+        if buf.len() > ticket.len() {
+            return Err(IpcError::BufferTooSmall {
+                needed: buf.len(),
+                got: ticket.len(),
+            });
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                self.base().add(ticket.offset()),
+                buf.as_mut_ptr(),
+                buf.len(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Return a blob to the arena
+    ///
+    /// Fails with [`IpcError::InvalidState`] for tickets that were already
+    /// freed or do not belong to this arena.
+    pub fn free(&self, ticket: ShmTicket) -> Result<()> {
+        let _guard = self.lock();
+
+        unsafe {
+            let block = self.validate(&ticket)?;
+            let size = self.u64_at(block) as usize;
+            self.set_u32(block + 8, BLOCK_FREE);
+            self.set_u64(OFF_LIVE, self.u64_at(OFF_LIVE) - 1);
+            self.set_u64(OFF_USED, self.u64_at(OFF_USED) - size as u64);
+
+            // Insert in address order so neighbours can be merged
+            let mut prev = 0usize;
+            let mut cur = self.u64_at(OFF_FREE_HEAD) as usize;
+            while cur != 0 && cur < block {
+                prev = cur;
+                cur = self.u64_at(cur + BLOCK_HEADER) as usize;
+            }
+            self.set_u64(block + BLOCK_HEADER, cur as u64);
+            self.link(prev, block);
+
+            if cur != 0 && block + size == cur {
+                self.set_u64(block, (size + self.u64_at(cur) as usize) as u64);
+                self.set_u64(block + BLOCK_HEADER, self.u64_at(cur + BLOCK_HEADER));
+            }
+            if prev != 0 && prev + self.u64_at(prev) as usize == block {
+                self.set_u64(prev, self.u64_at(prev) + self.u64_at(block));
+                self.set_u64(prev + BLOCK_HEADER, self.u64_at(block + BLOCK_HEADER));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check(&self, ticket: &ShmTicket) -> Result<()> {
+        let _guard = self.lock();
+        unsafe { self.validate(ticket).map(|_| ()) }
+    }
+
+    /// Validate a ticket and return its block offset. Requires the lock.
+    unsafe fn validate(&self, ticket: &ShmTicket) -> Result<usize> {
+        let invalid = || IpcError::InvalidState(format!("stale or foreign ticket {:?}", ticket));
+
+        let offset = ticket.offset();
+        if offset < ARENA_HEADER + BLOCK_HEADER
+            || !offset.is_multiple_of(BLOCK_ALIGN)
+            || offset >= self.shm.size()
+        {
+            return Err(invalid());
+        }
+
+        let block = offset - BLOCK_HEADER;
+        let size = self.u64_at(block) as usize;
+        if self.u32_at(block + 8) != BLOCK_USED
+            || self.u32_at(block + 12) != ticket.generation
+            || ticket.len() > size - BLOCK_HEADER
+        {
+            return Err(invalid());
+        }
+        Ok(block)
+    }
+
+    /// Point `prev`'s free-list link (or the list head) at `block`
+    unsafe fn link(&self, prev: usize, block: usize) {
+        if prev == 0 {
+            self.set_u64(OFF_FREE_HEAD, block as u64);
+        } else {
+            self.set_u64(prev + BLOCK_HEADER, block as u64);
+        }
+    }
+
+    fn lock(&self) -> ArenaGuard<'_> {
+        let lock = unsafe { &*(self.base().add(OFF_LOCK) as *const AtomicU32) };
+        let mut spins = 0u32;
+        while lock
+            .compare_exchange_weak(0, 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spins += 1;
+            if spins < 64 {
+                std::hint::spin_loop();
+            } else {
+                std::thread::yield_now();
+            }
+        }
+        ArenaGuard { lock }
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.shm.as_ptr() as *mut u8
+    }
+
+    unsafe fn u32_at(&self, offset: usize) -> u32 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u32)
+    }
+
+    unsafe fn set_u32(&self, offset: usize, value: u32) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u32, value)
+    }
+
+    unsafe fn u64_at(&self, offset: usize) -> u64 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u64)
+    }
+
+    unsafe fn set_u64(&self, offset: usize, value: u64) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u64, value)
+    }
+}
+
+/// Magic number identifying a growable header segment ("IPKG")
+const GROWABLE_MAGIC: u32 = 0x474B_5049;
+/// Growable layout version
+const GROWABLE_VERSION: u32 = 1;
+/// Size of the header segment
+const GROWABLE_HEADER: usize = 64;
+/// Upper bound on chained segments (the last one is `initial << 47` bytes)
+const GROWABLE_MAX_SEGMENTS: u32 = 48;
+
+// Growable header field offsets
+const OFF_GROW_MAGIC: usize = 0;
+const OFF_GROW_VERSION: usize = 4;
+const OFF_GROW_LOCK: usize = 8;
+const OFF_GROW_SEGMENTS: usize = 12;
+const OFF_GROW_LEN: usize = 16;
+const OFF_GROW_INITIAL: usize = 24;
+
+/// A shared memory buffer that grows on demand
+///
+/// Producers don't have to guess a maximum size up front: writing past the
+/// current capacity chains another segment, each twice the size of the one
+/// before, so a buffer that grows 100x needs only a handful of segments.
+/// Offsets address one contiguous logical buffer; reads and writes that
+/// cross a segment boundary are split transparently.
+///
+/// The header segment `name` records the segment count and the logical
+/// length, and data lives in `name.0`, `name.1`, ... Other processes
+/// [`open`](Self::open) the buffer by name and map segments added later on
+/// their next access. Writers are serialized by a spinlock in the header;
+/// as with [`SharedMemory`], readers racing a writer can observe partially
+/// written data. The creator removes every segment when it is dropped.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use ipckit::GrowableShm;
+///
+/// let producer = GrowableShm::create("scene", 4096)?;
+/// producer.append(&vec![0u8; 1 << 20])?; // grows past 4 KiB
+///
+/// let consumer = GrowableShm::open("scene")?;
+/// assert_eq!(consumer.len(), 1 << 20);
+/// let head = consumer.read(0, 64)?;
+/// # Ok::<(), ipckit::IpcError>(())
+/// ```
+pub struct GrowableShm {
+    header: SharedMemory,
+    segments: parking_lot::Mutex<Vec<SharedMemory>>,
+}
+
+impl GrowableShm {
+    /// Create a buffer whose first segment holds `initial_size` bytes
+    pub fn create(name: &str, initial_size: usize) -> Result<Self> {
+        if initial_size == 0 {
+            return Err(IpcError::InvalidName("Size must be greater than 0".into()));
+        }
+
+        let header = SharedMemory::create(name, GROWABLE_HEADER)?;
+        let first = SharedMemory::create(&segment_name(header.name(), 0), initial_size)?;
+        let shm = Self {
+            header,
+            segments: parking_lot::Mutex::new(vec![first]),
+        };
+        unsafe {
+            shm.set_u32(OFF_GROW_VERSION, GROWABLE_VERSION);
+            shm.set_u64(OFF_GROW_INITIAL, initial_size as u64);
+        }
+        shm.segment_count().store(1, Ordering::Release);
+        // Publish the magic last so openers never see a half-initialized header
+        unsafe { shm.set_u32(OFF_GROW_MAGIC, GROWABLE_MAGIC) };
+
+        Ok(shm)
+    }
+
+    /// Open an existing buffer created by another process
+    pub fn open(name: &str) -> Result<Self> {
+        let header = SharedMemory::open(name)?;
+        let shm = Self {
+            header,
+            segments: parking_lot::Mutex::new(Vec::new()),
+        };
+        if shm.header.size() < GROWABLE_HEADER
+            || unsafe { shm.u32_at(OFF_GROW_MAGIC) } != GROWABLE_MAGIC
+            || unsafe { shm.u32_at(OFF_GROW_VERSION) } != GROWABLE_VERSION
+        {
+            return Err(IpcError::InvalidState(format!(
+                "'{}' is not an ipckit growable segment",
+                name
+            )));
+        }
+        shm.map_segments(&mut shm.segments.lock())?;
+        Ok(shm)
+    }
+
+    /// Get the name of the header segment
+    pub fn name(&self) -> &str {
+        self.header.name()
+    }
+
+    /// Get the logical length: one past the highest byte written
+    pub fn len(&self) -> usize {
+        self.logical_len().load(Ordering::Acquire) as usize
+    }
+
+    /// Check if nothing has been written
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the number of bytes the current segments can hold
+    pub fn capacity(&self) -> usize {
+        segment_start(self.initial(), self.segment_count().load(Ordering::Acquire))
+    }
+
+    /// Get the number of chained data segments
+    pub fn segments(&self) -> usize {
+        self.segment_count().load(Ordering::Acquire) as usize
+    }
+
+    /// Grow the buffer until it can hold at least `capacity` bytes
+    pub fn reserve(&self, capacity: usize) -> Result<()> {
+        let _guard = self.lock();
+        self.grow(&mut self.segments.lock(), capacity)
+    }
+
+    /// Write `data` at `offset`, growing the buffer as needed
+    pub fn write(&self, offset: usize, data: &[u8]) -> Result<()> {
+        let _guard = self.lock();
+        self.write_locked(offset, data)
+    }
+
+    /// Write `data` after the current end and return its offset
+    pub fn append(&self, data: &[u8]) -> Result<usize> {
+        let _guard = self.lock();
+        let offset = self.len();
+        self.write_locked(offset, data)?;
+        Ok(offset)
+    }
+
+    /// Set the logical length to `len`, keeping the segments mapped
+    ///
+    /// Segments are never shrunk, so capacity reached once is reused.
+    pub fn truncate(&self, len: usize) {
+        let _guard = self.lock();
+        self.logical_len().fetch_min(len as u64, Ordering::AcqRel);
+    }
+
+    /// Read `len` bytes at `offset`
+    ///
+    /// Returns error if offset + len exceeds the logical length.
+    pub fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        self.read_into(offset, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Read data into an existing buffer
+    pub fn read_into(&self, offset: usize, buf: &mut [u8]) -> Result<()> {
+        let len = self.len();
+        if offset + buf.len() > len {
+            return Err(IpcError::BufferTooSmall {
+                needed: offset + buf.len(),
+                got: len,
+            });
+        }
+
+        let mut segments = self.segments.lock();
+        self.map_segments(&mut segments)?;
+        let mut done = 0;
+        for (seg, seg_offset, n) in self.chunks(offset, buf.len()) {
+            segments[seg].read_into(seg_offset, &mut buf[done..done + n])?;
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// Write with the header lock held (internal)
+    fn write_locked(&self, offset: usize, data: &[u8]) -> Result<()> {
+        let end = offset + data.len();
+        let mut segments = self.segments.lock();
+        self.grow(&mut segments, end)?;
+
+        let mut done = 0;
+        for (seg, seg_offset, n) in self.chunks(offset, data.len()) {
+            segments[seg].write(seg_offset, &data[done..done + n])?;
+            done += n;
+        }
+        self.logical_len().fetch_max(end as u64, Ordering::AcqRel);
+        Ok(())
+    }
+
+    /// Chain segments until `capacity` bytes fit. Requires the lock.
+    fn grow(&self, segments: &mut Vec<SharedMemory>, capacity: usize) -> Result<()> {
+        self.map_segments(segments)?;
+        let initial = self.initial();
+        while segment_start(initial, segments.len() as u32) < capacity {
+            let index = segments.len() as u32;
+            let size = segment_size(initial, index).ok_or_else(|| IpcError::BufferTooSmall {
+                needed: capacity,
+                got: segment_start(initial, index),
+            })?;
+            let mut segment = SharedMemory::create(&segment_name(self.name(), index), size)?;
+            // Segments are removed with the header, by the buffer's creator
+            segment.is_owner = self.header.is_owner;
+            segments.push(segment);
+            self.segment_count().store(index + 1, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// Map segments added by other processes since the last access
+    fn map_segments(&self, segments: &mut Vec<SharedMemory>) -> Result<()> {
+        let count = self.segment_count().load(Ordering::Acquire) as usize;
+        while segments.len() < count {
+            let mut segment =
+                SharedMemory::open(&segment_name(self.name(), segments.len() as u32))?;
+            segment.is_owner = self.header.is_owner;
+            segments.push(segment);
+        }
+        Ok(())
+    }
+
+    /// Split a logical range into `(segment, offset in segment, len)` pieces
+    fn chunks(&self, offset: usize, len: usize) -> Vec<(usize, usize, usize)> {
+        let initial = self.initial();
+        let mut chunks = Vec::new();
+        let (mut pos, end) = (offset, offset + len);
+        let mut seg = 0u32;
+        while pos < end {
+            let start = segment_start(initial, seg);
+            let stop = segment_start(initial, seg + 1);
+            if pos < stop {
+                let n = end.min(stop) - pos;
+                chunks.push((seg as usize, pos - start, n));
+                pos += n;
+            }
+            seg += 1;
+        }
+        chunks
+    }
+
+    fn initial(&self) -> usize {
+        unsafe { self.u64_at(OFF_GROW_INITIAL) as usize }
+    }
+
+    fn lock(&self) -> ArenaGuard<'_> {
+        let lock = unsafe { &*(self.base().add(OFF_GROW_LOCK) as *const AtomicU32) };
+        let mut spins = 0u32;
+        while lock
+            .compare_exchange_weak(0, 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spins += 1;
+            if spins < 64 {
+                std::hint::spin_loop();
+            } else {
+                std::thread::yield_now();
+            }
+        }
+        ArenaGuard { lock }
+    }
+
+    fn segment_count(&self) -> &AtomicU32 {
+        unsafe { &*(self.base().add(OFF_GROW_SEGMENTS) as *const AtomicU32) }
+    }
+
+    fn logical_len(&self) -> &AtomicU64 {
+        unsafe { &*(self.base().add(OFF_GROW_LEN) as *const AtomicU64) }
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.header.as_ptr() as *mut u8
+    }
+
+    unsafe fn u32_at(&self, offset: usize) -> u32 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u32)
+    }
+
+    unsafe fn set_u32(&self, offset: usize, value: u32) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u32, value)
+    }
+
+    unsafe fn u64_at(&self, offset: usize) -> u64 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u64)
+    }
+
+    unsafe fn set_u64(&self, offset: usize, value: u64) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u64, value)
+    }
+}
+
+impl Drop for GrowableShm {
+    fn drop(&mut self) {
+        if !self.header.is_owner {
+            return;
+        }
+        // Unlink segments added by other processes that this handle never mapped
+        let mut segments = self.segments.lock();
+        let _ = self.map_segments(&mut segments);
+    }
+}
+
+/// Name of data segment `index` of the growable buffer `name`
+fn segment_name(name: &str, index: u32) -> String {
+    format!("{}.{}", name, index)
+}
+
+/// Size of data segment `index`, or `None` past the segment limit
+fn segment_size(initial: usize, index: u32) -> Option<usize> {
+    if index >= GROWABLE_MAX_SEGMENTS {
+        return None;
+    }
+    initial.checked_mul(1usize.checked_shl(index)?)
+}
+
+/// Logical offset of data segment `index`: the combined size of the ones
+/// before it
+fn segment_start(initial: usize, index: u32) -> usize {
+    (0..index)
+        .map(|i| segment_size(initial, i).unwrap_or(usize::MAX))
+        .fold(0usize, usize::saturating_add)
 }
 
 impl Drop for SharedMemory {
@@ -439,4 +1448,180 @@ mod tests {
         let result = shm.write(90, &[0u8; 20]);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_shared_memory_snapshot() {
+        let name = format!("test_shm_snap_src_{}", std::process::id());
+        let snap_name = format!("test_shm_snap_{}", std::process::id());
+        let mut shm = SharedMemory::create(&name, 64).unwrap();
+        shm.write(0, b"frame 1").unwrap();
+
+        let snapshot = shm.snapshot(&snap_name).unwrap();
+        assert_eq!(snapshot.size(), 64);
+
+        // Mutating the live segment does not affect the snapshot
+        shm.write(0, b"frame 2").unwrap();
+        assert_eq!(snapshot.read(0, 7).unwrap(), b"frame 1");
+
+        // Other consumers can open the snapshot by name
+        let reader = SharedMemory::open(snapshot.name()).unwrap();
+        assert_eq!(reader.read(0, 7).unwrap(), b"frame 1");
+
+        // Snapshot names must be unique
+        assert!(shm.snapshot(&snap_name).is_err());
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Viewport {
+        width: u32,
+        height: u32,
+        zoom: f32,
+    }
+
+    unsafe impl ShmStruct for Viewport {
+        const SCHEMA: &'static str = "Viewport{width:u32,height:u32,zoom:f32}";
+    }
+
+    /// Same layout as `Viewport` under a newer version
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct ViewportV2 {
+        width: u32,
+        height: u32,
+        zoom: f32,
+    }
+
+    unsafe impl ShmStruct for ViewportV2 {
+        const VERSION: u32 = 2;
+        const SCHEMA: &'static str = "Viewport{width:u32,height:u32,zoom:f32}";
+    }
+
+    #[test]
+    fn test_map_struct_validates_layout() {
+        let name = format!("test_shm_struct_{}", std::process::id());
+        let writer = ShmMapped::<Viewport>::create(&name).unwrap();
+        assert_eq!(writer.get().width, 0);
+        writer.set(Viewport {
+            width: 1920,
+            height: 1080,
+            zoom: 1.5,
+        });
+
+        let reader = ShmMapped::<Viewport>::open(&name).unwrap();
+        assert_eq!(reader.get().height, 1080);
+        assert_eq!(reader.get(), writer.get());
+
+        // Same size, different version or schema: refused
+        assert!(matches!(
+            ShmMapped::<ViewportV2>::open(&name),
+            Err(IpcError::Incompatible(_))
+        ));
+        assert!(matches!(
+            ShmMapped::<[u32; 3]>::open(&name),
+            Err(IpcError::Incompatible(_))
+        ));
+        // Too large for the region (or a different schema where the OS
+        // rounds regions up to whole pages)
+        assert!(ShmMapped::<[u64; 16]>::open(&name).is_err());
+
+        // A raw segment without a header is not mistaken for a struct
+        let raw_name = format!("test_shm_struct_raw_{}", std::process::id());
+        let _raw = SharedMemory::create(&raw_name, 128).unwrap();
+        assert!(matches!(
+            SharedMemory::open(&raw_name)
+                .unwrap()
+                .map_struct::<Viewport>(),
+            Err(IpcError::InvalidState(_))
+        ));
+    }
+
+    #[test]
+    fn test_shm_arena_alloc_free() {
+        let name = format!("test_shm_arena_{}", std::process::id());
+        let arena = ShmArena::create(&name, 4096).unwrap();
+        let capacity = arena.capacity();
+
+        let a = arena.store(b"first blob").unwrap();
+        let b = arena.store(&[7u8; 100]).unwrap();
+        let c = arena.alloc(0).unwrap();
+        assert_eq!(arena.allocations(), 3);
+        assert!(c.is_empty());
+
+        // Another handle sees the same blobs and can free them
+        let consumer = ShmArena::open(&name).unwrap();
+        assert_eq!(consumer.read(&a).unwrap(), b"first blob");
+        assert_eq!(consumer.read(&b).unwrap(), vec![7u8; 100]);
+        consumer.free(b).unwrap();
+        assert!(consumer.free(b).is_err(), "double free is rejected");
+        assert!(arena.read(&b).is_err());
+
+        arena.free(a).unwrap();
+        arena.free(c).unwrap();
+        assert_eq!(arena.allocations(), 0);
+        assert_eq!(arena.available(), capacity);
+
+        // Freed neighbours were merged back into one block
+        let big = arena.alloc(capacity - 2 * BLOCK_HEADER).unwrap();
+        assert!(matches!(
+            arena.alloc(1024),
+            Err(IpcError::BufferTooSmall { .. })
+        ));
+        arena.free(big).unwrap();
+
+        assert!(ShmArena::open(&format!("test_shm_arena_missing_{}", std::process::id())).is_err());
+    }
+
+    #[test]
+    fn test_growable_shm_spans_segments() {
+        let name = format!("test_shm_grow_{}", std::process::id());
+        let producer = GrowableShm::create(&name, 16).unwrap();
+        assert_eq!((producer.capacity(), producer.segments()), (16, 1));
+        assert!(producer.is_empty());
+
+        let consumer = GrowableShm::open(&name).unwrap();
+        let data: Vec<u8> = (0..100).collect();
+        assert_eq!(producer.append(&data[..10]).unwrap(), 0);
+        assert_eq!(producer.append(&data[10..]).unwrap(), 10);
+        // 16 + 32 + 64 bytes
+        assert_eq!((producer.capacity(), producer.segments()), (112, 3));
+
+        // The consumer maps the new segments on access
+        assert_eq!(consumer.len(), 100);
+        assert_eq!(consumer.read(0, 100).unwrap(), data);
+        assert_eq!(consumer.read(14, 4).unwrap(), vec![14, 15, 16, 17]);
+        assert!(matches!(
+            consumer.read(90, 20),
+            Err(IpcError::BufferTooSmall {
+                needed: 110,
+                got: 100
+            })
+        ));
+
+        // Any handle can grow the buffer
+        consumer.write(200, b"tail").unwrap();
+        assert_eq!(producer.len(), 204);
+        assert_eq!(producer.segments(), 4);
+        assert_eq!(producer.read(200, 4).unwrap(), b"tail");
+
+        producer.truncate(0);
+        assert!(consumer.is_empty());
+        assert_eq!(consumer.capacity(), 240);
+    }
+
+    #[test]
+    fn test_growable_shm_removes_segments_with_creator() {
+        let name = format!("test_shm_grow_owner_{}", std::process::id());
+        let producer = GrowableShm::create(&name, 8).unwrap();
+        let consumer = GrowableShm::open(&name).unwrap();
+        consumer.reserve(64).unwrap();
+        drop(consumer);
+
+        // Segments the creator never mapped are removed with it
+        assert_eq!(producer.segments(), 4);
+        drop(producer);
+        assert!(SharedMemory::open(&name).is_err());
+        assert!(SharedMemory::open(&format!("{}.3", name)).is_err());
+        assert!(GrowableShm::open(&name).is_err());
+    }
 }
@@ -0,0 +1,473 @@
+//! Shared Memory Broadcast - Many-reader telemetry ring
+//!
+//! [`ShmBroadcast`] fans variable-length records (profiler samples, log
+//! lines, metrics ticks) out from one writer to any number of readers in
+//! other processes without a socket per consumer. The writer appends records
+//! to a byte ring and never waits: when the ring is full the oldest records
+//! are overwritten. Each [`ShmBroadcastReader`] keeps its own cursor, so
+//! readers can join at any time and consume at their own pace.
+//!
+//! A reader that falls more than a ring's worth behind is overrun. It
+//! notices, because the writer announces the region it is about to
+//! overwrite before touching it, skips ahead to the oldest record still
+//! intact, and counts what it missed in [`lost`](ShmBroadcastReader::lost).
+//! Every record carries a sequence number, so gaps are also visible per
+//! record.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::{ShmBroadcast, ShmBroadcastReader};
+//! use std::time::Duration;
+//!
+//! // Profiled process
+//! let samples = ShmBroadcast::create("profiler", 1 << 20).unwrap();
+//! samples.publish(b"main;render;draw 42").unwrap();
+//!
+//! // Any number of viewer tools
+//! let mut reader = ShmBroadcastReader::open("profiler").unwrap();
+//! let record = reader.recv_timeout(Duration::from_secs(1)).unwrap();
+//! println!("sample {}: {} bytes", record.seq, record.data.len());
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::shm::SharedMemory;
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Magic number identifying a broadcast segment ("IPKB")
+const RING_MAGIC: u32 = 0x424B_5049;
+/// Broadcast layout version
+const RING_VERSION: u32 = 1;
+/// Size of the header at the start of the segment
+const RING_HEADER: usize = 64;
+/// Size of the header in front of every record: length and sequence number
+const RECORD_HEADER: usize = 16;
+
+/// How often [`ShmBroadcastReader::recv_timeout`] checks for a new record
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Segment header field offsets
+const OFF_MAGIC: usize = 0;
+const OFF_VERSION: usize = 4;
+const OFF_CAPACITY: usize = 8;
+/// End of the last complete record
+const OFF_HEAD: usize = 16;
+/// End of the record being written; everything a ring length before it may
+/// already be overwritten
+const OFF_RESERVED: usize = 24;
+/// Start of the oldest record that is still intact
+const OFF_TAIL: usize = 32;
+/// Sequence number of the next record
+const OFF_SEQ: usize = 40;
+
+/// A record copied out of a [`ShmBroadcast`] ring
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShmRecord {
+    /// Sequence number assigned by the writer, starting at 0
+    pub seq: u64,
+    /// Record contents
+    pub data: Vec<u8>,
+}
+
+/// The ring segment shared by the writer and readers.
+struct Ring {
+    shm: SharedMemory,
+    capacity: usize,
+}
+
+impl Ring {
+    fn create(name: &str, capacity: usize) -> Result<Self> {
+        if capacity <= RECORD_HEADER {
+            return Err(IpcError::InvalidState(format!(
+                "broadcast capacity must exceed {} bytes",
+                RECORD_HEADER
+            )));
+        }
+        let total = capacity
+            .checked_add(RING_HEADER)
+            .ok_or_else(|| IpcError::InvalidState("broadcast size overflows".to_string()))?;
+        let ring = Self {
+            shm: SharedMemory::create(name, total)?,
+            capacity,
+        };
+        unsafe {
+            ring.set_u32(OFF_VERSION, RING_VERSION);
+            ring.set_u64(OFF_CAPACITY, capacity as u64);
+            for offset in [OFF_HEAD, OFF_RESERVED, OFF_TAIL, OFF_SEQ] {
+                ring.set_u64(offset, 0);
+            }
+            // Publish the magic last so openers never see a half-initialized ring
+            ring.set_u32(OFF_MAGIC, RING_MAGIC);
+        }
+        Ok(ring)
+    }
+
+    fn open(name: &str) -> Result<Self> {
+        let shm = SharedMemory::open(name)?;
+        let invalid = || IpcError::InvalidState(format!("'{}' is not an ipckit broadcast", name));
+        if shm.size() < RING_HEADER {
+            return Err(invalid());
+        }
+
+        let mut ring = Self { shm, capacity: 0 };
+        unsafe {
+            if ring.u32_at(OFF_MAGIC) != RING_MAGIC || ring.u32_at(OFF_VERSION) != RING_VERSION {
+                return Err(invalid());
+            }
+            ring.capacity = ring.u64_at(OFF_CAPACITY) as usize;
+        }
+        if ring.capacity <= RECORD_HEADER
+            || ring
+                .capacity
+                .checked_add(RING_HEADER)
+                .is_none_or(|n| n > ring.shm.size())
+        {
+            return Err(invalid());
+        }
+        Ok(ring)
+    }
+
+    fn counter(&self, offset: usize) -> &AtomicU64 {
+        unsafe { &*(self.base().add(offset) as *const AtomicU64) }
+    }
+
+    /// Copy `buf.len()` bytes out of the ring starting at position `pos`.
+    fn copy_out(&self, pos: u64, buf: &mut [u8]) {
+        let offset = (pos % self.capacity as u64) as usize;
+        let first = buf.len().min(self.capacity - offset);
+        unsafe {
+            let data = self.base().add(RING_HEADER);
+            std::ptr::copy_nonoverlapping(data.add(offset), buf.as_mut_ptr(), first);
+            std::ptr::copy_nonoverlapping(data, buf.as_mut_ptr().add(first), buf.len() - first);
+        }
+    }
+
+    /// Copy `bytes` into the ring starting at position `pos`.
+    fn copy_in(&self, pos: u64, bytes: &[u8]) {
+        let offset = (pos % self.capacity as u64) as usize;
+        let first = bytes.len().min(self.capacity - offset);
+        unsafe {
+            let data = self.base().add(RING_HEADER);
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), data.add(offset), first);
+            std::ptr::copy_nonoverlapping(bytes.as_ptr().add(first), data, bytes.len() - first);
+        }
+    }
+
+    /// Read the length and sequence number of the record at `pos`.
+    fn record_header(&self, pos: u64) -> (usize, u64) {
+        let mut header = [0u8; RECORD_HEADER];
+        self.copy_out(pos, &mut header);
+        let len = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let seq = u64::from_le_bytes(header[8..].try_into().unwrap());
+        (len as usize, seq)
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.shm.as_ptr() as *mut u8
+    }
+
+    unsafe fn u32_at(&self, offset: usize) -> u32 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u32)
+    }
+
+    unsafe fn set_u32(&self, offset: usize, value: u32) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u32, value)
+    }
+
+    unsafe fn u64_at(&self, offset: usize) -> u64 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u64)
+    }
+
+    unsafe fn set_u64(&self, offset: usize, value: u64) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u64, value)
+    }
+}
+
+/// Writer end of a shared memory broadcast ring
+///
+/// Only one process (or thread) may publish at a time; the segment does not
+/// arbitrate between competing writers. Readers never slow the writer down.
+pub struct ShmBroadcast {
+    ring: Ring,
+}
+
+impl ShmBroadcast {
+    /// Create a broadcast ring holding `capacity` bytes of records
+    ///
+    /// Each record takes 16 bytes of header plus its length, so a 1 MiB ring
+    /// of 100-byte samples keeps the last ~9000 of them for slow readers.
+    pub fn create(name: &str, capacity: usize) -> Result<Self> {
+        Ok(Self {
+            ring: Ring::create(name, capacity)?,
+        })
+    }
+
+    /// Get the name of the underlying segment
+    pub fn name(&self) -> &str {
+        self.ring.shm.name()
+    }
+
+    /// Get the ring size in bytes
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity
+    }
+
+    /// Get the largest record that fits in the ring
+    pub fn max_record_size(&self) -> usize {
+        self.ring.capacity - RECORD_HEADER
+    }
+
+    /// Get the number of records published so far
+    pub fn published(&self) -> u64 {
+        self.ring.counter(OFF_SEQ).load(Ordering::Acquire)
+    }
+
+    /// Append a record, overwriting the oldest ones if the ring is full
+    ///
+    /// Returns the record's sequence number.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IpcError::BufferTooSmall` if `data` exceeds
+    /// [`max_record_size`](Self::max_record_size).
+    pub fn publish(&self, data: &[u8]) -> Result<u64> {
+        if data.len() > self.max_record_size() {
+            return Err(IpcError::BufferTooSmall {
+                needed: data.len(),
+                got: self.max_record_size(),
+            });
+        }
+
+        let ring = &self.ring;
+        let capacity = ring.capacity as u64;
+        let head = ring.counter(OFF_HEAD).load(Ordering::Relaxed);
+        let end = head + (RECORD_HEADER + data.len()) as u64;
+
+        // Evict the records this one is about to overwrite
+        let mut tail = ring.counter(OFF_TAIL).load(Ordering::Relaxed);
+        while end - tail > capacity {
+            let (len, _) = ring.record_header(tail);
+            tail += (RECORD_HEADER + len) as u64;
+        }
+        ring.counter(OFF_TAIL).store(tail, Ordering::Release);
+
+        // Announce the overwrite before making it, so readers copying the
+        // old bytes can tell their copy may be torn
+        ring.counter(OFF_RESERVED).store(end, Ordering::Release);
+        fence(Ordering::Release);
+
+        let seq = ring.counter(OFF_SEQ).load(Ordering::Relaxed);
+        let mut header = [0u8; RECORD_HEADER];
+        header[..8].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        header[8..].copy_from_slice(&seq.to_le_bytes());
+        ring.copy_in(head, &header);
+        ring.copy_in(head + RECORD_HEADER as u64, data);
+
+        ring.counter(OFF_SEQ).store(seq + 1, Ordering::Release);
+        ring.counter(OFF_HEAD).store(end, Ordering::Release);
+        Ok(seq)
+    }
+}
+
+/// Reader end of a shared memory broadcast ring
+///
+/// Every reader has its own cursor; records are not consumed, so any number
+/// of readers see every record they keep up with.
+pub struct ShmBroadcastReader {
+    ring: Ring,
+    cursor: u64,
+    next_seq: Option<u64>,
+    lost: u64,
+}
+
+impl ShmBroadcastReader {
+    /// Attach to a ring, receiving only records published from now on
+    pub fn open(name: &str) -> Result<Self> {
+        let ring = Ring::open(name)?;
+        let cursor = ring.counter(OFF_HEAD).load(Ordering::Acquire);
+        let next_seq = ring.counter(OFF_SEQ).load(Ordering::Acquire);
+        Ok(Self {
+            ring,
+            cursor,
+            next_seq: Some(next_seq),
+            lost: 0,
+        })
+    }
+
+    /// Attach to a ring, starting with the oldest record it still holds
+    ///
+    /// [`lost`](Self::lost) counts from the first record received.
+    pub fn open_from_oldest(name: &str) -> Result<Self> {
+        let ring = Ring::open(name)?;
+        let cursor = ring.counter(OFF_TAIL).load(Ordering::Acquire);
+        Ok(Self {
+            ring,
+            cursor,
+            next_seq: None,
+            lost: 0,
+        })
+    }
+
+    /// Get the number of records skipped because the writer overran this
+    /// reader
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    /// Get the number of bytes published but not yet read
+    pub fn pending_bytes(&self) -> u64 {
+        let head = self.ring.counter(OFF_HEAD).load(Ordering::Acquire);
+        head.saturating_sub(self.cursor)
+    }
+
+    /// Take the next record, or `None` if the reader has caught up
+    pub fn try_recv(&mut self) -> Option<ShmRecord> {
+        let ring = &self.ring;
+        let capacity = ring.capacity as u64;
+
+        loop {
+            let head = ring.counter(OFF_HEAD).load(Ordering::Acquire);
+            if self.cursor >= head {
+                return None;
+            }
+
+            let (len, seq) = ring.record_header(self.cursor);
+            // Only allocate for a length that could be genuine; a torn
+            // header is caught by the overrun check below either way
+            let mut data = vec![0u8; len.min(ring.capacity - RECORD_HEADER)];
+            ring.copy_out(self.cursor + RECORD_HEADER as u64, &mut data);
+
+            fence(Ordering::Acquire);
+            let reserved = ring.counter(OFF_RESERVED).load(Ordering::Acquire);
+            if reserved > self.cursor + capacity {
+                // Overrun: the writer evicted this record before announcing
+                // the overwrite, so the tail has already moved past it
+                self.cursor = ring.counter(OFF_TAIL).load(Ordering::Acquire);
+                continue;
+            }
+
+            if let Some(expected) = self.next_seq {
+                self.lost += seq.saturating_sub(expected);
+            }
+            self.next_seq = Some(seq + 1);
+            self.cursor += (RECORD_HEADER + len) as u64;
+            return Some(ShmRecord { seq, data });
+        }
+    }
+
+    /// Wait up to `timeout` for the next record
+    ///
+    /// # Errors
+    ///
+    /// Returns `IpcError::Timeout` if nothing was published in time.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<ShmRecord> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(record) = self.try_recv() {
+                return Ok(record);
+            }
+            if Instant::now() >= deadline {
+                return Err(IpcError::Timeout);
+            }
+            std::thread::sleep(WAIT_POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_broadcast_fan_out() {
+        let name = format!("test_shmbc_{}", std::process::id());
+        let writer = ShmBroadcast::create(&name, 256).unwrap();
+        let mut early = ShmBroadcastReader::open(&name).unwrap();
+
+        writer.publish(b"one").unwrap();
+        // Late joiners only see what comes next, unless they ask for history
+        let mut late = ShmBroadcastReader::open(&name).unwrap();
+        let mut replay = ShmBroadcastReader::open_from_oldest(&name).unwrap();
+        assert_eq!(writer.publish(b"two").unwrap(), 1);
+
+        assert_eq!(early.try_recv().unwrap().data, b"one");
+        assert_eq!(early.try_recv().unwrap().data, b"two");
+        assert!(early.try_recv().is_none());
+        assert_eq!(
+            late.try_recv().unwrap(),
+            ShmRecord {
+                seq: 1,
+                data: b"two".to_vec()
+            }
+        );
+        assert_eq!(replay.try_recv().unwrap().seq, 0);
+        assert_eq!(replay.pending_bytes(), (RECORD_HEADER + 3) as u64);
+
+        assert!(matches!(
+            early.recv_timeout(Duration::from_millis(10)),
+            Err(IpcError::Timeout)
+        ));
+        assert!(matches!(
+            writer.publish(&[0; 241]),
+            Err(IpcError::BufferTooSmall { .. })
+        ));
+        assert_eq!(writer.published(), 2);
+    }
+
+    #[test]
+    fn test_broadcast_overrun() {
+        let name = format!("test_shmbc_overrun_{}", std::process::id());
+        let writer = ShmBroadcast::create(&name, 100).unwrap();
+        let mut reader = ShmBroadcastReader::open(&name).unwrap();
+
+        // 24-byte records: the ring holds four, so the first six are lost
+        for i in 0..10u64 {
+            writer.publish(&i.to_le_bytes()).unwrap();
+        }
+        assert_eq!(reader.try_recv().unwrap().seq, 6);
+        assert_eq!(reader.lost(), 6);
+        let rest: Vec<u64> = std::iter::from_fn(|| reader.try_recv())
+            .map(|r| r.seq)
+            .collect();
+        assert_eq!(rest, [7, 8, 9]);
+        assert_eq!(reader.lost(), 6);
+    }
+
+    #[test]
+    fn test_broadcast_records_are_consistent() {
+        let name = format!("test_shmbc_torn_{}", std::process::id());
+        let writer = ShmBroadcast::create(&name, 4096).unwrap();
+        let mut reader = ShmBroadcastReader::open(&name).unwrap();
+        let done = Arc::new(AtomicBool::new(false));
+
+        let publisher = std::thread::spawn({
+            let done = Arc::clone(&done);
+            move || {
+                for i in 0..20_000u64 {
+                    let len = 1 + (i as usize % 200);
+                    writer.publish(&vec![i as u8; len]).unwrap();
+                }
+                done.store(true, Ordering::SeqCst);
+            }
+        });
+
+        let mut received = 0u64;
+        let mut last = None;
+        while !done.load(Ordering::SeqCst) || reader.pending_bytes() > 0 {
+            let Some(record) = reader.try_recv() else {
+                continue;
+            };
+            assert!(last.is_none_or(|last| record.seq > last));
+            last = Some(record.seq);
+            assert_eq!(record.data.len(), 1 + (record.seq as usize % 200));
+            assert!(record.data.iter().all(|&b| b == record.seq as u8));
+            received += 1;
+        }
+        publisher.join().unwrap();
+        assert_eq!(last, Some(19_999));
+        assert_eq!(received + reader.lost(), 20_000);
+    }
+}
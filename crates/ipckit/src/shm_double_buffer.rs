@@ -0,0 +1,436 @@
+//! Shared Memory Double Buffer - Lock-free latest-frame streaming
+//!
+//! [`ShmDoubleBuffer`] is the usual way to stream frames (viewport images,
+//! sensor readouts, simulation state) from one producer to any number of
+//! readers in other processes. The producer fills a back buffer that no
+//! reader is being pointed at and then flips a generation counter; readers
+//! always copy the most recently published frame and never wait for the
+//! producer or each other.
+//!
+//! Every buffer carries a sequence counter (a seqlock): a reader that is
+//! overtaken while copying notices and retries with the newer frame, so it
+//! never returns a torn frame. With two buffers that can happen when the
+//! producer publishes back to back; a third buffer gives readers a whole
+//! extra frame time to finish copying
+//! ([`create_with_buffers`](ShmDoubleBuffer::create_with_buffers)).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::ShmDoubleBuffer;
+//! use std::time::Duration;
+//!
+//! // Renderer process
+//! let frames = ShmDoubleBuffer::create("viewport", 1920 * 1080 * 4).unwrap();
+//! frames.publish_with(1920 * 1080 * 4, |pixels| pixels.fill(0x80)).unwrap();
+//!
+//! // GUI process
+//! let frames = ShmDoubleBuffer::open("viewport").unwrap();
+//! let frame = frames.wait_for(0, Duration::from_secs(1)).unwrap();
+//! println!("frame {} has {} bytes", frame.generation, frame.data.len());
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::shm::SharedMemory;
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Magic number identifying a double buffer segment ("IPKD")
+const BUFFER_MAGIC: u32 = 0x444B_5049;
+/// Double buffer layout version
+const BUFFER_VERSION: u32 = 1;
+/// Size of the header at the start of the segment
+const BUFFER_HEADER: usize = 64;
+/// Size of the header in front of every frame buffer
+const FRAME_HEADER: usize = 64;
+/// Frame buffers start on cache line boundaries
+const FRAME_ALIGN: usize = 64;
+/// Largest number of frame buffers in one segment
+const MAX_BUFFERS: usize = 16;
+
+/// How often [`ShmDoubleBuffer::wait_for`] checks for a new frame
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Segment header field offsets
+const OFF_MAGIC: usize = 0;
+const OFF_VERSION: usize = 4;
+const OFF_BUFFERS: usize = 8;
+const OFF_CAPACITY: usize = 16;
+const OFF_GENERATION: usize = 24;
+
+// Frame header field offsets
+const FRAME_SEQ: usize = 0;
+const FRAME_GENERATION: usize = 8;
+const FRAME_LEN: usize = 16;
+
+/// A frame copied out of a [`ShmDoubleBuffer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShmFrame {
+    /// Generation the frame was published as, starting at 1
+    pub generation: u64,
+    /// Frame contents
+    pub data: Vec<u8>,
+}
+
+/// Single-producer, multi-reader latest-frame buffer in shared memory
+///
+/// Only one process (or thread) may publish at a time; the segment does not
+/// arbitrate between competing producers. Readers are unrestricted.
+pub struct ShmDoubleBuffer {
+    shm: SharedMemory,
+    buffers: usize,
+    capacity: usize,
+}
+
+impl ShmDoubleBuffer {
+    /// Create a double buffer for frames of up to `frame_capacity` bytes
+    pub fn create(name: &str, frame_capacity: usize) -> Result<Self> {
+        Self::create_with_buffers(name, frame_capacity, 2)
+    }
+
+    /// Create a buffer that rotates through `buffers` frame buffers
+    ///
+    /// Use 3 (triple buffering) when readers copy large frames while the
+    /// producer publishes continuously.
+    pub fn create_with_buffers(name: &str, frame_capacity: usize, buffers: usize) -> Result<Self> {
+        if !(2..=MAX_BUFFERS).contains(&buffers) {
+            return Err(IpcError::InvalidState(format!(
+                "buffer count must be between 2 and {}",
+                MAX_BUFFERS
+            )));
+        }
+
+        let total = frame_stride(frame_capacity)
+            .checked_mul(buffers)
+            .and_then(|n| n.checked_add(BUFFER_HEADER))
+            .ok_or_else(|| IpcError::InvalidState("double buffer size overflows".to_string()))?;
+        let shm = SharedMemory::create(name, total)?;
+
+        let buffer = Self {
+            shm,
+            buffers,
+            capacity: frame_capacity,
+        };
+        unsafe {
+            buffer.set_u32(OFF_VERSION, BUFFER_VERSION);
+            buffer.set_u32(OFF_BUFFERS, buffers as u32);
+            buffer.set_u64(OFF_CAPACITY, frame_capacity as u64);
+            buffer.set_u64(OFF_GENERATION, 0);
+            for index in 0..buffers {
+                let frame = buffer.frame_offset(index);
+                buffer.set_u64(frame + FRAME_SEQ, 0);
+                buffer.set_u64(frame + FRAME_GENERATION, 0);
+                buffer.set_u64(frame + FRAME_LEN, 0);
+            }
+            // Publish the magic last so openers never see a half-initialized buffer
+            buffer.set_u32(OFF_MAGIC, BUFFER_MAGIC);
+        }
+
+        Ok(buffer)
+    }
+
+    /// Open an existing double buffer created by another process
+    pub fn open(name: &str) -> Result<Self> {
+        let shm = SharedMemory::open(name)?;
+        let invalid =
+            || IpcError::InvalidState(format!("'{}' is not an ipckit double buffer", name));
+        if shm.size() < BUFFER_HEADER {
+            return Err(invalid());
+        }
+
+        let mut buffer = Self {
+            shm,
+            buffers: 0,
+            capacity: 0,
+        };
+        unsafe {
+            if buffer.u32_at(OFF_MAGIC) != BUFFER_MAGIC
+                || buffer.u32_at(OFF_VERSION) != BUFFER_VERSION
+            {
+                return Err(invalid());
+            }
+            buffer.buffers = buffer.u32_at(OFF_BUFFERS) as usize;
+            buffer.capacity = buffer.u64_at(OFF_CAPACITY) as usize;
+        }
+        let needed = frame_stride(buffer.capacity)
+            .checked_mul(buffer.buffers)
+            .and_then(|n| n.checked_add(BUFFER_HEADER));
+        if !(2..=MAX_BUFFERS).contains(&buffer.buffers)
+            || needed.is_none_or(|n| n > buffer.shm.size())
+        {
+            return Err(invalid());
+        }
+
+        Ok(buffer)
+    }
+
+    /// Get the name of the underlying segment
+    pub fn name(&self) -> &str {
+        self.shm.name()
+    }
+
+    /// Get the largest frame a buffer can hold
+    pub fn frame_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the number of frame buffers
+    pub fn buffers(&self) -> usize {
+        self.buffers
+    }
+
+    /// Get the generation of the latest published frame (0 before the first)
+    pub fn generation(&self) -> u64 {
+        self.generation_counter().load(Ordering::Acquire)
+    }
+
+    /// Publish a frame, returning its generation
+    ///
+    /// # Errors
+    ///
+    /// Returns `IpcError::BufferTooSmall` if `data` exceeds the frame capacity.
+    pub fn publish(&self, data: &[u8]) -> Result<u64> {
+        self.publish_with(data.len(), |buf| buf.copy_from_slice(data))
+    }
+
+    /// Publish a frame of `len` bytes rendered in place by `fill`
+    ///
+    /// `fill` writes straight into the back buffer, which readers are not
+    /// pointed at, so large frames need no intermediate copy. Its initial
+    /// contents are whatever that buffer held before.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IpcError::BufferTooSmall` if `len` exceeds the frame capacity.
+    pub fn publish_with<F>(&self, len: usize, fill: F) -> Result<u64>
+    where
+        F: FnOnce(&mut [u8]),
+    {
+        if len > self.capacity {
+            return Err(IpcError::BufferTooSmall {
+                needed: len,
+                got: self.capacity,
+            });
+        }
+
+        let generation = self.generation_counter().load(Ordering::Relaxed) + 1;
+        let frame = self.frame_offset(self.buffer_index(generation));
+        let seq = self.atomic_u64(frame + FRAME_SEQ);
+
+        // An odd sequence tells readers the buffer is being rewritten
+        let start = seq.load(Ordering::Relaxed);
+        seq.store(start + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        unsafe {
+            let data = std::slice::from_raw_parts_mut(self.base().add(frame + FRAME_HEADER), len);
+            fill(data);
+            self.set_u64(frame + FRAME_GENERATION, generation);
+            self.set_u64(frame + FRAME_LEN, len as u64);
+        }
+        seq.store(start + 2, Ordering::Release);
+
+        self.generation_counter()
+            .store(generation, Ordering::Release);
+        Ok(generation)
+    }
+
+    /// Copy the latest frame, or `None` if nothing has been published yet
+    pub fn latest(&self) -> Option<ShmFrame> {
+        let mut data = Vec::new();
+        self.read_latest_into(&mut data)
+            .map(|generation| ShmFrame { generation, data })
+    }
+
+    /// Copy the latest frame into `buf`, returning its generation
+    ///
+    /// Reuses `buf`'s allocation, so a reader polling every GUI tick does not
+    /// allocate per frame. Returns `None`, leaving `buf` untouched, if
+    /// nothing has been published yet.
+    pub fn read_latest_into(&self, buf: &mut Vec<u8>) -> Option<u64> {
+        loop {
+            let generation = self.generation();
+            if generation == 0 {
+                return None;
+            }
+            let frame = self.frame_offset(self.buffer_index(generation));
+            let seq = self.atomic_u64(frame + FRAME_SEQ);
+
+            let before = seq.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                // Overtaken by the producer; the counter has moved on too
+                std::hint::spin_loop();
+                continue;
+            }
+            unsafe {
+                let stored = self.u64_at(frame + FRAME_GENERATION);
+                let len = (self.u64_at(frame + FRAME_LEN) as usize).min(self.capacity);
+                buf.clear();
+                buf.reserve(len);
+                std::ptr::copy_nonoverlapping(
+                    self.base().add(frame + FRAME_HEADER),
+                    buf.as_mut_ptr(),
+                    len,
+                );
+                fence(Ordering::Acquire);
+                if seq.load(Ordering::Relaxed) == before && stored == generation {
+                    buf.set_len(len);
+                    return Some(generation);
+                }
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for a frame newer than `generation` and copy it
+    ///
+    /// Pass 0 to wait for the first frame, or the generation of the frame
+    /// already shown to wait for the next one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IpcError::Timeout` if no newer frame was published in time.
+    pub fn wait_for(&self, generation: u64, timeout: Duration) -> Result<ShmFrame> {
+        let deadline = Instant::now() + timeout;
+        while self.generation() <= generation {
+            if Instant::now() >= deadline {
+                return Err(IpcError::Timeout);
+            }
+            std::thread::sleep(WAIT_POLL_INTERVAL);
+        }
+        self.latest().ok_or(IpcError::Timeout)
+    }
+
+    fn buffer_index(&self, generation: u64) -> usize {
+        (generation % self.buffers as u64) as usize
+    }
+
+    fn frame_offset(&self, index: usize) -> usize {
+        BUFFER_HEADER + index * frame_stride(self.capacity)
+    }
+
+    fn generation_counter(&self) -> &AtomicU64 {
+        self.atomic_u64(OFF_GENERATION)
+    }
+
+    fn atomic_u64(&self, offset: usize) -> &AtomicU64 {
+        unsafe { &*(self.base().add(offset) as *const AtomicU64) }
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.shm.as_ptr() as *mut u8
+    }
+
+    unsafe fn u32_at(&self, offset: usize) -> u32 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u32)
+    }
+
+    unsafe fn set_u32(&self, offset: usize, value: u32) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u32, value)
+    }
+
+    unsafe fn u64_at(&self, offset: usize) -> u64 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u64)
+    }
+
+    unsafe fn set_u64(&self, offset: usize, value: u64) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u64, value)
+    }
+}
+
+/// Bytes occupied by one frame buffer including its header
+fn frame_stride(capacity: usize) -> usize {
+    FRAME_HEADER + capacity.div_ceil(FRAME_ALIGN) * FRAME_ALIGN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_double_buffer_publish_and_read() {
+        let name = format!("test_shmdb_{}", std::process::id());
+        let producer = ShmDoubleBuffer::create(&name, 16).unwrap();
+        let reader = ShmDoubleBuffer::open(&name).unwrap();
+        assert_eq!(reader.buffers(), 2);
+        assert_eq!(reader.frame_capacity(), 16);
+
+        assert_eq!(reader.generation(), 0);
+        assert!(reader.latest().is_none());
+        assert!(matches!(
+            reader.wait_for(0, Duration::from_millis(10)),
+            Err(IpcError::Timeout)
+        ));
+
+        assert_eq!(producer.publish(b"first").unwrap(), 1);
+        assert_eq!(producer.publish(b"second").unwrap(), 2);
+        let frame = reader.latest().unwrap();
+        assert_eq!(frame.generation, 2);
+        assert_eq!(frame.data, b"second");
+
+        // Readers only ever see the latest frame
+        producer.publish_with(3, |buf| buf.fill(7)).unwrap();
+        let mut buf = Vec::new();
+        assert_eq!(reader.read_latest_into(&mut buf), Some(3));
+        assert_eq!(buf, [7, 7, 7]);
+
+        assert!(matches!(
+            producer.publish(&[0; 17]),
+            Err(IpcError::BufferTooSmall { .. })
+        ));
+        assert_eq!(reader.generation(), 3);
+
+        let waiter = std::thread::spawn(move || reader.wait_for(3, Duration::from_secs(5)));
+        std::thread::sleep(Duration::from_millis(20));
+        producer.publish(b"fourth").unwrap();
+        assert_eq!(waiter.join().unwrap().unwrap().data, b"fourth");
+    }
+
+    #[test]
+    fn test_double_buffer_frames_are_consistent() {
+        let name = format!("test_shmdb_torn_{}", std::process::id());
+        let producer = ShmDoubleBuffer::create_with_buffers(&name, 4096, 3).unwrap();
+        let reader = ShmDoubleBuffer::open(&name).unwrap();
+        assert_eq!(reader.buffers(), 3);
+        let done = Arc::new(AtomicBool::new(false));
+
+        let writer = std::thread::spawn({
+            let done = Arc::clone(&done);
+            move || {
+                for i in 1..=2000u64 {
+                    let len = 1024 + (i as usize % 3) * 1024;
+                    producer.publish_with(len, |buf| buf.fill(i as u8)).unwrap();
+                }
+                done.store(true, Ordering::SeqCst);
+            }
+        });
+
+        let mut last = 0;
+        let mut buf = Vec::new();
+        while !done.load(Ordering::SeqCst) {
+            let Some(generation) = reader.read_latest_into(&mut buf) else {
+                continue;
+            };
+            assert!(generation >= last);
+            last = generation;
+            assert_eq!(buf.len(), 1024 + (generation as usize % 3) * 1024);
+            assert!(buf.iter().all(|&b| b == generation as u8));
+        }
+        writer.join().unwrap();
+        assert_eq!(reader.latest().unwrap().generation, 2000);
+    }
+
+    #[test]
+    fn test_double_buffer_rejects_other_segments() {
+        let name = format!("test_shmdb_invalid_{}", std::process::id());
+        let _shm = SharedMemory::create(&name, 4096).unwrap();
+        assert!(matches!(
+            ShmDoubleBuffer::open(&name),
+            Err(IpcError::InvalidState(_))
+        ));
+        assert!(matches!(
+            ShmDoubleBuffer::create_with_buffers("unused", 16, 1),
+            Err(IpcError::InvalidState(_))
+        ));
+    }
+}
@@ -0,0 +1,805 @@
+//! Shared Memory Queue - Bounded cross-process work queue
+//!
+//! [`ShmQueue`] is a brokerless FIFO between producer and consumer
+//! processes. Items are serialized into fixed-size slots of a shared memory
+//! segment; a pair of named semaphores (POSIX semaphores on Unix, semaphore
+//! objects on Windows) counts free slots and queued items, so `push` blocks
+//! while the queue is full and `pop` blocks while it is empty, without
+//! polling.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::ShmQueue;
+//!
+//! // Producer process
+//! let queue = ShmQueue::<String>::create("jobs", 128, 4096).unwrap();
+//! queue.push(&"render frame 1".to_string()).unwrap();
+//!
+//! // Consumer process
+//! let queue = ShmQueue::<String>::open("jobs").unwrap();
+//! let job = queue.pop().unwrap();
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::shm::SharedMemory;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Magic number identifying a queue segment ("IPKQ")
+const QUEUE_MAGIC: u32 = 0x514B_5049;
+/// Queue layout version
+const QUEUE_VERSION: u32 = 1;
+/// Size of the queue header at the start of the segment
+const QUEUE_HEADER: usize = 64;
+/// Size of the length prefix stored in every slot
+const SLOT_HEADER: usize = 8;
+
+// Queue header field offsets
+const OFF_MAGIC: usize = 0;
+const OFF_VERSION: usize = 4;
+const OFF_LOCK: usize = 8;
+const OFF_CLOSED: usize = 12;
+const OFF_CAPACITY: usize = 16;
+const OFF_SLOT_SIZE: usize = 24;
+const OFF_HEAD: usize = 32;
+const OFF_TAIL: usize = 40;
+
+/// Bounded FIFO of serialized items in shared memory
+///
+/// Any number of processes may push and pop. Items are encoded as JSON and
+/// must fit in `slot_size` bytes. The head/tail indices are guarded by a
+/// spinlock in the segment header that is only held while copying one slot;
+/// as with [`ShmArena`](crate::ShmArena), a process that crashes while
+/// holding it leaves the queue locked.
+///
+/// On macOS semaphore names are limited to 31 bytes, so keep queue names
+/// short there.
+pub struct ShmQueue<T> {
+    shm: SharedMemory,
+    /// Counts queued items
+    items: Semaphore,
+    /// Counts free slots
+    slots: Semaphore,
+    capacity: usize,
+    slot_size: usize,
+    /// Bytes between the starts of consecutive slots
+    stride: usize,
+    _marker: PhantomData<fn(T) -> T>,
+}
+
+/// Releases the queue spinlock on drop
+struct QueueGuard<'a> {
+    lock: &'a AtomicU32,
+}
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.lock.store(0, Ordering::Release);
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> ShmQueue<T> {
+    /// Create a queue of `capacity` slots holding up to `slot_size` bytes each
+    pub fn create(name: &str, capacity: usize, slot_size: usize) -> Result<Self> {
+        if capacity == 0 || capacity > i32::MAX as usize {
+            return Err(IpcError::InvalidState(format!(
+                "queue capacity must be between 1 and {}",
+                i32::MAX
+            )));
+        }
+
+        let stride = slot_stride(slot_size);
+        let total = stride
+            .and_then(|n| n.checked_mul(capacity))
+            .and_then(|n| n.checked_add(QUEUE_HEADER));
+        let (Some(stride), Some(total)) = (stride, total) else {
+            return Err(IpcError::InvalidState("queue size overflows".to_string()));
+        };
+        let shm = SharedMemory::create(name, total)?;
+
+        // Creating the segment exclusively means we own the name, so any
+        // semaphores still around belong to a queue that crashed
+        let items = Semaphore::create(&semaphore_name(name, "items"), 0)?;
+        let slots = Semaphore::create(&semaphore_name(name, "slots"), capacity as u32)?;
+
+        let queue = Self {
+            shm,
+            items,
+            slots,
+            capacity,
+            slot_size,
+            stride,
+            _marker: PhantomData,
+        };
+        unsafe {
+            queue.set_u32(OFF_VERSION, QUEUE_VERSION);
+            queue.set_u32(OFF_CLOSED, 0);
+            queue.set_u64(OFF_CAPACITY, capacity as u64);
+            queue.set_u64(OFF_SLOT_SIZE, slot_size as u64);
+            queue.set_u64(OFF_HEAD, 0);
+            queue.set_u64(OFF_TAIL, 0);
+            // Publish the magic last so openers never see a half-initialized queue
+            queue.set_u32(OFF_MAGIC, QUEUE_MAGIC);
+        }
+
+        Ok(queue)
+    }
+
+    /// Open an existing queue created by another process
+    pub fn open(name: &str) -> Result<Self> {
+        let shm = SharedMemory::open(name)?;
+        let invalid =
+            || IpcError::InvalidState(format!("'{}' is not an ipckit queue segment", name));
+        if shm.size() < QUEUE_HEADER {
+            return Err(invalid());
+        }
+
+        let header_u32 = |offset: usize| unsafe {
+            std::ptr::read_volatile(shm.as_ptr().add(offset) as *const u32)
+        };
+        let header_u64 = |offset: usize| unsafe {
+            std::ptr::read_volatile(shm.as_ptr().add(offset) as *const u64)
+        };
+        if header_u32(OFF_MAGIC) != QUEUE_MAGIC || header_u32(OFF_VERSION) != QUEUE_VERSION {
+            return Err(invalid());
+        }
+        let capacity = header_u64(OFF_CAPACITY) as usize;
+        let slot_size = header_u64(OFF_SLOT_SIZE) as usize;
+        let stride = slot_stride(slot_size).ok_or_else(invalid)?;
+        let needed = stride
+            .checked_mul(capacity)
+            .and_then(|n| n.checked_add(QUEUE_HEADER));
+        if !(1..=i32::MAX as usize).contains(&capacity) || needed.is_none_or(|n| n > shm.size()) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            items: Semaphore::open(&semaphore_name(name, "items"))?,
+            slots: Semaphore::open(&semaphore_name(name, "slots"))?,
+            shm,
+            capacity,
+            slot_size,
+            stride,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Get the name of the underlying segment
+    pub fn name(&self) -> &str {
+        self.shm.name()
+    }
+
+    /// Get the number of slots
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the largest encoded item size a slot can hold
+    pub fn slot_size(&self) -> usize {
+        self.slot_size
+    }
+
+    /// Get the number of queued items
+    pub fn len(&self) -> usize {
+        let _guard = self.lock();
+        unsafe { (self.u64_at(OFF_TAIL) - self.u64_at(OFF_HEAD)) as usize }
+    }
+
+    /// Check if the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check if the queue has been closed
+    pub fn is_closed(&self) -> bool {
+        unsafe { self.u32_at(OFF_CLOSED) != 0 }
+    }
+
+    /// Push an item, blocking while the queue is full
+    ///
+    /// # Errors
+    ///
+    /// - `IpcError::BufferTooSmall` if the encoded item exceeds the slot size.
+    /// - `IpcError::Closed` if the queue has been closed.
+    pub fn push(&self, item: &T) -> Result<()> {
+        self.push_inner(item, None)
+    }
+
+    /// Push an item, waiting at most `timeout` for a free slot
+    ///
+    /// # Errors
+    ///
+    /// As [`push`](Self::push), plus `IpcError::Timeout` if no slot freed up.
+    pub fn push_timeout(&self, item: &T, timeout: Duration) -> Result<()> {
+        self.push_inner(item, Some(timeout))
+    }
+
+    /// Push an item without blocking
+    ///
+    /// # Errors
+    ///
+    /// As [`push`](Self::push), plus `IpcError::WouldBlock` if the queue is full.
+    pub fn try_push(&self, item: &T) -> Result<()> {
+        self.push_inner(item, Some(Duration::ZERO))
+            .map_err(would_block)
+    }
+
+    /// Pop the oldest item, blocking while the queue is empty
+    ///
+    /// Items pushed before the queue was closed are still delivered.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IpcError::Closed` once the queue is closed and drained.
+    pub fn pop(&self) -> Result<T> {
+        self.pop_inner(None)
+    }
+
+    /// Pop the oldest item, waiting at most `timeout` for one to arrive
+    ///
+    /// # Errors
+    ///
+    /// As [`pop`](Self::pop), plus `IpcError::Timeout` if nothing arrived.
+    pub fn pop_timeout(&self, timeout: Duration) -> Result<T> {
+        self.pop_inner(Some(timeout))
+    }
+
+    /// Pop the oldest item without blocking
+    ///
+    /// # Errors
+    ///
+    /// As [`pop`](Self::pop), plus `IpcError::WouldBlock` if the queue is empty.
+    pub fn try_pop(&self) -> Result<T> {
+        self.pop_inner(Some(Duration::ZERO)).map_err(would_block)
+    }
+
+    /// Close the queue for every process using it
+    ///
+    /// Blocked and future pushes fail with `IpcError::Closed`; pops drain
+    /// the remaining items first.
+    pub fn close(&self) {
+        unsafe { self.set_u32(OFF_CLOSED, 1) };
+        // Each woken waiter passes the wakeup on before returning
+        let _ = self.items.post();
+        let _ = self.slots.post();
+    }
+
+    fn push_inner(&self, item: &T, timeout: Option<Duration>) -> Result<()> {
+        let data = serde_json::to_vec(item).map_err(|e| IpcError::serialization(e.to_string()))?;
+        self.push_bytes(&data, timeout)
+    }
+
+    fn pop_inner(&self, timeout: Option<Duration>) -> Result<T> {
+        let data = self.pop_bytes(timeout)?;
+        serde_json::from_slice(&data).map_err(|e| IpcError::deserialization(e.to_string()))
+    }
+
+    /// Push an already encoded item (internal, used by the shm transport)
+    pub(crate) fn push_bytes(&self, data: &[u8], timeout: Option<Duration>) -> Result<()> {
+        if data.len() > self.slot_size {
+            return Err(IpcError::BufferTooSmall {
+                needed: data.len(),
+                got: self.slot_size,
+            });
+        }
+        if self.is_closed() {
+            return Err(IpcError::Closed);
+        }
+
+        self.slots.wait(timeout)?;
+        if self.is_closed() {
+            let _ = self.slots.post();
+            return Err(IpcError::Closed);
+        }
+
+        {
+            let _guard = self.lock();
+            unsafe {
+                let tail = self.u64_at(OFF_TAIL);
+                let slot = self.slot_offset(tail);
+                self.set_u64(slot, data.len() as u64);
+                std::ptr::copy_nonoverlapping(
+                    data.as_ptr(),
+                    self.base().add(slot + SLOT_HEADER),
+                    data.len(),
+                );
+                self.set_u64(OFF_TAIL, tail + 1);
+            }
+        }
+
+        self.items.post()
+    }
+
+    /// Pop the oldest item without decoding it (internal)
+    pub(crate) fn pop_bytes(&self, timeout: Option<Duration>) -> Result<Vec<u8>> {
+        self.items.wait(timeout)?;
+
+        let data = {
+            let guard = self.lock();
+            unsafe {
+                let head = self.u64_at(OFF_HEAD);
+                if head == self.u64_at(OFF_TAIL) {
+                    // Only the wakeup posted by `close` gets here
+                    drop(guard);
+                    let _ = self.items.post();
+                    return Err(IpcError::Closed);
+                }
+                let slot = self.slot_offset(head);
+                let len = (self.u64_at(slot) as usize).min(self.slot_size);
+                let mut data = vec![0u8; len];
+                std::ptr::copy_nonoverlapping(
+                    self.base().add(slot + SLOT_HEADER),
+                    data.as_mut_ptr(),
+                    len,
+                );
+                self.set_u64(OFF_HEAD, head + 1);
+                data
+            }
+        };
+
+        self.slots.post()?;
+        Ok(data)
+    }
+
+    fn slot_offset(&self, index: u64) -> usize {
+        QUEUE_HEADER + (index % self.capacity as u64) as usize * self.stride
+    }
+
+    fn lock(&self) -> QueueGuard<'_> {
+        let lock = unsafe { &*(self.base().add(OFF_LOCK) as *const AtomicU32) };
+        let mut spins = 0u32;
+        while lock
+            .compare_exchange_weak(0, 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spins += 1;
+            if spins < 64 {
+                std::hint::spin_loop();
+            } else {
+                std::thread::yield_now();
+            }
+        }
+        QueueGuard { lock }
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.shm.as_ptr() as *mut u8
+    }
+
+    unsafe fn u32_at(&self, offset: usize) -> u32 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u32)
+    }
+
+    unsafe fn set_u32(&self, offset: usize, value: u32) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u32, value)
+    }
+
+    unsafe fn u64_at(&self, offset: usize) -> u64 {
+        std::ptr::read_volatile(self.base().add(offset) as *const u64)
+    }
+
+    unsafe fn set_u64(&self, offset: usize, value: u64) {
+        std::ptr::write_volatile(self.base().add(offset) as *mut u64, value)
+    }
+}
+
+/// Bytes occupied by one slot, keeping every slot 8-byte aligned
+fn slot_stride(slot_size: usize) -> Option<usize> {
+    slot_size
+        .div_ceil(8)
+        .checked_mul(8)?
+        .checked_add(SLOT_HEADER)
+}
+
+fn would_block(err: IpcError) -> IpcError {
+    match err {
+        IpcError::Timeout => IpcError::WouldBlock,
+        other => other,
+    }
+}
+
+/// Name of one of a queue's semaphores, derived from the segment name
+fn semaphore_name(queue: &str, role: &str) -> String {
+    let base = queue.trim_start_matches('/').replace(['/', '\\'], "_");
+    if cfg!(windows) {
+        format!("{}.{}", base, role)
+    } else {
+        format!("/{}.{}", base, &role[..1])
+    }
+}
+
+#[cfg(unix)]
+use unix::Semaphore;
+#[cfg(windows)]
+use windows::Semaphore;
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use std::ffi::CString;
+
+    /// Named POSIX semaphore
+    pub struct Semaphore {
+        sem: *mut libc::sem_t,
+        name: CString,
+        is_owner: bool,
+    }
+
+    // Safety: POSIX semaphore operations are thread-safe
+    unsafe impl Send for Semaphore {}
+    unsafe impl Sync for Semaphore {}
+
+    impl Semaphore {
+        pub fn create(name: &str, initial: u32) -> Result<Self> {
+            let c_name = CString::new(name)
+                .map_err(|_| IpcError::InvalidName("Invalid semaphore name".into()))?;
+            unsafe { libc::sem_unlink(c_name.as_ptr()) };
+
+            let sem = unsafe {
+                libc::sem_open(
+                    c_name.as_ptr(),
+                    libc::O_CREAT | libc::O_EXCL,
+                    0o666 as libc::c_uint,
+                    initial as libc::c_uint,
+                )
+            };
+            if sem == libc::SEM_FAILED {
+                return Err(open_error(name));
+            }
+
+            Ok(Self {
+                sem,
+                name: c_name,
+                is_owner: true,
+            })
+        }
+
+        pub fn open(name: &str) -> Result<Self> {
+            let c_name = CString::new(name)
+                .map_err(|_| IpcError::InvalidName("Invalid semaphore name".into()))?;
+            let sem = unsafe { libc::sem_open(c_name.as_ptr(), 0) };
+            if sem == libc::SEM_FAILED {
+                return Err(open_error(name));
+            }
+
+            Ok(Self {
+                sem,
+                name: c_name,
+                is_owner: false,
+            })
+        }
+
+        pub fn post(&self) -> Result<()> {
+            if unsafe { libc::sem_post(self.sem) } < 0 {
+                return Err(IpcError::Io(std::io::Error::last_os_error()));
+            }
+            Ok(())
+        }
+
+        /// Decrement the count, waiting at most `timeout` (forever if `None`)
+        pub fn wait(&self, timeout: Option<Duration>) -> Result<()> {
+            let Some(timeout) = timeout else {
+                return retry_eintr(|| unsafe { libc::sem_wait(self.sem) });
+            };
+            if timeout.is_zero() {
+                return retry_eintr(|| unsafe { libc::sem_trywait(self.sem) });
+            }
+            self.wait_timeout(timeout)
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+        fn wait_timeout(&self, timeout: Duration) -> Result<()> {
+            let mut now: libc::timespec = unsafe { std::mem::zeroed() };
+            unsafe { libc::clock_gettime(libc::CLOCK_REALTIME, &mut now) };
+            let nanos = now.tv_nsec as u64 + timeout.subsec_nanos() as u64;
+            let deadline = libc::timespec {
+                tv_sec: now.tv_sec
+                    + timeout.as_secs().min(i32::MAX as u64) as libc::time_t
+                    + (nanos / 1_000_000_000) as libc::time_t,
+                tv_nsec: (nanos % 1_000_000_000) as _,
+            };
+            retry_eintr(|| unsafe { libc::sem_timedwait(self.sem, &deadline) })
+        }
+
+        /// Platforms without `sem_timedwait` (macOS) poll instead
+        #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+        fn wait_timeout(&self, timeout: Duration) -> Result<()> {
+            let deadline = std::time::Instant::now() + timeout;
+            loop {
+                match retry_eintr(|| unsafe { libc::sem_trywait(self.sem) }) {
+                    Err(IpcError::Timeout) if std::time::Instant::now() < deadline => {
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                    result => return result,
+                }
+            }
+        }
+    }
+
+    impl Drop for Semaphore {
+        fn drop(&mut self) {
+            unsafe {
+                libc::sem_close(self.sem);
+                if self.is_owner {
+                    libc::sem_unlink(self.name.as_ptr());
+                }
+            }
+        }
+    }
+
+    fn retry_eintr(mut op: impl FnMut() -> libc::c_int) -> Result<()> {
+        loop {
+            if op() == 0 {
+                return Ok(());
+            }
+            let err = std::io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                Some(libc::EAGAIN) | Some(libc::ETIMEDOUT) => return Err(IpcError::Timeout),
+                _ => return Err(IpcError::Io(err)),
+            }
+        }
+    }
+
+    fn open_error(name: &str) -> IpcError {
+        let err = std::io::Error::last_os_error();
+        match err.kind() {
+            std::io::ErrorKind::NotFound => IpcError::NotFound(name.to_string()),
+            std::io::ErrorKind::AlreadyExists => IpcError::AlreadyExists(name.to_string()),
+            std::io::ErrorKind::PermissionDenied => IpcError::PermissionDenied(name.to_string()),
+            _ => IpcError::Io(err),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use windows_sys::Win32::Foundation::*;
+    use windows_sys::Win32::System::Threading::*;
+
+    /// Named Windows semaphore object
+    pub struct Semaphore {
+        handle: HANDLE,
+    }
+
+    // Safety: semaphore handles may be used from any thread
+    unsafe impl Send for Semaphore {}
+    unsafe impl Sync for Semaphore {}
+
+    fn to_wide(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(Some(0)).collect()
+    }
+
+    impl Semaphore {
+        pub fn create(name: &str, initial: u32) -> Result<Self> {
+            let wide_name = to_wide(name);
+            let handle = unsafe {
+                CreateSemaphoreW(ptr::null(), initial as i32, i32::MAX, wide_name.as_ptr())
+            };
+            if handle.is_null() {
+                return Err(IpcError::Io(std::io::Error::last_os_error()));
+            }
+            if unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+                unsafe { CloseHandle(handle) };
+                return Err(IpcError::AlreadyExists(name.to_string()));
+            }
+            Ok(Self { handle })
+        }
+
+        pub fn open(name: &str) -> Result<Self> {
+            let wide_name = to_wide(name);
+            let handle = unsafe { OpenSemaphoreW(SEMAPHORE_ALL_ACCESS, 0, wide_name.as_ptr()) };
+            if handle.is_null() {
+                let err = std::io::Error::last_os_error();
+                return Err(match err.raw_os_error() {
+                    Some(2) => IpcError::NotFound(name.to_string()),
+                    Some(5) => IpcError::PermissionDenied(name.to_string()),
+                    _ => IpcError::Io(err),
+                });
+            }
+            Ok(Self { handle })
+        }
+
+        pub fn post(&self) -> Result<()> {
+            if unsafe { ReleaseSemaphore(self.handle, 1, ptr::null_mut()) } == 0 {
+                return Err(IpcError::Io(std::io::Error::last_os_error()));
+            }
+            Ok(())
+        }
+
+        /// Decrement the count, waiting at most `timeout` (forever if `None`)
+        pub fn wait(&self, timeout: Option<Duration>) -> Result<()> {
+            let millis = timeout
+                .map(|t| t.as_millis().min(INFINITE as u128 - 1) as u32)
+                .unwrap_or(INFINITE);
+            match unsafe { WaitForSingleObject(self.handle, millis) } {
+                WAIT_OBJECT_0 => Ok(()),
+                WAIT_TIMEOUT => Err(IpcError::Timeout),
+                _ => Err(IpcError::Io(std::io::Error::last_os_error())),
+            }
+        }
+    }
+
+    impl Drop for Semaphore {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.handle) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shm_queue_push_pop() {
+        let name = format!("test_shmq_{}", std::process::id());
+        let producer = ShmQueue::<Vec<u32>>::create(&name, 2, 64).unwrap();
+        let consumer = ShmQueue::<Vec<u32>>::open(&name).unwrap();
+        assert_eq!(consumer.capacity(), 2);
+        assert_eq!(consumer.slot_size(), 64);
+
+        producer.push(&vec![1, 2, 3]).unwrap();
+        producer.push(&vec![4]).unwrap();
+        assert_eq!(consumer.len(), 2);
+        assert!(matches!(
+            producer.try_push(&vec![5]),
+            Err(IpcError::WouldBlock)
+        ));
+        assert!(matches!(
+            producer.push(&vec![0; 64]),
+            Err(IpcError::BufferTooSmall { .. })
+        ));
+
+        assert_eq!(consumer.pop().unwrap(), vec![1, 2, 3]);
+        producer
+            .push_timeout(&vec![5], Duration::from_millis(100))
+            .unwrap();
+
+        // A blocked pop is woken by a push from another thread
+        assert_eq!(consumer.pop().unwrap(), vec![4]);
+        assert_eq!(consumer.pop().unwrap(), vec![5]);
+        let pusher = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            producer.push(&vec![6]).unwrap();
+            producer
+        });
+        assert_eq!(consumer.pop().unwrap(), vec![6]);
+        let producer = pusher.join().unwrap();
+
+        assert!(matches!(
+            consumer.pop_timeout(Duration::from_millis(10)),
+            Err(IpcError::Timeout)
+        ));
+
+        // Close drains what is left, then reports Closed everywhere
+        producer.push(&vec![7]).unwrap();
+        producer.close();
+        assert!(matches!(producer.push(&vec![8]), Err(IpcError::Closed)));
+        assert_eq!(consumer.pop().unwrap(), vec![7]);
+        assert!(matches!(consumer.pop(), Err(IpcError::Closed)));
+        assert!(matches!(consumer.try_pop(), Err(IpcError::Closed)));
+    }
+
+    #[test]
+    fn test_shm_queue_open_rejects_bad_header() {
+        let name = format!("test_shmq_hdr_{}", std::process::id());
+        let queue = ShmQueue::<u32>::create(&name, 4, 16).unwrap();
+
+        unsafe { queue.set_u64(OFF_CAPACITY, 0) };
+        assert!(matches!(
+            ShmQueue::<u32>::open(&name),
+            Err(IpcError::InvalidState(_))
+        ));
+
+        unsafe {
+            queue.set_u64(OFF_CAPACITY, 4);
+            queue.set_u64(OFF_SLOT_SIZE, u64::MAX - 3);
+        }
+        assert!(matches!(
+            ShmQueue::<u32>::open(&name),
+            Err(IpcError::InvalidState(_))
+        ));
+
+        assert!(matches!(
+            ShmQueue::<u32>::create(&format!("{}_big", name), 2, usize::MAX - 3),
+            Err(IpcError::InvalidState(_))
+        ));
+    }
+
+    #[test]
+    fn test_shm_queue_many_producers_consumers() {
+        let name = format!("test_shmq_mpmc_{}", std::process::id());
+        let queue = ShmQueue::<u32>::create(&name, 4, 16).unwrap();
+
+        let producers: Vec<_> = (0..4u32)
+            .map(|p| {
+                let name = name.clone();
+                std::thread::spawn(move || {
+                    let queue = ShmQueue::<u32>::open(&name).unwrap();
+                    for i in 0..50 {
+                        queue.push(&(p * 1000 + i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        let consumers: Vec<_> = (0..3)
+            .map(|_| {
+                let name = name.clone();
+                std::thread::spawn(move || {
+                    let queue = ShmQueue::<u32>::open(&name).unwrap();
+                    let mut seen = Vec::new();
+                    while let Ok(item) = queue.pop() {
+                        seen.push(item);
+                    }
+                    seen
+                })
+            })
+            .collect();
+
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        queue.close();
+
+        let mut seen: Vec<u32> = consumers
+            .into_iter()
+            .flat_map(|c| c.join().unwrap())
+            .collect();
+        seen.sort_unstable();
+        let mut expected: Vec<u32> = (0..4u32)
+            .flat_map(|p| (0..50).map(move |i| p * 1000 + i))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_shm_queue_full_push_blocks() {
+        let name = format!("test_shmq_full_{}", std::process::id());
+        let producer = ShmQueue::<u32>::create(&name, 1, 16).unwrap();
+        let consumer = ShmQueue::<u32>::open(&name).unwrap();
+        producer.push(&1).unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let pusher = std::thread::spawn(move || {
+            producer.push(&2).unwrap();
+            tx.send(()).unwrap();
+            producer
+        });
+
+        // The push stays blocked until a slot frees up
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        assert_eq!(consumer.pop().unwrap(), 1);
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let _producer = pusher.join().unwrap();
+        assert_eq!(consumer.pop().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_shm_queue_close_wakes_blocked_pop() {
+        let name = format!("test_shmq_close_{}", std::process::id());
+        let producer = ShmQueue::<u32>::create(&name, 2, 16).unwrap();
+
+        let poppers: Vec<_> = (0..2)
+            .map(|_| {
+                let name = name.clone();
+                std::thread::spawn(move || ShmQueue::<u32>::open(&name).unwrap().pop())
+            })
+            .collect();
+
+        std::thread::sleep(Duration::from_millis(50));
+        producer.close();
+        for popper in poppers {
+            assert!(matches!(popper.join().unwrap(), Err(IpcError::Closed)));
+        }
+    }
+}
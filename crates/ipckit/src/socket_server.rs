@@ -10,6 +10,12 @@
 //! - Multiple client connections
 //! - Connection lifecycle management
 //! - Integration with existing IPC modules
+//! - Message taps for debug logging and live traffic inspection
+//! - Server-side pub/sub: push messages to every connection subscribed to a topic
+//! - Capability handshake and protocol version negotiation
+//! - Batched sends and receives for high-rate streams of small messages
+//! - Typed per-connection state for stateful protocols
+//! - Frame hooks for encrypting or checksumming messages on the wire
 //!
 //! # Example
 //!
@@ -32,15 +38,126 @@
 //!     }
 //! }
 //! ```
+//!
+//! # Broadcasting to subscribed clients
+//!
+//! ```rust,no_run
+//! use ipckit::{FnHandler, Message, SocketServer};
+//!
+//! let server = SocketServer::at("/tmp/my_app.sock").unwrap();
+//! let broadcaster = server.broadcaster();
+//!
+//! let topics = broadcaster.clone();
+//! let _server = server.spawn(FnHandler::new(move |conn, msg| {
+//!     if msg.method() == Some("subscribe") {
+//!         topics.subscribe(conn.id(), "tasks")?;
+//!     }
+//!     Ok(Some(Message::response(serde_json::json!({"ok": true}))))
+//! }));
+//!
+//! // Later, from any thread
+//! broadcaster.broadcast("tasks", &Message::json(serde_json::json!({"task": "done"})));
+//! ```
+//!
+//! # Per-connection state
+//!
+//! Handlers keep protocol state such as the authenticated user on the
+//! [`Connection`] itself, keyed by type, instead of in a map keyed by
+//! [`ConnectionId`]. The state returned from
+//! [`ConnectionHandler::on_connect`] is installed before the first message
+//! and dropped with the connection.
+//!
+//! ```rust,no_run
+//! use ipckit::{Connection, ConnectionHandler, Extensions, IpcError, Message, Result};
+//!
+//! struct Session {
+//!     user: Option<String>,
+//! }
+//!
+//! #[derive(Clone)]
+//! struct Auth;
+//!
+//! impl ConnectionHandler for Auth {
+//!     fn on_connect(&self, _conn: &mut Connection) -> Result<Extensions> {
+//!         let mut state = Extensions::new();
+//!         state.insert(Session { user: None });
+//!         Ok(state)
+//!     }
+//!
+//!     fn on_message(&self, conn: &mut Connection, msg: Message) -> Result<Option<Message>> {
+//!         let session = conn.state_mut::<Session>().unwrap();
+//!         if msg.method() == Some("login") {
+//!             session.user = msg.params().and_then(|p| p.as_str()).map(String::from);
+//!             return Ok(Some(Message::response(serde_json::json!({"ok": true}))));
+//!         }
+//!         match &session.user {
+//!             Some(user) => Ok(Some(Message::response(serde_json::json!({"user": user})))),
+//!             None => Err(IpcError::PermissionDenied("login first".into())),
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! # Capability handshake
+//!
+//! A client can open with a [`HANDSHAKE_METHOD`] request advertising its
+//! [`Capabilities`]: protocol and library version, codecs, compression and
+//! maximum frame size. The server answers with its own, both sides check the
+//! other against their [`Handshake`] requirements, and the agreed settings
+//! end up in [`ConnectionMetadata::handshake`]. A peer that is missing a
+//! required capability is rejected with [`IpcError::Incompatible`] instead
+//! of failing later with a decoding error.
+//!
+//! ```rust,no_run
+//! use ipckit::{Handshake, SocketClient};
+//!
+//! let handshake = Handshake::new().min_protocol_version(1);
+//! let client = SocketClient::connect_with_handshake("/tmp/my_app.sock", &handshake).unwrap();
+//! let info = client.handshake_info().unwrap();
+//! println!("server runs ipckit {}", info.peer.library_version);
+//! ```
+//!
+//! Servers answer handshakes automatically and, unless
+//! [`Handshake::require_handshake`] is set, keep serving older clients that
+//! never send one.
+//!
+//! # Live traffic inspection
+//!
+//! With [`SocketServerConfig::allow_attach`] enabled, a client that sends an
+//! [`ATTACH_METHOD`] request stops being a regular peer and instead receives
+//! a [`TapRecord`] for every message any other connection sends or receives.
+//! This is what `ipckit listen --attach <socket>` uses.
+//!
+//! ```rust,no_run
+//! use ipckit::{SocketClient, TapRecord};
+//!
+//! let mut client = SocketClient::connect("/tmp/my_app.sock").unwrap();
+//! client.attach().unwrap();
+//! while let Ok(msg) = client.recv() {
+//!     if let Some(record) = TapRecord::from_message(&msg) {
+//!         println!("[{}] {} {:?}", record.connection, record.direction.arrow(), record.message);
+//!     }
+//! }
+//! ```
 
-use crate::error::{IpcError, Result};
+use crate::access_log::LoggingMiddleware;
+use crate::api_server::Extensions;
+use crate::channel::{FrameHook, FrameHooks, TapDirection};
+use crate::error::{ErrorCode, IpcError, Result};
 use crate::graceful::{GracefulChannel, ShutdownState};
-use crate::local_socket::{LocalSocketListener, LocalSocketStream};
-use parking_lot::RwLock;
+use crate::metrics::MetricsRegistry;
+use crate::trace_context::TraceContext;
+use crate::transport::{
+    LocalSocketTransport, TcpTransport, Transport, TransportListener, TransportStream,
+};
+use crossbeam_channel::{RecvTimeoutError, Sender, TrySendError};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::io::{Read, Write};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::time::{Duration, SystemTime};
@@ -48,6 +165,56 @@ use std::time::{Duration, SystemTime};
 /// Unique connection identifier.
 pub type ConnectionId = u64;
 
+/// Method of the request that turns a connection into a traffic observer.
+pub const ATTACH_METHOD: &str = "ipckit.attach";
+
+/// Number of tap records buffered per attached observer before dropping.
+const ATTACH_BUFFER: usize = 1024;
+
+/// How often an idle attached observer is checked for disconnection.
+const ATTACH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Largest frame a connection accepts unless configured otherwise.
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Method of the request a client opens with to exchange [`Capabilities`].
+pub const HANDSHAKE_METHOD: &str = "ipckit.hello";
+
+/// Version of the framing and message protocol spoken by this build.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Gauge in the global [`MetricsRegistry`] counting connections currently
+/// served by [`SocketServer::run`] in this process.
+///
+/// [`MetricsRegistry`]: crate::metrics::MetricsRegistry
+pub const CONNECTIONS_GAUGE: &str = "socket_connections";
+
+/// How long a client waits for the server's handshake reply.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Observer invoked with every message sent or received on a connection.
+pub type ConnectionTap = Arc<dyn Fn(TapDirection, &Message) + Send + Sync>;
+
+/// Check run on an incoming frame while it is still arriving.
+///
+/// Called with the frame's declared length and the part received so far; see
+/// [`Connection::set_frame_check`].
+pub type FrameCheck = Arc<dyn Fn(usize, &[u8]) -> FrameVerdict + Send + Sync>;
+
+/// Decision of a [`FrameCheck`] about a partly received frame.
+#[derive(Debug, Clone)]
+pub enum FrameVerdict {
+    /// Too little of the frame has arrived to decide
+    NeedMore,
+    /// Receive the frame as usual
+    Accept,
+    /// Send the reply right away and drop the frame as the rest arrives
+    Reject(Message),
+}
+
+/// Observer invoked with every message seen by any server connection.
+type ServerTap = Arc<dyn Fn(ConnectionId, TapDirection, &Message) + Send + Sync>;
+
 /// Socket server configuration.
 #[derive(Debug, Clone)]
 pub struct SocketServerConfig {
@@ -61,6 +228,18 @@ pub struct SocketServerConfig {
     pub cleanup_on_start: bool,
     /// Read buffer size
     pub buffer_size: usize,
+    /// Allow clients to attach as live traffic observers (see [`ATTACH_METHOD`])
+    pub allow_attach: bool,
+    /// Capabilities advertised and required during the connection handshake
+    pub handshake: Handshake,
+    /// Trace connections and messages in `socket.*` spans (disabled by default)
+    pub access_log: Option<LoggingMiddleware>,
+    /// Largest frame accepted from a client, in bytes (default: 16 MiB)
+    pub max_frame_size: usize,
+    /// Backend the server listens on (default: [`LocalSocketTransport`])
+    pub transport: Arc<dyn Transport>,
+    /// Frame transforms installed on every accepted connection
+    pub frame_hooks: FrameHooks,
 }
 
 impl Default for SocketServerConfig {
@@ -71,6 +250,12 @@ impl Default for SocketServerConfig {
             connection_timeout: Duration::from_secs(30),
             cleanup_on_start: true,
             buffer_size: 8192,
+            allow_attach: false,
+            handshake: Handshake::default(),
+            access_log: None,
+            max_frame_size: MAX_FRAME_SIZE,
+            transport: Arc::new(LocalSocketTransport),
+            frame_hooks: FrameHooks::default(),
         }
     }
 }
@@ -83,6 +268,42 @@ impl SocketServerConfig {
             ..Default::default()
         }
     }
+
+    /// Create a new configuration listening on `listen`.
+    ///
+    /// Sets both [`path`](Self::path) and [`transport`](Self::transport).
+    pub fn listen(listen: Listen) -> Self {
+        let (path, transport): (String, Arc<dyn Transport>) = match listen {
+            Listen::Local(path) => (path, Arc::new(LocalSocketTransport)),
+            Listen::Tcp(addr) => (addr, Arc::new(TcpTransport::new().allow_remote(true))),
+            #[cfg(feature = "tls")]
+            Listen::Tls(addr, tls) => (
+                addr,
+                Arc::new(TcpTransport::new().allow_remote(true).with_tls(tls)),
+            ),
+        };
+        Self {
+            path,
+            transport,
+            ..Default::default()
+        }
+    }
+}
+
+/// Where a [`SocketServer`] listens, see [`SocketServerConfig::listen`].
+#[derive(Debug, Clone)]
+pub enum Listen {
+    /// Socket path (Unix) or pipe name (Windows)
+    Local(String),
+    /// TCP address, e.g. `127.0.0.1:7878`
+    ///
+    /// Any address is accepted: binding a non-loopback one exposes the
+    /// server to every client that can reach it, so only do that on a
+    /// trusted network.
+    Tcp(String),
+    /// TCP address with TLS, for clients outside the trusted network
+    #[cfg(feature = "tls")]
+    Tls(String, crate::tls::TlsConfig),
 }
 
 /// Get the default socket path for the current platform.
@@ -108,6 +329,9 @@ pub struct ConnectionMetadata {
     pub client_pid: Option<u32>,
     /// Client info string
     pub client_info: Option<String>,
+    /// Result of the capability handshake, if the peer performed one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handshake: Option<HandshakeInfo>,
 }
 
 mod system_time_serde {
@@ -137,7 +361,250 @@ impl Default for ConnectionMetadata {
             connected_at: SystemTime::now(),
             client_pid: None,
             client_info: None,
+            handshake: None,
+        }
+    }
+}
+
+/// A live server connection, as listed by [`SocketServer::connections`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionInfo {
+    /// Connection ID
+    pub id: ConnectionId,
+    /// Connection metadata
+    #[serde(flatten)]
+    pub metadata: ConnectionMetadata,
+    /// Messages received from the client so far
+    pub messages_received: u64,
+    /// Whether [`disconnect`](ConnectionRegistry::disconnect) was requested
+    pub disconnecting: bool,
+}
+
+/// What one side of a connection supports, exchanged during the handshake.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Protocol version (see [`PROTOCOL_VERSION`])
+    pub protocol_version: u32,
+    /// ipckit version of the peer
+    pub library_version: String,
+    /// Payload codecs the peer can decode
+    ///
+    /// Frames are always JSON, so this build advertises only `"json"`; add
+    /// codecs that [`FrameHooks`] installed on both sides apply.
+    #[serde(default)]
+    pub codecs: Vec<String>,
+    /// Compression algorithms the peer can decode (none by default, see
+    /// `codecs`)
+    #[serde(default)]
+    pub compression: Vec<String>,
+    /// Largest frame the peer accepts, in bytes
+    pub max_frame_size: usize,
+    /// Who the peer claims to be, see [`Handshake::identity`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    /// Secret proving `identity`; cleared once the server has checked it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl Default for Capabilities {
+    /// The capabilities of this build.
+    fn default() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            library_version: env!("CARGO_PKG_VERSION").to_string(),
+            codecs: vec!["json".to_string()],
+            compression: Vec::new(),
+            max_frame_size: MAX_FRAME_SIZE,
+            identity: None,
+            token: None,
+        }
+    }
+}
+
+/// Settings agreed on by both sides of a connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandshakeInfo {
+    /// What the peer advertised
+    pub peer: Capabilities,
+    /// Protocol version both sides speak
+    pub protocol_version: u32,
+    /// Codecs both sides support
+    pub codecs: Vec<String>,
+    /// Compression algorithms both sides support
+    pub compression: Vec<String>,
+    /// Largest frame either side may send
+    pub max_frame_size: usize,
+    /// Identity the peer proved with a token registered through
+    /// [`Handshake::credential`]; `None` for anonymous peers, including ones
+    /// that claimed an identity the server had no credentials to check (the
+    /// claim stays in `peer.identity`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+}
+
+/// Capabilities to advertise and requirements to enforce in a handshake.
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    capabilities: Capabilities,
+    min_protocol_version: u32,
+    required_codecs: Vec<String>,
+    required_compression: Vec<String>,
+    require_handshake: bool,
+    timeout: Duration,
+    /// Tokens accepted for each identity, by identity
+    credentials: HashMap<String, String>,
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Self {
+            capabilities: Capabilities::default(),
+            min_protocol_version: PROTOCOL_VERSION,
+            required_codecs: Vec::new(),
+            required_compression: Vec::new(),
+            require_handshake: false,
+            timeout: HANDSHAKE_TIMEOUT,
+            credentials: HashMap::new(),
+        }
+    }
+}
+
+impl Handshake {
+    /// Advertise this build's capabilities with no extra requirements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advertise different capabilities.
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Reject peers speaking an older protocol version.
+    pub fn min_protocol_version(mut self, version: u32) -> Self {
+        self.min_protocol_version = version;
+        self
+    }
+
+    /// Reject peers that cannot decode `codec`.
+    pub fn require_codec(mut self, codec: &str) -> Self {
+        self.required_codecs.push(codec.to_string());
+        self
+    }
+
+    /// Reject peers that do not support `compression`.
+    pub fn require_compression(mut self, compression: &str) -> Self {
+        self.required_compression.push(compression.to_string());
+        self
+    }
+
+    /// On a server, reject clients whose first message is not a handshake.
+    pub fn require_handshake(mut self, required: bool) -> Self {
+        self.require_handshake = required;
+        self
+    }
+
+    /// How long a client waits for the server's reply (default: 5 seconds).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// On a client, identify as `identity`, proven by `token`.
+    ///
+    /// Servers use the identity to authorize the connection, e.g. with an
+    /// [`EventAcl`](crate::event_bridge::EventAcl).
+    pub fn identity(mut self, identity: &str, token: &str) -> Self {
+        self.capabilities.identity = Some(identity.to_string());
+        self.capabilities.token = Some(token.to_string());
+        self
+    }
+
+    /// On a server, accept clients identifying as `identity` with `token`.
+    ///
+    /// Once any credential is registered, clients claiming an identity must
+    /// present its token or are rejected with [`IpcError::PermissionDenied`].
+    /// Without credentials, claimed identities are not proven, and clients
+    /// are treated as anonymous.
+    pub fn credential(mut self, identity: &str, token: &str) -> Self {
+        self.credentials
+            .insert(identity.to_string(), token.to_string());
+        self
+    }
+
+    /// Get the advertised capabilities.
+    pub fn local_capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Check a peer's capabilities and work out the agreed settings.
+    pub fn negotiate(&self, mut peer: Capabilities) -> Result<HandshakeInfo> {
+        if peer.protocol_version < self.min_protocol_version {
+            return Err(IpcError::Incompatible(format!(
+                "peer speaks protocol v{} (ipckit {}), need at least v{}",
+                peer.protocol_version, peer.library_version, self.min_protocol_version
+            )));
         }
+        let missing = |required: &[String], offered: &[String]| -> Vec<String> {
+            required
+                .iter()
+                .filter(|r| !offered.contains(r))
+                .cloned()
+                .collect()
+        };
+        let codecs = missing(&self.required_codecs, &peer.codecs);
+        if !codecs.is_empty() {
+            return Err(IpcError::Incompatible(format!(
+                "peer (ipckit {}) does not support codec(s): {}",
+                peer.library_version,
+                codecs.join(", ")
+            )));
+        }
+        let compression = missing(&self.required_compression, &peer.compression);
+        if !compression.is_empty() {
+            return Err(IpcError::Incompatible(format!(
+                "peer (ipckit {}) does not support compression: {}",
+                peer.library_version,
+                compression.join(", ")
+            )));
+        }
+
+        // Only an identity proven by a registered token is kept; without
+        // credentials, a claimed identity leaves the connection anonymous
+        let token = peer.token.take();
+        let identity = match peer.identity {
+            Some(ref identity) if !self.credentials.is_empty() => {
+                if self.credentials.get(identity).map(String::as_str) != token.as_deref() {
+                    return Err(IpcError::PermissionDenied(format!(
+                        "invalid credentials for identity '{}'",
+                        identity
+                    )));
+                }
+                Some(identity.clone())
+            }
+            _ => None,
+        };
+
+        let shared = |local: &[String], offered: &[String]| -> Vec<String> {
+            local
+                .iter()
+                .filter(|c| offered.contains(c))
+                .cloned()
+                .collect()
+        };
+        Ok(HandshakeInfo {
+            protocol_version: self
+                .capabilities
+                .protocol_version
+                .min(peer.protocol_version),
+            codecs: shared(&self.capabilities.codecs, &peer.codecs),
+            compression: shared(&self.capabilities.compression, &peer.compression),
+            max_frame_size: self.capabilities.max_frame_size.min(peer.max_frame_size),
+            identity,
+            peer,
+        })
     }
 }
 
@@ -148,6 +615,9 @@ pub struct Message {
     pub msg_type: MessageType,
     /// Message payload
     pub payload: serde_json::Value,
+    /// Trace the message was sent in, set from the sender's current context
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceContext>,
 }
 
 /// Message type enumeration.
@@ -171,76 +641,92 @@ pub enum MessageType {
 }
 
 impl Message {
-    /// Create a text message.
-    pub fn text(content: &str) -> Self {
+    /// Create a message stamped with the current [`TraceContext`].
+    fn new(msg_type: MessageType, payload: serde_json::Value) -> Self {
         Self {
-            msg_type: MessageType::Text,
-            payload: serde_json::json!({ "content": content }),
+            msg_type,
+            payload,
+            trace: TraceContext::current(),
         }
     }
 
+    /// Create a text message.
+    pub fn text(content: &str) -> Self {
+        Self::new(MessageType::Text, serde_json::json!({ "content": content }))
+    }
+
     /// Create a request message.
     pub fn request(method: &str, params: serde_json::Value) -> Self {
-        Self {
-            msg_type: MessageType::Request,
-            payload: serde_json::json!({
+        Self::new(
+            MessageType::Request,
+            serde_json::json!({
                 "method": method,
                 "params": params
             }),
-        }
+        )
     }
 
     /// Create a response message.
     pub fn response(result: serde_json::Value) -> Self {
-        Self {
-            msg_type: MessageType::Response,
-            payload: serde_json::json!({ "result": result }),
-        }
+        Self::new(
+            MessageType::Response,
+            serde_json::json!({ "result": result }),
+        )
     }
 
     /// Create an error message.
     pub fn error(code: i32, message: &str) -> Self {
-        Self {
-            msg_type: MessageType::Error,
-            payload: serde_json::json!({
+        Self::new(
+            MessageType::Error,
+            serde_json::json!({
                 "code": code,
                 "message": message
             }),
+        )
+    }
+
+    /// Create an error message from an [`IpcError`].
+    ///
+    /// The payload is the error's wire form: numeric `code`, string `kind`,
+    /// `message` and `retryable`.
+    pub fn from_error(err: &IpcError) -> Self {
+        Self::new(MessageType::Error, err.to_json())
+    }
+
+    /// Get the error code of an error message, if it carries a known one.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        if self.msg_type != MessageType::Error {
+            return None;
         }
+        self.payload
+            .get("code")
+            .and_then(|v| v.as_i64())
+            .and_then(|code| ErrorCode::from_i32(code as i32))
     }
 
     /// Create a ping message.
     pub fn ping() -> Self {
-        Self {
-            msg_type: MessageType::Ping,
-            payload: serde_json::json!({}),
-        }
+        Self::new(MessageType::Ping, serde_json::json!({}))
     }
 
     /// Create a pong message.
     pub fn pong() -> Self {
-        Self {
-            msg_type: MessageType::Pong,
-            payload: serde_json::json!({}),
-        }
+        Self::new(MessageType::Pong, serde_json::json!({}))
     }
 
     /// Create a JSON message.
     pub fn json(value: serde_json::Value) -> Self {
-        Self {
-            msg_type: MessageType::Text,
-            payload: value,
-        }
+        Self::new(MessageType::Text, value)
     }
 
     /// Create a binary message from raw bytes.
     pub fn binary(data: Vec<u8>) -> Self {
-        Self {
-            msg_type: MessageType::Binary,
-            payload: serde_json::json!({
+        Self::new(
+            MessageType::Binary,
+            serde_json::json!({
                 "data": base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &data)
             }),
-        }
+        )
     }
 
     /// Get the binary data (for binary messages).
@@ -274,30 +760,180 @@ impl Message {
     }
 }
 
+/// A message observed by a server-side tap, as delivered to attached clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TapRecord {
+    /// Connection the message was seen on
+    pub connection: ConnectionId,
+    /// Whether the server sent or received the message
+    pub direction: TapDirection,
+    /// The observed message
+    pub message: Message,
+}
+
+impl TapRecord {
+    /// Decode a tap record from a message received by an attached client.
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        serde_json::from_value(msg.payload.clone()).ok()
+    }
+
+    /// Encode the record as a message.
+    pub fn to_message(&self) -> Message {
+        Message::json(serde_json::to_value(self).unwrap_or_default())
+    }
+}
+
+/// Write handle to a connection, shared with the [`Broadcaster`]
+type SharedStream = Arc<Mutex<Box<dyn TransportStream>>>;
+
+/// Frame transforms of a connection, shared with the [`Broadcaster`]
+type SharedHooks = Arc<RwLock<FrameHooks>>;
+
 /// A single client connection.
 pub struct Connection {
     id: ConnectionId,
-    stream: LocalSocketStream,
+    stream: Box<dyn TransportStream>,
     metadata: ConnectionMetadata,
     buffer: Vec<u8>,
+    /// Bytes at the front of `buffer` belonging to the last frame handed out
+    consumed: usize,
+    max_frame_size: usize,
+    tap: Option<ConnectionTap>,
+    hooks: SharedHooks,
+    check: Option<FrameCheck>,
+    /// Whether `check` has decided on the partly received frame
+    checked: bool,
+    /// Bytes of a rejected frame still to be dropped as they arrive
+    discarding: usize,
+    /// Write handle shared with the server's [`Broadcaster`], if registered
+    writer: Option<SharedStream>,
+    /// Entry in the server's [`ConnectionRegistry`], if accepted by a server
+    registration: Option<Registration>,
+    /// Handler state, see [`state`](Connection::state)
+    state: Extensions,
 }
 
 impl Connection {
     /// Create a new connection.
-    fn new(id: ConnectionId, stream: LocalSocketStream) -> Self {
+    fn new(id: ConnectionId, stream: Box<dyn TransportStream>) -> Self {
         Self {
             id,
             stream,
             metadata: ConnectionMetadata::default(),
             buffer: Vec::with_capacity(8192),
+            consumed: 0,
+            max_frame_size: MAX_FRAME_SIZE,
+            tap: None,
+            hooks: SharedHooks::default(),
+            check: None,
+            checked: false,
+            discarding: 0,
+            writer: None,
+            registration: None,
+            state: Extensions::new(),
         }
     }
 
+    /// Observe every message sent or received on this connection.
+    ///
+    /// Replaces any previously set tap. Connections without a tap pay
+    /// nothing beyond an `Option` check.
+    pub fn tap<F>(&mut self, tap: F)
+    where
+        F: Fn(TapDirection, &Message) + Send + Sync + 'static,
+    {
+        self.tap = Some(Arc::new(tap));
+    }
+
+    /// Remove the tap.
+    pub fn clear_tap(&mut self) {
+        self.tap = None;
+    }
+
+    /// Transform every outgoing message before it is framed and written.
+    ///
+    /// Applies to broadcasts to this connection as well. The peer must undo
+    /// it with a matching [`on_after_recv`](Self::on_after_recv). Replaces
+    /// any previously set outgoing transform.
+    pub fn on_before_send<F>(&mut self, hook: F)
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.hooks.write().before_send = Some(Arc::new(hook));
+    }
+
+    /// Transform every incoming frame before it is decoded.
+    ///
+    /// [`recv_borrowed`](Self::recv_borrowed) returns the transformed bytes.
+    /// A failing hook fails the receive and drops the frame. Replaces any
+    /// previously set incoming transform.
+    pub fn on_after_recv<F>(&mut self, hook: F)
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.hooks.write().after_recv = Some(Arc::new(hook));
+    }
+
+    /// Get the frame transforms.
+    pub fn frame_hooks(&self) -> FrameHooks {
+        self.hooks.read().clone()
+    }
+
+    /// Replace both frame transforms.
+    pub fn set_frame_hooks(&mut self, hooks: FrameHooks) {
+        *self.hooks.write() = hooks;
+    }
+
+    /// Check incoming frames before they have fully arrived.
+    ///
+    /// `check` sees each frame that arrives in pieces, as received and
+    /// before any [`on_after_recv`](Self::on_after_recv) transform, every
+    /// time more of it comes in until it accepts or rejects the frame. A
+    /// rejected frame is answered with the given reply at once and its
+    /// remaining bytes are dropped instead of buffered. Frames that arrive
+    /// whole are not checked. Replaces any previously set check.
+    pub fn set_frame_check(&mut self, check: FrameCheck) {
+        self.check = Some(check);
+    }
+
+    /// Remove the frame check, returning it.
+    pub fn take_frame_check(&mut self) -> Option<FrameCheck> {
+        self.checked = false;
+        self.check.take()
+    }
+
+    /// Get the largest frame this connection accepts, in bytes.
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Set the largest frame this connection accepts (default: 16 MiB).
+    ///
+    /// A longer frame fails the receive with [`IpcError::BufferTooSmall`].
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
     /// Get the connection ID.
     pub fn id(&self) -> ConnectionId {
         self.id
     }
 
+    /// Check whether the client has closed the connection.
+    ///
+    /// Does not consume any pending data. Always `false` with the
+    /// interprocess backend and transports that cannot tell.
+    pub fn is_peer_closed(&self) -> bool {
+        self.stream.is_peer_closed()
+    }
+
+    /// Get a handle that checks [`is_peer_closed`](Self::is_peer_closed)
+    /// from any thread, if the transport can clone its streams.
+    pub(crate) fn peer_watch(&self) -> Option<impl Fn() -> bool + Send + Sync + 'static> {
+        let stream = self.stream.try_clone().ok()?;
+        Some(move || stream.is_peer_closed())
+    }
+
     /// Get the connection metadata.
     pub fn metadata(&self) -> &ConnectionMetadata {
         &self.metadata
@@ -306,55 +942,426 @@ impl Connection {
     /// Set client info.
     pub fn set_client_info(&mut self, info: &str) {
         self.metadata.client_info = Some(info.to_string());
+        self.publish_metadata();
+    }
+
+    /// Get this connection's state of type `T`.
+    ///
+    /// State is set by [`set_state`](Self::set_state) or returned from
+    /// [`ConnectionHandler::on_connect`], and lives as long as the
+    /// connection. Each type holds at most one value.
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.state.get()
+    }
+
+    /// Get a mutable reference to this connection's state of type `T`.
+    pub fn state_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.state.get_mut()
+    }
+
+    /// Set this connection's state of type `T`, returning the previous value.
+    pub fn set_state<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.state.insert(value)
+    }
+
+    /// Remove and return this connection's state of type `T`.
+    pub fn take_state<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.state.remove()
+    }
+
+    /// Get all state attached to this connection.
+    pub fn extensions(&self) -> &Extensions {
+        &self.state
+    }
+
+    /// Get all state attached to this connection, mutably.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.state
+    }
+
+    /// Check whether the server was asked to drop this connection with
+    /// [`SocketServer::disconnect`].
+    pub fn is_disconnect_requested(&self) -> bool {
+        self.registration
+            .as_ref()
+            .is_some_and(|r| r.entry.disconnecting.load(Ordering::Acquire))
+    }
+
+    /// Copy the metadata into the server's registry after a change.
+    fn publish_metadata(&self) {
+        if let Some(ref registration) = self.registration {
+            *registration.entry.metadata.write() = self.metadata.clone();
+        }
     }
 
     /// Send a message.
     pub fn send(&mut self, msg: &Message) -> Result<()> {
-        let data = serde_json::to_vec(msg).map_err(|e| IpcError::serialization(e.to_string()))?;
+        self.send_batch(std::slice::from_ref(msg))
+    }
 
-        // Write length prefix (4 bytes, little-endian)
-        let len = data.len() as u32;
-        self.stream.write_all(&len.to_le_bytes())?;
+    /// Send several messages with a single write.
+    ///
+    /// The messages are framed exactly as [`send`](Self::send) frames them,
+    /// so the peer can read them one at a time or with
+    /// [`recv_batch`](Self::recv_batch). Nothing is sent if any message
+    /// fails to encode or exceeds the frame size.
+    pub fn send_batch(&mut self, msgs: &[Message]) -> Result<()> {
+        let max_len = self
+            .metadata
+            .handshake
+            .as_ref()
+            .map_or(MAX_FRAME_SIZE, |h| h.max_frame_size);
+        let before_send = self.hooks.read().before_send.clone();
+        let mut frames = Vec::new();
+        for msg in msgs {
+            encode_frame(&mut frames, msg, max_len, before_send.as_ref())?;
+        }
+
+        // Broadcasts write through the shared handle, so frames must too
+        match self.writer {
+            Some(ref writer) => write_frames(&mut *writer.lock(), &frames)?,
+            None => write_frames(&mut self.stream, &frames)?,
+        }
 
-        // Write data
-        self.stream.write_all(&data)?;
-        self.stream.flush()?;
+        if let Some(ref tap) = self.tap {
+            for msg in msgs {
+                tap(TapDirection::Outbound, msg);
+            }
+        }
 
         Ok(())
     }
 
     /// Receive a message.
     pub fn recv(&mut self) -> Result<Message> {
-        // Read length prefix
-        let mut len_buf = [0u8; 4];
-        self.stream.read_exact(&mut len_buf)?;
-        let len = u32::from_le_bytes(len_buf) as usize;
+        let frame = self.wait_frame()?;
+        self.decode_frame(frame)
+    }
+
+    /// Receive a message, decoding binary data into `buf`.
+    ///
+    /// `buf` is cleared first and keeps its allocation, so a loop receiving
+    /// a stream of [`Message::binary`] frames allocates nothing once `buf`
+    /// has grown to the largest one. Binary messages return `None` with
+    /// their data in `buf`; any other message is returned as-is and leaves
+    /// `buf` empty.
+    pub fn recv_into(&mut self, buf: &mut Vec<u8>) -> Result<Option<Message>> {
+        buf.clear();
+        let frame = self.wait_frame()?;
+        let bytes = &self.buffer[frame.clone()];
+        let header = serde_json::from_slice::<FrameHeader>(bytes)
+            .map_err(|e| IpcError::deserialization(e.to_string()))?;
+        if header.msg_type != MessageType::Binary {
+            return self.decode_frame(frame).map(Some);
+        }
+
+        let binary = serde_json::from_slice::<BinaryFrame>(bytes)
+            .map_err(|e| IpcError::deserialization(e.to_string()))?;
+        base64::Engine::decode_vec(
+            &base64::engine::general_purpose::STANDARD,
+            binary.payload.data.as_bytes(),
+            buf,
+        )
+        .map_err(|e| IpcError::deserialization(e.to_string()))?;
+        self.tap_frame(frame);
+        Ok(None)
+    }
+
+    /// Receive the next frame without decoding or copying it.
+    ///
+    /// Returns the frame's encoded message, borrowed from the connection's
+    /// read buffer until the next receive. Useful for forwarding frames or
+    /// decoding them with a custom deserializer.
+    pub fn recv_borrowed(&mut self) -> Result<&[u8]> {
+        let frame = self.wait_frame()?;
+        self.tap_frame(frame.clone());
+        Ok(&self.buffer[frame])
+    }
+
+    /// Try to receive a message without blocking.
+    ///
+    /// Returns `Ok(None)` if no complete message has arrived yet. Partial
+    /// frames are kept until the rest arrives, so this can be polled from a
+    /// GUI tick or event loop and mixed freely with [`recv`](Self::recv).
+    pub fn try_recv(&mut self) -> Result<Option<Message>> {
+        loop {
+            if let Some(msg) = self.take_frame()? {
+                return Ok(Some(msg));
+            }
+            match self.read_more(Some(Duration::ZERO)) {
+                Ok(0) => return Err(IpcError::Closed),
+                Ok(_) => {}
+                Err(e) if e.is_would_block() => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Receive a message, waiting at most `timeout` for it.
+    ///
+    /// Blocks on the stream rather than polling. Fails with
+    /// [`IpcError::Timeout`] if no complete message arrived in time.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Message> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            if let Some(msg) = self.take_frame()? {
+                return Ok(msg);
+            }
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            match self.read_more(Some(remaining)) {
+                Ok(0) => return Err(IpcError::Closed),
+                Ok(_) => {}
+                Err(IpcError::Io(e)) if e.kind() == std::io::ErrorKind::TimedOut => {
+                    return Err(IpcError::Timeout)
+                }
+                Err(e) if e.is_would_block() && remaining.is_zero() => {
+                    return Err(IpcError::Timeout)
+                }
+                Err(e) if e.is_would_block() => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Receive up to `max_n` messages.
+    ///
+    /// Waits up to `max_wait` for the first message, then adds whatever else
+    /// has already arrived without waiting further. Returns an empty `Vec` if
+    /// nothing arrived in time. Reads pull in everything the socket has
+    /// buffered, so draining a burst takes few system calls.
+    pub fn recv_batch(&mut self, max_n: usize, max_wait: Duration) -> Result<Vec<Message>> {
+        let mut batch = Vec::new();
+        if max_n == 0 {
+            return Ok(batch);
+        }
+        match self.recv_timeout(max_wait) {
+            Ok(msg) => batch.push(msg),
+            Err(IpcError::Timeout) => return Ok(batch),
+            Err(e) => return Err(e),
+        }
+        while batch.len() < max_n {
+            match self.try_recv() {
+                Ok(Some(msg)) => batch.push(msg),
+                Ok(None) => break,
+                // Hand out what arrived before the peer left; the next call reports it
+                Err(IpcError::Closed) if !batch.is_empty() => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Block until a complete frame is buffered, returning its location.
+    fn wait_frame(&mut self) -> Result<Range<usize>> {
+        loop {
+            if let Some(frame) = self.next_frame()? {
+                return Ok(frame);
+            }
+            let n = self.read_more(None)?;
+            if n == 0 {
+                return Err(IpcError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+            }
+        }
+    }
+
+    /// Read more bytes from the stream into the frame buffer, waiting at
+    /// most `timeout` (forever if `None`).
+    fn read_more(&mut self, timeout: Option<Duration>) -> Result<usize> {
+        let mut chunk = [0u8; 8192];
+        loop {
+            let result = match timeout {
+                None => self.stream.read(&mut chunk),
+                Some(t) if t.is_zero() => self.stream.try_read(&mut chunk),
+                Some(t) => self.stream.read_with_timeout(&mut chunk, t),
+            };
+            match result {
+                Ok(n) => {
+                    self.buffer.extend_from_slice(&chunk[..n]);
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Decode the next complete frame from the buffer, if there is one.
+    fn take_frame(&mut self) -> Result<Option<Message>> {
+        match self.next_frame()? {
+            Some(frame) => self.decode_frame(frame).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Locate the next complete frame's message in the buffer, if there is
+    /// one. The frame is dropped from the buffer on the next call.
+    fn next_frame(&mut self) -> Result<Option<Range<usize>>> {
+        if self.consumed > 0 {
+            self.buffer.drain(..self.consumed);
+            self.consumed = 0;
+        }
+        if self.discarding > 0 {
+            let n = self.discarding.min(self.buffer.len());
+            self.buffer.drain(..n);
+            self.discarding -= n;
+            if self.discarding > 0 {
+                return Ok(None);
+            }
+        }
+        let Some(len_buf) = self.buffer.get(..4) else {
+            return Ok(None);
+        };
+        let len = u32::from_le_bytes(len_buf.try_into().unwrap_or_default()) as usize;
 
         // Validate length
-        if len > 16 * 1024 * 1024 {
+        if len > self.max_frame_size {
             return Err(IpcError::BufferTooSmall {
                 needed: len,
-                got: 16 * 1024 * 1024,
+                got: self.max_frame_size,
             });
         }
+        if self.buffer.len() < 4 + len {
+            if let Some(check) = self.check.clone().filter(|_| !self.checked) {
+                match check(len, &self.buffer[4..]) {
+                    FrameVerdict::NeedMore => {}
+                    FrameVerdict::Accept => self.checked = true,
+                    FrameVerdict::Reject(reply) => {
+                        self.send(&reply)?;
+                        self.discarding = 4 + len;
+                        return self.next_frame();
+                    }
+                }
+            }
+            return Ok(None);
+        }
 
-        // Read data
-        self.buffer.resize(len, 0);
-        self.stream.read_exact(&mut self.buffer)?;
+        self.checked = false;
+        self.consumed = 4 + len;
+        if let Some(ref registration) = self.registration {
+            registration.entry.messages.fetch_add(1, Ordering::Relaxed);
+        }
 
-        // Parse message
-        serde_json::from_slice(&self.buffer).map_err(|e| IpcError::deserialization(e.to_string()))
+        // Swap the frame for its transformed bytes in place
+        let after_recv = self.hooks.read().after_recv.clone();
+        if let Some(hook) = after_recv {
+            let data = hook(&self.buffer[4..4 + len])?;
+            let len = data.len();
+            self.buffer.splice(4..self.consumed, data);
+            self.consumed = 4 + len;
+            return Ok(Some(4..4 + len));
+        }
+        Ok(Some(4..4 + len))
     }
 
-    /// Try to receive a message without blocking.
+    /// Parse a located frame, passing it to the tap.
+    fn decode_frame(&self, frame: Range<usize>) -> Result<Message> {
+        let msg = serde_json::from_slice::<Message>(&self.buffer[frame])
+            .map_err(|e| IpcError::deserialization(e.to_string()))?;
+        if let Some(ref tap) = self.tap {
+            tap(TapDirection::Inbound, &msg);
+        }
+        Ok(msg)
+    }
+
+    /// Pass a located frame to the tap, parsing it only if there is one.
+    fn tap_frame(&self, frame: Range<usize>) {
+        if self.tap.is_some() {
+            let _ = self.decode_frame(frame);
+        }
+    }
+
+    /// Get the result of the capability handshake, if one took place.
+    pub fn handshake_info(&self) -> Option<&HandshakeInfo> {
+        self.metadata.handshake.as_ref()
+    }
+
+    /// Perform the client side of the capability handshake.
     ///
-    /// Note: This may not work correctly on all platforms as the underlying
-    /// stream may not support non-blocking reads.
-    pub fn try_recv(&mut self) -> Result<Option<Message>> {
-        // For simplicity, we don't implement true non-blocking I/O here
-        // A real implementation would use platform-specific non-blocking APIs
-        // or async I/O
-        Err(IpcError::WouldBlock)
+    /// Sends this side's capabilities and checks the server's reply against
+    /// `handshake`. Fails with [`IpcError::Incompatible`] if either side
+    /// rejects the other, including when the server predates the handshake,
+    /// with [`IpcError::PermissionDenied`] if the server rejects its
+    /// [`identity`](Handshake::identity), and with [`IpcError::Timeout`] if
+    /// it does not answer at all.
+    pub fn handshake(&mut self, handshake: &Handshake) -> Result<&HandshakeInfo> {
+        let params = serde_json::to_value(handshake.local_capabilities())
+            .map_err(|e| IpcError::serialization(e.to_string()))?;
+        self.send(&Message::request(HANDSHAKE_METHOD, params))?;
+
+        let reply = self.recv_timeout(handshake.timeout)?;
+
+        let peer = match reply.msg_type {
+            MessageType::Response => reply
+                .result()
+                .and_then(|r| serde_json::from_value::<Capabilities>(r.clone()).ok())
+                .ok_or_else(|| {
+                    IpcError::Incompatible(
+                        "server does not support the capability handshake".to_string(),
+                    )
+                })?,
+            MessageType::Error => {
+                let message = reply
+                    .payload
+                    .get("message")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("handshake rejected");
+                return Err(match reply.error_code() {
+                    Some(ErrorCode::Incompatible) => IpcError::Incompatible(message.to_string()),
+                    Some(ErrorCode::PermissionDenied) => {
+                        IpcError::PermissionDenied(message.to_string())
+                    }
+                    _ => IpcError::Incompatible(format!(
+                        "server does not support the capability handshake: {message}"
+                    )),
+                });
+            }
+            _ => {
+                return Err(IpcError::Incompatible(
+                    "server does not support the capability handshake".to_string(),
+                ))
+            }
+        };
+
+        let info = handshake.negotiate(peer)?;
+        Ok(self.metadata.handshake.insert(info))
+    }
+
+    /// Answer a client's [`HANDSHAKE_METHOD`] request.
+    ///
+    /// Replies with this side's capabilities, or with an
+    /// [`ErrorCode::Incompatible`] error if the client does not meet
+    /// `handshake`'s requirements, or with [`ErrorCode::PermissionDenied`]
+    /// if its identity is not proven by a registered
+    /// [`credential`](Handshake::credential). The error is returned too.
+    pub fn accept_handshake(&mut self, hello: &Message, handshake: &Handshake) -> Result<()> {
+        let negotiated = hello
+            .params()
+            .and_then(|p| serde_json::from_value::<Capabilities>(p.clone()).ok())
+            .ok_or_else(|| IpcError::Incompatible("malformed handshake".to_string()))
+            .and_then(|peer| handshake.negotiate(peer));
+
+        match negotiated {
+            Ok(info) => {
+                let capabilities = serde_json::to_value(handshake.local_capabilities())
+                    .map_err(|e| IpcError::serialization(e.to_string()))?;
+                self.send(&Message::response(capabilities))?;
+                self.metadata.handshake = Some(info);
+                self.publish_metadata();
+                Ok(())
+            }
+            Err(IpcError::Incompatible(reason)) => {
+                self.send(&Message::error(ErrorCode::Incompatible.as_i32(), &reason))?;
+                Err(IpcError::Incompatible(reason))
+            }
+            Err(IpcError::PermissionDenied(reason)) => {
+                self.send(&Message::error(
+                    ErrorCode::PermissionDenied.as_i32(),
+                    &reason,
+                ))?;
+                Err(IpcError::PermissionDenied(reason))
+            }
+            Err(e) => Err(e),
+        }
     }
 
     /// Send a request and wait for a response.
@@ -367,99 +1374,561 @@ impl Connection {
         let response = self.recv()?;
 
         match response.msg_type {
-            MessageType::Response => response
-                .result()
-                .cloned()
-                .ok_or_else(|| IpcError::deserialization("Missing result in response".to_string())),
-            MessageType::Error => {
-                let msg = response
-                    .payload
-                    .get("message")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("Unknown error");
-                Err(IpcError::Other(msg.to_string()))
+            MessageType::Response | MessageType::Error => response_result(response),
+            _ => Err(IpcError::deserialization(
+                "Unexpected message type".to_string(),
+            )),
+        }
+    }
+
+    /// Send a request and wait for its response, passing every other message
+    /// received in the meantime, such as pushed events, to `on_message`.
+    pub fn request_with<F: FnMut(Message)>(
+        &mut self,
+        method: &str,
+        params: serde_json::Value,
+        mut on_message: F,
+    ) -> Result<serde_json::Value> {
+        self.send(&Message::request(method, params))?;
+        loop {
+            let msg = self.recv()?;
+            match msg.msg_type {
+                MessageType::Response | MessageType::Error => return response_result(msg),
+                _ => on_message(msg),
+            }
+        }
+    }
+}
+
+/// Get the result of a response message, or the error of an error message.
+fn response_result(response: Message) -> Result<serde_json::Value> {
+    if response.msg_type == MessageType::Error {
+        let msg = response
+            .payload
+            .get("message")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Unknown error");
+        return Err(IpcError::Other(msg.to_string()));
+    }
+    response
+        .result()
+        .cloned()
+        .ok_or_else(|| IpcError::deserialization("Missing result in response".to_string()))
+}
+
+/// Append a length-prefixed message frame of at most `max_len` bytes.
+/// Just the type of an encoded [`Message`].
+#[derive(Deserialize)]
+struct FrameHeader {
+    msg_type: MessageType,
+}
+
+/// The data of an encoded binary [`Message`], borrowed from the frame.
+#[derive(Deserialize)]
+struct BinaryFrame<'a> {
+    #[serde(borrow)]
+    payload: BinaryPayload<'a>,
+}
+
+#[derive(Deserialize)]
+struct BinaryPayload<'a> {
+    #[serde(borrow)]
+    data: Cow<'a, str>,
+}
+
+/// Start of a frame holding a binary [`Message`] as encoded by [`Message::binary`].
+const BINARY_FRAME_START: &[u8] = br#"{"msg_type":"binary","payload":{"data":""#;
+
+/// Decode the data of a binary message from the start of its frame.
+///
+/// Returns as much data as `head` holds, or `None` if `head` does not start
+/// a binary message as [`Message::binary`] encodes it.
+pub(crate) fn binary_frame_prefix(head: &[u8]) -> Option<Vec<u8>> {
+    if head.len() < BINARY_FRAME_START.len() {
+        return BINARY_FRAME_START.starts_with(head).then(Vec::new);
+    }
+    let encoded = head.strip_prefix(BINARY_FRAME_START)?;
+    let end = encoded
+        .iter()
+        .position(|&b| b == b'"')
+        .unwrap_or(encoded.len() / 4 * 4);
+    base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &encoded[..end]).ok()
+}
+
+/// Encode a message as a frame, passing it through `before_send` first.
+fn encode_frame(
+    frames: &mut Vec<u8>,
+    msg: &Message,
+    max_len: usize,
+    before_send: Option<&FrameHook>,
+) -> Result<()> {
+    let data = serde_json::to_vec(msg).map_err(|e| IpcError::serialization(e.to_string()))?;
+    match before_send {
+        Some(hook) => push_frame(frames, &hook(&data)?, max_len),
+        None => push_frame(frames, &data, max_len),
+    }
+}
+
+/// Append a length-prefixed frame holding already encoded `data`.
+fn push_frame(frames: &mut Vec<u8>, data: &[u8], max_len: usize) -> Result<()> {
+    if data.len() > max_len {
+        return Err(IpcError::BufferTooSmall {
+            needed: data.len(),
+            got: max_len,
+        });
+    }
+
+    // Length prefix (4 bytes, little-endian) followed by the data
+    frames.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    frames.extend_from_slice(data);
+    Ok(())
+}
+
+/// Write encoded frames in one go.
+fn write_frames<W: Write>(writer: &mut W, frames: &[u8]) -> Result<()> {
+    writer.write_all(frames)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Connection handler trait for processing connections.
+pub trait ConnectionHandler: Clone + Send + 'static {
+    /// Handle a new connection, returning its initial state.
+    ///
+    /// The returned values are added to the connection's state (see
+    /// [`Connection::state`]) before its first message is handled. An error
+    /// closes the connection.
+    fn on_connect(&self, conn: &mut Connection) -> Result<Extensions> {
+        let _ = conn;
+        Ok(Extensions::new())
+    }
+
+    /// Handle a received message.
+    fn on_message(&self, conn: &mut Connection, msg: Message) -> Result<Option<Message>>;
+
+    /// Handle connection disconnect.
+    fn on_disconnect(&self, conn_id: ConnectionId) {
+        let _ = conn_id;
+    }
+
+    /// Turn a connection away while the server is shutting down.
+    ///
+    /// Called for connections accepted after shutdown is signaled and for
+    /// messages arriving on existing connections once it has been; the
+    /// connection is closed afterwards. Sends a [`ErrorCode::Closed`] error
+    /// by default.
+    fn on_reject(&self, conn: &mut Connection) {
+        let _ = conn.send(&Message::from_error(&IpcError::Closed));
+    }
+}
+
+/// A simple function-based handler.
+#[derive(Clone)]
+pub struct FnHandler<F>
+where
+    F: Fn(&mut Connection, Message) -> Result<Option<Message>> + Clone + Send + 'static,
+{
+    handler: F,
+}
+
+impl<F> FnHandler<F>
+where
+    F: Fn(&mut Connection, Message) -> Result<Option<Message>> + Clone + Send + 'static,
+{
+    /// Create a new function handler.
+    pub fn new(handler: F) -> Self {
+        Self { handler }
+    }
+}
+
+impl<F> ConnectionHandler for FnHandler<F>
+where
+    F: Fn(&mut Connection, Message) -> Result<Option<Message>> + Clone + Send + 'static,
+{
+    fn on_message(&self, conn: &mut Connection, msg: Message) -> Result<Option<Message>> {
+        (self.handler)(conn, msg)
+    }
+}
+
+/// Server-wide tap and the observers attached through [`ATTACH_METHOD`].
+#[derive(Default)]
+struct TapHub {
+    tap: RwLock<Option<ServerTap>>,
+    observers: Mutex<Vec<Sender<Message>>>,
+}
+
+impl TapHub {
+    fn observe(&self, id: ConnectionId, direction: TapDirection, msg: &Message) {
+        if let Some(ref tap) = *self.tap.read() {
+            tap(id, direction, msg);
+        }
+
+        let mut observers = self.observers.lock();
+        if observers.is_empty() {
+            return;
+        }
+
+        let record = TapRecord {
+            connection: id,
+            direction,
+            message: msg.clone(),
+        }
+        .to_message();
+        // Slow observers lose records rather than stalling the server
+        observers.retain(|tx| {
+            !matches!(
+                tx.try_send(record.clone()),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+    }
+
+    /// Stream tap records to an attached connection until it goes away.
+    fn serve_observer(&self, conn: &mut Connection, shutdown: &ShutdownState) {
+        // The observer's own traffic must not be fed back to it
+        conn.clear_tap();
+
+        // Registered before the ack, so everything after `attach` returns is seen
+        let (tx, rx) = crossbeam_channel::bounded(ATTACH_BUFFER);
+        self.observers.lock().push(tx);
+        let ack =
+            Message::response(serde_json::json!({ "attached": true, "connection": conn.id() }));
+        if conn.send(&ack).is_err() {
+            return;
+        }
+
+        while !shutdown.is_shutdown() {
+            match rx.recv_timeout(ATTACH_POLL_INTERVAL) {
+                Ok(record) => {
+                    if conn.send(&record).is_err() {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if conn.is_peer_closed() {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    }
+}
+
+/// Where and how a broadcast reaches one connection
+struct Subscriber {
+    writer: SharedStream,
+    hooks: SharedHooks,
+}
+
+#[derive(Default)]
+struct BroadcastInner {
+    writers: RwLock<HashMap<ConnectionId, Subscriber>>,
+    topics: RwLock<HashMap<String, HashSet<ConnectionId>>>,
+}
+
+/// Pushes messages to every connection subscribed to a topic.
+///
+/// Obtained from [`SocketServer::broadcaster`]; cheap to clone and usable
+/// from any thread, including connection handlers.
+#[derive(Clone)]
+pub struct Broadcaster {
+    inner: Arc<BroadcastInner>,
+    taps: Arc<TapHub>,
+}
+
+impl Broadcaster {
+    /// Subscribe a connection to a topic.
+    ///
+    /// Fails with [`IpcError::NotFound`] if the connection is unknown to the
+    /// server, has disconnected, or its stream cannot be shared for writing.
+    pub fn subscribe(&self, conn_id: ConnectionId, topic: &str) -> Result<()> {
+        if !self.inner.writers.read().contains_key(&conn_id) {
+            return Err(IpcError::NotFound(format!("connection {}", conn_id)));
+        }
+        self.inner
+            .topics
+            .write()
+            .entry(topic.to_string())
+            .or_default()
+            .insert(conn_id);
+        Ok(())
+    }
+
+    /// Unsubscribe a connection from a topic.
+    ///
+    /// Returns `true` if the connection was subscribed.
+    pub fn unsubscribe(&self, conn_id: ConnectionId, topic: &str) -> bool {
+        let mut topics = self.inner.topics.write();
+        let Some(subscribers) = topics.get_mut(topic) else {
+            return false;
+        };
+        let removed = subscribers.remove(&conn_id);
+        if subscribers.is_empty() {
+            topics.remove(topic);
+        }
+        removed
+    }
+
+    /// Send a message to every connection subscribed to `topic`.
+    ///
+    /// Connections that fail to receive it are dropped from all topics.
+    /// Returns the number of connections the message was delivered to.
+    pub fn broadcast(&self, topic: &str, msg: &Message) -> usize {
+        let subscribers: Vec<ConnectionId> = match self.inner.topics.read().get(topic) {
+            Some(subscribers) => subscribers.iter().copied().collect(),
+            None => return 0,
+        };
+
+        let mut frame = Vec::new();
+        if let Err(e) = encode_frame(&mut frame, msg, MAX_FRAME_SIZE, None) {
+            tracing::debug!("Not broadcasting to {}: {}", topic, e);
+            return 0;
+        }
+
+        let mut delivered = 0;
+        for id in subscribers {
+            let Some((writer, before_send)) = self
+                .inner
+                .writers
+                .read()
+                .get(&id)
+                .map(|s| (Arc::clone(&s.writer), s.hooks.read().before_send.clone()))
+            else {
+                continue;
+            };
+            // Connections with a send hook get their own copy of the frame
+            let result = match before_send {
+                Some(hook) => hook(&frame[4..]).and_then(|data| {
+                    let mut own = Vec::new();
+                    push_frame(&mut own, &data, MAX_FRAME_SIZE)?;
+                    write_frames(&mut *writer.lock(), &own)
+                }),
+                None => write_frames(&mut *writer.lock(), &frame),
+            };
+            match result {
+                Ok(()) => {
+                    delivered += 1;
+                    self.taps.observe(id, TapDirection::Outbound, msg);
+                }
+                Err(e) => {
+                    tracing::debug!("Dropping broadcast subscriber {}: {}", id, e);
+                    self.remove(id);
+                }
             }
-            _ => Err(IpcError::deserialization(
-                "Unexpected message type".to_string(),
-            )),
         }
+        delivered
     }
-}
 
-/// Connection handler trait for processing connections.
-pub trait ConnectionHandler: Clone + Send + 'static {
-    /// Handle a new connection.
-    fn on_connect(&self, conn: &mut Connection) -> Result<()> {
-        let _ = conn;
-        Ok(())
+    /// Get the connections subscribed to a topic.
+    pub fn subscribers(&self, topic: &str) -> Vec<ConnectionId> {
+        self.inner
+            .topics
+            .read()
+            .get(topic)
+            .map(|s| s.iter().copied().collect())
+            .unwrap_or_default()
     }
 
-    /// Handle a received message.
-    fn on_message(&self, conn: &mut Connection, msg: Message) -> Result<Option<Message>>;
+    /// Get all topics with at least one subscriber.
+    pub fn topics(&self) -> Vec<String> {
+        self.inner.topics.read().keys().cloned().collect()
+    }
 
-    /// Handle connection disconnect.
-    fn on_disconnect(&self, conn_id: ConnectionId) {
-        let _ = conn_id;
+    fn register(&self, conn: &mut Connection) {
+        match conn.stream.try_clone() {
+            Ok(stream) => {
+                let writer = Arc::new(Mutex::new(stream));
+                self.inner.writers.write().insert(
+                    conn.id(),
+                    Subscriber {
+                        writer: Arc::clone(&writer),
+                        hooks: Arc::clone(&conn.hooks),
+                    },
+                );
+                conn.writer = Some(writer);
+            }
+            Err(e) => tracing::debug!("Connection {} cannot receive broadcasts: {}", conn.id(), e),
+        }
+    }
+
+    /// Forget a connection and all of its subscriptions.
+    fn remove(&self, conn_id: ConnectionId) {
+        self.inner.writers.write().remove(&conn_id);
+        self.inner.topics.write().retain(|_, subscribers| {
+            subscribers.remove(&conn_id);
+            !subscribers.is_empty()
+        });
     }
 }
 
-/// A simple function-based handler.
-#[derive(Clone)]
-pub struct FnHandler<F>
-where
-    F: Fn(&mut Connection, Message) -> Result<Option<Message>> + Clone + Send + 'static,
-{
-    handler: F,
+/// A connection's entry in a [`ConnectionRegistry`].
+struct RegisteredConnection {
+    metadata: RwLock<ConnectionMetadata>,
+    messages: AtomicU64,
+    disconnecting: AtomicBool,
+    /// Handle used to wake the connection's blocked reads on disconnect
+    stream: Option<SharedStream>,
 }
 
-impl<F> FnHandler<F>
-where
-    F: Fn(&mut Connection, Message) -> Result<Option<Message>> + Clone + Send + 'static,
-{
-    /// Create a new function handler.
-    pub fn new(handler: F) -> Self {
-        Self { handler }
+/// Removes a connection from its registry when the connection is dropped.
+struct Registration {
+    id: ConnectionId,
+    entry: Arc<RegisteredConnection>,
+    registry: ConnectionRegistry,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.registry.inner.write().remove(&self.id);
     }
 }
 
-impl<F> ConnectionHandler for FnHandler<F>
-where
-    F: Fn(&mut Connection, Message) -> Result<Option<Message>> + Clone + Send + 'static,
-{
-    fn on_message(&self, conn: &mut Connection, msg: Message) -> Result<Option<Message>> {
-        (self.handler)(conn, msg)
+/// The live connections of a [`SocketServer`].
+///
+/// Obtained from [`SocketServer::registry`]; cheap to clone and usable from
+/// any thread, e.g. to serve admin routes with
+/// [`Router::connection_routes`](crate::api_server::Router::connection_routes).
+/// Connections are listed from the moment they are accepted until they are
+/// dropped.
+#[derive(Clone, Default)]
+pub struct ConnectionRegistry {
+    inner: Arc<RwLock<HashMap<ConnectionId, Arc<RegisteredConnection>>>>,
+}
+
+impl ConnectionRegistry {
+    /// Get every live connection, ordered by ID.
+    pub fn list(&self) -> Vec<ConnectionInfo> {
+        let mut connections: Vec<ConnectionInfo> = self
+            .inner
+            .read()
+            .iter()
+            .map(|(id, entry)| Self::info(*id, entry))
+            .collect();
+        connections.sort_by_key(|c| c.id);
+        connections
+    }
+
+    /// Get a live connection.
+    pub fn get(&self, id: ConnectionId) -> Option<ConnectionInfo> {
+        self.inner
+            .read()
+            .get(&id)
+            .map(|entry| Self::info(id, entry))
+    }
+
+    /// Get the number of live connections.
+    pub fn len(&self) -> usize {
+        self.inner.read().len()
+    }
+
+    /// Check whether there are no live connections.
+    pub fn is_empty(&self) -> bool {
+        self.inner.read().is_empty()
+    }
+
+    /// Drop a connection.
+    ///
+    /// The connection's socket is shut down, which ends
+    /// [`SocketServer::run`]'s loop for it and fails any pending `recv`.
+    /// Where the socket cannot be shared (native Windows pipes) the
+    /// connection is dropped before its next message is handled instead;
+    /// connections obtained from [`SocketServer::accept`] can check
+    /// [`Connection::is_disconnect_requested`]. Returns `false` if there is
+    /// no such connection.
+    pub fn disconnect(&self, id: ConnectionId) -> bool {
+        let Some(entry) = self.inner.read().get(&id).cloned() else {
+            return false;
+        };
+        entry.disconnecting.store(true, Ordering::Release);
+        if let Some(ref stream) = entry.stream {
+            if let Err(e) = stream.lock().shutdown() {
+                tracing::debug!("Cannot shut down connection {}: {}", id, e);
+            }
+        }
+        true
+    }
+
+    /// Drop every connection; see [`disconnect`](Self::disconnect).
+    ///
+    /// Returns the number of connections dropped.
+    pub fn disconnect_all(&self) -> usize {
+        let ids: Vec<ConnectionId> = self.inner.read().keys().copied().collect();
+        ids.into_iter().filter(|id| self.disconnect(*id)).count()
+    }
+
+    fn info(id: ConnectionId, entry: &RegisteredConnection) -> ConnectionInfo {
+        ConnectionInfo {
+            id,
+            metadata: entry.metadata.read().clone(),
+            messages_received: entry.messages.load(Ordering::Relaxed),
+            disconnecting: entry.disconnecting.load(Ordering::Acquire),
+        }
+    }
+
+    fn register(&self, conn: &mut Connection) {
+        let entry = Arc::new(RegisteredConnection {
+            metadata: RwLock::new(conn.metadata.clone()),
+            messages: AtomicU64::new(0),
+            disconnecting: AtomicBool::new(false),
+            stream: conn.writer.clone(),
+        });
+        self.inner.write().insert(conn.id(), Arc::clone(&entry));
+        conn.registration = Some(Registration {
+            id: conn.id(),
+            entry,
+            registry: self.clone(),
+        });
     }
 }
 
 /// Socket server for handling multiple client connections.
 pub struct SocketServer {
     config: SocketServerConfig,
-    listener: LocalSocketListener,
-    connections: Arc<RwLock<HashMap<ConnectionId, Arc<RwLock<Connection>>>>>,
+    listener: Box<dyn TransportListener>,
+    connections: ConnectionRegistry,
     shutdown: Arc<ShutdownState>,
     next_id: AtomicU64,
+    taps: Arc<TapHub>,
+    broadcaster: Broadcaster,
 }
 
 impl SocketServer {
     /// Create a new socket server.
-    pub fn new(config: SocketServerConfig) -> Result<Self> {
+    pub fn new(mut config: SocketServerConfig) -> Result<Self> {
+        // Never advertise frames larger than connections will accept
+        let advertised = &mut config.handshake.capabilities.max_frame_size;
+        *advertised = (*advertised).min(config.max_frame_size);
+
         // Cleanup old socket if requested
-        #[cfg(unix)]
-        if config.cleanup_on_start && !config.path.starts_with(r"\\.\pipe\") {
-            let _ = std::fs::remove_file(&config.path);
+        if config.cleanup_on_start {
+            config.transport.remove_stale(&config.path);
         }
 
-        let listener = LocalSocketListener::bind(&config.path)?;
+        let listener = config.transport.bind(&config.path)?;
+        let taps = Arc::new(TapHub::default());
 
         Ok(Self {
             config,
             listener,
-            connections: Arc::new(RwLock::new(HashMap::new())),
+            connections: ConnectionRegistry::default(),
             shutdown: Arc::new(ShutdownState::new()),
             next_id: AtomicU64::new(1),
+            broadcaster: Broadcaster {
+                inner: Arc::new(BroadcastInner::default()),
+                taps: Arc::clone(&taps),
+            },
+            taps,
         })
     }
 
+    /// Share a shutdown state with the server, e.g. one owned by the
+    /// component that will stop it.
+    pub fn with_shutdown_state(mut self, state: Arc<ShutdownState>) -> Self {
+        self.shutdown = state;
+        self
+    }
+
     /// Create a server with default configuration.
     pub fn with_defaults() -> Result<Self> {
         Self::new(SocketServerConfig::default())
@@ -475,9 +1944,89 @@ impl SocketServer {
         &self.config.path
     }
 
+    /// Get the address clients connect to.
+    ///
+    /// Differs from [`socket_path`](Self::socket_path) when the transport
+    /// resolves it, e.g. a TCP listener bound to port `0`.
+    pub fn local_addr(&self) -> String {
+        self.listener.local_addr()
+    }
+
+    /// Observe every message sent or received by connections accepted from
+    /// now on, e.g. for debug logging.
+    pub fn tap<F>(&self, tap: F)
+    where
+        F: Fn(ConnectionId, TapDirection, &Message) + Send + Sync + 'static,
+    {
+        *self.taps.tap.write() = Some(Arc::new(tap));
+    }
+
+    /// Remove the server-wide tap.
+    pub fn clear_tap(&self) {
+        *self.taps.tap.write() = None;
+    }
+
+    /// Wrap an accepted stream, tapping it if anything is observing.
+    fn new_connection(&self, stream: Box<dyn TransportStream>) -> Connection {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut conn = Connection::new(id, stream);
+        conn.set_max_frame_size(self.config.max_frame_size);
+        conn.set_frame_hooks(self.config.frame_hooks.clone());
+        if self.config.allow_attach || self.taps.tap.read().is_some() {
+            let taps = Arc::clone(&self.taps);
+            conn.tap(move |direction, msg| taps.observe(id, direction, msg));
+        }
+        self.broadcaster.register(&mut conn);
+        self.connections.register(&mut conn);
+        conn
+    }
+
+    /// Get a handle for pushing messages to subscribed connections.
+    ///
+    /// Remains usable after the server is moved into [`spawn`](Self::spawn).
+    pub fn broadcaster(&self) -> Broadcaster {
+        self.broadcaster.clone()
+    }
+
+    /// Subscribe a connection to a topic.
+    pub fn subscribe(&self, conn_id: ConnectionId, topic: &str) -> Result<()> {
+        self.broadcaster.subscribe(conn_id, topic)
+    }
+
+    /// Unsubscribe a connection from a topic.
+    pub fn unsubscribe(&self, conn_id: ConnectionId, topic: &str) -> bool {
+        self.broadcaster.unsubscribe(conn_id, topic)
+    }
+
+    /// Send a message to every connection subscribed to `topic`.
+    ///
+    /// Returns the number of connections the message was delivered to.
+    pub fn broadcast(&self, topic: &str, msg: &Message) -> usize {
+        self.broadcaster.broadcast(topic, msg)
+    }
+
     /// Get the current connection count.
     pub fn connection_count(&self) -> usize {
-        self.connections.read().len()
+        self.connections.len()
+    }
+
+    /// Get every live connection, ordered by ID.
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        self.connections.list()
+    }
+
+    /// Drop a connection; see [`ConnectionRegistry::disconnect`].
+    ///
+    /// Returns `false` if there is no such connection.
+    pub fn disconnect(&self, id: ConnectionId) -> bool {
+        self.connections.disconnect(id)
+    }
+
+    /// Get a handle to the live connections.
+    ///
+    /// Remains usable after the server is moved into [`spawn`](Self::spawn).
+    pub fn registry(&self) -> ConnectionRegistry {
+        self.connections.clone()
     }
 
     /// Accept a new connection.
@@ -487,18 +2036,7 @@ impl SocketServer {
         }
 
         let stream = self.listener.accept()?;
-        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
-        let conn = Connection::new(id, stream);
-
-        self.connections
-            .write()
-            .insert(id, Arc::new(RwLock::new(conn)));
-
-        // Return a new connection (we store a copy in the map)
-        let stream = self.listener.accept()?;
-        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
-
-        Ok(Connection::new(id, stream))
+        Ok(self.new_connection(stream))
     }
 
     /// Returns an iterator over incoming connections.
@@ -509,52 +2047,118 @@ impl SocketServer {
             }
 
             match self.listener.accept() {
-                Ok(stream) => {
-                    let id = self.next_id.fetch_add(1, Ordering::SeqCst);
-                    Some(Ok(Connection::new(id, stream)))
-                }
+                Ok(stream) => Some(Ok(self.new_connection(stream))),
+                Err(IpcError::Closed) => None,
                 Err(e) => Some(Err(e)),
             }
         })
     }
 
     /// Run the server with a handler (blocking).
+    ///
+    /// Each handler invocation holds an [`OperationGuard`](crate::OperationGuard)
+    /// on the server's shutdown state. Once shutdown is signaled, new
+    /// connections and new messages are turned away with
+    /// [`ConnectionHandler::on_reject`]; when the in-flight handlers have
+    /// finished, the remaining connections are dropped and `run` returns.
     pub fn run<H: ConnectionHandler>(&self, handler: H) -> Result<()> {
-        for conn_result in self.incoming() {
-            if self.shutdown.is_shutdown() {
+        let stopped = Arc::new(AtomicBool::new(false));
+        self.stop_after_drain(&stopped);
+
+        loop {
+            let conn_result = self.listener.accept();
+            if stopped.load(Ordering::Acquire) {
                 break;
             }
 
             match conn_result {
-                Ok(mut conn) => {
+                Ok(stream) if self.shutdown.is_shutdown() => {
+                    let mut conn = self.new_connection(stream);
+                    handler.on_reject(&mut conn);
+                    self.broadcaster.remove(conn.id());
+                }
+                Ok(stream) => {
+                    let mut conn = self.new_connection(stream);
                     let handler = handler.clone();
                     let shutdown = Arc::clone(&self.shutdown);
+                    let taps = Arc::clone(&self.taps);
+                    let allow_attach = self.config.allow_attach;
+                    let broadcaster = self.broadcaster.clone();
+                    let handshake = self.config.handshake.clone();
+                    let access_log = self.config.access_log.clone();
 
                     std::thread::spawn(move || {
-                        if let Err(e) = handler.on_connect(&mut conn) {
-                            tracing::error!("Connection error: {}", e);
-                            return;
+                        let connection = access_log.as_ref().map(|log| log.connection(conn.id()));
+                        let entered = connection.as_ref().map(|a| a.span().enter());
+
+                        match handler.on_connect(&mut conn) {
+                            Ok(state) => conn.state.extend(state),
+                            Err(e) => {
+                                tracing::error!("Connection error: {}", e);
+                                return;
+                            }
                         }
+                        MetricsRegistry::global().add_gauge(CONNECTIONS_GAUGE, 1);
 
+                        let mut first = true;
+                        let mut messages = 0;
                         loop {
-                            if shutdown.is_shutdown() {
+                            if shutdown.is_shutdown() || conn.is_disconnect_requested() {
                                 break;
                             }
 
+                            let greeting = std::mem::take(&mut first);
                             match conn.recv() {
-                                Ok(msg) => match handler.on_message(&mut conn, msg) {
-                                    Ok(Some(response)) => {
-                                        if let Err(e) = conn.send(&response) {
-                                            tracing::error!("Send error: {}", e);
-                                            break;
-                                        }
+                                Ok(msg) if greeting && msg.method() == Some(HANDSHAKE_METHOD) => {
+                                    if let Err(e) = conn.accept_handshake(&msg, &handshake) {
+                                        tracing::warn!("Handshake failed: {}", e);
+                                        break;
+                                    }
+                                }
+                                Ok(_) if greeting && handshake.require_handshake => {
+                                    let _ = conn.send(&Message::error(
+                                        ErrorCode::Incompatible.as_i32(),
+                                        "this server requires a capability handshake",
+                                    ));
+                                    break;
+                                }
+                                Ok(msg) if allow_attach && msg.method() == Some(ATTACH_METHOD) => {
+                                    taps.serve_observer(&mut conn, &shutdown);
+                                    break;
+                                }
+                                Ok(msg) => {
+                                    let Ok(_operation) = shutdown.begin_operation() else {
+                                        handler.on_reject(&mut conn);
+                                        break;
+                                    };
+                                    messages += 1;
+                                    // The handler runs in the sender's trace
+                                    let trace = msg.trace.as_ref().map(TraceContext::child);
+                                    let _trace = trace.as_ref().map(TraceContext::enter);
+                                    let access =
+                                        access_log.as_ref().map(|log| log.message(conn.id(), &msg));
+                                    let result = {
+                                        let _entered = access.as_ref().map(|a| a.span().enter());
+                                        handler.on_message(&mut conn, msg)
+                                    };
+                                    if let Some(access) = access {
+                                        access.finish_message(result.as_ref().map(Option::as_ref));
                                     }
-                                    Ok(None) => {}
-                                    Err(e) => {
-                                        tracing::error!("Handler error: {}", e);
-                                        let _ = conn.send(&Message::error(-1, &e.to_string()));
+
+                                    match result {
+                                        Ok(Some(response)) => {
+                                            if let Err(e) = conn.send(&response) {
+                                                tracing::error!("Send error: {}", e);
+                                                break;
+                                            }
+                                        }
+                                        Ok(None) => {}
+                                        Err(e) => {
+                                            tracing::error!("Handler error: {}", e);
+                                            let _ = conn.send(&Message::from_error(&e));
+                                        }
                                     }
-                                },
+                                }
                                 Err(IpcError::Io(ref e))
                                     if e.kind() == std::io::ErrorKind::UnexpectedEof =>
                                 {
@@ -567,9 +2171,19 @@ impl SocketServer {
                             }
                         }
 
+                        broadcaster.remove(conn.id());
                         handler.on_disconnect(conn.id());
+                        MetricsRegistry::global().add_gauge(CONNECTIONS_GAUGE, -1);
+
+                        drop(entered);
+                        if let Some(connection) = connection {
+                            connection.finish_connection(messages);
+                        }
                     });
                 }
+                // The listener will never accept again, e.g. stdio after its
+                // only connection ended
+                Err(IpcError::Closed) => break,
                 Err(e) => {
                     tracing::error!("Accept error: {}", e);
                 }
@@ -579,6 +2193,35 @@ impl SocketServer {
         Ok(())
     }
 
+    /// Once shutdown is signaled, wait for in-flight handlers, drop the
+    /// remaining connections and wake [`run`](Self::run)'s accept loop.
+    fn stop_after_drain(&self, stopped: &Arc<AtomicBool>) {
+        // A weak reference: the hook is owned by the state itself
+        let state = Arc::downgrade(&self.shutdown);
+        let registry = self.connections.clone();
+        let transport = Arc::clone(&self.config.transport);
+        let addr = self.listener.local_addr();
+        let stopped = Arc::clone(stopped);
+
+        self.shutdown.on_shutdown(move || {
+            let state = state.clone();
+            let registry = registry.clone();
+            let transport = Arc::clone(&transport);
+            let addr = addr.clone();
+            let stopped = Arc::clone(&stopped);
+            std::thread::spawn(move || {
+                if let Some(state) = state.upgrade() {
+                    let _ = state.wait_for_drain(None);
+                }
+                registry.disconnect_all();
+                stopped.store(true, Ordering::Release);
+                if let Err(e) = transport.connect(&addr) {
+                    tracing::debug!("Cannot wake the accept loop: {}", e);
+                }
+            });
+        });
+    }
+
     /// Spawn the server in a background thread.
     pub fn spawn<H: ConnectionHandler>(self, handler: H) -> JoinHandle<Result<()>> {
         std::thread::spawn(move || self.run(handler))
@@ -593,6 +2236,14 @@ impl SocketServer {
     pub fn is_shutdown(&self) -> bool {
         self.shutdown.is_shutdown()
     }
+
+    /// Get the server's shutdown state.
+    ///
+    /// Remains usable after the server is moved into [`spawn`](Self::spawn),
+    /// e.g. to stop it and [`wait_for_drain`](ShutdownState::wait_for_drain).
+    pub fn shutdown_state(&self) -> Arc<ShutdownState> {
+        Arc::clone(&self.shutdown)
+    }
 }
 
 impl GracefulChannel for SocketServer {
@@ -622,7 +2273,12 @@ pub struct SocketClient {
 impl SocketClient {
     /// Connect to a socket server.
     pub fn connect(path: &str) -> Result<Self> {
-        let stream = LocalSocketStream::connect(path)?;
+        Self::connect_with(&LocalSocketTransport, path)
+    }
+
+    /// Connect to a socket server listening on another transport.
+    pub fn connect_with(transport: &dyn Transport, addr: &str) -> Result<Self> {
+        let stream = transport.connect(addr)?;
         let connection = Connection::new(0, stream);
 
         Ok(Self { connection })
@@ -634,15 +2290,24 @@ impl SocketClient {
     /// If the connection cannot be established within the timeout,
     /// an error is returned.
     pub fn connect_timeout(path: &str, timeout: Duration) -> Result<Self> {
+        Self::connect_timeout_with(Arc::new(LocalSocketTransport), path, timeout)
+    }
+
+    /// Connect over another transport with a timeout.
+    pub fn connect_timeout_with(
+        transport: Arc<dyn Transport>,
+        addr: &str,
+        timeout: Duration,
+    ) -> Result<Self> {
         use std::sync::mpsc;
         use std::thread;
 
-        let path_owned = path.to_string();
+        let path_owned = addr.to_string();
         let (tx, rx) = mpsc::channel();
 
         // Spawn a thread to attempt the connection
         thread::spawn(move || {
-            let result = LocalSocketStream::connect(&path_owned);
+            let result = transport.connect(&path_owned);
             let _ = tx.send(result);
         });
 
@@ -657,6 +2322,18 @@ impl SocketClient {
         }
     }
 
+    /// Connect to a socket server and perform the capability handshake.
+    pub fn connect_with_handshake(path: &str, handshake: &Handshake) -> Result<Self> {
+        let mut client = Self::connect(path)?;
+        client.connection.handshake(handshake)?;
+        Ok(client)
+    }
+
+    /// Get the result of the capability handshake, if one took place.
+    pub fn handshake_info(&self) -> Option<&HandshakeInfo> {
+        self.connection.handshake_info()
+    }
+
     /// Connect to the default socket path.
     pub fn connect_default() -> Result<Self> {
         Self::connect(&default_socket_path())
@@ -677,6 +2354,40 @@ impl SocketClient {
         self.connection.recv()
     }
 
+    /// Try to receive a message without blocking.
+    pub fn try_recv(&mut self) -> Result<Option<Message>> {
+        self.connection.try_recv()
+    }
+
+    /// Receive a message, waiting at most `timeout` for it.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Message> {
+        self.connection.recv_timeout(timeout)
+    }
+
+    /// Receive a message, decoding binary data into `buf`.
+    ///
+    /// See [`Connection::recv_into`].
+    pub fn recv_into(&mut self, buf: &mut Vec<u8>) -> Result<Option<Message>> {
+        self.connection.recv_into(buf)
+    }
+
+    /// Receive the next frame without decoding or copying it.
+    ///
+    /// See [`Connection::recv_borrowed`].
+    pub fn recv_borrowed(&mut self) -> Result<&[u8]> {
+        self.connection.recv_borrowed()
+    }
+
+    /// Send several messages with a single write.
+    pub fn send_batch(&mut self, msgs: &[Message]) -> Result<()> {
+        self.connection.send_batch(msgs)
+    }
+
+    /// Receive up to `max_n` messages, waiting at most `max_wait` for the first.
+    pub fn recv_batch(&mut self, max_n: usize, max_wait: Duration) -> Result<Vec<Message>> {
+        self.connection.recv_batch(max_n, max_wait)
+    }
+
     /// Send a request and wait for a response.
     pub fn request(
         &mut self,
@@ -686,6 +2397,30 @@ impl SocketClient {
         self.connection.request(method, params)
     }
 
+    /// Send a request and wait for its response, passing every other message
+    /// received in the meantime to `on_message`.
+    ///
+    /// See [`Connection::request_with`].
+    pub fn request_with<F: FnMut(Message)>(
+        &mut self,
+        method: &str,
+        params: serde_json::Value,
+        on_message: F,
+    ) -> Result<serde_json::Value> {
+        self.connection.request_with(method, params, on_message)
+    }
+
+    /// Attach to the server as a live traffic observer.
+    ///
+    /// The server must have [`SocketServerConfig::allow_attach`] enabled.
+    /// Afterwards every [`recv`](Self::recv) yields a message that decodes
+    /// with [`TapRecord::from_message`].
+    pub fn attach(&mut self) -> Result<()> {
+        self.connection
+            .request(ATTACH_METHOD, serde_json::json!({}))
+            .map(|_| ())
+    }
+
     /// Get the underlying connection.
     pub fn connection(&mut self) -> &mut Connection {
         &mut self.connection
@@ -826,4 +2561,571 @@ mod tests {
 
         server_handle.join().unwrap();
     }
+
+    #[test]
+    fn test_try_recv_partial_frames() {
+        let name = format!("test_try_recv_{}", std::process::id());
+        let listener = LocalSocketTransport.bind(&name).unwrap();
+        let mut client = LocalSocketTransport.connect(&name).unwrap();
+        let mut conn = Connection::new(1, listener.accept().unwrap());
+
+        assert!(conn.try_recv().unwrap().is_none());
+
+        let payload = serde_json::to_vec(&Message::request("ping", serde_json::json!({}))).unwrap();
+        let mut frame = (payload.len() as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&payload);
+        let (head, tail) = frame.split_at(6);
+
+        client.write_all(head).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert!(conn.try_recv().unwrap().is_none());
+
+        client.write_all(tail).unwrap();
+        client.write_all(&frame).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let msg = conn.try_recv().unwrap().unwrap();
+        assert_eq!(msg.method(), Some("ping"));
+        // The second frame was buffered and is still seen by a blocking recv
+        assert_eq!(conn.recv().unwrap().method(), Some("ping"));
+
+        drop(client);
+        thread::sleep(Duration::from_millis(20));
+        assert!(matches!(conn.try_recv(), Err(IpcError::Closed)));
+    }
+
+    #[test]
+    fn test_recv_timeout() {
+        let name = format!("test_recv_timeout_{}", std::process::id());
+        let transports: [(Arc<dyn Transport>, &str); 2] = [
+            (Arc::new(LocalSocketTransport), &name),
+            (Arc::new(TcpTransport::new()), "127.0.0.1:0"),
+        ];
+        for (transport, addr) in transports {
+            let listener = transport.bind(addr).unwrap();
+            let mut client = Connection::new(2, transport.connect(&listener.local_addr()).unwrap());
+            let mut conn = Connection::new(1, listener.accept().unwrap());
+
+            let start = std::time::Instant::now();
+            assert!(matches!(
+                conn.recv_timeout(Duration::from_millis(30)),
+                Err(IpcError::Timeout)
+            ));
+            assert!(start.elapsed() >= Duration::from_millis(30));
+
+            // A blocked receive wakes up as soon as the message arrives
+            let sender = thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                client.send(&Message::text("late")).unwrap();
+                client
+            });
+            let msg = conn.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(msg.as_text(), Some("late"));
+            drop(sender.join().unwrap());
+            assert!(matches!(
+                conn.recv_timeout(Duration::from_secs(5)),
+                Err(IpcError::Closed)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_recv_into_reuses_buffer() {
+        let name = format!("test_recv_into_{}", std::process::id());
+        let listener = LocalSocketTransport.bind(&name).unwrap();
+        let mut client = Connection::new(2, LocalSocketTransport.connect(&name).unwrap());
+        let mut conn = Connection::new(1, listener.accept().unwrap());
+
+        client.send(&Message::binary(vec![7u8; 4096])).unwrap();
+        client.send(&Message::binary(vec![1, 2, 3])).unwrap();
+        client.send(&Message::text("hi")).unwrap();
+
+        let mut buf = Vec::new();
+        assert!(conn.recv_into(&mut buf).unwrap().is_none());
+        assert_eq!(buf, vec![7u8; 4096]);
+        let (ptr, capacity) = (buf.as_ptr(), buf.capacity());
+        assert!(conn.recv_into(&mut buf).unwrap().is_none());
+        assert_eq!(buf, [1, 2, 3]);
+        assert_eq!((buf.as_ptr(), buf.capacity()), (ptr, capacity));
+        let msg = conn.recv_into(&mut buf).unwrap().unwrap();
+        assert_eq!(msg.as_text(), Some("hi"));
+        assert!(buf.is_empty());
+
+        client.send(&Message::text("raw")).unwrap();
+        let frame = conn.recv_borrowed().unwrap();
+        let msg: Message = serde_json::from_slice(frame).unwrap();
+        assert_eq!(msg.as_text(), Some("raw"));
+
+        conn.set_max_frame_size(64);
+        client.send(&Message::binary(vec![0u8; 128])).unwrap();
+        assert!(matches!(
+            conn.recv_into(&mut buf),
+            Err(IpcError::BufferTooSmall { got: 64, .. })
+        ));
+    }
+
+    #[test]
+    fn test_batch_send_recv() {
+        let name = format!("test_batch_{}", std::process::id());
+        let listener = LocalSocketTransport.bind(&name).unwrap();
+        let mut client = Connection::new(2, LocalSocketTransport.connect(&name).unwrap());
+        let mut conn = Connection::new(1, listener.accept().unwrap());
+
+        let start = std::time::Instant::now();
+        assert!(conn
+            .recv_batch(10, Duration::from_millis(30))
+            .unwrap()
+            .is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(30));
+
+        let msgs: Vec<_> = (0..50)
+            .map(|i| Message::json(serde_json::json!({"seq": i})))
+            .collect();
+        client.send_batch(&msgs).unwrap();
+
+        let first = conn.recv_batch(20, Duration::from_secs(5)).unwrap();
+        assert_eq!(first.len(), 20);
+        assert_eq!(first[0].payload["seq"], 0);
+
+        // The rest is picked up by single and batched receives alike
+        assert_eq!(conn.recv().unwrap().payload["seq"], 20);
+        let mut rest = Vec::new();
+        while rest.len() < 29 {
+            rest.extend(conn.recv_batch(100, Duration::from_secs(5)).unwrap());
+        }
+        assert_eq!(rest.len(), 29);
+        assert_eq!(rest[28].payload["seq"], 49);
+
+        // An oversized message keeps the whole batch from being sent
+        let big = Message::binary(vec![0u8; MAX_FRAME_SIZE]);
+        assert!(matches!(
+            client.send_batch(&[msgs[0].clone(), big]),
+            Err(IpcError::BufferTooSmall { .. })
+        ));
+        assert!(conn
+            .recv_batch(10, Duration::from_millis(20))
+            .unwrap()
+            .is_empty());
+
+        client.send_batch(&msgs[..3]).unwrap();
+        drop(client);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(conn.recv_batch(10, Duration::ZERO).unwrap().len(), 3);
+        assert!(matches!(
+            conn.recv_batch(10, Duration::ZERO),
+            Err(IpcError::Closed)
+        ));
+    }
+
+    #[test]
+    fn test_server_tap_and_attach() {
+        use std::sync::Mutex as StdMutex;
+
+        let socket_name = format!("test_socket_tap_{}", std::process::id());
+        let config = SocketServerConfig {
+            allow_attach: true,
+            ..SocketServerConfig::with_path(&socket_name)
+        };
+        let server = SocketServer::new(config).unwrap();
+
+        let seen = Arc::new(StdMutex::new(Vec::new()));
+        {
+            let seen = Arc::clone(&seen);
+            server.tap(move |_, direction, msg| {
+                seen.lock()
+                    .unwrap()
+                    .push((direction, msg.as_text().map(String::from)));
+            });
+        }
+        let _server = server.spawn(FnHandler::new(|_conn, msg| {
+            Ok(Some(Message::text(&format!(
+                "echo: {}",
+                msg.as_text().unwrap_or_default()
+            ))))
+        }));
+        thread::sleep(Duration::from_millis(100));
+
+        let mut observer = SocketClient::connect(&socket_name).unwrap();
+        observer.attach().unwrap();
+
+        let mut client = SocketClient::connect(&socket_name).unwrap();
+        client.send(&Message::text("hello")).unwrap();
+        assert_eq!(client.recv().unwrap().as_text(), Some("echo: hello"));
+
+        let inbound = TapRecord::from_message(&observer.recv().unwrap()).unwrap();
+        assert_eq!(inbound.direction, TapDirection::Inbound);
+        assert_eq!(inbound.message.as_text(), Some("hello"));
+        let outbound = TapRecord::from_message(&observer.recv().unwrap()).unwrap();
+        assert_eq!(outbound.direction, TapDirection::Outbound);
+        assert_eq!(outbound.connection, inbound.connection);
+        assert_eq!(outbound.message.as_text(), Some("echo: hello"));
+
+        // The server-wide tap saw the same exchange, plus the attach request
+        let seen = seen.lock().unwrap();
+        assert!(seen.contains(&(TapDirection::Inbound, Some("hello".to_string()))));
+        assert!(seen.contains(&(TapDirection::Outbound, Some("echo: hello".to_string()))));
+    }
+
+    #[test]
+    fn test_server_over_other_transports() {
+        use crate::transport::{ShmTransport, TcpTransport};
+
+        let transports: [(Arc<dyn Transport>, String); 2] = [
+            (Arc::new(TcpTransport::new()), "127.0.0.1:0".to_string()),
+            (
+                Arc::new(ShmTransport::new(8, 256)),
+                format!("ipss{}", std::process::id()),
+            ),
+        ];
+        for (transport, path) in transports {
+            let config = SocketServerConfig {
+                path,
+                transport: Arc::clone(&transport),
+                ..Default::default()
+            };
+            let server = SocketServer::new(config).unwrap();
+            let addr = server.local_addr();
+            let _server = server.spawn(FnHandler::new(|_conn, msg| Ok(Some(msg))));
+
+            let mut client =
+                SocketClient::connect_timeout_with(transport, &addr, Duration::from_secs(5))
+                    .unwrap();
+            // Larger than a shm slot, so it spans several
+            let text = "x".repeat(1000);
+            client.send(&Message::text(&text)).unwrap();
+            assert_eq!(client.recv().unwrap().as_text(), Some(text.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_handshake_negotiate() {
+        let local = Handshake::new().require_codec("msgpack");
+        let peer = Capabilities {
+            max_frame_size: 1024,
+            codecs: vec!["json".to_string(), "msgpack".to_string()],
+            compression: vec!["zstd".to_string()],
+            ..Capabilities::default()
+        };
+
+        let info = local.negotiate(peer.clone()).unwrap();
+        assert_eq!(info.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(info.codecs, vec!["json"]);
+        assert!(info.compression.is_empty());
+        assert_eq!(info.max_frame_size, 1024);
+        assert_eq!(info.peer, peer);
+
+        let json_only = Capabilities {
+            codecs: vec!["json".to_string()],
+            ..Capabilities::default()
+        };
+        let err = local.negotiate(json_only).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Incompatible);
+        assert!(err.to_string().contains("msgpack"));
+
+        let old = Capabilities {
+            protocol_version: 0,
+            ..Capabilities::default()
+        };
+        assert!(matches!(
+            Handshake::new().negotiate(old),
+            Err(IpcError::Incompatible(_))
+        ));
+        assert!(Handshake::new()
+            .require_compression("zstd")
+            .negotiate(Capabilities::default())
+            .is_err());
+
+        // Identities are checked against registered credentials, and the
+        // token is not kept around
+        let server = Handshake::new().credential("alice", "secret");
+        let alice = Handshake::new().identity("alice", "secret");
+        let info = server
+            .negotiate(alice.local_capabilities().clone())
+            .unwrap();
+        assert_eq!(info.identity.as_deref(), Some("alice"));
+        assert!(info.peer.token.is_none());
+        let forged = Handshake::new().identity("alice", "guess");
+        assert!(matches!(
+            server.negotiate(forged.local_capabilities().clone()),
+            Err(IpcError::PermissionDenied(_))
+        ));
+
+        // Without credentials to check it, a claimed identity is not kept
+        let info = Handshake::new()
+            .negotiate(forged.local_capabilities().clone())
+            .unwrap();
+        assert_eq!(info.identity, None);
+        assert_eq!(info.peer.identity.as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn test_handshake_with_server() {
+        let socket_name = format!("test_socket_handshake_{}", std::process::id());
+        let config = SocketServerConfig {
+            handshake: Handshake::new().require_handshake(true),
+            ..SocketServerConfig::with_path(&socket_name)
+        };
+        let server = SocketServer::new(config).unwrap();
+        let _server = server.spawn(FnHandler::new(|conn, _msg| {
+            let negotiated = conn.handshake_info().map(|h| h.max_frame_size);
+            Ok(Some(Message::json(
+                serde_json::json!({ "max": negotiated }),
+            )))
+        }));
+        thread::sleep(Duration::from_millis(100));
+
+        // A client advertising a small frame limit gets it applied on both ends
+        let small = Handshake::new().capabilities(Capabilities {
+            max_frame_size: 64,
+            ..Capabilities::default()
+        });
+        let mut client = SocketClient::connect_with_handshake(&socket_name, &small).unwrap();
+        let info = client.handshake_info().unwrap();
+        assert_eq!(info.max_frame_size, 64);
+        assert_eq!(info.codecs, vec!["json"]);
+        assert_eq!(info.peer.library_version, env!("CARGO_PKG_VERSION"));
+
+        client.send(&Message::text("hi")).unwrap();
+        assert_eq!(client.recv().unwrap().payload["max"], 64);
+        assert!(matches!(
+            client.send(&Message::text(&"x".repeat(100))),
+            Err(IpcError::BufferTooSmall { .. })
+        ));
+
+        // Requirements the server cannot meet fail with a clear error
+        let picky = Handshake::new().require_compression("zstd");
+        let err = SocketClient::connect_with_handshake(&socket_name, &picky)
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), ErrorCode::Incompatible);
+
+        // Legacy clients that skip the handshake are turned away
+        let mut legacy = SocketClient::connect(&socket_name).unwrap();
+        legacy.send(&Message::text("hello")).unwrap();
+        assert_eq!(
+            legacy.recv().unwrap().error_code(),
+            Some(ErrorCode::Incompatible)
+        );
+    }
+
+    #[test]
+    fn test_handshake_with_legacy_server() {
+        let socket_name = format!("test_socket_legacy_{}", std::process::id());
+        let listener = LocalSocketTransport.bind(&socket_name).unwrap();
+        let server = thread::spawn(move || {
+            // A server that predates the handshake and echoes requests back
+            let mut conn = Connection::new(1, listener.accept().unwrap());
+            let msg = conn.recv().unwrap();
+            conn.send(&Message::response(
+                serde_json::json!({"echo": msg.method()}),
+            ))
+            .unwrap();
+        });
+
+        let err = SocketClient::connect_with_handshake(&socket_name, &Handshake::new())
+            .err()
+            .unwrap();
+        assert!(matches!(err, IpcError::Incompatible(_)));
+        server.join().unwrap();
+    }
+
+    #[cfg(not(all(windows, not(feature = "backend-interprocess"))))]
+    #[test]
+    fn test_broadcast_topics() {
+        let socket_name = format!("test_socket_broadcast_{}", std::process::id());
+        let server = SocketServer::at(&socket_name).unwrap();
+        let broadcaster = server.broadcaster();
+
+        let topics = broadcaster.clone();
+        let _server = server.spawn(FnHandler::new(move |conn, msg| {
+            let topic = msg.params().and_then(|p| p.as_str()).unwrap_or_default();
+            topics.subscribe(conn.id(), topic)?;
+            Ok(Some(Message::response(serde_json::json!(conn.id()))))
+        }));
+        thread::sleep(Duration::from_millis(100));
+
+        let mut gui_a = SocketClient::connect(&socket_name).unwrap();
+        let mut gui_b = SocketClient::connect(&socket_name).unwrap();
+        gui_a
+            .request("subscribe", serde_json::json!("tasks"))
+            .unwrap();
+        gui_b
+            .request("subscribe", serde_json::json!("tasks"))
+            .unwrap();
+        gui_b
+            .request("subscribe", serde_json::json!("logs"))
+            .unwrap();
+        assert_eq!(broadcaster.subscribers("tasks").len(), 2);
+
+        let event = Message::json(serde_json::json!({"task": "done"}));
+        assert_eq!(broadcaster.broadcast("tasks", &event), 2);
+        assert_eq!(broadcaster.broadcast("nobody", &event), 0);
+        assert_eq!(gui_a.recv().unwrap().payload["task"], "done");
+        assert_eq!(gui_b.recv().unwrap().payload["task"], "done");
+
+        // Disconnected clients are dropped from every topic
+        drop(gui_b);
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while broadcaster.topics().contains(&"logs".to_string())
+            && std::time::Instant::now() < deadline
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(broadcaster.subscribers("tasks").len(), 1);
+        assert!(broadcaster.subscribers("logs").is_empty());
+
+        let id = broadcaster.subscribers("tasks")[0];
+        assert!(broadcaster.unsubscribe(id, "tasks"));
+        assert!(broadcaster.subscribe(9999, "tasks").is_err());
+    }
+
+    #[test]
+    fn test_frame_hooks() {
+        fn flip(data: &[u8]) -> Result<Vec<u8>> {
+            Ok(data.iter().map(|b| b ^ 0x5a).collect())
+        }
+
+        let socket_name = format!("test_socket_hooks_{}", std::process::id());
+        let config = SocketServerConfig {
+            frame_hooks: FrameHooks::new().before_send(flip).after_recv(flip),
+            ..SocketServerConfig::with_path(&socket_name)
+        };
+        let server = SocketServer::new(config).unwrap();
+        let broadcaster = server.broadcaster();
+
+        let topics = broadcaster.clone();
+        let _server = server.spawn(FnHandler::new(move |conn, msg| {
+            topics.subscribe(conn.id(), "events")?;
+            Ok(Some(Message::response(
+                msg.params().cloned().unwrap_or_default(),
+            )))
+        }));
+        thread::sleep(Duration::from_millis(100));
+
+        let mut client = SocketClient::connect(&socket_name).unwrap();
+        client.connection().on_before_send(flip);
+        client.connection().on_after_recv(flip);
+        let reply = client.request("echo", serde_json::json!("hooked")).unwrap();
+        assert_eq!(reply, "hooked");
+
+        // Broadcasts go through each subscriber's hook as well
+        assert_eq!(
+            broadcaster.broadcast("events", &Message::json(serde_json::json!({"n": 1}))),
+            1
+        );
+        assert_eq!(client.recv().unwrap().payload["n"], 1);
+
+        // A failing receive hook fails the receive and drops the frame
+        client
+            .connection()
+            .on_after_recv(|_| Err(IpcError::deserialization("bad frame")));
+        client
+            .send(&Message::request("echo", serde_json::json!(2)))
+            .unwrap();
+        assert!(matches!(client.recv(), Err(IpcError::Deserialization(_))));
+        client.connection().on_after_recv(flip);
+        let reply = client.request("echo", serde_json::json!(3)).unwrap();
+        assert_eq!(reply, 3);
+    }
+
+    #[test]
+    fn test_connection_registry() {
+        let socket_name = format!("test_socket_registry_{}", std::process::id());
+        let server = SocketServer::at(&socket_name).unwrap();
+        let registry = server.registry();
+        let _server = server.spawn(FnHandler::new(|conn, _msg| {
+            conn.set_client_info("gui");
+            Ok(Some(Message::response(serde_json::json!(conn.id()))))
+        }));
+        thread::sleep(Duration::from_millis(100));
+
+        let mut client = SocketClient::connect(&socket_name).unwrap();
+        let id = client.request("hello", serde_json::json!({})).unwrap();
+        let id = id.as_u64().unwrap();
+
+        let connections = registry.list();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].id, id);
+        assert_eq!(connections[0].metadata.client_info.as_deref(), Some("gui"));
+        assert_eq!(connections[0].messages_received, 1);
+
+        // Kicking the connection closes it and removes it from the registry
+        assert!(registry.disconnect(id));
+        assert!(!registry.disconnect(9999));
+        assert!(client.recv().is_err());
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while !registry.is_empty() && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(registry.get(id).is_none());
+    }
+
+    #[test]
+    fn test_connection_state() {
+        struct Counter(u64);
+
+        #[derive(Clone)]
+        struct Counting;
+
+        impl ConnectionHandler for Counting {
+            fn on_connect(&self, conn: &mut Connection) -> Result<Extensions> {
+                conn.set_state(String::from("set in on_connect"));
+                let mut state = Extensions::new();
+                state.insert(Counter(0));
+                Ok(state)
+            }
+
+            fn on_message(&self, conn: &mut Connection, _msg: Message) -> Result<Option<Message>> {
+                assert!(conn.state::<String>().is_some());
+                let counter = conn.state_mut::<Counter>().unwrap();
+                counter.0 += 1;
+                Ok(Some(Message::response(serde_json::json!(counter.0))))
+            }
+        }
+
+        let socket_name = format!("test_socket_state_{}", std::process::id());
+        let _server = SocketServer::at(&socket_name).unwrap().spawn(Counting);
+        thread::sleep(Duration::from_millis(100));
+
+        let mut a = SocketClient::connect(&socket_name).unwrap();
+        let mut b = SocketClient::connect(&socket_name).unwrap();
+        let count = |client: &mut SocketClient| {
+            client
+                .request("count", serde_json::json!({}))
+                .unwrap()
+                .as_u64()
+        };
+        assert_eq!(count(&mut a), Some(1));
+        assert_eq!(count(&mut a), Some(2));
+        assert_eq!(count(&mut b), Some(1));
+    }
+
+    #[test]
+    fn test_shutdown_drains_in_flight_handlers() {
+        let socket_name = format!("test_socket_drain_{}", std::process::id());
+        let server = SocketServer::at(&socket_name).unwrap();
+        let state = server.shutdown_state();
+        let handle = server.spawn(FnHandler::new(|_conn, _msg| {
+            thread::sleep(Duration::from_millis(300));
+            Ok(Some(Message::response(serde_json::json!("done"))))
+        }));
+        thread::sleep(Duration::from_millis(100));
+
+        let mut busy = SocketClient::connect(&socket_name).unwrap();
+        busy.send(&Message::request("slow", serde_json::json!({})))
+            .unwrap();
+        thread::sleep(Duration::from_millis(50));
+        state.shutdown();
+        assert_eq!(state.pending_count(), 1);
+
+        // Connections made while draining are turned away
+        let mut late = SocketClient::connect(&socket_name).unwrap();
+        let rejected = late.recv().unwrap();
+        assert_eq!(rejected.error_code(), Some(ErrorCode::Closed));
+
+        // The in-flight request still completes before the server stops
+        state.wait_for_drain(Some(Duration::from_secs(2))).unwrap();
+        let response = busy.recv().unwrap();
+        assert_eq!(response.payload["result"], "done");
+        handle.join().unwrap().unwrap();
+    }
 }
@@ -8,7 +8,19 @@
 //! - Task lifecycle management (create, start, pause, resume, cancel, complete)
 //! - Task discovery and filtering
 //! - Real-time progress and log monitoring
-//! - Cooperative cancellation with cancellation tokens
+//! - Cooperative cancellation with cancellation tokens, also across processes
+//!   ([`SharedCancellationToken`])
+//! - Stall detection for running tasks that stop reporting activity
+//! - Deadlines that fail and cancel tasks running too long ([`TaskBuilder::timeout`])
+//! - Blocking and async waits for groups of tasks ([`TaskManager::wait_all`],
+//!   [`TaskManager::wait_any`])
+//! - Task trees: sub-tasks ([`TaskBuilder::parent`]) roll their progress up into
+//!   their parent (weighted by [`TaskBuilder::weight`]) and are cancelled with it
+//! - Recurring tasks on an interval or cron schedule ([`TaskManager::schedule`])
+//! - Typed, backpressured streams of intermediate results
+//!   ([`TaskHandle::output_sender`], [`TaskManager::output_receiver`])
+//! - Callbacks on status changes ([`TaskManager::on_transition`],
+//!   [`TaskManager::on_complete`], [`TaskManager::on_fail`])
 //!
 //! # Example
 //!
@@ -35,14 +47,48 @@
 //! ```
 
 use crate::error::{IpcError, Result};
-use crate::event_stream::{event_types, Event, EventBus, EventBusConfig, EventPublisher};
+use crate::event_stream::{
+    event_types, Event, EventBus, EventBusConfig, EventFilter, EventId, EventPublisher,
+};
+use crate::shm::ShmMapped;
+use crate::task_schedule::{Job, Schedule, ScheduleInfo, Scheduler};
 use crate::thread_pump::ThreadAffinity;
-use parking_lot::RwLock;
+use crossbeam_channel::{Receiver, SendTimeoutError, Sender, TrySendError};
+use parking_lot::{Condvar, Mutex, RwLock};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
-use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often waiters re-check tasks that may have been cancelled through a
+/// linked token, which does not signal the manager.
+const WAIT_RECHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Default number of unread results a task's output channel holds.
+const DEFAULT_OUTPUT_CAPACITY: usize = 64;
+
+/// Bounds for how often a task watchdog checks its task.
+const MIN_STALL_CHECK: Duration = Duration::from_millis(10);
+const MAX_STALL_CHECK: Duration = Duration::from_secs(1);
+
+/// What the manager does when a task stalls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StallAction {
+    /// Only emit `task.stalled` and leave the decision to the frontend
+    #[default]
+    Notify,
+    /// Emit `task.stalled` and mark the task as failed
+    Fail,
+    /// Emit `task.stalled` and cancel the task
+    Cancel,
+    /// Emit `task.stalled`, then fail the task with `"timeout"`, cancel its
+    /// token and emit `task.timeout`
+    Timeout,
+}
 
 /// Task status enumeration.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -110,6 +156,9 @@ pub struct TaskInfo {
     pub name: String,
     /// Task type (e.g., "upload", "download", "build")
     pub task_type: String,
+    /// ID of the task this is a sub-task of
+    #[serde(default)]
+    pub parent_id: Option<String>,
     /// Task status
     pub status: TaskStatus,
     /// Progress (0-100)
@@ -188,6 +237,41 @@ mod option_system_time_serde {
 #[derive(Debug, Clone)]
 pub struct CancellationToken {
     cancelled: Arc<AtomicBool>,
+    parent: Option<Box<CancellationToken>>,
+    shared: Option<SharedCancellationToken>,
+    probe: Option<Arc<CancelProbe>>,
+}
+
+/// Condition polled by [`CancellationToken::is_cancelled`] (internal)
+struct CancelProbe {
+    check: Box<dyn Fn() -> bool + Send + Sync>,
+    interval: Duration,
+    next_check: Mutex<Instant>,
+}
+
+impl CancelProbe {
+    /// Run the check unless it already ran within the interval.
+    fn fired(&self) -> bool {
+        {
+            let Some(mut next_check) = self.next_check.try_lock() else {
+                return false;
+            };
+            let now = Instant::now();
+            if now < *next_check {
+                return false;
+            }
+            *next_check = now + self.interval;
+        }
+        (self.check)()
+    }
+}
+
+impl std::fmt::Debug for CancelProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancelProbe")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Default for CancellationToken {
@@ -201,25 +285,346 @@ impl CancellationToken {
     pub fn new() -> Self {
         Self {
             cancelled: Arc::new(AtomicBool::new(false)),
+            parent: None,
+            shared: None,
+            probe: None,
         }
     }
 
+    /// Also cancel the token once `check` returns `true`.
+    ///
+    /// `check` is run by [`is_cancelled`](Self::is_cancelled) on whichever
+    /// thread asks, at most once per `interval`, so nothing has to watch the
+    /// condition in the background. Child tokens see it too.
+    pub(crate) fn cancel_when<F>(mut self, interval: Duration, check: F) -> Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.probe = Some(Arc::new(CancelProbe {
+            check: Box::new(check),
+            interval,
+            next_check: Mutex::new(Instant::now()),
+        }));
+        self
+    }
+
     /// Trigger cancellation.
+    ///
+    /// A token obtained from [`SharedCancellationToken::token`] also cancels
+    /// the shared flag, and with it every process watching it.
     pub fn cancel(&self) {
         self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(shared) = &self.shared {
+            shared.cancel();
+        }
     }
 
-    /// Check if cancellation has been requested.
+    /// Check if cancellation has been requested on this token or any ancestor.
     pub fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::SeqCst)
+        if self.cancelled.load(Ordering::SeqCst)
+            || self.shared.as_ref().is_some_and(|s| s.is_cancelled())
+            || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+        {
+            return true;
+        }
+        if self.probe.as_ref().is_some_and(|probe| probe.fired()) {
+            self.cancel();
+            return true;
+        }
+        false
     }
 
     /// Create a child token that is cancelled when the parent is cancelled.
+    ///
+    /// Cancelling the child does not affect the parent.
     pub fn child(&self) -> Self {
-        // For simplicity, we share the same atomic
-        // In a more sophisticated implementation, we'd have a hierarchy
         Self {
-            cancelled: Arc::clone(&self.cancelled),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            parent: Some(Box::new(self.clone())),
+            shared: None,
+            probe: None,
+        }
+    }
+}
+
+/// Cancellation flag shared between processes.
+///
+/// One process creates the flag under a name and the others open it; a
+/// cancel from any of them is seen by all. The flag lives in a small shared
+/// memory segment, so checking it is a single atomic load and needs no
+/// server in between, e.g. a GUI cancelling a render running in a worker:
+///
+/// ```rust,no_run
+/// use ipckit::SharedCancellationToken;
+///
+/// // GUI process
+/// let cancel = SharedCancellationToken::create("render-42-cancel")?;
+/// // ... spawn the worker, later:
+/// cancel.cancel();
+///
+/// // Worker process
+/// let token = SharedCancellationToken::open("render-42-cancel")?.token();
+/// while !token.is_cancelled() {
+///     // render the next tile
+/// #   break;
+/// }
+/// # Ok::<(), ipckit::IpcError>(())
+/// ```
+///
+/// The segment is removed when the creating process drops its last clone.
+#[derive(Clone)]
+pub struct SharedCancellationToken {
+    flag: Arc<ShmMapped<u32>>,
+}
+
+impl SharedCancellationToken {
+    /// Create a new, uncancelled flag named `name`.
+    pub fn create(name: &str) -> Result<Self> {
+        Ok(Self {
+            flag: Arc::new(ShmMapped::create(name)?),
+        })
+    }
+
+    /// Open a flag created by another process.
+    pub fn open(name: &str) -> Result<Self> {
+        Ok(Self {
+            flag: Arc::new(ShmMapped::open(name)?),
+        })
+    }
+
+    /// Get the flag name.
+    pub fn name(&self) -> &str {
+        self.flag.shm().name()
+    }
+
+    /// Trigger cancellation in every process sharing the flag.
+    pub fn cancel(&self) {
+        self.atomic().store(1, Ordering::SeqCst);
+    }
+
+    /// Check if any process has cancelled the flag.
+    pub fn is_cancelled(&self) -> bool {
+        self.atomic().load(Ordering::SeqCst) != 0
+    }
+
+    /// A [`CancellationToken`] backed by this flag.
+    ///
+    /// It can be handed to code expecting an ordinary token, including
+    /// [`TaskBuilder::cancel_on`]; its children are cancelled along with it.
+    pub fn token(&self) -> CancellationToken {
+        CancellationToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            parent: None,
+            shared: Some(self.clone()),
+            probe: None,
+        }
+    }
+
+    fn atomic(&self) -> &AtomicU32 {
+        // The value follows a 64-byte header, so it is suitably aligned
+        unsafe { AtomicU32::from_ptr(self.flag.as_ptr()) }
+    }
+}
+
+impl std::fmt::Debug for SharedCancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedCancellationToken")
+            .field("name", &self.name())
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Wakes threads waiting for tasks to finish.
+#[derive(Default)]
+struct CompletionSignal {
+    generation: Mutex<u64>,
+    cond: Condvar,
+    #[cfg(feature = "async")]
+    notify: tokio::sync::Notify,
+}
+
+impl CompletionSignal {
+    fn notify(&self) {
+        *self.generation.lock() += 1;
+        self.cond.notify_all();
+        #[cfg(feature = "async")]
+        self.notify.notify_waiters();
+    }
+
+    /// Block until `done` returns true or the deadline passes.
+    fn wait_until<F: FnMut() -> bool>(&self, deadline: Option<Instant>, mut done: F) -> Result<()> {
+        let mut generation = self.generation.lock();
+        loop {
+            if done() {
+                return Ok(());
+            }
+            let wait = next_wait(deadline)?;
+            self.cond.wait_for(&mut generation, wait);
+        }
+    }
+
+    /// Async version of [`wait_until`](Self::wait_until).
+    #[cfg(feature = "async")]
+    async fn wait_until_async<F: FnMut() -> bool>(
+        &self,
+        deadline: Option<Instant>,
+        mut done: F,
+    ) -> Result<()> {
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            // Register before checking so a completion in between is not missed
+            notified.as_mut().enable();
+
+            if done() {
+                return Ok(());
+            }
+            let wait = next_wait(deadline)?;
+            let _ = tokio::time::timeout(wait, notified).await;
+        }
+    }
+}
+
+/// Watch a task in the background for stalls and missed deadlines.
+///
+/// A stall is a running task with no activity for `stall.0`; a missed
+/// deadline is a task still unfinished `deadline` after it started. The
+/// watchdog exits when the task finishes or is removed from the manager.
+fn watch_task(
+    id: String,
+    state: Weak<TaskState>,
+    publisher: EventPublisher,
+    stall: Option<(Duration, StallAction)>,
+    deadline: Option<Duration>,
+) {
+    let shortest = stall.map(|(t, _)| t).into_iter().chain(deadline).min();
+    let Some(shortest) = shortest else { return };
+    let tick = (shortest / 4).clamp(MIN_STALL_CHECK, MAX_STALL_CHECK);
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(tick);
+
+        let Some(state) = state.upgrade() else { break };
+        let status = state.status();
+        if status.is_terminal() {
+            break;
+        }
+        let handle = || TaskHandle {
+            id: id.clone(),
+            state: Arc::clone(&state),
+            publisher: publisher.clone(),
+        };
+
+        if let Some(deadline) = deadline {
+            let elapsed = state.started.lock().map(|started| started.elapsed());
+            if let Some(elapsed) = elapsed.filter(|elapsed| *elapsed >= deadline) {
+                handle().time_out(serde_json::json!({
+                    "reason": "deadline",
+                    "elapsed_secs": elapsed.as_secs_f64(),
+                    "timeout_secs": deadline.as_secs_f64(),
+                }));
+                break;
+            }
+        }
+
+        let Some((timeout, action)) = stall else {
+            continue;
+        };
+        let idle = state.last_activity.lock().elapsed();
+        if status != TaskStatus::Running
+            || idle < timeout
+            || state.stalled.swap(true, Ordering::SeqCst)
+        {
+            continue;
+        }
+
+        publisher.publish(Event::with_resource(
+            event_types::TASK_STALLED,
+            &id,
+            serde_json::json!({
+                "idle_secs": idle.as_secs_f64(),
+                "timeout_secs": timeout.as_secs_f64(),
+                "action": action,
+            }),
+        ));
+
+        match action {
+            StallAction::Notify => {}
+            StallAction::Fail => {
+                handle().fail(&format!("Task stalled: no activity for {:?}", idle));
+                break;
+            }
+            StallAction::Cancel => {
+                let old = state.cancel();
+                publisher.task_cancelled(&id);
+                state.transitioned(old);
+                break;
+            }
+            StallAction::Timeout => {
+                handle().time_out(serde_json::json!({
+                    "reason": "stalled",
+                    "idle_secs": idle.as_secs_f64(),
+                    "timeout_secs": timeout.as_secs_f64(),
+                }));
+                break;
+            }
+        }
+    });
+}
+
+/// Time to sleep before re-checking, or `Timeout` if the deadline has passed.
+fn next_wait(deadline: Option<Instant>) -> Result<Duration> {
+    match deadline {
+        Some(deadline) => {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(IpcError::Timeout);
+            }
+            Ok(remaining.min(WAIT_RECHECK_INTERVAL))
+        }
+        None => Ok(WAIT_RECHECK_INTERVAL),
+    }
+}
+
+/// Callback run when a task changes status, with its old and new status.
+type TransitionHook = Arc<dyn Fn(&TaskInfo, TaskStatus, TaskStatus) + Send + Sync>;
+
+/// Hooks registered with [`TaskManager::on_transition`].
+#[derive(Default)]
+struct TransitionHooks {
+    next_id: AtomicU64,
+    hooks: RwLock<Vec<(u64, TransitionHook)>>,
+}
+
+impl TransitionHooks {
+    fn add(&self, hook: TransitionHook) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.hooks.write().push((id, hook));
+        id
+    }
+
+    fn remove(&self, id: u64) -> bool {
+        let mut hooks = self.hooks.write();
+        let before = hooks.len();
+        hooks.retain(|(hook_id, _)| *hook_id != id);
+        hooks.len() != before
+    }
+
+    fn is_empty(&self) -> bool {
+        self.hooks.read().is_empty()
+    }
+
+    fn run(&self, info: &TaskInfo, old: TaskStatus, new: TaskStatus) {
+        // Hooks may register or remove hooks, so don't hold the lock
+        let hooks: Vec<TransitionHook> = self
+            .hooks
+            .read()
+            .iter()
+            .map(|(_, hook)| Arc::clone(hook))
+            .collect();
+        for hook in hooks {
+            hook(info, old, new);
         }
     }
 }
@@ -230,31 +635,196 @@ struct TaskState {
     status: AtomicU8,
     progress: AtomicU8,
     cancel_token: CancellationToken,
+    completion: Arc<CompletionSignal>,
+    hooks: Arc<TransitionHooks>,
+    last_activity: Mutex<Instant>,
+    stalled: AtomicBool,
+    /// When the task first started running, for deadlines
+    started: Mutex<Option<Instant>>,
+    /// Share of the parent's progress this task accounts for
+    weight: f64,
+    parent: Option<Weak<TaskState>>,
+    children: Mutex<Vec<Weak<TaskState>>>,
+    /// Buffer size of the output channel
+    output_capacity: usize,
+    /// Intermediate results, created on first use
+    output: OnceLock<(Sender<serde_json::Value>, Receiver<serde_json::Value>)>,
 }
 
 impl TaskState {
-    fn new(info: TaskInfo) -> Self {
+    fn new(
+        info: TaskInfo,
+        cancel_token: CancellationToken,
+        completion: Arc<CompletionSignal>,
+        hooks: Arc<TransitionHooks>,
+    ) -> Self {
         Self {
             status: AtomicU8::new(info.status.into()),
             progress: AtomicU8::new(info.progress),
             info: RwLock::new(info),
-            cancel_token: CancellationToken::new(),
+            cancel_token,
+            completion,
+            hooks,
+            last_activity: Mutex::new(Instant::now()),
+            stalled: AtomicBool::new(false),
+            started: Mutex::new(None),
+            weight: 1.0,
+            parent: None,
+            children: Mutex::new(Vec::new()),
+            output_capacity: DEFAULT_OUTPUT_CAPACITY,
+            output: OnceLock::new(),
+        }
+    }
+
+    fn output(&self) -> &(Sender<serde_json::Value>, Receiver<serde_json::Value>) {
+        self.output
+            .get_or_init(|| crossbeam_channel::bounded(self.output_capacity))
+    }
+
+    /// Recompute the progress of this task's ancestors from their children.
+    ///
+    /// Finished ancestors keep their progress.
+    fn roll_up(&self, publisher: &EventPublisher) {
+        let mut parent = self.parent.as_ref().and_then(Weak::upgrade);
+        while let Some(state) = parent {
+            if state.status().is_terminal() {
+                return;
+            }
+            let Some(progress) = state.children_progress() else {
+                return;
+            };
+            state.set_progress(progress, None);
+            let id = state.info.read().id.clone();
+            publisher.progress(&id, progress as u64, 100, "");
+            parent = state.parent.as_ref().and_then(Weak::upgrade);
+        }
+    }
+
+    /// Weighted average progress of the live children, completed ones
+    /// counting as done.
+    fn children_progress(&self) -> Option<u8> {
+        let mut children = self.children.lock();
+        children.retain(|child| child.strong_count() > 0);
+
+        let (mut done, mut total) = (0.0, 0.0);
+        for child in children.iter().filter_map(Weak::upgrade) {
+            let progress = match child.status() {
+                TaskStatus::Completed => 100,
+                _ => child.progress.load(Ordering::SeqCst),
+            };
+            done += child.weight * progress as f64;
+            total += child.weight;
+        }
+        (total > 0.0).then(|| (done / total).round().min(100.0) as u8)
+    }
+
+    /// Record activity, resetting the stall timer.
+    fn touch(&self) {
+        *self.last_activity.lock() = Instant::now();
+        self.stalled.store(false, Ordering::SeqCst);
+    }
+
+    /// Cancel the task, returning its previous status.
+    fn cancel(&self) -> TaskStatus {
+        self.cancel_token.cancel();
+        let old = self.set_status(TaskStatus::Cancelled);
+        self.info.write().finished_at = Some(SystemTime::now());
+        old
+    }
+
+    /// Cancel the task unless it already finished, returning its previous
+    /// status if it was cancelled now. Of several concurrent calls, one wins.
+    fn cancel_unless_finished(&self) -> Option<TaskStatus> {
+        let mut current = self.status.load(Ordering::SeqCst);
+        loop {
+            if TaskStatus::from(current).is_terminal() {
+                return None;
+            }
+            match self.status.compare_exchange(
+                current,
+                TaskStatus::Cancelled.into(),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => break,
+                Err(actual) => current = actual,
+            }
+        }
+
+        self.cancel_token.cancel();
+        {
+            let mut info = self.info.write();
+            info.status = TaskStatus::Cancelled;
+            info.finished_at = Some(SystemTime::now());
         }
+        self.completion.notify();
+        Some(TaskStatus::from(current))
+    }
+
+    /// Record a cancellation that arrived through a linked token the way
+    /// [`TaskManager::cancel`] does: publish `task.cancelled` and run the
+    /// transition hooks, once. Returns whether the task is finished.
+    fn settle_linked_cancel(&self, id: &str, publisher: &EventPublisher) -> bool {
+        if !self.cancel_token.is_cancelled() {
+            return TaskStatus::from(self.status.load(Ordering::SeqCst)).is_terminal();
+        }
+        if let Some(old) = self.cancel_unless_finished() {
+            publisher.task_cancelled(id);
+            self.transitioned(old);
+        }
+        true
     }
 
     fn get_info(&self) -> TaskInfo {
         let mut info = self.info.read().clone();
-        info.status = TaskStatus::from(self.status.load(Ordering::SeqCst));
+        info.status = self.status();
         info.progress = self.progress.load(Ordering::SeqCst);
         info
     }
 
-    fn set_status(&self, status: TaskStatus) {
-        self.status.store(status.into(), Ordering::SeqCst);
+    fn status(&self) -> TaskStatus {
+        let status = TaskStatus::from(self.status.load(Ordering::SeqCst));
+        // A linked token may have been cancelled without going through the manager
+        if !status.is_terminal() && self.cancel_token.is_cancelled() {
+            TaskStatus::Cancelled
+        } else {
+            status
+        }
+    }
+
+    /// Store a new status, returning the previous one.
+    ///
+    /// Callers run [`transitioned`](Self::transitioned) once the rest of the
+    /// task info is updated.
+    fn set_status(&self, status: TaskStatus) -> TaskStatus {
+        let old = TaskStatus::from(self.status.swap(status.into(), Ordering::SeqCst));
         self.info.write().status = status;
+        if status == TaskStatus::Running {
+            // Time spent pending or paused does not count towards a stall
+            self.touch();
+        }
+        if status.is_terminal() {
+            self.completion.notify();
+        }
+        old
+    }
+
+    /// Run the transition hooks if the status changed from `old`.
+    ///
+    /// Must not be called with the manager's task map locked, as hooks may
+    /// use the manager.
+    fn transitioned(&self, old: TaskStatus) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let info = self.get_info();
+        if info.status != old {
+            self.hooks.run(&info, old, info.status);
+        }
     }
 
     fn set_progress(&self, progress: u8, message: Option<&str>) {
+        self.touch();
         let progress = progress.min(100);
         self.progress.store(progress, Ordering::SeqCst);
 
@@ -287,7 +857,7 @@ impl TaskHandle {
 
     /// Get the current status.
     pub fn status(&self) -> TaskStatus {
-        TaskStatus::from(self.state.status.load(Ordering::SeqCst))
+        self.state.status()
     }
 
     /// Get the current progress.
@@ -296,30 +866,51 @@ impl TaskHandle {
     }
 
     /// Update the task progress.
+    ///
+    /// The progress of the task's ancestors is recomputed from their children.
     pub fn set_progress(&self, progress: u8, message: Option<&str>) {
         self.state.set_progress(progress, message);
         self.publisher
             .progress(&self.id, progress as u64, 100, message.unwrap_or(""));
+        self.state.roll_up(&self.publisher);
     }
 
     /// Publish a log message.
     pub fn log(&self, level: &str, message: &str) {
+        self.state.touch();
         self.publisher.log(&self.id, level, message);
     }
 
     /// Publish stdout output.
     pub fn stdout(&self, line: &str) {
+        self.state.touch();
         self.publisher.stdout(&self.id, line);
     }
 
     /// Publish stderr output.
     pub fn stderr(&self, line: &str) {
+        self.state.touch();
         self.publisher.stderr(&self.id, line);
     }
 
+    /// Report that the task is still alive without publishing anything.
+    ///
+    /// Resets the stall timer for tasks created with
+    /// [`TaskBuilder::stall_timeout`].
+    pub fn heartbeat(&self) {
+        self.state.touch();
+    }
+
     /// Check if cancellation has been requested.
+    ///
+    /// A cancellation through a linked token is recorded on the first check
+    /// that sees it.
     pub fn is_cancelled(&self) -> bool {
-        self.state.cancel_token.is_cancelled()
+        if !self.state.cancel_token.is_cancelled() {
+            return false;
+        }
+        self.state.settle_linked_cancel(&self.id, &self.publisher);
+        true
     }
 
     /// Get the cancellation token.
@@ -329,14 +920,16 @@ impl TaskHandle {
 
     /// Mark the task as started.
     pub fn start(&self) {
-        self.state.set_status(TaskStatus::Running);
+        self.state.started.lock().get_or_insert_with(Instant::now);
+        let old = self.state.set_status(TaskStatus::Running);
         self.state.info.write().started_at = Some(SystemTime::now());
         self.publisher.task_started(&self.id, serde_json::json!({}));
+        self.state.transitioned(old);
     }
 
     /// Mark the task as completed with a result.
     pub fn complete(&self, result: serde_json::Value) {
-        self.state.set_status(TaskStatus::Completed);
+        let old = self.state.set_status(TaskStatus::Completed);
         self.state.set_progress(100, Some("Completed"));
 
         {
@@ -346,11 +939,13 @@ impl TaskHandle {
         }
 
         self.publisher.task_completed(&self.id, result);
+        self.state.roll_up(&self.publisher);
+        self.state.transitioned(old);
     }
 
     /// Mark the task as failed with an error.
     pub fn fail(&self, error: &str) {
-        self.state.set_status(TaskStatus::Failed);
+        let old = self.state.set_status(TaskStatus::Failed);
 
         {
             let mut info = self.state.info.write();
@@ -359,21 +954,207 @@ impl TaskHandle {
         }
 
         self.publisher.task_failed(&self.id, error);
+        self.state.transitioned(old);
+    }
+
+    /// Fail the task with `"timeout"`, fire its cancellation token so the
+    /// work stops, and emit `task.timeout` with `details`.
+    fn time_out(&self, details: serde_json::Value) {
+        if self.state.status().is_terminal() {
+            return;
+        }
+        // Fail before cancelling, otherwise the tripped token reads as Cancelled
+        let old = self.state.set_status(TaskStatus::Failed);
+        {
+            let mut info = self.state.info.write();
+            info.finished_at = Some(SystemTime::now());
+            info.error = Some("timeout".to_string());
+        }
+        self.state.cancel_token.cancel();
+
+        self.publisher.publish(Event::with_resource(
+            event_types::TASK_TIMEOUT,
+            &self.id,
+            details,
+        ));
+        self.publisher.task_failed(&self.id, "timeout");
+        self.state.transitioned(old);
     }
 
     /// Get the event publisher for this task.
     pub fn publisher(&self) -> &EventPublisher {
         &self.publisher
     }
+
+    /// Get a sender for streaming intermediate results of type `T`.
+    ///
+    /// Consumers read them in order through
+    /// [`TaskManager::output_receiver`]. The channel holds
+    /// [`TaskBuilder::output_buffer`] results; once it is full,
+    /// [`TaskOutputSender::send`] blocks until a consumer catches up.
+    pub fn output_sender<T: Serialize>(&self) -> TaskOutputSender<T> {
+        TaskOutputSender {
+            state: Arc::clone(&self.state),
+            tx: self.state.output().0.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Sending half of a task's output channel.
+///
+/// Created with [`TaskHandle::output_sender`]; clones send into the same
+/// channel.
+pub struct TaskOutputSender<T> {
+    state: Arc<TaskState>,
+    tx: Sender<serde_json::Value>,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T> Clone for TaskOutputSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+            tx: self.tx.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize> TaskOutputSender<T> {
+    /// Send a result, waiting while the channel is full.
+    ///
+    /// Fails with [`IpcError::Closed`] once the task has finished or been
+    /// cancelled, including while waiting.
+    pub fn send(&self, item: &T) -> Result<()> {
+        let mut value = self.encode(item)?;
+        loop {
+            match self.tx.send_timeout(value, WAIT_RECHECK_INTERVAL) {
+                Ok(()) => {
+                    self.state.touch();
+                    return Ok(());
+                }
+                Err(SendTimeoutError::Timeout(v)) if !self.state.status().is_terminal() => {
+                    value = v;
+                }
+                Err(_) => return Err(IpcError::Closed),
+            }
+        }
+    }
+
+    /// Send a result without waiting.
+    ///
+    /// Fails with [`IpcError::WouldBlock`] while the channel is full.
+    pub fn try_send(&self, item: &T) -> Result<()> {
+        let value = self.encode(item)?;
+        match self.tx.try_send(value) {
+            Ok(()) => {
+                self.state.touch();
+                Ok(())
+            }
+            Err(TrySendError::Full(_)) => Err(IpcError::WouldBlock),
+            Err(TrySendError::Disconnected(_)) => Err(IpcError::Closed),
+        }
+    }
+
+    fn encode(&self, item: &T) -> Result<serde_json::Value> {
+        if self.state.status().is_terminal() {
+            return Err(IpcError::Closed);
+        }
+        serde_json::to_value(item).map_err(|e| IpcError::serialization(e.to_string()))
+    }
+}
+
+/// Receiving half of a task's output channel.
+///
+/// Created with [`TaskManager::output_receiver`]. Each result is delivered
+/// to one receiver, so several receivers of the same task share the work.
+/// Iterating yields results until the task has finished and the channel is
+/// drained.
+pub struct TaskOutputReceiver<T> {
+    state: Arc<TaskState>,
+    rx: Receiver<serde_json::Value>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> TaskOutputReceiver<T> {
+    /// Wait for the next result.
+    ///
+    /// Returns `None` once the task has finished and every result it sent
+    /// has been received.
+    pub fn recv(&self) -> Result<Option<T>> {
+        self.recv_deadline(None)
+    }
+
+    /// Wait up to `timeout` for the next result.
+    ///
+    /// Fails with [`IpcError::Timeout`] if none arrives in time.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<T>> {
+        self.recv_deadline(Some(Instant::now() + timeout))
+    }
+
+    /// Take the next result if one is waiting.
+    pub fn try_recv(&self) -> Result<Option<T>> {
+        match self.rx.try_recv() {
+            Ok(value) => Self::decode(value).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn recv_deadline(&self, deadline: Option<Instant>) -> Result<Option<T>> {
+        loop {
+            // Results sent before the task finished are still delivered
+            let finished = self.state.status().is_terminal();
+            let wait = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return match self.rx.try_recv() {
+                            Ok(value) => Self::decode(value).map(Some),
+                            Err(_) if finished => Ok(None),
+                            Err(_) => Err(IpcError::Timeout),
+                        };
+                    }
+                    left.min(WAIT_RECHECK_INTERVAL)
+                }
+                None => WAIT_RECHECK_INTERVAL,
+            };
+            match self.rx.recv_timeout(wait) {
+                Ok(value) => return Self::decode(value).map(Some),
+                Err(_) if finished => return Ok(None),
+                Err(_) => {}
+            }
+        }
+    }
+
+    fn decode(value: serde_json::Value) -> Result<T> {
+        serde_json::from_value(value).map_err(|e| IpcError::deserialization(e.to_string()))
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for TaskOutputReceiver<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        self.recv().transpose()
+    }
 }
 
 /// Builder for creating tasks.
 #[derive(Debug, Clone)]
 pub struct TaskBuilder {
+    id: Option<String>,
     name: String,
     task_type: String,
+    parent_id: Option<String>,
+    cancel_parent: Option<CancellationToken>,
     metadata: HashMap<String, serde_json::Value>,
     labels: HashMap<String, String>,
+    stall_timeout: Option<Duration>,
+    stall_action: StallAction,
+    timeout: Option<Duration>,
+    weight: f64,
+    output_capacity: usize,
     /// Thread affinity requirement for this task.
     pub affinity: ThreadAffinity,
 }
@@ -382,14 +1163,59 @@ impl TaskBuilder {
     /// Create a new task builder.
     pub fn new(name: &str, task_type: &str) -> Self {
         Self {
+            id: None,
             name: name.to_string(),
             task_type: task_type.to_string(),
+            parent_id: None,
+            cancel_parent: None,
             metadata: HashMap::new(),
             labels: HashMap::new(),
+            stall_timeout: None,
+            stall_action: StallAction::Notify,
+            timeout: None,
+            weight: 1.0,
+            output_capacity: DEFAULT_OUTPUT_CAPACITY,
             affinity: ThreadAffinity::Any,
         }
     }
 
+    /// Use a caller-provided task ID instead of a generated one.
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Make the task a sub-task of the task `id`.
+    ///
+    /// The parent's progress follows the weighted average of its sub-tasks,
+    /// and cancelling the parent cancels its unfinished sub-tasks. Unless the
+    /// task has its own [`cancel_on`](Self::cancel_on) token, its token is a
+    /// child of the parent's, so cancelling the parent's handle directly
+    /// reaches it too.
+    pub fn parent(mut self, id: &str) -> Self {
+        self.parent_id = Some(id.to_string());
+        self
+    }
+
+    /// Set how much this task counts towards its parent's progress
+    /// (default: 1.0).
+    ///
+    /// A sub-task with weight 3 moves the parent three times as far as one
+    /// with weight 1. Negative weights are treated as 0.
+    pub fn weight(mut self, weight: f64) -> Self {
+        self.weight = weight.max(0.0);
+        self
+    }
+
+    /// Cancel the task whenever `token` is cancelled.
+    ///
+    /// Pass an API [`Request`](crate::api_server::Request)'s `cancel_token` to
+    /// cancel the task when the client aborts the request.
+    pub fn cancel_on(mut self, token: &CancellationToken) -> Self {
+        self.cancel_parent = Some(token.clone());
+        self
+    }
+
     /// Set the thread affinity requirement for this task.
     ///
     /// Tasks with [`ThreadAffinity::Main`] must be executed by the host's
@@ -400,6 +1226,40 @@ impl TaskBuilder {
         self
     }
 
+    /// Treat the task as stalled when it runs for `timeout` without any
+    /// progress, log output or heartbeat.
+    ///
+    /// A stalled task emits `task.stalled` once per quiet period, then
+    /// [`on_stall`](Self::on_stall) decides what else happens.
+    pub fn stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = Some(timeout);
+        self
+    }
+
+    /// Set what happens when the task stalls (default: only notify).
+    pub fn on_stall(mut self, action: StallAction) -> Self {
+        self.stall_action = action;
+        self
+    }
+
+    /// Fail the task if it is still unfinished `timeout` after it started.
+    ///
+    /// A timed-out task is marked [`TaskStatus::Failed`] with the error
+    /// `"timeout"`, its cancellation token is fired so the work (or a hosted
+    /// process) stops, and `task.timeout` is emitted. Time spent paused
+    /// counts towards the deadline.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set how many unread results the task's output channel holds before
+    /// [`TaskOutputSender::send`] waits (default: 64).
+    pub fn output_buffer(mut self, capacity: usize) -> Self {
+        self.output_capacity = capacity.max(1);
+        self
+    }
+
     /// Add metadata to the task.
     pub fn metadata(mut self, key: &str, value: serde_json::Value) -> Self {
         self.metadata.insert(key.to_string(), value);
@@ -411,6 +1271,11 @@ impl TaskBuilder {
         self.labels.insert(key.to_string(), value.to_string());
         self
     }
+
+    /// The task name (internal).
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
 }
 
 /// Task filter for querying tasks.
@@ -420,6 +1285,10 @@ pub struct TaskFilter {
     pub status: Option<Vec<TaskStatus>>,
     /// Filter by task type
     pub task_type: Option<String>,
+    /// Filter by parent task ID
+    pub parent_id: Option<String>,
+    /// Filter by ancestor task ID (any depth)
+    pub children_of: Option<String>,
     /// Filter by labels
     pub labels: HashMap<String, String>,
     /// Show only active tasks
@@ -450,6 +1319,23 @@ impl TaskFilter {
         self
     }
 
+    /// Filter by parent task, listing its direct sub-tasks.
+    pub fn parent(mut self, id: &str) -> Self {
+        self.parent_id = Some(id.to_string());
+        self
+    }
+
+    /// Filter by ancestor task, listing its whole subtree (not the task
+    /// itself).
+    ///
+    /// Resolving a subtree needs the other tasks, so this is applied by
+    /// [`TaskManager::list`]; [`matches`](Self::matches) only checks that the
+    /// task is a sub-task of something.
+    pub fn children_of(mut self, id: &str) -> Self {
+        self.children_of = Some(id.to_string());
+        self
+    }
+
     /// Show only active tasks.
     pub fn active(mut self) -> Self {
         self.active_only = true;
@@ -477,6 +1363,14 @@ impl TaskFilter {
             }
         }
 
+        // Check parent
+        if self.parent_id.is_some() && info.parent_id != self.parent_id {
+            return false;
+        }
+        if self.children_of.is_some() && info.parent_id.is_none() {
+            return false;
+        }
+
         // Check labels
         for (key, value) in &self.labels {
             match info.labels.get(key) {
@@ -510,12 +1404,83 @@ impl Default for TaskManagerConfig {
     }
 }
 
+/// IDs of all tasks below `id` in the task tree.
+fn descendants(tasks: &HashMap<String, Arc<TaskState>>, id: &str) -> HashSet<String> {
+    let mut found = HashSet::new();
+    let mut parents = vec![id.to_string()];
+    while let Some(parent) = parents.pop() {
+        for (child_id, child) in tasks {
+            let is_child = child.info.read().parent_id.as_deref() == Some(parent.as_str());
+            if is_child && child_id != id && found.insert(child_id.clone()) {
+                parents.push(child_id.clone());
+            }
+        }
+    }
+    found
+}
+
+/// Records the cancellation of tasks whose linked token was cancelled
+/// without going through the manager, for tasks nobody checks (internal).
+///
+/// One thread polls every linked task while any is unfinished.
+#[derive(Default)]
+struct LinkWatcher {
+    tasks: Mutex<Vec<(String, Weak<TaskState>)>>,
+    running: AtomicBool,
+}
+
+impl LinkWatcher {
+    fn watch(self: &Arc<Self>, id: &str, state: &Arc<TaskState>, publisher: &EventPublisher) {
+        let mut tasks = self.tasks.lock();
+        tasks.push((id.to_string(), Arc::downgrade(state)));
+        if self.running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        drop(tasks);
+
+        let watcher = Arc::clone(self);
+        let publisher = publisher.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(WAIT_RECHECK_INTERVAL);
+
+            let watched: Vec<_> = {
+                let mut tasks = watcher.tasks.lock();
+                tasks.retain(|(_, state)| state.strong_count() > 0);
+                if tasks.is_empty() {
+                    // Under the lock, so a task watched meanwhile starts a new thread
+                    watcher.running.store(false, Ordering::SeqCst);
+                    return;
+                }
+                tasks.clone()
+            };
+
+            // Hooks run while settling may create linked tasks, so the list
+            // is not locked here
+            let finished: HashSet<String> = watched
+                .into_iter()
+                .filter_map(|(id, state)| {
+                    let state = state.upgrade()?;
+                    state.settle_linked_cancel(&id, &publisher).then_some(id)
+                })
+                .collect();
+            watcher
+                .tasks
+                .lock()
+                .retain(|(id, _)| !finished.contains(id));
+        });
+    }
+}
+
 /// Task manager for creating and managing tasks.
 pub struct TaskManager {
     tasks: RwLock<HashMap<String, Arc<TaskState>>>,
     event_bus: EventBus,
     config: TaskManagerConfig,
     next_id: AtomicU64,
+    completion: Arc<CompletionSignal>,
+    hooks: Arc<TransitionHooks>,
+    scheduler: Arc<Scheduler>,
+    links: Arc<LinkWatcher>,
 }
 
 impl TaskManager {
@@ -528,17 +1493,60 @@ impl TaskManager {
             event_bus,
             config,
             next_id: AtomicU64::new(1),
+            completion: Arc::new(CompletionSignal::default()),
+            hooks: Arc::default(),
+            scheduler: Arc::default(),
+            links: Arc::default(),
         }
     }
 
     /// Create a new task.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a task with the builder's explicit ID already exists. Use
+    /// [`try_create`](Self::try_create) for IDs that come from elsewhere.
+    /// Generated IDs never collide.
     pub fn create(&self, builder: TaskBuilder) -> TaskHandle {
-        let id = format!("task-{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        self.try_create(builder).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Create a new task, failing with [`IpcError::AlreadyExists`] if a task
+    /// with the builder's explicit ID already exists.
+    ///
+    /// Without an explicit ID, the next free `task-{n}` is used, skipping
+    /// IDs callers have taken themselves.
+    pub fn try_create(&self, builder: TaskBuilder) -> Result<TaskHandle> {
+        let mut tasks = self.tasks.write();
+        let id = match builder.id {
+            Some(id) if tasks.contains_key(&id) => {
+                return Err(IpcError::AlreadyExists(format!("task {}", id)));
+            }
+            Some(id) => id,
+            // Skip generated IDs a caller has already taken explicitly
+            None => loop {
+                let id = format!("task-{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+                if !tasks.contains_key(&id) {
+                    break id;
+                }
+            },
+        };
+        let parent = builder
+            .parent_id
+            .as_ref()
+            .and_then(|id| tasks.get(id).cloned());
+        let linked = builder.cancel_parent.is_some() || parent.is_some();
+        let cancel_token = match (builder.cancel_parent, &parent) {
+            (Some(token), _) => token.child(),
+            (None, Some(parent)) => parent.cancel_token.child(),
+            (None, None) => CancellationToken::new(),
+        };
 
         let info = TaskInfo {
             id: id.clone(),
             name: builder.name,
             task_type: builder.task_type,
+            parent_id: builder.parent_id,
             status: TaskStatus::Pending,
             progress: 0,
             progress_message: None,
@@ -552,21 +1560,53 @@ impl TaskManager {
             result: None,
         };
 
-        let state = Arc::new(TaskState::new(info));
-        self.tasks.write().insert(id.clone(), Arc::clone(&state));
+        let state = Arc::new(TaskState {
+            weight: builder.weight,
+            output_capacity: builder.output_capacity,
+            parent: parent.as_ref().map(Arc::downgrade),
+            ..TaskState::new(
+                info,
+                cancel_token,
+                Arc::clone(&self.completion),
+                Arc::clone(&self.hooks),
+            )
+        });
+        if let Some(ref parent) = parent {
+            parent.children.lock().push(Arc::downgrade(&state));
+        }
+        tasks.insert(id.clone(), Arc::clone(&state));
+        drop(tasks);
 
         let publisher = self.event_bus.publisher();
+        if linked {
+            self.links.watch(&id, &state, &publisher);
+        }
         publisher.publish(Event::with_resource(
             event_types::TASK_CREATED,
             &id,
             serde_json::json!({}),
         ));
+        // A new sub-task has made no progress yet
+        state.roll_up(&publisher);
+
+        let stall = builder
+            .stall_timeout
+            .map(|timeout| (timeout, builder.stall_action));
+        if stall.is_some() || builder.timeout.is_some() {
+            watch_task(
+                id.clone(),
+                Arc::downgrade(&state),
+                publisher.clone(),
+                stall,
+                builder.timeout,
+            );
+        }
 
-        TaskHandle {
+        Ok(TaskHandle {
             id,
             state,
             publisher,
-        }
+        })
     }
 
     /// Spawn a task with a closure.
@@ -585,6 +1625,85 @@ impl TaskManager {
         handle
     }
 
+    /// Create a task from `builder` every time `schedule` fires and run
+    /// `job` with it on a new thread, as [`spawn`](Self::spawn) does.
+    ///
+    /// Returns the schedule ID, which each instance carries as its
+    /// `schedule` label. Fails for an invalid cron expression, and for a
+    /// builder with an ID, as every instance needs its own. See
+    /// [`task_schedule`](crate::task_schedule) for the schedule semantics.
+    pub fn schedule<F>(
+        self: &Arc<Self>,
+        builder: TaskBuilder,
+        schedule: Schedule,
+        job: F,
+    ) -> Result<String>
+    where
+        F: Fn(TaskHandle) + Send + Sync + 'static,
+    {
+        if let Some(id) = &builder.id {
+            return Err(IpcError::InvalidState(format!(
+                "scheduled task cannot have the fixed ID {}",
+                id
+            )));
+        }
+        self.scheduler.add(self, builder, schedule, job)
+    }
+
+    /// Stop a schedule from firing until it is resumed.
+    pub fn pause_schedule(&self, id: &str) -> Result<()> {
+        self.scheduler.set_paused(id, true)
+    }
+
+    /// Resume a paused schedule. Runs missed while paused are skipped.
+    pub fn resume_schedule(&self, id: &str) -> Result<()> {
+        self.scheduler.set_paused(id, false)
+    }
+
+    /// Remove a schedule. Runs already started are not affected.
+    pub fn unschedule(&self, id: &str) -> Result<()> {
+        self.scheduler.remove(id)
+    }
+
+    /// Get a schedule with its run history.
+    pub fn schedule_info(&self, id: &str) -> Option<ScheduleInfo> {
+        self.scheduler.info(id)
+    }
+
+    /// List all schedules.
+    pub fn schedules(&self) -> Vec<ScheduleInfo> {
+        self.scheduler.list()
+    }
+
+    /// Create and start one run of a schedule (internal).
+    pub(crate) fn run_scheduled(&self, builder: TaskBuilder, job: Job) -> TaskHandle {
+        let handle = self.create(builder);
+        let handle_clone = handle.clone();
+
+        std::thread::spawn(move || {
+            handle_clone.start();
+            job(handle_clone);
+        });
+
+        handle
+    }
+
+    /// Get a receiver for the intermediate results the task `id` streams
+    /// through [`TaskHandle::output_sender`].
+    pub fn output_receiver<T: DeserializeOwned>(&self, id: &str) -> Result<TaskOutputReceiver<T>> {
+        let state = self
+            .tasks
+            .read()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| IpcError::NotFound(id.to_string()))?;
+        Ok(TaskOutputReceiver {
+            rx: state.output().1.clone(),
+            state,
+            _marker: PhantomData,
+        })
+    }
+
     /// Get task information by ID.
     pub fn get(&self, id: &str) -> Option<TaskInfo> {
         self.tasks.read().get(id).map(|s| s.get_info())
@@ -601,27 +1720,44 @@ impl TaskManager {
 
     /// List tasks matching the filter.
     pub fn list(&self, filter: &TaskFilter) -> Vec<TaskInfo> {
-        self.tasks
-            .read()
-            .values()
-            .map(|s| s.get_info())
+        let tasks = self.tasks.read();
+        let subtree = filter
+            .children_of
+            .as_ref()
+            .map(|id| descendants(&tasks, id));
+        tasks
+            .iter()
+            .filter(|(id, _)| subtree.as_ref().is_none_or(|ids| ids.contains(*id)))
+            .map(|(_, s)| s.get_info())
             .filter(|info| filter.matches(info))
             .collect()
     }
 
-    /// Cancel a task.
+    /// Cancel a task and its unfinished sub-tasks.
     pub fn cancel(&self, id: &str) -> Result<()> {
         let tasks = self.tasks.read();
         let state = tasks
             .get(id)
             .ok_or_else(|| IpcError::NotFound(id.to_string()))?;
 
-        state.cancel_token.cancel();
-        state.set_status(TaskStatus::Cancelled);
-        state.info.write().finished_at = Some(SystemTime::now());
-
-        self.event_bus.publisher().task_cancelled(id);
+        let mut cancelled = vec![(Arc::clone(state), state.cancel())];
+        let publisher = self.event_bus.publisher();
+        publisher.task_cancelled(id);
+
+        // Cancel unfinished sub-tasks, all the way down the tree. Their
+        // linked tokens already read as cancelled, so go by the stored status.
+        for child_id in descendants(&tasks, id) {
+            let child = &tasks[&child_id];
+            if let Some(old) = child.cancel_unless_finished() {
+                cancelled.push((Arc::clone(child), old));
+                publisher.task_cancelled(&child_id);
+            }
+        }
+        drop(tasks);
 
+        for (state, old) in cancelled {
+            state.transitioned(old);
+        }
         Ok(())
     }
 
@@ -632,7 +1768,7 @@ impl TaskManager {
             .get(id)
             .ok_or_else(|| IpcError::NotFound(id.to_string()))?;
 
-        let current = TaskStatus::from(state.status.load(Ordering::SeqCst));
+        let current = state.status();
         if current != TaskStatus::Running {
             return Err(IpcError::InvalidState(format!(
                 "Cannot pause task in {:?} state",
@@ -640,12 +1776,15 @@ impl TaskManager {
             )));
         }
 
-        state.set_status(TaskStatus::Paused);
+        let old = state.set_status(TaskStatus::Paused);
+        let state = Arc::clone(state);
+        drop(tasks);
         self.event_bus.publisher().publish(Event::with_resource(
             event_types::TASK_PAUSED,
             id,
             serde_json::json!({}),
         ));
+        state.transitioned(old);
 
         Ok(())
     }
@@ -657,7 +1796,7 @@ impl TaskManager {
             .get(id)
             .ok_or_else(|| IpcError::NotFound(id.to_string()))?;
 
-        let current = TaskStatus::from(state.status.load(Ordering::SeqCst));
+        let current = state.status();
         if current != TaskStatus::Paused {
             return Err(IpcError::InvalidState(format!(
                 "Cannot resume task in {:?} state",
@@ -665,16 +1804,110 @@ impl TaskManager {
             )));
         }
 
-        state.set_status(TaskStatus::Running);
+        let old = state.set_status(TaskStatus::Running);
+        let state = Arc::clone(state);
+        drop(tasks);
         self.event_bus.publisher().publish(Event::with_resource(
             event_types::TASK_RESUMED,
             id,
             serde_json::json!({}),
         ));
+        state.transitioned(old);
 
         Ok(())
     }
 
+    /// Send a command to whoever owns a task.
+    ///
+    /// Commands are published as `task.command` events carrying
+    /// `{"command", "args"}`; a remote owner such as a `CliBridge` picks them
+    /// up with [`wait_commands`](Self::wait_commands). Returns the event ID.
+    pub fn send_command(
+        &self,
+        id: &str,
+        command: &str,
+        args: serde_json::Value,
+    ) -> Result<EventId> {
+        if !self.tasks.read().contains_key(id) {
+            return Err(IpcError::NotFound(id.to_string()));
+        }
+
+        let event = Event::with_resource(
+            event_types::TASK_COMMAND,
+            id,
+            serde_json::json!({ "command": command, "args": args }),
+        );
+        let event_id = event.id;
+        self.event_bus.publish(event);
+        Ok(event_id)
+    }
+
+    /// Wait for commands sent to a task after the event `after`.
+    ///
+    /// Returns as soon as at least one command is pending or the task has
+    /// finished, or with an empty list once `timeout` elapses. This is the
+    /// long-poll used by remote task owners to receive commands and notice
+    /// cancellation without hammering the server.
+    pub fn wait_commands(
+        &self,
+        id: &str,
+        after: Option<EventId>,
+        timeout: Duration,
+    ) -> Result<(TaskStatus, Vec<Event>)> {
+        self.wait_events(id, after, timeout, event_types::TASK_COMMAND)
+    }
+
+    /// Wait for log and output lines published by a task after the event
+    /// `after`.
+    ///
+    /// Behaves like [`wait_commands`](Self::wait_commands); with a zero
+    /// `timeout` it returns the retained log history straight away. Used by
+    /// `ipckit task logs -f` to follow a task.
+    pub fn wait_logs(
+        &self,
+        id: &str,
+        after: Option<EventId>,
+        timeout: Duration,
+    ) -> Result<(TaskStatus, Vec<Event>)> {
+        self.wait_events(id, after, timeout, "log.*")
+    }
+
+    fn wait_events(
+        &self,
+        id: &str,
+        after: Option<EventId>,
+        timeout: Duration,
+        event_type: &str,
+    ) -> Result<(TaskStatus, Vec<Event>)> {
+        // Subscribe before reading history so nothing slips in between
+        let subscriber = self.event_bus.subscribe(EventFilter::new().resource(id));
+        let filter = EventFilter::new().event_type(event_type).resource(id);
+        let mut events = self.event_bus.history_after(after, &filter);
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let status = self
+                .get(id)
+                .ok_or_else(|| IpcError::NotFound(id.to_string()))?
+                .status;
+            if !events.is_empty() || status.is_terminal() {
+                events.sort_by_key(|e| e.id);
+                events.dedup_by_key(|e| e.id);
+                return Ok((status, events));
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match subscriber.recv_timeout(remaining) {
+                Ok(event) => {
+                    if filter.matches(&event) && after.is_none_or(|last| event.id > last) {
+                        events.push(event);
+                    }
+                }
+                Err(_) => return Ok((status, events)),
+            }
+        }
+    }
+
     /// Remove a completed task from the manager.
     pub fn remove(&self, id: &str) -> Result<()> {
         let mut tasks = self.tasks.write();
@@ -682,7 +1915,7 @@ impl TaskManager {
             .get(id)
             .ok_or_else(|| IpcError::NotFound(id.to_string()))?;
 
-        let status = TaskStatus::from(state.status.load(Ordering::SeqCst));
+        let status = state.status();
         if !status.is_terminal() {
             return Err(IpcError::InvalidState(format!(
                 "Cannot remove task in {:?} state",
@@ -715,6 +1948,183 @@ impl TaskManager {
         });
     }
 
+    /// Block until every listed task reaches a terminal state.
+    ///
+    /// Returns the final info of each task, in the order given. Fails with
+    /// [`IpcError::NotFound`] if any ID is unknown and [`IpcError::Timeout`]
+    /// if the tasks are still running when `timeout` expires.
+    pub fn wait_all<I, S>(&self, ids: I, timeout: Option<Duration>) -> Result<Vec<TaskInfo>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let states = self.states_for(ids)?;
+        let deadline = timeout.map(|t| Instant::now() + t);
+        self.completion
+            .wait_until(deadline, || all_terminal(&states))?;
+        Ok(states.iter().map(|s| s.get_info()).collect())
+    }
+
+    /// Block until any of the listed tasks reaches a terminal state.
+    ///
+    /// Returns the info of the first finished task found. Fails like
+    /// [`wait_all`](Self::wait_all), and with [`IpcError::InvalidState`] if
+    /// `ids` is empty.
+    pub fn wait_any<I, S>(&self, ids: I, timeout: Option<Duration>) -> Result<TaskInfo>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let states = self.states_for(ids)?;
+        if states.is_empty() {
+            return Err(IpcError::InvalidState(
+                "wait_any needs at least one task".into(),
+            ));
+        }
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut finished = None;
+        self.completion.wait_until(deadline, || {
+            finished = first_terminal(&states);
+            finished.is_some()
+        })?;
+        Ok(finished.expect("set when the wait succeeds"))
+    }
+
+    /// Async version of [`wait_all`](Self::wait_all).
+    #[cfg(feature = "async")]
+    pub async fn wait_all_async<I, S>(
+        &self,
+        ids: I,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<TaskInfo>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let states = self.states_for(ids)?;
+        let deadline = timeout.map(|t| Instant::now() + t);
+        self.completion
+            .wait_until_async(deadline, || all_terminal(&states))
+            .await?;
+        Ok(states.iter().map(|s| s.get_info()).collect())
+    }
+
+    /// Async version of [`wait_any`](Self::wait_any).
+    #[cfg(feature = "async")]
+    pub async fn wait_any_async<I, S>(&self, ids: I, timeout: Option<Duration>) -> Result<TaskInfo>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let states = self.states_for(ids)?;
+        if states.is_empty() {
+            return Err(IpcError::InvalidState(
+                "wait_any needs at least one task".into(),
+            ));
+        }
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut finished = None;
+        self.completion
+            .wait_until_async(deadline, || {
+                finished = first_terminal(&states);
+                finished.is_some()
+            })
+            .await?;
+        Ok(finished.expect("set when the wait succeeds"))
+    }
+
+    fn states_for<I, S>(&self, ids: I) -> Result<Vec<Arc<TaskState>>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let tasks = self.tasks.read();
+        ids.into_iter()
+            .map(|id| {
+                let id = id.as_ref();
+                tasks
+                    .get(id)
+                    .cloned()
+                    .ok_or_else(|| IpcError::NotFound(id.to_string()))
+            })
+            .collect()
+    }
+
+    /// Run `hook` whenever a task changes status, with the task's info and
+    /// its old and new status. Returns an ID for [`remove_hook`](Self::remove_hook).
+    ///
+    /// Hooks run on the thread that made the change, after the task's info
+    /// is updated and its event published; they may use the manager. A task
+    /// cancelled through a linked token transitions when the cancellation is
+    /// noticed: by [`TaskHandle::is_cancelled`], or by the manager within a
+    /// short polling interval.
+    ///
+    /// ```rust
+    /// use ipckit::{TaskBuilder, TaskManager, TaskStatus};
+    ///
+    /// let manager = TaskManager::new(Default::default());
+    /// manager.on_transition(|info, old, new| {
+    ///     println!("{}: {:?} -> {:?}", info.id, old, new);
+    /// });
+    ///
+    /// let handle = manager.create(TaskBuilder::new("Build", "build"));
+    /// handle.start();
+    /// ```
+    pub fn on_transition<F>(&self, hook: F) -> u64
+    where
+        F: Fn(&TaskInfo, TaskStatus, TaskStatus) + Send + Sync + 'static,
+    {
+        self.hooks.add(Arc::new(hook))
+    }
+
+    /// Run `hook` whenever a task completes.
+    pub fn on_complete<F>(&self, hook: F) -> u64
+    where
+        F: Fn(&TaskInfo) + Send + Sync + 'static,
+    {
+        self.on_transition(move |info, _, new| {
+            if new == TaskStatus::Completed {
+                hook(info);
+            }
+        })
+    }
+
+    /// Run `hook` whenever a task fails, including timeouts.
+    pub fn on_fail<F>(&self, hook: F) -> u64
+    where
+        F: Fn(&TaskInfo) + Send + Sync + 'static,
+    {
+        self.on_transition(move |info, _, new| {
+            if new == TaskStatus::Failed {
+                hook(info);
+            }
+        })
+    }
+
+    /// Run the future returned by `hook` on the current tokio runtime
+    /// whenever a task changes status.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a tokio runtime.
+    #[cfg(feature = "async")]
+    pub fn on_transition_async<F, Fut>(&self, hook: F) -> u64
+    where
+        F: Fn(TaskInfo, TaskStatus, TaskStatus) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let runtime = tokio::runtime::Handle::current();
+        self.on_transition(move |info, old, new| {
+            runtime.spawn(hook(info.clone(), old, new));
+        })
+    }
+
+    /// Remove a hook registered with [`on_transition`](Self::on_transition)
+    /// or its variants. Returns `false` if there was no such hook.
+    pub fn remove_hook(&self, id: u64) -> bool {
+        self.hooks.remove(id)
+    }
+
     /// Get the event bus for this manager.
     pub fn event_bus(&self) -> &EventBus {
         &self.event_bus
@@ -735,17 +2145,34 @@ impl TaskManager {
         self.tasks
             .read()
             .values()
-            .filter(|s| TaskStatus::from(s.status.load(Ordering::SeqCst)).is_active())
+            .filter(|s| s.status().is_active())
             .count()
     }
 }
 
+fn all_terminal(states: &[Arc<TaskState>]) -> bool {
+    states.iter().all(|s| s.status().is_terminal())
+}
+
+fn first_terminal(states: &[Arc<TaskState>]) -> Option<TaskInfo> {
+    states
+        .iter()
+        .find(|s| s.status().is_terminal())
+        .map(|s| s.get_info())
+}
+
 impl Default for TaskManager {
     fn default() -> Self {
         Self::new(TaskManagerConfig::default())
     }
 }
 
+impl Drop for TaskManager {
+    fn drop(&mut self) {
+        self.scheduler.stop();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -960,6 +2387,205 @@ mod tests {
 
         assert!(token.is_cancelled());
         assert!(child.is_cancelled());
+
+        // Cancelling a child leaves its parent untouched
+        let parent = CancellationToken::new();
+        let child = parent.child();
+        let grandchild = child.child();
+        child.cancel();
+        assert!(grandchild.is_cancelled());
+        assert!(!parent.is_cancelled());
+
+        // A probe is polled by whoever checks, and latches once it fires
+        let closed = Arc::new(AtomicBool::new(false));
+        let probe = CancellationToken::new().cancel_when(Duration::ZERO, {
+            let closed = Arc::clone(&closed);
+            move || closed.load(Ordering::SeqCst)
+        });
+        let child = probe.child();
+        assert!(!child.is_cancelled());
+        closed.store(true, Ordering::SeqCst);
+        assert!(child.is_cancelled());
+        closed.store(false, Ordering::SeqCst);
+        assert!(probe.is_cancelled());
+    }
+
+    #[test]
+    fn test_shared_cancellation_token() {
+        let name = format!("test_cancel_{}", std::process::id());
+        let gui = SharedCancellationToken::create(&name).unwrap();
+        // Opened separately, as a worker process would
+        let worker = SharedCancellationToken::open(&name).unwrap();
+        let manager = TaskManager::new(Default::default());
+        let handle =
+            manager.create(TaskBuilder::new("Render", "render").cancel_on(&worker.token()));
+        handle.start();
+        assert!(!handle.is_cancelled());
+
+        gui.cancel();
+        assert!(worker.is_cancelled());
+        assert!(handle.is_cancelled());
+
+        // Cancelling through a derived token reaches the flag too
+        let other = SharedCancellationToken::create(&format!("{}_b", name)).unwrap();
+        other.token().cancel();
+        assert!(other.is_cancelled());
+    }
+
+    #[test]
+    fn test_task_cancel_on_parent_token() {
+        let manager = TaskManager::new(Default::default());
+        let request_token = CancellationToken::new();
+        let handle = manager.create(
+            TaskBuilder::new("Linked", "test")
+                .id("linked-1")
+                .cancel_on(&request_token),
+        );
+        handle.start();
+
+        assert_eq!(handle.id(), "linked-1");
+        assert!(!handle.is_cancelled());
+
+        request_token.cancel();
+        assert!(handle.is_cancelled());
+        assert_eq!(handle.status(), TaskStatus::Cancelled);
+        let info = manager.get("linked-1").unwrap();
+        assert_eq!(info.status, TaskStatus::Cancelled);
+        assert!(info.finished_at.is_some());
+
+        // The same ID cannot be taken twice
+        assert!(matches!(
+            manager.try_create(TaskBuilder::new("Again", "test").id("linked-1")),
+            Err(IpcError::AlreadyExists(_))
+        ));
+    }
+
+    #[test]
+    fn test_generated_ids_skip_explicit_ids() {
+        let manager = TaskManager::new(Default::default());
+        let first = manager.create(TaskBuilder::new("First", "test"));
+        assert_eq!(first.id(), "task-1");
+        manager
+            .try_create(TaskBuilder::new("Remote", "test").id("task-2"))
+            .unwrap();
+        manager
+            .try_create(TaskBuilder::new("Remote", "test").id("task-3"))
+            .unwrap();
+
+        let next = manager.create(TaskBuilder::new("Next", "test"));
+        assert_eq!(next.id(), "task-4");
+        assert_eq!(manager.get("task-2").unwrap().name, "Remote");
+        assert_eq!(manager.list(&Default::default()).len(), 4);
+    }
+
+    #[test]
+    fn test_linked_cancel_transitions_unwatched_task() {
+        let manager = TaskManager::new(Default::default());
+        let transitions = Arc::new(Mutex::new(Vec::new()));
+        manager.on_transition({
+            let transitions = Arc::clone(&transitions);
+            move |info, old, new| transitions.lock().push((info.id.clone(), old, new))
+        });
+        let events = manager
+            .event_bus()
+            .subscribe(EventFilter::new().event_type(event_types::TASK_CANCELLED));
+
+        let token = CancellationToken::new();
+        let handle = manager.create(TaskBuilder::new("Linked", "test").cancel_on(&token));
+        handle.start();
+        token.cancel();
+
+        // Nobody checks the handle, yet the manager records the cancellation once
+        let event = events.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(event.resource_id.as_deref(), Some(handle.id()));
+        assert!(manager.get(handle.id()).unwrap().finished_at.is_some());
+        assert!(handle.is_cancelled());
+        assert!(events.recv_timeout(Duration::from_millis(200)).is_err());
+        assert_eq!(
+            transitions.lock().last().cloned(),
+            Some((
+                handle.id().to_string(),
+                TaskStatus::Running,
+                TaskStatus::Cancelled
+            ))
+        );
+    }
+
+    #[test]
+    fn test_sub_tasks_cancelled_with_parent() {
+        let manager = TaskManager::new(Default::default());
+        let build = manager.create(TaskBuilder::new("Build", "build").id("build"));
+        let compile = manager.create(TaskBuilder::new("Compile", "step").parent("build"));
+        let link = manager.create(TaskBuilder::new("Link", "step").parent("build"));
+        let codegen = manager.create(TaskBuilder::new("Codegen", "step").parent(compile.id()));
+        let other = manager.create(TaskBuilder::new("Other", "build"));
+        for task in [&build, &compile, &link, &codegen, &other] {
+            task.start();
+        }
+        link.complete(serde_json::json!({}));
+
+        let children = manager.list(&TaskFilter::new().parent("build"));
+        assert_eq!(children.len(), 2);
+        let subtree = manager.list(&TaskFilter::new().children_of("build"));
+        assert_eq!(subtree.len(), 3);
+        assert!(!subtree
+            .iter()
+            .any(|t| t.id == "build" || t.id == other.id()));
+        assert_eq!(
+            manager.get(codegen.id()).unwrap().parent_id.as_deref(),
+            Some(compile.id())
+        );
+
+        manager.cancel("build").unwrap();
+        assert_eq!(compile.status(), TaskStatus::Cancelled);
+        assert_eq!(codegen.status(), TaskStatus::Cancelled);
+        // Finished and unrelated tasks are left alone
+        assert_eq!(link.status(), TaskStatus::Completed);
+        assert_eq!(other.status(), TaskStatus::Running);
+    }
+
+    #[test]
+    fn test_sub_task_token_follows_parent_handle() {
+        let manager = TaskManager::new(Default::default());
+        let parent = manager.create(TaskBuilder::new("Pipeline", "test"));
+        let child = manager.create(TaskBuilder::new("Stage", "test").parent(parent.id()));
+
+        parent.cancel_token().cancel();
+        assert!(child.is_cancelled());
+        assert_eq!(child.status(), TaskStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_progress_rolls_up_to_parent() {
+        let manager = TaskManager::new(Default::default());
+        let pipeline = manager.create(TaskBuilder::new("Pipeline", "test"));
+        let fetch = manager.create(TaskBuilder::new("Fetch", "test").parent(pipeline.id()));
+        let build = manager.create(
+            TaskBuilder::new("Build", "test")
+                .parent(pipeline.id())
+                .weight(3.0),
+        );
+        let compile = manager.create(TaskBuilder::new("Compile", "test").parent(build.id()));
+        let link = manager.create(TaskBuilder::new("Link", "test").parent(build.id()));
+        pipeline.start();
+        build.start();
+
+        fetch.complete(serde_json::json!({}));
+        assert_eq!(pipeline.progress(), 25);
+
+        // Grandchildren move the build, which moves the pipeline
+        compile.set_progress(100, None);
+        assert_eq!(build.progress(), 50);
+        assert_eq!(pipeline.progress(), 63);
+
+        link.complete(serde_json::json!({}));
+        assert_eq!(build.progress(), 100);
+        assert_eq!(pipeline.progress(), 100);
+
+        // A finished parent keeps its progress
+        pipeline.complete(serde_json::json!({}));
+        manager.create(TaskBuilder::new("Late", "test").parent(pipeline.id()));
+        assert_eq!(pipeline.progress(), 100);
     }
 
     #[test]
@@ -1039,4 +2665,253 @@ mod tests {
         let deserialized: TaskInfo = serde_json::from_str(&json).unwrap();
         assert_eq!(deserialized.affinity, ThreadAffinity::Main);
     }
+
+    #[test]
+    fn test_stall_detection() {
+        let manager = TaskManager::new(Default::default());
+        let stalled = manager
+            .event_bus()
+            .subscribe(EventFilter::new().event_type(event_types::TASK_STALLED));
+
+        let quiet = manager
+            .create(TaskBuilder::new("Quiet", "test").stall_timeout(Duration::from_millis(150)));
+        let failing = manager.create(
+            TaskBuilder::new("Hung", "test")
+                .stall_timeout(Duration::from_millis(60))
+                .on_stall(StallAction::Fail),
+        );
+        quiet.start();
+        failing.start();
+
+        // Heartbeats keep a task from stalling
+        for _ in 0..10 {
+            thread::sleep(Duration::from_millis(20));
+            quiet.heartbeat();
+        }
+        assert_eq!(quiet.status(), TaskStatus::Running);
+
+        let info = manager
+            .wait_any([failing.id()], Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(info.status, TaskStatus::Failed);
+        assert!(info.error.unwrap().starts_with("Task stalled"));
+
+        // Notify-only tasks keep running and are reported once per quiet period
+        let mut events = Vec::new();
+        while let Ok(event) = stalled.recv_timeout(Duration::from_millis(300)) {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .any(|e| e.resource_id.as_deref() == Some(quiet.id())));
+        assert_eq!(quiet.status(), TaskStatus::Running);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let manager = TaskManager::new(Default::default());
+        let timeouts = manager
+            .event_bus()
+            .subscribe(EventFilter::new().event_type(event_types::TASK_TIMEOUT));
+
+        let slow =
+            manager.create(TaskBuilder::new("Slow", "test").timeout(Duration::from_millis(50)));
+        let quick =
+            manager.create(TaskBuilder::new("Quick", "test").timeout(Duration::from_millis(50)));
+        let hung = manager.create(
+            TaskBuilder::new("Hung", "test")
+                .stall_timeout(Duration::from_millis(40))
+                .on_stall(StallAction::Timeout),
+        );
+
+        // The deadline only runs once the task has started
+        thread::sleep(Duration::from_millis(80));
+        assert_eq!(slow.status(), TaskStatus::Pending);
+
+        slow.start();
+        quick.start();
+        hung.start();
+        quick.complete(serde_json::json!(null));
+
+        let results = manager
+            .wait_all([slow.id(), hung.id()], Some(Duration::from_secs(5)))
+            .unwrap();
+        for info in &results {
+            assert_eq!(info.status, TaskStatus::Failed);
+            assert_eq!(info.error.as_deref(), Some("timeout"));
+        }
+        assert!(slow.is_cancelled());
+        assert!(hung.is_cancelled());
+
+        let mut reasons: Vec<_> = (0..2)
+            .map(|_| {
+                let event = timeouts.recv_timeout(Duration::from_secs(1)).unwrap();
+                event.data["reason"].as_str().unwrap().to_string()
+            })
+            .collect();
+        reasons.sort();
+        assert_eq!(reasons, ["deadline", "stalled"]);
+
+        thread::sleep(Duration::from_millis(80));
+        assert_eq!(quick.status(), TaskStatus::Completed);
+        assert!(timeouts.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_output_stream_in_order_with_backpressure() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Row {
+            n: u32,
+        }
+
+        let manager = TaskManager::new(Default::default());
+        let handle = manager.create(TaskBuilder::new("Query", "query").output_buffer(4));
+        let rows = manager.output_receiver::<Row>(handle.id()).unwrap();
+        handle.start();
+
+        let tx = handle.output_sender::<Row>();
+        for n in 0..4 {
+            tx.try_send(&Row { n }).unwrap();
+        }
+        assert!(matches!(
+            tx.try_send(&Row { n: 4 }),
+            Err(IpcError::WouldBlock)
+        ));
+
+        let producer = thread::spawn(move || {
+            for n in 4..100 {
+                tx.send(&Row { n }).unwrap();
+            }
+            handle.complete(serde_json::json!(null));
+            tx.send(&Row { n: 100 }).unwrap_err()
+        });
+
+        let received: Vec<u32> = rows.map(|row| row.unwrap().n).collect();
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+        assert!(matches!(producer.join().unwrap(), IpcError::Closed));
+        assert!(manager.output_receiver::<Row>("missing").is_err());
+    }
+
+    #[test]
+    fn test_wait_all_and_any() {
+        let manager = Arc::new(TaskManager::new(Default::default()));
+        let fast = manager.create(TaskBuilder::new("Fast", "test"));
+        let slow = manager.create(TaskBuilder::new("Slow", "test"));
+        let ids = [fast.id().to_string(), slow.id().to_string()];
+
+        assert!(matches!(
+            manager.wait_all(&ids, Some(Duration::from_millis(20))),
+            Err(IpcError::Timeout)
+        ));
+        assert!(matches!(
+            manager.wait_any(["missing"], None),
+            Err(IpcError::NotFound(_))
+        ));
+
+        thread::spawn({
+            let fast = fast.clone();
+            move || {
+                thread::sleep(Duration::from_millis(20));
+                fast.complete(serde_json::json!({"n": 1}));
+            }
+        });
+        let first = manager
+            .wait_any(&ids, Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(first.id, fast.id());
+        assert_eq!(first.result, Some(serde_json::json!({"n": 1})));
+
+        thread::spawn({
+            let slow = slow.clone();
+            move || {
+                thread::sleep(Duration::from_millis(20));
+                slow.fail("boom");
+            }
+        });
+        let results = manager
+            .wait_all(&ids, Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(results[0].status, TaskStatus::Completed);
+        assert_eq!(results[1].status, TaskStatus::Failed);
+        assert_eq!(results[1].error.as_deref(), Some("boom"));
+
+        // Cancellation through a linked token, which bypasses the manager, is noticed too
+        let parent = CancellationToken::new();
+        let linked = manager.create(TaskBuilder::new("Linked", "test").cancel_on(&parent));
+        parent.cancel();
+        let info = manager
+            .wait_any([linked.id()], Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(info.status, TaskStatus::Cancelled);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_wait_all_async() {
+        let manager = TaskManager::new(Default::default());
+        let handle = manager.create(TaskBuilder::new("Async", "test"));
+        let id = handle.id().to_string();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            handle.complete(serde_json::json!(null));
+        });
+
+        let results = manager
+            .wait_all_async([&id], Some(Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert_eq!(results[0].status, TaskStatus::Completed);
+        assert_eq!(manager.wait_any_async([&id], None).await.unwrap().id, id);
+    }
+
+    #[test]
+    fn test_transition_hooks() {
+        let manager = Arc::new(TaskManager::new(Default::default()));
+        let transitions = Arc::new(Mutex::new(Vec::new()));
+        let hook = manager.on_transition({
+            let transitions = Arc::clone(&transitions);
+            move |info, old, new| transitions.lock().push((info.name.clone(), old, new))
+        });
+
+        // Chain a follow-up task from a completion hook
+        manager.on_complete({
+            let weak = Arc::downgrade(&manager);
+            move |info| {
+                if let (Some(manager), "first") = (weak.upgrade(), info.name.as_str()) {
+                    manager
+                        .create(TaskBuilder::new("second", "chained"))
+                        .start();
+                }
+            }
+        });
+
+        let first = manager.create(TaskBuilder::new("first", "test"));
+        first.start();
+        first.complete(serde_json::json!(null));
+
+        let failed = manager.create(TaskBuilder::new("third", "test"));
+        failed.start();
+        manager.pause(failed.id()).unwrap();
+        manager.cancel(failed.id()).unwrap();
+        // Already cancelled: no transition
+        failed.state.cancel();
+
+        use TaskStatus::*;
+        assert_eq!(
+            *transitions.lock(),
+            vec![
+                ("first".to_string(), Pending, Running),
+                ("first".to_string(), Running, Completed),
+                ("second".to_string(), Pending, Running),
+                ("third".to_string(), Pending, Running),
+                ("third".to_string(), Running, Paused),
+                ("third".to_string(), Paused, Cancelled),
+            ]
+        );
+
+        assert!(manager.remove_hook(hook));
+        assert!(!manager.remove_hook(hook));
+    }
 }
@@ -0,0 +1,552 @@
+//! Task Schedule - Recurring tasks for the task manager
+//!
+//! [`TaskManager::schedule`] registers a [`TaskBuilder`] and a job closure
+//! with a [`Schedule`]. A scheduler thread owned by the manager creates a
+//! new task instance from the builder each time the schedule fires, starts
+//! it and runs the job on its own thread, just like [`TaskManager::spawn`].
+//!
+//! - [`Schedule::Every`] fires at a fixed interval, starting one interval
+//!   after the schedule is registered.
+//! - [`Schedule::Cron`] fires on a standard five-field cron expression
+//!   (`minute hour day-of-month month day-of-week`, evaluated in UTC) with
+//!   `*`, lists, ranges and steps, or one of `@hourly`, `@daily`,
+//!   `@weekly`, `@monthly` and `@yearly`.
+//!
+//! A run is skipped while the previous run of the same schedule is still
+//! active, and runs missed while a schedule was paused are not caught up.
+//! Each instance carries the label `schedule` with the schedule's ID, and
+//! the most recent runs are kept in the schedule's history.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use ipckit::{Schedule, TaskBuilder, TaskManager};
+//! use std::sync::Arc;
+//!
+//! let manager = Arc::new(TaskManager::new(Default::default()));
+//!
+//! let id = manager
+//!     .schedule(
+//!         TaskBuilder::new("Prune cache", "cleanup"),
+//!         Schedule::Cron("*/15 * * * *".into()),
+//!         |task| task.complete(serde_json::json!({ "pruned": 0 })),
+//!     )
+//!     .unwrap();
+//!
+//! manager.pause_schedule(&id).unwrap();
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::task_manager::{TaskBuilder, TaskHandle, TaskManager, TaskStatus};
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Number of past runs kept per schedule.
+const HISTORY_LEN: usize = 32;
+
+/// Longest the scheduler sleeps before re-reading the clock, so wall-clock
+/// jumps delay cron schedules by at most this much.
+const MAX_SLEEP: Duration = Duration::from_secs(30);
+
+/// When a scheduled task runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// At a fixed interval
+    Every(Duration),
+    /// On a five-field cron expression, in UTC
+    Cron(String),
+}
+
+impl Schedule {
+    /// The first time this schedule fires after `after`.
+    ///
+    /// Fails for an invalid cron expression; `None` means the schedule never
+    /// fires (a zero interval, or a date such as February 30).
+    pub fn next_after(&self, after: SystemTime) -> Result<Option<SystemTime>> {
+        Ok(Timing::parse(self)?.next_after(after))
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Every(interval) => write!(f, "every {:?}", interval),
+            Schedule::Cron(expr) => write!(f, "cron {}", expr),
+        }
+    }
+}
+
+/// A schedule with its cron expression parsed.
+enum Timing {
+    Every(Duration),
+    Cron(Cron),
+}
+
+impl Timing {
+    fn parse(schedule: &Schedule) -> Result<Self> {
+        match schedule {
+            Schedule::Every(interval) => Ok(Timing::Every(*interval)),
+            Schedule::Cron(expr) => Cron::parse(expr).map(Timing::Cron),
+        }
+    }
+
+    fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        match self {
+            Timing::Every(interval) if interval.is_zero() => None,
+            Timing::Every(interval) => Some(after + *interval),
+            Timing::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+/// A parsed cron expression; each field is a bit set of allowed values.
+struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day-of-month and day-of-week fields were both restricted,
+    /// in which case a day matching either one fires
+    either_day: bool,
+}
+
+impl Cron {
+    fn parse(expr: &str) -> Result<Self> {
+        let expanded = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let invalid =
+            |msg: &str| IpcError::Other(format!("invalid cron expression '{}': {}", expr, msg));
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(invalid("expected 5 fields"));
+        };
+        let field = |text: &str, min: u32, max: u32| {
+            parse_field(text, min, max).ok_or_else(|| invalid(&format!("bad field '{}'", text)))
+        };
+
+        let mut weekday_bits = field(weekdays, 0, 7)?;
+        // Both 0 and 7 mean Sunday
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits = (weekday_bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: field(minutes, 0, 59)?,
+            hours: field(hours, 0, 23)?,
+            days: field(days, 1, 31)?,
+            months: field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            either_day: !days.starts_with('*') && !weekdays.starts_with('*'),
+        })
+    }
+
+    fn day_matches(&self, day: u32, weekday: u32) -> bool {
+        let day = self.days & (1 << day) != 0;
+        let weekday = self.weekdays & (1 << weekday) != 0;
+        if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+
+    fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let secs = after.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+        let mut t = (secs / 60 + 1) * 60;
+
+        // Whole months and days are skipped at once, so five years of
+        // candidates take at most a few thousand steps
+        let limit = t + 5 * 366 * 86_400;
+        while t < limit {
+            let days = t.div_euclid(86_400);
+            let (year, month, day) = civil_from_days(days);
+            if self.months & (1 << month) == 0 {
+                let (year, month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                t = days_from_civil(year, month, 1) * 86_400;
+                continue;
+            }
+            // 1970-01-01 was a Thursday
+            let weekday = (days + 4).rem_euclid(7) as u32;
+            if !self.day_matches(day, weekday) {
+                t = (days + 1) * 86_400;
+                continue;
+            }
+            let hour = (t.rem_euclid(86_400) / 3600) as u32;
+            if self.hours & (1 << hour) == 0 {
+                t = (t / 3600 + 1) * 3600;
+                continue;
+            }
+            let minute = (t.rem_euclid(3600) / 60) as u32;
+            if self.minutes & (1 << minute) == 0 {
+                t += 60;
+                continue;
+            }
+            return Some(UNIX_EPOCH + Duration::from_secs(t as u64));
+        }
+        None
+    }
+}
+
+/// Parse one cron field (`*`, `5`, `1-5`, `*/15`, `10-50/10`, lists of
+/// these) into a bit set.
+fn parse_field(text: &str, min: u32, max: u32) -> Option<u64> {
+    let mut bits = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0)?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse().ok()?, end.parse().ok()?)
+        } else {
+            let start = range.parse().ok()?;
+            // `5/10` means from 5 to the end in steps of 10
+            (start, if part.contains('/') { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return None;
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Some(bits)
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Convert a (year, month, day) date to days since the Unix epoch.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (i64::from(month) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// One past run of a schedule.
+#[derive(Debug, Clone)]
+pub struct ScheduleRun {
+    /// ID of the task instance created for the run
+    pub task_id: String,
+    /// When the run was started
+    pub started_at: SystemTime,
+    /// Status of the task instance
+    pub status: TaskStatus,
+}
+
+/// Snapshot of a registered schedule.
+#[derive(Debug, Clone)]
+pub struct ScheduleInfo {
+    /// Schedule ID
+    pub id: String,
+    /// Name of the tasks it creates
+    pub name: String,
+    /// When it fires
+    pub schedule: Schedule,
+    /// Whether it is paused
+    pub paused: bool,
+    /// When it fires next, if it is not paused
+    pub next_run: Option<SystemTime>,
+    /// Number of runs started
+    pub run_count: u64,
+    /// Number of runs skipped because the previous one was still active
+    pub skipped: u64,
+    /// Most recent runs, oldest first
+    pub history: Vec<ScheduleRun>,
+}
+
+pub(crate) type Job = Arc<dyn Fn(TaskHandle) + Send + Sync>;
+
+struct Entry {
+    builder: TaskBuilder,
+    schedule: Schedule,
+    timing: Timing,
+    job: Job,
+    paused: bool,
+    next_run: Option<SystemTime>,
+    run_count: u64,
+    skipped: u64,
+    history: VecDeque<(TaskHandle, SystemTime)>,
+}
+
+impl Entry {
+    fn info(&self, id: &str) -> ScheduleInfo {
+        ScheduleInfo {
+            id: id.to_string(),
+            name: self.builder.name().to_string(),
+            schedule: self.schedule.clone(),
+            paused: self.paused,
+            next_run: if self.paused { None } else { self.next_run },
+            run_count: self.run_count,
+            skipped: self.skipped,
+            history: self
+                .history
+                .iter()
+                .map(|(task, started_at)| ScheduleRun {
+                    task_id: task.id().to_string(),
+                    started_at: *started_at,
+                    status: task.status(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    entries: BTreeMap<String, Entry>,
+    next_id: u64,
+    running: bool,
+    stopped: bool,
+}
+
+/// The schedules of one task manager and its scheduler thread.
+#[derive(Default)]
+pub(crate) struct Scheduler {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Scheduler {
+    pub(crate) fn add<F>(
+        self: &Arc<Self>,
+        manager: &Arc<TaskManager>,
+        builder: TaskBuilder,
+        schedule: Schedule,
+        job: F,
+    ) -> Result<String>
+    where
+        F: Fn(TaskHandle) + Send + Sync + 'static,
+    {
+        let timing = Timing::parse(&schedule)?;
+        let next_run = timing.next_after(SystemTime::now());
+        let mut state = self.state.lock();
+        state.next_id += 1;
+        let id = format!("schedule-{}", state.next_id);
+        state.entries.insert(
+            id.clone(),
+            Entry {
+                builder: builder.label("schedule", &id),
+                schedule,
+                timing,
+                job: Arc::new(job),
+                paused: false,
+                next_run,
+                run_count: 0,
+                skipped: 0,
+                history: VecDeque::new(),
+            },
+        );
+
+        if !std::mem::replace(&mut state.running, true) {
+            let scheduler = Arc::clone(self);
+            let manager = Arc::downgrade(manager);
+            std::thread::spawn(move || scheduler.run(manager));
+        }
+        self.changed.notify_all();
+        Ok(id)
+    }
+
+    pub(crate) fn set_paused(&self, id: &str, paused: bool) -> Result<()> {
+        let mut state = self.state.lock();
+        let entry = state
+            .entries
+            .get_mut(id)
+            .ok_or_else(|| IpcError::NotFound(id.to_string()))?;
+        if entry.paused != paused {
+            entry.paused = paused;
+            // Resuming picks up from now rather than running missed slots
+            if !paused {
+                entry.next_run = entry.timing.next_after(SystemTime::now());
+            }
+        }
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    pub(crate) fn remove(&self, id: &str) -> Result<()> {
+        let removed = self.state.lock().entries.remove(id);
+        self.changed.notify_all();
+        removed
+            .map(|_| ())
+            .ok_or_else(|| IpcError::NotFound(id.to_string()))
+    }
+
+    pub(crate) fn info(&self, id: &str) -> Option<ScheduleInfo> {
+        self.state.lock().entries.get(id).map(|e| e.info(id))
+    }
+
+    pub(crate) fn list(&self) -> Vec<ScheduleInfo> {
+        let state = self.state.lock();
+        state.entries.iter().map(|(id, e)| e.info(id)).collect()
+    }
+
+    /// Stop the scheduler thread.
+    pub(crate) fn stop(&self) {
+        self.state.lock().stopped = true;
+        self.changed.notify_all();
+    }
+
+    fn run(&self, manager: Weak<TaskManager>) {
+        let mut state = self.state.lock();
+        while !state.stopped {
+            let now = SystemTime::now();
+            let mut due = Vec::new();
+            let mut next: Option<SystemTime> = None;
+            for (id, entry) in state.entries.iter_mut() {
+                if entry.paused {
+                    continue;
+                }
+                let Some(at) = entry.next_run else { continue };
+                if at > now {
+                    next = Some(next.map_or(at, |next| next.min(at)));
+                    continue;
+                }
+                entry.next_run = entry.timing.next_after(now);
+                let busy = entry
+                    .history
+                    .back()
+                    .is_some_and(|(task, _)| task.status().is_active());
+                if busy {
+                    entry.skipped += 1;
+                } else {
+                    due.push((id.clone(), entry.builder.clone(), Arc::clone(&entry.job)));
+                }
+                if let Some(at) = entry.next_run {
+                    next = Some(next.map_or(at, |next| next.min(at)));
+                }
+            }
+
+            if !due.is_empty() {
+                let Some(manager) = manager.upgrade() else {
+                    return;
+                };
+                // The manager may be dropped here, which locks the state
+                let started: Vec<_> = MutexGuard::unlocked(&mut state, move || {
+                    due.into_iter()
+                        .map(|(id, builder, job)| (id, manager.run_scheduled(builder, job)))
+                        .collect()
+                });
+                for (id, task) in started {
+                    if let Some(entry) = state.entries.get_mut(&id) {
+                        entry.run_count += 1;
+                        if entry.history.len() == HISTORY_LEN {
+                            entry.history.pop_front();
+                        }
+                        entry.history.push_back((task, now));
+                    }
+                }
+                continue;
+            }
+
+            let sleep = next
+                .and_then(|next| next.duration_since(now).ok())
+                .map_or(MAX_SLEEP, |d| d.min(MAX_SLEEP));
+            self.changed.wait_until(&mut state, Instant::now() + sleep);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        // 2024-01-01T00:00:00Z, a Monday
+        let base = 1_704_067_200;
+        let next = |expr: &str, from: u64| {
+            Schedule::Cron(expr.into())
+                .next_after(at(from))
+                .unwrap()
+                .map(|t| t.duration_since(UNIX_EPOCH).unwrap().as_secs())
+        };
+
+        assert_eq!(next("*/15 * * * *", base), Some(base + 15 * 60));
+        assert_eq!(next("30 2 * * *", base), Some(base + 2 * 3600 + 30 * 60));
+        // Friday 2024-01-05 at 09:00
+        assert_eq!(next("0 9 * * 5", base), Some(base + 4 * 86_400 + 9 * 3600));
+        // Leap day
+        assert_eq!(
+            next("0 0 29 2 *", base),
+            Some(days_from_civil(2024, 2, 29) as u64 * 86_400)
+        );
+        assert_eq!(next("@monthly", base), Some(base + 31 * 86_400));
+        assert_eq!(next("0 0 30 2 *", base), None);
+
+        assert!(Schedule::Cron("* * *".into()).next_after(at(0)).is_err());
+        assert!(Schedule::Cron("61 * * * *".into())
+            .next_after(at(0))
+            .is_err());
+        assert_eq!(civil_from_days(days_from_civil(2000, 3, 1)), (2000, 3, 1));
+    }
+
+    #[test]
+    fn test_interval_schedule_runs_and_pauses() {
+        let manager = Arc::new(TaskManager::new(Default::default()));
+        let id = manager
+            .schedule(
+                TaskBuilder::new("Sync", "sync"),
+                Schedule::Every(Duration::from_millis(30)),
+                |task| task.complete(serde_json::json!(null)),
+            )
+            .unwrap();
+
+        std::thread::sleep(Duration::from_millis(200));
+        let info = manager.schedule_info(&id).unwrap();
+        assert!(info.run_count >= 2, "{:?}", info);
+        assert_eq!(
+            manager
+                .list(&crate::TaskFilter::new().label("schedule", &id))
+                .len() as u64,
+            info.run_count
+        );
+
+        manager.pause_schedule(&id).unwrap();
+        let paused = manager.schedule_info(&id).unwrap();
+        assert!(paused.paused && paused.next_run.is_none());
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(
+            manager.schedule_info(&id).unwrap().run_count,
+            paused.run_count
+        );
+
+        manager.unschedule(&id).unwrap();
+        assert!(manager.schedule_info(&id).is_none());
+        assert!(manager.pause_schedule(&id).is_err());
+    }
+}
@@ -0,0 +1,482 @@
+//! Testing - In-memory loopback transport with failure injection
+//!
+//! Code that talks to a daemon over IPC has to cope with dropped messages,
+//! slow peers, timeouts and disconnects, but reproducing those against a
+//! real pipe or socket is slow and flaky. This module provides an in-memory
+//! [`LoopbackEndpoint`] pair that behaves like a connected message channel,
+//! and a [`ChaosEndpoint`] wrapper that injects failures into it, either
+//! randomly (from a seeded generator, so runs are reproducible) or on
+//! demand.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ipckit::testing::{loopback, ChaosConfig, ChaosEndpoint};
+//! use ipckit::IpcError;
+//! use std::time::Duration;
+//!
+//! let (client, server) = loopback();
+//! let client = ChaosEndpoint::new(client, ChaosConfig::new().disconnect_after(1));
+//!
+//! client.send(b"ping").unwrap();
+//! assert_eq!(server.recv().unwrap(), b"ping");
+//!
+//! // The second send simulates the daemon going away
+//! assert!(matches!(client.send(b"ping"), Err(IpcError::Closed)));
+//! assert!(matches!(
+//!     server.recv_timeout(Duration::from_millis(10)),
+//!     Err(IpcError::Closed)
+//! ));
+//! ```
+
+use crate::error::{IpcError, Result};
+use parking_lot::{Condvar, Mutex};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// One direction of a loopback link.
+#[derive(Default)]
+struct Pipe {
+    state: Mutex<PipeState>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct PipeState {
+    messages: VecDeque<Vec<u8>>,
+    closed: bool,
+}
+
+impl Pipe {
+    fn push(&self, data: Vec<u8>) -> Result<()> {
+        let mut state = self.state.lock();
+        if state.closed {
+            return Err(IpcError::Closed);
+        }
+        state.messages.push_back(data);
+        self.ready.notify_one();
+        Ok(())
+    }
+
+    fn pop(&self, deadline: Option<Instant>) -> Result<Vec<u8>> {
+        let mut state = self.state.lock();
+        loop {
+            if let Some(data) = state.messages.pop_front() {
+                return Ok(data);
+            }
+            if state.closed {
+                return Err(IpcError::Closed);
+            }
+            match deadline {
+                Some(deadline) => {
+                    if self.ready.wait_until(&mut state, deadline).timed_out()
+                        && state.messages.is_empty()
+                        && !state.closed
+                    {
+                        return Err(IpcError::Timeout);
+                    }
+                }
+                None => self.ready.wait(&mut state),
+            }
+        }
+    }
+
+    fn close(&self) {
+        self.state.lock().closed = true;
+        self.ready.notify_all();
+    }
+}
+
+/// Both directions of a loopback link.
+#[derive(Default)]
+struct Link {
+    pipes: [Pipe; 2],
+}
+
+impl Link {
+    fn close(&self) {
+        for pipe in &self.pipes {
+            pipe.close();
+        }
+    }
+}
+
+/// Closes the link once every clone of one endpoint has been dropped.
+struct SideGuard(Arc<Link>);
+
+impl Drop for SideGuard {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// One end of an in-memory message link created by [`loopback`].
+///
+/// Messages are delivered in order and never lost. Closing either end, or
+/// dropping every clone of it, disconnects both: sends fail with
+/// [`IpcError::Closed`] and receives fail once the queued messages are drained.
+#[derive(Clone)]
+pub struct LoopbackEndpoint {
+    link: Arc<Link>,
+    side: usize,
+    _guard: Arc<SideGuard>,
+}
+
+/// Create a connected pair of loopback endpoints.
+pub fn loopback() -> (LoopbackEndpoint, LoopbackEndpoint) {
+    let link = Arc::new(Link::default());
+    let endpoint = |side| LoopbackEndpoint {
+        link: Arc::clone(&link),
+        side,
+        _guard: Arc::new(SideGuard(Arc::clone(&link))),
+    };
+    (endpoint(0), endpoint(1))
+}
+
+impl LoopbackEndpoint {
+    fn outgoing(&self) -> &Pipe {
+        &self.link.pipes[self.side]
+    }
+
+    fn incoming(&self) -> &Pipe {
+        &self.link.pipes[1 - self.side]
+    }
+
+    /// Send a message to the other end.
+    pub fn send(&self, data: impl AsRef<[u8]>) -> Result<()> {
+        self.outgoing().push(data.as_ref().to_vec())
+    }
+
+    /// Receive a message, blocking until one arrives or the link closes.
+    pub fn recv(&self) -> Result<Vec<u8>> {
+        self.incoming().pop(None)
+    }
+
+    /// Receive a message, failing with [`IpcError::Timeout`] after `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Vec<u8>> {
+        self.incoming().pop(Some(Instant::now() + timeout))
+    }
+
+    /// Receive a message if one is queued.
+    pub fn try_recv(&self) -> Result<Option<Vec<u8>>> {
+        match self.incoming().pop(Some(Instant::now())) {
+            Ok(data) => Ok(Some(data)),
+            Err(IpcError::Timeout) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the number of messages waiting to be received.
+    pub fn pending(&self) -> usize {
+        self.incoming().state.lock().messages.len()
+    }
+
+    /// Disconnect both ends of the link.
+    pub fn close(&self) {
+        self.link.close();
+    }
+
+    /// Check whether the link has been disconnected.
+    pub fn is_closed(&self) -> bool {
+        self.outgoing().state.lock().closed
+    }
+}
+
+impl std::fmt::Debug for LoopbackEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoopbackEndpoint")
+            .field("side", &self.side)
+            .field("pending", &self.pending())
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+/// Failure injection settings for a [`ChaosEndpoint`].
+#[derive(Debug, Clone, Default)]
+pub struct ChaosConfig {
+    /// Probability (0.0 - 1.0) that a sent message is silently dropped
+    pub drop_rate: f64,
+    /// Probability (0.0 - 1.0) that a receive times out
+    pub timeout_rate: f64,
+    /// Delay added before every send
+    pub delay: Duration,
+    /// Disconnect the link when this many messages have been sent
+    pub disconnect_after: Option<u64>,
+    /// Seed for the random generator, so failures are reproducible
+    pub seed: u64,
+}
+
+impl ChaosConfig {
+    /// Create a config that injects no failures.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the probability that a sent message is dropped.
+    pub fn drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Set the probability that a receive times out.
+    pub fn timeout_rate(mut self, rate: f64) -> Self {
+        self.timeout_rate = rate;
+        self
+    }
+
+    /// Set the delay added before every send.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Disconnect after `count` messages have been sent.
+    pub fn disconnect_after(mut self, count: u64) -> Self {
+        self.disconnect_after = Some(count);
+        self
+    }
+
+    /// Set the random seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Counters of the failures a [`ChaosEndpoint`] has injected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChaosStats {
+    /// Messages delivered to the other end
+    pub sent: u64,
+    /// Messages dropped
+    pub dropped: u64,
+    /// Receives failed with an injected timeout
+    pub timeouts: u64,
+    /// Whether the endpoint injected a disconnect
+    pub disconnected: bool,
+}
+
+/// A [`LoopbackEndpoint`] that injects drops, delays, timeouts and disconnects.
+pub struct ChaosEndpoint {
+    inner: LoopbackEndpoint,
+    config: Mutex<ChaosConfig>,
+    rng: Mutex<u64>,
+    forced_timeouts: AtomicU64,
+    stats: Mutex<ChaosStats>,
+}
+
+impl ChaosEndpoint {
+    /// Wrap an endpoint with the given failure settings.
+    pub fn new(inner: LoopbackEndpoint, config: ChaosConfig) -> Self {
+        let rng = Mutex::new(seed_state(config.seed));
+        Self {
+            inner,
+            config: Mutex::new(config),
+            rng,
+            forced_timeouts: AtomicU64::new(0),
+            stats: Mutex::new(ChaosStats::default()),
+        }
+    }
+
+    /// Get the current failure settings.
+    pub fn config(&self) -> ChaosConfig {
+        self.config.lock().clone()
+    }
+
+    /// Replace the failure settings, keeping the random sequence.
+    pub fn set_config(&self, config: ChaosConfig) {
+        *self.config.lock() = config;
+    }
+
+    /// Get the wrapped endpoint.
+    pub fn inner(&self) -> &LoopbackEndpoint {
+        &self.inner
+    }
+
+    /// Get the injected failure counters.
+    pub fn stats(&self) -> ChaosStats {
+        *self.stats.lock()
+    }
+
+    /// Make the next `count` receives fail with [`IpcError::Timeout`].
+    pub fn inject_timeouts(&self, count: u64) {
+        self.forced_timeouts.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// Disconnect the link immediately, as if the peer had crashed.
+    pub fn disconnect(&self) {
+        self.stats.lock().disconnected = true;
+        self.inner.close();
+    }
+
+    /// Send a message, subject to the configured failures.
+    pub fn send(&self, data: impl AsRef<[u8]>) -> Result<()> {
+        let config = self.config();
+
+        if let Some(limit) = config.disconnect_after {
+            if self.stats.lock().sent >= limit {
+                self.disconnect();
+                return Err(IpcError::Closed);
+            }
+        }
+
+        if !config.delay.is_zero() {
+            std::thread::sleep(config.delay);
+        }
+
+        if self.roll(config.drop_rate) {
+            self.stats.lock().dropped += 1;
+            return Ok(());
+        }
+
+        self.inner.send(data)?;
+        self.stats.lock().sent += 1;
+        Ok(())
+    }
+
+    /// Receive a message, blocking until one arrives or the link closes.
+    ///
+    /// Injected timeouts fail immediately since there is no deadline to wait for.
+    pub fn recv(&self) -> Result<Vec<u8>> {
+        if self.inject_timeout() {
+            return Err(IpcError::Timeout);
+        }
+        self.inner.recv()
+    }
+
+    /// Receive a message with a timeout.
+    ///
+    /// Injected timeouts wait out the full `timeout` first, like a real one.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Vec<u8>> {
+        if self.inject_timeout() {
+            std::thread::sleep(timeout);
+            return Err(IpcError::Timeout);
+        }
+        self.inner.recv_timeout(timeout)
+    }
+
+    /// Disconnect both ends of the link.
+    pub fn close(&self) {
+        self.inner.close();
+    }
+
+    /// Check whether the link has been disconnected.
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    fn inject_timeout(&self) -> bool {
+        let forced = self
+            .forced_timeouts
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        let timeout_rate = self.config.lock().timeout_rate;
+        if forced || self.roll(timeout_rate) {
+            self.stats.lock().timeouts += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Return true with the given probability.
+    fn roll(&self, probability: f64) -> bool {
+        if probability <= 0.0 {
+            return false;
+        }
+        // xorshift64*
+        let mut state = self.rng.lock();
+        *state ^= *state >> 12;
+        *state ^= *state << 25;
+        *state ^= *state >> 27;
+        let value = state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        ((value >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
+
+impl std::fmt::Debug for ChaosEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChaosEndpoint")
+            .field("inner", &self.inner)
+            .field("config", &*self.config.lock())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// xorshift state must be non-zero.
+fn seed_state(seed: u64) -> u64 {
+    if seed == 0 {
+        0x9E37_79B9_7F4A_7C15
+    } else {
+        seed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loopback_disconnect() {
+        let (a, b) = loopback();
+        a.send(b"one").unwrap();
+        a.send(b"two").unwrap();
+        b.send(b"back").unwrap();
+        assert_eq!(b.pending(), 2);
+        assert_eq!(a.recv().unwrap(), b"back");
+        assert!(a.try_recv().unwrap().is_none());
+        assert!(matches!(
+            a.recv_timeout(Duration::from_millis(10)),
+            Err(IpcError::Timeout)
+        ));
+
+        // Dropping every clone of one end disconnects the link, but
+        // already queued messages are still delivered
+        let a2 = a.clone();
+        drop(a);
+        assert!(!b.is_closed());
+        drop(a2);
+        assert!(b.is_closed());
+        assert_eq!(b.recv().unwrap(), b"one");
+        assert_eq!(b.recv().unwrap(), b"two");
+        assert!(matches!(b.recv(), Err(IpcError::Closed)));
+        assert!(matches!(b.send(b"x"), Err(IpcError::Closed)));
+    }
+
+    #[test]
+    fn test_chaos_injection() {
+        let (a, b) = loopback();
+        let config = ChaosConfig::new().drop_rate(0.5).seed(7);
+        let chaos = ChaosEndpoint::new(a.clone(), config.clone());
+        for _ in 0..100 {
+            chaos.send(b"msg").unwrap();
+        }
+        let stats = chaos.stats();
+        assert_eq!(stats.sent + stats.dropped, 100);
+        assert!(stats.dropped > 20 && stats.dropped < 80);
+        assert_eq!(b.pending() as u64, stats.sent);
+
+        // The same seed drops the same messages
+        let replay = ChaosEndpoint::new(a, config);
+        for _ in 0..100 {
+            replay.send(b"msg").unwrap();
+        }
+        assert_eq!(replay.stats(), stats);
+
+        replay.inject_timeouts(1);
+        b.send(b"reply").unwrap();
+        assert!(matches!(
+            replay.recv_timeout(Duration::from_millis(1)),
+            Err(IpcError::Timeout)
+        ));
+        assert_eq!(replay.recv().unwrap(), b"reply");
+        assert_eq!(replay.stats().timeouts, 1);
+
+        replay.disconnect();
+        assert!(replay.stats().disconnected);
+        assert!(b.is_closed());
+    }
+}
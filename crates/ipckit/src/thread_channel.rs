@@ -19,12 +19,67 @@
 //! let msg = rx.recv().unwrap();
 //! assert_eq!(msg, "Hello from thread!");
 //! ```
+//!
+//! # Waiting on several channels
+//!
+//! A [`ChannelSet`] blocks until any of its receivers is ready and reports
+//! which one, so a worker can serve a data channel and a control channel
+//! without polling. Byte streams such as pipe readers join a set through
+//! [`ThreadChannel::from_reader`].
+//!
+//! ```rust
+//! use ipckit::{ChannelSet, ThreadChannel};
+//!
+//! let (data_tx, data_rx) = ThreadChannel::<u32>::unbounded();
+//! let (_ctl_tx, ctl_rx) = ThreadChannel::<&str>::unbounded();
+//!
+//! let mut set = ChannelSet::new();
+//! let data = set.add(&data_rx);
+//! let ctl = set.add(&ctl_rx);
+//!
+//! data_tx.send(7).unwrap();
+//! let ready = set.ready();
+//! assert_eq!(ready, data);
+//! assert_ne!(ready, ctl);
+//! assert_eq!(data_rx.try_recv().unwrap(), 7);
+//! ```
+//!
+//! # Priorities
+//!
+//! Every channel has three lanes. [`ThreadSender::send`] uses the normal
+//! lane; [`ThreadSender::send_priority`] can put urgent control messages in
+//! the high lane, which receivers drain first, or bulk data in the low lane,
+//! which they drain last. The high lane is never bounded, so a command is
+//! not held up behind a full data queue.
+//!
+//! ```rust
+//! use ipckit::{Priority, ThreadChannel};
+//!
+//! let (tx, rx) = ThreadChannel::<&str>::bounded(2);
+//! tx.send("frame 1").unwrap();
+//! tx.send("frame 2").unwrap();
+//! tx.send_priority("cancel", Priority::High).unwrap();
+//! assert_eq!(rx.recv().unwrap(), "cancel");
+//! ```
 
 use crate::error::{IpcError, Result};
 use crate::graceful::{GracefulChannel, ShutdownState};
-use crossbeam_channel::{self, Receiver, RecvTimeoutError, Sender, TryRecvError, TrySendError};
+use crossbeam_channel::{self, Receiver, Select, Sender, TryRecvError, TrySendError};
+use std::io::Read;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Lane a message is sent on; higher lanes are received first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Bulk data, received once the other lanes are empty
+    Low,
+    /// The default lane
+    #[default]
+    Normal,
+    /// Control messages; this lane is unbounded
+    High,
+}
 
 /// A thread-safe channel sender for intra-process communication.
 ///
@@ -33,6 +88,8 @@ use std::time::Duration;
 #[derive(Debug)]
 pub struct ThreadSender<T> {
     inner: Sender<T>,
+    high: Sender<T>,
+    low: Sender<T>,
     shutdown: Arc<ShutdownState>,
 }
 
@@ -43,6 +100,8 @@ pub struct ThreadSender<T> {
 #[derive(Debug)]
 pub struct ThreadReceiver<T> {
     inner: Receiver<T>,
+    high: Receiver<T>,
+    low: Receiver<T>,
     shutdown: Arc<ShutdownState>,
 }
 
@@ -50,6 +109,8 @@ impl<T> Clone for ThreadSender<T> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            high: self.high.clone(),
+            low: self.low.clone(),
             shutdown: Arc::clone(&self.shutdown),
         }
     }
@@ -59,6 +120,8 @@ impl<T> Clone for ThreadReceiver<T> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            high: self.high.clone(),
+            low: self.low.clone(),
             shutdown: Arc::clone(&self.shutdown),
         }
     }
@@ -80,6 +143,47 @@ impl<T> ThreadSender<T> {
         self.inner.send(msg).map_err(|_| IpcError::Closed)
     }
 
+    /// Send a message on the lane for `priority`.
+    ///
+    /// Blocks like [`send`](Self::send) if the lane is full; the high lane
+    /// never is.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IpcError::Closed` if the channel has been shutdown or all receivers have been dropped.
+    pub fn send_priority(&self, msg: T, priority: Priority) -> Result<()> {
+        if self.shutdown.is_shutdown() {
+            return Err(IpcError::Closed);
+        }
+
+        self.lane(priority).send(msg).map_err(|_| IpcError::Closed)
+    }
+
+    /// Try to send a message on the lane for `priority` without blocking.
+    ///
+    /// # Errors
+    ///
+    /// - `IpcError::Closed` if the channel has been shutdown or all receivers have been dropped.
+    /// - `IpcError::WouldBlock` if the lane is full (bounded channels only).
+    pub fn try_send_priority(&self, msg: T, priority: Priority) -> Result<()> {
+        if self.shutdown.is_shutdown() {
+            return Err(IpcError::Closed);
+        }
+
+        self.lane(priority).try_send(msg).map_err(|e| match e {
+            TrySendError::Full(_) => IpcError::WouldBlock,
+            TrySendError::Disconnected(_) => IpcError::Closed,
+        })
+    }
+
+    fn lane(&self, priority: Priority) -> &Sender<T> {
+        match priority {
+            Priority::High => &self.high,
+            Priority::Normal => &self.inner,
+            Priority::Low => &self.low,
+        }
+    }
+
     /// Try to send a message without blocking.
     ///
     /// # Errors
@@ -119,20 +223,20 @@ impl<T> ThreadSender<T> {
 
     /// Check if the channel is empty.
     pub fn is_empty(&self) -> bool {
-        self.inner.is_empty()
+        self.inner.is_empty() && self.high.is_empty() && self.low.is_empty()
     }
 
-    /// Check if the channel is full (always false for unbounded channels).
+    /// Check if the normal lane is full (always false for unbounded channels).
     pub fn is_full(&self) -> bool {
         self.inner.is_full()
     }
 
-    /// Get the number of messages in the channel.
+    /// Get the number of messages in the channel, across all lanes.
     pub fn len(&self) -> usize {
-        self.inner.len()
+        self.inner.len() + self.high.len() + self.low.len()
     }
 
-    /// Get the capacity of the channel (None for unbounded channels).
+    /// Get the capacity of each of the normal and low lanes (None for unbounded channels).
     pub fn capacity(&self) -> Option<usize> {
         self.inner.capacity()
     }
@@ -151,7 +255,8 @@ impl<T> ThreadSender<T> {
 impl<T> ThreadReceiver<T> {
     /// Receive a message from the channel.
     ///
-    /// This method blocks until a message is available.
+    /// This method blocks until a message is available. Messages on the high
+    /// lane are received before normal ones, and those before low ones.
     ///
     /// # Errors
     ///
@@ -159,10 +264,10 @@ impl<T> ThreadReceiver<T> {
     pub fn recv(&self) -> Result<T> {
         if self.shutdown.is_shutdown() {
             // Try to drain remaining messages first
-            return self.inner.try_recv().map_err(|_| IpcError::Closed);
+            return self.try_recv().map_err(|_| IpcError::Closed);
         }
 
-        self.inner.recv().map_err(|_| IpcError::Closed)
+        self.recv_until(None)
     }
 
     /// Try to receive a message without blocking.
@@ -172,10 +277,19 @@ impl<T> ThreadReceiver<T> {
     /// - `IpcError::Closed` if the channel has been shutdown or all senders have been dropped.
     /// - `IpcError::WouldBlock` if no message is available.
     pub fn try_recv(&self) -> Result<T> {
-        self.inner.try_recv().map_err(|e| match e {
-            TryRecvError::Empty => IpcError::WouldBlock,
-            TryRecvError::Disconnected => IpcError::Closed,
-        })
+        let mut disconnected = true;
+        for lane in [&self.high, &self.inner, &self.low] {
+            match lane.try_recv() {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Empty) => disconnected = false,
+                Err(TryRecvError::Disconnected) => {}
+            }
+        }
+        if disconnected {
+            Err(IpcError::Closed)
+        } else {
+            Err(IpcError::WouldBlock)
+        }
     }
 
     /// Receive a message with a timeout.
@@ -185,27 +299,65 @@ impl<T> ThreadReceiver<T> {
     /// - `IpcError::Closed` if the channel has been shutdown or all senders have been dropped.
     /// - `IpcError::Timeout` if the timeout expires before a message is available.
     pub fn recv_timeout(&self, timeout: Duration) -> Result<T> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_deadline(deadline),
+            None => self.recv(),
+        }
+    }
+
+    /// Receive a message, waiting no later than `deadline`.
+    ///
+    /// Handy when one budget spans several receives, e.g. collecting
+    /// replies until a frame is due.
+    ///
+    /// # Errors
+    ///
+    /// - `IpcError::Closed` if the channel has been shutdown or all senders have been dropped.
+    /// - `IpcError::Timeout` if the deadline passes before a message is available.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<T> {
         if self.shutdown.is_shutdown() {
             return self.try_recv();
         }
 
-        self.inner.recv_timeout(timeout).map_err(|e| match e {
-            RecvTimeoutError::Timeout => IpcError::Timeout,
-            RecvTimeoutError::Disconnected => IpcError::Closed,
-        })
+        self.recv_until(Some(deadline))
+    }
+
+    /// Block until a message arrives on any lane (internal)
+    fn recv_until(&self, deadline: Option<Instant>) -> Result<T> {
+        loop {
+            match self.try_recv() {
+                Err(IpcError::WouldBlock) => {}
+                other => return other,
+            }
+
+            let mut select = Select::new();
+            select.recv(&self.high);
+            select.recv(&self.inner);
+            select.recv(&self.low);
+            match deadline {
+                Some(deadline) => {
+                    select
+                        .ready_deadline(deadline)
+                        .map_err(|_| IpcError::Timeout)?;
+                }
+                None => {
+                    select.ready();
+                }
+            }
+        }
     }
 
     /// Check if the channel is empty.
     pub fn is_empty(&self) -> bool {
-        self.inner.is_empty()
+        self.inner.is_empty() && self.high.is_empty() && self.low.is_empty()
     }
 
-    /// Get the number of messages in the channel.
+    /// Get the number of messages in the channel, across all lanes.
     pub fn len(&self) -> usize {
-        self.inner.len()
+        self.inner.len() + self.high.len() + self.low.len()
     }
 
-    /// Get the capacity of the channel (None for unbounded channels).
+    /// Get the capacity of each of the normal and low lanes (None for unbounded channels).
     pub fn capacity(&self) -> Option<usize> {
         self.inner.capacity()
     }
@@ -253,25 +405,16 @@ impl<T> ThreadChannel<T> {
     ///
     /// A tuple of (sender, receiver) for the channel.
     pub fn unbounded() -> (ThreadSender<T>, ThreadReceiver<T>) {
-        let (tx, rx) = crossbeam_channel::unbounded();
-        let shutdown = Arc::new(ShutdownState::new());
-
-        let sender = ThreadSender {
-            inner: tx,
-            shutdown: Arc::clone(&shutdown),
-        };
-
-        let receiver = ThreadReceiver {
-            inner: rx,
-            shutdown,
-        };
-
-        (sender, receiver)
+        Self::with_lanes(
+            crossbeam_channel::unbounded(),
+            crossbeam_channel::unbounded(),
+        )
     }
 
     /// Create a new bounded thread channel with the specified capacity.
     ///
-    /// A bounded channel will block on send when the channel is full.
+    /// A bounded channel will block on send when the channel is full. The
+    /// normal and low lanes each hold up to `capacity` messages.
     ///
     /// # Arguments
     ///
@@ -281,16 +424,31 @@ impl<T> ThreadChannel<T> {
     ///
     /// A tuple of (sender, receiver) for the channel.
     pub fn bounded(capacity: usize) -> (ThreadSender<T>, ThreadReceiver<T>) {
-        let (tx, rx) = crossbeam_channel::bounded(capacity);
+        Self::with_lanes(
+            crossbeam_channel::bounded(capacity),
+            crossbeam_channel::bounded(capacity),
+        )
+    }
+
+    /// Assemble a channel from its normal and low lanes (internal)
+    fn with_lanes(
+        (tx, rx): (Sender<T>, Receiver<T>),
+        (low_tx, low_rx): (Sender<T>, Receiver<T>),
+    ) -> (ThreadSender<T>, ThreadReceiver<T>) {
+        let (high_tx, high_rx) = crossbeam_channel::unbounded();
         let shutdown = Arc::new(ShutdownState::new());
 
         let sender = ThreadSender {
             inner: tx,
+            high: high_tx,
+            low: low_tx,
             shutdown: Arc::clone(&shutdown),
         };
 
         let receiver = ThreadReceiver {
             inner: rx,
+            high: high_rx,
+            low: low_rx,
             shutdown,
         };
 
@@ -335,6 +493,34 @@ impl<T> ThreadChannel<T> {
     }
 }
 
+impl ThreadChannel<Vec<u8>> {
+    /// Pump a byte stream, such as a pipe reader, into a channel.
+    ///
+    /// A background thread reads chunks of up to `chunk_size` bytes and
+    /// sends them on; the channel closes at end of stream or on a read
+    /// error. The returned receiver can be added to a [`ChannelSet`].
+    pub fn from_reader<R>(mut reader: R, chunk_size: usize) -> ThreadReceiver<Vec<u8>>
+    where
+        R: Read + Send + 'static,
+    {
+        let (tx, rx) = Self::unbounded();
+        let mut buf = vec![0u8; chunk_size.max(1)];
+        std::thread::spawn(move || loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if tx.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        });
+        rx
+    }
+}
+
 impl<T> GracefulChannel for ThreadChannel<T> {
     fn shutdown(&self) {
         self.sender.shutdown();
@@ -415,6 +601,120 @@ impl<T> GracefulChannel for ThreadReceiver<T> {
     }
 }
 
+/// A receiver that can be waited on as part of a [`ChannelSet`].
+pub trait Selectable {
+    /// Add this receiver to `set`, returning its index.
+    fn register<'a>(&'a self, set: &mut ChannelSet<'a>) -> usize;
+}
+
+impl<T> Selectable for ThreadReceiver<T> {
+    fn register<'a>(&'a self, set: &mut ChannelSet<'a>) -> usize {
+        set.watch(&self.high);
+        set.watch(&self.inner);
+        set.watch(&self.low)
+    }
+}
+
+impl<T> Selectable for Receiver<T> {
+    fn register<'a>(&'a self, set: &mut ChannelSet<'a>) -> usize {
+        set.watch(self)
+    }
+}
+
+/// Waits on several receivers at once.
+///
+/// Readiness is reported by index, in the order receivers were added; the
+/// caller then receives from that receiver without blocking. A receiver is
+/// also ready once it is disconnected, and readiness can be spurious, so
+/// the follow-up `try_recv` must tolerate finding nothing.
+pub struct ChannelSet<'a> {
+    select: Select<'a>,
+    /// Receiver index of each select operation
+    owners: Vec<usize>,
+    next: usize,
+    len: usize,
+}
+
+impl Default for ChannelSet<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ChannelSet<'a> {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self {
+            select: Select::new(),
+            owners: Vec::new(),
+            next: 0,
+            len: 0,
+        }
+    }
+
+    /// Add a receiver, returning the index [`ready`](Self::ready) reports for it.
+    pub fn add<S: Selectable + ?Sized>(&mut self, source: &'a S) -> usize {
+        let index = source.register(self);
+        self.next += 1;
+        self.len += 1;
+        index
+    }
+
+    /// Stop waiting on the receiver at `index`.
+    pub fn remove(&mut self, index: usize) {
+        for (op, owner) in self.owners.iter().enumerate() {
+            if *owner == index {
+                self.select.remove(op);
+            }
+        }
+        self.len -= 1;
+    }
+
+    /// Wait on `receiver` for the receiver being added (internal)
+    fn watch<T>(&mut self, receiver: &'a Receiver<T>) -> usize {
+        let op = self.select.recv(receiver);
+        debug_assert_eq!(op, self.owners.len());
+        self.owners.push(self.next);
+        self.next
+    }
+
+    /// Get the number of receivers in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the set has no receivers.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Block until a receiver is ready and return its index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the set is empty.
+    pub fn ready(&mut self) -> usize {
+        self.owners[self.select.ready()]
+    }
+
+    /// Return the index of a ready receiver, if any, without blocking.
+    pub fn try_ready(&mut self) -> Option<usize> {
+        self.select.try_ready().ok().map(|op| self.owners[op])
+    }
+
+    /// Block until a receiver is ready or `timeout` elapses.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IpcError::Timeout` if no receiver became ready in time.
+    pub fn ready_timeout(&mut self, timeout: Duration) -> Result<usize> {
+        self.select
+            .ready_timeout(timeout)
+            .map(|op| self.owners[op])
+            .map_err(|_| IpcError::Timeout)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -628,4 +928,91 @@ mod tests {
         tx.send(3).unwrap();
         assert_eq!(rx.recv().unwrap(), 3);
     }
+
+    #[test]
+    fn test_priority_lanes() {
+        let (tx, rx) = ThreadChannel::<&str>::bounded(2);
+        tx.send_priority("bulk", Priority::Low).unwrap();
+        tx.send("frame 1").unwrap();
+        tx.send("frame 2").unwrap();
+        assert!(matches!(tx.try_send("frame 3"), Err(IpcError::WouldBlock)));
+
+        // The high lane is unbounded and overtakes the full normal lane
+        for _ in 0..3 {
+            tx.try_send_priority("cmd", Priority::High).unwrap();
+        }
+        assert_eq!(tx.len(), 6);
+        let received: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            received,
+            ["cmd", "cmd", "cmd", "frame 1", "frame 2", "bulk"]
+        );
+
+        // A blocked receiver wakes for any lane
+        let sender = tx.clone();
+        let worker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            sender.send_priority("late", Priority::Low).unwrap();
+        });
+        assert_eq!(rx.recv().unwrap(), "late");
+        worker.join().unwrap();
+
+        drop(tx);
+        assert!(matches!(rx.recv(), Err(IpcError::Closed)));
+    }
+
+    #[test]
+    fn test_recv_deadline() {
+        let (tx, rx) = ThreadChannel::<u32>::unbounded();
+        let deadline = Instant::now() + Duration::from_millis(30);
+        assert!(matches!(rx.recv_deadline(deadline), Err(IpcError::Timeout)));
+        assert!(Instant::now() >= deadline);
+
+        tx.send_priority(5, Priority::High).unwrap();
+        assert_eq!(rx.recv_deadline(Instant::now()).unwrap(), 5);
+
+        // A passed deadline still returns queued messages
+        tx.send(6).unwrap();
+        assert_eq!(rx.recv_deadline(deadline).unwrap(), 6);
+    }
+
+    #[test]
+    fn test_channel_set() {
+        let (data_tx, data_rx) = ThreadChannel::<u32>::unbounded();
+        let (ctl_tx, ctl_rx) = ThreadChannel::<&str>::bounded(1);
+        let bytes_rx = ThreadChannel::from_reader(std::io::Cursor::new(b"abc".to_vec()), 2);
+
+        let mut set = ChannelSet::new();
+        let data = set.add(&data_rx);
+        let ctl = set.add(&ctl_rx);
+        let bytes = set.add(&bytes_rx);
+        assert_eq!(set.len(), 3);
+
+        let mut received = Vec::new();
+        while received.len() < 3 {
+            assert_eq!(set.ready(), bytes);
+            if let Ok(chunk) = bytes_rx.try_recv() {
+                received.extend(chunk);
+            }
+        }
+        assert_eq!(received, b"abc");
+        set.remove(bytes);
+
+        assert!(set.try_ready().is_none());
+        assert!(matches!(
+            set.ready_timeout(Duration::from_millis(10)),
+            Err(IpcError::Timeout)
+        ));
+
+        let worker = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            ctl_tx.send("stop").unwrap();
+        });
+        assert_eq!(set.ready(), ctl);
+        assert_eq!(ctl_rx.try_recv().unwrap(), "stop");
+        worker.join().unwrap();
+
+        data_tx.send(1).unwrap();
+        assert_eq!(set.ready(), data);
+    }
 }
@@ -0,0 +1,368 @@
+//! # TLS
+//!
+//! TLS for [`TcpTransport`](crate::TcpTransport), built on rustls, with
+//! optional mutual authentication. Requires the `tls` feature.
+//!
+//! Certificates and keys are PEM encoded. A server presents its certificate
+//! chain and, with [`TlsConfig::require_client_cert`], only accepts clients
+//! holding a certificate signed by the given CA. A client verifies the server
+//! against a CA and may present a certificate of its own.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ipckit::tls::TlsConfig;
+//! use ipckit::{Listen, SocketClient, SocketServer, SocketServerConfig, TcpTransport};
+//!
+//! let ca = std::fs::read("ca.pem")?;
+//!
+//! // On the host: listen on every interface, for a client in WSL
+//! let tls = TlsConfig::server(&std::fs::read("server.pem")?, &std::fs::read("server.key")?)?
+//!     .require_client_cert(&ca)?;
+//! let server = SocketServer::new(SocketServerConfig::listen(Listen::Tls(
+//!     "0.0.0.0:7878".to_string(),
+//!     tls,
+//! )))?;
+//!
+//! // In WSL
+//! let tls = TlsConfig::client(&ca, "ipckit-host")?
+//!     .with_client_cert(&std::fs::read("client.pem")?, &std::fs::read("client.key")?)?;
+//! let transport = TcpTransport::new().allow_remote(true).with_tls(tls);
+//! let client = SocketClient::connect_with(&transport, "172.20.0.1:7878")?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::transport::{wait_tcp_readable, TransportListener, TransportStream};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Certificates, key and trust roots for one end of a TLS connection.
+///
+/// Made with [`server`](Self::server) or [`client`](Self::client) and
+/// handed to [`TcpTransport::with_tls`](crate::TcpTransport::with_tls).
+#[derive(Clone)]
+pub struct TlsConfig {
+    certs: Vec<CertificateDer<'static>>,
+    key: Option<Arc<PrivateKeyDer<'static>>>,
+    /// CA the peer's certificate must chain to
+    roots: Option<Arc<RootCertStore>>,
+    /// Name the server's certificate must be valid for (clients only)
+    server_name: Option<ServerName<'static>>,
+}
+
+impl std::fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("certs", &self.certs.len())
+            .field("has_key", &self.key.is_some())
+            .field("verifies_peer", &self.roots.is_some())
+            .field("server_name", &self.server_name)
+            .finish()
+    }
+}
+
+impl TlsConfig {
+    /// Configure a server presenting `cert_pem` (the chain, leaf first) and
+    /// its private key.
+    pub fn server(cert_pem: &[u8], key_pem: &[u8]) -> Result<Self> {
+        Ok(Self {
+            certs: parse_certs(cert_pem)?,
+            key: Some(Arc::new(parse_key(key_pem)?)),
+            roots: None,
+            server_name: None,
+        })
+    }
+
+    /// Only accept clients presenting a certificate signed by `ca_pem`.
+    pub fn require_client_cert(mut self, ca_pem: &[u8]) -> Result<Self> {
+        self.roots = Some(Arc::new(parse_roots(ca_pem)?));
+        Ok(self)
+    }
+
+    /// Configure a client trusting servers signed by `ca_pem` whose
+    /// certificate is valid for `server_name` (a DNS name or IP address).
+    pub fn client(ca_pem: &[u8], server_name: &str) -> Result<Self> {
+        let server_name = ServerName::try_from(server_name.to_string()).map_err(|_| {
+            IpcError::InvalidName(format!("invalid TLS server name: {}", server_name))
+        })?;
+        Ok(Self {
+            certs: Vec::new(),
+            key: None,
+            roots: Some(Arc::new(parse_roots(ca_pem)?)),
+            server_name: Some(server_name),
+        })
+    }
+
+    /// Present a client certificate, for servers that require one.
+    pub fn with_client_cert(mut self, cert_pem: &[u8], key_pem: &[u8]) -> Result<Self> {
+        self.certs = parse_certs(cert_pem)?;
+        self.key = Some(Arc::new(parse_key(key_pem)?));
+        Ok(self)
+    }
+
+    /// Wrap a bound TCP listener (internal)
+    pub(crate) fn listen(&self, listener: TcpListener) -> Result<Box<dyn TransportListener>> {
+        Ok(Box::new(TlsListener {
+            listener,
+            config: self.server_config()?,
+        }))
+    }
+
+    /// Perform the client handshake over a connected TCP stream (internal)
+    pub(crate) fn connect(&self, mut sock: TcpStream) -> Result<Box<dyn TransportStream>> {
+        let server_name = self.server_name.clone().ok_or_else(|| {
+            IpcError::InvalidState("TLS config was not made for a client".to_string())
+        })?;
+        let mut conn =
+            ClientConnection::new(self.client_config()?, server_name).map_err(tls_error)?;
+        // Fail here rather than on the first message if the server is not trusted
+        while conn.is_handshaking() {
+            conn.complete_io(&mut sock).map_err(handshake_error)?;
+        }
+        Ok(Box::new(TlsStream {
+            conn: conn.into(),
+            sock,
+        }))
+    }
+
+    fn server_config(&self) -> Result<Arc<ServerConfig>> {
+        let key = match (&self.key, self.server_name.is_none()) {
+            (Some(key), true) => key,
+            _ => {
+                return Err(IpcError::InvalidState(
+                    "TLS config was not made for a server".to_string(),
+                ))
+            }
+        };
+        let builder = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?;
+        let builder = match &self.roots {
+            Some(roots) => builder.with_client_cert_verifier(
+                WebPkiClientVerifier::builder_with_provider(Arc::clone(roots), provider())
+                    .build()
+                    .map_err(|e| IpcError::Other(format!("TLS: {}", e)))?,
+            ),
+            None => builder.with_no_client_auth(),
+        };
+        builder
+            .with_single_cert(self.certs.clone(), key.clone_key())
+            .map(Arc::new)
+            .map_err(tls_error)
+    }
+
+    fn client_config(&self) -> Result<Arc<ClientConfig>> {
+        let roots = self.roots.clone().ok_or_else(|| {
+            IpcError::InvalidState("TLS client needs a CA certificate".to_string())
+        })?;
+        let builder = ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .with_root_certificates(roots);
+        let config = match &self.key {
+            Some(key) => builder
+                .with_client_auth_cert(self.certs.clone(), key.clone_key())
+                .map_err(tls_error)?,
+            None => builder.with_no_client_auth(),
+        };
+        Ok(Arc::new(config))
+    }
+}
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn tls_error(err: rustls::Error) -> IpcError {
+    IpcError::Other(format!("TLS: {}", err))
+}
+
+/// A failed handshake usually means a certificate was rejected
+fn handshake_error(err: std::io::Error) -> IpcError {
+    match err.kind() {
+        std::io::ErrorKind::InvalidData => {
+            IpcError::PermissionDenied(format!("TLS handshake failed: {}", err))
+        }
+        _ => IpcError::Io(err),
+    }
+}
+
+fn parse_certs(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_slice_iter(pem)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| IpcError::InvalidName(format!("invalid certificate PEM: {}", e)))?;
+    if certs.is_empty() {
+        return Err(IpcError::InvalidName(
+            "no certificate found in PEM data".to_string(),
+        ));
+    }
+    Ok(certs)
+}
+
+fn parse_key(pem: &[u8]) -> Result<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_slice(pem)
+        .map_err(|e| IpcError::InvalidName(format!("invalid private key PEM: {}", e)))
+}
+
+fn parse_roots(pem: &[u8]) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in parse_certs(pem)? {
+        roots.add(cert).map_err(tls_error)?;
+    }
+    Ok(roots)
+}
+
+struct TlsListener {
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+}
+
+impl TransportListener for TlsListener {
+    /// Accepts without waiting for the handshake, which happens on the
+    /// connection's first read or write.
+    fn accept(&self) -> Result<Box<dyn TransportStream>> {
+        let (sock, _) = self.listener.accept()?;
+        sock.set_nodelay(true)?;
+        let conn = ServerConnection::new(Arc::clone(&self.config)).map_err(tls_error)?;
+        Ok(Box::new(TlsStream {
+            conn: conn.into(),
+            sock,
+        }))
+    }
+
+    fn local_addr(&self) -> String {
+        TransportListener::local_addr(&self.listener)
+    }
+}
+
+/// One end of a TLS connection
+struct TlsStream {
+    conn: rustls::Connection,
+    sock: TcpStream,
+}
+
+/// Reads the socket without blocking
+struct NonBlocking<'a>(&'a mut TcpStream);
+
+impl Read for NonBlocking<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        TransportStream::try_read(self.0, buf)
+    }
+}
+
+impl TlsStream {
+    /// Send whatever records rustls has queued
+    fn flush_tls(&mut self) -> std::io::Result<()> {
+        while self.conn.wants_write() {
+            self.conn.write_tls(&mut self.sock)?;
+        }
+        Ok(())
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            match self.conn.reader().read(buf) {
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+            // Blocks for the next records (finishing the handshake first)
+            if self.conn.complete_io(&mut self.sock)? == (0, 0) {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.conn.is_handshaking() {
+            self.conn.complete_io(&mut self.sock)?;
+        }
+        let n = self.conn.writer().write(buf)?;
+        self.flush_tls()?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.conn.writer().flush()?;
+        self.flush_tls()?;
+        self.sock.flush()
+    }
+}
+
+impl TransportStream for TlsStream {
+    fn try_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            match self.conn.reader().read(buf) {
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+            // Fails with WouldBlock once the socket has nothing more
+            if self.conn.read_tls(&mut NonBlocking(&mut self.sock))? == 0 {
+                return Ok(0);
+            }
+            self.conn
+                .process_new_packets()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            // Handshake replies, key updates
+            self.flush_tls()?;
+        }
+    }
+
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> std::io::Result<usize> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            match TransportStream::try_read(self, buf) {
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+            // Wait for more records; a partial one reads as WouldBlock above
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            if !wait_tcp_readable(&self.sock, remaining)? {
+                return Err(std::io::ErrorKind::TimedOut.into());
+            }
+        }
+    }
+
+    /// TLS session state cannot be shared, so connections over TLS do not
+    /// receive [`Broadcaster`](crate::Broadcaster) messages.
+    fn try_clone(&self) -> Result<Box<dyn TransportStream>> {
+        Err(IpcError::Platform(
+            "cloning TLS streams is not supported".to_string(),
+        ))
+    }
+
+    fn shutdown(&self) -> std::io::Result<()> {
+        self.sock.shutdown(std::net::Shutdown::Both)
+    }
+
+    fn is_peer_closed(&self) -> bool {
+        TransportStream::is_peer_closed(&self.sock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_pem_is_rejected() {
+        assert!(matches!(
+            TlsConfig::server(b"not a certificate", b"not a key"),
+            Err(IpcError::InvalidName(_))
+        ));
+        assert!(matches!(
+            TlsConfig::client(b"", "localhost"),
+            Err(IpcError::InvalidName(_))
+        ));
+    }
+}
@@ -0,0 +1,285 @@
+//! # Trace Context
+//!
+//! Correlation IDs that follow a unit of work across processes. A
+//! [`TraceContext`] is a W3C `traceparent` pair of a 128-bit trace ID, shared
+//! by every hop, and a 64-bit span ID naming the current hop.
+//!
+//! The first hop assigns the trace ID and later hops derive a
+//! [`child`](TraceContext::child) from what they received:
+//!
+//! - [`ApiClient`] requests carry it in a `traceparent` header
+//!   ([`TRACE_HEADER`]), and [`ApiServer`] handlers run inside it.
+//! - Socket [`Message`]s carry it in their frame header, and
+//!   [`SocketServer`] handlers run inside it.
+//! - [`CliBridge`] tasks pass it to child processes through
+//!   `TRACEPARENT` ([`ENV_TRACEPARENT`]).
+//! - [`Event`]s record the trace they were published in.
+//!
+//! The context of the running hop is thread-local: [`TraceContext::enter`]
+//! makes one current, and every message, request and event created while it
+//! is entered is stamped with it.
+//!
+//! ## Exporting spans
+//!
+//! When access logging is enabled, the `api.request` and `socket.message`
+//! spans get a `trace_id` field, and the hook installed with
+//! [`set_trace_hook`] is called with each of them. With
+//! `tracing-opentelemetry`, the hook is where the span is parented to the
+//! remote context:
+//!
+//! ```rust,ignore
+//! use opentelemetry::propagation::TextMapPropagator;
+//! use tracing_opentelemetry::OpenTelemetrySpanExt;
+//!
+//! ipckit::set_trace_hook(|ctx, span| {
+//!     let carrier = HashMap::from([("traceparent".to_string(), ctx.to_string())]);
+//!     span.set_parent(TraceContextPropagator::new().extract(&carrier));
+//! });
+//! ```
+//!
+//! [`ApiClient`]: crate::api_server::ApiClient
+//! [`ApiServer`]: crate::api_server::ApiServer
+//! [`Message`]: crate::socket_server::Message
+//! [`SocketServer`]: crate::socket_server::SocketServer
+//! [`CliBridge`]: crate::cli_bridge::CliBridge
+//! [`Event`]: crate::event_stream::Event
+
+use crate::error::{IpcError, Result};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::Span;
+
+/// HTTP header carrying the trace context of an API request.
+pub const TRACE_HEADER: &str = "traceparent";
+
+/// Environment variable carrying the trace context to a child process.
+pub const ENV_TRACEPARENT: &str = "TRACEPARENT";
+
+type TraceHook = Arc<dyn Fn(&TraceContext, &Span) + Send + Sync>;
+
+static HOOK: RwLock<Option<TraceHook>> = RwLock::new(None);
+
+thread_local! {
+    static CURRENT: Cell<Option<TraceContext>> = const { Cell::new(None) };
+}
+
+/// The trace a hop belongs to and the hop's own span.
+///
+/// Formats and parses as a W3C `traceparent` value
+/// (`00-<trace id>-<span id>-01`), which is also its serialized form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+}
+
+impl TraceContext {
+    /// Start a new trace.
+    pub fn root() -> Self {
+        let high = random_u64();
+        Self {
+            trace_id: (u128::from(high) << 64) | u128::from(random_u64()),
+            span_id: random_u64(),
+            parent_id: None,
+        }
+    }
+
+    /// The context for the next hop of this trace.
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            span_id: random_u64(),
+            parent_id: Some(self.span_id),
+        }
+    }
+
+    /// The context entered on this thread, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.with(Cell::get)
+    }
+
+    /// The context entered on this thread, or a new trace if there is none.
+    pub fn current_or_root() -> Self {
+        Self::current().unwrap_or_else(Self::root)
+    }
+
+    /// The context a parent process passed through [`ENV_TRACEPARENT`].
+    pub fn from_env() -> Option<Self> {
+        std::env::var(ENV_TRACEPARENT).ok()?.parse().ok()
+    }
+
+    /// Make this the current context of the thread until the guard is
+    /// dropped.
+    pub fn enter(&self) -> TraceGuard {
+        TraceGuard {
+            previous: CURRENT.with(|current| current.replace(Some(*self))),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// The trace ID, as 32 hex digits.
+    pub fn trace_id(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    /// The span ID of this hop, as 16 hex digits.
+    pub fn span_id(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    /// The span ID of the hop this one was derived from.
+    pub fn parent_span_id(&self) -> Option<String> {
+        self.parent_id.map(|id| format!("{:016x}", id))
+    }
+
+    /// Record this context on `span` and pass both to the trace hook.
+    ///
+    /// The `trace_id` field is only recorded if the span declares it.
+    pub fn attach(&self, span: &Span) {
+        span.record("trace_id", self.trace_id().as_str());
+        let hook = HOOK.read().clone();
+        if let Some(hook) = hook {
+            hook(self, span);
+        }
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "00-{:032x}-{:016x}-01", self.trace_id, self.span_id)
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = IpcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || IpcError::deserialization(format!("invalid traceparent: {}", s));
+        let parts: Vec<&str> = s.trim().split('-').collect();
+        let [version, trace_id, span_id, _flags] = parts[..] else {
+            return Err(invalid());
+        };
+        if version.len() != 2 || trace_id.len() != 32 || span_id.len() != 16 {
+            return Err(invalid());
+        }
+        let trace_id = u128::from_str_radix(trace_id, 16).map_err(|_| invalid())?;
+        let span_id = u64::from_str_radix(span_id, 16).map_err(|_| invalid())?;
+        // All-zero IDs are invalid per the W3C spec
+        if trace_id == 0 || span_id == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            trace_id,
+            span_id,
+            parent_id: None,
+        })
+    }
+}
+
+impl From<TraceContext> for String {
+    fn from(ctx: TraceContext) -> Self {
+        ctx.to_string()
+    }
+}
+
+impl TryFrom<String> for TraceContext {
+    type Error = IpcError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Restores the previously current context when dropped.
+///
+/// Returned by [`TraceContext::enter`]; it cannot leave the thread it was
+/// created on.
+pub struct TraceGuard {
+    previous: Option<TraceContext>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.previous));
+    }
+}
+
+/// Install a hook called with every span ipckit attaches a trace context
+/// to, replacing any previous one.
+pub fn set_trace_hook<F>(hook: F)
+where
+    F: Fn(&TraceContext, &Span) + Send + Sync + 'static,
+{
+    *HOOK.write() = Some(Arc::new(hook));
+}
+
+/// Remove the trace hook.
+pub fn clear_trace_hook() {
+    *HOOK.write() = None;
+}
+
+/// A random non-zero ID.
+fn random_u64() -> u64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        let id = hasher.finish();
+        if id != 0 {
+            return id;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_round_trip() {
+        let root = TraceContext::root();
+        let child = root.child();
+        assert_eq!(child.trace_id(), root.trace_id());
+        assert_eq!(child.parent_span_id(), Some(root.span_id()));
+        assert_ne!(child.span_id(), root.span_id());
+
+        let header = child.to_string();
+        assert_eq!(header.len(), 55);
+        let parsed: TraceContext = header.parse().unwrap();
+        assert_eq!(parsed.trace_id(), child.trace_id());
+        assert_eq!(parsed.span_id(), child.span_id());
+
+        let json = serde_json::to_string(&child).unwrap();
+        assert_eq!(json, format!("\"{}\"", header));
+        assert!("00-abc-def-01".parse::<TraceContext>().is_err());
+        assert!(format!("00-{:032x}-{:016x}-01", 0, 1)
+            .parse::<TraceContext>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_enter_nests_and_restores() {
+        assert!(TraceContext::current().is_none());
+        let outer = TraceContext::root();
+        {
+            let _outer = outer.enter();
+            let inner = outer.child();
+            {
+                let _inner = inner.enter();
+                assert_eq!(TraceContext::current(), Some(inner));
+            }
+            assert_eq!(TraceContext::current(), Some(outer));
+        }
+        assert!(TraceContext::current().is_none());
+    }
+}
@@ -0,0 +1,1061 @@
+//! # Transport
+//!
+//! Pluggable byte-stream backends for [`SocketServer`](crate::SocketServer),
+//! [`ApiServer`](crate::ApiServer) and [`IpcChannel`](crate::IpcChannel).
+//!
+//! A [`Transport`] connects to and binds addresses; what an address looks
+//! like is up to the backend. The built-in backends are:
+//!
+//! | Backend | Address | Notes |
+//! |---------|---------|-------|
+//! | [`LocalSocketTransport`] | socket path / pipe name | Default for every server |
+//! | [`NamedPipeTransport`] | pipe name | Unix domain socket under `/tmp` on Unix |
+//! | [`ShmTransport`] | segment name | Shared-memory rings, no kernel socket |
+//! | [`TcpTransport`] | `127.0.0.1:port` | Loopback only by default; optional TLS |
+//! | [`StdioTransport`] | command line | Child serves its stdin/stdout, parent spawns it |
+//!
+//! Implement the three traits to run the servers over anything else that
+//! moves bytes in order, e.g. vsock or a test harness.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ipckit::{Message, SocketClient, SocketServer, SocketServerConfig, TcpTransport};
+//! use std::sync::Arc;
+//!
+//! let config = SocketServerConfig {
+//!     path: "127.0.0.1:0".to_string(),
+//!     transport: Arc::new(TcpTransport::new()),
+//!     ..Default::default()
+//! };
+//! let server = SocketServer::new(config)?;
+//! let addr = server.local_addr();
+//! // serve with server.spawn(handler) ...
+//!
+//! let mut client = SocketClient::connect_with(&TcpTransport::new(), &addr)?;
+//! client.send(&Message::json(serde_json::json!({"hello": "world"})))?;
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::local_socket::{LocalSocketListener, LocalSocketStream};
+use crate::pipe::NamedPipe;
+use crate::shm_queue::ShmQueue;
+use parking_lot::Mutex;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// A way of establishing byte-stream connections.
+pub trait Transport: Send + Sync + std::fmt::Debug {
+    /// Short backend name, used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Connect to a listener bound at `addr`.
+    fn connect(&self, addr: &str) -> Result<Box<dyn TransportStream>>;
+
+    /// Start listening at `addr`.
+    fn bind(&self, addr: &str) -> Result<Box<dyn TransportListener>>;
+
+    /// Remove whatever a crashed listener left behind at `addr`.
+    ///
+    /// Called by servers with `cleanup_on_start` before binding.
+    fn remove_stale(&self, _addr: &str) {}
+}
+
+/// The listening side of a [`Transport`].
+pub trait TransportListener: Send + Sync {
+    /// Wait for the next client.
+    ///
+    /// Fails with [`IpcError::Closed`] once the listener will never accept
+    /// another client, which stops a server's accept loop.
+    fn accept(&self) -> Result<Box<dyn TransportStream>>;
+
+    /// The address clients connect to, e.g. with the port a TCP listener
+    /// bound to `127.0.0.1:0` was given.
+    fn local_addr(&self) -> String;
+}
+
+/// One connection made by a [`Transport`].
+pub trait TransportStream: Read + Write + Send + Sync {
+    /// Read whatever data is available without blocking.
+    ///
+    /// Returns `Ok(0)` at end of stream and an error of kind
+    /// [`WouldBlock`](std::io::ErrorKind::WouldBlock) if nothing is available.
+    fn try_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// Read, waiting at most `timeout` for data to arrive.
+    ///
+    /// Returns `Ok(0)` at end of stream and an error of kind
+    /// [`TimedOut`](std::io::ErrorKind::TimedOut) if nothing arrived in
+    /// time. The default polls [`try_read`](Self::try_read); backends that
+    /// can block with a deadline override it.
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> std::io::Result<usize> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            match self.try_read(buf) {
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+            if std::time::Instant::now() >= deadline {
+                return Err(std::io::ErrorKind::TimedOut.into());
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Create another handle to the same connection, for writing from one
+    /// thread while another reads.
+    fn try_clone(&self) -> Result<Box<dyn TransportStream>>;
+
+    /// Shut down the connection, waking up reads blocked on it or a clone.
+    fn shutdown(&self) -> std::io::Result<()>;
+
+    /// Check whether the peer has closed the connection, without consuming
+    /// pending data. Backends that cannot tell return `false`.
+    fn is_peer_closed(&self) -> bool {
+        false
+    }
+}
+
+// ============================================================================
+// Local sockets
+// ============================================================================
+
+/// Unix domain sockets on Unix, named pipes on Windows (the default).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalSocketTransport;
+
+impl Transport for LocalSocketTransport {
+    fn name(&self) -> &'static str {
+        "local-socket"
+    }
+
+    fn connect(&self, addr: &str) -> Result<Box<dyn TransportStream>> {
+        Ok(Box::new(LocalSocketStream::connect(addr)?))
+    }
+
+    fn bind(&self, addr: &str) -> Result<Box<dyn TransportListener>> {
+        Ok(Box::new(LocalSocketListener::bind(addr)?))
+    }
+
+    fn remove_stale(&self, addr: &str) {
+        #[cfg(unix)]
+        if !addr.starts_with(r"\\.\pipe\") {
+            let _ = std::fs::remove_file(addr);
+        }
+        #[cfg(windows)]
+        let _ = addr;
+    }
+}
+
+impl TransportListener for LocalSocketListener {
+    fn accept(&self) -> Result<Box<dyn TransportStream>> {
+        Ok(Box::new(LocalSocketListener::accept(self)?))
+    }
+
+    fn local_addr(&self) -> String {
+        self.name().to_string()
+    }
+}
+
+impl TransportStream for LocalSocketStream {
+    fn try_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        LocalSocketStream::try_read(self, buf)
+    }
+
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> std::io::Result<usize> {
+        LocalSocketStream::read_with_timeout(self, buf, timeout)
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn TransportStream>> {
+        Ok(Box::new(LocalSocketStream::try_clone(self)?))
+    }
+
+    fn shutdown(&self) -> std::io::Result<()> {
+        LocalSocketStream::shutdown(self)
+    }
+
+    fn is_peer_closed(&self) -> bool {
+        LocalSocketStream::is_peer_closed(self)
+    }
+}
+
+// ============================================================================
+// Named pipes
+// ============================================================================
+
+/// [`NamedPipe`]s, one pipe instance per client.
+///
+/// Streams cannot be cloned, so connections served over this transport do
+/// not receive [`Broadcaster`](crate::Broadcaster) messages.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NamedPipeTransport;
+
+struct NamedPipeListener {
+    name: String,
+    pipe: Mutex<NamedPipe>,
+}
+
+impl Transport for NamedPipeTransport {
+    fn name(&self) -> &'static str {
+        "named-pipe"
+    }
+
+    fn connect(&self, addr: &str) -> Result<Box<dyn TransportStream>> {
+        Ok(Box::new(NamedPipe::connect(addr)?))
+    }
+
+    fn bind(&self, addr: &str) -> Result<Box<dyn TransportListener>> {
+        let pipe = NamedPipe::create(addr)?;
+        Ok(Box::new(NamedPipeListener {
+            name: pipe.name().to_string(),
+            pipe: Mutex::new(pipe),
+        }))
+    }
+}
+
+impl TransportListener for NamedPipeListener {
+    fn accept(&self) -> Result<Box<dyn TransportStream>> {
+        Ok(Box::new(self.pipe.lock().accept()?))
+    }
+
+    fn local_addr(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl TransportStream for NamedPipe {
+    fn try_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.wait_readable(Duration::ZERO)? {
+            self.read(buf)
+        } else {
+            Err(std::io::ErrorKind::WouldBlock.into())
+        }
+    }
+
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> std::io::Result<usize> {
+        if self.wait_readable(timeout)? {
+            self.read(buf)
+        } else {
+            Err(std::io::ErrorKind::TimedOut.into())
+        }
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn TransportStream>> {
+        Err(IpcError::Platform(
+            "cloning named pipes is not supported".to_string(),
+        ))
+    }
+
+    /// Cancels reads; writes still go through.
+    fn shutdown(&self) -> std::io::Result<()> {
+        self.canceller().cancel();
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Shared memory
+// ============================================================================
+
+/// Shared-memory rings built on [`ShmQueue`].
+///
+/// Binding creates a small rendezvous queue at the address. Each client
+/// creates a pair of rings (one per direction) named after the address and
+/// announces them there. Writes block while the peer's ring is full, and
+/// closing either end is seen by the other as end of stream.
+///
+/// Segment and semaphore names are derived from the address, so keep it
+/// short (macOS limits semaphore names to 31 bytes).
+#[derive(Debug, Clone, Copy)]
+pub struct ShmTransport {
+    slots: usize,
+    slot_size: usize,
+}
+
+impl Default for ShmTransport {
+    fn default() -> Self {
+        Self {
+            slots: 64,
+            slot_size: 64 * 1024,
+        }
+    }
+}
+
+impl ShmTransport {
+    /// Create a transport whose client connections use rings of `slots`
+    /// slots of `slot_size` bytes in each direction.
+    pub fn new(slots: usize, slot_size: usize) -> Self {
+        Self { slots, slot_size }
+    }
+}
+
+/// Distinguishes connections made by this process
+static NEXT_SHM_CONNECTION: AtomicU64 = AtomicU64::new(0);
+
+/// Both rings of one connection; closes them when the last handle is dropped
+struct ShmLink {
+    tx: ShmQueue<()>,
+    rx: ShmQueue<()>,
+}
+
+impl ShmLink {
+    fn close(&self) {
+        self.tx.close();
+        self.rx.close();
+    }
+}
+
+impl Drop for ShmLink {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+struct ShmStream {
+    link: Arc<ShmLink>,
+    /// Rest of the last slot that did not fit the caller's buffer
+    leftover: Vec<u8>,
+    pos: usize,
+}
+
+impl ShmStream {
+    fn new(link: ShmLink) -> Self {
+        Self {
+            link: Arc::new(link),
+            leftover: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    fn fill(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> std::io::Result<usize> {
+        if self.pos == self.leftover.len() {
+            match self.link.rx.pop_bytes(timeout) {
+                Ok(data) => {
+                    self.leftover = data;
+                    self.pos = 0;
+                }
+                Err(IpcError::Closed) => return Ok(0),
+                Err(IpcError::Timeout) => return Err(std::io::ErrorKind::WouldBlock.into()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let n = buf.len().min(self.leftover.len() - self.pos);
+        buf[..n].copy_from_slice(&self.leftover[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+struct ShmListener {
+    name: String,
+    accepts: ShmQueue<String>,
+}
+
+impl Transport for ShmTransport {
+    fn name(&self) -> &'static str {
+        "shm"
+    }
+
+    fn connect(&self, addr: &str) -> Result<Box<dyn TransportStream>> {
+        let accepts = ShmQueue::<String>::open(addr)?;
+        let id = format!(
+            "{}-{:x}-{:x}",
+            addr,
+            std::process::id(),
+            NEXT_SHM_CONNECTION.fetch_add(1, Ordering::Relaxed)
+        );
+        let link = ShmLink {
+            tx: ShmQueue::create(&format!("{}c", id), self.slots, self.slot_size)?,
+            rx: ShmQueue::create(&format!("{}s", id), self.slots, self.slot_size)?,
+        };
+        accepts.push(&id)?;
+        Ok(Box::new(ShmStream::new(link)))
+    }
+
+    fn bind(&self, addr: &str) -> Result<Box<dyn TransportListener>> {
+        Ok(Box::new(ShmListener {
+            name: addr.to_string(),
+            accepts: ShmQueue::create(addr, 16, 256)?,
+        }))
+    }
+}
+
+impl TransportListener for ShmListener {
+    fn accept(&self) -> Result<Box<dyn TransportStream>> {
+        loop {
+            let id = self.accepts.pop()?;
+            // A client that gave up before we got here has removed its rings
+            let link = match (
+                ShmQueue::open(&format!("{}s", id)),
+                ShmQueue::open(&format!("{}c", id)),
+            ) {
+                (Ok(tx), Ok(rx)) => ShmLink { tx, rx },
+                _ => continue,
+            };
+            return Ok(Box::new(ShmStream::new(link)));
+        }
+    }
+
+    fn local_addr(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl Read for ShmStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.fill(buf, None)
+    }
+}
+
+impl Write for ShmStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.link.tx.slot_size());
+        match self.link.tx.push_bytes(&buf[..n], None) {
+            Ok(()) => Ok(n),
+            Err(IpcError::Closed) => Err(std::io::ErrorKind::BrokenPipe.into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl TransportStream for ShmStream {
+    fn try_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.fill(buf, Some(Duration::ZERO))
+    }
+
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> std::io::Result<usize> {
+        self.fill(buf, Some(timeout)).map_err(|e| match e.kind() {
+            std::io::ErrorKind::WouldBlock => std::io::ErrorKind::TimedOut.into(),
+            _ => e,
+        })
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn TransportStream>> {
+        Ok(Box::new(ShmStream {
+            link: Arc::clone(&self.link),
+            leftover: Vec::new(),
+            pos: 0,
+        }))
+    }
+
+    fn shutdown(&self) -> std::io::Result<()> {
+        self.link.close();
+        Ok(())
+    }
+
+    fn is_peer_closed(&self) -> bool {
+        self.link.rx.is_closed() && self.link.rx.is_empty()
+    }
+}
+
+// ============================================================================
+// TCP
+// ============================================================================
+
+/// TCP, restricted to loopback addresses unless
+/// [`allow_remote`](Self::allow_remote) is set.
+///
+/// For hosts where local sockets are unavailable or blocked, e.g. some
+/// sandboxes and containers, and for clients that local sockets cannot
+/// reach: a container, WSL, or another machine on a trusted network.
+/// Anything that can reach the address can connect, so pair it with
+/// authentication (or TLS client certificates, with the `tls` feature)
+/// where that matters.
+#[derive(Debug, Clone, Default)]
+pub struct TcpTransport {
+    allow_remote: bool,
+    #[cfg(feature = "tls")]
+    tls: Option<crate::tls::TlsConfig>,
+}
+
+impl TcpTransport {
+    /// Create a loopback-only transport without TLS.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow binding and connecting to addresses other than loopback.
+    pub fn allow_remote(mut self, allow: bool) -> Self {
+        self.allow_remote = allow;
+        self
+    }
+
+    /// Encrypt connections with TLS.
+    ///
+    /// Servers need a config made by
+    /// [`TlsConfig::server`](crate::tls::TlsConfig::server), clients one made
+    /// by [`TlsConfig::client`](crate::tls::TlsConfig::client).
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: crate::tls::TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Resolve `addr`, refusing anything but loopback unless remote
+    /// addresses are allowed
+    fn resolve(&self, addr: &str) -> Result<SocketAddr> {
+        let resolved = addr
+            .to_socket_addrs()
+            .map_err(|_| IpcError::InvalidName(format!("invalid TCP address: {}", addr)))?
+            .find(|a| self.allow_remote || a.ip().is_loopback());
+        resolved.ok_or_else(|| {
+            IpcError::PermissionDenied(format!("{} is not a loopback address", addr))
+        })
+    }
+}
+
+impl Transport for TcpTransport {
+    fn name(&self) -> &'static str {
+        "tcp"
+    }
+
+    fn connect(&self, addr: &str) -> Result<Box<dyn TransportStream>> {
+        let stream = TcpStream::connect(self.resolve(addr)?).map_err(|e| match e.kind() {
+            std::io::ErrorKind::ConnectionRefused => {
+                IpcError::NotFound(format!("Connection refused: {}", addr))
+            }
+            _ => IpcError::Io(e),
+        })?;
+        stream.set_nodelay(true)?;
+        #[cfg(feature = "tls")]
+        if let Some(ref tls) = self.tls {
+            return tls.connect(stream);
+        }
+        Ok(Box::new(stream))
+    }
+
+    fn bind(&self, addr: &str) -> Result<Box<dyn TransportListener>> {
+        let listener = TcpListener::bind(self.resolve(addr)?).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AddrInUse => IpcError::AlreadyExists(addr.to_string()),
+            _ => IpcError::Io(e),
+        })?;
+        #[cfg(feature = "tls")]
+        if let Some(ref tls) = self.tls {
+            return tls.listen(listener);
+        }
+        Ok(Box::new(listener))
+    }
+}
+
+impl TransportListener for TcpListener {
+    fn accept(&self) -> Result<Box<dyn TransportStream>> {
+        let (stream, _) = TcpListener::accept(self)?;
+        stream.set_nodelay(true)?;
+        Ok(Box::new(stream))
+    }
+
+    fn local_addr(&self) -> String {
+        TcpListener::local_addr(self)
+            .map(|a| a.to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Wait until `stream` has data to read (or hit end of stream) or `timeout`
+/// passes, without consuming anything.
+pub(crate) fn wait_tcp_readable(stream: &TcpStream, timeout: Duration) -> std::io::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        crate::unix::wait_readable(stream.as_raw_fd(), timeout)
+    }
+    #[cfg(windows)]
+    {
+        // Peeking blocks up to the receive timeout; put the caller's back
+        let previous = stream.read_timeout()?;
+        stream.set_read_timeout(Some(timeout.max(Duration::from_millis(1))))?;
+        let result = stream.peek(&mut [0u8; 1]);
+        stream.set_read_timeout(previous)?;
+        match result {
+            Ok(_) => Ok(true),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+                ) =>
+            {
+                Ok(false)
+            }
+            // A reset connection is readable: the read reports the error
+            Err(_) => Ok(true),
+        }
+    }
+}
+
+impl TransportStream for TcpStream {
+    fn try_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            crate::unix::recv_nonblocking(self.as_raw_fd(), buf)
+        }
+        #[cfg(windows)]
+        {
+            // The receive timeout only affects reads, which clones don't do;
+            // the caller's own timeout is put back whatever the read returns
+            let previous = self.read_timeout()?;
+            self.set_read_timeout(Some(Duration::from_millis(1)))?;
+            let result = self.read(buf);
+            self.set_read_timeout(previous)?;
+            result.map_err(|e| match e.kind() {
+                std::io::ErrorKind::TimedOut => std::io::ErrorKind::WouldBlock.into(),
+                _ => e,
+            })
+        }
+    }
+
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> std::io::Result<usize> {
+        if !wait_tcp_readable(self, timeout)? {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        self.read(buf)
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn TransportStream>> {
+        Ok(Box::new(TcpStream::try_clone(self)?))
+    }
+
+    fn shutdown(&self) -> std::io::Result<()> {
+        TcpStream::shutdown(self, std::net::Shutdown::Both)
+    }
+
+    fn is_peer_closed(&self) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            crate::unix::socket_peer_closed(self.as_raw_fd())
+        }
+        #[cfg(windows)]
+        {
+            false
+        }
+    }
+}
+
+// ============================================================================
+// Stdio
+// ============================================================================
+
+/// A child process's stdin and stdout, as used by LSP- and DAP-style tools.
+///
+/// In the child, binding serves the process's own stdin/stdout as the one
+/// and only connection, so the same handler or router that would listen on
+/// a socket serves its parent instead, without creating any named endpoint.
+/// Once that connection ends, the listener reports [`IpcError::Closed`] and
+/// the server's `run` returns. Only one listener may be bound per process,
+/// and nothing else may write to stdout meanwhile; log to stderr.
+///
+/// In the parent, connecting spawns the address as a command line (split on
+/// whitespace; use [`StdioStream::spawn`] for anything more involved) and
+/// talks to the child over its stdin/stdout. Its stderr is inherited.
+///
+/// ```rust,no_run
+/// use ipckit::{SocketClient, SocketServer, SocketServerConfig, StdioTransport};
+/// use std::sync::Arc;
+///
+/// // Child: serve the handler over stdin/stdout
+/// let config = SocketServerConfig {
+///     transport: Arc::new(StdioTransport),
+///     ..Default::default()
+/// };
+/// // SocketServer::new(config)?.run(handler)?;
+///
+/// // Parent: spawn the child and talk to it
+/// let mut client = SocketClient::connect_with(&StdioTransport, "my-server --stdio")?;
+/// # Ok::<(), ipckit::IpcError>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdioTransport;
+
+/// Address reported by a stdio listener
+const STDIO_ADDR: &str = "stdio";
+
+/// Size of the chunks read from stdin or a child's stdout
+const STDIO_CHUNK: usize = 8192;
+
+/// How often blocked stdio reads and accepts check for shutdown
+const STDIO_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a dropped child gets to exit after its stdin is closed
+const CHILD_EXIT_GRACE: Duration = Duration::from_secs(1);
+
+/// Set once this process's stdin/stdout have been taken
+static STDIO_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// The write side and child process of a stdio connection, shared by clones.
+struct StdioLink {
+    writer: Mutex<Option<Box<dyn Write + Send>>>,
+    shutdown: AtomicBool,
+    child: Mutex<Option<Child>>,
+}
+
+impl Drop for StdioLink {
+    fn drop(&mut self) {
+        // Closing the child's stdin asks it to exit
+        self.writer.get_mut().take();
+        if let Some(mut child) = self.child.get_mut().take() {
+            let deadline = std::time::Instant::now() + CHILD_EXIT_GRACE;
+            while matches!(child.try_wait(), Ok(None)) && std::time::Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            if matches!(child.try_wait(), Ok(None)) {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+/// A connection over a pair of pipes: this process's stdin/stdout, or a
+/// child's stdout/stdin.
+///
+/// Reads are pumped by a background thread, so [`try_read`](TransportStream::try_read)
+/// and [`shutdown`](TransportStream::shutdown) work as for sockets. Dropping
+/// the last handle to a spawned child closes its stdin and, if it has not
+/// exited within a second, kills it.
+pub struct StdioStream {
+    rx: crossbeam_channel::Receiver<Vec<u8>>,
+    eof: Arc<AtomicBool>,
+    link: Arc<StdioLink>,
+    /// Rest of the last chunk that did not fit the caller's buffer
+    leftover: Vec<u8>,
+    pos: usize,
+}
+
+impl StdioStream {
+    /// Take this process's stdin and stdout.
+    ///
+    /// Fails with [`IpcError::AlreadyExists`] if they have been taken before.
+    pub fn stdio() -> Result<Self> {
+        if STDIO_TAKEN.swap(true, Ordering::SeqCst) {
+            return Err(IpcError::AlreadyExists(
+                "stdin/stdout are already in use".to_string(),
+            ));
+        }
+        Ok(Self::new(
+            std::io::stdin(),
+            Box::new(std::io::stdout()),
+            None,
+        ))
+    }
+
+    /// Spawn `command` with piped stdin and stdout and connect to it.
+    pub fn spawn(mut command: Command) -> Result<Self> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    IpcError::NotFound(format!("cannot spawn {:?}: {}", command.get_program(), e))
+                }
+                _ => IpcError::Io(e),
+            })?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(Self::new(stdout, Box::new(stdin), Some(child)))
+    }
+
+    /// Get the process ID of the spawned child, if any.
+    pub fn child_id(&self) -> Option<u32> {
+        self.link.child.lock().as_ref().map(Child::id)
+    }
+
+    fn new<R: Read + Send + 'static>(
+        mut reader: R,
+        writer: Box<dyn Write + Send>,
+        child: Option<Child>,
+    ) -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let eof = Arc::new(AtomicBool::new(false));
+        let pump_eof = Arc::clone(&eof);
+        std::thread::Builder::new()
+            .name("ipckit-stdio".to_string())
+            .spawn(move || {
+                let mut buf = vec![0u8; STDIO_CHUNK];
+                loop {
+                    match reader.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) => {
+                            if tx.send(buf[..n].to_vec()).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                        Err(_) => break,
+                    }
+                }
+                pump_eof.store(true, Ordering::SeqCst);
+            })
+            .expect("failed to spawn stdio reader");
+
+        Self {
+            rx,
+            eof,
+            link: Arc::new(StdioLink {
+                writer: Mutex::new(Some(writer)),
+                shutdown: AtomicBool::new(false),
+                child: Mutex::new(child),
+            }),
+            leftover: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Fill `buf` from the pump, waiting at most `timeout` (forever if `None`)
+    fn fill(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> std::io::Result<usize> {
+        if self.pos == self.leftover.len() {
+            let deadline = timeout.map(|t| std::time::Instant::now() + t);
+            loop {
+                if self.link.shutdown.load(Ordering::SeqCst) {
+                    return Ok(0);
+                }
+                // Wake up now and then to notice a shutdown
+                let wait = match deadline {
+                    Some(deadline) => deadline
+                        .saturating_duration_since(std::time::Instant::now())
+                        .min(STDIO_POLL_INTERVAL),
+                    None => STDIO_POLL_INTERVAL,
+                };
+                let received = if wait.is_zero() {
+                    self.rx.try_recv().map_err(|e| e.is_empty())
+                } else {
+                    self.rx.recv_timeout(wait).map_err(|e| e.is_timeout())
+                };
+                match received {
+                    Ok(data) => {
+                        self.leftover = data;
+                        self.pos = 0;
+                        break;
+                    }
+                    Err(true) if deadline.is_none_or(|d| std::time::Instant::now() < d) => {}
+                    Err(true) => return Err(std::io::ErrorKind::WouldBlock.into()),
+                    // The pump hit end of stream
+                    Err(false) => return Ok(0),
+                }
+            }
+        }
+        let n = buf.len().min(self.leftover.len() - self.pos);
+        buf[..n].copy_from_slice(&self.leftover[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl Read for StdioStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.fill(buf, None)
+    }
+}
+
+impl Write for StdioStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.link.writer.lock().as_mut() {
+            Some(writer) => writer.write(buf),
+            None => Err(std::io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.link.writer.lock().as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl TransportStream for StdioStream {
+    fn try_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.fill(buf, Some(Duration::ZERO))
+    }
+
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> std::io::Result<usize> {
+        self.fill(buf, Some(timeout)).map_err(|e| match e.kind() {
+            std::io::ErrorKind::WouldBlock => std::io::ErrorKind::TimedOut.into(),
+            _ => e,
+        })
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn TransportStream>> {
+        Ok(Box::new(StdioStream {
+            rx: self.rx.clone(),
+            eof: Arc::clone(&self.eof),
+            link: Arc::clone(&self.link),
+            leftover: Vec::new(),
+            pos: 0,
+        }))
+    }
+
+    /// Wakes up reads and closes the write side.
+    fn shutdown(&self) -> std::io::Result<()> {
+        self.link.shutdown.store(true, Ordering::SeqCst);
+        self.link.writer.lock().take();
+        Ok(())
+    }
+
+    fn is_peer_closed(&self) -> bool {
+        self.eof.load(Ordering::SeqCst) && self.rx.is_empty() && self.pos == self.leftover.len()
+    }
+}
+
+/// Hands out this process's stdio once, then waits for it to be done with.
+struct StdioListener {
+    stream: Mutex<Option<StdioStream>>,
+    served: Mutex<Weak<StdioLink>>,
+}
+
+impl Transport for StdioTransport {
+    fn name(&self) -> &'static str {
+        "stdio"
+    }
+
+    fn connect(&self, addr: &str) -> Result<Box<dyn TransportStream>> {
+        // What a server shutting down uses to wake its accept loop
+        if addr == STDIO_ADDR {
+            return Err(IpcError::InvalidName(
+                "a stdio listener cannot be connected to".to_string(),
+            ));
+        }
+        let mut args = addr.split_whitespace();
+        let program = args
+            .next()
+            .ok_or_else(|| IpcError::InvalidName("empty stdio command line".to_string()))?;
+        let mut command = Command::new(program);
+        command.args(args);
+        Ok(Box::new(StdioStream::spawn(command)?))
+    }
+
+    fn bind(&self, _addr: &str) -> Result<Box<dyn TransportListener>> {
+        Ok(Box::new(StdioListener {
+            stream: Mutex::new(Some(StdioStream::stdio()?)),
+            served: Mutex::new(Weak::new()),
+        }))
+    }
+}
+
+impl TransportListener for StdioListener {
+    fn accept(&self) -> Result<Box<dyn TransportStream>> {
+        if let Some(stream) = self.stream.lock().take() {
+            *self.served.lock() = Arc::downgrade(&stream.link);
+            return Ok(Box::new(stream));
+        }
+        // There is only one connection; report closed once it is dropped
+        while self.served.lock().strong_count() > 0 {
+            std::thread::sleep(STDIO_POLL_INTERVAL);
+        }
+        Err(IpcError::Closed)
+    }
+
+    fn local_addr(&self) -> String {
+        STDIO_ADDR.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_once(transport: &dyn Transport, listener: Box<dyn TransportListener>) {
+        let addr = listener.local_addr();
+        let server = std::thread::spawn(move || {
+            let mut stream = listener.accept().unwrap();
+            let mut buf = [0u8; 5];
+            stream.read_exact(&mut buf).unwrap();
+            stream.write_all(&buf).unwrap();
+        });
+
+        let mut client = transport.connect(&addr).unwrap();
+        client.write_all(b"hello").unwrap();
+        let mut buf = [0u8; 5];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        server.join().unwrap();
+
+        // The server's end is gone
+        let mut rest = Vec::new();
+        assert_eq!(client.read_to_end(&mut rest).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_builtin_transports_echo() {
+        let listener = TcpTransport::new().bind("127.0.0.1:0").unwrap();
+        echo_once(&TcpTransport::new(), listener);
+        assert!(matches!(
+            TcpTransport::new().bind("0.0.0.0:0"),
+            Err(IpcError::PermissionDenied(_))
+        ));
+
+        let name = format!("ipt{}", std::process::id());
+        let shm = ShmTransport::new(4, 2);
+        let listener = shm.bind(&name).unwrap();
+        echo_once(&shm, listener);
+
+        let name = format!("ipckit_pipe_transport_{}", std::process::id());
+        let listener = NamedPipeTransport.bind(&name).unwrap();
+        echo_once(&NamedPipeTransport, listener);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stdio_transport_spawns_child() {
+        let mut stream = StdioTransport.connect("cat").unwrap();
+        stream.write_all(b"hello").unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+
+        // Closing the child's stdin makes cat exit, which is end of stream
+        stream.shutdown().unwrap();
+        assert!(stream.write_all(b"x").is_err());
+        assert!(matches!(
+            StdioTransport.connect("ipckit-no-such-program"),
+            Err(IpcError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_stdio_listener_serves_once() {
+        let stream = StdioStream::new(
+            std::io::Cursor::new(b"ping".to_vec()),
+            Box::new(std::io::sink()),
+            None,
+        );
+        let listener = StdioListener {
+            stream: Mutex::new(Some(stream)),
+            served: Mutex::new(Weak::new()),
+        };
+        assert_eq!(listener.local_addr(), "stdio");
+
+        let mut conn = listener.accept().unwrap();
+        let mut data = Vec::new();
+        conn.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"ping");
+        assert!(conn.is_peer_closed());
+        assert_eq!(conn.try_read(&mut [0u8; 4]).unwrap(), 0);
+
+        // The next accept waits for the only connection to go away
+        let waiter = std::thread::spawn(move || listener.accept().err());
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!waiter.is_finished());
+        drop(conn);
+        assert!(matches!(waiter.join().unwrap(), Some(IpcError::Closed)));
+    }
+}
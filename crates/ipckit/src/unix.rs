@@ -1,17 +1,25 @@
 //! Unix-specific IPC utilities
 //!
 //! Provides Unix Domain Sockets and other Unix-specific IPC mechanisms.
+//!
+//! On Linux and Android, sockets can also live in the abstract namespace
+//! ([`UnixSocketServer::bind_abstract`]): they have no filesystem path, so
+//! there is nothing to clean up and no directory permissions to get right,
+//! and the name disappears when the last socket closes.
 
 use crate::error::{IpcError, Result};
+use crate::local_socket::LocalSocketStream;
 use std::io::{Read, Write};
-use std::os::unix::io::{FromRawFd, OwnedFd};
+use std::os::unix::io::{FromRawFd, OwnedFd, RawFd};
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Unix Domain Socket server
 pub struct UnixSocketServer {
     listener: UnixListener,
     path: PathBuf,
+    abstract_name: Option<String>,
 }
 
 /// Unix Domain Socket client connection
@@ -39,7 +47,23 @@ impl UnixSocketServer {
             _ => IpcError::Io(e),
         })?;
 
-        Ok(Self { listener, path })
+        Ok(Self {
+            listener,
+            path,
+            abstract_name: None,
+        })
+    }
+
+    /// Create a new Unix socket server in the abstract namespace (Linux and
+    /// Android only)
+    ///
+    /// Fails with [`IpcError::AlreadyExists`] if the name is taken.
+    pub fn bind_abstract(name: &str) -> Result<Self> {
+        Ok(Self {
+            listener: bind_abstract(name)?,
+            path: PathBuf::new(),
+            abstract_name: Some(name.to_string()),
+        })
     }
 
     /// Accept a new connection
@@ -54,15 +78,22 @@ impl UnixSocketServer {
         Ok(())
     }
 
-    /// Get the socket path
+    /// Get the socket path (empty for abstract sockets)
     pub fn path(&self) -> &Path {
         &self.path
     }
+
+    /// Get the abstract name, if bound with [`bind_abstract`](Self::bind_abstract)
+    pub fn abstract_name(&self) -> Option<&str> {
+        self.abstract_name.as_deref()
+    }
 }
 
 impl Drop for UnixSocketServer {
     fn drop(&mut self) {
-        let _ = std::fs::remove_file(&self.path);
+        if self.abstract_name.is_none() {
+            let _ = std::fs::remove_file(&self.path);
+        }
     }
 }
 
@@ -84,6 +115,14 @@ impl UnixSocketClient {
         Ok(Self { stream })
     }
 
+    /// Connect to a Unix socket server in the abstract namespace (Linux and
+    /// Android only)
+    pub fn connect_abstract(name: &str) -> Result<Self> {
+        Ok(Self {
+            stream: connect_abstract(name)?,
+        })
+    }
+
     /// Set the socket to non-blocking mode
     pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
         self.stream.set_nonblocking(nonblocking)?;
@@ -137,6 +176,131 @@ pub fn socketpair() -> Result<(OwnedFd, OwnedFd)> {
     Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
 }
 
+/// Create a pair of connected [`LocalSocketStream`]s
+///
+/// Neither end has a name or a filesystem path. Keep one end and hand the
+/// other to a child process (e.g. via its raw fd), or use both within one
+/// process.
+pub fn local_socketpair() -> Result<(LocalSocketStream, LocalSocketStream)> {
+    let (a, b) = UnixStream::pair()?;
+    Ok((
+        LocalSocketStream::from_unix_stream(a, String::new()),
+        LocalSocketStream::from_unix_stream(b, String::new()),
+    ))
+}
+
+/// Read whatever a socket has buffered without blocking.
+///
+/// `MSG_DONTWAIT` leaves the socket itself blocking, so clones of it (and
+/// reads racing this one) are unaffected.
+pub(crate) fn recv_nonblocking(fd: RawFd, buf: &mut [u8]) -> std::io::Result<usize> {
+    let ret = unsafe {
+        libc::recv(
+            fd,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+            libc::MSG_DONTWAIT,
+        )
+    };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(ret as usize)
+}
+
+/// Check whether the peer of a socket has closed it, without consuming data.
+pub(crate) fn socket_peer_closed(fd: RawFd) -> bool {
+    let mut byte = 0u8;
+    let ret = unsafe {
+        libc::recv(
+            fd,
+            &mut byte as *mut u8 as *mut libc::c_void,
+            1,
+            libc::MSG_PEEK | libc::MSG_DONTWAIT,
+        )
+    };
+    // 0 means orderly shutdown; -1 with EAGAIN means still open
+    ret == 0
+        || (ret < 0
+            && !matches!(
+                std::io::Error::last_os_error().kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted
+            ))
+}
+
+/// Wait until `fd` has data to read (or hit end of stream) or `timeout` passes.
+///
+/// Returns `Ok(false)` if nothing arrived in time.
+pub(crate) fn wait_readable(fd: RawFd, timeout: Duration) -> std::io::Result<bool> {
+    let mut pfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        let ms = remaining.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+        match unsafe { libc::poll(&mut pfd, 1, ms) } {
+            -1 => {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            0 => return Ok(false),
+            _ => return Ok(true),
+        }
+    }
+}
+
+/// Bind a listener in the abstract namespace.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn bind_abstract(name: &str) -> Result<UnixListener> {
+    let addr = abstract_addr(name)?;
+    UnixListener::bind_addr(&addr).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AddrInUse => IpcError::AlreadyExists(format!("@{}", name)),
+        _ => IpcError::Io(e),
+    })
+}
+
+/// Connect to a listener in the abstract namespace.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn connect_abstract(name: &str) -> Result<UnixStream> {
+    let addr = abstract_addr(name)?;
+    UnixStream::connect_addr(&addr).map_err(|e| match e.kind() {
+        std::io::ErrorKind::ConnectionRefused => {
+            IpcError::NotFound(format!("Connection refused: @{}", name))
+        }
+        _ => IpcError::Io(e),
+    })
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn abstract_addr(name: &str) -> Result<std::os::unix::net::SocketAddr> {
+    #[cfg(target_os = "android")]
+    use std::os::android::net::SocketAddrExt;
+    #[cfg(target_os = "linux")]
+    use std::os::linux::net::SocketAddrExt;
+
+    std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes()).map_err(IpcError::Io)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn bind_abstract(_name: &str) -> Result<UnixListener> {
+    Err(abstract_unsupported())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn connect_abstract(_name: &str) -> Result<UnixStream> {
+    Err(abstract_unsupported())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn abstract_unsupported() -> IpcError {
+    IpcError::Platform("abstract namespace sockets require Linux or Android".to_string())
+}
+
 /// Signal handling utilities
 pub mod signal {
     use std::sync::atomic::{AtomicBool, Ordering};
@@ -215,6 +379,29 @@ mod tests {
         handle.join().unwrap();
     }
 
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn test_abstract_socket() {
+        let name = format!("ipckit_test_unix_abstract_{}", std::process::id());
+        let server = UnixSocketServer::bind_abstract(&name).unwrap();
+        assert_eq!(server.abstract_name(), Some(name.as_str()));
+        assert!(matches!(
+            UnixSocketServer::bind_abstract(&name),
+            Err(IpcError::AlreadyExists(_))
+        ));
+
+        let mut client = UnixSocketClient::connect_abstract(&name).unwrap();
+        let mut conn = server.accept().unwrap();
+        client.write_all(b"hi").unwrap();
+        let mut buf = [0u8; 2];
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hi");
+
+        // The name is released with the listener
+        drop(server);
+        assert!(UnixSocketServer::bind_abstract(&name).is_ok());
+    }
+
     #[test]
     fn test_socketpair() {
         let (fd1, fd2) = socketpair().unwrap();
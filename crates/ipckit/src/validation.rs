@@ -0,0 +1,219 @@
+//! # Validation
+//!
+//! Field-level validation for message types. `#[derive(IpcMessage)]` and
+//! `ipc_message!` (from `ipckit-macros`) generate a `validate()` method from
+//! `#[ipc(...)]` field attributes that checks every rule and collects the
+//! failures into [`ValidationErrors`]:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize, IpcMessage)]
+//! struct CreateUser {
+//!     #[ipc(not_empty, max_len = 32, regex = "^[a-z_]+$")]
+//!     name: String,
+//!     #[ipc(range(min = 18, max = 130))]
+//!     age: Option<u8>,
+//! }
+//!
+//! let errors = request.validate().unwrap_err();
+//! for error in errors.errors() {
+//!     eprintln!("{}: {}", error.field, error.message);
+//! }
+//! ```
+//!
+//! `Option` fields are only checked when they hold a value. The rest of this
+//! module is what the generated code calls; it can also be used to write
+//! validators by hand.
+
+use crate::error::IpcError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Compiled pattern of a `regex` rule; the generated code keeps one per rule.
+pub use regex::Regex;
+
+/// A rule a field failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// Name of the field
+    pub field: String,
+    /// Rule that failed, e.g. `not_empty` or `range`
+    pub rule: String,
+    /// Human-readable description of the failure
+    pub message: String,
+}
+
+/// Every rule a message failed, in field order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    /// Create an empty set of errors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a failed rule.
+    pub fn add(&mut self, field: &str, rule: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            rule: rule.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Check whether no rule failed.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Get the number of failed rules.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Get every failed rule.
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// Get the failed rules of one field.
+    pub fn field<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a FieldError> + 'a {
+        self.errors.iter().filter(move |e| e.field == name)
+    }
+
+    /// `Ok(())` if no rule failed, otherwise `Err(self)`.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}: {}", error.field, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Invalid messages are reported like messages that failed to parse, so
+/// API handlers answer them with 400.
+impl From<ValidationErrors> for IpcError {
+    fn from(errors: ValidationErrors) -> Self {
+        IpcError::Deserialization(format!("invalid message: {}", errors))
+    }
+}
+
+/// Length used by the `not_empty` and `max_len` rules: characters for
+/// strings, elements for collections.
+pub trait HasLength {
+    /// Get the length of the value.
+    fn length(&self) -> usize;
+}
+
+impl HasLength for str {
+    fn length(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl HasLength for String {
+    fn length(&self) -> usize {
+        self.as_str().length()
+    }
+}
+
+impl<T> HasLength for [T] {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> HasLength for Vec<T> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K, V, S> HasLength for HashMap<K, V, S> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K, V> HasLength for BTreeMap<K, V> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T, S> HasLength for HashSet<T, S> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> HasLength for BTreeSet<T> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: HasLength + ?Sized> HasLength for &T {
+    fn length(&self) -> usize {
+        (**self).length()
+    }
+}
+
+/// Check `value` against a regular expression.
+pub fn matches_regex<S: AsRef<str> + ?Sized>(value: &S, regex: &Regex) -> bool {
+    regex.is_match(value.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_errors() {
+        let mut errors = ValidationErrors::new();
+        assert!(errors.clone().into_result().is_ok());
+
+        errors.add("name", "not_empty", "must not be empty");
+        errors.add("age", "range", "must be at most 130");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors.field("age").count(), 1);
+        assert_eq!(
+            errors.to_string(),
+            "name: must not be empty; age: must be at most 130"
+        );
+        assert_eq!(
+            serde_json::to_value(&errors).unwrap()[0]["rule"],
+            "not_empty"
+        );
+
+        let err: IpcError = errors.into_result().unwrap_err().into();
+        assert!(matches!(err, IpcError::Deserialization(_)));
+    }
+
+    #[test]
+    fn test_rule_helpers() {
+        assert_eq!("héllo".length(), 5);
+        assert_eq!(vec![1, 2].length(), 2);
+        let regex = Regex::new("^[a-z0-9_]+$").unwrap();
+        assert!(matches_regex("abc_1", &regex));
+        assert!(!matches_regex(&String::from("ABC"), &regex));
+    }
+}
@@ -17,10 +17,27 @@
 //!
 //! // Now when messages arrive, the thread will be woken
 //! ```
+//!
+//! ## Limiting the wake rate
+//!
+//! A chatty producer can wake a GUI event loop thousands of times per
+//! second. [`RateLimitedWaker`] issues at most a fixed number of wakes per
+//! second and coalesces the rest into one trailing wake, so the loop still
+//! sees the last message without being saturated:
+//!
+//! ```rust,ignore
+//! use ipckit::{WakeableChannel, WakeableWrapper};
+//!
+//! // At most 60 wakes per second, one per frame
+//! let mut channel = WakeableWrapper::new(channel).max_wakes_per_second(60);
+//! channel.set_waker(Box::new(waker));
+//! ```
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread::Thread;
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "async")]
 use tokio::sync::Notify;
@@ -276,6 +293,104 @@ impl EventLoopWaker for BroadcastWaker {
     }
 }
 
+/// A waker that issues at most one wake per interval.
+///
+/// The first wake passes straight through. Wakes arriving before the
+/// interval has elapsed are coalesced into a single trailing wake at the
+/// end of the interval, so no notification is lost, only merged. Clones
+/// share the same budget.
+#[derive(Clone)]
+pub struct RateLimitedWaker {
+    inner: Arc<RateLimitedInner>,
+}
+
+struct RateLimitedInner {
+    waker: Box<dyn EventLoopWaker>,
+    min_interval: Duration,
+    state: Mutex<RateLimitState>,
+    coalesced: AtomicU64,
+}
+
+#[derive(Default)]
+struct RateLimitState {
+    /// When the last wake was passed on
+    last_wake: Option<Instant>,
+    /// Whether a trailing wake is scheduled
+    pending: bool,
+}
+
+impl RateLimitedWaker {
+    /// Limit `waker` to at most `max_per_second` wakes per second.
+    pub fn new(waker: Box<dyn EventLoopWaker>, max_per_second: u32) -> Self {
+        Self::with_interval(waker, Duration::from_secs(1) / max_per_second.max(1))
+    }
+
+    /// Limit `waker` to at most one wake per `min_interval`.
+    pub fn with_interval(waker: Box<dyn EventLoopWaker>, min_interval: Duration) -> Self {
+        Self {
+            inner: Arc::new(RateLimitedInner {
+                waker,
+                min_interval,
+                state: Mutex::new(RateLimitState::default()),
+                coalesced: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Get the minimum time between two wakes.
+    pub fn min_interval(&self) -> Duration {
+        self.inner.min_interval
+    }
+
+    /// Get the number of wakes merged into another one so far.
+    pub fn coalesced(&self) -> u64 {
+        self.inner.coalesced.load(Ordering::Relaxed)
+    }
+}
+
+impl EventLoopWaker for RateLimitedWaker {
+    fn wake(&self) {
+        let now = Instant::now();
+        let mut state = self.inner.state.lock();
+        let due = state
+            .last_wake
+            .map_or(now, |last| last + self.inner.min_interval);
+        if due <= now && !state.pending {
+            state.last_wake = Some(now);
+            drop(state);
+            self.inner.waker.wake();
+            return;
+        }
+
+        self.inner.coalesced.fetch_add(1, Ordering::Relaxed);
+        if std::mem::replace(&mut state.pending, true) {
+            return;
+        }
+        drop(state);
+
+        let inner = Arc::clone(&self.inner);
+        std::thread::spawn(move || {
+            std::thread::sleep(due.saturating_duration_since(Instant::now()));
+            {
+                let mut state = inner.state.lock();
+                state.pending = false;
+                state.last_wake = Some(Instant::now());
+            }
+            if inner.waker.is_valid() {
+                inner.waker.wake();
+            }
+        });
+    }
+
+    fn is_valid(&self) -> bool {
+        self.inner.waker.is_valid()
+    }
+
+    fn clone_box(&self) -> Box<dyn EventLoopWaker> {
+        Box::new(self.clone())
+    }
+}
+
 /// A channel that can wake an event loop when messages arrive.
 pub trait WakeableChannel {
     /// Set the event loop waker.
@@ -295,6 +410,8 @@ pub trait WakeableChannel {
 pub struct WakeableWrapper<C> {
     inner: C,
     waker: Option<Box<dyn EventLoopWaker>>,
+    /// Minimum time between wakes, see [`max_wakes_per_second`](Self::max_wakes_per_second)
+    min_interval: Option<Duration>,
 }
 
 impl<C> WakeableWrapper<C> {
@@ -303,7 +420,20 @@ impl<C> WakeableWrapper<C> {
         Self {
             inner: channel,
             waker: None,
+            min_interval: None,
+        }
+    }
+
+    /// Issue at most `n` wakes per second, coalescing the rest.
+    ///
+    /// Applies to the current waker and to wakers set later; see
+    /// [`RateLimitedWaker`].
+    pub fn max_wakes_per_second(mut self, n: u32) -> Self {
+        self.min_interval = Some(Duration::from_secs(1) / n.max(1));
+        if let Some(waker) = self.waker.take() {
+            self.set_waker(waker);
         }
+        self
     }
 
     /// Get a reference to the inner channel.
@@ -333,7 +463,10 @@ impl<C> WakeableWrapper<C> {
 
 impl<C> WakeableChannel for WakeableWrapper<C> {
     fn set_waker(&mut self, waker: Box<dyn EventLoopWaker>) {
-        self.waker = Some(waker);
+        self.waker = Some(match self.min_interval {
+            Some(interval) => Box::new(RateLimitedWaker::with_interval(waker, interval)),
+            None => waker,
+        });
     }
 
     fn clear_waker(&mut self) {
@@ -445,4 +578,33 @@ mod tests {
         wrapper.clear_waker();
         assert!(wrapper.waker().is_none());
     }
+
+    #[test]
+    fn test_rate_limited_waker_coalesces() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let c = Arc::clone(&counter);
+        let mut wrapper = WakeableWrapper::new(()).max_wakes_per_second(20);
+        wrapper.set_waker(Box::new(CallbackWaker::new(move || {
+            c.fetch_add(1, Ordering::SeqCst);
+        })));
+
+        // A burst wakes once right away and once at the end of the interval
+        for _ in 0..100 {
+            wrapper.wake();
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        std::thread::sleep(Duration::from_millis(150));
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+
+        // Once the interval has passed, the next wake is immediate again
+        wrapper.wake();
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+
+        let limited = RateLimitedWaker::new(Box::new(CallbackWaker::new(|| {})), 20);
+        assert_eq!(limited.min_interval(), Duration::from_millis(50));
+        limited.wake();
+        limited.wake();
+        limited.wake();
+        assert_eq!(limited.coalesced(), 2);
+    }
 }
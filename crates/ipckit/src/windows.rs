@@ -31,6 +31,15 @@ impl PipeHandle {
     pub fn is_valid(&self) -> bool {
         self.handle != INVALID_HANDLE_VALUE
     }
+
+    /// Transfer ownership of the handle to the standard library
+    pub fn into_owned(self) -> std::os::windows::io::OwnedHandle {
+        use std::os::windows::io::FromRawHandle;
+
+        let handle = self.handle;
+        std::mem::forget(self);
+        unsafe { std::os::windows::io::OwnedHandle::from_raw_handle(handle as _) }
+    }
 }
 
 impl Drop for PipeHandle {
@@ -434,6 +443,62 @@ pub fn write_pipe(handle: &PipeHandle, buf: &[u8]) -> std::io::Result<usize> {
     Ok(bytes_written as usize)
 }
 
+/// Get the number of bytes that can be read from a pipe without blocking
+///
+/// Returns `Ok(usize::MAX)` once the other end has disconnected, so that the
+/// following read observes end of stream.
+pub fn pipe_bytes_available(handle: &PipeHandle) -> std::io::Result<usize> {
+    raw_pipe_bytes_available(handle.as_raw())
+}
+
+/// [`pipe_bytes_available`] for a pipe handle owned elsewhere
+pub(crate) fn raw_pipe_bytes_available(handle: HANDLE) -> std::io::Result<usize> {
+    let mut available: u32 = 0;
+    let ret = unsafe {
+        PeekNamedPipe(
+            handle,
+            ptr::null_mut(),
+            0,
+            ptr::null_mut(),
+            &mut available,
+            ptr::null_mut(),
+        )
+    };
+
+    if ret == 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() == Some(109) {
+            return Ok(usize::MAX);
+        }
+        return Err(err);
+    }
+
+    Ok(available as usize)
+}
+
+/// Check whether the other end of a pipe has been closed, without reading
+pub fn pipe_peer_closed(handle: &PipeHandle) -> bool {
+    raw_pipe_peer_closed(handle.as_raw())
+}
+
+/// [`pipe_peer_closed`] for a pipe handle owned elsewhere
+pub(crate) fn raw_pipe_peer_closed(handle: HANDLE) -> bool {
+    let mut available: u32 = 0;
+    let ret = unsafe {
+        PeekNamedPipe(
+            handle,
+            ptr::null_mut(),
+            0,
+            ptr::null_mut(),
+            &mut available,
+            ptr::null_mut(),
+        )
+    };
+
+    // Fails with ERROR_BROKEN_PIPE once the client has disconnected
+    ret == 0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,573 @@
+//! # WSL Bridge
+//!
+//! Lets a Windows process and a WSL2 process talk over ipckit even though a
+//! named pipe is invisible inside WSL and a Unix socket is invisible to
+//! Windows.
+//!
+//! The side that owns the endpoint runs a [`WslBridge`]: a small TCP relay
+//! that forwards every connection to the local socket or named pipe. The
+//! relay advertises its port and a random token in a bridge directory both
+//! sides can see (the Windows user's `%USERPROFILE%\.ipckit\bridge`, which
+//! WSL reaches under `/mnt/c`). The other side connects with
+//! [`WslTransport`], which finds the record, tries the addresses the host
+//! can be reached on and presents the token.
+//!
+//! | Endpoint on | Relay listens on | Client reaches it via |
+//! |-------------|------------------|-----------------------|
+//! | Windows | `0.0.0.0` (WSL's NAT network) | loopback (mirrored networking), then the default gateway |
+//! | WSL | `127.0.0.1` | loopback, forwarded by WSL |
+//!
+//! Only processes that can read the bridge directory learn the token, but the
+//! traffic itself is plain TCP. On Windows the firewall may need to allow the
+//! relay for the WSL network.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ipckit::{ApiClient, WslBridge, WslTransport};
+//! use std::sync::Arc;
+//!
+//! // Windows: expose the GUI's API server to the toolchain in WSL
+//! let _bridge = WslBridge::new("studio", r"\\.\pipe\studio").start()?;
+//!
+//! // WSL: talk to it as if it were local
+//! let client = ApiClient::new("studio").with_transport(Arc::new(WslTransport::new()));
+//! let status = client.get("/v1/status")?;
+//! # Ok::<(), ipckit::IpcError>(())
+//! ```
+
+use crate::error::{IpcError, Result};
+use crate::transport::{LocalSocketTransport, Transport, TransportListener, TransportStream};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Environment variable overriding the bridge directory.
+pub const ENV_BRIDGE_DIR: &str = "IPCKIT_BRIDGE_DIR";
+
+/// How long a client may take to present its token
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to try each candidate host address
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Reply to a client whose token was accepted and whose target is connected
+const ACCEPTED: &[u8] = b"ok\n";
+
+/// A relay advertised in the bridge directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BridgeRecord {
+    /// Name clients connect to
+    pub name: String,
+    /// Relay port
+    pub port: u16,
+    /// Secret clients present before anything is relayed
+    pub token: String,
+    /// Side the relay runs on: `"windows"` or `"wsl"`
+    pub side: String,
+    /// Process running the relay
+    pub pid: u32,
+}
+
+/// Exposes a local socket or named pipe to the other side of the
+/// Windows/WSL boundary.
+#[derive(Debug, Clone)]
+pub struct WslBridge {
+    name: String,
+    target: String,
+    bind: String,
+    dir: Option<PathBuf>,
+}
+
+impl WslBridge {
+    /// Expose `target` (a socket path or pipe name, as passed to
+    /// [`SocketServerConfig::with_path`](crate::SocketServerConfig::with_path))
+    /// to clients connecting to `name`.
+    pub fn new(name: &str, target: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            target: target.to_string(),
+            bind: if cfg!(windows) {
+                "0.0.0.0:0".to_string()
+            } else {
+                "127.0.0.1:0".to_string()
+            },
+            dir: None,
+        }
+    }
+
+    /// Listen on `addr` instead of the platform default.
+    pub fn bind(mut self, addr: &str) -> Self {
+        self.bind = addr.to_string();
+        self
+    }
+
+    /// Advertise the relay in `dir` instead of the default bridge directory.
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// Start relaying in a background thread.
+    ///
+    /// The relay runs, and stays advertised, until the handle is dropped.
+    pub fn start(self) -> Result<BridgeHandle> {
+        let dir = match self.dir {
+            Some(dir) => dir,
+            None => default_dir()?,
+        };
+        std::fs::create_dir_all(&dir)?;
+
+        let listener = TcpListener::bind(&self.bind)?;
+        let addr = listener.local_addr()?;
+        let record = BridgeRecord {
+            name: self.name.clone(),
+            port: addr.port(),
+            token: new_token(),
+            side: if cfg!(windows) { "windows" } else { "wsl" }.to_string(),
+            pid: std::process::id(),
+        };
+        let path = record_path(&dir, &self.name);
+        let json = serde_json::to_vec_pretty(&record)
+            .map_err(|e| IpcError::serialization(e.to_string()))?;
+        std::fs::write(&path, json)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            let token = record.token.clone();
+            let target = self.target;
+            std::thread::spawn(move || {
+                for sock in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(sock) = sock else { continue };
+                    let token = token.clone();
+                    let target = target.clone();
+                    let stop = Arc::clone(&stop);
+                    std::thread::spawn(move || {
+                        if let Err(e) = relay(sock, &token, &target, &stop) {
+                            tracing::debug!("WSL bridge connection ended: {}", e);
+                        }
+                    });
+                }
+            })
+        };
+
+        Ok(BridgeHandle {
+            record,
+            path,
+            addr,
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// A running [`WslBridge`]; stops the relay and withdraws its record on drop.
+pub struct BridgeHandle {
+    record: BridgeRecord,
+    path: PathBuf,
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BridgeHandle {
+    /// The advertised record.
+    pub fn record(&self) -> &BridgeRecord {
+        &self.record
+    }
+
+    /// The address the relay listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for BridgeHandle {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag
+        let wake = match self.addr {
+            SocketAddr::V4(a) if a.ip().is_unspecified() => {
+                SocketAddr::from(([127, 0, 0, 1], a.port()))
+            }
+            addr => addr,
+        };
+        let _ = TcpStream::connect_timeout(&wake, Duration::from_secs(1));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Check the token, connect to the target and copy bytes both ways
+fn relay(sock: TcpStream, token: &str, target: &str, stop: &AtomicBool) -> Result<()> {
+    sock.set_nodelay(true)?;
+    sock.set_read_timeout(Some(TOKEN_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(sock.try_clone()?)
+        .take(token.len() as u64 + 2)
+        .read_line(&mut line)?;
+    if line.trim_end() != token {
+        return Err(IpcError::PermissionDenied(
+            "WSL bridge client presented a wrong token".to_string(),
+        ));
+    }
+    sock.set_read_timeout(None)?;
+
+    let mut target = LocalSocketTransport.connect(target)?;
+    let mut sock: Box<dyn TransportStream> = Box::new(sock);
+    sock.write_all(ACCEPTED)?;
+    pump(sock.as_mut(), target.as_mut(), stop)?;
+    let _ = sock.shutdown();
+    let _ = target.shutdown();
+    Ok(())
+}
+
+/// Copy bytes between two streams until either closes.
+///
+/// Polls both with `try_read` so that it also works for streams that
+/// cannot be cloned, such as native Windows pipes.
+fn pump(a: &mut dyn TransportStream, b: &mut dyn TransportStream, stop: &AtomicBool) -> Result<()> {
+    let mut buf = vec![0u8; 64 * 1024];
+    while !stop.load(Ordering::SeqCst) {
+        let forwarded = forward(a, b, &mut buf)?.max(forward(b, a, &mut buf)?);
+        match forwarded {
+            Forwarded::Closed => return Ok(()),
+            Forwarded::Data => {}
+            Forwarded::Idle => std::thread::sleep(Duration::from_millis(1)),
+        }
+    }
+    Ok(())
+}
+
+/// Outcome of one [`forward`] step, ordered by precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Forwarded {
+    Idle,
+    Data,
+    Closed,
+}
+
+fn forward(
+    from: &mut dyn TransportStream,
+    to: &mut dyn TransportStream,
+    buf: &mut [u8],
+) -> Result<Forwarded> {
+    match from.try_read(buf) {
+        Ok(0) => Ok(Forwarded::Closed),
+        Ok(n) => {
+            to.write_all(&buf[..n])?;
+            Ok(Forwarded::Data)
+        }
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted
+            ) =>
+        {
+            Ok(Forwarded::Idle)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Connects to endpoints exposed by a [`WslBridge`] on the other side.
+///
+/// Addresses are bridge names. Binding is not supported; run a
+/// [`WslBridge`] next to the server instead.
+#[derive(Debug, Clone, Default)]
+pub struct WslTransport {
+    dir: Option<PathBuf>,
+}
+
+impl WslTransport {
+    /// Look for bridges in the default bridge directory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look for bridges in `dir` instead.
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: Some(dir.into()),
+        }
+    }
+
+    /// Read the record a bridge advertised under `name`.
+    pub fn lookup(&self, name: &str) -> Result<BridgeRecord> {
+        let dir = match self.dir {
+            Some(ref dir) => dir.clone(),
+            None => default_dir()?,
+        };
+        let path = record_path(&dir, name);
+        let data = std::fs::read(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => IpcError::NotFound(format!(
+                "no WSL bridge named '{}' in {}",
+                name,
+                dir.display()
+            )),
+            _ => IpcError::Io(e),
+        })?;
+        serde_json::from_slice(&data).map_err(|e| IpcError::deserialization(e.to_string()))
+    }
+}
+
+impl Transport for WslTransport {
+    fn name(&self) -> &'static str {
+        "wsl"
+    }
+
+    fn connect(&self, addr: &str) -> Result<Box<dyn TransportStream>> {
+        let record = self.lookup(addr)?;
+        let mut last_error = None;
+        for host in candidate_hosts(&record) {
+            let sock =
+                match TcpStream::connect_timeout(&(host, record.port).into(), CONNECT_TIMEOUT) {
+                    Ok(sock) => sock,
+                    Err(e) => {
+                        last_error = Some(e);
+                        continue;
+                    }
+                };
+            return handshake(sock, &record.token);
+        }
+        Err(IpcError::NotFound(format!(
+            "WSL bridge '{}' is advertised but unreachable on port {}{}",
+            addr,
+            record.port,
+            last_error.map(|e| format!(": {}", e)).unwrap_or_default()
+        )))
+    }
+
+    fn bind(&self, _addr: &str) -> Result<Box<dyn TransportListener>> {
+        Err(IpcError::Platform(
+            "WslTransport only connects; expose endpoints with WslBridge".to_string(),
+        ))
+    }
+}
+
+/// Present the token and wait for the relay to reach its target
+fn handshake(mut sock: TcpStream, token: &str) -> Result<Box<dyn TransportStream>> {
+    sock.set_nodelay(true)?;
+    sock.write_all(format!("{}\n", token).as_bytes())?;
+    sock.set_read_timeout(Some(TOKEN_TIMEOUT))?;
+    let mut reply = [0u8; ACCEPTED.len()];
+    match sock.read_exact(&mut reply) {
+        Ok(()) if reply == ACCEPTED => {}
+        _ => {
+            return Err(IpcError::PermissionDenied(
+                "WSL bridge refused the connection (stale record or target down)".to_string(),
+            ))
+        }
+    }
+    sock.set_read_timeout(None)?;
+    Ok(Box::new(sock))
+}
+
+/// Addresses the relay's host may be reachable on, most likely first
+fn candidate_hosts(record: &BridgeRecord) -> Vec<std::net::IpAddr> {
+    let mut hosts = vec![std::net::IpAddr::from([127, 0, 0, 1])];
+    // From WSL's NAT network, Windows is the default gateway
+    #[cfg(target_os = "linux")]
+    if record.side == "windows" {
+        hosts.extend(linux::default_gateway());
+        hosts.extend(linux::resolv_conf_nameserver());
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = record;
+    hosts.dedup();
+    hosts
+}
+
+/// Check if this process runs inside WSL.
+pub fn is_wsl() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        std::env::var_os("WSL_DISTRO_NAME").is_some()
+            || Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists()
+            || std::fs::read_to_string("/proc/sys/kernel/osrelease")
+                .is_ok_and(|release| release.to_lowercase().contains("microsoft"))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// The bridge directory: `$IPCKIT_BRIDGE_DIR`, else the Windows user's
+/// `.ipckit\bridge` (seen through `/mnt` from WSL).
+pub fn default_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os(ENV_BRIDGE_DIR) {
+        return Ok(PathBuf::from(dir));
+    }
+    #[cfg(windows)]
+    {
+        let profile = std::env::var_os("USERPROFILE")
+            .ok_or_else(|| IpcError::NotFound("USERPROFILE is not set".to_string()))?;
+        Ok(PathBuf::from(profile).join(".ipckit").join("bridge"))
+    }
+    #[cfg(target_os = "linux")]
+    {
+        if !is_wsl() {
+            return Err(IpcError::NotFound(format!(
+                "not running under WSL; set {} to share a bridge directory",
+                ENV_BRIDGE_DIR
+            )));
+        }
+        Ok(linux::windows_profile()?.join(".ipckit").join("bridge"))
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        Err(IpcError::NotFound(format!(
+            "WSL bridges need {} outside Windows and WSL",
+            ENV_BRIDGE_DIR
+        )))
+    }
+}
+
+fn record_path(dir: &Path, name: &str) -> PathBuf {
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{}.json", safe))
+}
+
+/// Generate a token that is hard to guess without reading the record
+fn new_token() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    (0..4)
+        .map(|i| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_usize(i);
+            hasher.write_u32(std::process::id());
+            hasher.write_u128(
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos(),
+            );
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    /// The Windows user profile, as a `/mnt/<drive>/...` path
+    pub fn windows_profile() -> Result<PathBuf> {
+        // Run from a Windows drive so cmd.exe doesn't complain about UNC paths
+        let output = std::process::Command::new("cmd.exe")
+            .args(["/c", "echo %USERPROFILE%"])
+            .current_dir("/mnt/c")
+            .output()
+            .map_err(|e| IpcError::NotFound(format!("cannot run cmd.exe: {}", e)))?;
+        let profile = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        windows_to_wsl_path(&profile)
+            .ok_or_else(|| IpcError::NotFound(format!("unexpected USERPROFILE '{}'", profile)))
+    }
+
+    /// `C:\Users\me` -> `/mnt/c/Users/me`
+    pub fn windows_to_wsl_path(path: &str) -> Option<PathBuf> {
+        let (drive, rest) = path.split_once(":\\")?;
+        if drive.len() != 1 {
+            return None;
+        }
+        Some(PathBuf::from(format!(
+            "/mnt/{}/{}",
+            drive.to_ascii_lowercase(),
+            rest.replace('\\', "/")
+        )))
+    }
+
+    /// Default route gateway from `/proc/net/route`
+    pub fn default_gateway() -> Option<IpAddr> {
+        let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+        routes.lines().skip(1).find_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(1) != Some(&"00000000") {
+                return None;
+            }
+            let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+            Some(IpAddr::V4(Ipv4Addr::from(gateway.to_le_bytes())))
+        })
+    }
+
+    /// WSL's generated resolv.conf points at the Windows host
+    pub fn resolv_conf_nameserver() -> Option<IpAddr> {
+        let conf = std::fs::read_to_string("/etc/resolv.conf").ok()?;
+        conf.lines()
+            .find_map(|line| line.strip_prefix("nameserver"))
+            .and_then(|addr| addr.trim().parse().ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket_server::{FnHandler, Message, SocketClient, SocketServer};
+
+    #[test]
+    fn test_bridge_relays_to_local_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = format!("test_wsl_bridge_{}", std::process::id());
+        let server = SocketServer::at(&target).unwrap();
+        let _server = server.spawn(FnHandler::new(|_conn, msg| Ok(Some(msg))));
+        std::thread::sleep(Duration::from_millis(100));
+
+        let bridge = WslBridge::new("studio", &target)
+            .dir(dir.path())
+            .start()
+            .unwrap();
+        let transport = WslTransport::with_dir(dir.path());
+        assert_eq!(transport.lookup("studio").unwrap(), *bridge.record());
+
+        let mut client = SocketClient::connect_with(&transport, "studio").unwrap();
+        let text = "z".repeat(200_000);
+        client.send(&Message::text(&text)).unwrap();
+        assert_eq!(client.recv().unwrap().as_text(), Some(text.as_str()));
+
+        // A client without the token gets nothing relayed
+        let mut stranger = TcpStream::connect(bridge.local_addr()).unwrap();
+        stranger.write_all(b"guess\n").unwrap();
+        let mut reply = Vec::new();
+        assert_eq!(stranger.read_to_end(&mut reply).unwrap_or(0), 0);
+
+        drop(bridge);
+        assert!(matches!(
+            transport.lookup("studio"),
+            Err(IpcError::NotFound(_))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_windows_to_wsl_path() {
+        assert_eq!(
+            linux::windows_to_wsl_path(r"C:\Users\me"),
+            Some(PathBuf::from("/mnt/c/Users/me"))
+        );
+        assert_eq!(linux::windows_to_wsl_path("%USERPROFILE%"), None);
+    }
+}
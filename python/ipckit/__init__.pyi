@@ -1,6 +1,6 @@
 """Type stubs for ipckit"""
 
-from typing import Any
+from typing import Any, Iterator
 
 __version__: str
 
@@ -150,6 +150,36 @@ class NamedPipe:
         """Write all data."""
         ...
 
+    @property
+    def closed(self) -> bool:
+        """Check if the pipe has been closed."""
+        ...
+
+    def close(self) -> None:
+        """Close the pipe."""
+        ...
+
+    def __enter__(self) -> NamedPipe:
+        """Enter context manager."""
+        ...
+
+    def __exit__(
+        self,
+        exc_type: type | None,
+        exc_value: BaseException | None,
+        traceback: Any | None,
+    ) -> None:
+        """Exit context manager (closes the pipe)."""
+        ...
+
+    def __iter__(self) -> Iterator[bytes]:
+        """Iterate over chunks of data read from the pipe, until the peer disconnects."""
+        ...
+
+    def __next__(self) -> bytes:
+        """Read the next chunk of data, up to 64 KiB."""
+        ...
+
 class SharedMemory:
     """Shared memory region for fast data exchange between processes."""
 
@@ -218,6 +248,41 @@ class SharedMemory:
         """Read all data from shared memory."""
         ...
 
+    def memoryview(
+        self, offset: int = 0, size: int | None = None, readonly: bool = False
+    ) -> memoryview:
+        """Get a zero-copy view of the shared memory region.
+
+        The view keeps this object alive for as long as it exists.
+
+        Args:
+            offset: Byte offset the view starts at.
+            size: Number of bytes to cover (default: to the end).
+            readonly: Return a read-only view.
+
+        Returns:
+            A byte-formatted memoryview over the mapped region.
+        """
+        ...
+
+    def write_from_buffer(self, offset: int, obj: Any) -> int:
+        """Copy from any buffer-protocol object into shared memory.
+
+        Args:
+            offset: Byte offset to write at.
+            obj: Object implementing the buffer protocol (bytes, bytearray,
+                memoryview, array.array, numpy arrays, ...).
+
+        Returns:
+            Number of bytes written.
+        """
+        ...
+
+    @property
+    def __array_interface__(self) -> dict[str, Any]:
+        """NumPy array interface, so ``numpy.asarray(shm)`` maps without copying."""
+        ...
+
 class IpcChannel:
     """High-level IPC channel for message passing."""
 
@@ -291,6 +356,36 @@ class IpcChannel:
         """
         ...
 
+    @property
+    def closed(self) -> bool:
+        """Check if the channel has been closed."""
+        ...
+
+    def close(self) -> None:
+        """Close the channel."""
+        ...
+
+    def __enter__(self) -> IpcChannel:
+        """Enter context manager."""
+        ...
+
+    def __exit__(
+        self,
+        exc_type: type | None,
+        exc_value: BaseException | None,
+        traceback: Any | None,
+    ) -> None:
+        """Exit context manager (closes the channel)."""
+        ...
+
+    def __iter__(self) -> Iterator[bytes]:
+        """Iterate over received messages, until the peer disconnects."""
+        ...
+
+    def __next__(self) -> bytes:
+        """Receive the next message."""
+        ...
+
 class FileChannel:
     """File-based IPC channel for frontend-backend communication.
 
@@ -401,6 +496,30 @@ class FileChannel:
         """
         ...
 
+    def watch(self) -> None:
+        """Start watching the inbox for changes.
+
+        Once watching, recv_wait and wait_response block until the peer
+        writes instead of polling the inbox on a timer.
+        """
+        ...
+
+    @property
+    def is_watching(self) -> bool:
+        """Whether the inbox is being watched."""
+        ...
+
+    def recv_wait(self, timeout_ms: int) -> list[dict[str, Any]]:
+        """Wait for new messages.
+
+        Args:
+            timeout_ms: Timeout in milliseconds
+
+        Returns:
+            List of new message dicts, empty if the timeout elapsed
+        """
+        ...
+
     def wait_response(self, request_id: str, timeout_ms: int) -> dict[str, Any]:
         """Wait for a response to a specific request.
 
@@ -420,6 +539,44 @@ class FileChannel:
         """Clear all messages in both inbox and outbox."""
         ...
 
+    def compact(self) -> int:
+        """Prune messages both sides have already read.
+
+        Returns:
+            The number of messages removed from the inbox and outbox
+        """
+        ...
+
+    @property
+    def closed(self) -> bool:
+        """Check if the channel has been closed."""
+        ...
+
+    def close(self) -> None:
+        """Close the channel, stopping any inbox watcher."""
+        ...
+
+    def __enter__(self) -> FileChannel:
+        """Enter context manager."""
+        ...
+
+    def __exit__(
+        self,
+        exc_type: type | None,
+        exc_value: BaseException | None,
+        traceback: Any | None,
+    ) -> None:
+        """Exit context manager (closes the channel)."""
+        ...
+
+    def __iter__(self) -> Iterator[dict[str, Any]]:
+        """Iterate over unread inbox messages, until the inbox is empty."""
+        ...
+
+    def __next__(self) -> dict[str, Any]:
+        """Take the next unread inbox message."""
+        ...
+
 class GracefulNamedPipe:
     """Named pipe with graceful shutdown support.
 
@@ -686,6 +843,36 @@ class GracefulIpcChannel:
         """
         ...
 
+    @property
+    def closed(self) -> bool:
+        """Check if the channel has been closed."""
+        ...
+
+    def close(self) -> None:
+        """Shut the channel down and close it."""
+        ...
+
+    def __enter__(self) -> GracefulIpcChannel:
+        """Enter context manager."""
+        ...
+
+    def __exit__(
+        self,
+        exc_type: type | None,
+        exc_value: BaseException | None,
+        traceback: Any | None,
+    ) -> None:
+        """Exit context manager (shuts down and closes the channel)."""
+        ...
+
+    def __iter__(self) -> Iterator[bytes]:
+        """Iterate over received messages, until shutdown or the peer disconnects."""
+        ...
+
+    def __next__(self) -> bytes:
+        """Receive the next message."""
+        ...
+
 # CLI Bridge classes
 
 class CliBridgeConfig:
@@ -952,7 +1139,7 @@ def wrap_command(
     This function runs a subprocess and automatically:
     - Registers it as a task with the API server
     - Captures and forwards stdout/stderr
-    - Parses progress from output
+    - Parses progress from output (using a preset for cargo, pip, ffmpeg, rsync)
     - Reports completion/failure
 
     Args:
@@ -985,6 +1172,7 @@ def parse_progress(line: str, parser_type: str = "all") -> ProgressInfo | None:
             - "percentage": Matches "50%", "Progress: 75%", etc.
             - "fraction": Matches "5/10", "[3/4]", etc.
             - "progress_bar": Matches "[=====>    ] 50%"
+            - "cargo", "pip", "ffmpeg", "rsync": Presets for these tools' output
             - "all": Try all parsers (default)
 
     Returns:
@@ -997,6 +1185,157 @@ def parse_progress(line: str, parser_type: str = "all") -> ProgressInfo | None:
     """
     ...
 
+# Discovery classes (channel registry)
+
+class ChannelEntry:
+    """A channel advertised in the discovery registry."""
+
+    @property
+    def name(self) -> str:
+        """Get the channel name."""
+        ...
+
+    @property
+    def kind(self) -> str:
+        """Get the channel type (pipe, shm, socket, file, ...)."""
+        ...
+
+    @property
+    def pid(self) -> int:
+        """Get the pid of the serving process."""
+        ...
+
+    @property
+    def purpose(self) -> str | None:
+        """Get what the channel is for."""
+        ...
+
+    @property
+    def registered_at(self) -> float:
+        """Get the registration time as Unix timestamp."""
+        ...
+
+class Registration:
+    """Keeps a channel advertised until unregistered.
+
+    Can be used as a context manager.
+    """
+
+    @property
+    def entry(self) -> ChannelEntry:
+        """Get the advertised entry."""
+        ...
+
+    def unregister(self) -> None:
+        """Stop advertising the channel."""
+        ...
+
+    def __enter__(self) -> Registration:
+        """Enter context manager."""
+        ...
+
+    def __exit__(
+        self,
+        exc_type: type | None,
+        exc_value: BaseException | None,
+        traceback: Any | None,
+    ) -> None:
+        """Exit context manager (unregisters the channel)."""
+        ...
+
+class Discovery:
+    """Registry of the channels served by running processes.
+
+    The registry lives in `$IPCKIT_DISCOVERY_DIR`, `$XDG_RUNTIME_DIR/ipckit`
+    or the temp directory. Entries of dead processes are pruned on listing.
+    """
+
+    def __init__(self, dir: str | None = None) -> None:
+        """Open the default registry, or the one in `dir`."""
+        ...
+
+    @property
+    def dir(self) -> str:
+        """Get the registry directory."""
+        ...
+
+    def register(self, name: str, kind: str, purpose: str | None = None) -> Registration:
+        """Advertise a channel served by this process.
+
+        Args:
+            name: Channel name, as passed to connect
+            kind: Channel type (pipe, shm, socket, file, ...)
+            purpose: What the channel is for
+
+        Returns:
+            Registration keeping the channel advertised
+        """
+        ...
+
+    def list(self) -> list[ChannelEntry]:
+        """List all channels whose process is still alive."""
+        ...
+
+    def find(self, name: str) -> ChannelEntry | None:
+        """Find a live channel by name."""
+        ...
+
+class SingleInstance:
+    """Exclusive ownership of a daemon name.
+
+    The OS lock is released when the process exits, so a crashed daemon
+    never blocks the next one. Can be used as a context manager.
+    """
+
+    @staticmethod
+    def acquire(name: str, dir: str | None = None) -> SingleInstance:
+        """Become the only running instance of `name`.
+
+        Args:
+            name: Daemon name
+            dir: Directory for the lock file (default: the runtime directory)
+
+        Raises:
+            FileExistsError: If another live process holds the name
+        """
+        ...
+
+    @staticmethod
+    def running(name: str, dir: str | None = None) -> int | None:
+        """Get the pid of the running instance of `name`, if any."""
+        ...
+
+    @property
+    def name(self) -> str:
+        """Get the daemon name."""
+        ...
+
+    @property
+    def path(self) -> str:
+        """Get the lock file path."""
+        ...
+
+    def cleanup_socket(self, path: str) -> None:
+        """Remove a socket file left behind by a previous instance."""
+        ...
+
+    def release(self) -> None:
+        """Release the lock."""
+        ...
+
+    def __enter__(self) -> SingleInstance:
+        """Enter context manager."""
+        ...
+
+    def __exit__(
+        self,
+        exc_type: type | None,
+        exc_value: BaseException | None,
+        traceback: Any | None,
+    ) -> None:
+        """Exit context manager (releases the lock)."""
+        ...
+
 # Metrics classes (Issue #10: Performance monitoring)
 
 class ChannelMetrics:
@@ -1107,6 +1446,10 @@ class ChannelMetrics:
         """Get latency percentile (e.g., 99 for p99)."""
         ...
 
+    def latency_quantile(self, q: float) -> int:
+        """Get latency at a quantile in 0.0..=1.0 (e.g., 0.999 for p99.9)."""
+        ...
+
     @property
     def elapsed_secs(self) -> float:
         """Get elapsed time since metrics started."""
@@ -1232,6 +1575,11 @@ class MetricsSnapshot:
         """99th percentile latency."""
         ...
 
+    @property
+    def p999_latency_us(self) -> int:
+        """99.9th percentile latency."""
+        ...
+
     @property
     def elapsed_secs(self) -> float:
         """Elapsed time in seconds."""
@@ -1270,6 +1618,9 @@ class ApiServerConfig:
         socket_path: Socket path for the server
         enable_cors: Whether to enable CORS
         cors_origins: List of allowed CORS origins
+        access_log: Whether requests are traced in `api.request` spans
+        admin_routes: Whether `/v1/connections` admin routes are served
+        health_routes: Whether `/healthz` and `/readyz` are served
     """
 
     def __init__(
@@ -1277,6 +1628,9 @@ class ApiServerConfig:
         socket_path: str | None = None,
         enable_cors: bool = True,
         cors_origins: list[str] | None = None,
+        access_log: bool = False,
+        admin_routes: bool = False,
+        health_routes: bool = False,
     ) -> None:
         """Create a new configuration.
 
@@ -1284,6 +1638,11 @@ class ApiServerConfig:
             socket_path: Socket path for the server
             enable_cors: Whether to enable CORS (default: True)
             cors_origins: List of allowed origins (default: ["*"])
+            access_log: Emit a tracing span per request (default: False)
+            admin_routes: Serve `GET /v1/connections` and
+                `DELETE /v1/connections/{id}` (default: False)
+            health_routes: Serve `GET /healthz` and `GET /readyz`
+                (default: False)
         """
         ...
 
@@ -1317,6 +1676,46 @@ class ApiServerConfig:
         """Set CORS allowed origins."""
         ...
 
+    @property
+    def access_log(self) -> bool:
+        """Get access logging enabled."""
+        ...
+
+    @access_log.setter
+    def access_log(self, value: bool) -> None:
+        """Set access logging enabled."""
+        ...
+
+    @property
+    def max_body_size(self) -> int:
+        """Get the largest accepted request body in bytes (default: 8 MiB)."""
+        ...
+
+    @max_body_size.setter
+    def max_body_size(self, value: int) -> None:
+        """Set the largest accepted request body in bytes."""
+        ...
+
+    @property
+    def admin_routes(self) -> bool:
+        """Get whether connection admin routes are served."""
+        ...
+
+    @admin_routes.setter
+    def admin_routes(self, value: bool) -> None:
+        """Set whether connection admin routes are served."""
+        ...
+
+    @property
+    def health_routes(self) -> bool:
+        """Get whether health and readiness routes are served."""
+        ...
+
+    @health_routes.setter
+    def health_routes(self, value: bool) -> None:
+        """Set whether health and readiness routes are served."""
+        ...
+
 class Request:
     """HTTP Request object.
 
@@ -1718,6 +2117,36 @@ class LocalSocketStream:
         """
         ...
 
+    @property
+    def closed(self) -> bool:
+        """Check if the socket has been closed."""
+        ...
+
+    def close(self) -> None:
+        """Close the socket."""
+        ...
+
+    def __enter__(self) -> LocalSocketStream:
+        """Enter context manager."""
+        ...
+
+    def __exit__(
+        self,
+        exc_type: type | None,
+        exc_value: BaseException | None,
+        traceback: Any | None,
+    ) -> None:
+        """Exit context manager (closes the socket)."""
+        ...
+
+    def __iter__(self) -> Iterator[Any]:
+        """Iterate over received JSON messages, until the peer disconnects."""
+        ...
+
+    def __next__(self) -> Any:
+        """Receive the next JSON message."""
+        ...
+
 # Event Stream classes (Publish-Subscribe)
 
 class Event:
@@ -1997,6 +2426,14 @@ class EventSubscriber:
         """Get all currently available events without blocking."""
         ...
 
+    def unsubscribe(self) -> bool:
+        """Remove this subscriber from the bus.
+
+        Events already queued can still be received; after that ``recv``
+        returns None. Returns False if it was already removed.
+        """
+        ...
+
 class EventBus:
     """Central event bus for publish-subscribe.
 
@@ -2057,6 +2494,21 @@ class EventBus:
         """Clear all event history."""
         ...
 
+    def subscriber_count(self) -> int:
+        """Get the number of live subscriptions."""
+        ...
+
+    def unsubscribe_matching(self, pattern: str) -> int:
+        """Remove every subscriber with an event type pattern matching ``pattern``.
+
+        ``"task.*"`` removes subscribers to ``"task.progress"`` and ``"task.*"``
+        alike; subscribers without a type filter are only removed by ``"*"``.
+
+        Returns:
+            Number of subscribers removed
+        """
+        ...
+
     def publish(self, event: Event) -> None:
         """Publish an event directly."""
         ...
@@ -2122,6 +2574,11 @@ class TaskInfo:
         """Get the task type."""
         ...
 
+    @property
+    def parent_id(self) -> str | None:
+        """Get the ID of the parent task, if this is a sub-task."""
+        ...
+
     @property
     def status(self) -> TaskStatus:
         """Get the current status."""
@@ -2205,6 +2662,56 @@ class CancellationToken:
         """Create a child token."""
         ...
 
+class SharedCancellationToken:
+    """Cancellation flag shared between processes.
+
+    Example:
+        # GUI process
+        cancel = SharedCancellationToken.create("render-42-cancel")
+        cancel.cancel()
+
+        # Worker process
+        token = SharedCancellationToken.open("render-42-cancel").token()
+        if token.is_cancelled:
+            return
+    """
+
+    @staticmethod
+    def create(name: str) -> SharedCancellationToken:
+        """Create a new cancellation flag shared between processes.
+
+        Args:
+            name: Flag name, unique across the system
+        """
+        ...
+
+    @staticmethod
+    def open(name: str) -> SharedCancellationToken:
+        """Open a cancellation flag created by another process.
+
+        Args:
+            name: Flag name passed to create()
+        """
+        ...
+
+    @property
+    def name(self) -> str:
+        """Get the flag name."""
+        ...
+
+    def cancel(self) -> None:
+        """Trigger cancellation in every process sharing the flag."""
+        ...
+
+    @property
+    def is_cancelled(self) -> bool:
+        """Check if any process has cancelled the flag."""
+        ...
+
+    def token(self) -> CancellationToken:
+        """Get a CancellationToken backed by this flag."""
+        ...
+
 class TaskBuilder:
     """Builder for creating tasks.
 
@@ -2248,6 +2755,31 @@ class TaskBuilder:
         """
         ...
 
+    def parent(self, id: str) -> TaskBuilder:
+        """Make the task a sub-task of another task.
+
+        The parent's progress follows the weighted average of its sub-tasks,
+        and cancelling the parent cancels its unfinished sub-tasks too.
+
+        Args:
+            id: Parent task ID
+
+        Returns:
+            Self for chaining
+        """
+        ...
+
+    def weight(self, weight: float) -> TaskBuilder:
+        """Set how much this task counts towards its parent's progress.
+
+        Args:
+            weight: Relative weight (default: 1.0)
+
+        Returns:
+            Self for chaining
+        """
+        ...
+
 class TaskFilter:
     """Filter for listing tasks.
 
@@ -2303,6 +2835,30 @@ class TaskFilter:
         """
         ...
 
+    def parent(self, id: str) -> TaskFilter:
+        """Filter by parent task, listing its direct sub-tasks.
+
+        Args:
+            id: Parent task ID
+
+        Returns:
+            Self for chaining
+        """
+        ...
+
+    def children_of(self, id: str) -> TaskFilter:
+        """Filter by ancestor task, listing its whole subtree.
+
+        Only applied by ``TaskManager.list``.
+
+        Args:
+            id: Ancestor task ID
+
+        Returns:
+            Self for chaining
+        """
+        ...
+
     def active(self) -> TaskFilter:
         """Show only active tasks.
 
@@ -0,0 +1,35 @@
+"""
+ipckit.testing - In-memory transports for testing IPC handling
+
+Lets plugin code be unit-tested inside plain pytest, including disconnects
+and timeouts, without spawning a real daemon:
+
+- loopback_pair(): Create two connected LoopbackEndpoint objects
+- LoopbackEndpoint: One end of an in-memory message link
+- ChaosEndpoint: Wraps an endpoint to inject drops, delays, timeouts and disconnects
+
+Example:
+    from ipckit.testing import ChaosEndpoint, loopback_pair
+
+    client, server = loopback_pair()
+    client = ChaosEndpoint(client, disconnect_after=1)
+
+    client.send_json({"method": "ping"})
+    assert server.recv_json(timeout_ms=100) == {"method": "ping"}
+
+    # The daemon "crashes" on the next send
+    with pytest.raises(ConnectionError):
+        client.send_json({"method": "ping"})
+"""
+
+from .ipckit import testing as _testing
+
+ChaosEndpoint = _testing.ChaosEndpoint
+LoopbackEndpoint = _testing.LoopbackEndpoint
+loopback_pair = _testing.loopback_pair
+
+__all__ = [
+    "ChaosEndpoint",
+    "LoopbackEndpoint",
+    "loopback_pair",
+]
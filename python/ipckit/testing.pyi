@@ -0,0 +1,145 @@
+"""Type stubs for ipckit.testing"""
+
+from typing import Any, TypedDict
+
+class ChaosStats(TypedDict):
+    sent: int
+    dropped: int
+    timeouts: int
+    disconnected: bool
+
+class LoopbackEndpoint:
+    """One end of an in-memory message link.
+
+    Messages are delivered in order and never lost. Closing either end
+    disconnects both: sends raise ConnectionError, and receives raise it once
+    the queued messages are drained.
+    """
+
+    def send(self, data: bytes) -> None:
+        """Send bytes to the other end.
+
+        Raises:
+            ConnectionError: If the link is closed
+        """
+        ...
+
+    def recv(self, timeout_ms: int | None = None) -> bytes:
+        """Receive bytes, waiting at most timeout_ms if given.
+
+        Raises:
+            TimeoutError: If nothing arrives within timeout_ms
+            ConnectionError: If the link is closed and drained
+        """
+        ...
+
+    def try_recv(self) -> bytes | None:
+        """Receive bytes if a message is queued, otherwise return None."""
+        ...
+
+    def send_json(self, obj: Any) -> None:
+        """Send a JSON-serializable object."""
+        ...
+
+    def recv_json(self, timeout_ms: int | None = None) -> Any:
+        """Receive a JSON object, waiting at most timeout_ms if given."""
+        ...
+
+    def close(self) -> None:
+        """Disconnect both ends of the link."""
+        ...
+
+    @property
+    def closed(self) -> bool:
+        """Whether the link has been disconnected."""
+        ...
+
+    @property
+    def pending(self) -> int:
+        """Number of messages waiting to be received."""
+        ...
+
+class ChaosEndpoint:
+    """A loopback endpoint that injects failures.
+
+    Drops and timeouts are drawn from a generator seeded with `seed`, so a
+    failing test can be replayed exactly.
+
+    Example:
+        client, server = loopback_pair()
+        flaky = ChaosEndpoint(client, drop_rate=0.2, seed=42)
+        flaky.inject_timeouts(1)
+        with pytest.raises(TimeoutError):
+            flaky.recv(timeout_ms=10)
+    """
+
+    drop_rate: float
+    """Probability (0.0 - 1.0) that a sent message is silently dropped."""
+
+    timeout_rate: float
+    """Probability (0.0 - 1.0) that a receive times out."""
+
+    delay_ms: int
+    """Delay in milliseconds added before every send."""
+
+    def __init__(
+        self,
+        endpoint: LoopbackEndpoint,
+        drop_rate: float = 0.0,
+        timeout_rate: float = 0.0,
+        delay_ms: int = 0,
+        disconnect_after: int | None = None,
+        seed: int = 0,
+    ) -> None:
+        """Wrap a loopback endpoint with failure injection.
+
+        Args:
+            endpoint: Endpoint to wrap (it stays usable on its own)
+            drop_rate: Probability that a sent message is dropped
+            timeout_rate: Probability that a receive times out
+            delay_ms: Delay added before every send
+            disconnect_after: Disconnect the link after this many sends
+            seed: Random seed for reproducible failures
+        """
+        ...
+
+    def send(self, data: bytes) -> None:
+        """Send bytes, subject to the configured failures."""
+        ...
+
+    def recv(self, timeout_ms: int | None = None) -> bytes:
+        """Receive bytes, waiting at most timeout_ms if given."""
+        ...
+
+    def send_json(self, obj: Any) -> None:
+        """Send a JSON-serializable object, subject to the configured failures."""
+        ...
+
+    def recv_json(self, timeout_ms: int | None = None) -> Any:
+        """Receive a JSON object, waiting at most timeout_ms if given."""
+        ...
+
+    def inject_timeouts(self, count: int = 1) -> None:
+        """Make the next `count` receives raise TimeoutError."""
+        ...
+
+    def disconnect(self) -> None:
+        """Disconnect the link immediately, as if the peer had crashed."""
+        ...
+
+    def close(self) -> None:
+        """Disconnect both ends of the link."""
+        ...
+
+    @property
+    def closed(self) -> bool:
+        """Whether the link has been disconnected."""
+        ...
+
+    def stats(self) -> ChaosStats:
+        """Get the injected failure counters."""
+        ...
+
+def loopback_pair() -> tuple[LoopbackEndpoint, LoopbackEndpoint]:
+    """Create a connected pair of loopback endpoints."""
+    ...
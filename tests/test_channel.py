@@ -152,5 +152,40 @@ def test_channel_multiple_messages():
     assert not client_thread.is_alive(), "Client thread timed out"
 
 
+
+def test_channel_context_manager_and_iteration():
+    """Test iterating a channel until the peer closes it."""
+    from ipckit import IpcChannel
+
+    name = f"test_channel_iter_{os.getpid()}"
+    messages = [b"first", b"second", b"third"]
+    received = []
+
+    def server():
+        with IpcChannel.create(name) as channel:
+            channel.wait_for_client()
+            received.extend(channel)
+        assert channel.closed
+
+    def client():
+        time.sleep(0.1)
+        with IpcChannel.connect(name) as channel:
+            for msg in messages:
+                channel.send(msg)
+
+    server_thread = threading.Thread(target=server)
+    client_thread = threading.Thread(target=client)
+
+    server_thread.start()
+    client_thread.start()
+
+    server_thread.join(timeout=5)
+    client_thread.join(timeout=5)
+
+    assert not server_thread.is_alive(), "Server thread timed out"
+    assert not client_thread.is_alive(), "Client thread timed out"
+    assert received == messages
+
+
 if __name__ == "__main__":
     pytest.main([__file__, "-v"])
@@ -101,5 +101,79 @@ def test_shared_memory_open():
     assert data == b"Shared data!"
 
 
+
+def test_shared_memory_memoryview_zero_copy():
+    """Test that memoryview writes land directly in shared memory."""
+    from ipckit import SharedMemory
+
+    name = f"test_shm_view_{os.getpid()}"
+    shm = SharedMemory.create(name, 64)
+
+    view = shm.memoryview()
+    assert len(view) == 64
+    assert not view.readonly
+
+    view[0:5] = b"hello"
+    assert shm.read(0, 5) == b"hello"
+
+    shm.write(10, b"world")
+    assert bytes(view[10:15]) == b"world"
+
+    readonly = shm.memoryview(10, 5, readonly=True)
+    assert readonly.readonly
+    assert bytes(readonly) == b"world"
+
+
+def test_shared_memory_memoryview_outlives_handle():
+    """Test that a memoryview keeps the mapping alive."""
+    import gc
+
+    from ipckit import SharedMemory
+
+    name = f"test_shm_view_alive_{os.getpid()}"
+    shm = SharedMemory.create(name, 16)
+    view = shm.memoryview()
+    view[0:4] = b"keep"
+
+    del shm
+    gc.collect()
+    assert bytes(view[0:4]) == b"keep"
+
+
+def test_shared_memory_write_from_buffer():
+    """Test writing from arbitrary buffer-protocol objects."""
+    import array
+
+    from ipckit import SharedMemory
+
+    name = f"test_shm_from_buffer_{os.getpid()}"
+    shm = SharedMemory.create(name, 64)
+
+    assert shm.write_from_buffer(0, bytearray(b"abc")) == 3
+    assert shm.write_from_buffer(8, array.array("I", [1, 2])) == 8
+    assert shm.read(0, 3) == b"abc"
+    assert shm.read(8, 8) == array.array("I", [1, 2]).tobytes()
+
+    with pytest.raises(ValueError):
+        shm.write_from_buffer(60, b"too long")
+
+
+def test_shared_memory_numpy_view():
+    """Test numpy arrays map shared memory without copying."""
+    np = pytest.importorskip("numpy")
+    from ipckit import SharedMemory
+
+    name = f"test_shm_numpy_{os.getpid()}"
+    shm = SharedMemory.create(name, 32)
+
+    arr = np.asarray(shm)
+    assert arr.shape == (32,)
+    arr[:4] = [1, 2, 3, 4]
+    assert shm.read(0, 4) == bytes([1, 2, 3, 4])
+
+    floats = np.frombuffer(shm.memoryview(), dtype=np.float32)
+    assert floats.shape == (8,)
+
+
 if __name__ == "__main__":
     pytest.main([__file__, "-v"])
@@ -0,0 +1,105 @@
+"""Tests for the ipckit.testing loopback transport and chaos wrapper."""
+
+import pytest
+
+
+class TestLoopback:
+    """Unit tests for loopback endpoints."""
+
+    def test_round_trip(self):
+        """Test sending bytes and JSON in both directions."""
+        from ipckit.testing import loopback_pair
+
+        client, server = loopback_pair()
+        client.send(b"hello")
+        server.send_json({"ok": True})
+
+        assert server.pending == 1
+        assert server.recv() == b"hello"
+        assert client.recv_json(timeout_ms=100) == {"ok": True}
+        assert client.try_recv() is None
+
+    def test_timeout(self):
+        """Test that an empty endpoint times out."""
+        from ipckit.testing import loopback_pair
+
+        client, _server = loopback_pair()
+        with pytest.raises(TimeoutError):
+            client.recv(timeout_ms=10)
+
+    def test_close(self):
+        """Test that closing one end disconnects both."""
+        from ipckit.testing import loopback_pair
+
+        client, server = loopback_pair()
+        client.send(b"last")
+        client.close()
+
+        assert server.closed
+        assert server.recv() == b"last"
+        with pytest.raises(ConnectionError):
+            server.recv()
+        with pytest.raises(ConnectionError):
+            server.send(b"reply")
+
+
+class TestChaos:
+    """Unit tests for failure injection."""
+
+    def test_disconnect_after(self):
+        """Test simulating a daemon crash after some messages."""
+        from ipckit.testing import ChaosEndpoint, loopback_pair
+
+        client, server = loopback_pair()
+        chaos = ChaosEndpoint(client, disconnect_after=2)
+        chaos.send(b"1")
+        chaos.send(b"2")
+        with pytest.raises(ConnectionError):
+            chaos.send(b"3")
+
+        assert server.recv() == b"1"
+        assert server.recv() == b"2"
+        assert chaos.stats()["disconnected"]
+
+    def test_injected_timeouts(self):
+        """Test forcing receives to time out."""
+        from ipckit.testing import ChaosEndpoint, loopback_pair
+
+        client, server = loopback_pair()
+        chaos = ChaosEndpoint(client)
+        server.send(b"reply")
+
+        chaos.inject_timeouts(2)
+        with pytest.raises(TimeoutError):
+            chaos.recv(timeout_ms=1)
+        with pytest.raises(TimeoutError):
+            chaos.recv()
+        assert chaos.recv() == b"reply"
+        assert chaos.stats()["timeouts"] == 2
+
+    def test_seeded_drops(self):
+        """Test that drops are reproducible for a given seed."""
+        from ipckit.testing import ChaosEndpoint, loopback_pair
+
+        def dropped(seed):
+            client, _server = loopback_pair()
+            chaos = ChaosEndpoint(client, drop_rate=0.5, seed=seed)
+            for _ in range(50):
+                chaos.send(b"x")
+            return chaos.stats()["dropped"]
+
+        assert dropped(42) == dropped(42)
+        assert 0 < dropped(42) < 50
+
+    def test_runtime_config(self):
+        """Test changing failure rates on a live endpoint."""
+        from ipckit.testing import ChaosEndpoint, loopback_pair
+
+        client, server = loopback_pair()
+        chaos = ChaosEndpoint(client, drop_rate=1.0)
+        chaos.send(b"lost")
+        assert server.pending == 0
+
+        chaos.drop_rate = 0.0
+        chaos.send(b"kept")
+        assert server.recv() == b"kept"